@@ -0,0 +1,37 @@
+//! Write the generated TypeScript definitions to a file, or to stdout
+//!
+//! With `--check`, exit non-zero instead of writing when the file is stale.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let (check, path) = match args.next().as_deref() {
+        Some("--check") => (true, args.next()),
+        other => (false, other.map(str::to_string)),
+    };
+    let definitions = flowscope_types::ts::definitions();
+
+    let Some(path) = path else {
+        print!("{}", definitions);
+        return ExitCode::SUCCESS;
+    };
+
+    if check {
+        return match std::fs::read_to_string(&path) {
+            Ok(existing) if existing == definitions => ExitCode::SUCCESS,
+            _ => {
+                eprintln!("{} is out of date; rerun flowscope-ts {}", path, path);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    match std::fs::write(&path, definitions) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("failed to write {}: {}", path, e);
+            ExitCode::FAILURE
+        }
+    }
+}
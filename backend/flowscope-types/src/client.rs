@@ -0,0 +1,1241 @@
+//! Typed HTTP client and WebSocket subscriber for the FlowScope API
+
+use std::collections::HashMap;
+
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::models::*;
+use crate::ws::{LogStreamMessage, TerminalControl, TerminalMessage, WsClientMessage, WsEnvelope, WsMessage};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("FlowScope returned {status}: {message}")]
+    Api { status: StatusCode, message: String },
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("invalid message: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// What the server does to log lines before returning them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogCleanup {
+    /// Drop terminal escape sequences, before grepping
+    pub strip_ansi: bool,
+    /// Parse lines holding a JSON object into `json`
+    pub parse_json: bool,
+}
+
+impl LogCleanup {
+    fn query(self) -> String {
+        format!("&strip_ansi={}&parse_json={}", self.strip_ansi, self.parse_json)
+    }
+}
+
+/// Client for a FlowScope backend, e.g. `FlowScopeClient::new("http://localhost:8850")`
+#[derive(Debug, Clone)]
+pub struct FlowScopeClient {
+    base_url: String,
+    http: reqwest::Client,
+    token: Option<String>,
+}
+
+impl FlowScopeClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            token: None,
+        }
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let builder = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(builder: RequestBuilder) -> Result<T> {
+        let response = builder.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+
+        let message = response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|body| body.get("error").and_then(|e| e.as_str()).map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        Err(ClientError::Api { status, message })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        Self::send(self.request(Method::GET, path)).await
+    }
+
+    /// GET a non-JSON body such as a YAML bundle or GraphML document
+    async fn get_text(&self, path: &str) -> Result<String> {
+        let response = self.request(Method::GET, path).send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(response.text().await?),
+            status => Err(ClientError::Api {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// GET a binary body such as an artifact
+    async fn get_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        let response = self.request(Method::GET, path).send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(response.bytes().await?.to_vec()),
+            status => Err(ClientError::Api {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        Self::send(self.request(Method::POST, path).json(body)).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let response = self.request(Method::DELETE, path).send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(ClientError::Api {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    fn encode(segment: &str) -> String {
+        segment
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    // -------------------------------------------------------------------------
+    // Topology & discovery
+    // -------------------------------------------------------------------------
+
+    pub async fn health(&self) -> Result<serde_json::Value> {
+        self.get("/health").await
+    }
+
+    pub async fn topology(&self) -> Result<SystemTopology> {
+        self.get("/api/topology").await
+    }
+
+    /// The topology rebuilt now rather than the server's cached snapshot
+    pub async fn refreshed_topology(&self) -> Result<SystemTopology> {
+        self.get("/api/topology?refresh=true").await
+    }
+
+    /// Wait up to `timeout_secs` for the topology to move past `version`
+    pub async fn wait_topology(&self, version: u64, timeout_secs: u64) -> Result<TopologyChange> {
+        self.get(&format!("/api/topology/wait?version={}&timeout={}s", version, timeout_secs))
+            .await
+    }
+
+    pub async fn containers(&self) -> Result<Vec<ContainerInfo>> {
+        self.get("/api/containers").await
+    }
+
+    /// Containers trimmed to `fields` (e.g. `["name", "status"]`); the result
+    /// is untyped since required fields may be left out
+    pub async fn containers_fields(&self, fields: &[&str]) -> Result<Vec<serde_json::Value>> {
+        self.get(&format!("/api/containers?fields={}", Self::encode(&fields.join(","))))
+            .await
+    }
+
+    /// Containers `selector` picks, e.g. `status=unhealthy and label team=core`
+    pub async fn containers_matching(&self, selector: &str) -> Result<Vec<ContainerInfo>> {
+        self.get(&format!("/api/containers?selector={}", Self::encode(selector)))
+            .await
+    }
+
+    pub async fn containers_with_stats(&self) -> Result<Vec<ContainerInfo>> {
+        self.get("/api/containers/stats").await
+    }
+
+    /// Swarm services with the tasks running them
+    pub async fn services(&self) -> Result<Vec<SwarmService>> {
+        self.get("/api/services").await
+    }
+
+    pub async fn networks(&self) -> Result<Vec<NetworkInfo>> {
+        self.get("/api/networks").await
+    }
+
+    pub async fn volumes(&self) -> Result<Vec<VolumeInfo>> {
+        self.get("/api/volumes").await
+    }
+
+    pub async fn image_sizes(&self) -> Result<HashMap<String, f64>> {
+        self.get("/api/images/sizes").await
+    }
+
+    /// Usage per image; with a `range` (such as `6h`) each image also carries
+    /// its recorded history, summed per `step`
+    pub async fn image_usage(&self, range: Option<&str>, step: Option<&str>) -> Result<Vec<ImageUsage>> {
+        let query: Vec<String> = [("range", range), ("step", step)]
+            .into_iter()
+            .filter_map(|(key, value)| Some(format!("{}={}", key, Self::encode(value?))))
+            .collect();
+        self.get(&format!("/api/images/usage?{}", query.join("&"))).await
+    }
+
+    pub async fn images(&self) -> Result<Vec<ImageInfo>> {
+        self.get("/api/images").await
+    }
+
+    /// Start pulling `image`; poll the returned job for the outcome
+    pub async fn pull_image(&self, image: &str) -> Result<Job> {
+        let request = ImagePullRequest {
+            image: image.to_string(),
+        };
+        self.post("/api/images/pull", &request).await
+    }
+
+    /// Remove a container; the server refuses running ones unless `force` is set
+    pub async fn remove(&self, id: &str, force: bool, remove_volumes: bool) -> Result<ActionResult> {
+        Self::send(self.request(
+            Method::DELETE,
+            &format!(
+                "/api/container/{}?force={}&remove_volumes={}",
+                Self::encode(id),
+                force,
+                remove_volumes
+            ),
+        ))
+        .await
+    }
+
+    pub async fn remove_image(&self, id: &str, force: bool) -> Result<()> {
+        self.delete(&format!("/api/image/{}?force={}", Self::encode(id), force)).await
+    }
+
+    pub async fn system_info(&self) -> Result<SystemInfo> {
+        self.get("/api/system/info").await
+    }
+
+    pub async fn cpu_map(&self) -> Result<CpuMap> {
+        self.get("/api/host/cpus").await
+    }
+
+    pub async fn flowchart(&self, id: &str) -> Result<Flowchart> {
+        self.get(&format!("/api/flowchart/{}", Self::encode(id))).await
+    }
+
+    /// A flowchart trimmed to `fields`, e.g. `["nodes.id", "nodes.status", "edges"]`
+    pub async fn flowchart_fields(&self, id: &str, fields: &[&str]) -> Result<serde_json::Value> {
+        self.get(&format!(
+            "/api/flowchart/{}?fields={}",
+            Self::encode(id),
+            Self::encode(&fields.join(","))
+        ))
+        .await
+    }
+
+    /// A flowchart without the nodes of containers `selector` does not pick
+    pub async fn flowchart_matching(&self, id: &str, selector: &str) -> Result<Flowchart> {
+        self.get(&format!(
+            "/api/flowchart/{}?selector={}",
+            Self::encode(id),
+            Self::encode(selector)
+        ))
+        .await
+    }
+
+    /// The network path between two containers as a flowchart
+    pub async fn network_path(&self, from: &str, to: &str) -> Result<Flowchart> {
+        self.get(&format!(
+            "/api/graph/path?from={}&to={}",
+            Self::encode(from),
+            Self::encode(to)
+        ))
+        .await
+    }
+
+    /// Drop a flowchart's saved layout so its nodes are placed afresh
+    pub async fn reset_layout(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/flowchart/{}/layout", Self::encode(id))).await
+    }
+
+    /// A flowchart as Mermaid or Graphviz source flowing in `direction`
+    pub async fn export_flowchart(&self, id: &str, format: FlowchartFormat, direction: FlowDirection) -> Result<String> {
+        let format = match format {
+            FlowchartFormat::Mermaid => "mermaid",
+            FlowchartFormat::Dot => "dot",
+        };
+        let direction = match direction {
+            FlowDirection::Lr => "lr",
+            FlowDirection::Td => "td",
+        };
+        self.get_text(&format!(
+            "/api/flowchart/{}/export?format={}&direction={}",
+            Self::encode(id),
+            format,
+            direction
+        ))
+        .await
+    }
+
+    /// A flowchart rendered as an SVG image; needs a server built with the `svg` feature
+    pub async fn flowchart_svg(&self, id: &str) -> Result<String> {
+        self.get_text(&format!("/api/flowchart/{}.svg", Self::encode(id))).await
+    }
+
+    /// Inferred dependency graph as `graphml` or `jgf` (JSON Graph Format)
+    pub async fn export_graph(&self, format: &str) -> Result<String> {
+        self.get_text(&format!("/api/graph/export?format={}", Self::encode(format)))
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Topology history
+    // -------------------------------------------------------------------------
+
+    /// Recorded topology snapshots, oldest first
+    pub async fn snapshots(&self) -> Result<Vec<SnapshotSummary>> {
+        self.get("/api/history/snapshots").await
+    }
+
+    /// What changed between two snapshots, each a snapshot id or an RFC 3339
+    /// time; `to` defaults to the latest snapshot
+    pub async fn topology_diff(&self, from: &str, to: Option<&str>) -> Result<TopologyDiff> {
+        let mut path = format!("/api/history/diff?from={}", Self::encode(from));
+        if let Some(to) = to {
+            path.push_str(&format!("&to={}", Self::encode(to)));
+        }
+        self.get(&path).await
+    }
+
+    // -------------------------------------------------------------------------
+    // Containers
+    // -------------------------------------------------------------------------
+
+    pub async fn container(&self, id: &str) -> Result<ContainerInfo> {
+        self.get(&format!("/api/container/{}", Self::encode(id))).await
+    }
+
+    /// Environment values are only shown unredacted to admin tokens
+    pub async fn container_detail(&self, id: &str, redact: bool) -> Result<ContainerDetail> {
+        self.get(&format!("/api/container/{}/detail?redact={}", Self::encode(id), redact))
+            .await
+    }
+
+    pub async fn container_logs(&self, id: &str, tail: usize) -> Result<ContainerLogs> {
+        self.get(&format!("/api/container/{}/logs?tail={}", Self::encode(id), tail))
+            .await
+    }
+
+    /// The last `tail` log lines of `streams` between `since` and `until`,
+    /// each RFC 3339, Unix seconds or an age such as `15m`, cleaned up as
+    /// `cleanup` asks
+    pub async fn container_logs_between(
+        &self,
+        id: &str,
+        tail: usize,
+        since: Option<&str>,
+        until: Option<&str>,
+        streams: LogStreams,
+        cleanup: LogCleanup,
+    ) -> Result<ContainerLogs> {
+        let mut path = format!("/api/container/{}/logs?tail={}", Self::encode(id), tail);
+        for (name, value) in [("since", since), ("until", until)] {
+            if let Some(value) = value {
+                path.push_str(&format!("&{}={}", name, Self::encode(value)));
+            }
+        }
+        let streams = match streams {
+            LogStreams::Stdout => "stdout",
+            LogStreams::Stderr => "stderr",
+            LogStreams::Both => "both",
+        };
+        self.get(&format!("{}&streams={}{}", path, streams, cleanup.query()))
+            .await
+    }
+
+    /// The last `tail` lines that contain `pattern`, or match it as a regular
+    /// expression when `regex` is set
+    pub async fn grep_logs(
+        &self,
+        id: &str,
+        tail: usize,
+        pattern: &str,
+        regex: bool,
+        cleanup: LogCleanup,
+    ) -> Result<ContainerLogs> {
+        self.get(&format!(
+            "/api/container/{}/logs?tail={}&grep={}&regex={}{}",
+            Self::encode(id),
+            tail,
+            Self::encode(pattern),
+            regex,
+            cleanup.query()
+        ))
+        .await
+    }
+
+    /// The last `tail` log lines of every container `selector` picks
+    pub async fn logs_matching(&self, selector: &str, tail: usize) -> Result<Vec<ContainerLogs>> {
+        self.get(&format!("/api/logs?selector={}&tail={}", Self::encode(selector), tail))
+            .await
+    }
+
+    /// Indexed log lines from the last `range` (such as `6h`) holding every
+    /// word of `query`, from `container` when named
+    pub async fn search_logs(&self, query: &str, container: Option<&str>, range: &str) -> Result<LogSearchResult> {
+        let mut path = format!("/api/logs/search?q={}&range={}", Self::encode(query), Self::encode(range));
+        if let Some(container) = container {
+            path.push_str(&format!("&container={}", Self::encode(container)));
+        }
+        self.get(&path).await
+    }
+
+    pub async fn container_stats(&self, id: &str) -> Result<ContainerStats> {
+        self.get(&format!("/api/container/{}/stats", Self::encode(id))).await
+    }
+
+    /// Ports the container actually listens on and the connections it holds open
+    /// Recorded stats of a container over the last `range` (such as `6h`),
+    /// averaged per `step` (such as `1m`); the server defaults to an hour of
+    /// sampling-interval points
+    pub async fn stats_history(&self, id: &str, range: Option<&str>, step: Option<&str>) -> Result<StatsHistory> {
+        let query: Vec<String> = [("range", range), ("step", step)]
+            .into_iter()
+            .filter_map(|(key, value)| Some(format!("{}={}", key, Self::encode(value?))))
+            .collect();
+        self.get(&format!(
+            "/api/container/{}/stats/history?{}",
+            Self::encode(id),
+            query.join("&")
+        ))
+        .await
+    }
+
+    pub async fn container_sockets(&self, id: &str) -> Result<ContainerSockets> {
+        self.get(&format!("/api/container/{}/sockets", Self::encode(id))).await
+    }
+
+    pub async fn container_vulnerabilities(&self, id: &str) -> Result<VulnerabilityReport> {
+        self.get(&format!("/api/container/{}/vulnerabilities", Self::encode(id))).await
+    }
+
+    /// Which categorization strategy decided the container's category
+    pub async fn explain_category(&self, id: &str) -> Result<CategoryExplanation> {
+        self.get(&format!("/api/container/{}/category/explain", Self::encode(id)))
+            .await
+    }
+
+    async fn action(&self, id: &str, action: &str) -> Result<ActionResult> {
+        Self::send(self.request(
+            Method::POST,
+            &format!("/api/container/{}/{}", Self::encode(id), action),
+        ))
+        .await
+    }
+
+    pub async fn restart(&self, id: &str) -> Result<ActionResult> {
+        self.action(id, "restart").await
+    }
+
+    pub async fn stop(&self, id: &str) -> Result<ActionResult> {
+        self.action(id, "stop").await
+    }
+
+    pub async fn start(&self, id: &str) -> Result<ActionResult> {
+        self.action(id, "start").await
+    }
+
+    pub async fn pause(&self, id: &str) -> Result<ActionResult> {
+        self.action(id, "pause").await
+    }
+
+    pub async fn unpause(&self, id: &str) -> Result<ActionResult> {
+        self.action(id, "unpause").await
+    }
+
+    /// Send `signal` (`SIGKILL` when `None`) to the container's main process
+    pub async fn kill(&self, id: &str, signal: Option<&str>) -> Result<ActionResult> {
+        let request = KillRequest {
+            signal: signal.map(String::from),
+        };
+        self.post(&format!("/api/container/{}/kill", Self::encode(id)), &request).await
+    }
+
+    /// Warm-up requests run after restarts of the container's service
+    pub async fn warmup_profile(&self, id: &str) -> Result<WarmupProfile> {
+        self.get(&format!("/api/container/{}/warmup", Self::encode(id))).await
+    }
+
+    pub async fn set_warmup_profile(&self, id: &str, profile: &WarmupProfile) -> Result<WarmupProfile> {
+        Self::send(
+            self.request(Method::PUT, &format!("/api/container/{}/warmup", Self::encode(id)))
+                .json(profile),
+        )
+        .await
+    }
+
+    pub async fn remove_warmup_profile(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/container/{}/warmup", Self::encode(id))).await
+    }
+
+    /// Run the warm-up requests now, without restarting
+    pub async fn warm_up(&self, id: &str) -> Result<WarmupReport> {
+        Self::send(self.request(
+            Method::POST,
+            &format!("/api/container/{}/warmup", Self::encode(id)),
+        ))
+        .await
+    }
+
+    /// Images the container's service ran over time
+    pub async fn image_history(&self, id: &str) -> Result<ImageTimeline> {
+        self.get(&format!("/api/container/{}/image-history", Self::encode(id)))
+            .await
+    }
+
+    /// Recreate the container on an image its service ran before
+    pub async fn rollback(&self, id: &str, digest: &str) -> Result<ActionResult> {
+        self.action(id, &format!("rollback?to={}", Self::encode(digest))).await
+    }
+
+    pub async fn preflight(&self, spec: &ContainerSpec) -> Result<PreflightReport> {
+        self.post("/api/containers/preflight", spec).await
+    }
+
+    /// Create and start a container; refused when its pre-flight checks fail
+    pub async fn create_container(&self, spec: &ContainerSpec) -> Result<ContainerInfo> {
+        self.post("/api/containers", spec).await
+    }
+
+    /// Run a command inside a running container and collect its output
+    pub async fn exec(&self, id: &str, request: &ExecRequest) -> Result<ExecResult> {
+        self.post(&format!("/api/container/{}/exec", Self::encode(id)), request)
+            .await
+    }
+
+    /// Attach a tools container to a running container's network and PID
+    /// namespaces until its TTL runs out
+    pub async fn debug_sidecar(&self, id: &str, request: &DebugSidecarRequest) -> Result<DebugSidecar> {
+        self.post(&format!("/api/container/{}/debug-sidecar", Self::encode(id)), request)
+            .await
+    }
+
+    pub async fn debug_sidecars(&self) -> Result<Vec<DebugSidecar>> {
+        self.get("/api/debug-sidecars").await
+    }
+
+    pub async fn remove_debug_sidecar(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/debug-sidecars/{}", Self::encode(id))).await
+    }
+
+    /// Readiness of every legacy container that has a rust equivalent, with
+    /// usage compared over the last `minutes`
+    pub async fn migrations(&self, minutes: u32) -> Result<Vec<MigrationReport>> {
+        self.get(&format!("/api/migrations?minutes={}", minutes)).await
+    }
+
+    pub async fn migration(&self, legacy: &str, minutes: u32) -> Result<MigrationReport> {
+        self.get(&format!("/api/migrations/{}?minutes={}", Self::encode(legacy), minutes))
+            .await
+    }
+
+    /// Start a job stopping `legacy`; refused unless its report is ready
+    pub async fn cut_over(&self, legacy: &str, minutes: u32) -> Result<Job> {
+        Self::send(self.request(
+            Method::POST,
+            &format!("/api/migrations/{}/cutover?minutes={}", Self::encode(legacy), minutes),
+        ))
+        .await
+    }
+
+    /// Create a one-off container; attach to it over the WebSocket to start it
+    pub async fn create_run(&self, request: &OneOffRunRequest) -> Result<OneOffRun> {
+        self.post("/api/containers/run", request).await
+    }
+
+    pub async fn runs(&self) -> Result<Vec<OneOffRun>> {
+        self.get("/api/runs").await
+    }
+
+    pub async fn remove_run(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/runs/{}", Self::encode(id))).await
+    }
+
+    /// Open a temporary proxy to a container port; the response carries the
+    /// forward's token and, for HTTP forwards, the link to open
+    pub async fn open_forward(&self, id: &str, request: &PortForwardRequest) -> Result<PortForward> {
+        self.post(&format!("/api/container/{}/forward", Self::encode(id)), request)
+            .await
+    }
+
+    pub async fn forwards(&self) -> Result<Vec<PortForward>> {
+        self.get("/api/forwards").await
+    }
+
+    pub async fn forward(&self, id: &str) -> Result<PortForward> {
+        self.get(&format!("/api/forwards/{}", Self::encode(id))).await
+    }
+
+    pub async fn close_forward(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/forwards/{}", Self::encode(id))).await
+    }
+
+    /// Check that a published port can be reached; `endpoint` is
+    /// `<container>:<hostPort>`
+    pub async fn test_endpoint(&self, endpoint: &str, request: &EndpointTestRequest) -> Result<EndpointTest> {
+        self.post(&format!("/api/endpoints/{}/test", Self::encode(endpoint)), request)
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Diagnostics
+    // -------------------------------------------------------------------------
+
+    pub async fn diagnostics(&self) -> Result<DiagnosticsReport> {
+        self.get("/api/diagnostics").await
+    }
+
+    pub async fn host_diagnostics(&self) -> Result<HostDiagnostics> {
+        self.get("/api/diagnostics/host").await
+    }
+
+    pub async fn process_diagnostics(&self) -> Result<Vec<ProcessDiagnostics>> {
+        self.get("/api/diagnostics/processes").await
+    }
+
+    pub async fn container_process_diagnostics(&self, id: &str) -> Result<ProcessDiagnostics> {
+        self.get(&format!("/api/container/{}/diagnostics/processes", Self::encode(id)))
+            .await
+    }
+
+    pub async fn container_processes(&self, id: &str) -> Result<ContainerProcesses> {
+        self.get(&format!("/api/container/{}/processes", Self::encode(id))).await
+    }
+
+    /// The daemon's inspect response, untyped since it is passed through as is;
+    /// environment values are only shown unredacted to `admin` tokens
+    pub async fn inspect(&self, id: &str, redact: bool) -> Result<serde_json::Value> {
+        self.get(&format!("/api/container/{}/inspect?redact={}", Self::encode(id), redact))
+            .await
+    }
+
+    pub async fn container_changes(&self, id: &str) -> Result<ContainerChanges> {
+        self.get(&format!("/api/container/{}/changes", Self::encode(id))).await
+    }
+
+    /// Prometheus text exposition of `/metrics`, for scrapers that go through the client
+    pub async fn prometheus_metrics(&self) -> Result<String> {
+        self.get_text("/metrics").await
+    }
+
+    /// TypeScript declarations of the API types, as served to the frontend
+    pub async fn typescript_schema(&self) -> Result<String> {
+        self.get_text("/api/schema/typescript").await
+    }
+
+    // -------------------------------------------------------------------------
+    // Templates
+    // -------------------------------------------------------------------------
+
+    pub async fn templates(&self) -> Result<Vec<ContainerTemplate>> {
+        self.get("/api/templates").await
+    }
+
+    pub async fn template(&self, name: &str) -> Result<ContainerTemplate> {
+        self.get(&format!("/api/templates/{}", Self::encode(name))).await
+    }
+
+    pub async fn create_template(&self, template: &ContainerTemplate) -> Result<ContainerTemplate> {
+        self.post("/api/templates", template).await
+    }
+
+    pub async fn update_template(&self, template: &ContainerTemplate) -> Result<ContainerTemplate> {
+        Self::send(
+            self.request(
+                Method::PUT,
+                &format!("/api/templates/{}", Self::encode(&template.name)),
+            )
+            .json(template),
+        )
+        .await
+    }
+
+    pub async fn delete_template(&self, name: &str) -> Result<()> {
+        self.delete(&format!("/api/templates/{}", Self::encode(name))).await
+    }
+
+    pub async fn instantiate_template(
+        &self,
+        name: &str,
+        request: &InstantiateRequest,
+    ) -> Result<InstantiateResponse> {
+        self.post(&format!("/api/templates/{}/instantiate", Self::encode(name)), request)
+            .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Groups
+    // -------------------------------------------------------------------------
+
+    pub async fn groups(&self) -> Result<Vec<ContainerGroup>> {
+        self.get("/api/groups").await
+    }
+
+    pub async fn group(&self, name: &str) -> Result<ContainerGroup> {
+        self.get(&format!("/api/groups/{}", Self::encode(name))).await
+    }
+
+    pub async fn create_group(&self, group: &ContainerGroup) -> Result<ContainerGroup> {
+        self.post("/api/groups", group).await
+    }
+
+    pub async fn update_group(&self, group: &ContainerGroup) -> Result<ContainerGroup> {
+        Self::send(
+            self.request(Method::PUT, &format!("/api/groups/{}", Self::encode(&group.name)))
+                .json(group),
+        )
+        .await
+    }
+
+    pub async fn delete_group(&self, name: &str) -> Result<()> {
+        self.delete(&format!("/api/groups/{}", Self::encode(name))).await
+    }
+
+    pub async fn group_flowchart(&self, name: &str) -> Result<Flowchart> {
+        self.get(&format!("/api/groups/{}/flowchart", Self::encode(name))).await
+    }
+
+    pub async fn group_stats(&self, name: &str) -> Result<GroupStats> {
+        self.get(&format!("/api/groups/{}/stats", Self::encode(name))).await
+    }
+
+    /// Run `restart`, `stop` or `start` on every member of a group
+    pub async fn group_action(&self, name: &str, action: &str) -> Result<Vec<ActionResult>> {
+        Self::send(self.request(
+            Method::POST,
+            &format!("/api/groups/{}/actions/{}", Self::encode(name), Self::encode(action)),
+        ))
+        .await
+    }
+
+    /// Restart, stop or start every container `selector` picks
+    pub async fn bulk_action(&self, action: &str, selector: &str) -> Result<Vec<ActionResult>> {
+        Self::send(self.request(
+            Method::POST,
+            &format!(
+                "/api/containers/actions/{}?selector={}",
+                Self::encode(action),
+                Self::encode(selector)
+            ),
+        ))
+        .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Dashboards
+    // -------------------------------------------------------------------------
+
+    pub async fn dashboards(&self) -> Result<Vec<Dashboard>> {
+        self.get("/api/dashboards").await
+    }
+
+    pub async fn dashboard(&self, id: &str) -> Result<Dashboard> {
+        self.get(&format!("/api/dashboards/{}", Self::encode(id))).await
+    }
+
+    /// Create a dashboard; the response carries the id the server assigned
+    pub async fn create_dashboard(&self, dashboard: &Dashboard) -> Result<Dashboard> {
+        self.post("/api/dashboards", dashboard).await
+    }
+
+    pub async fn update_dashboard(&self, dashboard: &Dashboard) -> Result<Dashboard> {
+        Self::send(
+            self.request(Method::PUT, &format!("/api/dashboards/{}", Self::encode(&dashboard.id)))
+                .json(dashboard),
+        )
+        .await
+    }
+
+    pub async fn delete_dashboard(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/dashboards/{}", Self::encode(id))).await
+    }
+
+    /// Every widget's data in one call
+    pub async fn dashboard_data(&self, id: &str) -> Result<DashboardData> {
+        self.get(&format!("/api/dashboards/{}/data", Self::encode(id))).await
+    }
+
+    // -------------------------------------------------------------------------
+    // Compose projects & jobs
+    // -------------------------------------------------------------------------
+
+    pub async fn projects(&self) -> Result<Vec<ComposeProject>> {
+        self.get("/api/projects").await
+    }
+
+    /// Start an `up`, `down` or `restart` job for a project; poll it with [`Self::job`]
+    pub async fn project_action(&self, name: &str, action: &str) -> Result<Job> {
+        Self::send(self.request(
+            Method::POST,
+            &format!("/api/projects/{}/{}", Self::encode(name), Self::encode(action)),
+        ))
+        .await
+    }
+
+    /// Start a job that brings a project service to `replicas` containers
+    pub async fn scale_service(&self, project: &str, service: &str, replicas: u32) -> Result<Job> {
+        Self::send(self.request(
+            Method::POST,
+            &format!(
+                "/api/projects/{}/services/{}/scale?replicas={}",
+                Self::encode(project),
+                Self::encode(service),
+                replicas
+            ),
+        ))
+        .await
+    }
+
+    /// Upload a project's `.env` file; the response compares it with the running containers
+    pub async fn upload_env_file(&self, project: &str, content: impl Into<String>) -> Result<EnvDriftReport> {
+        Self::send(
+            self.request(Method::PUT, &format!("/api/projects/{}/env", Self::encode(project)))
+                .header(reqwest::header::CONTENT_TYPE, "text/plain")
+                .body(content.into()),
+        )
+        .await
+    }
+
+    pub async fn env_drift(&self, project: &str) -> Result<EnvDriftReport> {
+        self.get(&format!("/api/projects/{}/env/drift", Self::encode(project))).await
+    }
+
+    pub async fn delete_env_file(&self, project: &str) -> Result<()> {
+        self.delete(&format!("/api/projects/{}/env", Self::encode(project))).await
+    }
+
+    pub async fn jobs(&self) -> Result<Vec<Job>> {
+        self.get("/api/jobs").await
+    }
+
+    pub async fn job(&self, id: &str) -> Result<Job> {
+        self.get(&format!("/api/jobs/{}", Self::encode(id))).await
+    }
+
+    // -------------------------------------------------------------------------
+    // Artifacts
+    // -------------------------------------------------------------------------
+
+    /// Start a job that archives a container's logs; the finished job lists the artifact
+    pub async fn export_logs(&self, id: &str) -> Result<Job> {
+        Self::send(self.request(
+            Method::POST,
+            &format!("/api/container/{}/logs/export", Self::encode(id)),
+        ))
+        .await
+    }
+
+    pub async fn artifacts(&self) -> Result<Vec<Artifact>> {
+        self.get("/api/artifacts").await
+    }
+
+    /// Download an artifact's content
+    pub async fn download_artifact(&self, id: &str) -> Result<Vec<u8>> {
+        self.get_bytes(&format!("/api/artifacts/{}", Self::encode(id))).await
+    }
+
+    pub async fn delete_artifact(&self, id: &str) -> Result<()> {
+        self.delete(&format!("/api/artifacts/{}", Self::encode(id))).await
+    }
+
+    // -------------------------------------------------------------------------
+    // Events
+    // -------------------------------------------------------------------------
+
+    /// Recorded daemon events, oldest first, optionally only those about
+    /// `container` (name or id prefix) since `since`
+    pub async fn events(
+        &self,
+        container: Option<&str>,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Vec<DockerEvent>> {
+        let mut query = Vec::new();
+        if let Some(container) = container {
+            query.push(format!("container={}", Self::encode(container)));
+        }
+        if let Some(since) = since {
+            query.push(format!("since={}", Self::encode(&since.to_rfc3339())));
+        }
+        self.get(&format!("/api/events?{}", query.join("&"))).await
+    }
+
+    /// Topology and container updates as Server-Sent Events, for networks whose
+    /// proxies refuse WebSocket upgrades; `last_event_id` picks up after the
+    /// last sequence number seen
+    pub async fn stream_events(&self, last_event_id: Option<u64>) -> Result<EventStream> {
+        let mut request = self
+            .request(Method::GET, "/api/events/stream")
+            .header(reqwest::header::ACCEPT, "text/event-stream");
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id.to_string());
+        }
+        let response = request.send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(EventStream {
+                response,
+                buffer: Vec::new(),
+            }),
+            status => Err(ClientError::Api {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    // -------------------------------------------------------------------------
+    // Alerts
+    // -------------------------------------------------------------------------
+
+    /// Configured rules, firing alerts and recently resolved ones
+    pub async fn alerts(&self) -> Result<AlertList> {
+        self.get("/api/alerts").await
+    }
+
+    /// Replay a candidate rule over recorded stats to see when it would have fired
+    pub async fn test_alert_rule(&self, request: &AlertRuleTest) -> Result<AlertRuleTestReport> {
+        self.post("/api/alerts/rules/test", request).await
+    }
+
+    // -------------------------------------------------------------------------
+    // Notification channels
+    // -------------------------------------------------------------------------
+
+    pub async fn notification_channels(&self) -> Result<Vec<NotificationChannel>> {
+        self.get("/api/notifications/channels").await
+    }
+
+    pub async fn notification_channel(&self, name: &str) -> Result<NotificationChannel> {
+        self.get(&format!("/api/notifications/channels/{}", Self::encode(name))).await
+    }
+
+    pub async fn create_notification_channel(&self, channel: &NotificationChannel) -> Result<NotificationChannel> {
+        self.post("/api/notifications/channels", channel).await
+    }
+
+    pub async fn update_notification_channel(&self, channel: &NotificationChannel) -> Result<NotificationChannel> {
+        Self::send(
+            self.request(
+                Method::PUT,
+                &format!("/api/notifications/channels/{}", Self::encode(&channel.name)),
+            )
+            .json(channel),
+        )
+        .await
+    }
+
+    pub async fn delete_notification_channel(&self, name: &str) -> Result<()> {
+        self.delete(&format!("/api/notifications/channels/{}", Self::encode(name))).await
+    }
+
+    /// Delivery totals per channel and the latest deliveries
+    pub async fn notification_status(&self) -> Result<NotificationStatus> {
+        self.get("/api/notifications/status").await
+    }
+
+    /// Render a channel's payload against a sample alert without saving it
+    pub async fn preview_notification(&self, channel: &NotificationChannel) -> Result<RenderedNotification> {
+        self.post("/api/notifications/preview", channel).await
+    }
+
+    // -------------------------------------------------------------------------
+    // Auth
+    // -------------------------------------------------------------------------
+
+    /// Scopes API tokens can hold and the routes that require each
+    pub async fn auth_scopes(&self) -> Result<Vec<ScopeInfo>> {
+        self.get("/api/auth/scopes").await
+    }
+
+    /// Requests, actions and WebSocket time per token; needs the `admin` scope
+    pub async fn auth_usage(&self) -> Result<Vec<TokenUsage>> {
+        self.get("/api/auth/usage").await
+    }
+
+    // -------------------------------------------------------------------------
+    // Config bundles
+    // -------------------------------------------------------------------------
+
+    /// Download the signed YAML bundle of all templates and groups
+    pub async fn export_bundle(&self) -> Result<String> {
+        self.get_text("/api/bundle/export").await
+    }
+
+    /// Import a bundle produced by `export_bundle`, optionally only reporting what would change
+    pub async fn import_bundle(
+        &self,
+        yaml: impl Into<String>,
+        mode: ImportMode,
+        dry_run: bool,
+    ) -> Result<ImportReport> {
+        let mode = match mode {
+            ImportMode::Merge => "merge",
+            ImportMode::Replace => "replace",
+        };
+        Self::send(
+            self.request(
+                Method::POST,
+                &format!("/api/bundle/import?mode={}&dryRun={}", mode, dry_run),
+            )
+            .header(reqwest::header::CONTENT_TYPE, "application/yaml")
+            .body(yaml.into()),
+        )
+        .await
+    }
+
+    // -------------------------------------------------------------------------
+    // Embed
+    // -------------------------------------------------------------------------
+
+    pub async fn create_embed_token(&self, request: &EmbedTokenRequest) -> Result<EmbedToken> {
+        self.post("/api/embed/tokens", request).await
+    }
+
+    // -------------------------------------------------------------------------
+    // WebSocket
+    // -------------------------------------------------------------------------
+
+    /// Open a WebSocket on `path`; `params` without a value are left out
+    async fn connect(&self, path: &str, params: &[(&str, Option<String>)]) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let ws_base = self
+            .base_url
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+        // Browsers cannot set headers on the upgrade, so the server also
+        // accepts the API token as a query parameter
+        let query: Vec<String> = params
+            .iter()
+            .map(|(key, value)| (*key, value.as_deref()))
+            .chain([("access_token", self.token.as_deref())])
+            .filter_map(|(key, value)| Some(format!("{}={}", key, Self::encode(value?))))
+            .collect();
+        let url = if query.is_empty() {
+            format!("{}{}", ws_base, path)
+        } else {
+            format!("{}{}?{}", ws_base, path, query.join("&"))
+        };
+
+        let (stream, _) = tokio_tungstenite::connect_async(url).await?;
+        Ok(stream)
+    }
+
+    /// Connect to `/ws`, optionally resuming a previous session
+    pub async fn subscribe(&self, resume_token: Option<&str>) -> Result<WsSubscription> {
+        let stream = self
+            .connect("/ws", &[("resume_token", resume_token.map(str::to_string))])
+            .await?;
+        Ok(WsSubscription { stream })
+    }
+
+    /// Open an interactive terminal in a running container, running `shell`
+    /// instead of bash or sh when given
+    pub async fn open_terminal(&self, id: &str, shell: Option<&str>, rows: u16, cols: u16) -> Result<TerminalSession> {
+        let path = format!("/ws/exec/{}", Self::encode(id));
+        let params = [
+            ("shell", shell.map(str::to_string)),
+            ("rows", Some(rows.to_string())),
+            ("cols", Some(cols.to_string())),
+        ];
+        let stream = self.connect(&path, &params).await?;
+        Ok(TerminalSession { stream })
+    }
+
+    /// Follow a container's logs, starting with the last `tail` lines
+    pub async fn follow_logs(&self, id: &str, tail: Option<usize>) -> Result<LogStream> {
+        let path = format!("/ws/logs/{}", Self::encode(id));
+        let stream = self.connect(&path, &[("tail", tail.map(|t| t.to_string()))]).await?;
+        Ok(LogStream { stream })
+    }
+}
+
+/// A frame received over the WebSocket
+#[derive(Debug, Clone)]
+pub enum WsFrame {
+    /// A sequenced update that counts towards the resume position
+    Update(WsEnvelope),
+    /// A connection-level message such as a welcome or heartbeat
+    Control(WsMessage),
+}
+
+/// Live connection to `/ws`
+pub struct WsSubscription {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl WsSubscription {
+    /// Next frame, or `None` once the server closes the connection
+    pub async fn next(&mut self) -> Result<Option<WsFrame>> {
+        while let Some(message) = self.stream.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            };
+
+            let value: serde_json::Value = serde_json::from_str(&text)?;
+            let frame = if value.get("seq").is_some() && value.get("topic").is_some() {
+                WsFrame::Update(serde_json::from_value(value)?)
+            } else {
+                WsFrame::Control(serde_json::from_value(value)?)
+            };
+            return Ok(Some(frame));
+        }
+        Ok(None)
+    }
+
+    pub async fn send(&mut self, message: &WsClientMessage) -> Result<()> {
+        let text = serde_json::to_string(message)?;
+        self.stream.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
+
+/// A frame received from a terminal
+#[derive(Debug, Clone)]
+pub enum TerminalFrame {
+    /// Raw bytes the command wrote
+    Output(Vec<u8>),
+    /// How the command ended, or a control message that failed
+    Message(TerminalMessage),
+}
+
+/// Live connection to `/ws/exec/:id`
+pub struct TerminalSession {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl TerminalSession {
+    /// Next frame, or `None` once the server closes the connection
+    pub async fn next(&mut self) -> Result<Option<TerminalFrame>> {
+        while let Some(message) = self.stream.next().await {
+            match message? {
+                Message::Binary(data) => return Ok(Some(TerminalFrame::Output(data))),
+                Message::Text(text) => return Ok(Some(TerminalFrame::Message(serde_json::from_str(&text)?))),
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Type `data` into the terminal
+    pub async fn write(&mut self, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.stream.send(Message::Binary(data.into())).await?;
+        Ok(())
+    }
+
+    pub async fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
+        let text = serde_json::to_string(&TerminalControl::Resize { rows, cols })?;
+        self.stream.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    /// Hang up; the server types Ctrl-C and Ctrl-D and closes the input
+    pub async fn close(mut self) -> Result<()> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
+
+/// Live connection to `/ws/logs/:id`
+pub struct LogStream {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl LogStream {
+    /// Next message, or `None` once the server closes the connection
+    pub async fn next(&mut self) -> Result<Option<LogStreamMessage>> {
+        while let Some(message) = self.stream.next().await {
+            match message? {
+                Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
+
+/// Live response of `/api/events/stream`
+pub struct EventStream {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+}
+
+impl EventStream {
+    /// Next update, or `None` once the server ends the stream
+    pub async fn next(&mut self) -> Result<Option<WsEnvelope>> {
+        loop {
+            // Events end with a blank line; a chunk may hold several or part of one
+            if let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+                let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                let event = String::from_utf8_lossy(&event);
+                // Keep-alive comments carry no data
+                let data: Vec<&str> = event
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                    .collect();
+                if !data.is_empty() {
+                    return Ok(Some(serde_json::from_str(&data.join("\n"))?));
+                }
+                continue;
+            }
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! FlowScope API types
+//!
+//! Serde models shared by the FlowScope backend and its consumers, plus (with
+//! the default `client` feature) a typed HTTP client and WebSocket subscriber.
+//! The `ts` feature generates matching TypeScript definitions.
+
+pub mod models;
+pub mod ws;
+
+#[cfg(feature = "ts")]
+pub mod ts;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "client")]
+pub use client::{ClientError, EventStream, FlowScopeClient, LogCleanup, WsFrame, WsSubscription};
@@ -0,0 +1,3030 @@
+//! Data models for FlowScope
+//!
+//! These models match the frontend TypeScript types for seamless integration
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// =============================================================================
+// CONTAINER MODELS
+// =============================================================================
+
+/// Container status enum matching Docker states
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerStatus {
+    Running,
+    Healthy,
+    Unhealthy,
+    Exited,
+    Created,
+    Paused,
+    Restarting,
+    Dead,
+}
+
+/// Container resource statistics
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerStats {
+    pub cpu_percent: f64,
+    pub memory_usage_mb: f64,
+    pub memory_limit_mb: f64,
+    pub memory_percent: f64,
+    pub network_rx_mb: f64,
+    pub network_tx_mb: f64,
+    pub network_rx_errors: u64,
+    pub network_tx_errors: u64,
+    pub network_rx_dropped: u64,
+    pub network_tx_dropped: u64,
+    pub block_read_mb: f64,
+    pub block_write_mb: f64,
+    /// Receive rate over the window since the previous sample; absent on a
+    /// container's first sample and right after a restart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network_rx_bytes_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network_tx_bytes_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub block_read_bytes_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub block_write_bytes_per_sec: Option<f64>,
+    /// Packets dropped per second over the same window as the byte rates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network_rx_dropped_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network_tx_dropped_per_sec: Option<f64>,
+    pub pids: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub pids_limit: Option<u64>,
+    /// Open file descriptors across the container's processes; only known
+    /// when FlowScope can read the Docker host's `/proc`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub open_fds: Option<u64>,
+    /// Highest share of any one process's open-files limit in use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub fd_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub inotify_instances: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub inotify_watches: Option<u64>,
+    /// Share of the per-user `fs.inotify.max_user_watches` budget in use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub inotify_watch_percent: Option<f64>,
+}
+
+impl From<&str> for ContainerStatus {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "running" => Self::Running,
+            "healthy" => Self::Healthy,
+            "unhealthy" => Self::Unhealthy,
+            "exited" => Self::Exited,
+            "created" => Self::Created,
+            "paused" => Self::Paused,
+            "restarting" => Self::Restarting,
+            "dead" => Self::Dead,
+            _ => Self::Exited,
+        }
+    }
+}
+
+/// Service category for grouping containers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "kebab-case")]
+pub enum ServiceCategory {
+    Aiml,
+    Application,
+    Infrastructure,
+    Frontend,
+    Monitoring,
+    Game,
+    Val,
+    Blockchain,
+    Other,
+}
+
+impl ServiceCategory {
+    pub fn from_name(name: &str) -> Self {
+        let lower = name.to_lowercase();
+        if lower.starts_with("aiml-") {
+            Self::Aiml
+        } else if lower.starts_with("application-") {
+            Self::Application
+        } else if lower.starts_with("infrastructure-") {
+            Self::Infrastructure
+        } else if lower.starts_with("frontend-") {
+            Self::Frontend
+        } else if lower.starts_with("monitoring-") {
+            Self::Monitoring
+        } else if lower.starts_with("game-") {
+            Self::Game
+        } else if lower.starts_with("val-") {
+            Self::Val
+        } else if lower.starts_with("valina-validator") || lower.contains("chain") {
+            Self::Blockchain
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Container information
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ContainerInfo {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub status: ContainerStatus,
+    pub health: Option<String>,
+    pub category: ServiceCategory,
+    pub ports: Vec<PortMapping>,
+    pub networks: Vec<String>,
+    pub created: DateTime<Utc>,
+    pub labels: HashMap<String, String>,
+    /// Name of the Docker host the container runs on, when FlowScope
+    /// watches several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub rust_equivalent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub stats: Option<ContainerStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub image_size_mb: Option<f64>,
+    /// Every logical group the container belongs to: its category, compose
+    /// projects and user-defined networks
+    #[serde(default)]
+    pub memberships: Vec<GroupMembership>,
+    /// Named volumes it mounts
+    #[serde(default)]
+    pub volume_names: Vec<String>,
+    /// When a container of this name last started, as far as the daemon's
+    /// events and FlowScope's listings show
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_started: Option<DateTime<Utc>>,
+    /// When it last started, stopped, paused, restarted or changed health
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_status_change: Option<DateTime<Utc>>,
+    /// When it was last created from a different image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_image_change: Option<DateTime<Utc>>,
+    /// When it was last created with a different command, labels, ports,
+    /// networks or mounts, renamed or updated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_config_change: Option<DateTime<Utc>>,
+    /// The latest HTTP health probe of its first published port, when probes
+    /// are on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub probe: Option<HealthProbe>,
+    /// Vulnerabilities found in its image, when scanning is on and the image
+    /// was scanned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub vulnerabilities: Option<VulnerabilitySummary>,
+}
+
+/// Outcome of requesting a container's health path over a published port
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct HealthProbe {
+    /// What was requested, such as `http://127.0.0.1:8080/health`
+    pub url: String,
+    /// Whether anything answered over HTTP
+    pub reachable: bool,
+    /// Whether the answer was a 2xx or 3xx
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub status: Option<u16>,
+    /// Time to the response headers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Image scanner FlowScope runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum Scanner {
+    Trivy,
+    Grype,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum VulnerabilitySeverity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// A known vulnerability in a package of an image
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct Vulnerability {
+    /// CVE or advisory id, such as `CVE-2024-3094`
+    pub id: String,
+    pub package: String,
+    pub installed_version: String,
+    /// First version without it, when a fix exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub fixed_version: Option<String>,
+    pub severity: VulnerabilitySeverity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub title: Option<String>,
+}
+
+/// Vulnerabilities of an image counted by severity
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilitySummary {
+    pub critical: usize,
+    pub high: usize,
+    pub medium: usize,
+    pub low: usize,
+    pub unknown: usize,
+    /// How many have a fixed version available
+    pub fixable: usize,
+}
+
+/// The latest scan of an image
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilityReport {
+    /// The reference that was scanned, such as `postgres:16`
+    pub image: String,
+    pub image_id: String,
+    pub scanner: Scanner,
+    pub scanned_at: DateTime<Utc>,
+    pub summary: VulnerabilitySummary,
+    /// Most severe first
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+/// A strategy deciding a container's category
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum CategoryStrategyKind {
+    /// An explicit category label on the container
+    Label,
+    /// The category configured for the container's compose project
+    Compose,
+    /// The first configured regex matching the container's name or image
+    Regex,
+    /// Well-known name prefixes, falling back to `other`
+    Heuristic,
+}
+
+/// What one strategy made of a container
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryStep {
+    pub strategy: CategoryStrategyKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub category: Option<ServiceCategory>,
+    /// Why the strategy did or did not apply
+    pub detail: String,
+}
+
+/// How a container's category was decided: the strategies tried in their
+/// configured order, ending with the one that matched
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryExplanation {
+    pub container: String,
+    pub category: ServiceCategory,
+    /// The strategy that decided; absent when none did and `other` was used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub strategy: Option<CategoryStrategyKind>,
+    pub steps: Vec<CategoryStep>,
+}
+
+/// Kind of logical group a container can belong to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum MembershipKind {
+    Category,
+    Project,
+    Network,
+}
+
+/// One logical group a container belongs to. A container on several
+/// networks or serving several compose projects lists each of them, rather
+/// than being shown under one picked arbitrarily.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct GroupMembership {
+    pub kind: MembershipKind,
+    pub name: String,
+    /// For a project the container was not started by: the project's
+    /// network it is attached to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub via: Option<String>,
+}
+
+impl GroupMembership {
+    /// Id of the flowchart showing this group's members
+    pub fn flowchart_id(&self) -> String {
+        match self.kind {
+            MembershipKind::Category => format!("{}-overview", self.name),
+            MembershipKind::Project => format!("project:{}", self.name),
+            MembershipKind::Network => format!("network:{}", self.name),
+        }
+    }
+}
+
+/// Port mapping information
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct PortMapping {
+    pub host_port: Option<u16>,
+    pub container_port: u16,
+    pub protocol: String,
+}
+
+// =============================================================================
+// FLOWCHART MODELS (matching frontend types)
+// =============================================================================
+
+/// Node type for the flowchart
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    Service,
+    Process,
+    Decision,
+    Group,
+}
+
+/// Connection type between nodes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionType {
+    Primary,
+    Secondary,
+    Data,
+    Control,
+    Network,
+    Volume,
+    Depends,
+}
+
+/// A node in the flowchart (matches frontend ServiceNode type)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct FlowchartNode {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub status: ContainerStatus,
+    pub node_type: NodeType,
+    pub category: ServiceCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub child_flowchart: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub metrics: Option<NodeMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub stats: Option<ContainerStats>,
+    /// Diagnostic warnings rendered as a badge on the node
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The container's latest HTTP health probe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub probe: Option<HealthProbe>,
+    /// Vulnerability counts of the container's image, for a severity badge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub vulnerabilities: Option<VulnerabilitySummary>,
+    /// Suggested canvas position, kept stable across refreshes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub position: Option<NodePosition>,
+}
+
+/// Top-left corner of a node on the canvas, in pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct NodePosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Metrics for a node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct NodeMetrics {
+    pub cpu_percent: Option<f64>,
+    pub memory_mb: Option<u64>,
+    pub uptime_hours: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub image_size_mb: Option<f64>,
+    /// Error lines logged per minute, over the last five minutes; absent
+    /// unless log rates are turned on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub log_errors_per_minute: Option<f64>,
+    /// Warning lines logged per minute, over the last five minutes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub log_warnings_per_minute: Option<f64>,
+}
+
+/// A connection between nodes (matches frontend ServiceConnection type)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct FlowchartConnection {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub label: Option<String>,
+    pub connection_type: ConnectionType,
+}
+
+/// A complete flowchart (matches frontend ServiceFlowchart type)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct Flowchart {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub nodes: Vec<FlowchartNode>,
+    pub connections: Vec<FlowchartConnection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub parent_id: Option<String>,
+}
+
+/// Which way an exported flowchart flows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum FlowDirection {
+    /// Left to right
+    #[default]
+    Lr,
+    /// Top down; `tb` is accepted as an alias
+    #[serde(alias = "tb")]
+    Td,
+}
+
+/// Formats `GET /api/flowchart/:id/export` can produce
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum FlowchartFormat {
+    #[default]
+    Mermaid,
+    /// Graphviz; `gv` is accepted as an alias
+    #[serde(alias = "gv")]
+    Dot,
+}
+
+impl FlowchartFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            FlowchartFormat::Mermaid => "text/vnd.mermaid; charset=utf-8",
+            FlowchartFormat::Dot => "text/vnd.graphviz; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FlowchartFormat::Mermaid => "mmd",
+            FlowchartFormat::Dot => "dot",
+        }
+    }
+}
+
+// =============================================================================
+// API RESPONSE MODELS
+// =============================================================================
+
+/// System topology overview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SystemTopology {
+    pub total_containers: usize,
+    pub running_containers: usize,
+    pub healthy_containers: usize,
+    pub unhealthy_containers: usize,
+    pub categories: HashMap<String, usize>,
+    pub flowcharts: Vec<FlowchartSummary>,
+    pub generated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub host: Option<HostResources>,
+}
+
+/// A NUMA node and its cores
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct NumaNode {
+    pub node: u32,
+    pub cores: Vec<u32>,
+}
+
+/// Which pinned containers may run on one core
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CoreAssignment {
+    pub core: u32,
+    pub numa_node: u32,
+    /// Running containers pinned to this core
+    pub containers: Vec<String>,
+    /// More than one pinned container competes for this core
+    pub contended: bool,
+}
+
+/// A running container with a cpuset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedContainer {
+    pub container: String,
+    pub cores: Vec<u32>,
+    pub numa_nodes: Vec<u32>,
+    /// Pinned across NUMA nodes, so some memory accesses are remote
+    pub spans_numa_nodes: bool,
+    pub cpu_weight: u64,
+}
+
+/// Host-level view of CPU pinning: `GET /api/host/cpus`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CpuMap {
+    pub cpus: u64,
+    pub numa_nodes: Vec<NumaNode>,
+    pub cores: Vec<CoreAssignment>,
+    pub pinned: Vec<PinnedContainer>,
+    /// Running containers without a cpuset, free to run on any core
+    pub floating: Vec<String>,
+    pub contended_cores: usize,
+}
+
+/// Response of `GET /api/topology/wait`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyChange {
+    /// Current topology version; pass it back as `version` to wait for the next change
+    pub version: u64,
+    /// Whether the version moved past the one asked about before the timeout
+    pub changed: bool,
+    /// The new topology, when it changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub topology: Option<SystemTopology>,
+}
+
+/// Host capacity and how much of it is committed by container limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct HostResources {
+    pub cpus: u64,
+    pub load_average_1m: Option<f64>,
+    pub cpu_load_percent: Option<f64>,
+    /// Sum of CPU limits across running containers
+    pub cpu_committed: f64,
+    pub cpu_headroom: f64,
+    pub containers_without_cpu_limit: usize,
+    pub memory_total_mb: f64,
+    pub memory_available_mb: Option<f64>,
+    pub memory_used_percent: Option<f64>,
+    /// Sum of memory limits across running containers
+    pub memory_committed_mb: f64,
+    pub memory_committed_percent: f64,
+    /// Negative when limits are overcommitted
+    pub memory_headroom_mb: f64,
+    pub containers_without_memory_limit: usize,
+    pub disk_path: String,
+    pub disk_total_gb: Option<f64>,
+    pub disk_available_gb: Option<f64>,
+    pub disk_used_percent: Option<f64>,
+}
+
+/// Summary of a flowchart for the overview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct FlowchartSummary {
+    pub id: String,
+    pub name: String,
+    pub node_count: usize,
+    pub category: ServiceCategory,
+}
+
+/// Network information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct NetworkInfo {
+    pub id: String,
+    pub name: String,
+    pub driver: String,
+    pub containers: Vec<String>,
+}
+
+/// A named volume and the containers mounting it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct VolumeInfo {
+    pub name: String,
+    pub driver: String,
+    /// Where the volume's data lives on its host
+    pub mountpoint: String,
+    /// `local` or `global`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub scope: Option<String>,
+    pub labels: HashMap<String, String>,
+    /// Names of the containers mounting it, running or not
+    pub containers: Vec<String>,
+}
+
+/// Stats summed across every container running the same image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageUsage {
+    pub image: String,
+    pub container_count: usize,
+    pub running_count: usize,
+    pub cpu_percent: f64,
+    pub memory_usage_mb: f64,
+    pub memory_limit_mb: f64,
+    pub network_rx_mb: f64,
+    pub network_tx_mb: f64,
+    pub pids: u64,
+    /// Names of the containers running the image
+    pub containers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub image_size_mb: Option<f64>,
+    /// Usage over the requested `range`, oldest first; only present when a
+    /// range was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub history: Option<Vec<ImageUsagePoint>>,
+}
+
+/// Step averages of every container of an image, summed
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageUsagePoint {
+    /// Start of the step
+    pub at: DateTime<Utc>,
+    /// Containers sampled in this step
+    pub containers: usize,
+    pub cpu_percent: f64,
+    pub memory_usage_mb: f64,
+    /// Containers without a known rate count as 0
+    pub network_rx_bytes_per_sec: f64,
+    pub network_tx_bytes_per_sec: f64,
+    pub pids: f64,
+}
+
+/// A local image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    pub id: String,
+    /// `repository:tag` references; empty for dangling images
+    pub tags: Vec<String>,
+    /// `repository@sha256:...` references of pulled images
+    pub digests: Vec<String>,
+    /// Untagged, typically left behind when a newer pull took its tag
+    pub dangling: bool,
+    pub size_mb: f64,
+    pub created: DateTime<Utc>,
+    /// Names of the containers created from it, running or not
+    pub containers: Vec<String>,
+}
+
+/// Body of `POST /api/images/pull`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImagePullRequest {
+    /// `repository[:tag]` or `repository@digest`; `latest` when no tag is given
+    pub image: String,
+}
+
+/// One image a service ran, from when FlowScope first saw it running
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageRun {
+    /// Image id (`sha256:...`), which a rollback recreates the container from
+    pub digest: String,
+    /// Reference the container was created with, such as `api:latest`
+    pub image: String,
+    /// Container seen running it
+    pub container: String,
+    pub since: DateTime<Utc>,
+}
+
+/// The images a service ran over time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImageTimeline {
+    /// `<project>/<service>` for compose services, else the container name
+    pub service: String,
+    /// Oldest first; the last run is the current image
+    pub runs: Vec<ImageRun>,
+}
+
+/// Everything discovered in one pass, as written by `flowscope --oneshot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyExport {
+    pub topology: SystemTopology,
+    pub containers: Vec<ContainerInfo>,
+    pub networks: Vec<NetworkInfo>,
+}
+
+// =============================================================================
+// CONTAINER DETAILS & ACTIONS
+// =============================================================================
+
+/// Detailed container information including environment and logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerDetail {
+    #[serde(flatten)]
+    pub info: ContainerInfo,
+    pub environment: Vec<String>,
+    pub command: Option<String>,
+    pub entrypoint: Option<Vec<String>>,
+    pub working_dir: Option<String>,
+    pub volumes: Vec<VolumeMount>,
+    pub health_check: Option<HealthCheckConfig>,
+    /// `linux` or `windows`; a Windows daemon can run either
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub platform: Option<String>,
+    /// CPU pinning and scheduling weight
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub cpu: Option<CpuPlacement>,
+}
+
+/// Where and how heavily a container is scheduled on the host's CPUs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CpuPlacement {
+    /// `--cpuset-cpus` as configured, e.g. `0-3,8`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub cpuset_cpus: Option<String>,
+    /// `--cpuset-mems`: NUMA nodes the container may allocate memory on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub cpuset_mems: Option<String>,
+    /// Cores the container is pinned to; empty when it may run on any
+    pub cores: Vec<u32>,
+    /// Relative weight under contention (`--cpu-shares`), 1024 when unset
+    pub cpu_shares: u64,
+    /// The same weight on the cgroup v2 scale (1-10000, 100 when unset)
+    pub cpu_weight: u64,
+    /// Hard limit in CPUs, from `--cpus` or quota/period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub cpu_limit: Option<f64>,
+}
+
+/// Volume mount information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeMount {
+    pub source: String,
+    pub destination: String,
+    pub mode: String,
+}
+
+/// Health check configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheckConfig {
+    pub test: Vec<String>,
+    pub interval_seconds: u64,
+    pub timeout_seconds: u64,
+    pub retries: u32,
+    pub start_period_seconds: u64,
+}
+
+/// Output stream a log line was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Which output streams to read logs from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum LogStreams {
+    Stdout,
+    Stderr,
+    #[default]
+    Both,
+}
+
+impl LogStreams {
+    pub fn stdout(self) -> bool {
+        self != LogStreams::Stderr
+    }
+
+    pub fn stderr(self) -> bool {
+        self != LogStreams::Stdout
+    }
+}
+
+/// One log line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// When the daemon received the line; `None` if it sent no timestamp
+    pub ts: Option<DateTime<Utc>>,
+    pub stream: LogStream,
+    pub line: String,
+    /// The object the line holds, when JSON parsing was asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub json: Option<serde_json::Value>,
+}
+
+/// Container logs response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerLogs {
+    pub container_id: String,
+    pub container_name: String,
+    pub logs: Vec<LogEntry>,
+    pub tail: usize,
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub streams: LogStreams,
+}
+
+/// One indexed log line matching a search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct LogSearchHit {
+    pub container: String,
+    /// When the line was indexed
+    pub ts: DateTime<Utc>,
+    pub line: String,
+}
+
+/// Log search response, newest lines first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct LogSearchResult {
+    pub query: String,
+    /// Start of the searched range
+    pub since: DateTime<Utc>,
+    pub hits: Vec<LogSearchHit>,
+    /// More lines matched than `limit`
+    pub truncated: bool,
+    /// Lines currently in the index
+    pub indexed_lines: usize,
+}
+
+/// Desired configuration for a container created through FlowScope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerSpec {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub image: String,
+    /// `KEY=value` pairs, as Docker expects them
+    #[serde(default)]
+    pub env: Vec<String>,
+    #[serde(default)]
+    pub ports: Vec<PortSpec>,
+    #[serde(default)]
+    pub networks: Vec<String>,
+    /// `source:destination[:mode]` entries; named volumes or absolute host paths
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+/// Port to expose on a container created through FlowScope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PortSpec {
+    pub container_port: u16,
+    #[serde(default)]
+    pub host_port: Option<u16>,
+    #[serde(default = "default_protocol")]
+    pub protocol: String,
+}
+
+fn default_protocol() -> String {
+    "tcp".to_string()
+}
+
+/// Outcome of a single pre-flight check
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum PreflightStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightCheck {
+    pub check: String,
+    pub status: PreflightStatus,
+    pub message: String,
+}
+
+/// Result of validating a `ContainerSpec`; `ok` is false when any check failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    pub ok: bool,
+    pub checks: Vec<PreflightCheck>,
+}
+
+/// Container action result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ActionResult {
+    pub success: bool,
+    pub container_id: String,
+    pub container_name: String,
+    pub action: String,
+    pub message: String,
+}
+
+// =============================================================================
+// DIAGNOSTICS
+// =============================================================================
+
+/// A process row from Docker's top API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessEntry {
+    pub pid: u64,
+    pub ppid: Option<u64>,
+    pub state: String,
+    pub command: String,
+    /// Listed only when the top call asked for these columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub cpu_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub memory_kb: Option<u64>,
+    /// Running time as `ps` prints it, such as `02:13:07`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub elapsed: Option<String>,
+}
+
+/// What is running inside a container, as listed by Docker's top API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerProcesses {
+    pub container_id: String,
+    pub container_name: String,
+    /// Empty unless the container is running
+    pub processes: Vec<ProcessEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+/// How a container's writable layer differs from its image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerChanges {
+    pub container_id: String,
+    pub container_name: String,
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    /// Sorted by path; directories holding a change are listed as modified
+    pub changes: Vec<FileChange>,
+}
+
+/// Process health of a single container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessDiagnostics {
+    pub container_id: String,
+    pub container_name: String,
+    pub process_count: u64,
+    pub zombie_count: usize,
+    pub zombies: Vec<ProcessEntry>,
+    pub pids_limit: Option<u64>,
+    pub pid_usage_percent: Option<f64>,
+    pub warnings: Vec<String>,
+}
+
+/// Severity of a diagnostic finding, ordered from least to most severe
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A single misconfiguration flagged by a diagnostics pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticFinding {
+    pub container_id: String,
+    pub container_name: String,
+    pub check: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub suggestion: Option<String>,
+}
+
+/// Combined diagnostics across all containers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub containers_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    pub findings: Vec<DiagnosticFinding>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// A host kernel parameter that affects containers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct HostParameter {
+    /// sysctl name, e.g. `net.core.somaxconn`
+    pub name: String,
+    /// Current value; absent when the host does not expose it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub value: Option<String>,
+    /// Value below which it gets flagged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub recommended: Option<String>,
+}
+
+/// A host parameter likely to cause container failures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct HostFinding {
+    pub parameter: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub suggestion: Option<String>,
+}
+
+/// Host kernel and network parameters: `GET /api/diagnostics/host`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct HostDiagnostics {
+    pub parameters: Vec<HostParameter>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub findings: Vec<HostFinding>,
+    pub generated_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// CHECKS
+// =============================================================================
+
+/// A topology assertion evaluated by `flowscope check`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "rule", rename_all = "kebab-case")]
+pub enum CheckRule {
+    /// Every container is running, and healthy where it has a healthcheck
+    AllHealthy {
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    /// No container runs an untagged or `latest` image
+    NoLatestTags {
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    /// Each service has a running container; a trailing `*` matches a name prefix
+    RequiredServices { services: Vec<String> },
+    /// No diagnostic findings at or above `severity`
+    Diagnostics {
+        #[serde(default = "default_check_severity")]
+        severity: Severity,
+    },
+}
+
+fn default_check_severity() -> Severity {
+    Severity::Error
+}
+
+impl CheckRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheckRule::AllHealthy { .. } => "all-healthy",
+            CheckRule::NoLatestTags { .. } => "no-latest-tags",
+            CheckRule::RequiredServices { .. } => "required-services",
+            CheckRule::Diagnostics { .. } => "diagnostics",
+        }
+    }
+}
+
+/// Contents of a rules file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CheckRules {
+    pub rules: Vec<CheckRule>,
+}
+
+/// Result of evaluating one rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct RuleOutcome {
+    pub rule: String,
+    pub passed: bool,
+    pub violations: Vec<String>,
+}
+
+/// Machine-readable result of `flowscope check`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub passed: bool,
+    pub results: Vec<RuleOutcome>,
+    pub checked_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// TEMPLATES
+// =============================================================================
+
+/// A variable a template expects to be filled in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A container spec with `${VAR}` placeholders and named value profiles
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// A `ContainerSpec` whose string values may contain `${VAR}` placeholders
+    pub spec: serde_json::Value,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    /// Profile name -> variable values
+    #[serde(default)]
+    pub profiles: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `POST /api/templates/:name/instantiate`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct InstantiateRequest {
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct InstantiateResponse {
+    pub template: String,
+    pub profile: Option<String>,
+    pub variables: BTreeMap<String, String>,
+    pub spec: ContainerSpec,
+    pub preflight: PreflightReport,
+}
+
+// =============================================================================
+// GROUPS
+// =============================================================================
+
+/// A user-defined set of containers, selected by name and/or labels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerGroup {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Container names that always belong to the group
+    #[serde(default)]
+    pub containers: Vec<String>,
+    /// Label selectors that must all match; a value of `*` only requires the key
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ContainerGroup {
+    /// Whether a container is listed explicitly or matches every label selector
+    pub fn matches(&self, container: &ContainerInfo) -> bool {
+        if self.containers.iter().any(|n| n == &container.name) {
+            return true;
+        }
+        !self.labels.is_empty()
+            && self.labels.iter().all(|(key, value)| {
+                container
+                    .labels
+                    .get(key)
+                    .is_some_and(|actual| value == "*" || actual == value)
+            })
+    }
+}
+
+/// Stats summed across a group's running members
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct GroupStats {
+    pub group: String,
+    pub container_count: usize,
+    pub running_count: usize,
+    pub cpu_percent: f64,
+    pub memory_usage_mb: f64,
+    pub memory_limit_mb: f64,
+    pub network_rx_mb: f64,
+    pub network_tx_mb: f64,
+    /// Summed over the members whose rates are known
+    pub network_rx_bytes_per_sec: f64,
+    pub network_tx_bytes_per_sec: f64,
+    pub block_read_bytes_per_sec: f64,
+    pub block_write_bytes_per_sec: f64,
+    pub pids: u64,
+    /// Members with their individual stats
+    pub containers: Vec<ContainerInfo>,
+    pub generated_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// DASHBOARDS
+// =============================================================================
+
+/// The containers a dashboard widget shows
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum WidgetTarget {
+    Container { name: String },
+    Group { name: String },
+    Category { category: ServiceCategory },
+}
+
+/// What a dashboard widget shows
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum WidgetKind {
+    /// One stat of every targeted container over the recorded history
+    MetricChart {
+        metric: AlertMetric,
+        #[serde(default = "default_chart_minutes")]
+        minutes: u32,
+    },
+    /// The last lines of each targeted container's logs
+    LogPanel {
+        #[serde(default = "default_log_tail")]
+        tail: usize,
+    },
+    /// The target's flowchart
+    Flowchart,
+    /// The ports the targeted containers publish on the host
+    EndpointList,
+}
+
+fn default_chart_minutes() -> u32 {
+    60
+}
+
+fn default_log_tail() -> usize {
+    50
+}
+
+/// Where a widget sits on the dashboard grid
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetLayout {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardWidget {
+    /// Unique within the dashboard; the widget's data is returned under it
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(flatten)]
+    pub kind: WidgetKind,
+    pub target: WidgetTarget,
+    #[serde(default)]
+    pub layout: Option<WidgetLayout>,
+}
+
+/// A user-composed set of widgets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct Dashboard {
+    /// Assigned by the server when the dashboard is created
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub widgets: Vec<DashboardWidget>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One recorded value of a stat
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct MetricPoint {
+    pub at: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// A stat of one container, oldest point first
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct MetricSeries {
+    pub container: String,
+    pub points: Vec<MetricPoint>,
+}
+
+/// A container's stats averaged over one step of a stats history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct StatsPoint {
+    /// Start of the step
+    pub at: DateTime<Utc>,
+    /// Samples averaged into this point
+    pub samples: usize,
+    pub cpu_percent: f64,
+    pub cpu_percent_max: f64,
+    pub memory_usage_mb: f64,
+    pub memory_usage_mb_max: f64,
+    pub memory_percent: f64,
+    /// Averages of the samples that had a rate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network_rx_bytes_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network_tx_bytes_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub block_read_bytes_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub block_write_bytes_per_sec: Option<f64>,
+    pub pids: f64,
+}
+
+/// Response of `GET /api/container/:id/stats/history`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct StatsHistory {
+    pub container: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub step_secs: u64,
+    /// Oldest first; steps without samples, such as while the container was
+    /// stopped, have no point
+    pub points: Vec<StatsPoint>,
+}
+
+/// A container port published on the host
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedEndpoint {
+    /// `<container>:<hostPort>`, as taken by `POST /api/endpoints/:id/test`
+    pub endpoint: String,
+    pub container: String,
+    pub container_port: u16,
+    pub host_port: u16,
+    pub protocol: String,
+}
+
+/// The data a widget shows, by widget type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum WidgetPayload {
+    MetricChart { metric: AlertMetric, series: Vec<MetricSeries> },
+    LogPanel { logs: Vec<ContainerLogs> },
+    Flowchart { flowchart: Flowchart },
+    EndpointList { endpoints: Vec<PublishedEndpoint> },
+}
+
+/// One widget's data, or why it could not be resolved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetData {
+    pub widget: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub data: Option<WidgetPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub error: Option<String>,
+}
+
+/// Every widget of a dashboard resolved at once, in widget order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardData {
+    pub dashboard: String,
+    pub widgets: Vec<WidgetData>,
+    pub resolved_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// CONFIG BUNDLES
+// =============================================================================
+
+/// Records users author through the API (templates, groups, dashboards and
+/// notification channels) exported as a signed YAML document, so they can be
+/// versioned in Git and promoted between FlowScope instances. Alert and
+/// category rules live in configuration files and are not included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    #[serde(default)]
+    pub templates: Vec<ContainerTemplate>,
+    #[serde(default)]
+    pub groups: Vec<ContainerGroup>,
+    #[serde(default)]
+    pub dashboards: Vec<Dashboard>,
+    #[serde(default)]
+    pub notifications: Vec<NotificationChannel>,
+    /// Base64url HMAC-SHA256 over the bundle's JSON form with this field empty
+    #[serde(default)]
+    pub signature: String,
+}
+
+/// How an imported bundle is applied
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Create or overwrite records from the bundle, keep everything else
+    #[default]
+    Merge,
+    /// Make each section match the bundle exactly, removing records it lacks
+    Replace,
+}
+
+/// Records touched in one section of an import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportChanges {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub mode: ImportMode,
+    /// When true nothing was written
+    pub dry_run: bool,
+    pub templates: ImportChanges,
+    pub groups: ImportChanges,
+    /// Keyed by dashboard id
+    pub dashboards: ImportChanges,
+    pub notifications: ImportChanges,
+}
+
+// =============================================================================
+// EMBED
+// =============================================================================
+
+/// Body of `POST /api/embed/tokens`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedTokenRequest {
+    /// Flowchart ids the token may render; `*` allows any
+    pub scope: Vec<String>,
+    #[serde(default)]
+    pub ttl_seconds: Option<i64>,
+}
+
+/// A signed embed token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedToken {
+    pub token: String,
+    pub scope: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// SYSTEM
+// =============================================================================
+
+/// Isolation the daemon reports in `docker info` `SecurityOptions`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityProfile {
+    /// The daemon runs as an unprivileged user (rootlesskit)
+    pub rootless: bool,
+    pub selinux: bool,
+    pub apparmor: bool,
+    /// Seccomp profile name, usually `builtin`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub seccomp: Option<String>,
+    /// User namespace remapping (`userns-remap`)
+    pub userns: bool,
+}
+
+/// FlowScope features that depend on how the daemon is run, so clients can
+/// hide what will not work
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlags {
+    /// Memory, CPU and PID limits are enforced; rootless daemons on cgroup v1 cannot set them
+    pub resource_limits: bool,
+    /// Ports below 1024 can be published; rootless daemons cannot by default
+    pub privileged_ports: bool,
+    /// Bind mounts are readable without an SELinux relabel (`:z` or `:Z`)
+    pub unlabelled_bind_mounts: bool,
+}
+
+/// Response of `GET /api/system/info`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    pub server_version: Option<String>,
+    pub operating_system: Option<String>,
+    /// `linux` or `windows`
+    pub os_type: Option<String>,
+    pub kernel_version: Option<String>,
+    pub architecture: Option<String>,
+    /// `1` or `2`
+    pub cgroup_version: Option<String>,
+    /// `cgroupfs`, `systemd` or `none`
+    pub cgroup_driver: Option<String>,
+    pub security: SecurityProfile,
+    pub features: FeatureFlags,
+}
+
+// =============================================================================
+// PROJECTS
+// =============================================================================
+
+/// A Docker Compose project, discovered from `com.docker.compose.*` labels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeProject {
+    pub name: String,
+    /// Services with their `depends_on` dependencies first
+    pub services: Vec<String>,
+    /// Member container names, in the order `up` starts them
+    pub containers: Vec<String>,
+    pub running: usize,
+}
+
+/// A Swarm service with the tasks FlowScope can see
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SwarmService {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    /// `replicated`, `global` or the job modes; empty when the service is
+    /// only known from its tasks
+    pub mode: String,
+    /// Replica count of a replicated service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub desired_replicas: Option<u64>,
+    pub running_tasks: usize,
+    /// `com.docker.stack.namespace` of services deployed with `docker stack`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub stack: Option<String>,
+    /// Ports published through the routing mesh
+    pub ports: Vec<PortMapping>,
+    pub tasks: Vec<SwarmTask>,
+}
+
+/// A Swarm task, read from the labels of the container running it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SwarmTask {
+    pub id: String,
+    /// `<service>.<slot>.<task id>`, or `<service>.<node id>.<task id>` for
+    /// global services
+    pub name: String,
+    pub service: String,
+    pub service_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub slot: Option<u64>,
+    pub node_id: String,
+    /// Docker host the container was listed on, when watching several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub host: Option<String>,
+    pub container_id: String,
+    pub container_name: String,
+    pub status: ContainerStatus,
+}
+
+/// A value that differs between the project's `.env` file and a container.
+/// Values are never returned, only `sha256:` fingerprints of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EnvDrift {
+    pub variable: String,
+    pub container: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEnvDrift {
+    pub service: String,
+    pub containers: Vec<String>,
+    /// Whether any replica runs with a variable the `.env` file declares;
+    /// `unset` and `extra` are only reported for services that do
+    pub uses_env_file: bool,
+    /// Declared in `.env` but missing from a replica
+    pub unset: Vec<String>,
+    /// Set on a replica but not declared in `.env`
+    pub extra: Vec<String>,
+    pub drifted: Vec<EnvDrift>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EnvDriftReport {
+    pub project: String,
+    pub uploaded_at: DateTime<Utc>,
+    /// Variables the `.env` file declares
+    pub declared: Vec<String>,
+    pub services: Vec<ServiceEnvDrift>,
+    /// True when no service has unset or drifted variables
+    pub ok: bool,
+}
+
+// =============================================================================
+// JOBS
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A long-running operation started by the API and polled for progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    /// What the job does, e.g. `project-up`
+    pub kind: String,
+    /// What it acts on, e.g. a project name
+    pub target: String,
+    pub status: JobStatus,
+    /// Steps planned; progress is `results.length` of this
+    pub total_steps: usize,
+    pub results: Vec<ActionResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Ids of the artifacts the job produced, downloadable at `/api/artifacts/:id`
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+// =============================================================================
+// ARTIFACTS
+// =============================================================================
+
+/// What an artifact holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    Pcap,
+    Logs,
+    Report,
+    Backup,
+}
+
+/// A file produced by a job and kept for download until it expires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    pub id: String,
+    pub kind: ArtifactKind,
+    /// Name offered to the browser when downloading
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    /// Hex SHA-256 of the content, also sent as the download's ETag
+    pub sha256: String,
+    /// Container the artifact was produced from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub container: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub job_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// SOCKETS
+// =============================================================================
+
+/// How a container's sockets were read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum SocketSource {
+    /// `ss` run inside the container
+    Exec,
+    /// The container's network namespace tables under the host's `/proc`
+    Procfs,
+}
+
+/// A TCP port a container accepts connections on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ListeningPort {
+    pub port: u16,
+    /// Bound address; `0.0.0.0` or `::` for every interface
+    pub address: String,
+    /// Whether the image exposes the port or it is published
+    pub declared: bool,
+}
+
+/// An outbound TCP connection a container holds open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SocketPeer {
+    pub address: String,
+    pub port: u16,
+    /// Container the address belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub container: Option<String>,
+}
+
+/// What a container actually listens on and connects to, as opposed to
+/// what its image declares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerSockets {
+    pub container: String,
+    pub source: SocketSource,
+    pub listening: Vec<ListeningPort>,
+    pub connections: Vec<SocketPeer>,
+}
+
+// =============================================================================
+// EVENTS
+// =============================================================================
+
+/// The object a Docker event is about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EventActor {
+    /// Container, image, network or volume id
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub name: Option<String>,
+}
+
+/// The FlowScope API request an event most likely resulted from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EventOrigin {
+    pub method: String,
+    pub path: String,
+    /// Name of the API token that made the request, when auth is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub token: Option<String>,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// One event from the daemon's event stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DockerEvent {
+    pub time: DateTime<Utc>,
+    /// `container`, `image`, `network`, `volume`, `daemon`, ...
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// `start`, `die`, `kill`, `health_status`, `exec_start`, ...
+    pub action: String,
+    /// What followed the action's colon, such as the new health status or
+    /// the command an exec ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub detail: Option<String>,
+    pub actor: EventActor,
+    /// Labels and event-specific attributes such as `exitCode` or `signal`
+    pub attributes: BTreeMap<String, String>,
+    /// Set when the event followed a FlowScope action; absent for changes
+    /// made outside FlowScope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub origin: Option<EventOrigin>,
+}
+
+// =============================================================================
+// ALERTS
+// =============================================================================
+
+/// A container stat an alert rule can watch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    CpuPercent,
+    MemoryPercent,
+    MemoryUsageMb,
+    Pids,
+    NetworkRxErrors,
+    NetworkTxErrors,
+    /// Packets dropped per second; reads 0 until a rate is known, so a
+    /// rising drop rate fires rather than a lifetime total
+    NetworkRxDropped,
+    NetworkTxDropped,
+    /// Reads 0 when descriptor usage is unknown
+    FdPercent,
+    /// Reads 0 when inotify usage is unknown
+    InotifyWatchPercent,
+}
+
+impl AlertMetric {
+    pub fn value(self, stats: &ContainerStats) -> f64 {
+        match self {
+            AlertMetric::CpuPercent => stats.cpu_percent,
+            AlertMetric::MemoryPercent => stats.memory_percent,
+            AlertMetric::MemoryUsageMb => stats.memory_usage_mb,
+            AlertMetric::Pids => stats.pids as f64,
+            AlertMetric::NetworkRxErrors => stats.network_rx_errors as f64,
+            AlertMetric::NetworkTxErrors => stats.network_tx_errors as f64,
+            AlertMetric::NetworkRxDropped => stats.network_rx_dropped_per_sec.unwrap_or(0.0),
+            AlertMetric::NetworkTxDropped => stats.network_tx_dropped_per_sec.unwrap_or(0.0),
+            AlertMetric::FdPercent => stats.fd_percent.unwrap_or(0.0),
+            AlertMetric::InotifyWatchPercent => stats.inotify_watch_percent.unwrap_or(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum AlertOperator {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl AlertOperator {
+    pub fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            AlertOperator::Gt => value > threshold,
+            AlertOperator::Gte => value >= threshold,
+            AlertOperator::Lt => value < threshold,
+            AlertOperator::Lte => value <= threshold,
+        }
+    }
+}
+
+/// `metric operator threshold` held for `forSeconds`, e.g. memory_percent > 90 for 300s
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: AlertMetric,
+    pub operator: AlertOperator,
+    pub threshold: f64,
+    /// How long the condition must hold before the rule fires; 0 fires on the first sample
+    #[serde(default)]
+    pub for_seconds: u64,
+    /// Container names the rule applies to; a trailing `*` matches a prefix,
+    /// and an empty list means every container
+    #[serde(default)]
+    pub containers: Vec<String>,
+    /// Names of [`ContainerGroup`]s; when set the rule applies only to their
+    /// members
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Container selector such as `category=aiml and label team=core`; the
+    /// rule applies to current containers matching it, `containers` and `groups`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub selector: Option<String>,
+}
+
+/// Body of `POST /api/alerts/rules/test`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleTest {
+    pub rule: AlertRule,
+    /// How far back to evaluate; defaults to the whole retained history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub hours: Option<u32>,
+}
+
+/// A period during which a rule would have been firing for one container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct AlertFiring {
+    pub container: String,
+    pub fired_at: DateTime<Utc>,
+    /// When the condition stopped holding; absent while it still holds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Most extreme value seen while firing
+    pub peak: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleTestReport {
+    pub rule: AlertRule,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub containers_evaluated: usize,
+    pub samples_evaluated: usize,
+    pub firings: Vec<AlertFiring>,
+}
+
+/// What a configured alert rule watches
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AlertCondition {
+    /// A stat compared with a threshold
+    Stat {
+        metric: AlertMetric,
+        operator: AlertOperator,
+        threshold: f64,
+    },
+    /// The container having a status
+    Status { status: ContainerStatus },
+}
+
+/// An alert rule from the `alerts` section of the configuration file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ConfiguredAlertRule {
+    pub name: String,
+    /// The condition as written, such as `memory_percent > 90 for 5m`
+    pub when: String,
+    pub condition: AlertCondition,
+    pub for_seconds: u64,
+    /// How urgent the alert is, for routing notifications; `warning` unless set
+    pub severity: Severity,
+    /// Container names the rule applies to, as in [`AlertRule`]
+    pub containers: Vec<String>,
+    /// Groups whose members the rule applies to, as in [`AlertRule`]
+    pub groups: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub selector: Option<String>,
+}
+
+/// A configured rule firing, or having fired, for one container
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub rule: String,
+    pub when: String,
+    pub container: String,
+    pub fired_at: DateTime<Utc>,
+    /// Absent while the alert is firing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Most extreme value seen while firing; absent for status rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub peak: Option<f64>,
+}
+
+/// Response of `GET /api/alerts`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct AlertList {
+    pub rules: Vec<ConfiguredAlertRule>,
+    /// Oldest first
+    pub firing: Vec<Alert>,
+    /// Recently resolved, newest first
+    pub resolved: Vec<Alert>,
+}
+
+// =============================================================================
+// NOTIFICATIONS
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Webhook,
+    Slack,
+    Discord,
+    Email,
+}
+
+/// Where notifications are sent, and how their payload is shaped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationChannel {
+    pub name: String,
+    pub kind: NotificationKind,
+    /// Webhook URL, or the recipient address for email
+    pub target: String,
+    /// Handlebars template for the payload, referencing `event`, `alert` and
+    /// `container` fields; the kind's default payload when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub template: Option<String>,
+    /// Only notify about containers in these categories; every category when empty
+    #[serde(default)]
+    pub categories: Vec<ServiceCategory>,
+    /// Only notify about events at least this severe; every event when absent.
+    /// Container transitions are errors, alerts carry their rule's severity
+    /// and resolved alerts are info.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub min_severity: Option<Severity>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A payload rendered for a channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedNotification {
+    pub channel: String,
+    /// `application/json` for webhooks, Slack and Discord, `text/plain` for email
+    pub content_type: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryState {
+    /// Being sent or waiting to be retried
+    Pending,
+    Delivered,
+    /// Every attempt failed, or the receiver refused the payload
+    Failed,
+}
+
+/// One notification sent, or being sent, to one channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationDelivery {
+    pub id: String,
+    pub channel: String,
+    /// e.g. `alert.firing` or `container.unhealthy`
+    pub event: String,
+    pub summary: String,
+    pub state: DeliveryState,
+    pub attempts: u32,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_error: Option<String>,
+}
+
+/// Delivery totals of one channel since the server started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelDeliveryStatus {
+    pub channel: String,
+    pub kind: NotificationKind,
+    /// The sink that sends to this channel; absent when none handles its kind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub sink: Option<String>,
+    pub delivered: u64,
+    pub failed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_delivered_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_failed_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_error: Option<String>,
+}
+
+/// Response of `GET /api/notifications/status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationStatus {
+    pub channels: Vec<ChannelDeliveryStatus>,
+    /// Latest deliveries, newest first
+    pub recent: Vec<NotificationDelivery>,
+}
+
+// =============================================================================
+// AUTH
+// =============================================================================
+
+/// Permission an API token can hold; `admin` includes every other scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum ApiScope {
+    #[serde(rename = "containers:read")]
+    ContainersRead,
+    #[serde(rename = "containers:actions")]
+    ContainersActions,
+    #[serde(rename = "logs:read")]
+    LogsRead,
+    #[serde(rename = "exec")]
+    Exec,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 5] = [
+        ApiScope::ContainersRead,
+        ApiScope::ContainersActions,
+        ApiScope::LogsRead,
+        ApiScope::Exec,
+        ApiScope::Admin,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiScope::ContainersRead => "containers:read",
+            ApiScope::ContainersActions => "containers:actions",
+            ApiScope::LogsRead => "logs:read",
+            ApiScope::Exec => "exec",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+/// A named set of scopes a token can be given instead of listing them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ApiRole {
+    /// Reads topology, containers and logs
+    Viewer,
+    /// A viewer that can also start, stop, restart and scale containers
+    Operator,
+    /// Everything, including removing containers
+    Admin,
+}
+
+impl ApiRole {
+    pub fn scopes(self) -> &'static [ApiScope] {
+        match self {
+            ApiRole::Viewer => &[ApiScope::ContainersRead, ApiScope::LogsRead],
+            ApiRole::Operator => &[ApiScope::ContainersRead, ApiScope::LogsRead, ApiScope::ContainersActions],
+            ApiRole::Admin => &[ApiScope::Admin],
+        }
+    }
+}
+
+/// A scope and the routes that require it, as listed at `/api/auth/scopes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeInfo {
+    pub scope: ApiScope,
+    pub description: String,
+    /// `METHOD /path` patterns, `*` matching one path segment
+    pub routes: Vec<String>,
+}
+
+/// Limits applied to a token through its rate class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub requests_per_minute: Option<u32>,
+    /// Limit on requests needing `containers:actions` or `exec`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub actions_per_hour: Option<u32>,
+}
+
+/// Usage of one API token since the server started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub role: Option<ApiRole>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub rate_class: Option<String>,
+    pub limits: RateLimits,
+    pub requests: u64,
+    /// Requests that needed `containers:actions` or `exec`
+    pub actions: u64,
+    /// Requests refused for exceeding a limit
+    pub rejected: u64,
+    pub ws_connections: u64,
+    pub ws_active: u64,
+    /// Time spent connected over WebSocket, counting open connections so far
+    pub ws_seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+// =============================================================================
+// EXEC
+// =============================================================================
+
+/// Body of `POST /api/container/:id/exec`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ExecRequest {
+    /// Program and arguments, run without a shell; use `["sh", "-c", "..."]`
+    /// for pipes and variables
+    pub cmd: Vec<String>,
+    /// Working directory inside the container; the image's when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub working_dir: Option<String>,
+    /// How long to wait for the command; 30 when absent, at most 300
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// Output of a command run inside a container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ExecResult {
+    pub container: String,
+    pub cmd: Vec<String>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Absent when the command timed out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub exit_code: Option<i64>,
+    /// The command outlived its timeout; it may still be running
+    pub timed_out: bool,
+    /// Output beyond the size limit was dropped
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+// =============================================================================
+// DEBUG SIDECARS
+// =============================================================================
+
+/// Body of `POST /api/container/:id/kill`; optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct KillRequest {
+    /// `SIGHUP`, `HUP` or a signal number; `SIGKILL` when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub signal: Option<String>,
+}
+
+/// Body of `POST /api/container/:id/debug-sidecar`; every field is optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DebugSidecarRequest {
+    /// Tools image; the server's configured default when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub ttl_seconds: Option<u64>,
+}
+
+/// A tools container sharing a target's network and PID namespaces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct DebugSidecar {
+    pub id: String,
+    pub name: String,
+    pub target: String,
+    pub image: String,
+    pub created_at: DateTime<Utc>,
+    /// When the sidecar is removed
+    pub expires_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// ONE-OFF RUNS
+// =============================================================================
+
+/// Body of `POST /api/containers/run`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct OneOffRunRequest {
+    pub image: String,
+    /// Command to run; the image's default when empty
+    #[serde(default)]
+    pub cmd: Vec<String>,
+    /// `KEY=value` environment entries
+    #[serde(default)]
+    pub env: Vec<String>,
+    /// Network to join, such as a compose project's default network
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub network: Option<String>,
+    /// Allocate a terminal, like `docker run -t`; output then arrives as a
+    /// single console stream
+    #[serde(default = "default_run_tty")]
+    pub tty: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub ttl_seconds: Option<u64>,
+}
+
+fn default_run_tty() -> bool {
+    true
+}
+
+/// A one-off container, created by `POST /api/containers/run` and started
+/// when a WebSocket client attaches to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct OneOffRun {
+    /// Container id, also the run's id on the WebSocket
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub cmd: Vec<String>,
+    pub network: Option<String>,
+    pub tty: bool,
+    /// Name of the API token that created the run, the only one allowed to
+    /// attach; `None` when auth is disabled
+    pub started_by: Option<String>,
+    pub attached: bool,
+    pub created_at: DateTime<Utc>,
+    /// When the run is removed if no client has attached
+    pub attach_by: DateTime<Utc>,
+    /// When the container is stopped and removed, finished or not
+    pub expires_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// PORT FORWARDS
+// =============================================================================
+
+/// How a port forward admits connections
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardProtocol {
+    /// Browser access: the first request carries the forward's token, which
+    /// is exchanged for a cookie
+    #[default]
+    Http,
+    /// Raw TCP, accepted only from the address that opened the forward
+    Tcp,
+}
+
+/// Body of `POST /api/container/:id/forward`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PortForwardRequest {
+    /// Container port to reach, published or not
+    pub port: u16,
+    #[serde(default)]
+    pub protocol: ForwardProtocol,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub ttl_seconds: Option<u64>,
+}
+
+/// One entry in a port forward's audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardAuditEvent {
+    pub at: DateTime<Utc>,
+    /// `opened`, `authorized`, `connected`, `refused` or `closed`
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub peer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub detail: Option<String>,
+}
+
+/// A temporary proxy from a FlowScope port to a container port
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct PortForward {
+    pub id: String,
+    pub target: String,
+    pub container_port: u16,
+    pub protocol: ForwardProtocol,
+    /// FlowScope port the forward listens on
+    pub listen_port: u16,
+    /// Link that opens an HTTP forward; only returned when it is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub url: Option<String>,
+    /// Secret admitting HTTP clients; only returned when it is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub token: Option<String>,
+    /// Name of the API token that opened the forward
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub opened_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Connections admitted so far
+    pub connections: u64,
+    pub audit: Vec<ForwardAuditEvent>,
+}
+
+// =============================================================================
+// ENDPOINT TESTS
+// =============================================================================
+
+/// Where an endpoint test connects from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeVantage {
+    /// FlowScope itself
+    #[default]
+    Local,
+    /// The configured relay, from outside the host
+    Relay,
+}
+
+/// Body of `POST /api/endpoints/:id/test`; every field is optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointTestRequest {
+    #[serde(default)]
+    pub vantage: ProbeVantage,
+    /// Address a relay test connects to; the server's configured public host
+    /// when absent. Must be a configured relay host unless the caller is an
+    /// admin. Local tests always connect to the server's local test host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub host: Option<String>,
+    /// Whether to attempt a TLS handshake. When absent one is attempted, and
+    /// a port that does not speak TLS is not an error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub tls: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub timeout_ms: Option<u64>,
+}
+
+/// What a relay is asked to connect to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointProbe {
+    pub host: String,
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub tls: Option<bool>,
+    pub timeout_ms: u64,
+}
+
+/// The TLS session a port negotiated
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TlsInfo {
+    /// `TLS 1.2` or `TLS 1.3`
+    pub version: String,
+    pub cipher_suite: String,
+    /// Protocol agreed through ALPN, such as `h2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub alpn: Option<String>,
+    pub handshake_ms: f64,
+    /// Certificates the server presented, its own first
+    pub certificates: u32,
+    /// Whether the chain verifies against the public web roots for the host
+    pub trusted: bool,
+    /// Why the chain did not verify
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub trust_error: Option<String>,
+}
+
+/// What connecting to a port found; also what a relay answers with
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeOutcome {
+    pub reachable: bool,
+    /// Time to complete the TCP handshake
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub connect_ms: Option<f64>,
+    /// Why the port could not be reached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub tls: Option<TlsInfo>,
+    /// Why a requested TLS handshake failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub tls_error: Option<String>,
+}
+
+/// Result of `POST /api/endpoints/:id/test`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointTest {
+    /// `<container>:<hostPort>`
+    pub endpoint: String,
+    pub container: String,
+    pub container_port: u16,
+    pub host: String,
+    pub host_port: u16,
+    pub vantage: ProbeVantage,
+    #[serde(flatten)]
+    pub outcome: ProbeOutcome,
+    pub tested_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// MIGRATIONS
+// =============================================================================
+
+/// Resource usage of one container over a report's window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    pub samples: usize,
+    pub avg_cpu_percent: f64,
+    pub peak_cpu_percent: f64,
+    pub avg_memory_mb: f64,
+    pub peak_memory_mb: f64,
+}
+
+/// One container of a legacy/rust pair as the migration report saw it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationSide {
+    pub container: String,
+    pub status: ContainerStatus,
+    pub health: Option<String>,
+    /// Host port of the first published TCP port, the one probed
+    pub probed_port: Option<u16>,
+    pub probe: Option<ProbeOutcome>,
+    /// `None` when no stats were sampled in the window
+    pub usage: Option<UsageSummary>,
+}
+
+/// One condition a rust container must meet before the cut-over
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessCriterion {
+    /// Stable identifier, such as `rust-reachable`
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Side-by-side comparison of a legacy container and its rust equivalent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub legacy: MigrationSide,
+    pub rust: MigrationSide,
+    /// Minutes of stats history the usage figures cover
+    pub window_minutes: u32,
+    pub criteria: Vec<ReadinessCriterion>,
+    /// Every criterion passed, so the legacy container may be stopped
+    pub ready: bool,
+    pub generated_at: DateTime<Utc>,
+}
+
+// =============================================================================
+// WARM-UP
+// =============================================================================
+
+/// Requests sent to a service after a restart so caches and JITs are primed
+/// before real traffic arrives; stored per service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupProfile {
+    /// Container port the service answers HTTP on
+    pub port: u16,
+    /// Paths to request, such as `/` or `/api/products?page=1`
+    pub paths: Vec<String>,
+    /// GET requests sent to each path
+    #[serde(default = "default_warmup_count")]
+    pub count: u32,
+    /// Requests in flight at once
+    #[serde(default = "default_warmup_concurrency")]
+    pub concurrency: u32,
+    /// How long to wait for the port to accept connections after a restart
+    #[serde(default = "default_warmup_ready_timeout")]
+    pub ready_timeout_secs: u64,
+}
+
+fn default_warmup_count() -> u32 {
+    10
+}
+
+fn default_warmup_concurrency() -> u32 {
+    4
+}
+
+fn default_warmup_ready_timeout() -> u64 {
+    30
+}
+
+/// Outcome of the warm-up requests to one path
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupPathResult {
+    pub path: String,
+    pub requests: u32,
+    /// Requests answered with a 2xx or 3xx status
+    pub succeeded: u32,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    /// Last failure seen, status or connection error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub last_error: Option<String>,
+}
+
+/// Result of warming a container up
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupReport {
+    pub container: String,
+    /// `http://<address>:<port>` the requests went to
+    pub base_url: String,
+    /// Time spent waiting for the port to accept connections
+    pub ready_ms: f64,
+    pub paths: Vec<WarmupPathResult>,
+    pub duration_ms: f64,
+    /// Why warm-up stopped early, such as the port never opening
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub error: Option<String>,
+}
+
+/// Result of a restart that was followed by a warm-up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct WarmedActionResult {
+    #[serde(flatten)]
+    pub result: ActionResult,
+    pub warmup: WarmupReport,
+}
+
+// =============================================================================
+// TOPOLOGY HISTORY
+// =============================================================================
+
+/// A container as a topology snapshot recorded it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotContainer {
+    pub name: String,
+    pub id: String,
+    pub image: String,
+    pub status: ContainerStatus,
+}
+
+/// A dependency between two containers, by name
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEdge {
+    pub source: String,
+    pub target: String,
+    /// `network`, `env`, `depends_on` or `socket`
+    pub kind: String,
+}
+
+/// Containers and their dependencies at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TopologySnapshot {
+    /// `YYYYMMDDTHHMMSSZ` of `taken_at`, so ids sort by time
+    pub id: String,
+    pub taken_at: DateTime<Utc>,
+    /// Sorted by name
+    pub containers: Vec<SnapshotContainer>,
+    pub edges: Vec<SnapshotEdge>,
+}
+
+/// A snapshot as listed by `GET /api/history/snapshots`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSummary {
+    pub id: String,
+    pub taken_at: DateTime<Utc>,
+    pub container_count: usize,
+    pub edge_count: usize,
+}
+
+/// A container in both snapshots that differs between them
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerChange {
+    pub name: String,
+    /// Which of `id`, `image` and `status` changed; a new `id` means the
+    /// container was recreated
+    pub fields: Vec<String>,
+    pub before: SnapshotContainer,
+    pub after: SnapshotContainer,
+}
+
+/// Response of `GET /api/history/diff`: what changed from one snapshot to another
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyDiff {
+    pub from: SnapshotSummary,
+    pub to: SnapshotSummary,
+    pub added: Vec<SnapshotContainer>,
+    pub removed: Vec<SnapshotContainer>,
+    pub changed: Vec<ContainerChange>,
+    pub added_edges: Vec<SnapshotEdge>,
+    pub removed_edges: Vec<SnapshotEdge>,
+}
@@ -0,0 +1,298 @@
+//! TypeScript definitions generated from the models
+//!
+//! Every type that crosses the API boundary is listed in [`definitions`]; a new
+//! model must be added there to show up in the generated file.
+
+use ts_rs::TS;
+
+use crate::models::*;
+use crate::ws::*;
+
+const HEADER: &str = "// Generated from the flowscope-types crate. Do not edit by hand.\n\
+// Regenerate with: cargo run -p flowscope-types --features ts --bin flowscope-ts -- <path>\n";
+
+macro_rules! declarations {
+    ($($ty:ty),* $(,)?) => {
+        vec![$(format!("export {}", <$ty as TS>::decl())),*]
+    };
+}
+
+/// All declarations as a single TypeScript module
+pub fn definitions() -> String {
+    let decls = declarations![
+        serde_json::Value,
+        // Containers
+        ContainerStatus,
+        ContainerStats,
+        ServiceCategory,
+        ContainerInfo,
+        HealthProbe,
+        Scanner,
+        VulnerabilitySeverity,
+        Vulnerability,
+        VulnerabilitySummary,
+        VulnerabilityReport,
+        CategoryStrategyKind,
+        CategoryStep,
+        CategoryExplanation,
+        MembershipKind,
+        GroupMembership,
+        PortMapping,
+        ContainerDetail,
+        VolumeMount,
+        HealthCheckConfig,
+        LogStream,
+        LogStreams,
+        LogEntry,
+        ContainerLogs,
+        LogSearchHit,
+        LogSearchResult,
+        ActionResult,
+        // Flowcharts & topology
+        NodeType,
+        ConnectionType,
+        FlowchartNode,
+        NodePosition,
+        NodeMetrics,
+        FlowchartConnection,
+        Flowchart,
+        FlowchartSummary,
+        FlowDirection,
+        FlowchartFormat,
+        SystemTopology,
+        TopologyChange,
+        HostResources,
+        CpuPlacement,
+        NumaNode,
+        CoreAssignment,
+        PinnedContainer,
+        CpuMap,
+        NetworkInfo,
+        VolumeInfo,
+        ImageUsage,
+        ImageUsagePoint,
+        ImageInfo,
+        ImagePullRequest,
+        ImageRun,
+        ImageTimeline,
+        TopologyExport,
+        // Topology history
+        SnapshotContainer,
+        SnapshotEdge,
+        TopologySnapshot,
+        SnapshotSummary,
+        ContainerChange,
+        TopologyDiff,
+        // Specs & pre-flight
+        ContainerSpec,
+        PortSpec,
+        PreflightStatus,
+        PreflightCheck,
+        PreflightReport,
+        // Diagnostics
+        ProcessEntry,
+        ContainerProcesses,
+        FileChangeKind,
+        FileChange,
+        ContainerChanges,
+        ProcessDiagnostics,
+        Severity,
+        DiagnosticFinding,
+        DiagnosticsReport,
+        HostParameter,
+        HostFinding,
+        HostDiagnostics,
+        // Checks
+        CheckRule,
+        CheckRules,
+        RuleOutcome,
+        CheckReport,
+        // Templates
+        TemplateVariable,
+        ContainerTemplate,
+        InstantiateRequest,
+        InstantiateResponse,
+        // Groups
+        ContainerGroup,
+        GroupStats,
+        // Dashboards
+        WidgetTarget,
+        WidgetKind,
+        WidgetLayout,
+        DashboardWidget,
+        Dashboard,
+        MetricPoint,
+        MetricSeries,
+        StatsPoint,
+        StatsHistory,
+        PublishedEndpoint,
+        WidgetPayload,
+        WidgetData,
+        DashboardData,
+        // Config bundles
+        ConfigBundle,
+        ImportMode,
+        ImportChanges,
+        ImportReport,
+        // Projects & jobs
+        ComposeProject,
+        SwarmService,
+        SwarmTask,
+        EnvDrift,
+        ServiceEnvDrift,
+        EnvDriftReport,
+        JobStatus,
+        Job,
+        ArtifactKind,
+        Artifact,
+        // Sockets
+        SocketSource,
+        ListeningPort,
+        SocketPeer,
+        ContainerSockets,
+        // Events
+        EventActor,
+        EventOrigin,
+        DockerEvent,
+        // Alerts
+        AlertMetric,
+        AlertOperator,
+        AlertRule,
+        AlertRuleTest,
+        AlertFiring,
+        AlertRuleTestReport,
+        AlertCondition,
+        ConfiguredAlertRule,
+        Alert,
+        AlertList,
+        // Notifications
+        NotificationKind,
+        NotificationChannel,
+        RenderedNotification,
+        DeliveryState,
+        NotificationDelivery,
+        ChannelDeliveryStatus,
+        NotificationStatus,
+        // Auth
+        ApiScope,
+        ApiRole,
+        ScopeInfo,
+        RateLimits,
+        TokenUsage,
+        // Exec
+        ExecRequest,
+        ExecResult,
+        // Debug sidecars
+        KillRequest,
+        DebugSidecarRequest,
+        DebugSidecar,
+        // One-off runs
+        OneOffRunRequest,
+        OneOffRun,
+        // Port forwards
+        ForwardProtocol,
+        PortForwardRequest,
+        ForwardAuditEvent,
+        PortForward,
+        // Endpoint tests
+        ProbeVantage,
+        EndpointTestRequest,
+        EndpointProbe,
+        TlsInfo,
+        ProbeOutcome,
+        EndpointTest,
+        // Migrations
+        UsageSummary,
+        MigrationSide,
+        ReadinessCriterion,
+        MigrationReport,
+        // Warm-up
+        WarmupProfile,
+        WarmupPathResult,
+        WarmupReport,
+        WarmedActionResult,
+        // System
+        SecurityProfile,
+        FeatureFlags,
+        SystemInfo,
+        // Embed
+        EmbedTokenRequest,
+        EmbedToken,
+        // WebSocket
+        WsMessage,
+        WsClientMessage,
+        WsAction,
+        RunStream,
+        WsTopic,
+        WsEnvelope,
+        TerminalControl,
+        TerminalMessage,
+        LogStreamMessage,
+    ];
+
+    let body = large_ints_as_numbers(&decls.join("\n\n"));
+    format!("{}\n{}\n", HEADER, body)
+}
+
+/// ts-rs maps 64-bit integers to `bigint`, but serde_json writes them as plain
+/// JSON numbers, which is what the browser actually receives. Only the type
+/// keyword is rewritten; comments, string literals and property names are
+/// copied as they are.
+fn large_ints_as_numbers(decls: &str) -> String {
+    let mut out = String::with_capacity(decls.len());
+    let mut rest = decls;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if c == '"' {
+            string_literal_len(rest)
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            let after = rest[len..].trim_start();
+            if &rest[..len] == "bigint" && !after.starts_with(':') && !after.starts_with("?:") {
+                out.push_str("number");
+                rest = &rest[len..];
+                continue;
+            }
+            len
+        } else {
+            c.len_utf8()
+        };
+        out.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// Length of the double-quoted string literal `rest` starts with, quotes included
+fn string_literal_len(rest: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return i + 1,
+            _ => escaped = false,
+        }
+    }
+    rest.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_bigint_types_become_numbers() {
+        let decl = "/** Size in bytes; not a bigint */\ntype T = { bigint: bigint, kind: \"bigint\" | \"x\\\"bigint\", \
+                    limit?: Array<bigint> | null, };";
+        assert_eq!(
+            large_ints_as_numbers(decl),
+            "/** Size in bytes; not a bigint */\ntype T = { bigint: number, kind: \"bigint\" | \"x\\\"bigint\", \
+             limit?: Array<number> | null, };"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! WebSocket protocol types
+//!
+//! Messages exchanged over `/ws`. Sequenced updates arrive wrapped in a
+//! [`WsEnvelope`]; connection-level messages (welcome, heartbeat, acks, action
+//! results) are sent as bare [`WsMessage`] values.
+//!
+//! Terminals on `/ws/exec/:id` carry raw bytes in binary frames both ways;
+//! text frames hold [`TerminalControl`] and [`TerminalMessage`] values.
+//! `/ws/logs/:id` sends [`LogStreamMessage`] values.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Alert, ContainerInfo};
+
+/// Messages sent from the server to WebSocket clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WsMessage {
+    /// First message on every connection; carries the token to resume with
+    #[serde(rename_all = "camelCase")]
+    Welcome {
+        resume_token: String,
+        resumed: bool,
+        seq: u64,
+    },
+    /// Containers that changed since the previous update. When `full` is set the
+    /// list is the complete container set and replaces any client-side state.
+    #[serde(rename_all = "camelCase")]
+    ContainerUpdate {
+        containers: Vec<ContainerInfo>,
+        removed: Vec<String>,
+        full: bool,
+        timestamp: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    TopologyUpdate {
+        total_containers: usize,
+        running_containers: usize,
+        healthy_containers: usize,
+        unhealthy_containers: usize,
+        timestamp: String,
+    },
+    Heartbeat {
+        timestamp: String,
+    },
+    /// Acknowledges a `setInterval` request with the interval actually applied
+    #[serde(rename_all = "camelCase")]
+    IntervalUpdated {
+        interval_ms: u64,
+        requested_ms: u64,
+    },
+    /// A `watchLogs` request was accepted
+    #[serde(rename_all = "camelCase")]
+    LogWatchStarted {
+        watch_id: String,
+        container: String,
+        pattern: String,
+    },
+    /// A log line matched a watch; sent as soon as it is seen, never replayed
+    #[serde(rename_all = "camelCase")]
+    LogMatch {
+        watch_id: String,
+        container: String,
+        line: String,
+        timestamp: String,
+    },
+    /// A watch stopped, because the client asked or the log stream ended
+    #[serde(rename_all = "camelCase")]
+    LogWatchEnded { watch_id: String, reason: String },
+    /// Outcome of an `action` request, carrying the request's id back
+    #[serde(rename_all = "camelCase")]
+    ActionResult {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        request_id: Option<String>,
+        action: WsAction,
+        container: String,
+        success: bool,
+        message: String,
+    },
+    /// An `attachRun` request started the run; output follows
+    #[serde(rename_all = "camelCase")]
+    RunAttached { run_id: String },
+    /// Output of an attached run, in the order it was written
+    #[serde(rename_all = "camelCase")]
+    RunOutput {
+        run_id: String,
+        stream: RunStream,
+        data: String,
+    },
+    /// An attached run ended and its container was removed. `exit_code` is
+    /// set when the process exited on its own.
+    #[serde(rename_all = "camelCase")]
+    RunExited {
+        run_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        exit_code: Option<i64>,
+        reason: String,
+    },
+    /// A configured alert rule fired for a container, or stopped firing
+    /// when `resolved_at` is set
+    Alert { alert: Alert },
+    /// A client message could not be handled
+    Error {
+        message: String,
+    },
+}
+
+/// Control messages sent by clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WsClientMessage {
+    #[serde(rename_all = "camelCase")]
+    SetInterval { interval_ms: u64 },
+    /// Receive log lines of `container` matching the regex `pattern` for as
+    /// long as this connection stays open
+    WatchLogs { container: String, pattern: String },
+    #[serde(rename_all = "camelCase")]
+    UnwatchLogs { watch_id: String },
+    /// Run a lifecycle action on `container`; the `actionResult` echoes
+    /// `request_id` so several actions can be in flight at once
+    #[serde(rename_all = "camelCase")]
+    Action {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        request_id: Option<String>,
+        action: WsAction,
+        container: String,
+    },
+    /// Start a one-off run created by `POST /api/containers/run` and stream
+    /// its output. The run is stopped and removed when this connection closes.
+    #[serde(rename_all = "camelCase")]
+    AttachRun { run_id: String },
+    /// Write `data` to an attached run's stdin; `eof` closes stdin afterwards
+    #[serde(rename_all = "camelCase")]
+    RunInput {
+        run_id: String,
+        #[serde(default)]
+        data: String,
+        #[serde(default)]
+        eof: bool,
+    },
+}
+
+/// Text frames sent by terminal clients; keystrokes go in binary frames
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TerminalControl {
+    /// The terminal now shows `rows` lines of `cols` characters
+    Resize { rows: u16, cols: u16 },
+}
+
+/// Text frames sent to terminal clients; output comes in binary frames
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TerminalMessage {
+    /// The command ended and the socket is about to close. `exit_code` is set
+    /// when the command exited on its own.
+    #[serde(rename_all = "camelCase")]
+    Exited {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[cfg_attr(feature = "ts", ts(optional))]
+        exit_code: Option<i64>,
+        reason: String,
+    },
+    /// A control message could not be handled
+    Error { message: String },
+}
+
+/// Messages sent on `/ws/logs/:id`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LogStreamMessage {
+    /// Log lines in the order they were written; the first batch holds the
+    /// requested tail
+    Lines { lines: Vec<String> },
+    /// The container stopped; lines follow again if it starts
+    Paused { reason: String },
+    /// The container is running again and its new lines follow
+    Resumed,
+    /// Streaming is over and the socket is about to close
+    Ended { reason: String },
+}
+
+/// Output stream of a one-off run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum RunStream {
+    Stdout,
+    Stderr,
+    /// Terminal output of a run with a TTY, where stdout and stderr are merged
+    Console,
+}
+
+/// Container actions available over the socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum WsAction {
+    Restart,
+    Stop,
+    Start,
+}
+
+impl WsAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WsAction::Restart => "restart",
+            WsAction::Stop => "stop",
+            WsAction::Start => "start",
+        }
+    }
+}
+
+/// Channels a message is published on; each topic has its own replay buffer
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+#[serde(rename_all = "lowercase")]
+pub enum WsTopic {
+    Topology,
+    Containers,
+    Alerts,
+}
+
+impl WsTopic {
+    pub const ALL: [WsTopic; 3] = [WsTopic::Topology, WsTopic::Containers, WsTopic::Alerts];
+
+    /// Snapshot topics carry full state, so a newer message supersedes older ones.
+    /// Other topics carry deltas that must be applied in order.
+    pub fn is_snapshot(self) -> bool {
+        matches!(self, WsTopic::Topology)
+    }
+}
+
+/// A published message with its global sequence number
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct WsEnvelope {
+    pub seq: u64,
+    pub topic: WsTopic,
+    #[serde(flatten)]
+    pub message: WsMessage,
+}
@@ -0,0 +1,630 @@
+//! Alert rules
+//!
+//! A rule is a condition on one container stat that must hold for a while
+//! before it fires, e.g. `memory_percent > 90 for 300s`. Rules are evaluated
+//! against the [stats history](crate::metrics); `POST /api/alerts/rules/test`
+//! replays a candidate rule over it to show when the rule would have fired,
+//! so thresholds can be tuned before anyone gets paged.
+//!
+//! Rules in the `alerts` section of the configuration file are evaluated live
+//! every [`SAMPLE_INTERVAL`](crate::metrics::SAMPLE_INTERVAL) against the
+//! latest stats and the container list. Each is written as a condition such
+//! as `memory_percent > 90 for 5m` or `status == unhealthy`:
+//!
+//! ```yaml
+//! alerts:
+//!   - name: memory
+//!     when: memory_percent > 90 for 5m
+//!     containers: [shop-*]
+//!   - name: unhealthy
+//!     when: status == unhealthy for 1m
+//!     severity: error
+//!     selector: label team=core
+//!   - name: checkout cpu
+//!     when: cpu_percent > 80 for 10m
+//!     groups: [checkout]
+//! ```
+//!
+//! `groups` names [container groups](crate::groups); the rule then only
+//! applies to their current members.
+//!
+//! `severity` is `info`, `warning` (the default) or `error`, and decides which
+//! notification channels hear about the rule.
+//!
+//! A rule firing or resolving is published as a `WsMessage::Alert` on the
+//! `alerts` topic, and `GET /api/alerts` lists the rules, what is firing and
+//! what resolved recently.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use tracing::{error, info, warn};
+
+use crate::{
+    check::matches_pattern,
+    groups::member_of,
+    metrics::{parse_span, MetricSample, RETENTION, SAMPLE_INTERVAL},
+    models::{
+        Alert, AlertCondition, AlertFiring, AlertList, AlertMetric, AlertOperator, AlertRule, AlertRuleTest,
+        AlertRuleTestReport, ConfiguredAlertRule, ContainerGroup, ContainerInfo, ContainerStats, ContainerStatus, Severity,
+    },
+    selector::{self, Selector, SelectorError},
+    websocket::{WsMessage, WsTopic},
+    AppState,
+};
+
+/// Resolved alerts kept for `GET /api/alerts`, oldest dropped first
+pub const MAX_RESOLVED: usize = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlertError {
+    #[error("invalid alert rule: {0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Selector(#[from] SelectorError),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl AlertError {
+    fn status(&self) -> StatusCode {
+        match self {
+            AlertError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for AlertError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Alert error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+pub fn validate(rule: &AlertRule) -> Result<(), AlertError> {
+    if rule.name.trim().is_empty() {
+        return Err(AlertError::Invalid("name must not be empty".to_string()));
+    }
+    if !rule.threshold.is_finite() {
+        return Err(AlertError::Invalid("threshold must be a finite number".to_string()));
+    }
+    Selector::parse(rule.selector.as_deref())?;
+    Ok(())
+}
+
+fn applies_to(rule: &AlertRule, container: &str) -> bool {
+    rule.containers.is_empty() || rule.containers.iter().any(|p| matches_pattern(p, container))
+}
+
+/// Replay `rule` over `samples`, returning how many containers it applied to
+/// and every period it would have been firing, in the order they started
+pub fn evaluate(rule: &AlertRule, samples: &[MetricSample]) -> (usize, Vec<AlertFiring>) {
+    let mut by_container: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in samples.iter().filter(|s| applies_to(rule, &s.container)) {
+        by_container.entry(&sample.container).or_default().push(sample);
+    }
+    // The most extreme value is the highest one for rising conditions, the lowest for falling ones
+    let worse = |a: f64, b: f64| match rule.operator {
+        AlertOperator::Gt | AlertOperator::Gte => a.max(b),
+        AlertOperator::Lt | AlertOperator::Lte => a.min(b),
+    };
+    let hold = chrono::Duration::seconds(rule.for_seconds.min(i64::MAX as u64) as i64);
+
+    let containers = by_container.len();
+    let mut firings = Vec::new();
+    for (container, mut series) in by_container {
+        series.sort_by_key(|s| s.at);
+        let mut pending_since = None;
+        let mut firing: Option<AlertFiring> = None;
+
+        for sample in series {
+            let value = rule.metric.value(&sample.stats);
+            if !rule.operator.holds(value, rule.threshold) {
+                pending_since = None;
+                if let Some(mut done) = firing.take() {
+                    done.resolved_at = Some(sample.at);
+                    firings.push(done);
+                }
+                continue;
+            }
+
+            let since = *pending_since.get_or_insert(sample.at);
+            match &mut firing {
+                Some(active) => active.peak = worse(active.peak, value),
+                None if sample.at - since >= hold => {
+                    firing = Some(AlertFiring {
+                        container: container.to_string(),
+                        fired_at: sample.at,
+                        resolved_at: None,
+                        peak: value,
+                    })
+                }
+                None => {}
+            }
+        }
+        firings.extend(firing);
+    }
+
+    firings.sort_by(|a, b| a.fired_at.cmp(&b.fired_at).then_with(|| a.container.cmp(&b.container)));
+    (containers, firings)
+}
+
+/// POST /api/alerts/rules/test - Show when a candidate rule would have fired
+/// over the last `hours` of recorded stats
+pub async fn test_rule(
+    State(state): State<AppState>,
+    Json(request): Json<AlertRuleTest>,
+) -> Result<Json<AlertRuleTestReport>, AlertError> {
+    validate(&request.rule)?;
+    let to = Utc::now();
+    let from = match request.hours {
+        Some(0) => return Err(AlertError::Invalid("hours must be at least 1".to_string())),
+        Some(hours) => to - chrono::Duration::hours(hours.into()).min(RETENTION),
+        None => to - RETENTION,
+    };
+
+    let mut samples = state.metrics.since(from);
+    let selector = Selector::parse(request.rule.selector.as_deref())?;
+    if selector.is_some() || !request.rule.groups.is_empty() {
+        let groups = request
+            .rule
+            .groups
+            .iter()
+            .map(|name| {
+                state
+                    .groups
+                    .get(name)
+                    .ok_or_else(|| AlertError::Invalid(format!("group '{}' not found", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let selected: HashSet<String> = selector::select(selector.as_ref(), state.docker.list_containers().await?)
+            .into_iter()
+            .filter(|c| member_of(&groups, &request.rule.groups, c))
+            .map(|c| c.name)
+            .collect();
+        samples.retain(|s| selected.contains(&s.container));
+    }
+    let (containers_evaluated, firings) = evaluate(&request.rule, &samples);
+    Ok(Json(AlertRuleTestReport {
+        samples_evaluated: samples.iter().filter(|s| applies_to(&request.rule, &s.container)).count(),
+        rule: request.rule,
+        from,
+        to,
+        containers_evaluated,
+        firings,
+    }))
+}
+
+/// Parse a condition such as `memory_percent > 90 for 5m` or
+/// `status == unhealthy`, returning it and how long it must hold
+pub fn parse_when(when: &str) -> Result<(AlertCondition, u64), AlertError> {
+    let invalid = |why: String| AlertError::Invalid(format!("'{}': {}", when, why));
+    let words: Vec<&str> = when.split_whitespace().collect();
+    let (condition, hold) = match words.as_slice() {
+        [subject, operator, value, "for", span] => ((*subject, *operator, *value), Some(*span)),
+        [subject, operator, value] => ((*subject, *operator, *value), None),
+        _ => return Err(invalid("expected `<stat> <op> <number>` or `status == <status>`, then `for <span>`".to_string())),
+    };
+    let for_seconds = match hold {
+        Some(span) => parse_span(span)
+            .ok_or_else(|| invalid(format!("'{}' is not a span such as 90s or 5m", span)))?
+            .num_seconds() as u64,
+        None => 0,
+    };
+
+    let condition = match condition {
+        ("status", "==", status) => {
+            let status: ContainerStatus = serde_json::from_value(serde_json::Value::String(status.to_string()))
+                .map_err(|_| invalid(format!("'{}' is not a container status", status)))?;
+            AlertCondition::Status { status }
+        }
+        ("status", operator, _) => return Err(invalid(format!("status can only be compared with ==, not {}", operator))),
+        (metric, operator, threshold) => AlertCondition::Stat {
+            metric: serde_json::from_value::<AlertMetric>(serde_json::Value::String(metric.to_string()))
+                .map_err(|_| invalid(format!("'{}' is not a stat alerts can watch", metric)))?,
+            operator: match operator {
+                ">" => AlertOperator::Gt,
+                ">=" => AlertOperator::Gte,
+                "<" => AlertOperator::Lt,
+                "<=" => AlertOperator::Lte,
+                other => return Err(invalid(format!("'{}' is not one of >, >=, < and <=", other))),
+            },
+            threshold: threshold
+                .parse::<f64>()
+                .ok()
+                .filter(|t| t.is_finite())
+                .ok_or_else(|| invalid(format!("'{}' is not a number", threshold)))?,
+        },
+    };
+    Ok((condition, for_seconds))
+}
+
+/// A rule from the configuration file, checked and parsed
+pub fn configured_rule(
+    name: String,
+    when: String,
+    severity: Severity,
+    containers: Vec<String>,
+    groups: Vec<String>,
+    selector: Option<String>,
+) -> Result<ConfiguredAlertRule, AlertError> {
+    if name.trim().is_empty() {
+        return Err(AlertError::Invalid("name must not be empty".to_string()));
+    }
+    let (condition, for_seconds) = parse_when(&when)?;
+    Selector::parse(selector.as_deref())?;
+    Ok(ConfiguredAlertRule {
+        name,
+        when,
+        condition,
+        for_seconds,
+        severity,
+        containers,
+        groups,
+        selector,
+    })
+}
+
+struct LiveRule {
+    rule: ConfiguredAlertRule,
+    selector: Option<Selector>,
+}
+
+impl LiveRule {
+    fn applies_to(&self, container: &ContainerInfo, groups: &[ContainerGroup]) -> bool {
+        (self.rule.containers.is_empty() || self.rule.containers.iter().any(|p| matches_pattern(p, &container.name)))
+            && member_of(groups, &self.rule.groups, container)
+            && self.selector.as_ref().is_none_or(|s| s.matches(container))
+    }
+
+    /// Whether the condition holds, and the stat it read
+    fn holds(&self, container: &ContainerInfo, stats: Option<&ContainerStats>) -> (bool, Option<f64>) {
+        match &self.rule.condition {
+            AlertCondition::Status { status } => (&container.status == status, None),
+            AlertCondition::Stat {
+                metric,
+                operator,
+                threshold,
+            } => match stats {
+                Some(stats) => {
+                    let value = metric.value(stats);
+                    (operator.holds(value, *threshold), Some(value))
+                }
+                // No stats, such as for a stopped container, cannot breach a threshold
+                None => (false, None),
+            },
+        }
+    }
+
+    fn worse(&self, a: f64, b: f64) -> f64 {
+        match &self.rule.condition {
+            AlertCondition::Stat {
+                operator: AlertOperator::Lt | AlertOperator::Lte,
+                ..
+            } => a.min(b),
+            _ => a.max(b),
+        }
+    }
+}
+
+#[derive(Default)]
+struct EngineState {
+    /// When each (rule, container) condition started holding
+    pending: HashMap<(usize, String), DateTime<Utc>>,
+    firing: BTreeMap<(usize, String), Alert>,
+    resolved: VecDeque<Alert>,
+}
+
+/// Live evaluation of the configured alert rules
+pub struct AlertEngine {
+    rules: Vec<LiveRule>,
+    state: Mutex<EngineState>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<ConfiguredAlertRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .map(|rule| LiveRule {
+                // Checked when the configuration was loaded
+                selector: Selector::parse(rule.selector.as_deref()).ok().flatten(),
+                rule,
+            })
+            .collect();
+        Self {
+            rules,
+            state: Mutex::new(EngineState::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluate every rule against `containers` as listed at `at`, with the
+    /// current `groups` and the latest stats by container name; returns the
+    /// alerts that fired or resolved
+    pub fn observe(
+        &self,
+        at: DateTime<Utc>,
+        containers: &[ContainerInfo],
+        groups: &[ContainerGroup],
+        stats: &HashMap<String, ContainerStats>,
+    ) -> Vec<Alert> {
+        let mut state = self.state.lock().unwrap();
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (index, live) in self.rules.iter().enumerate() {
+            let hold = chrono::Duration::seconds(live.rule.for_seconds.min(i64::MAX as u64) as i64);
+            for container in containers.iter().filter(|c| live.applies_to(c, groups)) {
+                let key = (index, container.name.clone());
+                let (holds, value) = live.holds(container, stats.get(&container.name));
+                if !holds {
+                    continue;
+                }
+                seen.insert(key.clone());
+                let since = *state.pending.entry(key.clone()).or_insert(at);
+                match state.firing.get_mut(&key) {
+                    Some(alert) => {
+                        alert.peak = match (alert.peak, value) {
+                            (Some(peak), Some(value)) => Some(live.worse(peak, value)),
+                            (peak, value) => peak.or(value),
+                        }
+                    }
+                    None if at - since >= hold => {
+                        let alert = Alert {
+                            rule: live.rule.name.clone(),
+                            when: live.rule.when.clone(),
+                            container: container.name.clone(),
+                            fired_at: at,
+                            resolved_at: None,
+                            peak: value,
+                        };
+                        changes.push(alert.clone());
+                        state.firing.insert(key, alert);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        // Anything not holding any more, including containers that are gone
+        state.pending.retain(|key, _| seen.contains(key));
+        let stopped: Vec<(usize, String)> = state.firing.keys().filter(|k| !seen.contains(*k)).cloned().collect();
+        for key in stopped {
+            if let Some(mut alert) = state.firing.remove(&key) {
+                alert.resolved_at = Some(at);
+                changes.push(alert.clone());
+                state.resolved.push_front(alert);
+            }
+        }
+        state.resolved.truncate(MAX_RESOLVED);
+        changes
+    }
+
+    pub fn list(&self) -> AlertList {
+        let state = self.state.lock().unwrap();
+        let mut firing: Vec<Alert> = state.firing.values().cloned().collect();
+        firing.sort_by(|a, b| a.fired_at.cmp(&b.fired_at).then_with(|| a.container.cmp(&b.container)));
+        AlertList {
+            rules: self.rules.iter().map(|r| r.rule.clone()).collect(),
+            firing,
+            resolved: state.resolved.iter().cloned().collect(),
+        }
+    }
+}
+
+/// One evaluation round; stats older than two sampling rounds are ignored
+async fn evaluate_live(state: &AppState) -> Result<(), bollard::errors::Error> {
+    let containers = state.docker.list_containers().await?;
+    let now = Utc::now();
+    let fresh = chrono::Duration::from_std(SAMPLE_INTERVAL * 2).unwrap();
+    let stats: HashMap<String, ContainerStats> = state
+        .metrics
+        .latest()
+        .into_iter()
+        .filter(|s| now - s.at <= fresh)
+        .map(|s| (s.container, s.stats))
+        .collect();
+
+    for alert in state.alerts.observe(now, &containers, &state.groups.list(), &stats) {
+        match alert.resolved_at {
+            None => warn!("Alert '{}' fired for {}: {}", alert.rule, alert.container, alert.when),
+            Some(_) => info!("Alert '{}' resolved for {}", alert.rule, alert.container),
+        }
+        state.hub.publish(WsTopic::Alerts, WsMessage::Alert { alert });
+    }
+    Ok(())
+}
+
+/// Spawn the background task that evaluates the configured rules
+pub fn spawn_evaluator(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = evaluate_live(&state).await {
+                warn!("Failed to evaluate alert rules: {}", e);
+            }
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+/// GET /api/alerts - Configured rules, firing alerts and recently resolved ones
+pub async fn list_alerts(State(state): State<AppState>) -> Json<AlertList> {
+    Json(state.alerts.list())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        docker_api::fake,
+        models::{AlertMetric, ContainerStats},
+    };
+    use chrono::{DateTime, TimeZone};
+
+    fn sample(container: &str, minute: i64, memory_percent: f64) -> MetricSample {
+        let start: DateTime<Utc> = Utc.with_ymd_and_hms(2025, 6, 1, 10, 0, 0).unwrap();
+        MetricSample {
+            container: container.to_string(),
+            host: None,
+            image: None,
+            at: start + chrono::Duration::minutes(minute),
+            stats: ContainerStats {
+                memory_percent,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn fires_after_holding_and_resolves() {
+        let rule = AlertRule {
+            name: "memory".to_string(),
+            metric: AlertMetric::MemoryPercent,
+            operator: AlertOperator::Gt,
+            threshold: 90.0,
+            for_seconds: 120,
+            containers: vec!["application-*".to_string()],
+            groups: Vec::new(),
+            selector: None,
+        };
+        let samples = [
+            // A one-minute spike is too short to fire
+            sample("application-api-1", 0, 95.0),
+            sample("application-api-1", 1, 50.0),
+            // Held from minute 2, fires at minute 4, resolves at minute 6
+            sample("application-api-1", 2, 92.0),
+            sample("application-api-1", 3, 97.0),
+            sample("application-api-1", 4, 93.0),
+            sample("application-api-1", 5, 91.0),
+            sample("application-api-1", 6, 40.0),
+            // Still firing at the end of the window
+            sample("application-api-2", 0, 99.0),
+            sample("application-api-2", 3, 99.5),
+            // Not matched by the rule
+            sample("infrastructure-postgres", 0, 100.0),
+        ];
+
+        let (containers, firings) = evaluate(&rule, &samples);
+        assert_eq!(containers, 2);
+        assert_eq!(firings.len(), 2);
+
+        // Ordered by when they fired
+        assert_eq!(firings[0].container, "application-api-2");
+        assert_eq!(firings[0].fired_at, samples[8].at);
+        assert_eq!(firings[0].resolved_at, None);
+
+        assert_eq!(firings[1].container, "application-api-1");
+        assert_eq!(firings[1].fired_at, samples[4].at);
+        assert_eq!(firings[1].resolved_at, Some(samples[6].at));
+        assert_eq!(firings[1].peak, 93.0);
+    }
+
+    fn container(name: &str, status: ContainerStatus) -> ContainerInfo {
+        ContainerInfo { status, ..fake::container(name) }
+    }
+
+    #[test]
+    fn configured_rules_fire_live_and_resolve() {
+        let (condition, for_seconds) = parse_when("memory_percent > 90 for 5m").unwrap();
+        assert_eq!(for_seconds, 300);
+        assert_eq!(
+            condition,
+            AlertCondition::Stat {
+                metric: AlertMetric::MemoryPercent,
+                operator: AlertOperator::Gt,
+                threshold: 90.0
+            }
+        );
+        assert!(parse_when("status != running").is_err());
+        assert!(parse_when("disk_percent > 90").is_err());
+        assert!(parse_when("memory_percent > 90 for ever").is_err());
+
+        // Drops alert on the rate, not on the lifetime total
+        let (drops, _) = parse_when("network_rx_dropped > 5").unwrap();
+        let AlertCondition::Stat { metric, .. } = drops else { panic!("not a stat condition") };
+        let stats = ContainerStats { network_rx_dropped: 10_000, ..Default::default() };
+        assert_eq!(metric.value(&stats), 0.0);
+        let stats = ContainerStats { network_rx_dropped_per_sec: Some(12.5), ..stats };
+        assert_eq!(metric.value(&stats), 12.5);
+
+        let memory = configured_rule("memory".to_string(), "memory_percent > 90 for 1m".to_string(), Severity::Warning, vec![], vec![], None);
+        let unhealthy = configured_rule("unhealthy".to_string(), "status == unhealthy".to_string(), Severity::Error, vec![], vec![], None);
+        let engine = AlertEngine::new(vec![memory.unwrap(), unhealthy.unwrap()]);
+        let start: DateTime<Utc> = Utc.with_ymd_and_hms(2025, 6, 1, 10, 0, 0).unwrap();
+        let at = |seconds: i64| start + chrono::Duration::seconds(seconds);
+        let stats = |memory_percent: f64| {
+            HashMap::from([(
+                "shop-api-1".to_string(),
+                ContainerStats {
+                    memory_percent,
+                    ..Default::default()
+                },
+            )])
+        };
+        let api = |status| vec![container("shop-api-1", status)];
+
+        // Status rules fire at once; stat rules once the condition has held
+        let fired = engine.observe(at(0), &api(ContainerStatus::Unhealthy), &[], &stats(95.0));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].rule, "unhealthy");
+        assert!(engine.observe(at(30), &api(ContainerStatus::Unhealthy), &[], &stats(97.0)).is_empty());
+        let fired = engine.observe(at(60), &api(ContainerStatus::Unhealthy), &[], &stats(93.0));
+        assert_eq!((fired[0].rule.as_str(), fired[0].peak), ("memory", Some(93.0)));
+        engine.observe(at(90), &api(ContainerStatus::Unhealthy), &[], &stats(99.0));
+        assert_eq!(engine.list().firing.len(), 2);
+
+        // Recovering resolves both; a container that goes away would too
+        let resolved = engine.observe(at(120), &api(ContainerStatus::Healthy), &[], &stats(40.0));
+        assert_eq!(resolved.len(), 2);
+        assert!(resolved.iter().all(|a| a.resolved_at == Some(at(120))));
+        let list = engine.list();
+        assert!(list.firing.is_empty());
+        assert_eq!(list.resolved.iter().find(|a| a.rule == "memory").unwrap().peak, Some(99.0));
+        assert_eq!(list.rules.len(), 2);
+    }
+
+    #[test]
+    fn group_rules_only_watch_members() {
+        let rule = configured_rule(
+            "checkout down".to_string(),
+            "status == unhealthy".to_string(),
+            Severity::Error,
+            vec![],
+            vec!["checkout".to_string()],
+            None,
+        );
+        let engine = AlertEngine::new(vec![rule.unwrap()]);
+        let checkout: ContainerGroup = serde_json::from_value(serde_json::json!({
+            "name": "checkout",
+            "containers": ["payments"],
+        }))
+        .unwrap();
+        let containers = [
+            container("payments", ContainerStatus::Unhealthy),
+            container("search", ContainerStatus::Unhealthy),
+        ];
+
+        let fired = engine.observe(Utc::now(), &containers, &[checkout], &HashMap::new());
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].container, "payments");
+
+        // Once the group is gone the rule has no members left
+        let resolved = engine.observe(Utc::now(), &containers, &[], &HashMap::new());
+        assert_eq!(resolved.len(), 1);
+        assert!(resolved[0].resolved_at.is_some());
+    }
+}
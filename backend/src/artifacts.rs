@@ -0,0 +1,462 @@
+//! Artifact store
+//!
+//! Files produced for download (packet captures, log archives, reports,
+//! backups) are kept as artifacts: the content under `<data_dir>/artifacts/<id>`
+//! and the metadata in an index beside it. A job writes one through an
+//! [`ArtifactWriter`] and lists its id on the job; `GET /api/artifacts/:id`
+//! streams it back. Artifacts expire after `FLOWSCOPE_ARTIFACT_RETENTION_HOURS`
+//! (default 72), and the oldest are dropped early once the store holds more
+//! than `FLOWSCOPE_ARTIFACT_MAX_MB` (default 1024).
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{error, info, warn};
+
+use crate::{
+    docker_api::LogWindow,
+    jobs::JobHandle,
+    models::{ActionResult, Artifact, ArtifactKind, ContainerInfo, Job},
+    store::{data_dir, JsonStore, StoreError},
+    AppState,
+};
+
+const MIB: u64 = 1024 * 1024;
+pub const DEFAULT_RETENTION_HOURS: u64 = 72;
+pub const DEFAULT_MAX_MB: u64 = 1024;
+/// How often expired artifacts are looked for
+pub const REAP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Read size when streaming a download
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Most log lines a log export holds
+pub const LOG_EXPORT_TAIL: usize = 100_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArtifactError {
+    #[error("artifact '{0}' not found")]
+    NotFound(String),
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("artifact is larger than the store's {0} byte limit")]
+    TooLarge(u64),
+    #[error("artifact file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl ArtifactError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ArtifactError::NotFound(_) | ArtifactError::ContainerNotFound(_) => StatusCode::NOT_FOUND,
+            ArtifactError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ArtifactError::Io(_) | ArtifactError::Store(_) | ArtifactError::Docker(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+impl IntoResponse for ArtifactError {
+    fn into_response(self) -> Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Artifact error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// What a new artifact is, before its content is written
+#[derive(Debug, Clone)]
+pub struct ArtifactDraft {
+    pub kind: ArtifactKind,
+    pub file_name: String,
+    pub content_type: String,
+    pub container: Option<String>,
+    pub job_id: Option<String>,
+}
+
+pub struct ArtifactStore {
+    dir: PathBuf,
+    index: JsonStore<Artifact>,
+    retention: chrono::Duration,
+    max_bytes: u64,
+}
+
+impl ArtifactStore {
+    /// Open the store in `dir`, discarding content whose writer never finished
+    pub fn open_at(dir: PathBuf, retention: chrono::Duration, max_bytes: u64) -> Self {
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                if entry.path().extension().is_some_and(|ext| ext == "partial") {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        Self {
+            index: JsonStore::open_at(dir.join("index.json")),
+            dir,
+            retention,
+            max_bytes,
+        }
+    }
+
+    /// `<data_dir>/artifacts` with retention and size limits from the environment
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|&v| v > 0)
+                .unwrap_or(default)
+        };
+        Self::open_at(
+            data_dir().join("artifacts"),
+            chrono::Duration::hours(var("FLOWSCOPE_ARTIFACT_RETENTION_HOURS", DEFAULT_RETENTION_HOURS) as i64),
+            var("FLOWSCOPE_ARTIFACT_MAX_MB", DEFAULT_MAX_MB) * MIB,
+        )
+    }
+
+    /// All artifacts, newest first
+    pub fn list(&self) -> Vec<Artifact> {
+        let mut artifacts = self.index.list();
+        artifacts.sort_by_key(|a| std::cmp::Reverse(a.created_at));
+        artifacts
+    }
+
+    pub fn get(&self, id: &str) -> Option<Artifact> {
+        self.index.get(id)
+    }
+
+    /// Start writing a new artifact; it is listed once [`ArtifactWriter::finish`] succeeds
+    pub async fn create(self: &Arc<Self>, draft: ArtifactDraft) -> Result<ArtifactWriter, ArtifactError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let partial = self.dir.join(format!("{}.partial", id));
+        let file = tokio::fs::File::create(&partial).await?;
+        Ok(ArtifactWriter {
+            store: self.clone(),
+            id,
+            draft,
+            partial,
+            file,
+            hasher: Sha256::new(),
+            size: 0,
+            finished: false,
+        })
+    }
+
+    /// Delete an artifact and its content
+    pub fn remove(&self, id: &str) -> Result<Option<Artifact>, ArtifactError> {
+        let Some(artifact) = self.index.remove(id)? else {
+            return Ok(None);
+        };
+        match std::fs::remove_file(self.dir.join(&artifact.id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        Ok(Some(artifact))
+    }
+
+    /// Delete artifacts expired at `now`, then the oldest until the store
+    /// fits its size limit, returning what was deleted
+    pub fn prune(&self, now: DateTime<Utc>) -> Vec<Artifact> {
+        let mut artifacts = self.list();
+        artifacts.reverse();
+        let mut total: u64 = artifacts.iter().map(|a| a.size_bytes).sum();
+
+        let mut removed = Vec::new();
+        for artifact in artifacts {
+            if artifact.expires_at > now && total <= self.max_bytes {
+                continue;
+            }
+            match self.remove(&artifact.id) {
+                Ok(_) => {
+                    total -= artifact.size_bytes;
+                    info!("Removed artifact {} ({})", artifact.id, artifact.file_name);
+                    removed.push(artifact);
+                }
+                Err(e) => warn!("Failed to remove artifact {}: {}", artifact.id, e),
+            }
+        }
+        removed
+    }
+}
+
+/// Content of an artifact being written; dropping it unfinished discards it
+pub struct ArtifactWriter {
+    store: Arc<ArtifactStore>,
+    id: String,
+    draft: ArtifactDraft,
+    partial: PathBuf,
+    file: tokio::fs::File,
+    hasher: Sha256,
+    size: u64,
+    finished: bool,
+}
+
+impl ArtifactWriter {
+    pub async fn write(&mut self, bytes: &[u8]) -> Result<(), ArtifactError> {
+        self.size += bytes.len() as u64;
+        if self.size > self.store.max_bytes {
+            return Err(ArtifactError::TooLarge(self.store.max_bytes));
+        }
+        self.hasher.update(bytes);
+        self.file.write_all(bytes).await?;
+        Ok(())
+    }
+
+    /// Store the content and list the artifact, making room for it if needed
+    pub async fn finish(mut self) -> Result<Artifact, ArtifactError> {
+        self.file.flush().await?;
+        tokio::fs::rename(&self.partial, self.store.dir.join(&self.id)).await?;
+        self.finished = true;
+
+        let created_at = Utc::now();
+        let artifact = Artifact {
+            id: self.id.clone(),
+            kind: self.draft.kind,
+            file_name: self.draft.file_name.clone(),
+            content_type: self.draft.content_type.clone(),
+            size_bytes: self.size,
+            sha256: format!("{:x}", self.hasher.clone().finalize()),
+            container: self.draft.container.clone(),
+            job_id: self.draft.job_id.clone(),
+            created_at,
+            expires_at: created_at + self.store.retention,
+        };
+        self.store.index.put(&artifact.id, artifact.clone())?;
+        self.store.prune(created_at);
+        info!(
+            "Stored artifact {} ({}, {} bytes)",
+            artifact.id, artifact.file_name, artifact.size_bytes
+        );
+        Ok(artifact)
+    }
+}
+
+impl Drop for ArtifactWriter {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = std::fs::remove_file(&self.partial);
+        }
+    }
+}
+
+/// Spawn the background task that removes expired artifacts
+pub fn spawn_reaper(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(REAP_INTERVAL).await;
+            state.artifacts.prune(Utc::now());
+        }
+    });
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ArtifactQuery {
+    pub kind: Option<ArtifactKind>,
+    pub container: Option<String>,
+}
+
+/// GET /api/artifacts?kind=&container= - Stored artifacts, newest first
+pub async fn list_artifacts(
+    State(state): State<AppState>,
+    Query(query): Query<ArtifactQuery>,
+) -> impl IntoResponse {
+    let artifacts: Vec<Artifact> = state
+        .artifacts
+        .list()
+        .into_iter()
+        .filter(|a| query.kind.is_none_or(|kind| a.kind == kind))
+        .filter(|a| query.container.is_none() || a.container == query.container)
+        .collect();
+    Json(artifacts)
+}
+
+/// GET /api/artifacts/:id - Stream an artifact's content
+pub async fn download_artifact(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ArtifactError> {
+    let artifact = state.artifacts.get(&id).ok_or_else(|| ArtifactError::NotFound(id.clone()))?;
+    let file = match tokio::fs::File::open(state.artifacts.dir.join(&artifact.id)).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(ArtifactError::NotFound(id)),
+        Err(e) => return Err(e.into()),
+    };
+
+    let body = futures::stream::try_unfold(file, |mut file| async move {
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        let read = file.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok::<_, std::io::Error>(None);
+        }
+        chunk.truncate(read);
+        Ok(Some((Bytes::from(chunk), file)))
+    });
+    Ok((
+        [
+            (header::CONTENT_TYPE, artifact.content_type.clone()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", artifact.file_name.replace('"', "")),
+            ),
+            (header::CONTENT_LENGTH, artifact.size_bytes.to_string()),
+            (header::ETAG, format!("\"{}\"", artifact.sha256)),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// DELETE /api/artifacts/:id - Delete an artifact before it expires
+pub async fn delete_artifact(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ArtifactError> {
+    let artifact = state.artifacts.remove(&id)?.ok_or(ArtifactError::NotFound(id))?;
+    info!("Deleted artifact {} ({})", artifact.id, artifact.file_name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/container/:id/logs/export - Start a job that archives a
+/// container's logs as a downloadable artifact
+pub async fn export_logs(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ArtifactError> {
+    let container = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or(ArtifactError::ContainerNotFound(id))?;
+
+    let (jobs, name) = (state.jobs.clone(), container.name.clone());
+    let job: Job = jobs.spawn("logs-export", &name, 1, move |handle| async move {
+        write_logs(&state, container, handle).await.map_err(|e| e.to_string())
+    });
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn write_logs(state: &AppState, container: ContainerInfo, handle: JobHandle) -> Result<(), ArtifactError> {
+    let logs = state
+        .docker
+        .get_container_logs(&container.id, LogWindow::tail(LOG_EXPORT_TAIL))
+        .await?
+        .ok_or_else(|| ArtifactError::ContainerNotFound(container.name.clone()))?;
+
+    let mut writer = state
+        .artifacts
+        .create(ArtifactDraft {
+            kind: ArtifactKind::Logs,
+            file_name: format!("{}-{}.log", container.name, Utc::now().format("%Y%m%dT%H%M%SZ")),
+            content_type: "text/plain; charset=utf-8".to_string(),
+            container: Some(container.name.clone()),
+            job_id: Some(handle.id().to_string()),
+        })
+        .await?;
+    for entry in &logs.logs {
+        let line = match entry.ts {
+            Some(ts) => format!("{} {}\n", ts.to_rfc3339_opts(SecondsFormat::Nanos, true), entry.line),
+            None => format!("{}\n", entry.line),
+        };
+        writer.write(line.as_bytes()).await?;
+    }
+    let artifact = writer.finish().await?;
+
+    handle.attach(&artifact);
+    handle.record(ActionResult {
+        success: true,
+        container_id: container.id,
+        container_name: container.name,
+        action: "logs-export".to_string(),
+        message: format!("Exported {} log lines ({} bytes)", logs.logs.len(), artifact.size_bytes),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(max_bytes: u64) -> (PathBuf, Arc<ArtifactStore>) {
+        let dir = std::env::temp_dir().join(format!("flowscope-artifacts-{}", uuid::Uuid::new_v4()));
+        let store = ArtifactStore::open_at(dir.clone(), chrono::Duration::hours(1), max_bytes);
+        (dir, Arc::new(store))
+    }
+
+    fn draft(file_name: &str) -> ArtifactDraft {
+        ArtifactDraft {
+            kind: ArtifactKind::Report,
+            file_name: file_name.to_string(),
+            content_type: "text/plain".to_string(),
+            container: None,
+            job_id: None,
+        }
+    }
+
+    async fn put(store: &Arc<ArtifactStore>, file_name: &str, content: &[u8]) -> Artifact {
+        let mut writer = store.create(draft(file_name)).await.unwrap();
+        writer.write(content).await.unwrap();
+        writer.finish().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn stores_content_with_checksum() {
+        let (dir, store) = store(MIB);
+        let artifact = put(&store, "report.txt", b"hello").await;
+
+        assert_eq!(artifact.size_bytes, 5);
+        assert_eq!(
+            artifact.sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(std::fs::read(dir.join(&artifact.id)).unwrap(), b"hello");
+        assert_eq!(store.list().len(), 1);
+
+        // An abandoned write leaves nothing behind
+        let mut writer = store.create(draft("partial.txt")).await.unwrap();
+        writer.write(b"half").await.unwrap();
+        drop(writer);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        store.remove(&artifact.id).unwrap();
+        assert!(!dir.join(&artifact.id).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn evicts_oldest_over_limit_and_expired() {
+        let (dir, store) = store(10);
+        let first = put(&store, "first", b"123456").await;
+        let second = put(&store, "second", b"123456").await;
+        let names: Vec<String> = store.list().into_iter().map(|a| a.file_name).collect();
+        assert_eq!(names, ["second"]);
+        assert!(!dir.join(&first.id).exists());
+
+        let mut writer = store.create(draft("huge")).await.unwrap();
+        assert!(matches!(writer.write(&[0; 11]).await, Err(ArtifactError::TooLarge(10))));
+
+        let removed = store.prune(second.expires_at);
+        assert_eq!(removed.len(), 1);
+        assert!(store.list().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
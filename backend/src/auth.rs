@@ -0,0 +1,619 @@
+//! API tokens and scopes
+//!
+//! Tokens are defined in a YAML file (`FLOWSCOPE_TOKENS_FILE`, default
+//! `<data dir>/tokens.yaml`), each with a role, the scopes it holds, or both:
+//!
+//! ```yaml
+//! tokens:
+//!   - name: ci
+//!     token: sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+//!     scopes: [containers:read]
+//!   - name: ops
+//!     token: s3cret
+//!     role: operator
+//!     rate_class: automation
+//! rate_classes:
+//!   automation:
+//!     requests_per_minute: 120
+//!     actions_per_hour: 30
+//! ```
+//!
+//! A `viewer` reads containers and logs, an `operator` can also start, stop,
+//! restart and scale them, and an `admin` holds every scope, including the
+//! destructive routes such as removing a container. A token may be stored as
+//! its `sha256:` digest instead of in plain text, and may be limited by a rate
+//! class (see [`crate::usage`]).
+//! A single key holding every scope can be set in `FLOWSCOPE_API_KEY` instead
+//! of, or alongside, the file.
+//!
+//! Every request is matched against [`ROUTE_SCOPES`] to find the scope it needs
+//! and must carry a token holding that scope, as `Authorization: Bearer`, an
+//! `X-API-Key` header or, for the WebSocket upgrade, an `access_token` query
+//! parameter. With `FLOWSCOPE_ANONYMOUS_READS=true`, requests that only need
+//! `containers:read` may come without one. With neither a tokens file nor an
+//! API key authentication is off and every route is open.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{
+    config::parse_bool,
+    models::{ApiRole, ApiScope, RateLimits, ScopeInfo},
+    store::data_dir,
+    AppState,
+};
+
+/// Routes and the scope each requires, first match wins. `*` matches one path
+/// segment; `None` marks public routes. Unlisted GETs need `containers:read`
+/// and every other unlisted request needs `admin`.
+pub const ROUTE_SCOPES: &[(&str, &str, Option<ApiScope>)] = &[
+    ("GET", "/health", None),
+    ("GET", "/embed/flowchart/*", None),
+    ("GET", "/api/auth/scopes", None),
+    ("GET", "/api/auth/usage", Some(ApiScope::Admin)),
+    ("GET", "/api/container/*/logs", Some(ApiScope::LogsRead)),
+    ("GET", "/api/logs", Some(ApiScope::LogsRead)),
+    ("GET", "/api/logs/search", Some(ApiScope::LogsRead)),
+    ("GET", "/ws/logs/*", Some(ApiScope::LogsRead)),
+    ("POST", "/api/container/*/logs/export", Some(ApiScope::LogsRead)),
+    // Artifacts hold log archives and packet captures
+    ("GET", "/api/artifacts/*", Some(ApiScope::LogsRead)),
+    ("POST", "/api/container/*/restart", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/stop", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/start", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/pause", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/unpause", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/kill", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/rollback", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/warmup", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/groups/*/actions/*", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/containers/actions/*", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/containers", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/projects/*/*", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/projects/*/services/*/scale", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/templates/*/instantiate", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/migrations/*/cutover", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/images/pull", Some(ApiScope::ContainersActions)),
+    ("POST", "/api/container/*/exec", Some(ApiScope::Exec)),
+    ("GET", "/ws/exec/*", Some(ApiScope::Exec)),
+    ("POST", "/api/container/*/debug-sidecar", Some(ApiScope::Exec)),
+    ("DELETE", "/api/debug-sidecars/*", Some(ApiScope::Exec)),
+    ("POST", "/api/container/*/forward", Some(ApiScope::Exec)),
+    ("DELETE", "/api/forwards/*", Some(ApiScope::Exec)),
+    ("POST", "/api/containers/run", Some(ApiScope::Exec)),
+    ("DELETE", "/api/runs/*", Some(ApiScope::Exec)),
+    // Destructive: what they remove cannot be brought back
+    ("DELETE", "/api/container/*", Some(ApiScope::Admin)),
+    ("DELETE", "/api/image/*", Some(ApiScope::Admin)),
+    // Evaluations that change nothing
+    ("POST", "/api/containers/preflight", Some(ApiScope::ContainersRead)),
+    ("POST", "/api/alerts/rules/test", Some(ApiScope::ContainersRead)),
+    ("POST", "/api/endpoints/*/test", Some(ApiScope::ContainersRead)),
+    // Reads that expose secrets or configuration
+    ("GET", "/api/bundle/export", Some(ApiScope::Admin)),
+    ("GET", "/api/notifications/channels", Some(ApiScope::Admin)),
+    ("GET", "/api/notifications/channels/*", Some(ApiScope::Admin)),
+    ("GET", "/api/notifications/status", Some(ApiScope::Admin)),
+];
+
+/// Routes that destroy containers or images; they need `admin` and are
+/// recorded as actions
+const DESTRUCTIVE_ROUTES: &[(&str, &str)] = &[("DELETE", "/api/container/*"), ("DELETE", "/api/image/*")];
+
+pub fn matches_route(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    pattern.len() == path.len() && pattern.iter().zip(&path).all(|(p, s)| *p == "*" || p == s)
+}
+
+/// The scope a request needs, or `None` for public routes
+pub fn required_scope(method: &Method, path: &str) -> Option<ApiScope> {
+    ROUTE_SCOPES
+        .iter()
+        .find(|(m, pattern, _)| *m == method.as_str() && matches_route(pattern, path))
+        .map_or(
+            Some(if method == Method::GET { ApiScope::ContainersRead } else { ApiScope::Admin }),
+            |(_, _, scope)| *scope,
+        )
+}
+
+/// Whether a request destroys containers or images
+pub fn is_destructive(method: &Method, path: &str) -> bool {
+    DESTRUCTIVE_ROUTES
+        .iter()
+        .any(|(m, pattern)| *m == method.as_str() && matches_route(pattern, path))
+}
+
+fn describe(scope: ApiScope) -> &'static str {
+    match scope {
+        ApiScope::ContainersRead => "Read topology, containers, stats, diagnostics and stored configuration",
+        ApiScope::ContainersActions => "Start, stop, pause, signal, restart, scale and create containers, and pull images",
+        ApiScope::LogsRead => "Read container logs",
+        ApiScope::Exec => "Run commands inside containers or in one-off containers",
+        ApiScope::Admin => {
+            "Remove containers and images, and manage templates, groups, dashboards, bundles and notifications; includes every other scope"
+        }
+    }
+}
+
+/// Every scope with the routes that require it
+pub fn scopes() -> Vec<ScopeInfo> {
+    ApiScope::ALL
+        .into_iter()
+        .map(|scope| {
+            let mut routes: Vec<String> = ROUTE_SCOPES
+                .iter()
+                .filter(|(_, _, s)| *s == Some(scope))
+                .map(|(method, pattern, _)| format!("{} {}", method, pattern))
+                .collect();
+            match scope {
+                ApiScope::ContainersRead => routes.push("GET *".to_string()),
+                ApiScope::Admin => routes.push("* *".to_string()),
+                _ => {}
+            }
+            ScopeInfo {
+                scope,
+                description: describe(scope).to_string(),
+                routes,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct TokensFile {
+    tokens: Vec<TokenEntry>,
+    #[serde(default)]
+    rate_classes: BTreeMap<String, RateClassEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenEntry {
+    name: String,
+    token: String,
+    #[serde(default)]
+    role: Option<ApiRole>,
+    #[serde(default)]
+    scopes: Vec<ApiScope>,
+    #[serde(default)]
+    rate_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct RateClassEntry {
+    requests_per_minute: Option<u32>,
+    actions_per_hour: Option<u32>,
+}
+
+/// A configured token, identified by the digest of its secret
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub name: String,
+    pub role: Option<ApiRole>,
+    /// The role's scopes and any listed besides
+    pub scopes: Vec<ApiScope>,
+    pub rate_class: Option<String>,
+    pub limits: RateLimits,
+    digest: [u8; 32],
+}
+
+impl ApiToken {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.iter().any(|s| *s == scope || *s == ApiScope::Admin)
+    }
+}
+
+#[cfg(test)]
+impl ApiToken {
+    /// An unlimited token holding every scope
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            role: Some(ApiRole::Admin),
+            scopes: vec![ApiScope::Admin],
+            rate_class: None,
+            limits: RateLimits::default(),
+            digest: digest(name),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Authenticator {
+    pub fn with_tokens(tokens: Vec<ApiToken>) -> Self {
+        Self {
+            tokens,
+            anonymous_reads: false,
+        }
+    }
+}
+
+/// The token a request was authenticated with, available to handlers as a
+/// request extension
+#[derive(Debug, Clone)]
+pub struct Caller(pub ApiToken);
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthConfigError {
+    #[error("failed to read {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("failed to parse {0}: {1}")]
+    Parse(String, serde_yaml::Error),
+    #[error("token '{0}': {1}")]
+    Invalid(String, String),
+    #[error("{0}: {1}")]
+    Setting(&'static str, String),
+}
+
+fn digest(secret: &str) -> [u8; 32] {
+    Sha256::digest(secret.as_bytes()).into()
+}
+
+fn parse_digest(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// Name `FLOWSCOPE_API_KEY` is known by in logs, errors and usage
+const API_KEY_NAME: &str = "api-key";
+
+#[derive(Debug, Default)]
+pub struct Authenticator {
+    tokens: Vec<ApiToken>,
+    /// Whether requests needing only `containers:read` may come without a token
+    anonymous_reads: bool,
+}
+
+impl Authenticator {
+    /// Load tokens from `FLOWSCOPE_TOKENS_FILE` or `<data dir>/tokens.yaml`,
+    /// then `FLOWSCOPE_API_KEY` and `FLOWSCOPE_ANONYMOUS_READS`. A missing file
+    /// with no API key turns authentication off; an unreadable one is an
+    /// error, so a typo never silently opens the API.
+    pub fn from_env() -> Result<Self, AuthConfigError> {
+        let path = std::env::var("FLOWSCOPE_TOKENS_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| data_dir().join("tokens.yaml"));
+        let shown = path.display().to_string();
+
+        let mut auth = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let file: TokensFile =
+                    serde_yaml::from_str(&text).map_err(|e| AuthConfigError::Parse(shown.clone(), e))?;
+                let auth = Self::from_entries(file.tokens, &file.rate_classes)?;
+                info!("Loaded {} API tokens from {}", auth.tokens.len(), shown);
+                auth
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(AuthConfigError::Read(shown, e)),
+        };
+        let var = |name: &str| std::env::var(name).ok();
+        auth.configure(&var)?;
+        if !auth.enabled() {
+            warn!(
+                "No API tokens file at {} and no FLOWSCOPE_API_KEY, authentication is disabled",
+                shown
+            );
+        } else if auth.anonymous_reads {
+            info!("Read-only requests are allowed without an API token");
+        }
+        Ok(auth)
+    }
+
+    /// Add `FLOWSCOPE_API_KEY` as a token holding every scope and read
+    /// `FLOWSCOPE_ANONYMOUS_READS`
+    fn configure(&mut self, var: &impl Fn(&str) -> Option<String>) -> Result<(), AuthConfigError> {
+        if let Some(key) = var("FLOWSCOPE_API_KEY").map(|k| k.trim().to_string()).filter(|k| !k.is_empty()) {
+            if self.tokens.iter().any(|t| t.name == API_KEY_NAME) {
+                return Err(AuthConfigError::Invalid(
+                    API_KEY_NAME.to_string(),
+                    "the name is taken by FLOWSCOPE_API_KEY".to_string(),
+                ));
+            }
+            self.tokens.push(ApiToken {
+                name: API_KEY_NAME.to_string(),
+                role: Some(ApiRole::Admin),
+                scopes: vec![ApiScope::Admin],
+                rate_class: None,
+                limits: RateLimits::default(),
+                digest: digest(&key),
+            });
+        }
+        self.anonymous_reads = match var("FLOWSCOPE_ANONYMOUS_READS").filter(|v| !v.trim().is_empty()) {
+            None => false,
+            Some(value) => parse_bool(&value).ok_or_else(|| {
+                AuthConfigError::Setting("FLOWSCOPE_ANONYMOUS_READS", format!("'{}' is not true or false", value))
+            })?,
+        };
+        Ok(())
+    }
+
+    fn from_entries(
+        entries: Vec<TokenEntry>,
+        rate_classes: &BTreeMap<String, RateClassEntry>,
+    ) -> Result<Self, AuthConfigError> {
+        let tokens = entries
+            .into_iter()
+            .map(|entry| {
+                let digest = match entry.token.strip_prefix("sha256:") {
+                    Some(hex) => parse_digest(hex).ok_or_else(|| {
+                        AuthConfigError::Invalid(entry.name.clone(), "sha256: needs 64 hex digits".to_string())
+                    })?,
+                    None if entry.token.is_empty() => {
+                        return Err(AuthConfigError::Invalid(entry.name, "token must not be empty".to_string()))
+                    }
+                    None => digest(&entry.token),
+                };
+                if entry.role.is_none() && entry.scopes.is_empty() {
+                    return Err(AuthConfigError::Invalid(entry.name, "needs a role or scopes".to_string()));
+                }
+                let mut scopes: Vec<ApiScope> = entry.role.map_or(&[][..], ApiRole::scopes).to_vec();
+                for scope in entry.scopes {
+                    if !scopes.contains(&scope) {
+                        scopes.push(scope);
+                    }
+                }
+                let limits = match &entry.rate_class {
+                    Some(class) => {
+                        let class = rate_classes.get(class).ok_or_else(|| {
+                            AuthConfigError::Invalid(entry.name.clone(), format!("unknown rate class '{}'", class))
+                        })?;
+                        RateLimits {
+                            requests_per_minute: class.requests_per_minute,
+                            actions_per_hour: class.actions_per_hour,
+                        }
+                    }
+                    None => RateLimits::default(),
+                };
+                Ok(ApiToken {
+                    name: entry.name,
+                    role: entry.role,
+                    scopes,
+                    rate_class: entry.rate_class,
+                    limits,
+                    digest,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            tokens,
+            anonymous_reads: false,
+        })
+    }
+
+    pub fn tokens(&self) -> &[ApiToken] {
+        &self.tokens
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Whether a request needing `scope` may come without a token
+    pub fn admits_anonymously(&self, scope: ApiScope) -> bool {
+        self.anonymous_reads && scope == ApiScope::ContainersRead
+    }
+
+    /// Whether a caller holding `token`, or none, may use `scope`: anyone when
+    /// authentication is off, anonymous callers only what anonymous reads allow
+    pub fn grants(&self, token: Option<&ApiToken>, scope: ApiScope) -> bool {
+        if !self.enabled() {
+            return true;
+        }
+        match token {
+            Some(token) => token.allows(scope),
+            None => self.admits_anonymously(scope),
+        }
+    }
+
+    pub fn authenticate(&self, secret: &str) -> Option<&ApiToken> {
+        let presented = digest(secret);
+        self.tokens.iter().find(|t| t.digest == presented)
+    }
+}
+
+/// Bearer token from the `Authorization` header, an `X-API-Key` header, or the
+/// `access_token` query parameter browsers have to use for WebSocket upgrades
+fn presented_token(request: &Request) -> Option<&str> {
+    let value_of = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+    value_of("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| value_of("x-api-key"))
+        .or_else(|| {
+            request
+                .uri()
+                .query()?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("access_token="))
+        })
+        .map(str::trim)
+}
+
+fn reject(status: StatusCode, message: String) -> Response {
+    reject_with(status, message, None)
+}
+
+fn reject_with(status: StatusCode, message: String, retry_after: Option<u64>) -> Response {
+    let mut response = (status, Json(serde_json::json!({ "error": message }))).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+    }
+    if let Some(seconds) = retry_after {
+        response.headers_mut().insert(header::RETRY_AFTER, seconds.into());
+    }
+    response
+}
+
+/// Middleware checking every request against the scope its route requires and
+/// the token's rate limits
+pub async fn require_scope(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    if !state.auth.enabled() {
+        return next.run(request).await;
+    }
+    let Some(scope) = required_scope(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let presented = presented_token(&request);
+    if presented.is_none() && state.auth.admits_anonymously(scope) {
+        return next.run(request).await;
+    }
+    let token = match presented.and_then(|secret| state.auth.authenticate(secret)) {
+        Some(token) => token.clone(),
+        None => return reject(StatusCode::UNAUTHORIZED, "missing or unknown API token".to_string()),
+    };
+    if !token.allows(scope) {
+        return reject(
+            StatusCode::FORBIDDEN,
+            format!("token '{}' lacks the '{}' scope", token.name, scope.as_str()),
+        );
+    }
+    if let Err(limit) = state.usage.admit(&token, scope, Instant::now()) {
+        return reject_with(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("token '{}' exceeded {}", token.name, limit.what),
+            Some(limit.retry_after.as_secs().max(1)),
+        );
+    }
+
+    request.extensions_mut().insert(Caller(token));
+    next.run(request).await
+}
+
+/// GET /api/auth/scopes - Scopes and the routes that require them
+pub async fn list_scopes() -> Json<Vec<ScopeInfo>> {
+    Json(scopes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn routes_map_to_scopes() {
+        let scope = |method: Method, path: &str| required_scope(&method, path);
+        assert_eq!(scope(Method::GET, "/health"), None);
+        assert_eq!(scope(Method::GET, "/api/containers"), Some(ApiScope::ContainersRead));
+        assert_eq!(scope(Method::GET, "/api/container/api-1/logs"), Some(ApiScope::LogsRead));
+        assert_eq!(scope(Method::POST, "/api/container/api-1/restart"), Some(ApiScope::ContainersActions));
+        assert_eq!(scope(Method::POST, "/api/projects/shop/up"), Some(ApiScope::ContainersActions));
+        assert_eq!(scope(Method::POST, "/api/containers/preflight"), Some(ApiScope::ContainersRead));
+        assert_eq!(scope(Method::DELETE, "/api/groups/checkout"), Some(ApiScope::Admin));
+        assert_eq!(scope(Method::GET, "/api/bundle/export"), Some(ApiScope::Admin));
+        assert_eq!(scope(Method::DELETE, "/api/container/api-1"), Some(ApiScope::Admin));
+        assert!(is_destructive(&Method::DELETE, "/api/container/api-1"));
+        assert!(!is_destructive(&Method::POST, "/api/container/api-1/stop"));
+    }
+
+    #[test]
+    fn roles_grant_their_scopes() {
+        let file: TokensFile = serde_yaml::from_str(
+            "tokens:
+              - name: dash
+                token: v
+                role: viewer
+              - name: oncall
+                token: o
+                role: operator
+                scopes: [exec]
+              - name: root
+                token: r
+                role: admin",
+        )
+        .unwrap();
+        let auth = Authenticator::from_entries(file.tokens, &file.rate_classes).unwrap();
+        let allowed = |secret: &str, scope| auth.authenticate(secret).unwrap().allows(scope);
+
+        assert!(allowed("v", ApiScope::ContainersRead) && allowed("v", ApiScope::LogsRead));
+        assert!(!allowed("v", ApiScope::ContainersActions));
+        assert!(allowed("o", ApiScope::ContainersActions) && allowed("o", ApiScope::Exec));
+        assert!(!allowed("o", ApiScope::Admin));
+        assert!(allowed("r", ApiScope::Admin));
+
+        let bare: TokensFile = serde_yaml::from_str("tokens: [{ name: none, token: x }]").unwrap();
+        assert!(Authenticator::from_entries(bare.tokens, &bare.rate_classes).is_err());
+    }
+
+    #[test]
+    fn tokens_match_by_digest() {
+        let file: TokensFile = serde_yaml::from_str(
+            "tokens:
+              - name: ci
+                token: sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+                scopes: [containers:read]
+              - name: ops
+                token: s3cret
+                scopes: [admin]",
+        )
+        .unwrap();
+        let auth = Authenticator::from_entries(file.tokens, &file.rate_classes).unwrap();
+        assert!(auth.enabled());
+
+        // sha256("test")
+        let ci = auth.authenticate("test").unwrap();
+        assert_eq!(ci.name, "ci");
+        assert!(ci.allows(ApiScope::ContainersRead) && !ci.allows(ApiScope::ContainersActions));
+        assert!(auth.authenticate("s3cret").unwrap().allows(ApiScope::Exec));
+        assert!(auth.authenticate("nope").is_none());
+
+        let bad = TokenEntry {
+            name: "short".to_string(),
+            token: "sha256:abc".to_string(),
+            role: None,
+            scopes: vec![ApiScope::ContainersRead],
+            rate_class: None,
+        };
+        assert!(Authenticator::from_entries(vec![bad], &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn api_key_and_anonymous_reads() {
+        let env = HashMap::from([("FLOWSCOPE_API_KEY", " k3y "), ("FLOWSCOPE_ANONYMOUS_READS", "yes")]);
+        let var = |name: &str| env.get(name).map(|v| v.to_string());
+        let mut auth = Authenticator::default();
+        assert!(!auth.enabled());
+        auth.configure(&var).unwrap();
+        assert!(auth.enabled());
+        assert_eq!(auth.authenticate("k3y").unwrap().name, "api-key");
+        assert!(auth.authenticate("k3y").unwrap().allows(ApiScope::Exec));
+        assert!(auth.admits_anonymously(ApiScope::ContainersRead));
+        assert!(!auth.admits_anonymously(ApiScope::ContainersActions));
+        assert!(!auth.admits_anonymously(ApiScope::LogsRead));
+
+        let var = |name: &str| (name == "FLOWSCOPE_ANONYMOUS_READS").then(|| "sometimes".to_string());
+        assert!(Authenticator::default().configure(&var).is_err());
+    }
+
+    #[test]
+    fn anonymous_dashboard_reads_get_no_logs() {
+        let env = HashMap::from([("FLOWSCOPE_API_KEY", "k3y"), ("FLOWSCOPE_ANONYMOUS_READS", "true")]);
+        let mut auth = Authenticator::default();
+        auth.configure(&|name: &str| env.get(name).map(|v| v.to_string())).unwrap();
+
+        // Anonymous reads reach a dashboard's data, but its log panels stay empty
+        let scope = required_scope(&Method::GET, "/api/dashboards/ops/data").unwrap();
+        assert!(auth.admits_anonymously(scope));
+        assert!(!auth.grants(None, ApiScope::LogsRead));
+        assert!(auth.grants(None, ApiScope::ContainersRead));
+        assert!(auth.grants(auth.authenticate("k3y"), ApiScope::LogsRead));
+        assert!(Authenticator::default().grants(None, ApiScope::LogsRead));
+    }
+}
@@ -0,0 +1,792 @@
+//! Circuit breaker and load shedding around Docker calls
+//!
+//! Every Docker call goes through [`ResilientDocker`], which applies a timeout
+//! and counts consecutive transport failures. Once the threshold is reached the
+//! breaker opens: calls fail immediately instead of piling onto a struggling
+//! daemon, read endpoints serve their last good response marked as degraded,
+//! and mutating endpoints return 503. After a cooldown a single call is let
+//! through as a probe; success closes the breaker again.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use bollard::{
+    container::{AttachContainerResults, Config, Stats},
+    errors::Error,
+    models::{
+        ContainerInspectResponse, ContainerSummary, ContainerTopResponse, EventMessage, FilesystemChange, ImageInspect, ImageSummary,
+        Network,
+        Service, SystemInfo, Volume,
+    },
+    Docker,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    auth::{ApiToken, Caller},
+    docker_api::{DockerApi, ExecOutput, ExecSession, LogWindow, STOP_GRACE_SECS},
+    models::LogEntry,
+    AppState,
+};
+
+/// Largest response body kept for degraded serving
+const MAX_CACHED_BODY: usize = 4 * 1024 * 1024;
+/// Most responses kept for degraded serving
+const MAX_CACHED_RESPONSES: usize = 256;
+/// Most bytes kept across all cached responses
+const MAX_CACHED_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Per-call timeout
+    pub call_timeout: Duration,
+    /// Consecutive failures that open the breaker
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before probing
+    pub cooldown: Duration,
+}
+
+impl BreakerConfig {
+    /// Read `FLOWSCOPE_DOCKER_TIMEOUT_SECS` (default 10),
+    /// `FLOWSCOPE_BREAKER_THRESHOLD` (default 5) and
+    /// `FLOWSCOPE_BREAKER_COOLDOWN_SECS` (default 30)
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|&v| v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            call_timeout: Duration::from_secs(var("FLOWSCOPE_DOCKER_TIMEOUT_SECS", 10)),
+            failure_threshold: var("FLOWSCOPE_BREAKER_THRESHOLD", 5) as u32,
+            cooldown: Duration::from_secs(var("FLOWSCOPE_BREAKER_COOLDOWN_SECS", 30)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Circuit {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// A probe went out at `since`; nothing else is let through until it reports
+    /// back or would have timed out
+    HalfOpen { since: Instant },
+}
+
+/// Breaker state as reported on `/health`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakerStatus {
+    /// `closed`, `open` or `half-open`
+    pub state: &'static str,
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+struct CachedResponse {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    cached_at: DateTime<Utc>,
+    last_used: u64,
+}
+
+/// Last good read responses, evicting the least recently used once either the
+/// entry or the byte bound is reached
+#[derive(Default)]
+struct ResponseCache {
+    entries: HashMap<String, CachedResponse>,
+    bytes: usize,
+    clock: u64,
+}
+
+impl ResponseCache {
+    fn get(&mut self, key: &str) -> Option<&CachedResponse> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(entry)
+    }
+
+    fn insert(&mut self, key: String, content_type: Option<HeaderValue>, body: Bytes) {
+        if body.len() > MAX_CACHED_BODY {
+            return;
+        }
+        if let Some(old) = self.entries.remove(&key) {
+            self.bytes -= old.body.len();
+        }
+        while self.entries.len() >= MAX_CACHED_RESPONSES || self.bytes + body.len() > MAX_CACHED_BYTES {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.bytes -= evicted.body.len();
+            }
+        }
+
+        self.clock += 1;
+        self.bytes += body.len();
+        self.entries.insert(
+            key,
+            CachedResponse {
+                content_type,
+                body,
+                cached_at: Utc::now(),
+                last_used: self.clock,
+            },
+        );
+    }
+}
+
+/// Cache key for a read: the URL and the caller's scopes, since routes such as
+/// inspect and dashboard data answer differently depending on who asks
+fn cache_key(caller: Option<&ApiToken>, uri: &Uri) -> String {
+    let mut scopes: Vec<&str> = caller
+        .map(|token| token.scopes.iter().map(|scope| scope.as_str()).collect())
+        .unwrap_or_default();
+    scopes.sort_unstable();
+    scopes.dedup();
+    format!("{} {}", scopes.join(","), uri)
+}
+
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: Mutex<(Circuit, Option<String>)>,
+    /// Last successful response per read URL and caller scopes, served while
+    /// the breaker is open
+    responses: Mutex<ResponseCache>,
+    /// Failed calls by kind since startup, for `/metrics`
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new((Circuit::Closed { failures: 0 }, None)),
+            responses: Mutex::default(),
+            errors: Mutex::default(),
+        }
+    }
+
+    fn count_error(&self, kind: &'static str) {
+        *self.errors.lock().unwrap().entry(kind).or_default() += 1;
+    }
+
+    /// Calls that failed since startup: `api` errors the daemon answered with,
+    /// `transport` failures, `timeout`s and `breaker_open` rejections
+    pub fn error_counts(&self) -> BTreeMap<&'static str, u64> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    /// Whether a call may go to the daemon. An expired open breaker lets one
+    /// call through as a probe and moves to half-open; a probe that never
+    /// reports back, such as a stream, is replaced once it would have timed out.
+    fn allow(&self) -> bool {
+        let mut guard = self.state.lock().unwrap();
+        let now = Instant::now();
+        match guard.0 {
+            Circuit::Closed { .. } => true,
+            Circuit::Open { until } if now < until => false,
+            Circuit::HalfOpen { since } if now < since + self.config.call_timeout => false,
+            Circuit::Open { .. } | Circuit::HalfOpen { .. } => {
+                info!("Docker circuit breaker half-open, probing daemon");
+                guard.0 = Circuit::HalfOpen { since: now };
+                true
+            }
+        }
+    }
+
+    /// Whether requests should currently be shed
+    pub fn is_open(&self) -> bool {
+        matches!(self.state.lock().unwrap().0, Circuit::Open { until } if Instant::now() < until)
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(self.state.lock().unwrap().0, Circuit::Closed { .. })
+    }
+
+    fn record_success(&self) {
+        let mut guard = self.state.lock().unwrap();
+        if matches!(guard.0, Circuit::HalfOpen { .. }) {
+            info!("Docker circuit breaker closed");
+        }
+        *guard = (Circuit::Closed { failures: 0 }, None);
+    }
+
+    fn record_failure(&self, error: &str) {
+        let mut guard = self.state.lock().unwrap();
+        let failures = match guard.0 {
+            Circuit::Closed { failures } => failures + 1,
+            Circuit::HalfOpen { .. } => self.config.failure_threshold,
+            Circuit::Open { .. } => return,
+        };
+
+        guard.0 = if failures >= self.config.failure_threshold {
+            warn!(
+                "Docker circuit breaker open for {}s after {} failures: {}",
+                self.config.cooldown.as_secs(),
+                failures,
+                error
+            );
+            Circuit::Open {
+                until: Instant::now() + self.config.cooldown,
+            }
+        } else {
+            Circuit::Closed { failures }
+        };
+        guard.1 = Some(error.to_string());
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let guard = self.state.lock().unwrap();
+        let (state, consecutive_failures, retry_after_secs) = match guard.0 {
+            Circuit::Closed { failures } => ("closed", failures, None),
+            Circuit::HalfOpen { .. } => ("half-open", self.config.failure_threshold, None),
+            Circuit::Open { until } => (
+                "open",
+                self.config.failure_threshold,
+                Some(until.saturating_duration_since(Instant::now()).as_secs() + 1),
+            ),
+        };
+        BreakerStatus {
+            state,
+            consecutive_failures,
+            retry_after_secs,
+            last_error: guard.1.clone(),
+        }
+    }
+
+    /// Run a Docker call under the timeout and breaker
+    async fn call<T>(&self, fut: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+        self.call_within(self.config.call_timeout, fut).await
+    }
+
+    /// Run a call that waits out the stop grace period, such as stopping a
+    /// container, with the grace period added to the timeout
+    async fn call_with_grace<T>(&self, fut: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+        let timeout = self.config.call_timeout + Duration::from_secs(STOP_GRACE_SECS);
+        self.call_within(timeout, fut).await
+    }
+
+    async fn call_within<T>(&self, timeout: Duration, fut: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+        if !self.allow() {
+            self.count_error("breaker_open");
+            return Err(open_error());
+        }
+
+        match tokio::time::timeout(timeout, fut).await {
+            Ok(Ok(value)) => {
+                self.record_success();
+                Ok(value)
+            }
+            Ok(Err(e)) => {
+                if is_transport_error(&e) {
+                    self.count_error("transport");
+                    self.record_failure(&e.to_string());
+                } else {
+                    // The daemon answered; an API error says nothing about its health
+                    self.count_error("api");
+                    self.record_success();
+                }
+                Err(e)
+            }
+            Err(_) => {
+                self.count_error("timeout");
+                self.record_failure(&format!("call timed out after {}s", timeout.as_secs()));
+                Err(Error::RequestTimeoutError)
+            }
+        }
+    }
+}
+
+fn open_error() -> Error {
+    Error::DockerResponseServerError {
+        status_code: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+        message: "Docker circuit breaker is open".to_string(),
+    }
+}
+
+/// Errors that mean the daemon could not be reached, as opposed to a rejected request
+fn is_transport_error(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::RequestTimeoutError
+            | Error::IOError { .. }
+            | Error::HyperResponseError { .. }
+            | Error::HyperLegacyError { .. }
+            | Error::HttpClientError { .. }
+            | Error::SocketNotFoundError(_)
+    )
+}
+
+/// [`DockerApi`] wrapper that routes every call through a [`CircuitBreaker`]
+pub struct ResilientDocker<D = Docker> {
+    inner: D,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<D: DockerApi> ResilientDocker<D> {
+    pub fn new(inner: D, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+impl<D: DockerApi> DockerApi for ResilientDocker<D> {
+    async fn list_containers(&self) -> Result<Vec<ContainerSummary>, Error> {
+        self.breaker.call(self.inner.list_containers()).await
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse, Error> {
+        self.breaker.call(self.inner.inspect_container(id)).await
+    }
+
+    async fn stats(&self, id: &str) -> Result<Option<Stats>, Error> {
+        self.breaker.call(self.inner.stats(id)).await
+    }
+
+    async fn top_processes(&self, id: &str, ps_args: &str) -> Result<ContainerTopResponse, Error> {
+        self.breaker.call(self.inner.top_processes(id, ps_args)).await
+    }
+
+    async fn container_changes(&self, id: &str) -> Result<Vec<FilesystemChange>, Error> {
+        self.breaker.call(self.inner.container_changes(id)).await
+    }
+
+    async fn logs(&self, id: &str, window: LogWindow) -> Result<Vec<LogEntry>, Error> {
+        self.breaker.call(self.inner.logs(id, window)).await
+    }
+
+    /// Long-lived streams cannot sit under the call timeout, so the breaker only
+    /// gates opening them
+    fn follow_logs(&self, id: &str) -> BoxStream<'static, Result<String, Error>> {
+        if self.breaker.allow() {
+            self.inner.follow_logs(id)
+        } else {
+            stream::once(async { Err(open_error()) }).boxed()
+        }
+    }
+
+    fn stream_logs(&self, id: &str, tail: Option<usize>, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        if self.breaker.allow() {
+            self.inner.stream_logs(id, tail, since)
+        } else {
+            stream::once(async { Err(open_error()) }).boxed()
+        }
+    }
+
+    fn stream_timestamped_logs(&self, id: &str, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        if self.breaker.allow() {
+            self.inner.stream_timestamped_logs(id, since)
+        } else {
+            stream::once(async { Err(open_error()) }).boxed()
+        }
+    }
+
+    async fn exec(&self, id: &str, cmd: &[&str]) -> Result<Option<String>, Error> {
+        self.breaker.call(self.inner.exec(id, cmd)).await
+    }
+
+    /// Runs as long as the command does, so skips the call timeout; callers
+    /// bound it themselves
+    async fn exec_output(
+        &self,
+        id: &str,
+        cmd: &[String],
+        working_dir: Option<&str>,
+        limit: usize,
+    ) -> Result<ExecOutput, Error> {
+        if self.breaker.allow() {
+            self.inner.exec_output(id, cmd, working_dir, limit).await
+        } else {
+            Err(open_error())
+        }
+    }
+
+    /// Opening the session is timed; the streams it returns are not
+    async fn exec_terminal(&self, id: &str, cmd: &[String], rows: u16, cols: u16) -> Result<ExecSession, Error> {
+        self.breaker.call(self.inner.exec_terminal(id, cmd, rows, cols)).await
+    }
+
+    async fn resize_exec(&self, exec_id: &str, rows: u16, cols: u16) -> Result<(), Error> {
+        self.breaker.call(self.inner.resize_exec(exec_id, rows, cols)).await
+    }
+
+    async fn exec_exit_code(&self, exec_id: &str) -> Result<Option<i64>, Error> {
+        self.breaker.call(self.inner.exec_exit_code(exec_id)).await
+    }
+
+    fn events(&self, since: i64) -> BoxStream<'static, Result<EventMessage, Error>> {
+        if self.breaker.allow() {
+            self.inner.events(since)
+        } else {
+            stream::once(async { Err(open_error()) }).boxed()
+        }
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>, Error> {
+        self.breaker.call(self.inner.list_images()).await
+    }
+
+    async fn inspect_image(&self, image: &str) -> Result<ImageInspect, Error> {
+        self.breaker.call(self.inner.inspect_image(image)).await
+    }
+
+    async fn list_networks(&self) -> Result<Vec<Network>, Error> {
+        self.breaker.call(self.inner.list_networks()).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<Volume>, Error> {
+        self.breaker.call(self.inner.list_volumes()).await
+    }
+
+    async fn list_services(&self) -> Result<Vec<Service>, Error> {
+        self.breaker.call(self.inner.list_services()).await
+    }
+
+    async fn info(&self) -> Result<SystemInfo, Error> {
+        self.breaker.call(self.inner.info()).await
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<(), Error> {
+        self.breaker.call_with_grace(self.inner.restart_container(id)).await
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<(), Error> {
+        self.breaker.call_with_grace(self.inner.stop_container(id)).await
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<(), Error> {
+        self.breaker.call(self.inner.pause_container(id)).await
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<(), Error> {
+        self.breaker.call(self.inner.unpause_container(id)).await
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<(), Error> {
+        self.breaker.call(self.inner.kill_container(id, signal)).await
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), Error> {
+        self.breaker.call(self.inner.start_container(id)).await
+    }
+
+    async fn create_container(&self, name: &str, config: Config<String>) -> Result<String, Error> {
+        self.breaker.call(self.inner.create_container(name, config)).await
+    }
+
+    async fn remove_container(&self, id: &str, volumes: bool) -> Result<(), Error> {
+        self.breaker.call_with_grace(self.inner.remove_container(id, volumes)).await
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<(), Error> {
+        self.breaker.call(self.inner.rename_container(id, name)).await
+    }
+
+    async fn scale_service(&self, service: &str, replicas: u64) -> Result<(), Error> {
+        self.breaker.call(self.inner.scale_service(service, replicas)).await
+    }
+
+    /// Pulls take as long as the download, so like log streams they skip the
+    /// call timeout; an open breaker still refuses them
+    async fn pull_image(&self, image: &str) -> Result<(), Error> {
+        if self.breaker.allow() {
+            self.inner.pull_image(image).await
+        } else {
+            Err(open_error())
+        }
+    }
+
+    async fn remove_image(&self, image: &str, force: bool) -> Result<(), Error> {
+        self.breaker.call(self.inner.remove_image(image, force)).await
+    }
+
+    /// Opening the attachment is timed; the streams it returns are not
+    async fn attach_container(&self, id: &str) -> Result<AttachContainerResults, Error> {
+        self.breaker.call(self.inner.attach_container(id)).await
+    }
+
+    /// Waits as long as the container runs, so skips the call timeout
+    async fn wait_container(&self, id: &str) -> Result<i64, Error> {
+        if self.breaker.allow() {
+            self.inner.wait_container(id).await
+        } else {
+            Err(open_error())
+        }
+    }
+}
+
+fn unavailable(status: &BreakerStatus) -> Response {
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({
+            "error": "Docker daemon is unavailable",
+            "details": status.last_error,
+            "retryAfterSecs": status.retry_after_secs,
+        })),
+    )
+        .into_response();
+    if let Some(secs) = status.retry_after_secs {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+}
+
+/// Serve a cached read response, adding `"degraded": true` to JSON objects and
+/// an `X-FlowScope-Degraded` header to everything
+fn degraded(cached: &CachedResponse) -> Response {
+    let body = match serde_json::from_slice::<serde_json::Value>(&cached.body) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert("degraded".to_string(), true.into());
+            map.insert("cachedAt".to_string(), cached.cached_at.to_rfc3339().into());
+            Bytes::from(serde_json::to_vec(&map).unwrap_or_default())
+        }
+        _ => cached.body.clone(),
+    };
+
+    let mut response = Response::new(Body::from(body));
+    let headers = response.headers_mut();
+    if let Some(content_type) = &cached.content_type {
+        headers.insert(header::CONTENT_TYPE, content_type.clone());
+    }
+    headers.insert("x-flowscope-degraded", HeaderValue::from_static("true"));
+    if let Ok(value) = HeaderValue::from_str(&cached.cached_at.to_rfc3339()) {
+        headers.insert("x-flowscope-cached-at", value);
+    }
+    response
+}
+
+/// Middleware for Docker-backed routes: caches successful reads and sheds load
+/// while the breaker is open
+pub async fn shed_load(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let breaker = &state.breaker;
+    let is_read = request.method() == Method::GET;
+    let token = caller.as_ref().map(|Extension(Caller(token))| token);
+    let key = cache_key(token, request.uri());
+
+    if breaker.is_open() {
+        if is_read {
+            if let Some(cached) = breaker.responses.lock().unwrap().get(&key) {
+                return degraded(cached);
+            }
+        }
+        return unavailable(&breaker.status());
+    }
+
+    let response = next.run(request).await;
+    if !is_read {
+        return response;
+    }
+
+    if response.status() != StatusCode::OK {
+        // The call that tripped the breaker, or one turned away while a probe
+        // is out, can still be answered from cache
+        if !breaker.is_closed() {
+            if let Some(cached) = breaker.responses.lock().unwrap().get(&key) {
+                return degraded(cached);
+            }
+        }
+        return response;
+    }
+
+    // Only bodies of known, cacheable size are buffered; streams pass through
+    let (parts, body) = response.into_parts();
+    let cacheable = body.size_hint().exact().is_some_and(|len| len <= MAX_CACHED_BODY as u64);
+    if !cacheable {
+        return Response::from_parts(parts, body);
+    }
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_CACHED_BODY).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    breaker.responses.lock().unwrap().insert(
+        key,
+        parts.headers.get(header::CONTENT_TYPE).cloned(),
+        bytes.clone(),
+    );
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ApiScope;
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(BreakerConfig {
+            call_timeout: Duration::from_millis(50),
+            failure_threshold: 2,
+            cooldown,
+        })
+    }
+
+    async fn fail(breaker: &CircuitBreaker) -> Result<(), Error> {
+        breaker.call(async { Err::<(), _>(Error::RequestTimeoutError) }).await
+    }
+
+    #[tokio::test]
+    async fn opens_after_the_threshold_and_closes_after_a_probe() {
+        let breaker = breaker(Duration::ZERO);
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.status().state, "closed");
+        assert_eq!(breaker.status().consecutive_failures, 1);
+
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.status().state, "open");
+
+        // The cooldown has passed, so the next call is the probe
+        breaker.call(async { Ok(()) }).await.unwrap();
+        assert_eq!(breaker.status().state, "closed");
+        assert_eq!(breaker.status().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn stays_open_through_the_cooldown() {
+        let breaker = breaker(Duration::from_secs(60));
+        fail(&breaker).await.unwrap_err();
+        fail(&breaker).await.unwrap_err();
+        assert!(breaker.is_open());
+
+        let err = breaker.call(async { Ok(()) }).await.unwrap_err();
+        assert!(matches!(err, Error::DockerResponseServerError { status_code: 503, .. }));
+        assert_eq!(breaker.error_counts()["breaker_open"], 1);
+        assert!(breaker.status().retry_after_secs.is_some());
+    }
+
+    #[tokio::test]
+    async fn half_open_lets_a_single_probe_through() {
+        let breaker = breaker(Duration::ZERO);
+        fail(&breaker).await.unwrap_err();
+        fail(&breaker).await.unwrap_err();
+
+        assert!(breaker.allow());
+        assert_eq!(breaker.status().state, "half-open");
+        assert!(!breaker.allow());
+        assert!(!breaker.allow());
+
+        // A probe that never reports back is replaced once it would have timed out
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+
+        // A failed probe reopens the breaker
+        breaker.record_failure("still down");
+        assert_eq!(breaker.status().state, "open");
+    }
+
+    #[tokio::test]
+    async fn api_errors_do_not_count_as_failures() {
+        let breaker = breaker(Duration::ZERO);
+        for _ in 0..3 {
+            let rejected = Error::DockerResponseServerError {
+                status_code: 404,
+                message: "no such container".to_string(),
+            };
+            breaker.call(async { Err::<(), _>(rejected) }).await.unwrap_err();
+        }
+        assert_eq!(breaker.status().state, "closed");
+        assert_eq!(breaker.error_counts()["api"], 3);
+    }
+
+    #[tokio::test]
+    async fn timeouts_count_as_failures() {
+        let breaker = breaker(Duration::from_secs(60));
+        for _ in 0..2 {
+            let err = breaker
+                .call(async {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(())
+                })
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::RequestTimeoutError));
+        }
+        assert!(breaker.is_open());
+        assert_eq!(breaker.error_counts()["timeout"], 2);
+    }
+
+    #[test]
+    fn cache_keys_separate_callers_by_scope() {
+        let uri: Uri = "/api/container/web/inspect?redact=false".parse().unwrap();
+        let admin = ApiToken::named("admin");
+        let mut viewer = ApiToken::named("viewer");
+        viewer.scopes = vec![ApiScope::LogsRead, ApiScope::ContainersRead];
+        let mut other_viewer = ApiToken::named("other");
+        other_viewer.scopes = vec![ApiScope::ContainersRead, ApiScope::LogsRead];
+
+        let mut cache = ResponseCache::default();
+        cache.insert(cache_key(Some(&admin), &uri), None, Bytes::from_static(b"secret"));
+
+        assert!(cache.get(&cache_key(Some(&viewer), &uri)).is_none());
+        assert!(cache.get(&cache_key(None, &uri)).is_none());
+        assert_eq!(cache_key(Some(&viewer), &uri), cache_key(Some(&other_viewer), &uri));
+        assert_eq!(&cache.get(&cache_key(Some(&admin), &uri)).unwrap().body[..], b"secret");
+    }
+
+    #[test]
+    fn cache_evicts_the_least_recently_used() {
+        let mut cache = ResponseCache::default();
+        let body = Bytes::from(vec![0; MAX_CACHED_BODY]);
+        for i in 0..MAX_CACHED_BYTES / MAX_CACHED_BODY {
+            cache.insert(format!("/{}", i), None, body.clone());
+        }
+        assert_eq!(cache.bytes, MAX_CACHED_BYTES);
+
+        // Touch the oldest so the second oldest goes first
+        cache.get("/0").unwrap();
+        cache.insert("/new".to_string(), None, body.clone());
+        assert_eq!(cache.bytes, MAX_CACHED_BYTES);
+        assert!(cache.get("/0").is_some());
+        assert!(cache.get("/1").is_none());
+        assert!(cache.get("/new").is_some());
+
+        // Replacing an entry frees its old body first
+        cache.insert("/new".to_string(), None, Bytes::from_static(b"small"));
+        assert_eq!(cache.bytes, MAX_CACHED_BYTES - MAX_CACHED_BODY + 5);
+
+        // Too large to keep at all
+        cache.insert("/huge".to_string(), None, Bytes::from(vec![0; MAX_CACHED_BODY + 1]));
+        assert!(cache.get("/huge").is_none());
+
+        for i in 0..MAX_CACHED_RESPONSES * 2 {
+            cache.insert(format!("/bust={}", i), None, Bytes::from_static(b"{}"));
+        }
+        assert_eq!(cache.entries.len(), MAX_CACHED_RESPONSES);
+    }
+}
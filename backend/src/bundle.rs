@@ -0,0 +1,387 @@
+//! Configuration export/import bundles
+//!
+//! Exports every record users author through the API (templates, groups,
+//! dashboards and notification channels) as one YAML document signed with
+//! `FLOWSCOPE_BUNDLE_SECRET`. Instances sharing the secret can import each
+//! other's bundles; the signature covers the bundle's JSON form, so
+//! reformatting or commenting the YAML in Git does not invalidate it, but any
+//! change to the records does.
+//!
+//! Alert rules and category rules are not part of a bundle: they live in the
+//! configuration and categories files, which are versioned as files already.
+//! Bundles are signed, not encrypted, and notification channel targets often
+//! embed webhook tokens, so treat an exported bundle like a secret.
+
+use std::collections::BTreeSet;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+
+use crate::{
+    dashboards::{self, DashboardError},
+    groups::{self, GroupError},
+    models::{ConfigBundle, Dashboard, ImportChanges, ImportMode, ImportReport},
+    notifications::{self, NotificationError},
+    store::{JsonStore, StoreError},
+    templates::{self, TemplateError},
+    AppState,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bundle format version written by this build
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("bundle signing is not configured; set FLOWSCOPE_BUNDLE_SECRET")]
+    NotConfigured,
+    #[error("invalid bundle: {0}")]
+    Parse(String),
+    #[error("bundle signature is missing or does not match")]
+    BadSignature,
+    #[error("unsupported bundle version {0}; this instance reads version {BUNDLE_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("duplicate {0} '{1}' in bundle")]
+    Duplicate(&'static str, String),
+    #[error("template '{0}': {1}")]
+    Template(String, TemplateError),
+    #[error("group '{0}': {1}")]
+    Group(String, GroupError),
+    #[error("dashboard '{0}': {1}")]
+    Dashboard(String, DashboardError),
+    #[error("notification channel '{0}': {1}")]
+    Notification(String, NotificationError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl BundleError {
+    fn status(&self) -> StatusCode {
+        match self {
+            BundleError::NotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+            BundleError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for BundleError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Bundle error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Signs and verifies config bundles
+pub struct BundleSigner {
+    secret: Option<Vec<u8>>,
+}
+
+impl BundleSigner {
+    /// Build from `FLOWSCOPE_BUNDLE_SECRET`; without it export and import are disabled
+    pub fn from_env() -> Self {
+        let secret = std::env::var("FLOWSCOPE_BUNDLE_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(String::into_bytes);
+        if secret.is_none() {
+            warn!("FLOWSCOPE_BUNDLE_SECRET not set, config bundle export/import is disabled");
+        }
+        Self { secret }
+    }
+
+    /// MAC over the bundle's JSON form with the signature field cleared. Going
+    /// through `serde_json::Value` sorts object keys, so records holding a
+    /// `HashMap` still sign identically on both ends.
+    fn mac(&self, bundle: &ConfigBundle) -> Result<HmacSha256, BundleError> {
+        let secret = self.secret.as_ref().ok_or(BundleError::NotConfigured)?;
+        let unsigned = ConfigBundle {
+            signature: String::new(),
+            ..bundle.clone()
+        };
+        let canonical = serde_json::to_value(&unsigned)
+            .and_then(|value| serde_json::to_vec(&value))
+            .map_err(|e| BundleError::Parse(e.to_string()))?;
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(&canonical);
+        Ok(mac)
+    }
+
+    pub fn sign(&self, mut bundle: ConfigBundle) -> Result<ConfigBundle, BundleError> {
+        bundle.signature = URL_SAFE_NO_PAD.encode(self.mac(&bundle)?.finalize().into_bytes());
+        Ok(bundle)
+    }
+
+    pub fn verify(&self, bundle: &ConfigBundle) -> Result<(), BundleError> {
+        let mac = self.mac(bundle)?;
+        let provided = URL_SAFE_NO_PAD
+            .decode(&bundle.signature)
+            .map_err(|_| BundleError::BadSignature)?;
+        mac.verify_slice(&provided).map_err(|_| BundleError::BadSignature)
+    }
+}
+
+/// Work out what importing `incoming` into `store` would change
+fn plan<T>(store: &JsonStore<T>, incoming: &[(String, T)], mode: ImportMode) -> ImportChanges
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    let mut changes = ImportChanges::default();
+    for (name, _) in incoming {
+        if store.contains(name) {
+            changes.updated.push(name.clone());
+        } else {
+            changes.created.push(name.clone());
+        }
+    }
+    if mode == ImportMode::Replace {
+        let keep: BTreeSet<&str> = incoming.iter().map(|(n, _)| n.as_str()).collect();
+        changes.removed = store
+            .keys()
+            .into_iter()
+            .filter(|k| !keep.contains(k.as_str()))
+            .collect();
+    }
+    changes
+}
+
+fn apply<T>(store: &JsonStore<T>, incoming: Vec<(String, T)>, changes: &ImportChanges) -> Result<(), StoreError>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    for (name, record) in incoming {
+        store.put(&name, record)?;
+    }
+    for name in &changes.removed {
+        store.remove(name)?;
+    }
+    Ok(())
+}
+
+/// Reject bundles that name the same record twice
+fn unique<T>(kind: &'static str, records: Vec<T>, name: impl Fn(&T) -> &str) -> Result<Vec<(String, T)>, BundleError> {
+    let mut seen = BTreeSet::new();
+    records
+        .into_iter()
+        .map(|r| {
+            let n = name(&r).to_string();
+            if !seen.insert(n.clone()) {
+                return Err(BundleError::Duplicate(kind, n));
+            }
+            Ok((n, r))
+        })
+        .collect()
+}
+
+/// Dashboards are keyed by the id their instance assigned, so a bundle must carry it
+fn validate_dashboard(dashboard: &Dashboard) -> Result<(), BundleError> {
+    let invalid = |e| BundleError::Dashboard(dashboard.name.clone(), e);
+    if dashboard.id.trim().is_empty() {
+        return Err(invalid(DashboardError::Invalid("id must not be empty".to_string())));
+    }
+    dashboards::validate(dashboard).map_err(invalid)
+}
+
+/// GET /api/bundle/export - Download all user configuration as signed YAML
+pub async fn export_bundle(State(state): State<AppState>) -> Result<impl IntoResponse, BundleError> {
+    let bundle = state.bundles.sign(ConfigBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        templates: state.templates.list(),
+        groups: state.groups.list(),
+        dashboards: state.dashboards.list(),
+        notifications: state.notifications.list(),
+        signature: String::new(),
+    })?;
+    let yaml = serde_yaml::to_string(&bundle).map_err(|e| BundleError::Parse(e.to_string()))?;
+
+    info!(
+        "Exported bundle with {} templates, {} groups, {} dashboards and {} notification channels",
+        bundle.templates.len(),
+        bundle.groups.len(),
+        bundle.dashboards.len(),
+        bundle.notifications.len()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/yaml"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"flowscope-bundle.yaml\""),
+        ],
+        yaml,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportParams {
+    #[serde(default)]
+    pub mode: ImportMode,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// POST /api/bundle/import?mode=merge|replace&dryRun=true - Apply a signed YAML bundle
+pub async fn import_bundle(
+    State(state): State<AppState>,
+    Query(params): Query<ImportParams>,
+    body: String,
+) -> Result<Json<ImportReport>, BundleError> {
+    let bundle: ConfigBundle = serde_yaml::from_str(&body).map_err(|e| BundleError::Parse(e.to_string()))?;
+    state.bundles.verify(&bundle)?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(BundleError::UnsupportedVersion(bundle.version));
+    }
+
+    // Validate everything before writing anything
+    for template in &bundle.templates {
+        templates::validate(template).map_err(|e| BundleError::Template(template.name.clone(), e))?;
+    }
+    for group in &bundle.groups {
+        groups::validate(group).map_err(|e| BundleError::Group(group.name.clone(), e))?;
+    }
+    for dashboard in &bundle.dashboards {
+        validate_dashboard(dashboard)?;
+    }
+    for channel in &bundle.notifications {
+        notifications::validate(channel).map_err(|e| BundleError::Notification(channel.name.clone(), e))?;
+    }
+    let incoming_templates = unique("template", bundle.templates, |t| &t.name)?;
+    let incoming_groups = unique("group", bundle.groups, |g| &g.name)?;
+    let incoming_dashboards = unique("dashboard", bundle.dashboards, |d| &d.id)?;
+    let incoming_notifications = unique("notification channel", bundle.notifications, |c| &c.name)?;
+
+    let report = ImportReport {
+        mode: params.mode,
+        dry_run: params.dry_run,
+        templates: plan(&state.templates, &incoming_templates, params.mode),
+        groups: plan(&state.groups, &incoming_groups, params.mode),
+        dashboards: plan(&state.dashboards, &incoming_dashboards, params.mode),
+        notifications: plan(&state.notifications, &incoming_notifications, params.mode),
+    };
+
+    if !params.dry_run {
+        apply(&state.templates, incoming_templates, &report.templates)?;
+        apply(&state.groups, incoming_groups, &report.groups)?;
+        apply(&state.dashboards, incoming_dashboards, &report.dashboards)?;
+        apply(&state.notifications, incoming_notifications, &report.notifications)?;
+        info!(
+            "Imported bundle exported at {} ({:?})",
+            bundle.exported_at, params.mode
+        );
+    }
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ContainerGroup;
+
+    fn signer() -> BundleSigner {
+        BundleSigner {
+            secret: Some(b"test-secret".to_vec()),
+        }
+    }
+
+    fn bundle() -> ConfigBundle {
+        ConfigBundle {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            templates: Vec::new(),
+            groups: vec![ContainerGroup {
+                name: "api".to_string(),
+                description: Some("API tier".to_string()),
+                containers: vec!["application-api-1".to_string()],
+                labels: [("tier".to_string(), "*".to_string())].into(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }],
+            dashboards: Vec::new(),
+            notifications: Vec::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn signature_survives_yaml_round_trip() {
+        let yaml = serde_yaml::to_string(&signer().sign(bundle()).unwrap()).unwrap();
+        let parsed: ConfigBundle = serde_yaml::from_str(&yaml).unwrap();
+        signer().verify(&parsed).unwrap();
+    }
+
+    #[test]
+    fn tampered_or_foreign_bundles_are_rejected() {
+        let mut signed = signer().sign(bundle()).unwrap();
+        let other = BundleSigner {
+            secret: Some(b"another-secret".to_vec()),
+        };
+        assert!(matches!(other.verify(&signed), Err(BundleError::BadSignature)));
+
+        signed.groups[0].containers.push("frontend-web".to_string());
+        assert!(matches!(signer().verify(&signed), Err(BundleError::BadSignature)));
+    }
+
+    #[test]
+    fn every_section_is_signed_and_read_back() {
+        let mut full = bundle();
+        full.dashboards.push(Dashboard {
+            id: "ops".to_string(),
+            name: "Ops".to_string(),
+            description: None,
+            widgets: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        full.notifications.push(
+            serde_json::from_value(serde_json::json!({
+                "name": "oncall",
+                "kind": "slack",
+                "target": "https://hooks.slack.com/services/T000/B000/XXXX",
+            }))
+            .unwrap(),
+        );
+        let signed = signer().sign(full).unwrap();
+        let parsed: ConfigBundle = serde_yaml::from_str(&serde_yaml::to_string(&signed).unwrap()).unwrap();
+        signer().verify(&parsed).unwrap();
+        assert_eq!((parsed.dashboards[0].id.as_str(), parsed.notifications[0].name.as_str()), ("ops", "oncall"));
+
+        // A bundle from before these sections existed still reads
+        let old: ConfigBundle = serde_yaml::from_str("version: 1\nexportedAt: 2024-01-01T00:00:00Z\n").unwrap();
+        assert!(old.dashboards.is_empty() && old.notifications.is_empty());
+
+        let mut tampered = signed;
+        tampered.notifications[0].target = "https://attacker.example/hook".to_string();
+        assert!(matches!(signer().verify(&tampered), Err(BundleError::BadSignature)));
+    }
+
+    #[test]
+    fn dashboards_without_an_id_are_rejected() {
+        let dashboard = Dashboard {
+            id: String::new(),
+            name: "Ops".to_string(),
+            description: None,
+            widgets: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        assert!(matches!(validate_dashboard(&dashboard), Err(BundleError::Dashboard(..))));
+        assert!(validate_dashboard(&Dashboard { id: "ops".to_string(), ..dashboard }).is_ok());
+    }
+}
@@ -0,0 +1,437 @@
+//! Container categorization
+//!
+//! A container's category comes from a chain of [`Categorizer`] strategies
+//! tried in order until one matches. The chain is read from
+//! `FLOWSCOPE_CATEGORIES_FILE`, the configuration's `categories_file` or
+//! `<data dir>/categories.yaml`:
+//!
+//! ```yaml
+//! strategies:
+//!   - kind: label            # flowscope.category=aiml
+//!   - kind: compose
+//!     projects: { shop: application, ledger: blockchain }
+//!   - kind: regex
+//!     rules:
+//!       - { pattern: "^ollama", category: aiml }
+//!       - { pattern: "grafana|prometheus", category: monitoring, field: image }
+//!   - kind: heuristic        # aiml-*, val-*, ...
+//! ```
+//!
+//! Without a file the chain is the category label followed by the name-prefix
+//! heuristics. A container no strategy matches is `other`.
+//! `GET /api/container/:id/category/explain` shows how a container's category
+//! was decided.
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use regex::Regex;
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{CategoryExplanation, CategoryStep, CategoryStrategyKind, ContainerInfo, ServiceCategory},
+    projects::PROJECT_LABEL,
+    AppState,
+};
+
+/// Label naming a container's category explicitly, unless configured otherwise
+pub const CATEGORY_LABEL: &str = "flowscope.category";
+
+/// What strategies look at to categorize a container
+#[derive(Debug, Clone, Copy)]
+pub struct Subject<'a> {
+    pub name: &'a str,
+    pub image: &'a str,
+    pub labels: &'a HashMap<String, String>,
+}
+
+impl<'a> From<&'a ContainerInfo> for Subject<'a> {
+    fn from(container: &'a ContainerInfo) -> Self {
+        Self {
+            name: &container.name,
+            image: &container.image,
+            labels: &container.labels,
+        }
+    }
+}
+
+/// What a strategy made of a container, with the reason
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Matched(ServiceCategory, String),
+    Skipped(String),
+}
+
+/// One way of deciding a container's category
+pub trait Categorizer: Send + Sync {
+    fn kind(&self) -> CategoryStrategyKind;
+    fn categorize(&self, subject: Subject<'_>) -> Verdict;
+}
+
+/// A category from its JSON name, e.g. `aiml` or `infrastructure`
+fn parse_category(value: &str) -> Option<ServiceCategory> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+}
+
+/// The category a label names
+pub struct LabelCategorizer {
+    pub label: String,
+}
+
+impl Categorizer for LabelCategorizer {
+    fn kind(&self) -> CategoryStrategyKind {
+        CategoryStrategyKind::Label
+    }
+
+    fn categorize(&self, subject: Subject<'_>) -> Verdict {
+        match subject.labels.get(&self.label) {
+            None => Verdict::Skipped(format!("no {} label", self.label)),
+            Some(value) => match parse_category(value) {
+                Some(category) => Verdict::Matched(category, format!("label {}={}", self.label, value)),
+                None => Verdict::Skipped(format!("label {}={} is not a category", self.label, value)),
+            },
+        }
+    }
+}
+
+/// The category configured for the container's compose project
+pub struct ComposeCategorizer {
+    pub projects: BTreeMap<String, ServiceCategory>,
+}
+
+impl Categorizer for ComposeCategorizer {
+    fn kind(&self) -> CategoryStrategyKind {
+        CategoryStrategyKind::Compose
+    }
+
+    fn categorize(&self, subject: Subject<'_>) -> Verdict {
+        let Some(project) = subject.labels.get(PROJECT_LABEL) else {
+            return Verdict::Skipped("not started by compose".to_string());
+        };
+        match self.projects.get(project) {
+            Some(category) => Verdict::Matched(category.clone(), format!("compose project {}", project)),
+            None => Verdict::Skipped(format!("no category configured for compose project {}", project)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegexField {
+    #[default]
+    Name,
+    Image,
+}
+
+pub struct RegexRule {
+    pub pattern: Regex,
+    pub category: ServiceCategory,
+    pub field: RegexField,
+}
+
+/// The category of the first rule whose pattern matches
+pub struct RegexCategorizer {
+    pub rules: Vec<RegexRule>,
+}
+
+impl Categorizer for RegexCategorizer {
+    fn kind(&self) -> CategoryStrategyKind {
+        CategoryStrategyKind::Regex
+    }
+
+    fn categorize(&self, subject: Subject<'_>) -> Verdict {
+        for rule in &self.rules {
+            let (field, value) = match rule.field {
+                RegexField::Name => ("name", subject.name),
+                RegexField::Image => ("image", subject.image),
+            };
+            if rule.pattern.is_match(value) {
+                return Verdict::Matched(rule.category.clone(), format!("{} matches /{}/", field, rule.pattern));
+            }
+        }
+        Verdict::Skipped(format!("none of {} rules matched", self.rules.len()))
+    }
+}
+
+/// Well-known name prefixes such as `aiml-` or `monitoring-`
+pub struct HeuristicCategorizer;
+
+impl Categorizer for HeuristicCategorizer {
+    fn kind(&self) -> CategoryStrategyKind {
+        CategoryStrategyKind::Heuristic
+    }
+
+    fn categorize(&self, subject: Subject<'_>) -> Verdict {
+        match ServiceCategory::from_name(subject.name) {
+            ServiceCategory::Other => Verdict::Skipped("no known name prefix".to_string()),
+            category => Verdict::Matched(category, "name prefix".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CategoriesConfigError {
+    #[error("failed to read {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("failed to parse {0}: {1}")]
+    Parse(String, serde_yaml::Error),
+    #[error("regex rule /{0}/: {1}")]
+    Pattern(String, regex::Error),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum StrategyEntry {
+    Label {
+        #[serde(default = "default_label")]
+        label: String,
+    },
+    Compose {
+        #[serde(default)]
+        projects: BTreeMap<String, ServiceCategory>,
+    },
+    Regex {
+        #[serde(default)]
+        rules: Vec<RegexEntry>,
+    },
+    Heuristic,
+}
+
+fn default_label() -> String {
+    CATEGORY_LABEL.to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct RegexEntry {
+    pattern: String,
+    category: ServiceCategory,
+    #[serde(default)]
+    field: RegexField,
+}
+
+#[derive(Debug, Deserialize)]
+struct CategoriesFile {
+    strategies: Vec<StrategyEntry>,
+}
+
+/// Strategies in the order they are tried
+pub struct Categorizers {
+    strategies: Vec<Box<dyn Categorizer>>,
+}
+
+impl Default for Categorizers {
+    /// The category label, then the name-prefix heuristics
+    fn default() -> Self {
+        Self::new(vec![
+            Box::new(LabelCategorizer { label: default_label() }),
+            Box::new(HeuristicCategorizer),
+        ])
+    }
+}
+
+impl Categorizers {
+    pub fn new(strategies: Vec<Box<dyn Categorizer>>) -> Self {
+        Self { strategies }
+    }
+
+    /// Load the chain from `path`, the configured `categories_file` (see
+    /// [`crate::config`]), using the default chain when there is no file. An
+    /// unreadable file is an error rather than silently regrouping every
+    /// container.
+    pub fn open(path: &std::path::Path) -> Result<Self, CategoriesConfigError> {
+        let shown = path.display().to_string();
+
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(CategoriesConfigError::Read(shown, e)),
+        };
+        let chain = Self::parse(&text).map_err(|e| match e {
+            CategoriesConfigError::Parse(_, e) => CategoriesConfigError::Parse(shown.clone(), e),
+            e => e,
+        })?;
+        if chain.strategies.is_empty() {
+            warn!("{} lists no strategies, every container will be 'other'", shown);
+        }
+        info!("Loaded {} categorization strategies from {}", chain.strategies.len(), shown);
+        Ok(chain)
+    }
+
+    fn parse(text: &str) -> Result<Self, CategoriesConfigError> {
+        let file: CategoriesFile =
+            serde_yaml::from_str(text).map_err(|e| CategoriesConfigError::Parse(String::new(), e))?;
+        let strategies = file
+            .strategies
+            .into_iter()
+            .map(|entry| -> Result<Box<dyn Categorizer>, CategoriesConfigError> {
+                Ok(match entry {
+                    StrategyEntry::Label { label } => Box::new(LabelCategorizer { label }),
+                    StrategyEntry::Compose { projects } => Box::new(ComposeCategorizer { projects }),
+                    StrategyEntry::Regex { rules } => Box::new(RegexCategorizer {
+                        rules: rules
+                            .into_iter()
+                            .map(|rule| {
+                                Ok(RegexRule {
+                                    pattern: Regex::new(&rule.pattern)
+                                        .map_err(|e| CategoriesConfigError::Pattern(rule.pattern.clone(), e))?,
+                                    category: rule.category,
+                                    field: rule.field,
+                                })
+                            })
+                            .collect::<Result<_, CategoriesConfigError>>()?,
+                    }),
+                    StrategyEntry::Heuristic => Box::new(HeuristicCategorizer),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(strategies))
+    }
+
+    /// The category of the first strategy that matches, `other` if none does
+    pub fn categorize(&self, subject: Subject<'_>) -> ServiceCategory {
+        self.strategies
+            .iter()
+            .find_map(|s| match s.categorize(subject) {
+                Verdict::Matched(category, _) => Some(category),
+                Verdict::Skipped(_) => None,
+            })
+            .unwrap_or(ServiceCategory::Other)
+    }
+
+    /// Every strategy tried for `container`, up to the one that matched
+    pub fn explain(&self, container: &ContainerInfo) -> CategoryExplanation {
+        let mut steps = Vec::new();
+        let mut decided = None;
+        for strategy in &self.strategies {
+            let (category, detail) = match strategy.categorize(container.into()) {
+                Verdict::Matched(category, detail) => (Some(category), detail),
+                Verdict::Skipped(detail) => (None, detail),
+            };
+            steps.push(CategoryStep {
+                strategy: strategy.kind(),
+                category: category.clone(),
+                detail,
+            });
+            if let Some(category) = category {
+                decided = Some((category, strategy.kind()));
+                break;
+            }
+        }
+        let (category, strategy) = match decided {
+            Some((category, strategy)) => (category, Some(strategy)),
+            None => (ServiceCategory::Other, None),
+        };
+        CategoryExplanation {
+            container: container.name.clone(),
+            category,
+            strategy,
+            steps,
+        }
+    }
+}
+
+/// GET /api/container/:id/category/explain - Which strategy decided the
+/// container's category, and what the ones before it made of it
+pub async fn explain_category(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.docker.get_container(&id).await {
+        Ok(Some(container)) => (StatusCode::OK, Json(state.docker.categorizers().explain(&container))).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to get container '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to get container",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake;
+
+    fn container(name: &str, labels: &[(&str, &str)]) -> ContainerInfo {
+        ContainerInfo {
+            id: "0123456789ab".to_string(),
+            image: "ghcr.io/ollama/ollama:latest".to_string(),
+            category: ServiceCategory::Other,
+            labels: fake::labels(labels),
+            ..fake::container(name)
+        }
+    }
+
+    const CONFIG: &str = r#"
+strategies:
+  - kind: label
+  - kind: compose
+    projects: { shop: application }
+  - kind: regex
+    rules:
+      - { pattern: "^ollama/", category: aiml, field: image }
+      - { pattern: "ollama", category: aiml, field: image }
+  - kind: heuristic
+"#;
+
+    #[test]
+    fn first_matching_strategy_decides() {
+        let chain = Categorizers::parse(CONFIG).unwrap();
+        let categorize = |c: &ContainerInfo| chain.categorize(c.into());
+
+        let labelled = container("shop-api-1", &[(CATEGORY_LABEL, "monitoring"), (PROJECT_LABEL, "shop")]);
+        assert_eq!(categorize(&labelled), ServiceCategory::Monitoring);
+        let composed = container("shop-api-1", &[(CATEGORY_LABEL, "bogus"), (PROJECT_LABEL, "shop")]);
+        assert_eq!(categorize(&composed), ServiceCategory::Application);
+        assert_eq!(categorize(&container("llm", &[])), ServiceCategory::Aiml);
+        assert_eq!(Categorizers::default().categorize((&container("llm", &[])).into()), ServiceCategory::Other);
+    }
+
+    #[test]
+    fn explanations_list_the_strategies_tried() {
+        let chain = Categorizers::parse(CONFIG).unwrap();
+        let explained = chain.explain(&container("llm", &[(PROJECT_LABEL, "lab")]));
+        assert_eq!(explained.category, ServiceCategory::Aiml);
+        assert_eq!(explained.strategy, Some(CategoryStrategyKind::Regex));
+        let details: Vec<&str> = explained.steps.iter().map(|s| s.detail.as_str()).collect();
+        assert_eq!(
+            details,
+            [
+                "no flowscope.category label",
+                "no category configured for compose project lab",
+                "image matches /ollama/"
+            ]
+        );
+
+        let unmatched = Categorizers::new(Vec::new()).explain(&container("llm", &[]));
+        assert_eq!((unmatched.category, unmatched.strategy), (ServiceCategory::Other, None));
+    }
+
+    #[test]
+    fn invalid_config_is_an_error() {
+        assert!(matches!(
+            Categorizers::parse("strategies:\n  - kind: regex\n    rules: [{ pattern: \"(\", category: aiml }]"),
+            Err(CategoriesConfigError::Pattern(..))
+        ));
+        assert!(matches!(
+            Categorizers::parse("strategies:\n  - kind: compose\n    projects: { shop: nonsense }"),
+            Err(CategoriesConfigError::Parse(..))
+        ));
+    }
+}
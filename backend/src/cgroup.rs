@@ -0,0 +1,430 @@
+//! cgroup v1/v2 stats normalisation
+//!
+//! Docker passes container stats through from the cgroup hierarchy, so their
+//! shape depends on the host: v1 hosts count page cache in `usage` and report
+//! `total_inactive_file`, v2 hosts report `inactive_file` instead, and older
+//! daemons leave out `online_cpus`. Windows containers have no cgroups at all
+//! and report a private working set, 100ns CPU intervals and storage counters
+//! instead. Everything here turns a raw sample into [`ContainerStats`] the way
+//! `docker stats` reads it, whichever the host runs.
+//!
+//! Network and block I/O counters are cumulative; [`IoRateTracker`] turns
+//! consecutive samples into per-second rates.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use bollard::container::{MemoryStatsStats, Stats};
+use chrono::{DateTime, Utc};
+
+use crate::models::ContainerStats;
+
+/// Limits at or above this are the kernel's "unlimited" sentinel: v1 reports
+/// `0x7FFFFFFFFFFFF000`, v2 `max` surfaces as `u64::MAX`
+const UNLIMITED: u64 = 1 << 62;
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Only Windows daemons set `num_procs`
+pub fn is_windows(stats: &Stats) -> bool {
+    stats.num_procs > 0
+}
+
+/// Memory in use minus reclaimable inactive page cache, as `docker stats` shows it;
+/// the private working set on Windows
+pub fn memory_usage(stats: &Stats) -> u64 {
+    if is_windows(stats) {
+        return stats.memory_stats.privateworkingset.unwrap_or(0);
+    }
+    let usage = stats.memory_stats.usage.unwrap_or(0);
+    let inactive = match &stats.memory_stats.stats {
+        Some(MemoryStatsStats::V1(v1)) if v1.total_inactive_file < usage => v1.total_inactive_file,
+        Some(MemoryStatsStats::V1(v1)) => v1.inactive_file,
+        Some(MemoryStatsStats::V2(v2)) => v2.inactive_file,
+        None => 0,
+    };
+    if inactive < usage {
+        usage - inactive
+    } else {
+        usage
+    }
+}
+
+fn limited(value: u64) -> Option<u64> {
+    (value > 0 && value < UNLIMITED).then_some(value)
+}
+
+/// Memory limit in bytes, or `None` when the container is unconstrained
+pub fn memory_limit(stats: &Stats) -> Option<u64> {
+    let hierarchical = match &stats.memory_stats.stats {
+        Some(MemoryStatsStats::V1(v1)) => Some(v1.hierarchical_memory_limit),
+        _ => None,
+    };
+    stats
+        .memory_stats
+        .limit
+        .and_then(limited)
+        .or(hierarchical.and_then(limited))
+}
+
+/// CPUs the container may use; v1 daemons before API 1.27 only report per-CPU usage
+pub fn online_cpus(stats: &Stats) -> u64 {
+    stats
+        .cpu_stats
+        .online_cpus
+        .filter(|&n| n > 0)
+        .or_else(|| {
+            stats
+                .cpu_stats
+                .cpu_usage
+                .percpu_usage
+                .as_ref()
+                .map(|per_cpu| per_cpu.len() as u64)
+                .filter(|&n| n > 0)
+        })
+        .unwrap_or(1)
+}
+
+/// CPU usage since the previous sample, 100% per core
+pub fn cpu_percent(stats: &Stats) -> f64 {
+    if is_windows(stats) {
+        return windows_cpu_percent(stats);
+    }
+    let cpu_delta = stats
+        .cpu_stats
+        .cpu_usage
+        .total_usage
+        .saturating_sub(stats.precpu_stats.cpu_usage.total_usage);
+    let system_delta = stats
+        .cpu_stats
+        .system_cpu_usage
+        .unwrap_or(0)
+        .saturating_sub(stats.precpu_stats.system_cpu_usage.unwrap_or(0));
+
+    if system_delta > 0 && cpu_delta > 0 {
+        (cpu_delta as f64 / system_delta as f64) * online_cpus(stats) as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Windows counts CPU time in 100ns intervals across `num_procs` processors,
+/// so usage is measured against the wall-clock time between samples
+fn windows_cpu_percent(stats: &Stats) -> f64 {
+    let elapsed = match (
+        chrono::DateTime::parse_from_rfc3339(&stats.read),
+        chrono::DateTime::parse_from_rfc3339(&stats.preread),
+    ) {
+        (Ok(read), Ok(preread)) => (read - preread).num_nanoseconds().unwrap_or(0),
+        _ => 0,
+    };
+    let possible = (elapsed.max(0) as u64 / 100) * stats.num_procs as u64;
+    let used = stats
+        .cpu_stats
+        .cpu_usage
+        .total_usage
+        .saturating_sub(stats.precpu_stats.cpu_usage.total_usage);
+
+    if possible > 0 {
+        used as f64 / possible as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Bytes read and written; v1 names the ops `Read`/`Write`, v2 `read`/`write`,
+/// and Windows reports storage counters instead
+pub fn block_io(stats: &Stats) -> (u64, u64) {
+    if is_windows(stats) {
+        return (
+            stats.storage_stats.read_size_bytes.unwrap_or(0),
+            stats.storage_stats.write_size_bytes.unwrap_or(0),
+        );
+    }
+    stats
+        .blkio_stats
+        .io_service_bytes_recursive
+        .as_ref()
+        .map(|io| {
+            io.iter().fold((0u64, 0u64), |(r, w), entry| {
+                if entry.op.eq_ignore_ascii_case("read") {
+                    (r + entry.value, w)
+                } else if entry.op.eq_ignore_ascii_case("write") {
+                    (r, w + entry.value)
+                } else {
+                    (r, w)
+                }
+            })
+        })
+        .unwrap_or((0, 0))
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Bytes received and sent, summed across interfaces
+pub fn network_io(stats: &Stats) -> (u64, u64) {
+    stats
+        .networks
+        .as_ref()
+        .map(|nets| {
+            nets.values()
+                .fold((0u64, 0u64), |(rx, tx), net| (rx + net.rx_bytes, tx + net.tx_bytes))
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Packets dropped on receive and send, summed across interfaces
+fn network_drops(stats: &Stats) -> (u64, u64) {
+    stats
+        .networks
+        .as_ref()
+        .map(|nets| {
+            nets.values()
+                .fold((0u64, 0u64), |(rx, tx), net| (rx + net.rx_dropped, tx + net.tx_dropped))
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Cumulative I/O counters of one sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IoCounters {
+    pub at: DateTime<Utc>,
+    pub network_rx: u64,
+    pub network_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+    pub network_rx_dropped: u64,
+    pub network_tx_dropped: u64,
+}
+
+impl IoCounters {
+    /// Counters of a sample; `None` when it carries no read time
+    pub fn of(stats: &Stats) -> Option<Self> {
+        let at = DateTime::parse_from_rfc3339(&stats.read).ok()?.with_timezone(&Utc);
+        let (network_rx, network_tx) = network_io(stats);
+        let (block_read, block_write) = block_io(stats);
+        let (network_rx_dropped, network_tx_dropped) = network_drops(stats);
+        Some(Self {
+            at,
+            network_rx,
+            network_tx,
+            block_read,
+            block_write,
+            network_rx_dropped,
+            network_tx_dropped,
+        })
+    }
+
+    fn counters(&self) -> [u64; 6] {
+        [
+            self.network_rx,
+            self.network_tx,
+            self.block_read,
+            self.block_write,
+            self.network_rx_dropped,
+            self.network_tx_dropped,
+        ]
+    }
+}
+
+/// Bytes, and dropped packets, per second between two samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IoRates {
+    pub network_rx: f64,
+    pub network_tx: f64,
+    pub block_read: f64,
+    pub block_write: f64,
+    pub network_rx_dropped: f64,
+    pub network_tx_dropped: f64,
+}
+
+impl IoRates {
+    /// Rates from `previous` to `current`; `None` when no time passed or a
+    /// counter went backwards, which means the container restarted
+    pub fn between(previous: &IoCounters, current: &IoCounters) -> Option<Self> {
+        let elapsed = (current.at - previous.at).num_milliseconds() as f64 / 1000.0;
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let (before, after) = (previous.counters(), current.counters());
+        if before.iter().zip(&after).any(|(b, a)| a < b) {
+            return None;
+        }
+        let rate = |i: usize| round2((after[i] - before[i]) as f64 / elapsed);
+        Some(Self {
+            network_rx: rate(0),
+            network_tx: rate(1),
+            block_read: rate(2),
+            block_write: rate(3),
+            network_rx_dropped: rate(4),
+            network_tx_dropped: rate(5),
+        })
+    }
+
+    pub fn apply(&self, stats: &mut ContainerStats) {
+        stats.network_rx_bytes_per_sec = Some(self.network_rx);
+        stats.network_tx_bytes_per_sec = Some(self.network_tx);
+        stats.block_read_bytes_per_sec = Some(self.block_read);
+        stats.block_write_bytes_per_sec = Some(self.block_write);
+        stats.network_rx_dropped_per_sec = Some(self.network_rx_dropped);
+        stats.network_tx_dropped_per_sec = Some(self.network_tx_dropped);
+    }
+}
+
+/// Samples closer together than this reuse the previous rates, so callers
+/// polling at once don't turn each other's windows into noise
+pub const MIN_RATE_WINDOW: chrono::Duration = chrono::Duration::seconds(1);
+/// Containers not sampled for this long are forgotten
+const FORGET_AFTER: chrono::Duration = chrono::Duration::minutes(10);
+
+/// Last counters and rates of every container sampled
+#[derive(Debug, Default)]
+pub struct IoRateTracker {
+    samples: Mutex<HashMap<String, (IoCounters, Option<IoRates>)>>,
+}
+
+impl IoRateTracker {
+    /// Record a sample of `container` and return its rates over the window
+    /// since the previous one
+    pub fn observe(&self, container: &str, current: IoCounters) -> Option<IoRates> {
+        let mut samples = self.samples.lock().unwrap();
+        samples.retain(|_, (counters, _)| current.at - counters.at < FORGET_AFTER);
+        if let Some((previous, rates)) = samples.get(container) {
+            let elapsed = current.at - previous.at;
+            if elapsed >= chrono::Duration::zero() && elapsed < MIN_RATE_WINDOW {
+                return *rates;
+            }
+        }
+        let rates = samples
+            .get(container)
+            .and_then(|(previous, _)| IoRates::between(previous, &current));
+        samples.insert(container.to_string(), (current, rates));
+        rates
+    }
+}
+
+/// Normalise one stats sample
+pub fn container_stats(stats: &Stats) -> ContainerStats {
+    let memory_usage = memory_usage(stats) as f64 / MIB;
+    let memory_limit = memory_limit(stats).map_or(0.0, |limit| limit as f64 / MIB);
+    let memory_percent = if memory_limit > 0.0 {
+        (memory_usage / memory_limit) * 100.0
+    } else {
+        0.0
+    };
+
+    let networks = stats.networks.as_ref();
+    let (network_rx, network_tx) = network_io(stats);
+    // Packet errors and drops, summed across interfaces
+    let (rx_errors, tx_errors, rx_dropped, tx_dropped) = networks
+        .map(|nets| {
+            nets.values().fold((0u64, 0u64, 0u64, 0u64), |(re, te, rd, td), net| {
+                (re + net.rx_errors, te + net.tx_errors, rd + net.rx_dropped, td + net.tx_dropped)
+            })
+        })
+        .unwrap_or((0, 0, 0, 0));
+    let (block_read, block_write) = block_io(stats);
+
+    ContainerStats {
+        cpu_percent: round2(cpu_percent(stats)),
+        memory_usage_mb: round2(memory_usage),
+        memory_limit_mb: round2(memory_limit),
+        memory_percent: round2(memory_percent),
+        network_rx_mb: round2(network_rx as f64 / MIB),
+        network_tx_mb: round2(network_tx as f64 / MIB),
+        network_rx_errors: rx_errors,
+        network_tx_errors: tx_errors,
+        network_rx_dropped: rx_dropped,
+        network_tx_dropped: tx_dropped,
+        block_read_mb: round2(block_read as f64 / MIB),
+        block_write_mb: round2(block_write as f64 / MIB),
+        pids: stats.pids_stats.current.unwrap_or(0),
+        pids_limit: stats.pids_stats.limit.and_then(limited),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded(name: &str) -> Stats {
+        let path = format!("{}/tests/fixtures/stats/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        serde_json::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn cgroup_v1_sample() {
+        let stats = recorded("cgroup_v1");
+        assert!(matches!(stats.memory_stats.stats, Some(MemoryStatsStats::V1(_))));
+
+        let normalised = container_stats(&stats);
+        // 600 MiB usage of which 200 MiB is inactive page cache
+        assert_eq!(normalised.memory_usage_mb, 400.0);
+        // Unlimited sentinel on both the limit and the hierarchical limit
+        assert_eq!(normalised.memory_limit_mb, 0.0);
+        assert_eq!(normalised.memory_percent, 0.0);
+        // No online_cpus: two per-CPU counters, 0.5s of 2s system time
+        assert_eq!(online_cpus(&stats), 2);
+        assert_eq!(normalised.cpu_percent, 50.0);
+        assert_eq!((normalised.block_read_mb, normalised.block_write_mb), (8.0, 3.0));
+        assert_eq!(normalised.pids_limit, None);
+    }
+
+    #[test]
+    fn cgroup_v2_sample() {
+        let stats = recorded("cgroup_v2");
+        assert!(matches!(stats.memory_stats.stats, Some(MemoryStatsStats::V2(_))));
+
+        let normalised = container_stats(&stats);
+        // 300 MiB usage of which 44 MiB is inactive page cache
+        assert_eq!(normalised.memory_usage_mb, 256.0);
+        assert_eq!(normalised.memory_limit_mb, 512.0);
+        assert_eq!(normalised.memory_percent, 50.0);
+        assert_eq!(normalised.cpu_percent, 100.0);
+        assert_eq!((normalised.block_read_mb, normalised.block_write_mb), (6.0, 1.0));
+        // pids.max of "max" is reported as u64::MAX
+        assert_eq!(normalised.pids, 15);
+        assert_eq!(normalised.pids_limit, None);
+    }
+
+    #[test]
+    fn windows_sample() {
+        let stats = recorded("windows");
+        assert!(is_windows(&stats) && stats.memory_stats.stats.is_none());
+
+        let normalised = container_stats(&stats);
+        assert_eq!(normalised.memory_usage_mb, 180.0);
+        assert_eq!(normalised.memory_limit_mb, 0.0);
+        // 1s of CPU time in 100ns intervals over 1s across 4 processors
+        assert_eq!(normalised.cpu_percent, 25.0);
+        assert_eq!((normalised.block_read_mb, normalised.block_write_mb), (12.0, 4.0));
+    }
+
+    #[test]
+    fn io_rates_over_sampling_windows() {
+        let start = Utc::now();
+        let sample = |seconds: i64, bytes: u64| IoCounters {
+            at: start + chrono::Duration::seconds(seconds),
+            network_rx: bytes,
+            network_tx: bytes / 2,
+            block_read: bytes * 2,
+            block_write: 0,
+            network_rx_dropped: bytes / 100,
+            network_tx_dropped: 0,
+        };
+        let tracker = IoRateTracker::default();
+
+        assert_eq!(tracker.observe("api", sample(0, 1000)), None);
+        let rates = tracker.observe("api", sample(5, 6000)).unwrap();
+        assert_eq!((rates.network_rx, rates.network_tx, rates.block_read), (1000.0, 500.0, 2000.0));
+        assert_eq!((rates.network_rx_dropped, rates.network_tx_dropped), (10.0, 0.0));
+        // A second caller within the window sees the same rates
+        assert_eq!(tracker.observe("api", sample(5, 7000)), Some(rates));
+        // Counters reset by a restart give no rate, then count from the restart
+        assert_eq!(tracker.observe("api", sample(10, 100)), None);
+        assert_eq!(tracker.observe("api", sample(12, 300)).unwrap().network_rx, 100.0);
+    }
+}
@@ -0,0 +1,257 @@
+//! Per-container change times
+//!
+//! Every container listing carries when the container last started, last
+//! changed status, last changed image and last changed configuration, so
+//! clients can sort by "what changed most recently" without fetching the
+//! event history. Times are kept by container name, which survives a compose
+//! service being recreated, and come from two sources: daemon events, which
+//! the event collector feeds in as they arrive (and backfills on startup),
+//! and successive container listings, which catch recreations with another
+//! image or configuration and any status change whose event was missed.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+};
+
+use bollard::models::ContainerSummary;
+use chrono::{DateTime, Utc};
+
+use crate::models::{ContainerInfo, ContainerStatus, DockerEvent};
+
+/// Container event actions that change a container's status
+const STATUS_ACTIONS: &[&str] = &[
+    "start", "restart", "die", "stop", "kill", "pause", "unpause", "oom", "health_status",
+];
+/// Container event actions that change a container's configuration in place
+const CONFIG_ACTIONS: &[&str] = &["rename", "update"];
+
+/// What a listing showed of a container
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    id: String,
+    image_id: String,
+    fingerprint: u64,
+    status: ContainerStatus,
+    at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeTimes {
+    pub last_started: Option<DateTime<Utc>>,
+    pub last_status_change: Option<DateTime<Utc>>,
+    pub last_image_change: Option<DateTime<Utc>>,
+    pub last_config_change: Option<DateTime<Utc>>,
+}
+
+impl ChangeTimes {
+    /// Copy the times onto a listed container
+    pub fn apply(self, info: &mut ContainerInfo) {
+        info.last_started = self.last_started;
+        info.last_status_change = self.last_status_change;
+        info.last_image_change = self.last_image_change;
+        info.last_config_change = self.last_config_change;
+    }
+}
+
+/// Keep the later of a recorded time and a new one
+fn bump(slot: &mut Option<DateTime<Utc>>, at: DateTime<Utc>) {
+    if slot.is_none_or(|current| current < at) {
+        *slot = Some(at);
+    }
+}
+
+/// Hash of what `docker update`, `docker rename` or a recreation can change
+/// and a listing shows: command, labels, published ports, networks and mounts
+pub fn fingerprint(summary: &ContainerSummary) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    summary.command.hash(&mut hasher);
+    let labels: BTreeMap<_, _> = summary.labels.iter().flatten().collect();
+    labels.hash(&mut hasher);
+    let mut ports: Vec<String> = summary
+        .ports
+        .iter()
+        .flatten()
+        .map(|p| format!("{:?}:{:?}->{}/{:?}", p.ip, p.public_port, p.private_port, p.typ))
+        .collect();
+    ports.sort();
+    ports.hash(&mut hasher);
+    let networks: Vec<&String> = summary
+        .network_settings
+        .as_ref()
+        .and_then(|n| n.networks.as_ref())
+        .map(|n| {
+            let mut names: Vec<&String> = n.keys().collect();
+            names.sort();
+            names
+        })
+        .unwrap_or_default();
+    networks.hash(&mut hasher);
+    let mut mounts: Vec<(Option<&String>, Option<&String>)> = summary
+        .mounts
+        .iter()
+        .flatten()
+        .map(|m| (m.source.as_ref(), m.destination.as_ref()))
+        .collect();
+    mounts.sort();
+    mounts.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    times: ChangeTimes,
+    snapshot: Option<Snapshot>,
+}
+
+/// Change times of every container seen, by name
+#[derive(Debug, Default)]
+pub struct ChangeTracker {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ChangeTracker {
+    /// Record a daemon event; anything but a container event is ignored
+    pub fn observe_event(&self, event: &DockerEvent) {
+        if event.event_type != "container" {
+            return;
+        }
+        let Some(name) = &event.actor.name else {
+            return;
+        };
+        let action = event.action.as_str();
+        let mut entries = self.entries.lock().unwrap();
+        let times = &mut entries.entry(name.clone()).or_default().times;
+        if action == "start" {
+            bump(&mut times.last_started, event.time);
+        }
+        if STATUS_ACTIONS.contains(&action) {
+            bump(&mut times.last_status_change, event.time);
+        }
+        if CONFIG_ACTIONS.contains(&action) {
+            bump(&mut times.last_config_change, event.time);
+        }
+    }
+
+    /// Compare a listed container with the previous listing, then return its
+    /// change times. The first listing of a name dates its image and
+    /// configuration to the container's creation.
+    pub fn observe(&self, container: &ContainerInfo, image_id: &str, fingerprint: u64, at: DateTime<Utc>) -> ChangeTimes {
+        let current = Snapshot {
+            id: container.id.clone(),
+            image_id: image_id.to_string(),
+            fingerprint,
+            status: container.status.clone(),
+            at,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entry(container.name.clone()).or_default();
+        let times = &mut entry.times;
+        match &entry.snapshot {
+            None => {
+                bump(&mut times.last_image_change, container.created);
+                bump(&mut times.last_config_change, container.created);
+            }
+            Some(previous) => {
+                if previous.id != current.id && previous.image_id != current.image_id {
+                    bump(&mut times.last_image_change, container.created);
+                }
+                if previous.id != current.id && previous.fingerprint != current.fingerprint {
+                    bump(&mut times.last_config_change, container.created);
+                }
+                // In place changes, unless an event already dated them
+                if previous.fingerprint != current.fingerprint
+                    && times.last_config_change.is_none_or(|t| t <= previous.at)
+                {
+                    bump(&mut times.last_config_change, at);
+                }
+                if previous.status != current.status && times.last_status_change.is_none_or(|t| t <= previous.at) {
+                    bump(&mut times.last_status_change, at);
+                    let was_running = is_running(&previous.status);
+                    if !was_running && is_running(&current.status) {
+                        bump(&mut times.last_started, at);
+                    }
+                }
+            }
+        }
+        entry.snapshot = Some(current);
+        entry.times.clone()
+    }
+}
+
+fn is_running(status: &ContainerStatus) -> bool {
+    matches!(
+        status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{docker_api::fake, models::EventActor};
+
+    fn container(id: &str, status: ContainerStatus, created: DateTime<Utc>) -> ContainerInfo {
+        ContainerInfo {
+            id: id.to_string(),
+            status,
+            created,
+            ..fake::container("shop-api-1")
+        }
+    }
+
+    fn event(action: &str, time: DateTime<Utc>) -> DockerEvent {
+        DockerEvent {
+            time,
+            event_type: "container".to_string(),
+            action: action.to_string(),
+            detail: None,
+            actor: EventActor {
+                id: "a1".to_string(),
+                name: Some("shop-api-1".to_string()),
+            },
+            attributes: BTreeMap::new(),
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn listings_date_recreations_and_missed_transitions() {
+        let tracker = ChangeTracker::default();
+        let created = Utc::now() - chrono::Duration::hours(2);
+        let t = |minutes| created + chrono::Duration::minutes(minutes);
+
+        let first = tracker.observe(&container("a1", ContainerStatus::Running, created), "sha256:1", 7, t(60));
+        assert_eq!(first.last_image_change, Some(created));
+        assert_eq!(first.last_config_change, Some(created));
+        assert_eq!(first.last_started, None);
+
+        let stopped = tracker.observe(&container("a1", ContainerStatus::Exited, created), "sha256:1", 7, t(61));
+        assert_eq!(stopped.last_status_change, Some(t(61)));
+
+        // Recreated on a new image with the same configuration
+        let recreated = tracker.observe(&container("b2", ContainerStatus::Running, t(62)), "sha256:2", 7, t(63));
+        assert_eq!(recreated.last_image_change, Some(t(62)));
+        assert_eq!(recreated.last_config_change, Some(created));
+        assert_eq!(recreated.last_started, Some(t(63)));
+    }
+
+    #[test]
+    fn events_take_precedence_over_listings() {
+        let tracker = ChangeTracker::default();
+        let created = Utc::now() - chrono::Duration::hours(1);
+        let t = |minutes| created + chrono::Duration::minutes(minutes);
+
+        tracker.observe(&container("a1", ContainerStatus::Exited, created), "sha256:1", 7, t(10));
+        tracker.observe_event(&event("start", t(11)));
+        tracker.observe_event(&event("exec_start", t(12)));
+        let times = tracker.observe(&container("a1", ContainerStatus::Running, created), "sha256:1", 7, t(15));
+        assert_eq!(times.last_started, Some(t(11)));
+        assert_eq!(times.last_status_change, Some(t(11)));
+
+        tracker.observe_event(&event("rename", t(20)));
+        let times = tracker.observe(&container("a1", ContainerStatus::Running, created), "sha256:1", 7, t(25));
+        assert_eq!(times.last_config_change, Some(t(20)));
+    }
+}
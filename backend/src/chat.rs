@@ -0,0 +1,265 @@
+//! Slack and Discord notifiers
+//!
+//! Sinks for chat incoming webhooks. A channel without a template gets a
+//! formatted message: Slack [blocks] with a header, the container's details
+//! as fields and a context line, or a Discord [embed] coloured by severity.
+//! A channel with a template is rendered from it like any webhook, so teams
+//! can still shape the message themselves.
+//!
+//! [blocks]: https://api.slack.com/block-kit
+//! [embed]: https://discord.com/developers/docs/resources/message#embed-object
+
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+
+use crate::{
+    delivery::{NotificationSink, SinkError, WebhookSink},
+    models::{NotificationChannel, NotificationKind, RenderedNotification, Severity},
+    notifications::{render, NotificationContext, NotificationError},
+};
+
+/// Slack caps header text at 150 characters
+const SLACK_HEADER_LIMIT: usize = 150;
+/// Discord caps embed titles at 256 characters
+const DISCORD_TITLE_LIMIT: usize = 256;
+
+/// `text` in at most `limit` characters, ending in `…` when it had to be cut
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    match text.char_indices().nth(limit.saturating_sub(1)) {
+        Some((end, _)) if limit > 0 => format!("{}…", &text[..end]),
+        _ => String::new(),
+    }
+}
+
+fn emoji(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => ":large_green_circle:",
+        Severity::Warning => ":large_orange_circle:",
+        Severity::Error => ":red_circle:",
+    }
+}
+
+/// Embed colour per severity, as Discord's 24-bit integer
+fn colour(severity: Severity) -> u32 {
+    match severity {
+        Severity::Info => 0x16a34a,
+        Severity::Warning => 0xd97706,
+        Severity::Error => 0xdc2626,
+    }
+}
+
+/// Name and value of each detail shown under the title
+fn details(context: &NotificationContext) -> Vec<(&'static str, String)> {
+    let container = &context.container;
+    let category = serde_json::to_value(&container.category)
+        .ok()
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_default();
+    let mut details = vec![("Container", container.name.clone())];
+    for (name, value) in [("Status", &container.status), ("Image", &container.image)] {
+        if !value.is_empty() {
+            details.push((name, value.clone()));
+        }
+    }
+    details.push(("Category", category));
+    if let Some(alert) = &context.alert {
+        details.push(("Rule", alert.rule.clone()));
+        details.push(("Value", format!("{} (threshold {})", alert.value, alert.threshold)));
+    }
+    details
+}
+
+/// A Slack message with blocks, and `text` for notifications and old clients
+pub fn slack_message(context: &NotificationContext) -> Value {
+    let fields: Vec<Value> = details(context)
+        .into_iter()
+        .map(|(name, value)| json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", name, value) }))
+        .collect();
+    json!({
+        "text": context.summary,
+        "blocks": [
+            {
+                "type": "header",
+                "text": {
+                    "type": "plain_text",
+                    "text": truncate(&format!("{} {}", emoji(context.severity), context.summary), SLACK_HEADER_LIMIT),
+                    "emoji": true,
+                },
+            },
+            // Slack allows at most ten fields per section
+            { "type": "section", "fields": fields.into_iter().take(10).collect::<Vec<_>>() },
+            {
+                "type": "context",
+                "elements": [{
+                    "type": "mrkdwn",
+                    "text": format!("FlowScope · `{}` · <!date^{}^{{date_short_pretty}} {{time}}|{}>",
+                        context.event,
+                        context.timestamp.timestamp(),
+                        context.timestamp.to_rfc3339()),
+                }],
+            },
+        ],
+    })
+}
+
+/// A Discord message with one embed
+pub fn discord_message(context: &NotificationContext) -> Value {
+    let fields: Vec<Value> = details(context)
+        .into_iter()
+        .map(|(name, value)| json!({ "name": name, "value": value, "inline": true }))
+        .collect();
+    json!({
+        "username": "FlowScope",
+        "embeds": [{
+            "title": truncate(&context.summary, DISCORD_TITLE_LIMIT),
+            "color": colour(context.severity),
+            "fields": fields,
+            "footer": { "text": context.event },
+            "timestamp": context.timestamp.to_rfc3339(),
+        }],
+    })
+}
+
+/// Renders the channel's template when it has one, otherwise `message`
+fn format(
+    channel: &NotificationChannel,
+    context: &NotificationContext,
+    strict: bool,
+    message: fn(&NotificationContext) -> Value,
+) -> Result<RenderedNotification, NotificationError> {
+    if channel.template.is_some() {
+        return render(channel, context, strict);
+    }
+    Ok(RenderedNotification {
+        channel: channel.name.clone(),
+        content_type: "application/json".to_string(),
+        body: message(context).to_string(),
+    })
+}
+
+/// Posts Slack blocks to incoming webhooks
+pub struct SlackSink {
+    webhook: WebhookSink,
+}
+
+impl SlackSink {
+    pub fn new() -> Self {
+        Self {
+            webhook: WebhookSink::new(),
+        }
+    }
+}
+
+impl NotificationSink for SlackSink {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn handles(&self, kind: NotificationKind) -> bool {
+        kind == NotificationKind::Slack
+    }
+
+    fn format(
+        &self,
+        channel: &NotificationChannel,
+        context: &NotificationContext,
+        strict: bool,
+    ) -> Result<RenderedNotification, NotificationError> {
+        format(channel, context, strict, slack_message)
+    }
+
+    fn send<'a>(
+        &'a self,
+        channel: &'a NotificationChannel,
+        payload: &'a RenderedNotification,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        self.webhook.send(channel, payload)
+    }
+}
+
+/// Posts Discord embeds to channel webhooks
+pub struct DiscordSink {
+    webhook: WebhookSink,
+}
+
+impl DiscordSink {
+    pub fn new() -> Self {
+        Self {
+            webhook: WebhookSink::new(),
+        }
+    }
+}
+
+impl NotificationSink for DiscordSink {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    fn handles(&self, kind: NotificationKind) -> bool {
+        kind == NotificationKind::Discord
+    }
+
+    fn format(
+        &self,
+        channel: &NotificationChannel,
+        context: &NotificationContext,
+        strict: bool,
+    ) -> Result<RenderedNotification, NotificationError> {
+        format(channel, context, strict, discord_message)
+    }
+
+    fn send<'a>(
+        &'a self,
+        channel: &'a NotificationChannel,
+        payload: &'a RenderedNotification,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        self.webhook.send(channel, payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        delivery::status_notification,
+        docker_api::fake,
+        models::{ContainerInfo, ContainerStatus, ServiceCategory},
+    };
+
+    #[test]
+    fn messages_carry_the_container_and_severity() {
+        let postgres = ContainerInfo {
+            id: "c0ffee".to_string(),
+            image: "postgres:16".to_string(),
+            status: ContainerStatus::Unhealthy,
+            category: ServiceCategory::Infrastructure,
+            ..fake::container("infrastructure-postgres")
+        };
+        let context = status_notification(&postgres);
+
+        let slack = slack_message(&context);
+        assert_eq!(slack["text"], "infrastructure-postgres is unhealthy");
+        assert_eq!(slack["blocks"][0]["text"]["text"], ":red_circle: infrastructure-postgres is unhealthy");
+        let fields: Vec<&str> = slack["blocks"][1]["fields"].as_array().unwrap().iter().map(|f| f["text"].as_str().unwrap()).collect();
+        assert_eq!(
+            fields,
+            ["*Container*\ninfrastructure-postgres", "*Status*\nunhealthy", "*Image*\npostgres:16", "*Category*\ninfrastructure"]
+        );
+
+        let discord = discord_message(&NotificationContext::sample());
+        let embed = &discord["embeds"][0];
+        assert_eq!(embed["color"], 0xd97706);
+        assert_eq!(embed["footer"]["text"], "alert.firing");
+        assert_eq!(embed["fields"][5]["value"], "95.2 (threshold 90)");
+
+        assert_eq!(truncate("abcdef", 4), "abc…");
+        assert_eq!(truncate("abc", 4), "abc");
+        assert_eq!(truncate("abcd", 4), "abcd");
+        assert_eq!(truncate("héllo wörld", 5), "héll…");
+        assert_eq!(truncate("abc", 0), "");
+        assert_eq!(truncate("", 0), "");
+    }
+}
@@ -0,0 +1,197 @@
+//! Topology assertions for CI
+//!
+//! `flowscope check --rules rules.yaml` evaluates hygiene rules against the
+//! live topology, prints a JSON report and exits non-zero when any rule fails,
+//! so deployments can be gated on topology health:
+//!
+//! ```yaml
+//! rules:
+//!   - rule: all-healthy
+//!     exclude: [monitoring-*]
+//!   - rule: no-latest-tags
+//!   - rule: required-services
+//!     services: [infrastructure-postgres, application-api-*]
+//!   - rule: diagnostics
+//!     severity: warning
+//! ```
+
+use std::{path::PathBuf, process::ExitCode};
+
+use chrono::Utc;
+use clap::Args;
+use tracing::{error, info, warn};
+
+use crate::discovery::DockerDiscovery;
+use crate::docker_api::DockerApi;
+use crate::models::{CheckReport, CheckRule, CheckRules, ContainerInfo, ContainerStatus, RuleOutcome};
+use crate::oneshot::{discovery_failed, write_output, EXIT_OK, EXIT_OUTPUT, EXIT_UNHEALTHY};
+
+#[derive(Debug, Clone, Args)]
+pub struct CheckArgs {
+    /// YAML rules file
+    #[arg(long)]
+    pub rules: PathBuf,
+    /// File to write the JSON report to; stdout when omitted
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// Exact container name, or a prefix when the pattern ends in `*`
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+fn excluded(exclude: &[String], container: &ContainerInfo) -> bool {
+    exclude.iter().any(|p| matches_pattern(p, &container.name))
+}
+
+fn is_up(container: &ContainerInfo) -> bool {
+    matches!(container.status, ContainerStatus::Running | ContainerStatus::Healthy)
+}
+
+/// Untagged references resolve to `latest`; digests and bare image ids are pinned
+fn is_floating_tag(image: &str) -> bool {
+    if image.contains('@') || image.starts_with("sha256:") {
+        return false;
+    }
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.split_once(':').is_none_or(|(_, tag)| tag == "latest")
+}
+
+/// Evaluate every rule against the current topology
+pub async fn evaluate<D: DockerApi>(
+    discovery: &DockerDiscovery<D>,
+    rules: &CheckRules,
+) -> Result<CheckReport, bollard::errors::Error> {
+    let containers = discovery.list_containers().await?;
+    let mut results = Vec::with_capacity(rules.rules.len());
+
+    for rule in &rules.rules {
+        let violations: Vec<String> = match rule {
+            CheckRule::AllHealthy { exclude } => containers
+                .iter()
+                .filter(|c| !excluded(exclude, c) && !is_up(c))
+                .map(|c| format!("{} is {}", c.name, format!("{:?}", c.status).to_lowercase()))
+                .collect(),
+            CheckRule::NoLatestTags { exclude } => containers
+                .iter()
+                .filter(|c| !excluded(exclude, c) && is_floating_tag(&c.image))
+                .map(|c| format!("{} runs {} without a pinned tag", c.name, c.image))
+                .collect(),
+            CheckRule::RequiredServices { services } => services
+                .iter()
+                .filter(|s| !containers.iter().any(|c| is_up(c) && matches_pattern(s, &c.name)))
+                .map(|s| format!("no running container matches {}", s))
+                .collect(),
+            CheckRule::Diagnostics { severity } => discovery
+                .get_diagnostics()
+                .await?
+                .findings
+                .into_iter()
+                .filter(|f| f.severity >= *severity)
+                .map(|f| format!("{}: {}: {}", f.container_name, f.check, f.message))
+                .collect(),
+        };
+        results.push(RuleOutcome {
+            rule: rule.name().to_string(),
+            passed: violations.is_empty(),
+            violations,
+        });
+    }
+
+    Ok(CheckReport {
+        passed: results.iter().all(|r| r.passed),
+        results,
+        checked_at: Utc::now(),
+    })
+}
+
+/// Load the rules, evaluate them and report the outcome as an exit status
+pub async fn run<D: DockerApi>(discovery: &DockerDiscovery<D>, args: &CheckArgs) -> ExitCode {
+    let rules: CheckRules = match std::fs::read_to_string(&args.rules)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_yaml::from_str(&text).map_err(|e| e.to_string()))
+    {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!("Failed to load rules from {}: {}", args.rules.display(), e);
+            return ExitCode::from(EXIT_OUTPUT);
+        }
+    };
+
+    let report = match evaluate(discovery, &rules).await {
+        Ok(report) => report,
+        Err(e) => return discovery_failed(e),
+    };
+    let body = serde_json::to_string_pretty(&report).expect("report serializes") + "\n";
+    if let Err(e) = write_output(args.output.as_deref(), &body) {
+        error!("Failed to write report: {}", e);
+        return ExitCode::from(EXIT_OUTPUT);
+    }
+
+    let failed: Vec<&str> = report
+        .results
+        .iter()
+        .filter(|r| !r.passed)
+        .map(|r| r.rule.as_str())
+        .collect();
+    if failed.is_empty() {
+        info!("All {} rules passed", report.results.len());
+        ExitCode::from(EXIT_OK)
+    } else {
+        warn!("{} of {} rules failed: {}", failed.len(), report.results.len(), failed.join(", "));
+        ExitCode::from(EXIT_UNHEALTHY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+    use crate::models::Severity;
+
+    #[test]
+    fn floating_tags() {
+        assert!(is_floating_tag("flowscope/api:latest"));
+        assert!(is_floating_tag("redis"));
+        assert!(is_floating_tag("registry:5000/team/app"));
+        assert!(!is_floating_tag("registry:5000/team/app:1.2"));
+        assert!(!is_floating_tag("postgres@sha256:abc"));
+        assert!(!is_floating_tag("sha256:0123abcd"));
+    }
+
+    #[tokio::test]
+    async fn rules_against_fixture() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let rules: CheckRules = serde_yaml::from_str(
+            "rules:
+              - rule: all-healthy
+                exclude: [monitoring-*]
+              - rule: no-latest-tags
+              - rule: required-services
+                services: [infrastructure-postgres, application-api-*, cache-redis]
+              - rule: diagnostics
+                severity: error",
+        )
+        .unwrap();
+        let report = evaluate(&discovery, &rules).await.unwrap();
+
+        let outcome = |name: &str| report.results.iter().find(|r| r.rule == name).unwrap();
+        assert!(!report.passed);
+        assert!(outcome("all-healthy").passed);
+        assert_eq!(outcome("no-latest-tags").violations.len(), 3);
+        assert_eq!(
+            outcome("required-services").violations,
+            ["no running container matches cache-redis"]
+        );
+        assert!(outcome("diagnostics").passed);
+
+        let strict = CheckRules {
+            rules: vec![CheckRule::Diagnostics { severity: Severity::Warning }],
+        };
+        assert!(!evaluate(&discovery, &strict).await.unwrap().passed);
+    }
+}
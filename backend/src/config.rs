@@ -0,0 +1,590 @@
+//! Server configuration
+//!
+//! Settings are read from a YAML file (`--config`, `FLOWSCOPE_CONFIG`, default
+//! `<data dir>/config.yaml`), then overridden by environment variables, then
+//! by command-line flags:
+//!
+//! ```yaml
+//! bind: 0.0.0.0:8850              # FLOWSCOPE_BIND, --bind
+//! provider: kubernetes            # FLOWSCOPE_PROVIDER, --provider
+//! docker_host: podman             # FLOWSCOPE_DOCKER_HOST, DOCKER_HOST, --docker-host
+//! ws_interval_secs: 5             # FLOWSCOPE_WS_INTERVAL_SECS, --ws-interval
+//! cors_origins:                   # FLOWSCOPE_CORS_ORIGINS, comma-separated
+//!   - https://dash.example.com
+//! categories_file: /etc/flowscope/categories.yaml   # FLOWSCOPE_CATEGORIES_FILE
+//! features:                       # FLOWSCOPE_FEATURE_<NAME>=true|false
+//!   exec: true
+//!   stats_history: true
+//!   event_log: true
+//!   image_history: true
+//!   topology_history: true
+//! alerts:                         # see the alerts module
+//!   - name: memory
+//!     when: memory_percent > 90 for 5m
+//! probes:                         # see the probe module
+//!   enabled: true                 # FLOWSCOPE_PROBES=true|false
+//!   path: /health
+//!   interval_secs: 30
+//!   timeout_ms: 2000
+//! vulnerabilities:                # see the vulnerabilities module
+//!   scanner: trivy                # FLOWSCOPE_SCANNER=trivy|grype|off
+//!   command: /usr/local/bin/trivy
+//!   interval_secs: 21600
+//!   timeout_secs: 600
+//! log_search:                     # see the log_search module
+//!   enabled: true                 # FLOWSCOPE_LOG_SEARCH=true|false
+//!   selector: label team=core
+//!   retention_hours: 24
+//!   max_lines: 200000
+//! log_rates:                      # see the log_rates module
+//!   enabled: true                 # FLOWSCOPE_LOG_RATES=true|false
+//!   error_pattern: '(?i)\b(error|fatal)\b'
+//!   warn_pattern: '(?i)\bwarn\b'
+//! ```
+//!
+//! Every setting is optional. Without `cors_origins` any origin may call the
+//! API. `provider` picks what is discovered: `docker` (the default) or
+//! `kubernetes`, see the kubernetes module. `ws_interval_secs` is the update interval of WebSocket clients that do
+//! not ask for one. Turning `exec` off refuses every request that needs the
+//! `exec` scope; the other features are the background tasks that record
+//! stats, daemon events, image history and topology snapshots. A missing file
+//! means the defaults; an unreadable file or a malformed setting is an error,
+//! like the token and category files. HTTP health probes are off unless
+//! `probes.enabled` or `FLOWSCOPE_PROBES` turns them on, and images are only
+//! scanned for vulnerabilities once a scanner is named. Logs are only indexed
+//! for search with `log_search.enabled` or `FLOWSCOPE_LOG_SEARCH`, and error
+//! lines only counted with `log_rates.enabled` or `FLOWSCOPE_LOG_RATES`.
+
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use clap::Args;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    alerts,
+    auth::required_scope,
+    log_rates, log_search,
+    models::{ApiScope, ConfiguredAlertRule, Scanner, Severity},
+    probe,
+    provider::ProviderKind,
+    selector::Selector,
+    store::data_dir,
+    vulnerabilities,
+    AppState,
+};
+
+const DEFAULT_BIND: &str = "0.0.0.0:8850";
+const DEFAULT_WS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Flags that override the configuration file and the environment
+#[derive(Debug, Clone, Default, Args)]
+pub struct ConfigArgs {
+    /// Configuration file; defaults to FLOWSCOPE_CONFIG or <data dir>/config.yaml
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Address to listen on, such as 127.0.0.1:8850
+    #[arg(long, global = true)]
+    pub bind: Option<SocketAddr>,
+    /// Daemon endpoint: a unix://, tcp:// or http:// URL, a socket path or
+    /// `podman`; overrides FLOWSCOPE_DOCKER_HOST and DOCKER_HOST
+    #[arg(long, global = true)]
+    pub docker_host: Option<String>,
+    /// What to discover: docker or kubernetes; overrides FLOWSCOPE_PROVIDER
+    #[arg(long, global = true)]
+    pub provider: Option<String>,
+    /// Seconds between WebSocket updates for clients that do not choose
+    #[arg(long, global = true)]
+    pub ws_interval: Option<u64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("failed to parse {0}: {1}")]
+    Parse(String, serde_yaml::Error),
+    #[error("{0}: {1}")]
+    Invalid(&'static str, String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    bind: Option<SocketAddr>,
+    provider: Option<String>,
+    docker_host: Option<String>,
+    ws_interval_secs: Option<u64>,
+    cors_origins: Option<Vec<String>>,
+    categories_file: Option<PathBuf>,
+    features: FeaturesFile,
+    alerts: Vec<AlertRuleFile>,
+    probes: ProbesFile,
+    vulnerabilities: VulnerabilitiesFile,
+    log_search: LogSearchFile,
+    log_rates: LogRatesFile,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertRuleFile {
+    name: String,
+    when: String,
+    #[serde(default)]
+    severity: Option<Severity>,
+    #[serde(default)]
+    containers: Vec<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    selector: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProbesFile {
+    enabled: Option<bool>,
+    path: Option<String>,
+    interval_secs: Option<u64>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct VulnerabilitiesFile {
+    scanner: Option<Scanner>,
+    command: Option<String>,
+    interval_secs: Option<u64>,
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LogSearchFile {
+    enabled: Option<bool>,
+    selector: Option<String>,
+    retention_hours: Option<u64>,
+    max_lines: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LogRatesFile {
+    enabled: Option<bool>,
+    error_pattern: Option<String>,
+    warn_pattern: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FeaturesFile {
+    exec: Option<bool>,
+    stats_history: Option<bool>,
+    event_log: Option<bool>,
+    image_history: Option<bool>,
+    topology_history: Option<bool>,
+}
+
+/// Optional parts of FlowScope, all on by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    /// Exec, terminals, debug sidecars, one-off runs and port forwards
+    pub exec: bool,
+    /// The stats sampler behind alerts, dashboards, migrations and `/metrics`
+    pub stats_history: bool,
+    /// The daemon event collector behind `/api/events` and event-driven updates
+    pub event_log: bool,
+    /// The tracker of which images each service ran, behind rollbacks
+    pub image_history: bool,
+    /// The topology snapshots behind `/api/history`
+    pub topology_history: bool,
+}
+
+/// HTTP health probes of published ports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeSettings {
+    pub enabled: bool,
+    /// Requested on every container without a `flowscope.probe` label
+    pub path: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+/// Vulnerability scans of the images containers run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanSettings {
+    /// Off when `None`
+    pub scanner: Option<Scanner>,
+    /// The scanner's executable; `trivy` or `grype` on the `PATH` by default
+    pub command: String,
+    /// How long a scan stays fresh before the image is scanned again
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+/// The full-text index of container logs behind `/api/logs/search`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogSearchSettings {
+    pub enabled: bool,
+    /// Containers whose logs are indexed; all when `None`
+    pub selector: Option<Selector>,
+    /// How long lines stay searchable
+    pub retention: Duration,
+    /// Lines kept at most, oldest dropped first
+    pub max_lines: usize,
+}
+
+/// Error and warning lines counted per container from its logs
+#[derive(Debug, Clone)]
+pub struct LogRateSettings {
+    pub enabled: bool,
+    pub error_pattern: Regex,
+    pub warn_pattern: Regex,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind: SocketAddr,
+    /// Docker unless set to Kubernetes
+    pub provider: ProviderKind,
+    /// `--docker-host`, which overrides the environment
+    pub docker_host_flag: Option<String>,
+    /// `docker_host` from the file, used when neither the flag nor the
+    /// environment names an endpoint
+    pub docker_host_file: Option<String>,
+    /// Update interval for WebSocket clients that never asked for one
+    pub ws_interval: Duration,
+    /// Origins allowed to call the API from a browser; any when empty
+    pub cors_origins: Vec<String>,
+    pub categories_file: PathBuf,
+    pub features: Features,
+    pub alert_rules: Vec<ConfiguredAlertRule>,
+    pub probes: ProbeSettings,
+    pub vulnerabilities: ScanSettings,
+    pub log_search: LogSearchSettings,
+    pub log_rates: LogRateSettings,
+}
+
+/// `true`/`false`, also as `1`/`0`, `yes`/`no` and `on`/`off`
+pub fn parse_bool(value: &str) -> Option<bool> {
+    let value = value.trim();
+    let any = |words: [&str; 4]| words.iter().any(|w| value.eq_ignore_ascii_case(w));
+    if any(["true", "1", "yes", "on"]) {
+        Some(true)
+    } else if any(["false", "0", "no", "off"]) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+impl Config {
+    /// Read the file and the environment, then apply `args`
+    pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let path = args
+            .config
+            .clone()
+            .or_else(|| var("FLOWSCOPE_CONFIG").map(PathBuf::from))
+            .unwrap_or_else(|| data_dir().join("config.yaml"));
+        let shown = path.display().to_string();
+
+        let file = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let file = serde_yaml::from_str(&text).map_err(|e| ConfigError::Parse(shown.clone(), e))?;
+                info!("Loaded configuration from {}", shown);
+                file
+            }
+            // An explicitly named file has to exist
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && args.config.is_none() => ConfigFile::default(),
+            Err(e) => return Err(ConfigError::Read(shown, e)),
+        };
+        Self::resolve(file, &var, args)
+    }
+
+    fn resolve(file: ConfigFile, var: &impl Fn(&str) -> Option<String>, args: &ConfigArgs) -> Result<Self, ConfigError> {
+        let bind = match (args.bind, var("FLOWSCOPE_BIND")) {
+            (Some(bind), _) => bind,
+            (None, Some(value)) => value
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::Invalid("FLOWSCOPE_BIND", format!("'{}' is not an address and port", value)))?,
+            (None, None) => file.bind.unwrap_or_else(|| DEFAULT_BIND.parse().unwrap()),
+        };
+
+        let provider = match (&args.provider, var("FLOWSCOPE_PROVIDER")) {
+            (Some(value), _) => ProviderKind::parse(value).map_err(|e| ConfigError::Invalid("--provider", e))?,
+            (None, Some(value)) => ProviderKind::parse(&value).map_err(|e| ConfigError::Invalid("FLOWSCOPE_PROVIDER", e))?,
+            (None, None) => match file.provider {
+                Some(value) => ProviderKind::parse(&value).map_err(|e| ConfigError::Invalid("provider", e))?,
+                None => ProviderKind::Docker,
+            },
+        };
+
+        let ws_secs = match (args.ws_interval, var("FLOWSCOPE_WS_INTERVAL_SECS")) {
+            (Some(secs), _) => Some(secs),
+            (None, Some(value)) => Some(value.trim().parse().map_err(|_| {
+                ConfigError::Invalid("FLOWSCOPE_WS_INTERVAL_SECS", format!("'{}' is not a whole number", value))
+            })?),
+            (None, None) => file.ws_interval_secs,
+        };
+        let ws_interval = match ws_secs {
+            Some(0) => return Err(ConfigError::Invalid("ws_interval_secs", "must be at least 1".to_string())),
+            Some(secs) => Duration::from_secs(secs),
+            None => DEFAULT_WS_INTERVAL,
+        };
+
+        let cors_origins = match var("FLOWSCOPE_CORS_ORIGINS") {
+            Some(value) => value.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect(),
+            None => file.cors_origins.unwrap_or_default(),
+        };
+
+        let categories_file = var("FLOWSCOPE_CATEGORIES_FILE")
+            .map(PathBuf::from)
+            .or(file.categories_file)
+            .unwrap_or_else(|| data_dir().join("categories.yaml"));
+
+        let feature = |name: &'static str, from_file: Option<bool>| match var(name) {
+            Some(value) => {
+                parse_bool(&value).ok_or_else(|| ConfigError::Invalid(name, format!("'{}' is not true or false", value)))
+            }
+            None => Ok(from_file.unwrap_or(true)),
+        };
+        let features = Features {
+            exec: feature("FLOWSCOPE_FEATURE_EXEC", file.features.exec)?,
+            stats_history: feature("FLOWSCOPE_FEATURE_STATS_HISTORY", file.features.stats_history)?,
+            event_log: feature("FLOWSCOPE_FEATURE_EVENT_LOG", file.features.event_log)?,
+            image_history: feature("FLOWSCOPE_FEATURE_IMAGE_HISTORY", file.features.image_history)?,
+            topology_history: feature("FLOWSCOPE_FEATURE_TOPOLOGY_HISTORY", file.features.topology_history)?,
+        };
+
+        let alert_rules = file
+            .alerts
+            .into_iter()
+            .map(|r| {
+                let name = r.name.clone();
+                let severity = r.severity.unwrap_or(Severity::Warning);
+                alerts::configured_rule(r.name, r.when, severity, r.containers, r.groups, r.selector)
+                    .map_err(|e| ConfigError::Invalid("alerts", format!("rule '{}': {}", name, e)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let probes = ProbeSettings {
+            enabled: match var("FLOWSCOPE_PROBES") {
+                Some(value) => parse_bool(&value)
+                    .ok_or_else(|| ConfigError::Invalid("FLOWSCOPE_PROBES", format!("'{}' is not true or false", value)))?,
+                None => file.probes.enabled.unwrap_or(false),
+            },
+            path: match file.probes.path {
+                Some(path) if !path.starts_with('/') => {
+                    return Err(ConfigError::Invalid("probes", format!("path '{}' does not start with /", path)))
+                }
+                Some(path) => path,
+                None => probe::DEFAULT_PATH.to_string(),
+            },
+            interval: match file.probes.interval_secs {
+                Some(0) => return Err(ConfigError::Invalid("probes", "interval_secs must be at least 1".to_string())),
+                Some(secs) => Duration::from_secs(secs),
+                None => probe::DEFAULT_INTERVAL,
+            },
+            timeout: match file.probes.timeout_ms {
+                Some(0) => return Err(ConfigError::Invalid("probes", "timeout_ms must be at least 1".to_string())),
+                Some(ms) => Duration::from_millis(ms),
+                None => probe::DEFAULT_TIMEOUT,
+            },
+        };
+
+        let scanner = match var("FLOWSCOPE_SCANNER") {
+            Some(value) if parse_bool(&value) == Some(false) => None,
+            Some(value) => Some(serde_yaml::from_str(value.trim()).map_err(|_| {
+                ConfigError::Invalid("FLOWSCOPE_SCANNER", format!("'{}' is not trivy, grype or off", value))
+            })?),
+            None => file.vulnerabilities.scanner,
+        };
+        let vulnerabilities = ScanSettings {
+            command: file.vulnerabilities.command.unwrap_or_else(|| match scanner {
+                Some(Scanner::Grype) => "grype".to_string(),
+                _ => "trivy".to_string(),
+            }),
+            scanner,
+            interval: match file.vulnerabilities.interval_secs {
+                Some(0) => return Err(ConfigError::Invalid("vulnerabilities", "interval_secs must be at least 1".to_string())),
+                Some(secs) => Duration::from_secs(secs),
+                None => vulnerabilities::DEFAULT_INTERVAL,
+            },
+            timeout: match file.vulnerabilities.timeout_secs {
+                Some(0) => return Err(ConfigError::Invalid("vulnerabilities", "timeout_secs must be at least 1".to_string())),
+                Some(secs) => Duration::from_secs(secs),
+                None => vulnerabilities::DEFAULT_TIMEOUT,
+            },
+        };
+
+        let log_search = LogSearchSettings {
+            enabled: match var("FLOWSCOPE_LOG_SEARCH") {
+                Some(value) => parse_bool(&value)
+                    .ok_or_else(|| ConfigError::Invalid("FLOWSCOPE_LOG_SEARCH", format!("'{}' is not true or false", value)))?,
+                None => file.log_search.enabled.unwrap_or(false),
+            },
+            selector: Selector::parse(file.log_search.selector.as_deref())
+                .map_err(|e| ConfigError::Invalid("log_search", format!("selector: {}", e)))?,
+            retention: match file.log_search.retention_hours {
+                Some(0) => return Err(ConfigError::Invalid("log_search", "retention_hours must be at least 1".to_string())),
+                Some(hours) => Duration::from_secs(hours * 60 * 60),
+                None => log_search::DEFAULT_RETENTION,
+            },
+            max_lines: match file.log_search.max_lines {
+                Some(0) => return Err(ConfigError::Invalid("log_search", "max_lines must be at least 1".to_string())),
+                Some(lines) => lines,
+                None => log_search::DEFAULT_MAX_LINES,
+            },
+        };
+
+        let pattern = |name: &str, pattern: Option<String>, default: &str| {
+            let pattern = pattern.as_deref().unwrap_or(default);
+            Regex::new(pattern).map_err(|e| ConfigError::Invalid("log_rates", format!("{}: {}", name, e)))
+        };
+        let log_rates = LogRateSettings {
+            enabled: match var("FLOWSCOPE_LOG_RATES") {
+                Some(value) => parse_bool(&value)
+                    .ok_or_else(|| ConfigError::Invalid("FLOWSCOPE_LOG_RATES", format!("'{}' is not true or false", value)))?,
+                None => file.log_rates.enabled.unwrap_or(false),
+            },
+            error_pattern: pattern("error_pattern", file.log_rates.error_pattern, log_rates::DEFAULT_ERROR_PATTERN)?,
+            warn_pattern: pattern("warn_pattern", file.log_rates.warn_pattern, log_rates::DEFAULT_WARN_PATTERN)?,
+        };
+
+        Ok(Self {
+            bind,
+            provider,
+            docker_host_flag: args.docker_host.clone(),
+            docker_host_file: file.docker_host,
+            ws_interval,
+            cors_origins,
+            categories_file,
+            features,
+            alert_rules,
+            probes,
+            vulnerabilities,
+            log_search,
+            log_rates,
+        })
+    }
+}
+
+/// Middleware refusing requests that need a feature the configuration turned off
+pub async fn require_features(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let scope = required_scope(request.method(), request.uri().path());
+    if scope == Some(ApiScope::Exec) && !state.config.features.exec {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "exec is turned off in the configuration" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn flags_override_environment_override_file() {
+        let file: ConfigFile = serde_yaml::from_str(
+            "bind: 127.0.0.1:9000
+provider: kubernetes
+ws_interval_secs: 10
+docker_host: podman
+cors_origins: [https://dash.example.com]
+features:
+  exec: false
+  event_log: false
+alerts:
+  - name: unhealthy
+    when: status == unhealthy
+    severity: error
+probes:
+  enabled: true
+  path: /healthz
+vulnerabilities:
+  scanner: trivy
+log_search:
+  selector: label team=core
+  retention_hours: 6
+log_rates:
+  enabled: true
+  error_pattern: FAIL",
+        )
+        .unwrap();
+        let env = HashMap::from([
+            ("FLOWSCOPE_WS_INTERVAL_SECS", "2"),
+            ("FLOWSCOPE_FEATURE_EVENT_LOG", "on"),
+            ("FLOWSCOPE_SCANNER", "grype"),
+            ("FLOWSCOPE_LOG_SEARCH", "true"),
+        ]);
+        let var = |name: &str| env.get(name).map(|v| v.to_string());
+        let args = ConfigArgs {
+            bind: Some("0.0.0.0:8080".parse().unwrap()),
+            ..Default::default()
+        };
+
+        let config = Config::resolve(file, &var, &args).unwrap();
+        assert_eq!(config.bind, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.provider, ProviderKind::Kubernetes);
+        assert_eq!(config.ws_interval, Duration::from_secs(2));
+        assert_eq!(config.docker_host_file.as_deref(), Some("podman"));
+        assert_eq!(config.cors_origins, ["https://dash.example.com"]);
+        assert!(!config.features.exec && config.features.event_log && config.features.image_history);
+        assert_eq!(config.alert_rules[0].name, "unhealthy");
+        assert_eq!(config.alert_rules[0].severity, Severity::Error);
+        assert!(config.probes.enabled);
+        assert_eq!(config.probes.path, "/healthz");
+        assert_eq!(config.probes.interval, probe::DEFAULT_INTERVAL);
+        assert_eq!(config.vulnerabilities.scanner, Some(Scanner::Grype));
+        assert_eq!(config.vulnerabilities.command, "grype");
+        assert!(config.log_search.enabled && config.log_search.selector.is_some());
+        assert_eq!(config.log_search.retention, Duration::from_secs(6 * 60 * 60));
+        assert_eq!(config.log_search.max_lines, log_search::DEFAULT_MAX_LINES);
+        assert!(config.log_rates.enabled);
+        assert!(config.log_rates.error_pattern.is_match("test FAIL") && !config.log_rates.error_pattern.is_match("ERROR"));
+        assert!(config.log_rates.warn_pattern.is_match("WARN"));
+
+        let defaults = Config::resolve(ConfigFile::default(), &|_: &str| None, &ConfigArgs::default()).unwrap();
+        assert_eq!(defaults.bind, DEFAULT_BIND.parse().unwrap());
+        assert_eq!(defaults.provider, ProviderKind::Docker);
+        assert_eq!(defaults.ws_interval, DEFAULT_WS_INTERVAL);
+        assert!(!defaults.probes.enabled);
+        assert_eq!(defaults.probes.path, "/health");
+        assert_eq!(defaults.vulnerabilities.scanner, None);
+        assert!(!defaults.log_search.enabled);
+        assert!(!defaults.log_rates.enabled);
+
+        let bad = |name: &'static str, value: &'static str| {
+            let var = move |n: &str| (n == name).then(|| value.to_string());
+            Config::resolve(ConfigFile::default(), &var, &ConfigArgs::default()).is_err()
+        };
+        assert!(bad("FLOWSCOPE_BIND", "8850"));
+        assert!(bad("FLOWSCOPE_PROVIDER", "nomad"));
+        assert!(bad("FLOWSCOPE_WS_INTERVAL_SECS", "0"));
+        assert!(bad("FLOWSCOPE_FEATURE_EXEC", "maybe"));
+        assert!(bad("FLOWSCOPE_SCANNER", "clair"));
+        assert!(serde_yaml::from_str::<ConfigFile>("port: 80").is_err());
+        let file = serde_yaml::from_str("alerts: [{name: cpu, when: cpu_percent >> 90}]").unwrap();
+        assert!(Config::resolve(file, &|_: &str| None, &ConfigArgs::default()).is_err());
+        let file = serde_yaml::from_str("probes: {path: health}").unwrap();
+        assert!(Config::resolve(file, &|_: &str| None, &ConfigArgs::default()).is_err());
+        let file = serde_yaml::from_str("log_search: {retention_hours: 0}").unwrap();
+        assert!(Config::resolve(file, &|_: &str| None, &ConfigArgs::default()).is_err());
+        let file = serde_yaml::from_str("log_rates: {error_pattern: '(error'}").unwrap();
+        assert!(Config::resolve(file, &|_: &str| None, &ConfigArgs::default()).is_err());
+    }
+}
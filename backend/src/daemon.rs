@@ -0,0 +1,249 @@
+//! Finding the container engine
+//!
+//! The daemon endpoint comes from `--docker-host`, then
+//! `FLOWSCOPE_DOCKER_HOST`, then `DOCKER_HOST`, then `docker_host` in the
+//! configuration file. Each takes a `unix://`,
+//! `tcp://` or `http://` URL, a socket path, or `podman` for the current
+//! user's rootless Podman socket. With none of them set FlowScope probes the
+//! Docker socket, rootless Docker and rootless then rootful Podman, and uses
+//! the first that answers a ping.
+//!
+//! A daemon that cannot be reached does not stop the server: requests fail
+//! through the circuit breaker until it is back. An endpoint that cannot be
+//! used at all, such as a socket path that does not exist, leaves FlowScope
+//! running without a daemon and the reason on `/health`.
+
+use std::{path::Path, time::Duration};
+
+use bollard::{errors::Error, Docker};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::docker_api;
+
+/// Where the Docker CLI and daemon put the socket
+const DOCKER_SOCKET: &str = "unix:///var/run/docker.sock";
+/// Rootful Podman's socket
+const PODMAN_SOCKET: &str = "unix:///run/podman/podman.sock";
+/// How long each probed socket gets to answer a ping
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The daemon FlowScope talks to, as shown on `/health`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonEndpoint {
+    pub host: String,
+    /// `--docker-host`, the variable it was read from, `probed` or `default`
+    pub source: String,
+    /// Why no connection could be made; the daemon is unavailable until
+    /// FlowScope restarts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DaemonEndpoint {
+    /// Whether the one daemon is reached over a local socket, so this host's
+    /// `/proc` and disks are the daemon's; a TCP daemon may be anywhere and
+    /// `FLOWSCOPE_DOCKER_HOSTS` spans several hosts
+    pub fn is_local(&self) -> bool {
+        self.error.is_none()
+            && self.source != "FLOWSCOPE_DOCKER_HOSTS"
+            && (self.host.starts_with("unix://") || self.host.starts_with("npipe://"))
+    }
+}
+
+/// The user's runtime directory, where rootless engines put their sockets
+fn runtime_dir(var: &impl Fn(&str) -> Option<String>) -> String {
+    var("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()).unwrap_or_else(|| {
+        #[cfg(unix)]
+        // SAFETY: getuid cannot fail and touches no memory
+        let uid = unsafe { libc::getuid() };
+        #[cfg(not(unix))]
+        let uid = 0;
+        format!("/run/user/{}", uid)
+    })
+}
+
+/// `value` as a URL: `podman` for rootless Podman, a bare path as a socket
+pub fn expand(value: &str, var: &impl Fn(&str) -> Option<String>) -> String {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("podman") {
+        format!("unix://{}/podman/podman.sock", runtime_dir(var))
+    } else if value.starts_with('/') {
+        format!("unix://{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// The endpoint chosen by the flag, the environment or the configuration
+/// file, and where it came from
+pub fn configured(
+    flag: Option<&str>,
+    file: Option<&str>,
+    var: &impl Fn(&str) -> Option<String>,
+) -> Option<(String, String)> {
+    let set = |v: Option<String>| v.filter(|v| !v.trim().is_empty());
+    if let Some(flag) = set(flag.map(str::to_string)) {
+        return Some((expand(&flag, var), "--docker-host".to_string()));
+    }
+    ["FLOWSCOPE_DOCKER_HOST", "DOCKER_HOST"]
+        .into_iter()
+        .find_map(|name| set(var(name)).map(|v| (expand(&v, var), name.to_string())))
+        .or_else(|| set(file.map(str::to_string)).map(|v| (expand(&v, var), "config file".to_string())))
+}
+
+/// Sockets tried when nothing is configured, in order
+pub fn candidates(var: &impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let runtime = runtime_dir(var);
+    vec![
+        DOCKER_SOCKET.to_string(),
+        format!("unix://{}/docker.sock", runtime),
+        format!("unix://{}/podman/podman.sock", runtime),
+        PODMAN_SOCKET.to_string(),
+    ]
+}
+
+fn socket_exists(host: &str) -> bool {
+    host.strip_prefix("unix://").is_some_and(|path| Path::new(path).exists())
+}
+
+/// A client whose every request fails with `reason`, for running without a
+/// daemon
+pub fn unavailable(reason: String) -> Docker {
+    let transport = move |_| {
+        let reason = reason.clone();
+        async move { Err(Error::from(std::io::Error::new(std::io::ErrorKind::NotConnected, reason))) }
+    };
+    Docker::connect_with_custom_transport(transport, None::<String>, 120, bollard::API_DEFAULT_VERSION)
+        .expect("a custom transport needs no connection")
+}
+
+async fn answers(docker: &Docker) -> Result<(), String> {
+    match tokio::time::timeout(PROBE_TIMEOUT, docker.ping()).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no answer within {}s", PROBE_TIMEOUT.as_secs())),
+    }
+}
+
+/// Connect to the configured endpoint, or the first probed socket that
+/// answers; when nothing can be used the client fails every request and the
+/// endpoint carries the reason
+pub async fn connect(flag: Option<&str>, file: Option<&str>) -> (Docker, DaemonEndpoint) {
+    let var = |name: &str| std::env::var(name).ok();
+
+    let (host, source) = match configured(flag, file, &var) {
+        Some(configured) => configured,
+        None => {
+            let existing: Vec<String> = candidates(&var).into_iter().filter(|h| socket_exists(h)).collect();
+            let mut first = None;
+            for host in &existing {
+                let Ok(docker) = docker_api::connect(host) else { continue };
+                match answers(&docker).await {
+                    Ok(()) => {
+                        info!("Found a container engine at {}", host);
+                        let endpoint = DaemonEndpoint {
+                            host: host.clone(),
+                            source: "probed".to_string(),
+                            error: None,
+                        };
+                        return (docker, endpoint);
+                    }
+                    Err(e) => {
+                        warn!("{} does not answer: {}", host, e);
+                        first.get_or_insert(host.clone());
+                    }
+                }
+            }
+            match first {
+                Some(host) => (host, "probed".to_string()),
+                None => (DOCKER_SOCKET.to_string(), "default".to_string()),
+            }
+        }
+    };
+
+    match docker_api::connect(&host) {
+        Ok(docker) => {
+            if let Err(e) = answers(&docker).await {
+                warn!("The container engine at {} is not reachable yet: {}", host, e);
+            }
+            let endpoint = DaemonEndpoint { host, source, error: None };
+            (docker, endpoint)
+        }
+        Err(e) => {
+            let reason = match e {
+                Error::SocketNotFoundError(_) if source == "default" => format!(
+                    "no Docker or Podman socket found (tried {}); set FLOWSCOPE_DOCKER_HOST or --docker-host",
+                    candidates(&var).join(", ")
+                ),
+                e => format!("cannot use {} from {}: {}", host, source, e),
+            };
+            tracing::error!("{}", reason);
+            let endpoint = DaemonEndpoint {
+                host,
+                source,
+                error: Some(reason.clone()),
+            };
+            (unavailable(reason), endpoint)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn only_a_single_socket_daemon_is_local() {
+        let endpoint = |host: &str, source: &str| DaemonEndpoint {
+            host: host.to_string(),
+            source: source.to_string(),
+            error: None,
+        };
+        assert!(endpoint(DOCKER_SOCKET, "default").is_local());
+        assert!(endpoint("npipe:////./pipe/docker_engine", "DOCKER_HOST").is_local());
+        assert!(!endpoint("tcp://10.0.0.2:2375", "DOCKER_HOST").is_local());
+        assert!(!endpoint("edge=unix:///var/run/docker.sock", "FLOWSCOPE_DOCKER_HOSTS").is_local());
+        let failed = DaemonEndpoint {
+            error: Some("no daemon".to_string()),
+            ..endpoint(DOCKER_SOCKET, "default")
+        };
+        assert!(!failed.is_local());
+    }
+
+    #[test]
+    fn flag_then_environment() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("XDG_RUNTIME_DIR", "/run/user/1000"),
+            ("FLOWSCOPE_DOCKER_HOST", "podman"),
+            ("DOCKER_HOST", "tcp://10.0.0.2:2375"),
+        ]);
+        let var = |name: &str| env.get(name).map(|v| v.to_string());
+        assert_eq!(
+            configured(Some("/srv/engine.sock"), None, &var),
+            Some(("unix:///srv/engine.sock".to_string(), "--docker-host".to_string()))
+        );
+        assert_eq!(
+            configured(None, None, &var),
+            Some((
+                "unix:///run/user/1000/podman/podman.sock".to_string(),
+                "FLOWSCOPE_DOCKER_HOST".to_string()
+            ))
+        );
+        let env: HashMap<&str, &str> = HashMap::from([("DOCKER_HOST", "tcp://10.0.0.2:2375")]);
+        let var = |name: &str| env.get(name).map(|v| v.to_string());
+        assert_eq!(configured(Some(" "), Some("podman"), &var).unwrap().1, "DOCKER_HOST");
+        let var = |_: &str| None;
+        assert_eq!(configured(None, Some("podman"), &var).unwrap().1, "config file");
+    }
+
+    #[tokio::test]
+    async fn runs_without_a_daemon() {
+        let (docker, endpoint) = connect(Some("/nonexistent/docker.sock"), None).await;
+        assert_eq!(endpoint.source, "--docker-host");
+        assert!(endpoint.error.unwrap().contains("/nonexistent/docker.sock"));
+        assert!(docker.ping().await.is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! Custom dashboards
+//!
+//! A dashboard is a user-composed set of widgets (metric charts, log panels,
+//! flowcharts and endpoint lists), each bound to a container, a group or a
+//! category. `GET /api/dashboards/:id/data` resolves every widget in one call,
+//! so the frontend renders a dashboard without a round trip per widget. A
+//! widget that cannot be resolved, say because its group was deleted, carries
+//! an error instead of failing the whole dashboard.
+
+use std::collections::BTreeSet;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{
+    auth::Caller,
+    docker_api::LogWindow,
+    metrics::RETENTION,
+    models::{
+        ApiScope, ContainerInfo, Dashboard, DashboardData, DashboardWidget, MetricPoint, MetricSeries,
+        PublishedEndpoint, WidgetData, WidgetKind, WidgetPayload, WidgetTarget,
+    },
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub type DashboardStore = JsonStore<Dashboard>;
+
+/// Most log lines a log panel shows per container
+pub const MAX_LOG_TAIL: usize = 1000;
+/// Most containers a log panel reads logs from
+pub const MAX_LOG_CONTAINERS: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DashboardError {
+    #[error("dashboard '{0}' not found")]
+    NotFound(String),
+    #[error("invalid dashboard: {0}")]
+    Invalid(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl DashboardError {
+    fn status(&self) -> StatusCode {
+        match self {
+            DashboardError::NotFound(_) => StatusCode::NOT_FOUND,
+            DashboardError::Invalid(_) => StatusCode::BAD_REQUEST,
+            DashboardError::Docker(_) | DashboardError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for DashboardError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Dashboard error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+pub fn validate(dashboard: &Dashboard) -> Result<(), DashboardError> {
+    let invalid = |message: String| Err(DashboardError::Invalid(message));
+    if dashboard.name.trim().is_empty() {
+        return invalid("name must not be empty".to_string());
+    }
+    let mut ids = BTreeSet::new();
+    for widget in &dashboard.widgets {
+        if widget.id.trim().is_empty() {
+            return invalid("widget ids must not be empty".to_string());
+        }
+        if !ids.insert(widget.id.as_str()) {
+            return invalid(format!("widget id '{}' is used twice", widget.id));
+        }
+        match &widget.target {
+            WidgetTarget::Container { name } | WidgetTarget::Group { name } if name.trim().is_empty() => {
+                return invalid(format!("widget '{}' targets an empty name", widget.id));
+            }
+            _ => {}
+        }
+        match widget.kind {
+            WidgetKind::MetricChart { minutes, .. }
+                if minutes == 0 || i64::from(minutes) > RETENTION.num_minutes() =>
+            {
+                return invalid(format!(
+                    "widget '{}': minutes must be between 1 and {}",
+                    widget.id,
+                    RETENTION.num_minutes()
+                ));
+            }
+            WidgetKind::LogPanel { tail } if tail == 0 || tail > MAX_LOG_TAIL => {
+                return invalid(format!("widget '{}': tail must be between 1 and {}", widget.id, MAX_LOG_TAIL));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn lookup(state: &AppState, id: &str) -> Result<Dashboard, DashboardError> {
+    state
+        .dashboards
+        .get(id)
+        .ok_or_else(|| DashboardError::NotFound(id.to_string()))
+}
+
+/// The containers a widget shows, or why there are none
+fn members<'a>(state: &AppState, target: &WidgetTarget, containers: &'a [ContainerInfo]) -> Result<Vec<&'a ContainerInfo>, String> {
+    match target {
+        WidgetTarget::Container { name } => containers
+            .iter()
+            .find(|c| c.name == *name || c.id == *name)
+            .map(|c| vec![c])
+            .ok_or_else(|| format!("container '{}' not found", name)),
+        WidgetTarget::Group { name } => {
+            let group = state.groups.get(name).ok_or_else(|| format!("group '{}' not found", name))?;
+            Ok(containers.iter().filter(|c| group.matches(c)).collect())
+        }
+        WidgetTarget::Category { category } => Ok(containers.iter().filter(|c| c.category == *category).collect()),
+    }
+}
+
+/// Ports `containers` publish on the host, once per container, port and protocol
+fn published_endpoints(containers: &[&ContainerInfo]) -> Vec<PublishedEndpoint> {
+    let mut endpoints: Vec<PublishedEndpoint> = containers
+        .iter()
+        .flat_map(|c| {
+            c.ports.iter().filter_map(|p| {
+                Some(PublishedEndpoint {
+                    endpoint: format!("{}:{}", c.name, p.host_port?),
+                    container: c.name.clone(),
+                    container_port: p.container_port,
+                    host_port: p.host_port?,
+                    protocol: p.protocol.clone(),
+                })
+            })
+        })
+        .collect();
+    // Docker lists a port once per address family it is published on
+    endpoints.dedup();
+    endpoints
+}
+
+async fn resolve_widget(
+    state: &AppState,
+    widget: &DashboardWidget,
+    containers: &[ContainerInfo],
+    logs_allowed: bool,
+) -> Result<WidgetPayload, String> {
+    let members = members(state, &widget.target, containers)?;
+    match widget.kind {
+        WidgetKind::MetricChart { metric, minutes } => {
+            let samples = state.metrics.since(Utc::now() - chrono::Duration::minutes(minutes.into()));
+            let series = members
+                .iter()
+                .map(|c| MetricSeries {
+                    container: c.name.clone(),
+                    points: samples
+                        .iter()
+                        .filter(|s| s.container == c.name)
+                        .map(|s| MetricPoint {
+                            at: s.at,
+                            value: metric.value(&s.stats),
+                        })
+                        .collect(),
+                })
+                .collect();
+            Ok(WidgetPayload::MetricChart { metric, series })
+        }
+        WidgetKind::LogPanel { tail } => {
+            if !logs_allowed {
+                return Err(format!("log panels need the '{}' scope", ApiScope::LogsRead.as_str()));
+            }
+            let reads = members
+                .iter()
+                .take(MAX_LOG_CONTAINERS)
+                .map(|c| state.docker.get_container_logs(&c.id, LogWindow::tail(tail)));
+            let logs = futures_util::future::try_join_all(reads).await.map_err(|e| e.to_string())?;
+            Ok(WidgetPayload::LogPanel {
+                logs: logs.into_iter().flatten().collect(),
+            })
+        }
+        WidgetKind::Flowchart => {
+            let flowchart = match &widget.target {
+                WidgetTarget::Group { name } => {
+                    let group = state.groups.get(name).ok_or_else(|| format!("group '{}' not found", name))?;
+                    Some(state.docker.generate_group_flowchart(&group).await.map_err(|e| e.to_string())?)
+                }
+                WidgetTarget::Container { .. } => {
+                    let id = &members[0].name;
+                    state.docker.generate_flowchart(id).await.map_err(|e| e.to_string())?
+                }
+                WidgetTarget::Category { category } => {
+                    let slug = serde_json::to_value(category).map_err(|e| e.to_string())?;
+                    let id = format!("{}-overview", slug.as_str().unwrap_or_default());
+                    state.docker.generate_flowchart(&id).await.map_err(|e| e.to_string())?
+                }
+            };
+            let mut flowchart = flowchart.ok_or_else(|| "the target has no flowchart".to_string())?;
+            state.layouts.apply(&mut flowchart);
+            Ok(WidgetPayload::Flowchart { flowchart })
+        }
+        WidgetKind::EndpointList => Ok(WidgetPayload::EndpointList {
+            endpoints: published_endpoints(&members),
+        }),
+    }
+}
+
+/// Resolve every widget of `dashboard` against one listing of the containers
+pub async fn resolve(
+    state: &AppState,
+    dashboard: &Dashboard,
+    logs_allowed: bool,
+) -> Result<DashboardData, bollard::errors::Error> {
+    let containers = state.docker.list_containers().await?;
+    let widgets = futures_util::future::join_all(dashboard.widgets.iter().map(|widget| {
+        let containers = &containers;
+        async move {
+            let (data, error) = match resolve_widget(state, widget, containers, logs_allowed).await {
+                Ok(data) => (Some(data), None),
+                Err(e) => (None, Some(e)),
+            };
+            WidgetData {
+                widget: widget.id.clone(),
+                data,
+                error,
+            }
+        }
+    }))
+    .await;
+    Ok(DashboardData {
+        dashboard: dashboard.id.clone(),
+        widgets,
+        resolved_at: Utc::now(),
+    })
+}
+
+/// GET /api/dashboards - List dashboards
+pub async fn list_dashboards(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.dashboards.list())
+}
+
+/// GET /api/dashboards/:id - Get a dashboard
+pub async fn get_dashboard(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Dashboard>, DashboardError> {
+    lookup(&state, &id).map(Json)
+}
+
+/// POST /api/dashboards - Create a dashboard
+pub async fn create_dashboard(
+    State(state): State<AppState>,
+    Json(mut dashboard): Json<Dashboard>,
+) -> Result<impl IntoResponse, DashboardError> {
+    validate(&dashboard)?;
+    dashboard.id = uuid::Uuid::new_v4().to_string();
+    dashboard.created_at = Utc::now();
+    dashboard.updated_at = dashboard.created_at;
+
+    state.dashboards.put(&dashboard.id, dashboard.clone())?;
+    info!("Created dashboard '{}' ({})", dashboard.name, dashboard.id);
+    Ok((StatusCode::CREATED, Json(dashboard)))
+}
+
+/// PUT /api/dashboards/:id - Replace a dashboard
+pub async fn update_dashboard(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(mut dashboard): Json<Dashboard>,
+) -> Result<Json<Dashboard>, DashboardError> {
+    let existing = lookup(&state, &id)?;
+
+    dashboard.id = id.clone();
+    validate(&dashboard)?;
+    dashboard.created_at = existing.created_at;
+    dashboard.updated_at = Utc::now();
+
+    state.dashboards.put(&id, dashboard.clone())?;
+    info!("Updated dashboard '{}' ({})", dashboard.name, id);
+    Ok(Json(dashboard))
+}
+
+/// DELETE /api/dashboards/:id - Delete a dashboard
+pub async fn delete_dashboard(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, DashboardError> {
+    match state.dashboards.remove(&id)? {
+        Some(dashboard) => {
+            info!("Deleted dashboard '{}' ({})", dashboard.name, id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(DashboardError::NotFound(id)),
+    }
+}
+
+/// GET /api/dashboards/:id/data - Every widget's data in one response. For
+/// anonymous callers and tokens without `logs:read`, log panels carry an
+/// error instead.
+pub async fn get_dashboard_data(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    caller: Option<Extension<Caller>>,
+) -> Result<Json<DashboardData>, DashboardError> {
+    let dashboard = lookup(&state, &id)?;
+    let token = caller.as_ref().map(|Extension(Caller(token))| token);
+    let logs_allowed = state.auth.grants(token, ApiScope::LogsRead);
+    Ok(Json(resolve(&state, &dashboard, logs_allowed).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DockerDiscovery;
+    use crate::docker_api::fake::FakeDocker;
+    use crate::models::{AlertMetric, ServiceCategory};
+
+    fn dashboard(widgets: Vec<DashboardWidget>) -> Dashboard {
+        Dashboard {
+            id: String::new(),
+            name: "ops".to_string(),
+            description: None,
+            widgets,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn widget(id: &str, kind: WidgetKind, target: WidgetTarget) -> DashboardWidget {
+        DashboardWidget {
+            id: id.to_string(),
+            title: None,
+            kind,
+            target,
+            layout: None,
+        }
+    }
+
+    #[test]
+    fn widgets_are_validated() {
+        let category = || WidgetTarget::Category {
+            category: ServiceCategory::Application,
+        };
+        let chart = |minutes| WidgetKind::MetricChart {
+            metric: AlertMetric::CpuPercent,
+            minutes,
+        };
+
+        assert!(validate(&dashboard(vec![widget("cpu", chart(60), category())])).is_ok());
+        assert!(validate(&dashboard(vec![widget("cpu", chart(0), category())])).is_err());
+        assert!(validate(&dashboard(vec![widget("cpu", chart(60 * 25), category())])).is_err());
+        assert!(validate(&dashboard(vec![widget("logs", WidgetKind::LogPanel { tail: 0 }, category())])).is_err());
+        assert!(validate(&dashboard(vec![
+            widget("a", WidgetKind::Flowchart, category()),
+            widget("a", WidgetKind::EndpointList, category()),
+        ]))
+        .is_err());
+        assert!(validate(&dashboard(vec![widget(
+            "a",
+            WidgetKind::Flowchart,
+            WidgetTarget::Group { name: " ".to_string() }
+        )]))
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn endpoints_of_a_category() {
+        let containers = DockerDiscovery::new(FakeDocker::load("stack")).list_containers().await.unwrap();
+        let application: Vec<&ContainerInfo> = containers
+            .iter()
+            .filter(|c| c.category == ServiceCategory::Application)
+            .collect();
+
+        let endpoints = published_endpoints(&application);
+        assert_eq!(endpoints.len(), 1);
+        assert_eq!(endpoints[0].endpoint, "application-api-1:8080");
+        assert_eq!(endpoints[0].container_port, 8080);
+    }
+}
@@ -0,0 +1,241 @@
+//! Debug sidecars
+//!
+//! `POST /api/container/:id/debug-sidecar` starts a tools container (netshoot
+//! by default, `FLOWSCOPE_DEBUG_IMAGE` to change it) in the target's network
+//! and PID namespaces, so `tcpdump`, `dig` or `strace` can be run against a
+//! container whose own image ships none of them. Every sidecar has a TTL: a
+//! background reaper removes it once expired, and its command only sleeps for
+//! the TTL, so it exits and is removed by the daemon even if FlowScope is
+//! restarted in between.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{ContainerStatus, DebugSidecar, DebugSidecarRequest},
+    ttl,
+    AppState,
+};
+
+pub const DEFAULT_IMAGE: &str = "nicolaka/netshoot:latest";
+pub const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+pub const MAX_TTL: Duration = Duration::from_secs(2 * 60 * 60);
+/// How often expired sidecars are looked for
+pub const REAP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Label naming the container a sidecar is attached to
+pub const TARGET_LABEL: &str = "flowscope.debug.target";
+/// Label holding the RFC 3339 time a sidecar expires
+pub const EXPIRES_LABEL: &str = "flowscope.debug.expires-at";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DebugError {
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("container '{0}' is not running; a sidecar needs its namespaces")]
+    NotRunning(String),
+    #[error("debug sidecar '{0}' not found")]
+    NotFound(String),
+    #[error("invalid debug sidecar: {0}")]
+    Invalid(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl DebugError {
+    fn status(&self) -> StatusCode {
+        match self {
+            DebugError::ContainerNotFound(_) | DebugError::NotFound(_) => StatusCode::NOT_FOUND,
+            DebugError::NotRunning(_) => StatusCode::CONFLICT,
+            DebugError::Invalid(_) => StatusCode::BAD_REQUEST,
+            DebugError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for DebugError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Debug sidecar error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Sidecars started by this instance that have not been removed yet
+#[derive(Debug)]
+pub struct DebugSidecars {
+    image: String,
+    sidecars: Mutex<Vec<DebugSidecar>>,
+}
+
+impl DebugSidecars {
+    pub fn new(image: &str) -> Self {
+        Self {
+            image: image.to_string(),
+            sidecars: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Default image from `FLOWSCOPE_DEBUG_IMAGE`, netshoot when unset
+    pub fn from_env() -> Self {
+        Self::new(&std::env::var("FLOWSCOPE_DEBUG_IMAGE").unwrap_or_else(|_| DEFAULT_IMAGE.to_string()))
+    }
+
+    /// All tracked sidecars, soonest to expire first
+    pub fn list(&self) -> Vec<DebugSidecar> {
+        let mut sidecars = self.sidecars.lock().unwrap().clone();
+        sidecars.sort_by_key(|s| s.expires_at);
+        sidecars
+    }
+
+    fn insert(&self, sidecar: DebugSidecar) {
+        self.sidecars.lock().unwrap().push(sidecar);
+    }
+
+    /// Stop tracking the sidecar with this id or name
+    fn take(&self, id: &str) -> Option<DebugSidecar> {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let index = sidecars.iter().position(|s| s.id == id || s.name == id)?;
+        Some(sidecars.remove(index))
+    }
+
+    /// Stop tracking and return every sidecar expired at `now`
+    fn take_expired(&self, now: DateTime<Utc>) -> Vec<DebugSidecar> {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let (expired, live) = std::mem::take(&mut *sidecars)
+            .into_iter()
+            .partition(|s| s.expires_at <= now);
+        *sidecars = live;
+        expired
+    }
+}
+
+/// Spawn the background task that removes expired sidecars
+pub fn spawn_reaper(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(REAP_INTERVAL).await;
+            for sidecar in state.debug.take_expired(Utc::now()) {
+                // A sidecar that fails to go away here still exits when its sleep ends
+                match state.docker.discard_container(&sidecar.id).await {
+                    Ok(()) => info!("Removed expired debug sidecar {} for {}", sidecar.name, sidecar.target),
+                    Err(e) => warn!("Failed to remove expired debug sidecar {}: {}", sidecar.name, e),
+                }
+            }
+        }
+    });
+}
+
+/// GET /api/debug-sidecars - List running debug sidecars
+pub async fn list_sidecars(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.debug.list())
+}
+
+/// POST /api/container/:id/debug-sidecar - Attach a tools container to a running container
+pub async fn launch_sidecar(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<DebugSidecarRequest>>,
+) -> Result<impl IntoResponse, DebugError> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    let ttl = ttl::requested(request.ttl_seconds, DEFAULT_TTL, MAX_TTL).map_err(DebugError::Invalid)?;
+    let image = match request.image.as_deref().map(str::trim) {
+        Some("") => return Err(DebugError::Invalid("image must not be empty".to_string())),
+        Some(image) => image.to_string(),
+        None => state.debug.image.clone(),
+    };
+
+    let target = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or_else(|| DebugError::ContainerNotFound(id.clone()))?;
+    if !matches!(
+        target.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    ) {
+        return Err(DebugError::NotRunning(target.name));
+    }
+
+    let created_at = Utc::now();
+    let expires_at = created_at + chrono::Duration::from_std(ttl).expect("TTL is capped");
+    let suffix: String = uuid::Uuid::new_v4().simple().to_string().chars().take(8).collect();
+    let name = format!("{}-debug-{}", target.name, suffix);
+    let labels = HashMap::from([
+        (TARGET_LABEL.to_string(), target.name.clone()),
+        (EXPIRES_LABEL.to_string(), expires_at.to_rfc3339()),
+    ]);
+
+    let sidecar_id = state
+        .docker
+        .launch_debug_sidecar(&target, &name, &image, ttl, labels)
+        .await?;
+    let sidecar = DebugSidecar {
+        id: sidecar_id,
+        name,
+        target: target.name,
+        image,
+        created_at,
+        expires_at,
+    };
+    state.debug.insert(sidecar.clone());
+    info!(
+        "Started debug sidecar {} ({}) for {} until {}",
+        sidecar.name, sidecar.image, sidecar.target, sidecar.expires_at
+    );
+    Ok((StatusCode::CREATED, Json(sidecar)))
+}
+
+/// DELETE /api/debug-sidecars/:id - Remove a debug sidecar before it expires
+pub async fn remove_sidecar(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, DebugError> {
+    let sidecar = state.debug.take(&id).ok_or_else(|| DebugError::NotFound(id))?;
+    if let Err(e) = state.docker.discard_container(&sidecar.id).await {
+        // Keep tracking it so the reaper tries again
+        state.debug.insert(sidecar);
+        return Err(e.into());
+    }
+    info!("Removed debug sidecar {} for {}", sidecar.name, sidecar.target);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_sidecars_are_taken_once() {
+        let now = Utc::now();
+        let sidecar = |name: &str, minutes| DebugSidecar {
+            id: name.to_string(),
+            name: name.to_string(),
+            target: "api".to_string(),
+            image: DEFAULT_IMAGE.to_string(),
+            created_at: now,
+            expires_at: now + chrono::Duration::minutes(minutes),
+        };
+        let sidecars = DebugSidecars::new(DEFAULT_IMAGE);
+        sidecars.insert(sidecar("late", 10));
+        sidecars.insert(sidecar("early", -1));
+
+        let expired: Vec<String> = sidecars.take_expired(now).into_iter().map(|s| s.name).collect();
+        assert_eq!(expired, ["early"]);
+        assert!(sidecars.take_expired(now).is_empty());
+        assert_eq!(sidecars.take("late").unwrap().name, "late");
+    }
+}
@@ -0,0 +1,566 @@
+//! Notification delivery
+//!
+//! A background task follows the WebSocket hub and notifies the saved
+//! channels when a configured alert fires or resolves (`alert.firing`,
+//! `alert.resolved`) and when a container turns unhealthy or exits
+//! (`container.unhealthy`, `container.exited`), skipping channels limited to
+//! other categories or to more severe events. The first [`NotificationSink`]
+//! that handles a channel's kind formats the payload, from the channel's
+//! template unless the sink has a format of its own, and sends it:
+//! [`WebhookSink`] POSTs webhook channels, the [chat](crate::chat) sinks
+//! Slack and Discord ones, and email channels have no sink yet. Deliveries
+//! that fail with a
+//! connection error, a timeout, a 429 or a 5xx are retried with exponential
+//! backoff up to [`MAX_ATTEMPTS`] times. `GET /api/notifications/status`
+//! reports per-channel totals and the latest deliveries.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{extract::State, Json};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::{
+    chat::{DiscordSink, SlackSink},
+    models::{
+        Alert, AlertCondition, ChannelDeliveryStatus, ConfiguredAlertRule, ContainerInfo, ContainerStatus,
+        DeliveryState, NotificationChannel, NotificationDelivery, NotificationKind, NotificationStatus,
+        RenderedNotification, ServiceCategory, Severity,
+    },
+    notifications::{self, render, AlertContext, ContainerContext, NotificationContext, NotificationError},
+    websocket::WsMessage,
+    AppState,
+};
+
+pub const MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry; doubled after every further failure
+const FIRST_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Deliveries kept for the status endpoint
+const RECENT_DELIVERIES: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SinkError {
+    /// The receiver may accept the payload later
+    #[error("{0}")]
+    Retryable(String),
+    /// Sending the same payload again would fail the same way
+    #[error("{0}")]
+    Rejected(String),
+}
+
+/// Somewhere rendered notifications can be sent
+pub trait NotificationSink: Send + Sync {
+    /// Shown on the status endpoint
+    fn name(&self) -> &'static str;
+
+    fn handles(&self, kind: NotificationKind) -> bool;
+
+    /// The payload for `context`, rendered from the channel's template unless
+    /// the sink formats it itself
+    fn format(
+        &self,
+        channel: &NotificationChannel,
+        context: &NotificationContext,
+        strict: bool,
+    ) -> Result<RenderedNotification, NotificationError> {
+        render(channel, context, strict)
+    }
+
+    fn send<'a>(
+        &'a self,
+        channel: &'a NotificationChannel,
+        payload: &'a RenderedNotification,
+    ) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+/// POSTs the payload to the channel's URL
+pub struct WebhookSink {
+    http: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build the webhook HTTP client"),
+        }
+    }
+}
+
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn handles(&self, kind: NotificationKind) -> bool {
+        kind == NotificationKind::Webhook
+    }
+
+    fn send<'a>(
+        &'a self,
+        channel: &'a NotificationChannel,
+        payload: &'a RenderedNotification,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let response = self
+                .http
+                .post(&channel.target)
+                .header(reqwest::header::CONTENT_TYPE, &payload.content_type)
+                .body(payload.body.clone())
+                .send()
+                .await
+                // Without the URL, which may carry a secret
+                .map_err(|e| SinkError::Retryable(e.without_url().to_string()))?;
+            let status = response.status();
+            if status.is_success() {
+                Ok(())
+            } else if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                Err(SinkError::Retryable(format!("answered {}", status)))
+            } else {
+                Err(SinkError::Rejected(format!("answered {}", status)))
+            }
+        })
+    }
+}
+
+/// What the dispatcher notifies about `alert`, with `rules` giving the
+/// metric and severity behind it and `container` its details when it still
+/// exists
+pub fn alert_notification(
+    alert: &Alert,
+    rules: &[ConfiguredAlertRule],
+    container: Option<&ContainerInfo>,
+) -> NotificationContext {
+    let resolved = alert.resolved_at.is_some();
+    let rule = rules.iter().find(|r| r.name == alert.rule);
+    NotificationContext {
+        event: if resolved { "alert.resolved" } else { "alert.firing" }.to_string(),
+        summary: format!(
+            "{}: {} on {}{}",
+            alert.rule,
+            alert.when,
+            alert.container,
+            if resolved { " resolved" } else { "" }
+        ),
+        severity: match rule {
+            _ if resolved => Severity::Info,
+            Some(rule) => rule.severity,
+            None => Severity::Warning,
+        },
+        timestamp: alert.resolved_at.unwrap_or(alert.fired_at),
+        alert: match rule.map(|r| &r.condition) {
+            Some(AlertCondition::Stat {
+                metric,
+                operator,
+                threshold,
+            }) => Some(AlertContext {
+                rule: alert.rule.clone(),
+                metric: *metric,
+                operator: *operator,
+                threshold: *threshold,
+                value: alert.peak.unwrap_or(*threshold),
+                fired_at: alert.fired_at,
+                resolved_at: alert.resolved_at,
+            }),
+            _ => None,
+        },
+        container: container.map(ContainerContext::from).unwrap_or_else(|| ContainerContext {
+            id: String::new(),
+            name: alert.container.clone(),
+            image: String::new(),
+            status: String::new(),
+            category: ServiceCategory::from_name(&alert.container),
+            labels: HashMap::new(),
+        }),
+    }
+}
+
+/// What the dispatcher notifies about `container` having just turned unhealthy
+/// or exited
+pub fn status_notification(container: &ContainerInfo) -> NotificationContext {
+    let context = ContainerContext::from(container);
+    NotificationContext {
+        event: format!("container.{}", context.status),
+        summary: match container.status {
+            ContainerStatus::Unhealthy => format!("{} is unhealthy", container.name),
+            _ => format!("{} exited", container.name),
+        },
+        severity: Severity::Error,
+        timestamp: container.last_status_change.unwrap_or_else(Utc::now),
+        alert: None,
+        container: context,
+    }
+}
+
+/// Container statuses as last seen, to notice transitions
+#[derive(Default)]
+pub struct StatusTracker {
+    statuses: HashMap<String, ContainerStatus>,
+}
+
+impl StatusTracker {
+    pub fn new(containers: &[ContainerInfo]) -> Self {
+        Self {
+            statuses: containers.iter().map(|c| (c.id.clone(), c.status.clone())).collect(),
+        }
+    }
+
+    /// Apply a container delta, returning the containers that turned unhealthy
+    /// or exited. Containers seen for the first time are only remembered.
+    pub fn observe(&mut self, changed: &[ContainerInfo], removed: &[String]) -> Vec<ContainerInfo> {
+        for id in removed {
+            self.statuses.remove(id);
+        }
+        changed
+            .iter()
+            .filter(|c| {
+                let before = self.statuses.insert(c.id.clone(), c.status.clone());
+                matches!(c.status, ContainerStatus::Unhealthy | ContainerStatus::Exited)
+                    && before.is_some_and(|b| b != c.status)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Totals of a channel nothing was sent to yet; `kind` and `sink` are filled
+/// in from the channel when reporting
+fn no_deliveries(channel: &str) -> ChannelDeliveryStatus {
+    ChannelDeliveryStatus {
+        channel: channel.to_string(),
+        kind: NotificationKind::Webhook,
+        sink: None,
+        delivered: 0,
+        failed: 0,
+        last_delivered_at: None,
+        last_failed_at: None,
+        last_error: None,
+    }
+}
+
+#[derive(Default)]
+struct Deliveries {
+    channels: HashMap<String, ChannelDeliveryStatus>,
+    /// Newest first
+    recent: VecDeque<NotificationDelivery>,
+}
+
+/// The sinks, and how deliveries through them went
+pub struct Notifier {
+    sinks: Vec<Box<dyn NotificationSink>>,
+    first_backoff: Duration,
+    deliveries: Mutex<Deliveries>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::with_sinks(
+            vec![
+                Box::new(SlackSink::new()),
+                Box::new(DiscordSink::new()),
+                Box::new(WebhookSink::new()),
+            ],
+            FIRST_BACKOFF,
+        )
+    }
+
+    pub fn with_sinks(sinks: Vec<Box<dyn NotificationSink>>, first_backoff: Duration) -> Self {
+        Self {
+            sinks,
+            first_backoff,
+            deliveries: Mutex::new(Deliveries::default()),
+        }
+    }
+
+    fn sink(&self, kind: NotificationKind) -> Option<&dyn NotificationSink> {
+        self.sinks.iter().find(|s| s.handles(kind)).map(|s| s.as_ref())
+    }
+
+    /// The payload `channel` would get for `context`
+    pub fn format(
+        &self,
+        channel: &NotificationChannel,
+        context: &NotificationContext,
+        strict: bool,
+    ) -> Result<RenderedNotification, NotificationError> {
+        match self.sink(channel.kind) {
+            Some(sink) => sink.format(channel, context, strict),
+            None => render(channel, context, strict),
+        }
+    }
+
+    /// Deliver `context` to every channel that wants it and has a sink, each
+    /// in its own task
+    pub fn notify(self: &Arc<Self>, channels: Vec<NotificationChannel>, context: NotificationContext) {
+        for channel in channels {
+            if self.sink(channel.kind).is_none() || !notifications::wants(&channel, &context) {
+                continue;
+            }
+            let notifier = self.clone();
+            let context = context.clone();
+            tokio::spawn(async move {
+                notifier.deliver(&channel, &context).await;
+            });
+        }
+    }
+
+    /// Render and send `context` to `channel`, retrying with backoff
+    pub async fn deliver(&self, channel: &NotificationChannel, context: &NotificationContext) -> NotificationDelivery {
+        let mut delivery = NotificationDelivery {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel: channel.name.clone(),
+            event: context.event.clone(),
+            summary: context.summary.clone(),
+            state: DeliveryState::Pending,
+            attempts: 0,
+            created_at: Utc::now(),
+            finished_at: None,
+            last_error: None,
+        };
+        let Some(sink) = self.sink(channel.kind) else {
+            return self.finish(delivery, Err("no sink handles this kind of channel".to_string()));
+        };
+        let payload = match sink.format(channel, context, false) {
+            Ok(payload) => payload,
+            Err(e) => return self.finish(delivery, Err(e.to_string())),
+        };
+
+        let mut backoff = self.first_backoff;
+        loop {
+            delivery.attempts += 1;
+            self.record(&delivery);
+            match sink.send(channel, &payload).await {
+                Ok(()) => return self.finish(delivery, Ok(())),
+                Err(SinkError::Retryable(e)) if delivery.attempts < MAX_ATTEMPTS => {
+                    warn!(
+                        "Notifying '{}' failed (attempt {}), retrying in {:?}: {}",
+                        channel.name, delivery.attempts, backoff, e
+                    );
+                    delivery.last_error = Some(e);
+                }
+                Err(e) => return self.finish(delivery, Err(e.to_string())),
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    fn finish(&self, mut delivery: NotificationDelivery, outcome: Result<(), String>) -> NotificationDelivery {
+        let now = Utc::now();
+        delivery.finished_at = Some(now);
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let totals = deliveries
+            .channels
+            .entry(delivery.channel.clone())
+            .or_insert_with(|| no_deliveries(&delivery.channel));
+        match outcome {
+            Ok(()) => {
+                info!("Notified '{}' of {}", delivery.channel, delivery.event);
+                delivery.state = DeliveryState::Delivered;
+                totals.delivered += 1;
+                totals.last_delivered_at = Some(now);
+            }
+            Err(e) => {
+                warn!("Failed to notify '{}' of {}: {}", delivery.channel, delivery.event, e);
+                delivery.state = DeliveryState::Failed;
+                delivery.last_error = Some(e.clone());
+                totals.failed += 1;
+                totals.last_failed_at = Some(now);
+                totals.last_error = Some(e);
+            }
+        }
+        Self::record_locked(&mut deliveries, &delivery);
+        delivery
+    }
+
+    fn record(&self, delivery: &NotificationDelivery) {
+        Self::record_locked(&mut self.deliveries.lock().unwrap(), delivery);
+    }
+
+    fn record_locked(deliveries: &mut Deliveries, delivery: &NotificationDelivery) {
+        match deliveries.recent.iter_mut().find(|d| d.id == delivery.id) {
+            Some(existing) => *existing = delivery.clone(),
+            None => {
+                deliveries.recent.push_front(delivery.clone());
+                deliveries.recent.truncate(RECENT_DELIVERIES);
+            }
+        }
+    }
+
+    /// Totals for every one of `channels`, and the latest deliveries
+    pub fn status(&self, channels: &[NotificationChannel]) -> NotificationStatus {
+        let deliveries = self.deliveries.lock().unwrap();
+        NotificationStatus {
+            channels: channels
+                .iter()
+                .map(|channel| {
+                    let mut status = deliveries
+                        .channels
+                        .get(&channel.name)
+                        .cloned()
+                        .unwrap_or_else(|| no_deliveries(&channel.name));
+                    status.kind = channel.kind;
+                    status.sink = self.sink(channel.kind).map(|s| s.name().to_string());
+                    status
+                })
+                .collect(),
+            recent: deliveries.recent.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Spawn the background task that turns alerts and container transitions into
+/// notifications
+pub fn spawn_dispatcher(state: AppState) {
+    tokio::spawn(async move {
+        let (_, mut receiver) = state.hub.subscribe(None);
+        let mut tracker = StatusTracker::new(&state.hub.containers());
+        loop {
+            let envelope = match receiver.recv().await {
+                Ok(envelope) => envelope,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Notification dispatcher skipped {} updates", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let contexts = match envelope.message {
+                WsMessage::Alert { alert } => {
+                    let containers = state.hub.containers();
+                    let container = containers.iter().find(|c| c.name == alert.container);
+                    vec![alert_notification(&alert, &state.config.alert_rules, container)]
+                }
+                WsMessage::ContainerUpdate { containers, removed, .. } => tracker
+                    .observe(&containers, &removed)
+                    .iter()
+                    .map(status_notification)
+                    .collect(),
+                _ => continue,
+            };
+            if contexts.is_empty() {
+                continue;
+            }
+            let channels = state.notifications.list();
+            for context in contexts {
+                state.notifier.notify(channels.clone(), context);
+            }
+        }
+    });
+}
+
+/// GET /api/notifications/status - Delivery totals per channel and the latest
+/// deliveries
+pub async fn get_status(State(state): State<AppState>) -> Json<NotificationStatus> {
+    Json(state.notifier.status(&state.notifications.list()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{http::StatusCode, routing::post, Router};
+
+    use super::*;
+    use crate::docker_api::fake;
+
+    fn container(id: &str, status: ContainerStatus) -> ContainerInfo {
+        ContainerInfo {
+            id: id.to_string(),
+            status,
+            category: ServiceCategory::Blockchain,
+            ..fake::container(&format!("shop-{}-1", id))
+        }
+    }
+
+    fn channel(target: String) -> NotificationChannel {
+        NotificationChannel {
+            name: "ops".to_string(),
+            kind: NotificationKind::Webhook,
+            target,
+            template: None,
+            categories: Vec::new(),
+            min_severity: None,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        }
+    }
+
+    #[test]
+    fn only_transitions_are_notified() {
+        let mut tracker = StatusTracker::new(&[container("api", ContainerStatus::Healthy)]);
+        // A container first seen already exited is not a transition
+        let turned = tracker.observe(
+            &[container("api", ContainerStatus::Unhealthy), container("job", ContainerStatus::Exited)],
+            &[],
+        );
+        assert_eq!(turned.len(), 1);
+        let context = status_notification(&turned[0]);
+        assert_eq!(context.event, "container.unhealthy");
+        assert_eq!(context.summary, "shop-api-1 is unhealthy");
+
+        assert!(tracker.observe(&[container("api", ContainerStatus::Unhealthy)], &[]).is_empty());
+        let turned = tracker.observe(&[container("api", ContainerStatus::Exited)], &[]);
+        assert_eq!(status_notification(&turned[0]).event, "container.exited");
+
+        tracker.observe(&[], &["api".to_string()]);
+        assert!(tracker.observe(&[container("api", ContainerStatus::Exited)], &[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_are_retried() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route(
+                "/flaky",
+                post(move || {
+                    let counter = counter.clone();
+                    async move {
+                        match counter.fetch_add(1, Ordering::SeqCst) {
+                            0 | 1 => StatusCode::SERVICE_UNAVAILABLE,
+                            _ => StatusCode::NO_CONTENT,
+                        }
+                    }
+                }),
+            )
+            .route("/gone", post(|| async { StatusCode::GONE }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let notifier = Notifier::with_sinks(vec![Box::new(WebhookSink::new())], Duration::from_millis(10));
+        let context = NotificationContext::sample();
+        let flaky = channel(format!("http://{}/flaky", address));
+        let delivery = notifier.deliver(&flaky, &context).await;
+        assert_eq!(delivery.state, DeliveryState::Delivered);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // A 4xx other than 429 is not retried
+        let gone = NotificationChannel {
+            name: "archive".to_string(),
+            ..channel(format!("http://{}/gone", address))
+        };
+        let delivery = notifier.deliver(&gone, &context).await;
+        assert_eq!(delivery.state, DeliveryState::Failed);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.last_error.as_deref(), Some("answered 410 Gone"));
+
+        let status = notifier.status(&[flaky, gone]);
+        assert_eq!(status.channels[0].delivered, 1);
+        assert_eq!(status.channels[0].sink.as_deref(), Some("webhook"));
+        assert_eq!(status.channels[1].failed, 1);
+        assert_eq!(status.recent.len(), 2);
+        assert_eq!(status.recent[0].channel, "archive");
+    }
+}
@@ -0,0 +1,496 @@
+//! Container diagnostics
+//!
+//! Heuristic checks that flag containers likely to misbehave before they do,
+//! such as zombie processes piling up, PID counts approaching the limit,
+//! environment variables that point at services which do not exist, or
+//! healthchecks tuned so tightly that a slow probe marks a working container
+//! unhealthy. Host-wide kernel parameters are checked in [`kernel`].
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::models::{DiagnosticFinding, DiagnosticsReport, ProcessDiagnostics, ProcessEntry, Severity};
+
+pub mod kernel;
+
+/// `ps` arguments passed to Docker's top API
+pub const TOP_PS_ARGS: &str = "-o pid,ppid,stat,comm";
+/// `ps` columns for the process list, with usage and full command lines
+pub const PROCESS_LIST_PS_ARGS: &str = "-o pid,ppid,user,stat,pcpu,rss,etime,args";
+/// Zombie count at which a container is flagged
+pub const ZOMBIE_WARN_THRESHOLD: usize = 3;
+/// PID usage (percent of pids-limit) at which a container is flagged
+pub const PID_LIMIT_WARN_PERCENT: f64 = 80.0;
+
+/// Kernels report "no limit" as a huge sentinel rather than omitting the value
+fn effective_limit(limit: Option<u64>) -> Option<u64> {
+    limit.filter(|&l| l > 0 && l < i32::MAX as u64)
+}
+
+/// Parse the table returned by Docker's top API into process entries
+pub fn parse_top(titles: &[String], processes: &[Vec<String>]) -> Vec<ProcessEntry> {
+    let column = |name: &str| {
+        titles
+            .iter()
+            .position(|t| t.eq_ignore_ascii_case(name))
+    };
+    let pid_col = column("PID");
+    let ppid_col = column("PPID");
+    let stat_col = column("STAT").or_else(|| column("S"));
+    let cmd_col = column("COMMAND")
+        .or_else(|| column("CMD"))
+        .or_else(|| column("Name"));
+    let user_col = column("USER").or_else(|| column("UID"));
+    let cpu_col = column("%CPU").or_else(|| column("C"));
+    let rss_col = column("RSS");
+    let elapsed_col = column("ELAPSED");
+
+    processes
+        .iter()
+        .map(|row| {
+            let get = |col: Option<usize>| col.and_then(|i| row.get(i)).cloned();
+            ProcessEntry {
+                pid: get(pid_col).and_then(|p| p.parse().ok()).unwrap_or(0),
+                ppid: get(ppid_col).and_then(|p| p.parse().ok()),
+                state: get(stat_col).unwrap_or_default(),
+                command: get(cmd_col).unwrap_or_default(),
+                user: get(user_col),
+                cpu_percent: get(cpu_col).and_then(|c| c.parse().ok()),
+                memory_kb: get(rss_col).and_then(|r| r.parse().ok()),
+                elapsed: get(elapsed_col),
+            }
+        })
+        .collect()
+}
+
+/// Zombie processes have `Z` as the first character of their state
+pub fn is_zombie(process: &ProcessEntry) -> bool {
+    process.state.starts_with('Z')
+}
+
+/// Warnings derived from a PID count and limit, usable without a top call
+pub fn pid_limit_warning(pids: u64, limit: Option<u64>) -> Option<String> {
+    let limit = effective_limit(limit)?;
+    let percent = pids as f64 / limit as f64 * 100.0;
+    (percent >= PID_LIMIT_WARN_PERCENT)
+        .then(|| format!("PID count {} is {:.0}% of pids-limit {}", pids, percent, limit))
+}
+
+pub fn zombie_warning(zombies: usize) -> Option<String> {
+    (zombies >= ZOMBIE_WARN_THRESHOLD).then(|| {
+        format!("{} zombie processes; the init process is not reaping children", zombies)
+    })
+}
+
+/// Build the process report for a container from its top output and pids-limit
+pub fn process_report(
+    container_id: String,
+    container_name: String,
+    processes: Vec<ProcessEntry>,
+    pids_limit: Option<u64>,
+) -> ProcessDiagnostics {
+    let pids_limit = effective_limit(pids_limit);
+    let zombies: Vec<ProcessEntry> = processes.iter().filter(|p| is_zombie(p)).cloned().collect();
+    let process_count = processes.len() as u64;
+
+    let warnings = [
+        zombie_warning(zombies.len()),
+        pid_limit_warning(process_count, pids_limit),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    ProcessDiagnostics {
+        container_id,
+        container_name,
+        process_count,
+        zombie_count: zombies.len(),
+        zombies,
+        pids_limit,
+        pid_usage_percent: pids_limit
+            .map(|l| (process_count as f64 / l as f64 * 10000.0).round() / 100.0),
+        warnings,
+    }
+}
+
+// =============================================================================
+// ENVIRONMENT SANITY CHECKS
+// =============================================================================
+
+/// Values pulled from a container inspect that the environment checks need
+#[derive(Debug, Clone, Default)]
+pub struct EnvProfile {
+    pub container_id: String,
+    pub container_name: String,
+    pub env: Vec<String>,
+    pub exposed_ports: Vec<u16>,
+    pub mount_destinations: Vec<String>,
+    /// IPs on the container's networks
+    pub addresses: Vec<std::net::IpAddr>,
+}
+
+impl EnvProfile {
+    fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env.iter().filter_map(|e| e.split_once('='))
+    }
+
+    fn has_var(&self, name: &str) -> bool {
+        self.vars().any(|(k, _)| k == name)
+    }
+}
+
+fn finding(
+    profile: &EnvProfile,
+    check: &str,
+    severity: Severity,
+    message: String,
+    suggestion: Option<String>,
+) -> DiagnosticFinding {
+    DiagnosticFinding {
+        container_id: profile.container_id.clone(),
+        container_name: profile.container_name.clone(),
+        check: check.to_string(),
+        severity,
+        message,
+        suggestion,
+    }
+}
+
+/// Hostnames that never refer to another container
+fn is_external_host(host: &str) -> bool {
+    host.is_empty()
+        || host == "localhost"
+        || host == "host.docker.internal"
+        || host.parse::<std::net::IpAddr>().is_ok()
+        || host.contains('.')
+        || host.contains('$')
+}
+
+/// Hostname in an address such as `db:5432`, or in the authority of a URL
+/// such as `postgres://user:pw@db:5432/app` when it has a scheme
+fn address_host(value: &str) -> &str {
+    let authority = match value.split_once("://") {
+        Some((_, rest)) => {
+            let rest = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+            rest.rsplit_once('@').map_or(rest, |(_, host)| host)
+        }
+        None => value,
+    };
+    authority.split(':').next().unwrap_or("").trim()
+}
+
+/// Host a variable points at: the value of `*_HOST` or `*_ADDR`, or the
+/// host of a `*_URL` or `*_URI`
+pub fn referenced_host<'a>(key: &str, value: &'a str) -> Option<&'a str> {
+    let address = key.ends_with("_HOST") || key.ends_with("_ADDR");
+    let url = (key.ends_with("_URL") || key.ends_with("_URI")) && value.contains("://");
+    if !address && !url {
+        return None;
+    }
+    let host = address_host(value);
+    (!is_external_host(host)).then_some(host)
+}
+
+/// A container naming another one in its environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvDependency {
+    pub source_id: String,
+    pub target_id: String,
+    pub variable: String,
+}
+
+/// Resolve `*_HOST`/`*_URL`-style variables to the containers they name; `hosts`
+/// maps each resolvable hostname to the ids of the containers answering to it
+pub fn env_dependencies(profiles: &[EnvProfile], hosts: &HashMap<String, Vec<String>>) -> Vec<EnvDependency> {
+    let mut dependencies = Vec::new();
+    for profile in profiles {
+        for (key, value) in profile.vars() {
+            let Some(targets) = referenced_host(key, value).and_then(|h| hosts.get(&h.to_lowercase())) else {
+                continue;
+            };
+            for target in targets.iter().filter(|t| **t != profile.container_id) {
+                dependencies.push(EnvDependency {
+                    source_id: profile.container_id.clone(),
+                    target_id: target.clone(),
+                    variable: key.to_string(),
+                });
+            }
+        }
+    }
+    dependencies
+}
+
+/// Run all environment checks; `known_hosts` holds every resolvable container
+/// name, compose service name and network alias
+pub fn env_findings(profiles: &[EnvProfile], known_hosts: &HashSet<String>) -> Vec<DiagnosticFinding> {
+    let mut findings = Vec::new();
+
+    for profile in profiles {
+        let has_localtime = profile
+            .mount_destinations
+            .iter()
+            .any(|d| d == "/etc/localtime" || d == "/etc/timezone");
+        if !profile.has_var("TZ") && !has_localtime {
+            findings.push(finding(
+                profile,
+                "timezone",
+                Severity::Info,
+                "No TZ variable or /etc/localtime mount; timestamps will be in UTC".to_string(),
+                Some("Set TZ (e.g. TZ=UTC) to make the timezone explicit".to_string()),
+            ));
+        }
+
+        if !profile.has_var("LANG") && !profile.has_var("LC_ALL") {
+            findings.push(finding(
+                profile,
+                "locale",
+                Severity::Info,
+                "Neither LANG nor LC_ALL is set; non-ASCII output may be mangled".to_string(),
+                Some("Set LANG=C.UTF-8".to_string()),
+            ));
+        }
+
+        for (key, value) in profile.vars() {
+            if key.ends_with("_HOST") {
+                let host = address_host(value);
+                if !is_external_host(host) && !known_hosts.contains(&host.to_lowercase()) {
+                    findings.push(finding(
+                        profile,
+                        "dangling-host",
+                        Severity::Warning,
+                        format!("{}={} does not match any container, service or network alias", key, value),
+                        Some(format!("Check that a container named '{}' exists and shares a network", host)),
+                    ));
+                }
+            }
+
+            if key.ends_with("_PORT") && !key.ends_with("_HOST_PORT") {
+                let declared_for_self = !profile.vars().any(|(k, _)| {
+                    k.strip_suffix("_HOST")
+                        .is_some_and(|prefix| key.strip_suffix("_PORT") == Some(prefix))
+                });
+                if let (true, Ok(port)) = (declared_for_self, value.trim().parse::<u16>()) {
+                    if !profile.exposed_ports.contains(&port) {
+                        findings.push(finding(
+                            profile,
+                            "unexposed-port",
+                            Severity::Info,
+                            format!("{}={} but port {} is not exposed or published", key, value, port),
+                            Some(format!("Add EXPOSE {} or a port mapping if other services connect to it", port)),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+/// Convert process reports into findings for the combined diagnostics report
+pub fn process_findings(reports: &[ProcessDiagnostics]) -> Vec<DiagnosticFinding> {
+    reports
+        .iter()
+        .flat_map(|r| {
+            r.warnings.iter().map(|w| DiagnosticFinding {
+                container_id: r.container_id.clone(),
+                container_name: r.container_name.clone(),
+                check: "processes".to_string(),
+                severity: Severity::Warning,
+                message: w.clone(),
+                suggestion: Some("Run the container with an init process (docker run --init)".to_string())
+                    .filter(|_| r.zombie_count >= ZOMBIE_WARN_THRESHOLD),
+            })
+        })
+        .collect()
+}
+
+// =============================================================================
+// HEALTHCHECK TIMING
+// =============================================================================
+
+/// Docker's defaults for unset healthcheck options
+pub const DEFAULT_HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_HEALTH_RETRIES: u32 = 3;
+/// Share of the timeout a probe may take before the timeout counts as tight
+pub const TIGHT_TIMEOUT_RATIO: f64 = 0.8;
+
+/// One entry of a container's health log
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub start: DateTime<Utc>,
+    pub duration: Duration,
+    /// 0 healthy, 1 unhealthy, -1 when the probe hit the timeout
+    pub exit_code: i64,
+}
+
+/// A container's healthcheck settings, with defaults filled in, and its
+/// recent probes oldest first
+#[derive(Debug, Clone)]
+pub struct HealthProfile {
+    pub container_id: String,
+    pub container_name: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub start_period: Duration,
+    pub retries: u32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub probes: Vec<Probe>,
+}
+
+/// Whole seconds, rounded up, as Docker and Compose write durations
+fn secs(duration: Duration) -> String {
+    format!("{}s", duration.as_secs() + u64::from(duration.subsec_nanos() > 0))
+}
+
+fn health_finding(profile: &HealthProfile, message: String, suggestion: String) -> DiagnosticFinding {
+    DiagnosticFinding {
+        container_id: profile.container_id.clone(),
+        container_name: profile.container_name.clone(),
+        check: "healthcheck-timing".to_string(),
+        severity: Severity::Warning,
+        message,
+        suggestion: Some(suggestion),
+    }
+}
+
+/// Time from start until the first passing probe, when the health log covers
+/// a startup that failed probes before passing
+fn observed_startup(profile: &HealthProfile) -> Option<Duration> {
+    let started_at = profile.started_at?;
+    let first = profile.probes.first()?;
+    if first.start < started_at || first.exit_code == 0 {
+        return None;
+    }
+    let passed = profile.probes.iter().find(|p| p.exit_code == 0)?;
+    (passed.start - started_at).to_std().ok()
+}
+
+/// Flag healthchecks whose timeout, interval, start period or retries leave
+/// too little headroom for the probes actually observed
+pub fn healthcheck_findings(profiles: &[HealthProfile]) -> Vec<DiagnosticFinding> {
+    let mut findings = Vec::new();
+    for profile in profiles {
+        let slowest = profile.probes.iter().map(|p| p.duration).max();
+        let timed_out = profile.probes.iter().filter(|p| p.exit_code == -1).count();
+        let mut timeout = profile.timeout;
+
+        if let Some(slowest) = slowest {
+            if timed_out > 0 || slowest.as_secs_f64() >= profile.timeout.as_secs_f64() * TIGHT_TIMEOUT_RATIO {
+                // Twice the slowest probe, and never less than what just proved too short
+                timeout = Duration::from_secs((slowest * 2).as_secs_f64().ceil() as u64)
+                    .max(profile.timeout + Duration::from_secs(1));
+                let observed = if timed_out > 0 {
+                    format!("{} of the last {} probes timed out", timed_out, profile.probes.len())
+                } else {
+                    format!("the slowest recent probe took {:.1}s", slowest.as_secs_f64())
+                };
+                findings.push(health_finding(
+                    profile,
+                    format!("Healthcheck timeout {} is tight: {}", secs(profile.timeout), observed),
+                    format!("Raise the timeout to {} (--health-timeout / healthcheck.timeout)", secs(timeout)),
+                ));
+            }
+        }
+
+        if timeout >= profile.interval {
+            let subject = if timeout == profile.timeout {
+                format!("Healthcheck timeout {} is", secs(timeout))
+            } else {
+                format!("A {} timeout would be", secs(timeout))
+            };
+            findings.push(health_finding(
+                profile,
+                format!(
+                    "{} no shorter than the interval {}; probes would run back to back",
+                    subject,
+                    secs(profile.interval)
+                ),
+                format!("Raise the interval to {} (--health-interval / healthcheck.interval)", secs(timeout * 2)),
+            ));
+        }
+
+        if let Some(startup) = observed_startup(profile).filter(|&s| s > profile.start_period) {
+            let suggested = Duration::from_secs((startup.as_secs_f64() * 1.5).ceil() as u64);
+            findings.push(health_finding(
+                profile,
+                format!(
+                    "Start period {} is shorter than the {} the container took to pass its first probe; failures during startup count towards unhealthy",
+                    secs(profile.start_period),
+                    secs(startup)
+                ),
+                format!("Set the start period to {} (--health-start-period / healthcheck.start_period)", secs(suggested)),
+            ));
+        }
+
+        if profile.retries < 2 {
+            findings.push(health_finding(
+                profile,
+                format!("Healthcheck retries is {}; one slow probe marks the container unhealthy", profile.retries),
+                format!("Set retries to {} (--health-retries / healthcheck.retries)", DEFAULT_HEALTH_RETRIES),
+            ));
+        }
+    }
+    findings
+}
+
+/// Assemble a report, most severe findings first
+pub fn build_report(mut findings: Vec<DiagnosticFinding>, containers_checked: usize) -> DiagnosticsReport {
+    findings.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.container_name.cmp(&b.container_name))
+    });
+
+    let count = |sev: Severity| findings.iter().filter(|f| f.severity == sev).count();
+    DiagnosticsReport {
+        containers_checked,
+        errors: count(Severity::Error),
+        warnings: count(Severity::Warning),
+        infos: count(Severity::Info),
+        findings,
+        generated_at: chrono::Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_rows_with_usage_columns() {
+        let titles: Vec<String> = ["PID", "PPID", "USER", "STAT", "%CPU", "RSS", "ELAPSED", "COMMAND"]
+            .map(String::from)
+            .to_vec();
+        let row: Vec<String> = ["812", "1", "www-data", "S", "12.5", "20480", "01:02:03", "php-fpm: pool www"]
+            .map(String::from)
+            .to_vec();
+        let process = &parse_top(&titles, &[row])[0];
+        assert_eq!((process.pid, process.ppid), (812, Some(1)));
+        assert_eq!(process.user.as_deref(), Some("www-data"));
+        assert_eq!((process.cpu_percent, process.memory_kb), (Some(12.5), Some(20480)));
+        assert_eq!(process.elapsed.as_deref(), Some("01:02:03"));
+        assert_eq!(process.command, "php-fpm: pool www");
+    }
+
+    #[test]
+    fn host_variables_may_hold_urls() {
+        assert_eq!(referenced_host("DB_HOST", "db:5432"), Some("db"));
+        assert_eq!(referenced_host("DB_HOST", "http://db:5432"), Some("db"));
+        assert_eq!(referenced_host("API_ADDR", "grpc://user@api:9000/v1"), Some("api"));
+        assert_eq!(referenced_host("DATABASE_URL", "postgres://u:pw@db:5432/app"), Some("db"));
+        assert_eq!(referenced_host("DATABASE_URL", "db:5432"), None);
+        assert_eq!(referenced_host("UPSTREAM_HOST", "https://example.com"), None);
+
+        let profile = EnvProfile {
+            env: vec!["TZ=UTC".into(), "LANG=C.UTF-8".into(), "CACHE_HOST=http://cache:6379".into()],
+            ..Default::default()
+        };
+        let known: HashSet<String> = ["cache".to_string()].into();
+        assert!(env_findings(&[profile], &known).is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+//! Host kernel and network parameter diagnostics
+//!
+//! Reads the sysctls behind a family of "mysterious" container failures (dropped
+//! connections under load, file watchers dying with ENOSPC, no outbound
+//! traffic from bridge networks) and flags values likely to cause them.
+//!
+//! Values come from `<proc>/sys`, where `<proc>` is `FLOWSCOPE_HOST_PROC`
+//! (default `/proc`); mount the host's `/proc` there when FlowScope runs in a
+//! container. `net.*` parameters are per network namespace, so they describe
+//! the host only when FlowScope shares its network.
+
+use std::path::{Path, PathBuf};
+
+use crate::models::{HostDiagnostics, HostFinding, HostParameter, Severity};
+
+/// Every parameter read, in report order
+pub const PARAMETERS: &[&str] = &[
+    "net.core.somaxconn",
+    "net.ipv4.ip_forward",
+    "net.netfilter.nf_conntrack_count",
+    "net.netfilter.nf_conntrack_max",
+    "fs.file-nr",
+    "fs.file-max",
+    "fs.inotify.max_user_watches",
+    "fs.inotify.max_user_instances",
+    "vm.max_map_count",
+];
+
+/// Share of a kernel table in use at which it is flagged
+pub const TABLE_WARN_PERCENT: f64 = 80.0;
+
+/// Parameters flagged when below a minimum
+const MINIMUMS: &[(&str, u64, Severity, &str, &str)] = &[
+    (
+        "net.core.somaxconn",
+        1024,
+        Severity::Warning,
+        "listen backlogs are capped at this value, so bursts of new connections are dropped or reset",
+        "sysctl -w net.core.somaxconn=4096, or per container with --sysctl net.core.somaxconn=4096",
+    ),
+    (
+        "fs.file-max",
+        65536,
+        Severity::Warning,
+        "the whole host runs out of file descriptors and containers fail with 'too many open files'",
+        "sysctl -w fs.file-max=1048576",
+    ),
+    (
+        "fs.inotify.max_user_watches",
+        65536,
+        Severity::Warning,
+        "file watchers in dev servers, log shippers and config reloaders fail with ENOSPC ('no space left on device'); containers running as the same uid share this budget",
+        "sysctl -w fs.inotify.max_user_watches=524288",
+    ),
+    (
+        "fs.inotify.max_user_instances",
+        256,
+        Severity::Warning,
+        "starting another file watcher fails with 'too many open files' once every uid's instances are used",
+        "sysctl -w fs.inotify.max_user_instances=1024",
+    ),
+    (
+        "vm.max_map_count",
+        262144,
+        Severity::Info,
+        "Elasticsearch, OpenSearch and some JVM services refuse to start or crash under memory-mapped load",
+        "sysctl -w vm.max_map_count=262144",
+    ),
+];
+
+/// Directory holding sysctls, under `FLOWSCOPE_HOST_PROC` or `/proc`
+pub fn sysctl_root() -> PathBuf {
+    crate::procfs::proc_root().join("sys")
+}
+
+/// Read every parameter in [`PARAMETERS`], whitespace-normalised
+pub fn read_parameters(root: &Path) -> Vec<(String, Option<String>)> {
+    PARAMETERS
+        .iter()
+        .map(|name| {
+            let value = std::fs::read_to_string(root.join(name.replace('.', "/")))
+                .ok()
+                .map(|v| v.split_whitespace().collect::<Vec<_>>().join(" "));
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn finding(parameter: &str, severity: Severity, message: String, suggestion: &str) -> HostFinding {
+    HostFinding {
+        parameter: parameter.to_string(),
+        severity,
+        message,
+        suggestion: Some(suggestion.to_string()),
+    }
+}
+
+/// Flag parameters likely to cause container failures
+pub fn evaluate(values: Vec<(String, Option<String>)>) -> HostDiagnostics {
+    let value = |name: &str| {
+        values
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    };
+    let number = |name: &str| value(name).and_then(|v| v.parse::<u64>().ok());
+    let mut findings = Vec::new();
+
+    for (name, minimum, severity, consequence, suggestion) in MINIMUMS {
+        if let Some(current) = number(name).filter(|v| v < minimum) {
+            findings.push(finding(
+                name,
+                *severity,
+                format!("{} is {} (below {}): {}", name, current, minimum, consequence),
+                suggestion,
+            ));
+        }
+    }
+
+    if value("net.ipv4.ip_forward").is_some_and(|v| v != "1") {
+        findings.push(finding(
+            "net.ipv4.ip_forward",
+            Severity::Error,
+            "IP forwarding is disabled, so containers on bridge networks cannot reach anything outside the host".to_string(),
+            "sysctl -w net.ipv4.ip_forward=1 and persist it in /etc/sysctl.d; check that nothing resets it on boot",
+        ));
+    }
+
+    // fs.file-nr is "allocated unused max"
+    let file_nr: Vec<u64> = value("fs.file-nr")
+        .map(|v| v.split(' ').filter_map(|n| n.parse().ok()).collect())
+        .unwrap_or_default();
+    let tables = [
+        (
+            "fs.file-nr",
+            file_nr.first().copied(),
+            file_nr.get(2).copied(),
+            "file handles",
+            "the host is about to run out of file descriptors",
+            "find the process holding them (ls /proc/*/fd | wc -l per container) or raise fs.file-max",
+        ),
+        (
+            "net.netfilter.nf_conntrack_count",
+            number("net.netfilter.nf_conntrack_count"),
+            number("net.netfilter.nf_conntrack_max"),
+            "conntrack entries",
+            "new connections are silently dropped once the table is full ('nf_conntrack: table full, dropping packet')",
+            "sysctl -w net.netfilter.nf_conntrack_max=262144, or look for a container opening short-lived connections in a loop",
+        ),
+    ];
+    for (name, used, max, what, consequence, suggestion) in tables {
+        let (Some(used), Some(max)) = (used, max.filter(|m| *m > 0)) else {
+            continue;
+        };
+        let percent = used as f64 / max as f64 * 100.0;
+        if percent >= TABLE_WARN_PERCENT {
+            findings.push(finding(
+                name,
+                Severity::Warning,
+                format!("{} of {} {} in use ({:.0}%): {}", used, max, what, percent, consequence),
+                suggestion,
+            ));
+        }
+    }
+
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    let recommended = |name: &str| match name {
+        "net.ipv4.ip_forward" => Some("1".to_string()),
+        _ => MINIMUMS
+            .iter()
+            .find(|(n, ..)| *n == name)
+            .map(|(_, minimum, ..)| format!(">= {}", minimum)),
+    };
+    HostDiagnostics {
+        parameters: values
+            .iter()
+            .map(|(name, value)| HostParameter {
+                name: name.clone(),
+                value: value.clone(),
+                recommended: recommended(name),
+            })
+            .collect(),
+        errors: findings.iter().filter(|f| f.severity == Severity::Error).count(),
+        warnings: findings.iter().filter(|f| f.severity == Severity::Warning).count(),
+        findings,
+        generated_at: chrono::Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_risky_values() {
+        let values = [
+            ("net.core.somaxconn", Some("128")),
+            ("net.ipv4.ip_forward", Some("0")),
+            ("net.netfilter.nf_conntrack_count", Some("900")),
+            ("net.netfilter.nf_conntrack_max", Some("1000")),
+            ("fs.file-nr", Some("2048 0 9223372036854775807")),
+            ("fs.file-max", Some("9223372036854775807")),
+            ("fs.inotify.max_user_watches", Some("8192")),
+            ("fs.inotify.max_user_instances", Some("1024")),
+            ("vm.max_map_count", None),
+        ]
+        .into_iter()
+        .map(|(n, v)| (n.to_string(), v.map(str::to_string)))
+        .collect();
+
+        let report = evaluate(values);
+        let flagged: Vec<&str> = report.findings.iter().map(|f| f.parameter.as_str()).collect();
+        assert_eq!(
+            flagged,
+            [
+                "net.ipv4.ip_forward",
+                "net.core.somaxconn",
+                "fs.inotify.max_user_watches",
+                "net.netfilter.nf_conntrack_count",
+            ]
+        );
+        assert_eq!((report.errors, report.warnings), (1, 3));
+        assert_eq!(report.parameters[0].recommended.as_deref(), Some(">= 1024"));
+    }
+}
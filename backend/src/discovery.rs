@@ -2,40 +2,199 @@
 //!
 //! Connects to Docker daemon and discovers container topology,
 //! network relationships, and generates flowchart data.
+//!
+//! Listings are cached while the daemon's event stream is trusted (see
+//! [`crate::update_feed`]): the event collector passes every event to
+//! [`DockerDiscovery::observe_event`], and any event that can change how a
+//! container is listed drops the cache, so topology, flowchart and container
+//! requests reuse one listing until something actually changes.
+
+use bollard::models::{ChangeType, MountPointTypeEnum};
+use bollard::Docker;
+use chrono::{DateTime, TimeZone, Utc};
+use futures_util::stream::BoxStream;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::categorize::{Categorizers, Subject};
+use crate::cgroup;
+use crate::changes::{self, ChangeTracker};
+use crate::diagnostics;
+use crate::graph::{self, DependencyGraph, EdgeKind, GraphEdge};
+use crate::docker_api::{DockerApi, ExecOutput, ExecSession, LogWindow};
+use crate::preflight::{self, PreflightContext};
+use crate::log_rates::LogRateTable;
+use crate::probe::ProbeTable;
+use crate::procfs;
+use crate::projects;
+use crate::sockets;
+use crate::system;
+use crate::host::{self, CommittedLimits};
+use crate::hosts;
+use crate::models::*;
+use crate::update_feed::SAFETY_POLL;
+use crate::vulnerabilities::VulnerabilityTable;
+
+/// Container event actions that leave the listing as it was; health checks
+/// run an exec every few seconds
+const QUIET_ACTIONS: &[&str] = &[
+    "attach", "detach", "top", "resize", "export", "commit", "copy", "archive-path", "extract-to-dir",
+];
+
+/// Stands in for environment values in redacted inspect responses
+const REDACTED: &str = "[redacted]";
+
+/// Blank the values of `NAME=value` environment entries
+fn redact_environment(env: &mut [String]) {
+    for variable in env.iter_mut() {
+        if let Some((name, _)) = variable.split_once('=') {
+            *variable = format!("{}={}", name, REDACTED);
+        }
+    }
+}
 
-use bollard::{
-    container::{ListContainersOptions, LogsOptions, RestartContainerOptions, StopContainerOptions, InspectContainerOptions, StatsOptions},
-    image::ListImagesOptions,
-    network::ListNetworksOptions,
-    Docker,
-};
-use chrono::{TimeZone, Utc};
-use futures_util::StreamExt;
-use std::collections::HashMap;
+/// Whether `event` can change how a container is listed
+pub fn changes_listing(event: &DockerEvent) -> bool {
+    event.event_type == "container"
+        && !event.action.starts_with("exec_")
+        && !QUIET_ACTIONS.contains(&event.action.as_str())
+}
 
-use crate::models::*;
+/// The last listing, reused while events vouch for it
+#[derive(Default)]
+struct ContainerCache {
+    listing: Option<(Vec<ContainerInfo>, Instant)>,
+    /// Whether events are reliable enough to invalidate the listing
+    trusted: bool,
+    /// Bumped on every invalidation, so a listing that raced an event is
+    /// not cached
+    generation: u64,
+}
 
 /// Docker discovery service
-pub struct DockerDiscovery {
-    docker: Docker,
+pub struct DockerDiscovery<D = Docker> {
+    docker: D,
+    /// Host `/proc` for descriptor, inotify and socket figures; none without it
+    proc_root: Option<PathBuf>,
+    io_rates: cgroup::IoRateTracker,
+    changes: ChangeTracker,
+    /// Latest HTTP health probes, set on every listing
+    probes: ProbeTable,
+    /// Latest vulnerability counts, set on every listing
+    vulnerabilities: VulnerabilityTable,
+    /// Error and warning lines counted from followed logs, for node metrics
+    log_rates: LogRateTable,
+    categorizers: Categorizers,
+    cache: Mutex<ContainerCache>,
 }
 
-impl DockerDiscovery {
-    pub fn new(docker: Docker) -> Self {
-        Self { docker }
+impl<D: DockerApi> DockerDiscovery<D> {
+    pub fn new(docker: D) -> Self {
+        Self {
+            docker,
+            proc_root: None,
+            io_rates: cgroup::IoRateTracker::default(),
+            changes: ChangeTracker::default(),
+            probes: ProbeTable::default(),
+            vulnerabilities: VulnerabilityTable::default(),
+            log_rates: LogRateTable::default(),
+            categorizers: Categorizers::default(),
+            cache: Mutex::default(),
+        }
+    }
+
+    /// The client underneath, so tests elsewhere can see what was done with it
+    #[cfg(test)]
+    pub fn api(&self) -> &D {
+        &self.docker
+    }
+
+    /// Decide categories with this chain of strategies
+    pub fn with_categorizers(mut self, categorizers: Categorizers) -> Self {
+        self.categorizers = categorizers;
+        self
+    }
+
+    pub fn categorizers(&self) -> &Categorizers {
+        &self.categorizers
+    }
+
+    pub fn probes(&self) -> &ProbeTable {
+        &self.probes
+    }
+
+    pub fn vulnerabilities(&self) -> &VulnerabilityTable {
+        &self.vulnerabilities
+    }
+
+    pub fn log_rates(&self) -> &LogRateTable {
+        &self.log_rates
+    }
+
+    /// Date a daemon event in the change times containers are listed with,
+    /// dropping the cached listing when the event changes it
+    pub fn observe_event(&self, event: &DockerEvent) {
+        self.changes.observe_event(event);
+        if changes_listing(event) {
+            self.invalidate_cache();
+        }
+    }
+
+    /// Cache listings only while events are trusted to announce changes
+    pub fn trust_cache(&self, trusted: bool) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.trusted = trusted;
+        if !trusted {
+            cache.listing = None;
+            cache.generation += 1;
+        }
+    }
+
+    /// List afresh on the next request
+    pub fn invalidate_cache(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.listing = None;
+        cache.generation += 1;
+    }
+
+    /// Read per-container descriptor and inotify usage, and the host's
+    /// memory, load and disk, from this `/proc` and filesystem; only for a
+    /// daemon running on this host
+    pub fn with_proc_root(mut self, root: PathBuf) -> Self {
+        self.proc_root = Some(root);
+        self
     }
 
-    /// Get all containers with their information
+    /// Get all containers with their information, from the cache while it
+    /// is trusted and no older than the publisher's safety poll
     pub async fn list_containers(&self) -> Result<Vec<ContainerInfo>, bollard::errors::Error> {
-        let options = ListContainersOptions::<String> {
-            all: true,
-            ..Default::default()
-        };
+        {
+            let cache = self.cache.lock().unwrap();
+            if let Some((containers, listed)) = cache.listing.as_ref().filter(|_| cache.trusted) {
+                if listed.elapsed() < SAFETY_POLL {
+                    let mut containers = containers.clone();
+                    self.probes.apply(&mut containers);
+                    self.vulnerabilities.apply(&mut containers);
+                    return Ok(containers);
+                }
+            }
+        }
+        self.refresh_containers().await
+    }
 
-        let containers = self.docker.list_containers(Some(options)).await?;
+    /// List containers from the daemon, refreshing the cache
+    pub async fn refresh_containers(&self) -> Result<Vec<ContainerInfo>, bollard::errors::Error> {
+        let (started, generation) = (Instant::now(), self.cache.lock().unwrap().generation);
+        let containers = self.docker.list_containers().await?;
+        let network_projects = self.network_projects().await;
+        let listed_at = Utc::now();
         let mut result = Vec::new();
 
         for container in containers {
+            let fingerprint = changes::fingerprint(&container);
+            let image_id = container.image_id.clone().unwrap_or_default();
             let id = container.id.clone().unwrap_or_default();
             let names = container.names.unwrap_or_default();
             let name = names
@@ -73,22 +232,34 @@ impl DockerDiscovery {
                 .into_iter()
                 .map(|p| PortMapping {
                     host_port: p.public_port,
-                    container_port: p.private_port as u16,
+                    container_port: p.private_port,
                     protocol: p.typ.map(|t| format!("{:?}", t).to_lowercase()).unwrap_or_else(|| "tcp".to_string()),
                 })
                 .collect();
 
             // Get networks
-            let networks: Vec<String> = container
+            let mut networks: Vec<String> = container
                 .network_settings
                 .as_ref()
                 .and_then(|ns| ns.networks.as_ref())
                 .map(|nets| nets.keys().cloned().collect())
                 .unwrap_or_default();
+            networks.sort();
 
             // Get labels
             let labels = container.labels.unwrap_or_default();
 
+            // Named volumes; bind mounts and tmpfs have no name to share
+            let mut volume_names: Vec<String> = container
+                .mounts
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|m| m.typ == Some(MountPointTypeEnum::VOLUME))
+                .filter_map(|m| m.name)
+                .collect();
+            volume_names.sort();
+            volume_names.dedup();
+
             // Check for Rust equivalent (convention: name ends with -rust-prod)
             let rust_equivalent = if !name.contains("rust") {
                 let rust_name = name.replace("-prod", "-rust-prod");
@@ -104,9 +275,15 @@ impl DockerDiscovery {
                 .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
                 .unwrap_or_else(Utc::now);
 
-            let category = ServiceCategory::from_name(&name);
+            let category = self.categorizers.categorize(Subject {
+                name: &name,
+                image: &image,
+                labels: &labels,
+            });
+            let memberships = memberships(&category, &labels, &networks, &network_projects);
+            let host = labels.get(hosts::HOST_LABEL).cloned();
 
-            result.push(ContainerInfo {
+            let mut info = ContainerInfo {
                 id: id.chars().take(12).collect(),
                 name,
                 image,
@@ -117,97 +294,81 @@ impl DockerDiscovery {
                 networks,
                 created,
                 labels,
+                host,
                 rust_equivalent,
                 stats: None, // Stats fetched separately for performance
                 image_size_mb: None,
-            });
+                memberships,
+                volume_names,
+                last_started: None,
+                last_status_change: None,
+                last_image_change: None,
+                last_config_change: None,
+                probe: None,
+                vulnerabilities: None,
+            };
+            self.changes.observe(&info, &image_id, fingerprint, listed_at).apply(&mut info);
+            result.push(info);
         }
 
         // Sort by name for consistent output
         result.sort_by(|a, b| a.name.cmp(&b.name));
 
+        let mut cache = self.cache.lock().unwrap();
+        if cache.trusted && cache.generation == generation {
+            cache.listing = Some((result.clone(), started));
+        }
+        self.probes.apply(&mut result);
+        self.vulnerabilities.apply(&mut result);
         Ok(result)
     }
 
-    /// Get container stats (CPU, Memory, Network I/O) for a specific container
-    pub async fn get_container_stats(&self, container_id: &str) -> Result<Option<ContainerStats>, bollard::errors::Error> {
-        let options = StatsOptions {
-            stream: false,
-            one_shot: true,
+    /// Compose project owning each network Compose created, by network name;
+    /// empty when networks cannot be listed
+    async fn network_projects(&self) -> HashMap<String, String> {
+        let networks = match self.docker.list_networks().await {
+            Ok(networks) => networks,
+            Err(e) => {
+                tracing::debug!("Listing networks for memberships failed: {}", e);
+                return HashMap::new();
+            }
         };
-        
-        let mut stream = self.docker.stats(container_id, Some(options));
-        
-        if let Some(result) = stream.next().await {
-            match result {
-                Ok(stats) => {
-                    // Calculate CPU percentage
-                    let cpu_delta = stats.cpu_stats.cpu_usage.total_usage.saturating_sub(
-                        stats.precpu_stats.cpu_usage.total_usage
-                    );
-                    let system_delta = stats.cpu_stats.system_cpu_usage.unwrap_or(0).saturating_sub(
-                        stats.precpu_stats.system_cpu_usage.unwrap_or(0)
-                    );
-                    let num_cpus = stats.cpu_stats.online_cpus.unwrap_or(1) as f64;
-                    
-                    let cpu_percent = if system_delta > 0 && cpu_delta > 0 {
-                        (cpu_delta as f64 / system_delta as f64) * num_cpus * 100.0
-                    } else {
-                        0.0
-                    };
-
-                    // Calculate memory usage
-                    let memory_usage = stats.memory_stats.usage.unwrap_or(0) as f64 / (1024.0 * 1024.0);
-                    let memory_limit = stats.memory_stats.limit.unwrap_or(1) as f64 / (1024.0 * 1024.0);
-                    let memory_percent = if memory_limit > 0.0 {
-                        (memory_usage / memory_limit) * 100.0
-                    } else {
-                        0.0
-                    };
+        networks
+            .into_iter()
+            .filter_map(|n| {
+                let project = n.labels?.remove(projects::PROJECT_LABEL)?;
+                Some((n.name?, project))
+            })
+            .collect()
+    }
 
-                    // Calculate network I/O
-                    let (network_rx, network_tx) = stats.networks
-                        .as_ref()
-                        .map(|nets| {
-                            nets.values().fold((0u64, 0u64), |(rx, tx), net| {
-                                (rx + net.rx_bytes, tx + net.tx_bytes)
-                            })
-                        })
-                        .unwrap_or((0, 0));
-
-                    // Calculate block I/O
-                    let (block_read, block_write) = stats.blkio_stats.io_service_bytes_recursive
-                        .as_ref()
-                        .map(|io| {
-                            io.iter().fold((0u64, 0u64), |(r, w), entry| {
-                                match entry.op.as_str() {
-                                    "read" | "Read" => (r + entry.value, w),
-                                    "write" | "Write" => (r, w + entry.value),
-                                    _ => (r, w)
-                                }
-                            })
-                        })
-                        .unwrap_or((0, 0));
-
-                    Ok(Some(ContainerStats {
-                        cpu_percent: (cpu_percent * 100.0).round() / 100.0,
-                        memory_usage_mb: (memory_usage * 100.0).round() / 100.0,
-                        memory_limit_mb: (memory_limit * 100.0).round() / 100.0,
-                        memory_percent: (memory_percent * 100.0).round() / 100.0,
-                        network_rx_mb: (network_rx as f64 / (1024.0 * 1024.0) * 100.0).round() / 100.0,
-                        network_tx_mb: (network_tx as f64 / (1024.0 * 1024.0) * 100.0).round() / 100.0,
-                        block_read_mb: (block_read as f64 / (1024.0 * 1024.0) * 100.0).round() / 100.0,
-                        block_write_mb: (block_write as f64 / (1024.0 * 1024.0) * 100.0).round() / 100.0,
-                        pids: stats.pids_stats.current.unwrap_or(0),
-                    }))
+    /// Get container stats (CPU, Memory, Network I/O) for a specific container
+    pub async fn get_container_stats(&self, container_id: &str) -> Result<Option<ContainerStats>, bollard::errors::Error> {
+        match self.docker.stats(container_id).await {
+            Ok(Some(stats)) => {
+                let mut result = cgroup::container_stats(&stats);
+                let key = if stats.id.is_empty() { container_id } else { stats.id.as_str() };
+                let rates = cgroup::IoCounters::of(&stats).and_then(|c| self.io_rates.observe(key, c));
+                if let Some(rates) = rates {
+                    rates.apply(&mut result);
                 }
-                Err(_) => Ok(None)
+                if let Some(usage) = self.fd_usage(&stats.id).await {
+                    usage.apply(&mut result);
+                }
+                Ok(Some(result))
             }
-        } else {
-            Ok(None)
+            Ok(None) | Err(_) => Ok(None)
         }
     }
 
+    /// Descriptor and inotify usage of a container's processes, when the host `/proc` is readable
+    async fn fd_usage(&self, id: &str) -> Option<procfs::FdUsage> {
+        let root = self.proc_root.as_deref().filter(|_| !id.is_empty())?;
+        let processes = self.top_processes(id, false).await.ok()?;
+        let pids: Vec<u64> = processes.iter().map(|p| p.pid).filter(|&pid| pid > 0).collect();
+        procfs::fd_usage(root, id, &pids)
+    }
+
     /// Get all containers with their live stats (more expensive, used for detail views)
     pub async fn list_containers_with_stats(&self) -> Result<Vec<ContainerInfo>, bollard::errors::Error> {
         let mut containers = self.list_containers().await?;
@@ -226,12 +387,7 @@ impl DockerDiscovery {
 
     /// Get image sizes for optimization analysis
     pub async fn list_image_sizes(&self) -> Result<HashMap<String, f64>, bollard::errors::Error> {
-        let options = ListImagesOptions::<String> {
-            all: false,
-            ..Default::default()
-        };
-        
-        let images = self.docker.list_images(Some(options)).await?;
+        let images = self.docker.list_images().await?;
         let mut sizes: HashMap<String, f64> = HashMap::new();
         
         for image in images {
@@ -247,6 +403,85 @@ impl DockerDiscovery {
         Ok(sizes)
     }
 
+    /// Every local image with the containers created from it, newest first
+    pub async fn list_images(&self) -> Result<Vec<ImageInfo>, bollard::errors::Error> {
+        let images = self.docker.list_images().await?;
+        let containers = self.docker.list_containers().await?;
+        let mut result: Vec<ImageInfo> = images
+            .into_iter()
+            .map(|image| {
+                // Older daemons list untagged images as `<none>:<none>`
+                let tags: Vec<String> = image.repo_tags.into_iter().filter(|t| t != "<none>:<none>").collect();
+                let digests = image.repo_digests.into_iter().filter(|d| d != "<none>@<none>").collect();
+                let mut users: Vec<String> = containers
+                    .iter()
+                    .filter(|c| {
+                        c.image_id.as_ref() == Some(&image.id) || c.image.as_ref().is_some_and(|i| tags.contains(i))
+                    })
+                    .filter_map(|c| Some(c.names.as_ref()?.first()?.trim_start_matches('/').to_string()))
+                    .collect();
+                users.sort();
+                let size_mb = image.size as f64 / (1024.0 * 1024.0);
+                ImageInfo {
+                    id: image.id,
+                    dangling: tags.is_empty(),
+                    tags,
+                    digests,
+                    size_mb: (size_mb * 100.0).round() / 100.0,
+                    created: Utc.timestamp_opt(image.created, 0).single().unwrap_or_default(),
+                    containers: users,
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| a.id.cmp(&b.id)));
+        Ok(result)
+    }
+
+    /// Pull `image`, waiting until the daemon has it
+    pub async fn pull_image(&self, image: &str) -> Result<(), bollard::errors::Error> {
+        self.docker.pull_image(image).await
+    }
+
+    /// Remove an image by id or tag; `force` also removes images that
+    /// containers were created from
+    pub async fn remove_image(&self, image: &str, force: bool) -> Result<(), bollard::errors::Error> {
+        self.docker.remove_image(image, force).await
+    }
+
+    /// CPU and memory summed per image, heaviest CPU users first
+    pub async fn get_image_usage(&self) -> Result<Vec<ImageUsage>, bollard::errors::Error> {
+        let containers = self.list_containers_with_stats().await?;
+        let sizes = self.list_image_sizes().await?;
+
+        let mut by_image: BTreeMap<&str, Vec<ContainerInfo>> = BTreeMap::new();
+        for container in &containers {
+            by_image.entry(&container.image).or_default().push(container.clone());
+        }
+
+        let mut usage: Vec<ImageUsage> = by_image
+            .into_iter()
+            .map(|(image, members)| {
+                let total = |f| total_stat(&members, f);
+                ImageUsage {
+                    image: image.to_string(),
+                    container_count: members.len(),
+                    running_count: members.iter().filter(|c| Self::is_running(c)).count(),
+                    cpu_percent: total(|s| s.cpu_percent),
+                    memory_usage_mb: total(|s| s.memory_usage_mb),
+                    memory_limit_mb: total(|s| s.memory_limit_mb),
+                    network_rx_mb: total(|s| s.network_rx_mb),
+                    network_tx_mb: total(|s| s.network_tx_mb),
+                    pids: members.iter().filter_map(|c| c.stats.as_ref()).map(|s| s.pids).sum(),
+                    containers: members.iter().map(|c| c.name.clone()).collect(),
+                    image_size_mb: sizes.get(image).copied(),
+                    history: None,
+                }
+            })
+            .collect();
+        usage.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent));
+        Ok(usage)
+    }
+
     /// Get container details by ID or name
     pub async fn get_container(&self, id: &str) -> Result<Option<ContainerInfo>, bollard::errors::Error> {
         let containers = self.list_containers().await?;
@@ -257,11 +492,7 @@ impl DockerDiscovery {
 
     /// Get all networks with connected containers
     pub async fn list_networks(&self) -> Result<Vec<NetworkInfo>, bollard::errors::Error> {
-        let options = ListNetworksOptions::<String> {
-            ..Default::default()
-        };
-
-        let networks = self.docker.list_networks(Some(options)).await?;
+        let networks = self.docker.list_networks().await?;
         let mut result = Vec::new();
 
         for network in networks {
@@ -270,10 +501,11 @@ impl DockerDiscovery {
             let driver = network.driver.unwrap_or_else(|| "bridge".to_string());
 
             // Get containers in this network
-            let containers: Vec<String> = network
+            let mut containers: Vec<String> = network
                 .containers
                 .map(|c| c.keys().cloned().collect())
                 .unwrap_or_default();
+            containers.sort();
 
             result.push(NetworkInfo {
                 id: id.chars().take(12).collect(),
@@ -286,10 +518,111 @@ impl DockerDiscovery {
         Ok(result)
     }
 
+    /// Named volumes with the containers mounting each, sorted by name
+    pub async fn list_volumes(&self) -> Result<Vec<VolumeInfo>, bollard::errors::Error> {
+        let volumes = self.docker.list_volumes().await?;
+        let containers = self.list_containers().await?;
+        let mut result: Vec<VolumeInfo> = volumes
+            .into_iter()
+            .map(|volume| VolumeInfo {
+                containers: containers
+                    .iter()
+                    .filter(|c| c.volume_names.contains(&volume.name))
+                    .map(|c| c.name.clone())
+                    .collect(),
+                scope: volume.scope.map(|s| s.to_string()).filter(|s| !s.is_empty()),
+                name: volume.name,
+                driver: volume.driver,
+                mountpoint: volume.mountpoint,
+                labels: volume.labels,
+            })
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
     /// Generate system topology overview
     pub async fn get_topology(&self) -> Result<SystemTopology, bollard::errors::Error> {
         let containers = self.list_containers().await?;
-        
+        let mut topology = self.build_topology(&containers);
+        topology.host = self.get_host_resources(&containers).await;
+        Ok(topology)
+    }
+
+    /// Collect host capacity and the limits committed by running containers;
+    /// `None` when the daemon does not report its capacity. Memory, load and
+    /// disk figures are only read when the daemon is local.
+    pub async fn get_host_resources(&self, containers: &[ContainerInfo]) -> Option<HostResources> {
+        let info = match self.docker.info().await {
+            Ok(info) => info,
+            Err(e) => {
+                tracing::warn!("Failed to read host capacity: {}", e);
+                return None;
+            }
+        };
+
+        let running = containers
+            .iter()
+            .filter(|c| matches!(c.status, ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy));
+        let inspections = futures_util::future::join_all(
+            running.map(|c| self.docker.inspect_container(&c.id)),
+        )
+        .await;
+
+        let mut committed = CommittedLimits::default();
+        for inspect in inspections.into_iter().flatten() {
+            let hc = inspect.host_config.unwrap_or_default();
+            committed.add(hc.memory, hc.nano_cpus, hc.cpu_quota, hc.cpu_period);
+        }
+
+        let disk_path = host::disk_path(info.docker_root_dir.as_deref());
+        let (meminfo, load_average, disk) = match &self.proc_root {
+            Some(root) => (
+                host::read_meminfo(root),
+                host::read_load_average(root),
+                host::disk_usage(std::path::Path::new(&disk_path)),
+            ),
+            None => Default::default(),
+        };
+
+        Some(host::summarize(
+            info.ncpu.unwrap_or(0).max(0) as u64,
+            info.mem_total.unwrap_or(0).max(0) as u64,
+            meminfo,
+            load_average,
+            disk,
+            disk_path,
+            committed,
+        ))
+    }
+
+    /// Map the running containers' CPU pinning onto the host's cores, with
+    /// `numa_nodes` as the host's NUMA layout (all on node 0 when empty)
+    pub async fn cpu_map(&self, numa_nodes: Vec<NumaNode>) -> Result<CpuMap, bollard::errors::Error> {
+        let info = self.docker.info().await?;
+        let containers = self.list_containers().await?;
+
+        let running: Vec<&ContainerInfo> = containers.iter().filter(|c| Self::is_running(c)).collect();
+        let inspections = futures_util::future::join_all(
+            running.iter().map(|c| self.docker.inspect_container(&c.id)),
+        )
+        .await;
+
+        let mut placements: Vec<(String, CpuPlacement)> = running
+            .iter()
+            .zip(inspections)
+            .filter_map(|(c, inspect)| {
+                let hc = inspect.ok()?.host_config.unwrap_or_default();
+                Some((c.name.clone(), host::cpu_placement(&hc)))
+            })
+            .collect();
+        placements.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(host::cpu_map(info.ncpu.unwrap_or(0).max(0) as u64, numa_nodes, &placements))
+    }
+
+    /// Summarize an already-fetched container list into a topology overview
+    pub fn build_topology(&self, containers: &[ContainerInfo]) -> SystemTopology {
         let total = containers.len();
         let running = containers.iter().filter(|c| matches!(c.status, ContainerStatus::Running | ContainerStatus::Healthy)).count();
         let healthy = containers.iter().filter(|c| c.status == ContainerStatus::Healthy).count();
@@ -297,15 +630,15 @@ impl DockerDiscovery {
 
         // Count by category
         let mut categories: HashMap<String, usize> = HashMap::new();
-        for container in &containers {
+        for container in containers {
             let cat_name = format!("{:?}", container.category).to_lowercase();
             *categories.entry(cat_name).or_insert(0) += 1;
         }
 
         // Generate flowchart summaries
-        let flowcharts = self.generate_flowchart_summaries(&containers);
+        let flowcharts = self.generate_flowchart_summaries(containers);
 
-        Ok(SystemTopology {
+        SystemTopology {
             total_containers: total,
             running_containers: running,
             healthy_containers: healthy,
@@ -313,7 +646,8 @@ impl DockerDiscovery {
             categories,
             flowcharts,
             generated_at: Utc::now(),
-        })
+            host: None,
+        }
     }
 
     /// Generate flowchart summaries for each category
@@ -325,7 +659,7 @@ impl DockerDiscovery {
         for container in containers {
             by_category
                 .entry(container.category.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(container);
         }
 
@@ -340,6 +674,37 @@ impl DockerDiscovery {
             });
         }
 
+        // HashMap iteration order is random; keep the list stable between calls
+        summaries.sort_by(|a, b| a.id.cmp(&b.id));
+
+        // And one for each host when there are several
+        let mut by_host: BTreeMap<&str, usize> = BTreeMap::new();
+        for host in containers.iter().filter_map(|c| c.host.as_deref()) {
+            *by_host.entry(host).or_default() += 1;
+        }
+        if by_host.len() > 1 {
+            summaries.extend(by_host.into_iter().map(|(host, node_count)| FlowchartSummary {
+                id: format!("host:{}", host),
+                name: format!("Host {}", host),
+                node_count,
+                category: ServiceCategory::Other,
+            }));
+        }
+
+        // And one for each swarm service
+        let mut by_service: BTreeMap<&str, Vec<&ContainerInfo>> = BTreeMap::new();
+        for container in containers {
+            if let Some(service) = container.labels.get(projects::SWARM_SERVICE_LABEL) {
+                by_service.entry(service).or_default().push(container);
+            }
+        }
+        summaries.extend(by_service.into_iter().map(|(service, tasks)| FlowchartSummary {
+            id: format!("service:{}", service),
+            name: format!("Service {}", service),
+            node_count: tasks.len() + 1,
+            category: tasks[0].category.clone(),
+        }));
+
         // Add system overview
         summaries.insert(0, FlowchartSummary {
             id: "system-overview".to_string(),
@@ -399,6 +764,69 @@ impl DockerDiscovery {
             }
         }
 
+        // A swarm service fanning out to its tasks
+        if let Some(name) = id.strip_prefix("service:") {
+            let tasks = containers.iter().filter_map(swarm_task).collect();
+            let services = swarm_services(self.docker.list_services().await?, tasks);
+            let Some(service) = services.into_iter().find(|s| s.name == name) else {
+                return Ok(None);
+            };
+            return Ok(Some(self.swarm_service_flowchart(service, &containers).await));
+        }
+
+        // Containers on one of several hosts
+        if let Some(host) = id.strip_prefix("host:") {
+            let members: Vec<ContainerInfo> = containers
+                .iter()
+                .filter(|c| c.host.as_deref() == Some(host))
+                .cloned()
+                .collect();
+            if members.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(
+                self.members_flowchart(
+                    id.to_string(),
+                    format!("Host {}", host),
+                    format!("{} containers on host {}", members.len(), host),
+                    members,
+                )
+                .await,
+            ));
+        }
+
+        // Projects and networks list every member once, however many other
+        // projects or networks it also belongs to. `project-<name>` names a
+        // project too, unless a container goes by that name.
+        let membership = match id.split_once(':') {
+            Some(("project", name)) => Some((MembershipKind::Project, name)),
+            Some(("network", name)) => Some((MembershipKind::Network, name)),
+            _ => id
+                .strip_prefix("project-")
+                .filter(|_| !containers.iter().any(|c| c.id == id || c.name == id))
+                .map(|name| (MembershipKind::Project, name)),
+        };
+        if let Some((kind, name)) = membership {
+            let members: Vec<ContainerInfo> = containers
+                .iter()
+                .filter(|c| c.memberships.iter().any(|m| m.kind == kind && m.name == name))
+                .cloned()
+                .collect();
+            if members.is_empty() {
+                return Ok(None);
+            }
+            let label = if kind == MembershipKind::Project { "project" } else { "network" };
+            return Ok(Some(
+                self.members_flowchart(
+                    id.to_string(),
+                    name.to_string(),
+                    format!("{} containers in {} {}", members.len(), label, name),
+                    members,
+                )
+                .await,
+            ));
+        }
+
         // Check if it's a container-specific flowchart
         if let Some(container) = containers.iter().find(|c| c.id == id || c.name == id) {
             return Ok(Some(self.generate_container_flowchart_with_stats(container, &containers, &networks).await));
@@ -455,6 +883,10 @@ impl DockerDiscovery {
                 child_flowchart: Some(format!("{}-overview", cat_id)),
                 metrics: None,
                 stats: None,
+                warnings: Vec::new(),
+                probe: None,
+                vulnerabilities: None,
+                position: None,
             });
         }
 
@@ -488,13 +920,55 @@ impl DockerDiscovery {
             }
         }
 
+        let category_count = nodes.len();
+
+        // With several hosts, a group for each host as well
+        let mut by_host: BTreeMap<&str, Vec<&ContainerInfo>> = BTreeMap::new();
+        for container in containers {
+            if let Some(host) = &container.host {
+                by_host.entry(host).or_default().push(container);
+            }
+        }
+        if by_host.len() > 1 {
+            for (host, members) in by_host {
+                let healthy = members
+                    .iter()
+                    .filter(|c| matches!(c.status, ContainerStatus::Healthy | ContainerStatus::Running))
+                    .count();
+                let status = if healthy == members.len() {
+                    ContainerStatus::Healthy
+                } else if healthy > 0 {
+                    ContainerStatus::Running
+                } else {
+                    ContainerStatus::Unhealthy
+                };
+                let id = format!("host:{}", host);
+                nodes.push(FlowchartNode {
+                    id: id.clone(),
+                    name: format!("{} ({})", host, members.len()),
+                    description: "Docker host".to_string(),
+                    status,
+                    node_type: NodeType::Group,
+                    category: ServiceCategory::Other,
+                    port: None,
+                    child_flowchart: Some(id),
+                    metrics: None,
+                    stats: None,
+                    warnings: Vec::new(),
+                    probe: None,
+                    vulnerabilities: None,
+                    position: None,
+                });
+            }
+        }
+
         Flowchart {
             id: "system-overview".to_string(),
             name: "VAL System Overview".to_string(),
             description: format!(
                 "Complete system topology: {} containers across {} categories",
                 containers.len(),
-                nodes.len()
+                category_count
             ),
             nodes,
             connections,
@@ -502,194 +976,188 @@ impl DockerDiscovery {
         }
     }
 
-    /// Generate flowchart for a specific category
-    fn generate_category_flowchart(
+    /// Generate category flowchart with stats for each container, linked by
+    /// the dependencies inferred between them
+    async fn generate_category_flowchart_with_stats(
         &self,
         category: &ServiceCategory,
         containers: &[ContainerInfo],
         _networks: &[NetworkInfo],
     ) -> Flowchart {
-        let mut nodes = Vec::new();
-        let mut connections = Vec::new();
+        let cat_name = Self::category_display_name(category);
+        self.members_flowchart(
+            format!("{:?}-overview", category).to_lowercase(),
+            format!("{} Services", cat_name),
+            format!("{} services in the {} category", containers.len(), cat_name),
+            containers.to_vec(),
+        )
+        .await
+    }
 
-        // Sort containers by name for consistent ordering
-        let mut sorted_containers: Vec<_> = containers.to_vec();
-        sorted_containers.sort_by(|a, b| {
-            // Try to extract numeric suffix for natural sorting
-            let num_a = a.name.split('-').last().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
-            let num_b = b.name.split('-').last().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
+    /// Flowchart of a set of containers with stats for each, linked by the
+    /// dependencies inferred between them; each container is one node
+    async fn members_flowchart(
+        &self,
+        id: String,
+        name: String,
+        description: String,
+        mut members: Vec<ContainerInfo>,
+    ) -> Flowchart {
+        let mut seen = HashSet::new();
+        members.retain(|c| seen.insert(c.id.clone()));
+        // Sort containers by replica number for consistent ordering
+        members.sort_by(|a, b| {
+            let num_a = a.name.split('-').next_back().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
+            let num_b = b.name.split('-').next_back().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
             num_a.cmp(&num_b)
         });
 
-        for container in &sorted_containers {
-            let port = container.ports.first().and_then(|p| p.host_port);
-            
-            nodes.push(FlowchartNode {
-                id: container.id.clone(),
-                name: container.name.clone(),
-                description: format!("Image: {}", container.image),
-                status: container.status.clone(),
-                node_type: NodeType::Service,
-                category: container.category.clone(),
-                port,
-                child_flowchart: Some(container.name.clone()),
-                metrics: None,
-                stats: None,
-            });
-        }
-
-        // Create a simple chain/loop for homogeneous services (like validators)
-        // Connect each node to the next one, and last to first for a ring
-        if sorted_containers.len() > 1 {
-            for i in 0..sorted_containers.len() {
-                let source = &sorted_containers[i];
-                let target = &sorted_containers[(i + 1) % sorted_containers.len()];
-                
-                connections.push(FlowchartConnection {
-                    id: format!("{}-to-{}", source.id, target.id),
-                    source: source.id.clone(),
-                    target: target.id.clone(),
-                    label: None,
-                    connection_type: ConnectionType::Network,
-                });
-            }
-        }
+        // Stats and warnings are fetched for every container at once
+        let (nodes, edges) = futures_util::future::join(
+            futures_util::future::join_all(members.iter().map(|c| self.service_node_with_stats(c))),
+            self.infer_edges(&members),
+        )
+        .await;
+        let connections = graph::flowchart_connections(&members, &edges);
 
-        let cat_name = Self::category_display_name(category);
         Flowchart {
-            id: format!("{:?}-overview", category).to_lowercase(),
-            name: format!("{} Services", cat_name),
-            description: format!("{} services in the {} category", containers.len(), cat_name),
+            id,
+            name,
+            description,
             nodes,
             connections,
             parent_id: Some("system-overview".to_string()),
         }
     }
 
-    /// Generate category flowchart with stats for each container
-    async fn generate_category_flowchart_with_stats(
-        &self,
-        category: &ServiceCategory,
-        containers: &[ContainerInfo],
-        _networks: &[NetworkInfo],
-    ) -> Flowchart {
-        let mut nodes = Vec::new();
-        let mut connections = Vec::new();
-
-        // Sort containers by name for consistent ordering
-        let mut sorted_containers: Vec<_> = containers.to_vec();
-        sorted_containers.sort_by(|a, b| {
-            let num_a = a.name.split('-').last().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
-            let num_b = b.name.split('-').last().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
-            num_a.cmp(&num_b)
+    /// A service node with live stats, log error rates and warning badges
+    async fn service_node_with_stats(&self, container: &ContainerInfo) -> FlowchartNode {
+        let stats = self.get_container_stats(&container.name).await.ok().flatten();
+        let warnings = self.node_warnings(container, stats.as_ref()).await;
+        let metrics = self.log_rates.rate(&container.name, Utc::now()).map(|rate| NodeMetrics {
+            cpu_percent: None,
+            memory_mb: None,
+            uptime_hours: None,
+            image_size_mb: None,
+            log_errors_per_minute: Some(rate.errors_per_minute),
+            log_warnings_per_minute: Some(rate.warnings_per_minute),
         });
-
-        for container in &sorted_containers {
-            let port = container.ports.first().and_then(|p| p.host_port);
-            
-            // Fetch stats for this container
-            let stats = self.get_container_stats(&container.name).await.ok().flatten();
-            
-            nodes.push(FlowchartNode {
-                id: container.id.clone(),
-                name: container.name.clone(),
-                description: format!("Image: {}", container.image),
-                status: container.status.clone(),
-                node_type: NodeType::Service,
-                category: container.category.clone(),
-                port,
-                child_flowchart: Some(container.name.clone()),
-                metrics: None,
-                stats,
-            });
+        FlowchartNode {
+            id: container.id.clone(),
+            name: container.name.clone(),
+            description: format!("Image: {}", container.image),
+            status: container.status.clone(),
+            node_type: NodeType::Service,
+            category: container.category.clone(),
+            port: container.ports.first().and_then(|p| p.host_port),
+            child_flowchart: Some(container.name.clone()),
+            metrics,
+            stats,
+            warnings,
+            probe: container.probe.clone(),
+            vulnerabilities: container.vulnerabilities.clone(),
+            position: None,
         }
+    }
 
-        // Create connections for homogeneous services
-        if sorted_containers.len() > 1 {
-            for i in 0..sorted_containers.len() {
-                let source = &sorted_containers[i];
-                let target = &sorted_containers[(i + 1) % sorted_containers.len()];
-                
-                connections.push(FlowchartConnection {
-                    id: format!("{}-to-{}", source.id, target.id),
-                    source: source.id.clone(),
-                    target: target.id.clone(),
-                    label: None,
-                    connection_type: ConnectionType::Network,
-                });
-            }
+    /// Flowchart of a swarm service: the service, then each of its tasks
+    /// labeled with the host or node running it
+    async fn swarm_service_flowchart(&self, service: SwarmService, containers: &[ContainerInfo]) -> Flowchart {
+        let service_id = format!("service:{}", service.name);
+        let task_containers: Vec<(&SwarmTask, &ContainerInfo)> = service
+            .tasks
+            .iter()
+            .filter_map(|t| containers.iter().find(|c| c.id == t.container_id).map(|c| (t, c)))
+            .collect();
+
+        let desired = service.desired_replicas.map_or(service.tasks.len(), |r| r as usize);
+        let status = if desired > 0 && service.running_tasks >= desired {
+            ContainerStatus::Healthy
+        } else if service.running_tasks > 0 {
+            ContainerStatus::Running
+        } else {
+            ContainerStatus::Unhealthy
+        };
+        let mode = if service.mode.is_empty() { "swarm" } else { service.mode.as_str() };
+        let mut nodes = vec![FlowchartNode {
+            id: service_id.clone(),
+            name: service.name.clone(),
+            description: format!(
+                "Image: {}, {} service, {}/{} tasks running",
+                service.image, mode, service.running_tasks, desired
+            ),
+            status,
+            node_type: NodeType::Process,
+            category: task_containers
+                .first()
+                .map_or(ServiceCategory::Other, |(_, c)| c.category.clone()),
+            port: service.ports.iter().find_map(|p| p.host_port),
+            child_flowchart: None,
+            metrics: None,
+            stats: None,
+            warnings: Vec::new(),
+            probe: None,
+            vulnerabilities: None,
+            position: None,
+        }];
+
+        let task_nodes =
+            futures_util::future::join_all(task_containers.iter().map(|(_, c)| self.service_node_with_stats(c))).await;
+        let mut connections = Vec::new();
+        for ((task, _), mut node) in task_containers.iter().zip(task_nodes) {
+            let placement = task.host.clone().unwrap_or_else(|| format!("node {}", task.node_id));
+            node.description = match task.slot {
+                Some(slot) => format!("Task {} on {}, {}", slot, placement, node.description),
+                None => format!("Task on {}, {}", placement, node.description),
+            };
+            connections.push(FlowchartConnection {
+                id: format!("{}-to-{}", service_id, node.id),
+                source: service_id.clone(),
+                target: node.id.clone(),
+                label: Some(placement),
+                connection_type: ConnectionType::Primary,
+            });
+            nodes.push(node);
         }
 
-        let cat_name = Self::category_display_name(category);
         Flowchart {
-            id: format!("{:?}-overview", category).to_lowercase(),
-            name: format!("{} Services", cat_name),
-            description: format!("{} services in the {} category", containers.len(), cat_name),
+            id: service_id,
+            name: format!("Service {}", service.name),
+            description: format!("Swarm service {} and its {} tasks", service.name, nodes.len() - 1),
             nodes,
             connections,
             parent_id: Some("system-overview".to_string()),
         }
     }
 
-    /// Generate container flowchart with stats
+    /// Flowchart of a container and the containers wired to it: environment
+    /// references, compose `depends_on` and open connections either way, or a
+    /// shared network when nothing more specific links them
     async fn generate_container_flowchart_with_stats(
         &self,
         container: &ContainerInfo,
         all_containers: &[ContainerInfo],
         _networks: &[NetworkInfo],
     ) -> Flowchart {
-        let mut nodes = Vec::new();
-        let mut connections = Vec::new();
-
-        // Add the main container with stats
-        let main_stats = self.get_container_stats(&container.name).await.ok().flatten();
-        nodes.push(FlowchartNode {
-            id: container.id.clone(),
-            name: container.name.clone(),
-            description: format!("Image: {}", container.image),
-            status: container.status.clone(),
-            node_type: NodeType::Service,
-            category: container.category.clone(),
-            port: container.ports.first().and_then(|p| p.host_port),
-            child_flowchart: None,
-            metrics: None,
-            stats: main_stats,
-        });
-
-        // Find related containers (same network)
-        for other in all_containers {
-            if other.id == container.id {
-                continue;
-            }
-
-            let shared = container.networks.iter().any(|n| {
-                n != "bridge" && other.networks.contains(n)
-            });
-
-            if shared {
-                let other_stats = self.get_container_stats(&other.name).await.ok().flatten();
-                nodes.push(FlowchartNode {
-                    id: other.id.clone(),
-                    name: other.name.clone(),
-                    description: format!("Image: {}", other.image),
-                    status: other.status.clone(),
-                    node_type: NodeType::Service,
-                    category: other.category.clone(),
-                    port: other.ports.first().and_then(|p| p.host_port),
-                    child_flowchart: Some(other.name.clone()),
-                    metrics: None,
-                    stats: other_stats,
-                });
+        let touches = |edge: &GraphEdge, name: &str| edge.source == name || edge.target == name;
+        let edges: Vec<GraphEdge> = self
+            .infer_edges(all_containers)
+            .await
+            .into_iter()
+            .filter(|e| touches(e, &container.name))
+            .collect();
+        let related: Vec<ContainerInfo> = std::iter::once(container.clone())
+            .chain(
+                all_containers
+                    .iter()
+                    .filter(|c| c.id != container.id && edges.iter().any(|e| touches(e, &c.name)))
+                    .cloned(),
+            )
+            .collect();
 
-                connections.push(FlowchartConnection {
-                    id: format!("{}-to-{}", container.id, other.id),
-                    source: container.id.clone(),
-                    target: other.id.clone(),
-                    label: None,
-                    connection_type: ConnectionType::Network,
-                });
-            }
-        }
+        let mut nodes = futures_util::future::join_all(related.iter().map(|c| self.service_node_with_stats(c))).await;
+        nodes[0].child_flowchart = None;
+        let connections = graph::flowchart_connections(&related, &edges);
 
         Flowchart {
             id: container.name.clone(),
@@ -705,110 +1173,595 @@ impl DockerDiscovery {
         }
     }
 
-    /// Generate detailed flowchart for a specific container
-    fn generate_container_flowchart(
-        &self,
-        container: &ContainerInfo,
-        all_containers: &[ContainerInfo],
-        _networks: &[NetworkInfo],
-    ) -> Flowchart {
-        let mut nodes = Vec::new();
-        let mut connections = Vec::new();
-
-        // Add the main container as central node
-        nodes.push(FlowchartNode {
-            id: container.id.clone(),
-            name: container.name.clone(),
-            description: format!("Image: {}", container.image),
-            status: container.status.clone(),
-            node_type: NodeType::Service,
-            category: container.category.clone(),
-            port: container.ports.first().and_then(|p| p.host_port),
-            child_flowchart: None,
-            metrics: None,
-            stats: None,
-        });
+    /// Containers belonging to a group
+    pub async fn group_members(&self, group: &ContainerGroup) -> Result<Vec<ContainerInfo>, bollard::errors::Error> {
+        Ok(self
+            .list_containers()
+            .await?
+            .into_iter()
+            .filter(|c| group.matches(c))
+            .collect())
+    }
+
+    /// Compose projects, each with its services in dependency order
+    pub async fn list_projects(&self) -> Result<Vec<ComposeProject>, bollard::errors::Error> {
+        Ok(projects::projects(self.list_containers().await?)
+            .into_iter()
+            .map(|(name, members)| projects::summary(name, &members))
+            .collect())
+    }
+
+    /// Members of a compose project in dependency order, or `None` when no
+    /// container belongs to it
+    pub async fn project_members(&self, name: &str) -> Result<Option<Vec<ContainerInfo>>, bollard::errors::Error> {
+        Ok(projects::projects(self.list_containers().await?).remove(name))
+    }
+
+    /// Swarm tasks running as containers on the watched hosts
+    pub async fn list_tasks(&self) -> Result<Vec<SwarmTask>, bollard::errors::Error> {
+        let mut tasks: Vec<SwarmTask> = self.list_containers().await?.iter().filter_map(swarm_task).collect();
+        tasks.sort_by(|a, b| (&a.service, a.slot, &a.name).cmp(&(&b.service, b.slot, &b.name)));
+        Ok(tasks)
+    }
+
+    /// Swarm services with their tasks. Services only a worker's tasks name,
+    /// which a worker's daemon cannot list, are included from the tasks alone.
+    pub async fn list_services(&self) -> Result<Vec<SwarmService>, bollard::errors::Error> {
+        let (services, tasks) = futures_util::future::try_join(self.docker.list_services(), self.list_tasks()).await?;
+        Ok(swarm_services(services, tasks))
+    }
+
+    /// Members of a compose project with the environment each runs with
+    pub async fn project_environment(
+        &self,
+        name: &str,
+    ) -> Result<Option<Vec<(ContainerInfo, Vec<String>)>>, bollard::errors::Error> {
+        let Some(members) = self.project_members(name).await? else {
+            return Ok(None);
+        };
+        let mut environment = Vec::with_capacity(members.len());
+        for container in members {
+            let inspect = self.docker.inspect_container(&container.id).await?;
+            let env = inspect.config.and_then(|c| c.env).unwrap_or_default();
+            environment.push((container, env));
+        }
+        Ok(Some(environment))
+    }
+
+    /// Flowchart of a group's members, linked by the dependencies inferred
+    /// between them; a member matched by several selectors appears once
+    pub async fn generate_group_flowchart(&self, group: &ContainerGroup) -> Result<Flowchart, bollard::errors::Error> {
+        let members = self.group_members(group).await?;
+        let description = group
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("{} containers in group {}", members.len(), group.name));
+        Ok(self
+            .members_flowchart(format!("group:{}", group.name), group.name.clone(), description, members)
+            .await)
+    }
+
+    /// Stats summed across a group's running members
+    pub async fn get_group_stats(&self, group: &ContainerGroup) -> Result<GroupStats, bollard::errors::Error> {
+        let mut members = self.group_members(group).await?;
+        for container in members.iter_mut().filter(|c| Self::is_running(c)) {
+            container.stats = self.get_container_stats(&container.name).await.ok().flatten();
+        }
+
+        let total = |f| total_stat(&members, f);
+
+        Ok(GroupStats {
+            group: group.name.clone(),
+            container_count: members.len(),
+            running_count: members.iter().filter(|c| Self::is_running(c)).count(),
+            cpu_percent: total(|s| s.cpu_percent),
+            memory_usage_mb: total(|s| s.memory_usage_mb),
+            memory_limit_mb: total(|s| s.memory_limit_mb),
+            network_rx_mb: total(|s| s.network_rx_mb),
+            network_tx_mb: total(|s| s.network_tx_mb),
+            network_rx_bytes_per_sec: total(|s| s.network_rx_bytes_per_sec.unwrap_or(0.0)),
+            network_tx_bytes_per_sec: total(|s| s.network_tx_bytes_per_sec.unwrap_or(0.0)),
+            block_read_bytes_per_sec: total(|s| s.block_read_bytes_per_sec.unwrap_or(0.0)),
+            block_write_bytes_per_sec: total(|s| s.block_write_bytes_per_sec.unwrap_or(0.0)),
+            pids: members.iter().filter_map(|c| c.stats.as_ref()).map(|s| s.pids).sum(),
+            containers: members,
+            generated_at: Utc::now(),
+        })
+    }
+
+    /// Process diagnostics (zombies, PID usage) for a single container
+    pub async fn get_process_diagnostics(&self, id: &str) -> Result<Option<ProcessDiagnostics>, bollard::errors::Error> {
+        let container = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        self.process_diagnostics_for(&container).await.map(Some)
+    }
+
+    /// Processes running in a container, with their CPU and memory use
+    pub async fn list_processes(&self, id: &str) -> Result<Option<ContainerProcesses>, bollard::errors::Error> {
+        let container = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        let processes = if Self::is_running(&container) {
+            let inspect = self.docker.inspect_container(&container.id).await?;
+            // Windows daemons reject `ps` arguments
+            let ps_args = if inspect.platform.as_deref() == Some("windows") {
+                ""
+            } else {
+                diagnostics::PROCESS_LIST_PS_ARGS
+            };
+            let top = self.docker.top_processes(&container.id, ps_args).await?;
+            diagnostics::parse_top(&top.titles.unwrap_or_default(), &top.processes.unwrap_or_default())
+        } else {
+            Vec::new()
+        };
+        Ok(Some(ContainerProcesses {
+            container_id: container.id,
+            container_name: container.name,
+            processes,
+        }))
+    }
 
-        // Find related containers (same network)
-        for other in all_containers {
-            if other.id == container.id {
+    /// Paths the container added, changed or deleted since it was created
+    pub async fn container_changes(&self, id: &str) -> Result<Option<ContainerChanges>, bollard::errors::Error> {
+        let container = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        let mut changes: Vec<FileChange> = self
+            .docker
+            .container_changes(&container.id)
+            .await?
+            .into_iter()
+            .map(|change| FileChange {
+                path: change.path,
+                kind: match change.kind {
+                    ChangeType::_0 => FileChangeKind::Modified,
+                    ChangeType::_1 => FileChangeKind::Added,
+                    ChangeType::_2 => FileChangeKind::Deleted,
+                },
+            })
+            .collect();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        let count = |kind| changes.iter().filter(|c| c.kind == kind).count();
+        Ok(Some(ContainerChanges {
+            container_id: container.id,
+            container_name: container.name,
+            added: count(FileChangeKind::Added),
+            modified: count(FileChangeKind::Modified),
+            deleted: count(FileChangeKind::Deleted),
+            changes,
+        }))
+    }
+
+    /// Process diagnostics for every running container
+    pub async fn list_process_diagnostics(&self) -> Result<Vec<ProcessDiagnostics>, bollard::errors::Error> {
+        let containers = self.list_containers().await?;
+        let mut reports = Vec::new();
+
+        for container in containers.iter().filter(|c| Self::is_running(c)) {
+            match self.process_diagnostics_for(container).await {
+                Ok(report) => reports.push(report),
+                Err(e) => tracing::debug!("Skipping process diagnostics for {}: {}", container.name, e),
+            }
+        }
+
+        Ok(reports)
+    }
+
+    async fn process_diagnostics_for(&self, container: &ContainerInfo) -> Result<ProcessDiagnostics, bollard::errors::Error> {
+        let inspect = self.docker.inspect_container(&container.id).await?;
+        let windows = inspect.platform.as_deref() == Some("windows");
+        let processes = self.top_processes(&container.id, windows).await?;
+        let pids_limit = inspect
+            .host_config
+            .and_then(|hc| hc.pids_limit)
+            .filter(|&l| l > 0)
+            .map(|l| l as u64);
+
+        Ok(diagnostics::process_report(
+            container.id.clone(),
+            container.name.clone(),
+            processes,
+            pids_limit,
+        ))
+    }
+
+    /// Windows daemons reject `ps` arguments and list name, PID, CPU and working set
+    async fn top_processes(&self, id: &str, windows: bool) -> Result<Vec<ProcessEntry>, bollard::errors::Error> {
+        let ps_args = if windows { "" } else { diagnostics::TOP_PS_ARGS };
+        let top = self.docker.top_processes(id, ps_args).await?;
+        Ok(diagnostics::parse_top(
+            &top.titles.unwrap_or_default(),
+            &top.processes.unwrap_or_default(),
+        ))
+    }
+
+    /// Run every diagnostics pass and combine the findings
+    pub async fn get_diagnostics(&self) -> Result<DiagnosticsReport, bollard::errors::Error> {
+        let containers = self.list_containers().await?;
+        let (profiles, hosts) = self.env_profiles(&containers).await;
+        let known_hosts: HashSet<String> = hosts.into_keys().collect();
+
+        let mut findings = diagnostics::env_findings(&profiles, &known_hosts);
+        findings.extend(diagnostics::process_findings(&self.list_process_diagnostics().await?));
+        findings.extend(diagnostics::healthcheck_findings(&self.health_profiles(&containers).await));
+
+        Ok(diagnostics::build_report(findings, containers.len()))
+    }
+
+    /// Healthcheck settings and recent probes of the containers that define a healthcheck
+    async fn health_profiles(&self, containers: &[ContainerInfo]) -> Vec<diagnostics::HealthProfile> {
+        let parse_time = |time: &str| DateTime::parse_from_rfc3339(time).ok().map(|t| t.with_timezone(&Utc));
+        let duration = |nanos: Option<i64>, default: Duration| {
+            nanos.filter(|&n| n > 0).map_or(default, |n| Duration::from_nanos(n as u64))
+        };
+        let mut profiles = Vec::new();
+
+        for container in containers {
+            let inspect = match self.docker.inspect_container(&container.id).await {
+                Ok(inspect) => inspect,
+                Err(e) => {
+                    tracing::debug!("Skipping healthcheck diagnostics for {}: {}", container.name, e);
+                    continue;
+                }
+            };
+            let Some(healthcheck) = inspect.config.and_then(|c| c.healthcheck) else {
+                continue;
+            };
+            let disabled = healthcheck.test.as_ref().and_then(|t| t.first()).is_some_and(|t| t == "NONE");
+            if disabled {
                 continue;
             }
 
-            let shared = container.networks.iter().any(|n| {
-                n != "bridge" && other.networks.contains(n)
+            let state = inspect.state.unwrap_or_default();
+            let probes = state
+                .health
+                .and_then(|h| h.log)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|probe| {
+                    let start = parse_time(probe.start.as_deref()?)?;
+                    let end = parse_time(probe.end.as_deref()?)?;
+                    Some(diagnostics::Probe {
+                        start,
+                        duration: (end - start).to_std().unwrap_or_default(),
+                        exit_code: probe.exit_code.unwrap_or(0),
+                    })
+                })
+                .collect();
+
+            profiles.push(diagnostics::HealthProfile {
+                container_id: container.id.clone(),
+                container_name: container.name.clone(),
+                interval: duration(healthcheck.interval, diagnostics::DEFAULT_HEALTH_INTERVAL),
+                timeout: duration(healthcheck.timeout, diagnostics::DEFAULT_HEALTH_TIMEOUT),
+                start_period: duration(healthcheck.start_period, Duration::ZERO),
+                retries: healthcheck
+                    .retries
+                    .filter(|&r| r > 0)
+                    .map_or(diagnostics::DEFAULT_HEALTH_RETRIES, |r| r as u32),
+                started_at: state.started_at.as_deref().and_then(parse_time),
+                probes,
             });
+        }
+        profiles
+    }
 
-            if shared {
-                nodes.push(FlowchartNode {
-                    id: other.id.clone(),
-                    name: other.name.clone(),
-                    description: format!("Image: {}", other.image),
-                    status: other.status.clone(),
-                    node_type: NodeType::Service,
-                    category: other.category.clone(),
-                    port: other.ports.first().and_then(|p| p.host_port),
-                    child_flowchart: Some(other.name.clone()),
-                    metrics: None,
-                    stats: None,
-                });
+    /// Environment profiles for the containers, plus every hostname that
+    /// resolves to one of them (container name, compose service, hostname,
+    /// network alias) mapped to the ids answering to it
+    async fn env_profiles(
+        &self,
+        containers: &[ContainerInfo],
+    ) -> (Vec<diagnostics::EnvProfile>, HashMap<String, Vec<String>>) {
+        let mut hosts: HashMap<String, Vec<String>> = HashMap::new();
+        let mut add_host = |host: &str, id: &str| {
+            let ids = hosts.entry(host.to_lowercase()).or_default();
+            if !ids.iter().any(|i| i == id) {
+                ids.push(id.to_string());
+            }
+        };
+        let mut profiles = Vec::new();
 
-                connections.push(FlowchartConnection {
-                    id: format!("{}-to-{}", container.id, other.id),
-                    source: container.id.clone(),
-                    target: other.id.clone(),
-                    label: None,
-                    connection_type: ConnectionType::Network,
-                });
+        for container in containers {
+            add_host(&container.name, &container.id);
+            if let Some(service) = container.labels.get("com.docker.compose.service") {
+                add_host(service, &container.id);
             }
-        }
 
-        Flowchart {
-            id: container.name.clone(),
-            name: format!("{} Detail", container.name),
-            description: format!(
-                "Container {} and its {} connected services",
-                container.name,
-                nodes.len() - 1
-            ),
-            nodes,
-            connections,
-            parent_id: Some(format!("{:?}-overview", container.category).to_lowercase()),
+            let inspect = match self.docker.inspect_container(&container.id).await {
+                Ok(inspect) => inspect,
+                Err(e) => {
+                    tracing::debug!("Skipping diagnostics for {}: {}", container.name, e);
+                    continue;
+                }
+            };
+
+            if let Some(hostname) = inspect.config.as_ref().and_then(|c| c.hostname.as_deref()) {
+                add_host(hostname, &container.id);
+            }
+            let mut addresses = Vec::new();
+            for endpoint in inspect
+                .network_settings
+                .as_ref()
+                .and_then(|ns| ns.networks.as_ref())
+                .into_iter()
+                .flat_map(|nets| nets.values())
+            {
+                for alias in endpoint.aliases.iter().flatten() {
+                    add_host(alias, &container.id);
+                }
+                addresses.extend(endpoint.ip_address.as_deref().and_then(|ip| ip.parse::<std::net::IpAddr>().ok()));
+            }
+
+            let mut exposed_ports: Vec<u16> = inspect
+                .config
+                .as_ref()
+                .and_then(|c| c.exposed_ports.as_ref())
+                .map(|ports| {
+                    ports
+                        .keys()
+                        .filter_map(|p| p.split('/').next().and_then(|n| n.parse().ok()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            exposed_ports.extend(container.ports.iter().map(|p| p.container_port));
+
+            profiles.push(diagnostics::EnvProfile {
+                container_id: container.id.clone(),
+                container_name: container.name.clone(),
+                env: inspect.config.as_ref().and_then(|c| c.env.clone()).unwrap_or_default(),
+                exposed_ports,
+                mount_destinations: inspect
+                    .mounts
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|m| m.destination)
+                    .collect(),
+                addresses,
+            });
         }
+
+        (profiles, hosts)
     }
 
-    /// Infer connection type based on service names
-    fn infer_connection_type(&self, source: &str, target: &str) -> Option<ConnectionType> {
-        let source_lower = source.to_lowercase();
-        let target_lower = target.to_lowercase();
+    /// Every container with the dependencies FlowScope can infer between them
+    pub async fn dependency_graph(&self) -> Result<DependencyGraph, bollard::errors::Error> {
+        let containers = self.list_containers().await?;
+        let edges = self.infer_edges(&containers).await;
+        Ok(DependencyGraph {
+            nodes: containers,
+            edges,
+            generated_at: Utc::now(),
+        })
+    }
 
-        // Gateway/Router patterns
-        if source_lower.contains("gateway") || source_lower.contains("router") {
-            return Some(ConnectionType::Primary);
+    /// Dependencies among `containers`: shared non-default networks and named
+    /// volumes, `*_HOST`/`*_URL` variables, compose `depends_on` and open
+    /// connections, keyed by name
+    async fn infer_edges(&self, containers: &[ContainerInfo]) -> Vec<GraphEdge> {
+        let (profiles, hosts) = self.env_profiles(containers).await;
+        let name_of = |id: &str| {
+            containers
+                .iter()
+                .find(|c| c.id == id)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| id.to_string())
+        };
+
+        let mut edges = Vec::new();
+        for (i, source) in containers.iter().enumerate() {
+            for target in &containers[i + 1..] {
+                let mut shared: Vec<&str> = source
+                    .networks
+                    .iter()
+                    .filter(|n| n.as_str() != "bridge" && target.networks.contains(n))
+                    .map(String::as_str)
+                    .collect();
+                if shared.is_empty() {
+                    continue;
+                }
+                shared.sort_unstable();
+                edges.push(GraphEdge {
+                    source: source.name.clone(),
+                    target: target.name.clone(),
+                    kind: EdgeKind::Network,
+                    label: shared.join(", "),
+                });
+            }
+        }
+        for (i, source) in containers.iter().enumerate() {
+            for target in &containers[i + 1..] {
+                let shared: Vec<&str> = source
+                    .volume_names
+                    .iter()
+                    .filter(|v| target.volume_names.contains(v))
+                    .map(String::as_str)
+                    .collect();
+                if !shared.is_empty() {
+                    edges.push(GraphEdge {
+                        source: source.name.clone(),
+                        target: target.name.clone(),
+                        kind: EdgeKind::Volume,
+                        label: shared.join(", "),
+                    });
+                }
+            }
+        }
+        for dependency in diagnostics::env_dependencies(&profiles, &hosts) {
+            edges.push(GraphEdge {
+                source: name_of(&dependency.source_id),
+                target: name_of(&dependency.target_id),
+                kind: EdgeKind::Env,
+                label: dependency.variable,
+            });
+        }
+        for source in containers {
+            for target in containers.iter().filter(|t| projects::depends(source, t)) {
+                edges.push(GraphEdge {
+                    source: source.name.clone(),
+                    target: target.name.clone(),
+                    kind: EdgeKind::DependsOn,
+                    label: projects::service(target).to_string(),
+                });
+            }
         }
 
-        // Database patterns
-        if target_lower.contains("postgres") || target_lower.contains("redis") || target_lower.contains("db") {
-            return Some(ConnectionType::Data);
+        let running = containers.iter().filter(|c| Self::is_running(c));
+        let observed: Vec<(String, Vec<sockets::Socket>)> =
+            futures_util::future::join_all(running.map(|c| async { (c.id.clone(), self.read_sockets(&c.id, false).await) }))
+                .await
+                .into_iter()
+                .filter_map(|(id, read)| Some((id, read?.1)))
+                .collect();
+        for (source, target, port) in sockets::socket_edges(&observed, &sockets::owners(&profiles)) {
+            edges.push(GraphEdge {
+                source: name_of(&source),
+                target: name_of(&target),
+                kind: EdgeKind::Socket,
+                label: format!("tcp/{}", port),
+            });
         }
+        edges
+    }
 
-        // Queue patterns
-        if target_lower.contains("rabbitmq") || target_lower.contains("queue") {
-            return Some(ConnectionType::Secondary);
+    /// TCP sockets of a running container, from `ss` run inside it when
+    /// `exec` allows, else from the host's `/proc`; `None` when neither works
+    async fn read_sockets(&self, id: &str, exec: bool) -> Option<(SocketSource, Vec<sockets::Socket>)> {
+        if exec {
+            if let Ok(Some(output)) = self.docker.exec(id, sockets::SS_COMMAND).await {
+                if let Some(found) = sockets::parse_ss(&output) {
+                    return Some((SocketSource::Exec, found));
+                }
+            }
         }
+        let root = self.proc_root.as_deref()?;
+        let processes = self.top_processes(id, false).await.ok()?;
+        let pids: Vec<u64> = processes.iter().map(|p| p.pid).filter(|&pid| pid > 0).collect();
+        sockets::read_procfs(root, id, &pids).map(|found| (SocketSource::Procfs, found))
+    }
 
-        // Same service prefix (likely related)
-        let source_prefix = source_lower.split('-').next().unwrap_or("");
-        let target_prefix = target_lower.split('-').next().unwrap_or("");
-        if source_prefix == target_prefix && !source_prefix.is_empty() {
-            return Some(ConnectionType::Control);
+    /// What a running container listens on and which containers it is
+    /// connected to; `None` when its sockets cannot be read
+    pub async fn container_sockets(
+        &self,
+        container: &ContainerInfo,
+    ) -> Result<Option<ContainerSockets>, bollard::errors::Error> {
+        let Some((source, found)) = self.read_sockets(&container.id, true).await else {
+            return Ok(None);
+        };
+        let containers = self.list_containers().await?;
+        let (profiles, _) = self.env_profiles(&containers).await;
+        let declared = profiles
+            .iter()
+            .find(|p| p.container_id == container.id)
+            .map(|p| p.exposed_ports.clone())
+            .unwrap_or_default();
+        Ok(Some(sockets::report(
+            &container.name,
+            source,
+            &found,
+            &declared,
+            &sockets::owners(&profiles),
+        )))
+    }
+
+    /// Warning badges for a flowchart node
+    async fn node_warnings(&self, container: &ContainerInfo, stats: Option<&ContainerStats>) -> Vec<String> {
+        if !Self::is_running(container) {
+            return Vec::new();
         }
 
-        None
+        // Windows containers reject the ps arguments, and have no zombies to count
+        let zombies = self
+            .top_processes(&container.id, false)
+            .await
+            .map(|procs| procs.iter().filter(|p| diagnostics::is_zombie(p)).count())
+            .unwrap_or(0);
+
+        [
+            diagnostics::zombie_warning(zombies),
+            stats.and_then(|s| diagnostics::pid_limit_warning(s.pids, s.pids_limit)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn is_running(container: &ContainerInfo) -> bool {
+        matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+        )
+    }
+
+    /// Daemon version, isolation and the features it supports
+    pub async fn system_info(&self) -> Result<SystemInfo, bollard::errors::Error> {
+        Ok(system::system_info(&self.docker.info().await?))
+    }
+
+    /// Validate a container spec against current networks, volumes, images and ports
+    pub async fn preflight(&self, spec: &ContainerSpec) -> Result<PreflightReport, bollard::errors::Error> {
+        let containers = self.list_containers().await?;
+        let networks = self.list_networks().await?.into_iter().map(|n| n.name).collect();
+        let volumes = self
+            .docker
+            .list_volumes()
+            .await?
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        let images = self
+            .docker
+            .list_images()
+            .await?
+            .into_iter()
+            .flat_map(|i| i.repo_tags)
+            .collect();
+        let features = self.system_info().await?.features;
+
+        Ok(preflight::run(
+            spec,
+            &PreflightContext {
+                containers,
+                networks,
+                volumes,
+                images,
+                features,
+            },
+        ))
     }
 
-    /// Get detailed container information including environment, volumes, health check
-    pub async fn get_container_detail(&self, id: &str) -> Result<Option<ContainerDetail>, bollard::errors::Error> {
+    /// The daemon's inspect response as it is; `redact` blanks the values of
+    /// environment variables, leaving their names
+    pub async fn inspect_raw(
+        &self,
+        id: &str,
+        redact: bool,
+    ) -> Result<Option<bollard::models::ContainerInspectResponse>, bollard::errors::Error> {
+        let container = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        let mut inspect = self.docker.inspect_container(&container.id).await?;
+        if redact {
+            if let Some(env) = inspect.config.as_mut().and_then(|c| c.env.as_mut()) {
+                redact_environment(env);
+            }
+        }
+        Ok(Some(inspect))
+    }
+
+    /// Get detailed container information including environment, volumes,
+    /// health check; `redact` blanks environment values as in [`Self::inspect_raw`]
+    pub async fn get_container_detail(
+        &self,
+        id: &str,
+        redact: bool,
+    ) -> Result<Option<ContainerDetail>, bollard::errors::Error> {
         // First get basic container info
         let container_info = match self.get_container(id).await? {
             Some(info) => info,
@@ -816,13 +1769,16 @@ impl DockerDiscovery {
         };
 
         // Inspect for detailed information
-        let inspect = self.docker.inspect_container(&container_info.id, None::<InspectContainerOptions>).await?;
+        let inspect = self.docker.inspect_container(&container_info.id).await?;
         
         // Extract environment variables
-        let environment = inspect.config
+        let mut environment = inspect.config
             .as_ref()
             .and_then(|c| c.env.clone())
             .unwrap_or_default();
+        if redact {
+            redact_environment(&mut environment);
+        }
 
         // Extract command
         let command = inspect.config
@@ -871,46 +1827,59 @@ impl DockerDiscovery {
             working_dir,
             volumes,
             health_check,
+            platform: inspect.platform.filter(|p| !p.is_empty()),
+            cpu: inspect.host_config.as_ref().map(host::cpu_placement),
         }))
     }
 
     /// Get container logs
-    pub async fn get_container_logs(&self, id: &str, tail: usize) -> Result<Option<ContainerLogs>, bollard::errors::Error> {
+    pub async fn get_container_logs(&self, id: &str, window: LogWindow) -> Result<Option<ContainerLogs>, bollard::errors::Error> {
         // First verify container exists
         let container_info = match self.get_container(id).await? {
             Some(info) => info,
             None => return Ok(None),
         };
 
-        let options = LogsOptions::<String> {
-            stdout: true,
-            stderr: true,
-            tail: tail.to_string(),
-            ..Default::default()
-        };
-
-        let mut stream = self.docker.logs(&container_info.id, Some(options));
-        let mut logs = Vec::new();
-
-        while let Some(result) = stream.next().await {
-            match result {
-                Ok(output) => {
-                    let line = output.to_string();
-                    logs.push(line);
-                }
-                Err(_) => break,
-            }
-        }
+        let logs = self.docker.logs(&container_info.id, window).await?;
 
         Ok(Some(ContainerLogs {
             container_id: container_info.id,
             container_name: container_info.name,
             logs,
-            tail,
-            since: None,
+            tail: window.tail,
+            since: window.since,
+            until: window.until,
+            streams: window.streams,
         }))
     }
 
+    /// Follow a container's logs from now on; the stream ends when it stops
+    pub fn follow_logs(&self, id: &str) -> BoxStream<'static, Result<String, bollard::errors::Error>> {
+        self.docker.follow_logs(id)
+    }
+
+    /// A container's last `tail` lines (all when `None`) written since
+    /// `since` (Unix seconds), then new ones until it stops
+    pub fn stream_logs(
+        &self,
+        id: &str,
+        tail: Option<usize>,
+        since: i64,
+    ) -> BoxStream<'static, Result<String, bollard::errors::Error>> {
+        self.docker.stream_logs(id, tail, since)
+    }
+
+    /// A container's output written since `since` (Unix seconds), then new
+    /// output until it stops, each line prefixed with its timestamp
+    pub fn stream_timestamped_logs(&self, id: &str, since: i64) -> BoxStream<'static, Result<String, bollard::errors::Error>> {
+        self.docker.stream_timestamped_logs(id, since)
+    }
+
+    /// Daemon events from `since` (Unix seconds) onwards, then new ones as they happen
+    pub fn events(&self, since: i64) -> BoxStream<'static, Result<bollard::models::EventMessage, bollard::errors::Error>> {
+        self.docker.events(since)
+    }
+
     /// Restart a container
     pub async fn restart_container(&self, id: &str) -> Result<Option<ActionResult>, bollard::errors::Error> {
         let container_info = match self.get_container(id).await? {
@@ -918,9 +1887,7 @@ impl DockerDiscovery {
             None => return Ok(None),
         };
 
-        let options = RestartContainerOptions { t: 10 };
-        
-        match self.docker.restart_container(&container_info.id, Some(options)).await {
+        match self.docker.restart_container(&container_info.id).await {
             Ok(_) => Ok(Some(ActionResult {
                 success: true,
                 container_id: container_info.id,
@@ -945,9 +1912,7 @@ impl DockerDiscovery {
             None => return Ok(None),
         };
 
-        let options = StopContainerOptions { t: 10 };
-        
-        match self.docker.stop_container(&container_info.id, Some(options)).await {
+        match self.docker.stop_container(&container_info.id).await {
             Ok(_) => Ok(Some(ActionResult {
                 success: true,
                 container_id: container_info.id,
@@ -965,6 +1930,81 @@ impl DockerDiscovery {
         }
     }
 
+    /// Pause a running container, keeping its memory and connections
+    pub async fn pause_container(&self, id: &str) -> Result<Option<ActionResult>, bollard::errors::Error> {
+        let container_info = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        match self.docker.pause_container(&container_info.id).await {
+            Ok(_) => Ok(Some(ActionResult {
+                success: true,
+                container_id: container_info.id,
+                container_name: container_info.name,
+                action: "pause".to_string(),
+                message: "Container paused".to_string(),
+            })),
+            Err(e) => Ok(Some(ActionResult {
+                success: false,
+                container_id: container_info.id,
+                container_name: container_info.name,
+                action: "pause".to_string(),
+                message: format!("Failed to pause: {}", e),
+            })),
+        }
+    }
+
+    /// Resume a paused container
+    pub async fn unpause_container(&self, id: &str) -> Result<Option<ActionResult>, bollard::errors::Error> {
+        let container_info = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        match self.docker.unpause_container(&container_info.id).await {
+            Ok(_) => Ok(Some(ActionResult {
+                success: true,
+                container_id: container_info.id,
+                container_name: container_info.name,
+                action: "unpause".to_string(),
+                message: "Container unpaused".to_string(),
+            })),
+            Err(e) => Ok(Some(ActionResult {
+                success: false,
+                container_id: container_info.id,
+                container_name: container_info.name,
+                action: "unpause".to_string(),
+                message: format!("Failed to unpause: {}", e),
+            })),
+        }
+    }
+
+    /// Send `signal` to a container's main process
+    pub async fn kill_container(&self, id: &str, signal: &str) -> Result<Option<ActionResult>, bollard::errors::Error> {
+        let container_info = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        match self.docker.kill_container(&container_info.id, signal).await {
+            Ok(_) => Ok(Some(ActionResult {
+                success: true,
+                container_id: container_info.id,
+                container_name: container_info.name,
+                action: "kill".to_string(),
+                message: format!("Sent {}", signal),
+            })),
+            Err(e) => Ok(Some(ActionResult {
+                success: false,
+                container_id: container_info.id,
+                container_name: container_info.name,
+                action: "kill".to_string(),
+                message: format!("Failed to send {}: {}", signal, e),
+            })),
+        }
+    }
+
     /// Start a container
     pub async fn start_container(&self, id: &str) -> Result<Option<ActionResult>, bollard::errors::Error> {
         let container_info = match self.get_container(id).await? {
@@ -972,7 +2012,7 @@ impl DockerDiscovery {
             None => return Ok(None),
         };
 
-        match self.docker.start_container::<String>(&container_info.id, None).await {
+        match self.docker.start_container(&container_info.id).await {
             Ok(_) => Ok(Some(ActionResult {
                 success: true,
                 container_id: container_info.id,
@@ -989,4 +2029,647 @@ impl DockerDiscovery {
             })),
         }
     }
+
+    /// Remove a container, stopping it first when `force` is set; `volumes`
+    /// removes its anonymous volumes with it
+    pub async fn remove_container(
+        &self,
+        id: &str,
+        force: bool,
+        volumes: bool,
+    ) -> Result<Option<ActionResult>, bollard::errors::Error> {
+        let container_info = match self.get_container(id).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        let running = Self::is_running(&container_info);
+        let removed = async {
+            if running && force {
+                self.docker.stop_container(&container_info.id).await?;
+            }
+            self.docker.remove_container(&container_info.id, volumes).await
+        };
+        let (success, message) = match removed.await {
+            Ok(()) if running => (true, "Container stopped and removed".to_string()),
+            Ok(()) => (true, "Container removed".to_string()),
+            Err(e) => (false, format!("Failed to remove: {}", e)),
+        };
+        Ok(Some(ActionResult {
+            success,
+            container_id: container_info.id,
+            container_name: container_info.name,
+            action: "remove".to_string(),
+            message,
+        }))
+    }
+
+    /// Host ports `template` publishes at fixed numbers, which a second replica
+    /// could not bind
+    pub async fn fixed_host_ports(&self, template: &ContainerInfo) -> Result<Vec<String>, bollard::errors::Error> {
+        let inspect = self.docker.inspect_container(&template.id).await?;
+        let mut ports: Vec<String> = inspect
+            .host_config
+            .and_then(|hc| hc.port_bindings)
+            .unwrap_or_default()
+            .into_values()
+            .flatten()
+            .flatten()
+            .filter_map(|binding| binding.host_port)
+            .filter(|port| !port.is_empty() && port != "0")
+            .collect();
+        ports.sort();
+        ports.dedup();
+        Ok(ports)
+    }
+
+    /// Create and start a copy of `template` named `name`, on the same networks
+    /// with the compose service as alias and `number` as its container number
+    pub async fn create_replica(&self, template: &ContainerInfo, name: &str, number: u32) -> ActionResult {
+        let created = async {
+            let inspect = self.docker.inspect_container(&template.id).await?;
+            let mut config: bollard::container::Config<String> = inspect.config.unwrap_or_default().into();
+            // The template's hostname and MAC address are its own
+            config.hostname = None;
+            config.mac_address = None;
+            config
+                .labels
+                .get_or_insert_with(HashMap::new)
+                .insert(projects::CONTAINER_NUMBER_LABEL.to_string(), number.to_string());
+            config.host_config = inspect.host_config;
+
+            let aliases = template.labels.get(projects::SERVICE_LABEL).map(|s| vec![s.clone()]);
+            let networks = inspect.network_settings.and_then(|n| n.networks).unwrap_or_default();
+            config.networking_config = Some(bollard::container::NetworkingConfig {
+                endpoints_config: networks
+                    .into_keys()
+                    .map(|network| {
+                        let endpoint = bollard::models::EndpointSettings {
+                            aliases: aliases.clone(),
+                            ..Default::default()
+                        };
+                        (network, endpoint)
+                    })
+                    .collect(),
+            });
+
+            let id = self.docker.create_container(name, config).await?;
+            self.docker.start_container(&id).await?;
+            Ok::<_, bollard::errors::Error>(id)
+        };
+
+        let (success, container_id, message) = match created.await {
+            Ok(id) => (true, id, "Replica created and started".to_string()),
+            Err(e) => (false, String::new(), format!("Failed to create replica: {}", e)),
+        };
+        ActionResult {
+            success,
+            container_id,
+            container_name: name.to_string(),
+            action: "create".to_string(),
+            message,
+        }
+    }
+
+    /// Create and start `image` as `name`, joined to `target`'s network and PID
+    /// namespaces. The sidecar sleeps for `ttl` and is removed by the daemon
+    /// when it exits, so it goes away even if nothing reaps it. The image is
+    /// pulled when the daemon does not have it, and a sidecar that fails to
+    /// start is removed again.
+    pub async fn launch_debug_sidecar(
+        &self,
+        target: &ContainerInfo,
+        name: &str,
+        image: &str,
+        ttl: Duration,
+        labels: HashMap<String, String>,
+    ) -> Result<String, bollard::errors::Error> {
+        let namespace = format!("container:{}", target.id);
+        let config = bollard::container::Config {
+            image: Some(image.to_string()),
+            cmd: Some(vec!["sleep".to_string(), ttl.as_secs().to_string()]),
+            tty: Some(true),
+            labels: Some(labels),
+            host_config: Some(bollard::models::HostConfig {
+                network_mode: Some(namespace.clone()),
+                pid_mode: Some(namespace),
+                cap_add: Some(vec!["NET_ADMIN".to_string(), "SYS_PTRACE".to_string()]),
+                auto_remove: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let id = self.create_pulling(name, config).await?;
+        self.start_or_remove(&id).await?;
+        Ok(id)
+    }
+
+    /// Start a just-created container, removing it when it does not start
+    async fn start_or_remove(&self, id: &str) -> Result<(), bollard::errors::Error> {
+        if let Err(e) = self.docker.start_container(id).await {
+            if let Err(e) = self.docker.remove_container(id, false).await {
+                tracing::warn!("Failed to remove container {} that did not start: {}", id, e);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Create a container, pulling its image first when the daemon lacks it
+    async fn create_pulling(
+        &self,
+        name: &str,
+        config: bollard::container::Config<String>,
+    ) -> Result<String, bollard::errors::Error> {
+        match self.docker.create_container(name, config.clone()).await {
+            Err(e) if error_status(&e) == Some(404) => {
+                if let Some(image) = &config.image {
+                    self.docker.pull_image(image).await?;
+                }
+                self.docker.create_container(name, config).await
+            }
+            created => created,
+        }
+    }
+
+    /// Create and start a container from `spec` and return its id, pulling the
+    /// image when the daemon lacks it. A port without a host port is published
+    /// on one the daemon picks. A container that fails to start is removed
+    /// again, so a failed request leaves nothing behind.
+    pub async fn create_from_spec(&self, spec: &ContainerSpec) -> Result<String, bollard::errors::Error> {
+        let mut port_bindings: HashMap<String, Option<Vec<bollard::models::PortBinding>>> = HashMap::new();
+        for port in &spec.ports {
+            let binding = bollard::models::PortBinding {
+                host_ip: None,
+                host_port: port.host_port.map(|p| p.to_string()),
+            };
+            port_bindings
+                .entry(format!("{}/{}", port.container_port, port.protocol))
+                .or_insert_with(|| Some(Vec::new()))
+                .get_or_insert_with(Vec::new)
+                .push(binding);
+        }
+        let config = bollard::container::Config {
+            image: Some(spec.image.clone()),
+            env: (!spec.env.is_empty()).then(|| spec.env.clone()),
+            labels: Some(spec.labels.clone()),
+            exposed_ports: Some(port_bindings.keys().map(|p| (p.clone(), HashMap::new())).collect()),
+            host_config: Some(bollard::models::HostConfig {
+                port_bindings: Some(port_bindings),
+                binds: (!spec.volumes.is_empty()).then(|| spec.volumes.clone()),
+                network_mode: spec.networks.first().cloned(),
+                ..Default::default()
+            }),
+            networking_config: Some(bollard::container::NetworkingConfig {
+                endpoints_config: spec
+                    .networks
+                    .iter()
+                    .map(|network| (network.clone(), bollard::models::EndpointSettings::default()))
+                    .collect(),
+            }),
+            ..Default::default()
+        };
+
+        // An empty name lets the daemon pick one
+        let id = self.create_pulling(spec.name.as_deref().unwrap_or_default(), config).await?;
+        self.start_or_remove(&id).await?;
+        self.invalidate_cache();
+        Ok(id)
+    }
+
+    /// Create, but do not start, a one-off container for `request` with stdin
+    /// held open for an attachment. The image is pulled when the daemon does
+    /// not have it.
+    pub async fn create_one_off(
+        &self,
+        name: &str,
+        request: &OneOffRunRequest,
+        labels: HashMap<String, String>,
+    ) -> Result<String, bollard::errors::Error> {
+        let config = bollard::container::Config {
+            image: Some(request.image.clone()),
+            cmd: (!request.cmd.is_empty()).then(|| request.cmd.clone()),
+            env: (!request.env.is_empty()).then(|| request.env.clone()),
+            tty: Some(request.tty),
+            attach_stdin: Some(true),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            open_stdin: Some(true),
+            stdin_once: Some(true),
+            labels: Some(labels),
+            host_config: Some(bollard::models::HostConfig {
+                network_mode: request.network.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        self.create_pulling(name, config).await
+    }
+
+    /// Attach to a created container, then start it
+    pub async fn attach_and_start(
+        &self,
+        id: &str,
+    ) -> Result<bollard::container::AttachContainerResults, bollard::errors::Error> {
+        let attached = self.docker.attach_container(id).await?;
+        self.docker.start_container(id).await?;
+        Ok(attached)
+    }
+
+    /// Exit code of a container, once it is no longer running
+    pub async fn exit_code(&self, id: &str) -> Result<i64, bollard::errors::Error> {
+        self.docker.wait_container(id).await
+    }
+
+    /// Stop and remove a short-lived container such as a debug sidecar,
+    /// tolerating one the daemon already stopped or removed
+    pub async fn discard_container(&self, id: &str) -> Result<(), bollard::errors::Error> {
+        // 304: already stopped; 404: already removed; 409: removal in progress
+        let settled = |result: Result<(), bollard::errors::Error>| match result {
+            Err(e) if !matches!(error_status(&e), Some(304 | 404 | 409)) => Err(e),
+            _ => Ok(()),
+        };
+        settled(self.docker.stop_container(id).await)?;
+        settled(self.docker.remove_container(id, false).await)
+    }
+
+    /// Run a command in a running container, stdout and stderr each cut off
+    /// after `limit` bytes
+    pub async fn exec_command(
+        &self,
+        id: &str,
+        cmd: &[String],
+        working_dir: Option<&str>,
+        limit: usize,
+    ) -> Result<ExecOutput, bollard::errors::Error> {
+        self.docker.exec_output(id, cmd, working_dir, limit).await
+    }
+
+    /// Start an interactive `cmd` in a running container on a TTY
+    pub async fn open_terminal(
+        &self,
+        id: &str,
+        cmd: &[String],
+        rows: u16,
+        cols: u16,
+    ) -> Result<ExecSession, bollard::errors::Error> {
+        self.docker.exec_terminal(id, cmd, rows, cols).await
+    }
+
+    pub async fn resize_terminal(&self, exec_id: &str, rows: u16, cols: u16) -> Result<(), bollard::errors::Error> {
+        self.docker.resize_exec(exec_id, rows, cols).await
+    }
+
+    /// Exit code of a terminal's command, `None` while it is still running
+    pub async fn terminal_exit_code(&self, exec_id: &str) -> Result<Option<i64>, bollard::errors::Error> {
+        self.docker.exec_exit_code(exec_id).await
+    }
+
+    /// An address FlowScope can reach the container at: its IP on the first
+    /// network that assigned one
+    pub async fn container_address(&self, id: &str) -> Result<Option<std::net::IpAddr>, bollard::errors::Error> {
+        let inspect = self.docker.inspect_container(id).await?;
+        let networks: BTreeMap<String, bollard::models::EndpointSettings> = inspect
+            .network_settings
+            .and_then(|n| n.networks)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        Ok(networks
+            .into_values()
+            .filter_map(|endpoint| endpoint.ip_address)
+            .find_map(|ip| ip.parse().ok()))
+    }
+
+    /// Id of the image each container was created from, by container id
+    pub async fn image_ids(&self) -> Result<HashMap<String, String>, bollard::errors::Error> {
+        Ok(self
+            .docker
+            .list_containers()
+            .await?
+            .into_iter()
+            .filter_map(|c| Some((c.id?, c.image_id?)))
+            .collect())
+    }
+
+    /// Recreate `container` from `image`, keeping its name, configuration,
+    /// volumes and networks, and return the new container's id. The old
+    /// container is stopped and renamed aside, and only removed once the new
+    /// one started; if anything fails it gets its name back and is started
+    /// again.
+    ///
+    /// Only environment variables set on the container itself are carried
+    /// over, so the new image's defaults replace the old image's. Anonymous
+    /// volumes, such as those an image's `VOLUME` creates, are mounted into
+    /// the new container instead of being left behind with the old one.
+    pub async fn recreate_container(&self, container: &ContainerInfo, image: &str) -> Result<String, bollard::errors::Error> {
+        let inspect = self.docker.inspect_container(&container.id).await?;
+        let short_id: String = container.id.chars().take(12).collect();
+        let image_env = match &inspect.image {
+            Some(old_image) => match self.docker.inspect_image(old_image).await {
+                Ok(old_image) => old_image.config.and_then(|c| c.env).unwrap_or_default(),
+                Err(e) => {
+                    tracing::warn!("Keeping the whole environment of {}, its image could not be inspected: {}", container.name, e);
+                    Vec::new()
+                }
+            },
+            None => Vec::new(),
+        };
+        let mut config: bollard::container::Config<String> = inspect.config.unwrap_or_default().into();
+        config.image = Some(image.to_string());
+        // A hostname defaulted from the old id would name the wrong container
+        if config.hostname.as_deref() == Some(short_id.as_str()) {
+            config.hostname = None;
+        }
+        config.env = config.env.map(|env| user_env(env, &image_env));
+        let mut host_config = inspect.host_config.unwrap_or_default();
+        let volumes = anonymous_volumes(inspect.mounts.as_deref().unwrap_or_default(), &host_config);
+        if !volumes.is_empty() {
+            host_config.binds.get_or_insert_with(Vec::new).extend(volumes);
+        }
+        config.host_config = Some(host_config);
+        let networks = inspect.network_settings.and_then(|n| n.networks).unwrap_or_default();
+        config.networking_config = Some(bollard::container::NetworkingConfig {
+            endpoints_config: networks
+                .into_iter()
+                .map(|(network, endpoint)| {
+                    let aliases = endpoint.aliases.map(|a| a.into_iter().filter(|a| *a != short_id).collect());
+                    let endpoint = bollard::models::EndpointSettings {
+                        aliases,
+                        ..Default::default()
+                    };
+                    (network, endpoint)
+                })
+                .collect(),
+        });
+
+        let running = Self::is_running(container);
+        if running {
+            self.docker.stop_container(&container.id).await?;
+        }
+        let aside = format!("{}-replaced-{}", container.name, short_id);
+        self.docker.rename_container(&container.id, &aside).await?;
+
+        let mut created = None;
+        let replaced = async {
+            let id = self.docker.create_container(&container.name, config).await?;
+            created = Some(id.clone());
+            self.docker.start_container(&id).await?;
+            Ok::<_, bollard::errors::Error>(id)
+        }
+        .await;
+        match replaced {
+            Ok(id) => {
+                if let Err(e) = self.docker.remove_container(&container.id, false).await {
+                    tracing::warn!("Failed to remove replaced container {}: {}", aside, e);
+                }
+                Ok(id)
+            }
+            Err(e) => {
+                if let Some(id) = created {
+                    if let Err(e) = self.docker.remove_container(&id, false).await {
+                        tracing::warn!("Failed to remove container {} after a failed recreate: {}", id, e);
+                    }
+                }
+                let restored = async {
+                    self.docker.rename_container(&container.id, &container.name).await?;
+                    if running {
+                        self.docker.start_container(&container.id).await?;
+                    }
+                    Ok::<_, bollard::errors::Error>(())
+                };
+                if let Err(e) = restored.await {
+                    tracing::warn!("Failed to restore {} after a failed recreate: {}", container.name, e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Set a Swarm service's replica count
+    pub async fn scale_swarm_service(&self, service: &str, replicas: u32) -> ActionResult {
+        let (success, message) = match self.docker.scale_service(service, replicas as u64).await {
+            Ok(()) => (true, format!("Service scaled to {} replicas", replicas)),
+            Err(e) => (false, format!("Failed to scale service: {}", e)),
+        };
+        ActionResult {
+            success,
+            container_id: String::new(),
+            container_name: service.to_string(),
+            action: "scale".to_string(),
+            message,
+        }
+    }
+}
+
+/// Environment entries set on the container rather than inherited unchanged
+/// from its image
+fn user_env(env: Vec<String>, image_env: &[String]) -> Vec<String> {
+    env.into_iter().filter(|entry| !image_env.contains(entry)).collect()
+}
+
+/// `<volume>:<destination>` binds for the volumes mounted into a container
+/// that its host config does not name, i.e. anonymous ones
+fn anonymous_volumes(mounts: &[bollard::models::MountPoint], host_config: &bollard::models::HostConfig) -> Vec<String> {
+    let bound: HashSet<&str> = host_config
+        .binds
+        .iter()
+        .flatten()
+        .filter_map(|bind| bind.split(':').nth(1))
+        .chain(host_config.mounts.iter().flatten().filter_map(|m| m.target.as_deref()))
+        .collect();
+    mounts
+        .iter()
+        .filter(|m| m.typ == Some(bollard::models::MountPointTypeEnum::VOLUME))
+        .filter_map(|m| {
+            let (name, destination) = (m.name.as_deref()?, m.destination.as_deref()?);
+            if bound.contains(destination) {
+                return None;
+            }
+            let mode = if m.rw == Some(false) { ":ro" } else { "" };
+            Some(format!("{}:{}{}", name, destination, mode))
+        })
+        .collect()
+}
+
+/// HTTP status of an error the daemon answered with
+pub fn error_status(e: &bollard::errors::Error) -> Option<u16> {
+    match e {
+        bollard::errors::Error::DockerResponseServerError { status_code, .. } => Some(*status_code),
+        _ => None,
+    }
+}
+
+/// Networks every container can be attached to, which group nothing
+const SWARM_SERVICE_ID_LABEL: &str = "com.docker.swarm.service.id";
+const SWARM_TASK_ID_LABEL: &str = "com.docker.swarm.task.id";
+const SWARM_TASK_NAME_LABEL: &str = "com.docker.swarm.task.name";
+const SWARM_NODE_ID_LABEL: &str = "com.docker.swarm.node.id";
+
+/// The swarm task a container runs, from the labels the daemon sets on it
+fn swarm_task(container: &ContainerInfo) -> Option<SwarmTask> {
+    let label = |key: &str| container.labels.get(key).cloned().unwrap_or_default();
+    let service = container.labels.get(projects::SWARM_SERVICE_LABEL)?.clone();
+    let id = container.labels.get(SWARM_TASK_ID_LABEL)?.clone();
+    let name = label(SWARM_TASK_NAME_LABEL);
+    // `<service>.<slot>.<task id>`; global tasks have the node id instead
+    let slot = name
+        .strip_prefix(&format!("{}.", service))
+        .and_then(|rest| rest.split('.').next())
+        .and_then(|slot| slot.parse().ok());
+    Some(SwarmTask {
+        id,
+        name: if name.is_empty() { container.name.clone() } else { name },
+        service,
+        service_id: label(SWARM_SERVICE_ID_LABEL),
+        slot,
+        node_id: label(SWARM_NODE_ID_LABEL),
+        host: container.host.clone(),
+        container_id: container.id.clone(),
+        container_name: container.name.clone(),
+        status: container.status.clone(),
+    })
+}
+
+/// Services as the manager lists them, plus those only known from their
+/// tasks, each with its tasks; sorted by name
+fn swarm_services(services: Vec<bollard::models::Service>, tasks: Vec<SwarmTask>) -> Vec<SwarmService> {
+    let mut by_service: BTreeMap<String, Vec<SwarmTask>> = BTreeMap::new();
+    for task in tasks {
+        by_service.entry(task.service.clone()).or_default().push(task);
+    }
+    let running = |tasks: &[SwarmTask]| {
+        tasks
+            .iter()
+            .filter(|t| matches!(t.status, ContainerStatus::Running | ContainerStatus::Healthy))
+            .count()
+    };
+
+    let mut listed: Vec<SwarmService> = services
+        .into_iter()
+        .filter_map(|service| {
+            let spec = service.spec?;
+            let name = spec.name?;
+            let tasks = by_service.remove(&name).unwrap_or_default();
+            let image = spec
+                .task_template
+                .and_then(|t| t.container_spec)
+                .and_then(|c| c.image)
+                .unwrap_or_default();
+            let mode = spec.mode.unwrap_or_default();
+            let (mode, desired_replicas) = if let Some(replicated) = mode.replicated {
+                ("replicated", replicated.replicas.map(|r| r.max(0) as u64))
+            } else if mode.global.is_some() {
+                ("global", None)
+            } else if mode.replicated_job.is_some() {
+                ("replicated-job", None)
+            } else if mode.global_job.is_some() {
+                ("global-job", None)
+            } else {
+                ("", None)
+            };
+            let ports = service
+                .endpoint
+                .and_then(|e| e.ports)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|p| {
+                    Some(PortMapping {
+                        host_port: p.published_port.and_then(|p| u16::try_from(p).ok()),
+                        container_port: u16::try_from(p.target_port?).ok()?,
+                        protocol: p.protocol.map_or_else(|| "tcp".to_string(), |p| p.to_string()),
+                    })
+                })
+                .collect();
+            Some(SwarmService {
+                id: service.id.unwrap_or_default(),
+                name,
+                // Deployed images are pinned to a digest; the tag is what was asked for
+                image: image.split('@').next().unwrap_or_default().to_string(),
+                mode: mode.to_string(),
+                desired_replicas,
+                running_tasks: running(&tasks),
+                stack: spec.labels.and_then(|mut l| l.remove(projects::STACK_LABEL)),
+                ports,
+                tasks,
+            })
+        })
+        .collect();
+
+    // Tasks of services this host cannot list, such as on a worker node
+    listed.extend(by_service.into_iter().map(|(name, tasks)| SwarmService {
+        id: tasks[0].service_id.clone(),
+        name,
+        image: String::new(),
+        mode: String::new(),
+        desired_replicas: None,
+        running_tasks: running(&tasks),
+        stack: None,
+        ports: Vec::new(),
+        tasks,
+    }));
+    listed.sort_by(|a, b| a.name.cmp(&b.name));
+    listed
+}
+
+const DEFAULT_NETWORKS: [&str; 3] = ["bridge", "host", "none"];
+
+/// The category, compose projects and user-defined networks a container
+/// belongs to. Besides the project that started it, a container serves every
+/// project whose network it is attached to.
+fn memberships(
+    category: &ServiceCategory,
+    labels: &HashMap<String, String>,
+    networks: &[String],
+    network_projects: &HashMap<String, String>,
+) -> Vec<GroupMembership> {
+    let mut memberships = vec![GroupMembership {
+        kind: MembershipKind::Category,
+        name: format!("{:?}", category).to_lowercase(),
+        via: None,
+    }];
+    let own = labels.get(projects::PROJECT_LABEL);
+    if let Some(project) = own {
+        memberships.push(GroupMembership {
+            kind: MembershipKind::Project,
+            name: project.clone(),
+            via: None,
+        });
+    }
+    for network in networks {
+        let Some(project) = network_projects.get(network).filter(|p| Some(*p) != own) else {
+            continue;
+        };
+        let known = memberships
+            .iter()
+            .any(|m| m.kind == MembershipKind::Project && &m.name == project);
+        if !known {
+            memberships.push(GroupMembership {
+                kind: MembershipKind::Project,
+                name: project.clone(),
+                via: Some(network.clone()),
+            });
+        }
+    }
+    memberships.extend(
+        networks
+            .iter()
+            .filter(|n| !DEFAULT_NETWORKS.contains(&n.as_str()))
+            .map(|n| GroupMembership {
+                kind: MembershipKind::Network,
+                name: n.clone(),
+                via: None,
+            }),
+    );
+    memberships
 }
+
+/// Sum one stat across the containers that have stats, to two decimals
+fn total_stat(containers: &[ContainerInfo], f: fn(&ContainerStats) -> f64) -> f64 {
+    let sum: f64 = containers.iter().filter_map(|c| c.stats.as_ref()).map(f).sum();
+    (sum * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests;
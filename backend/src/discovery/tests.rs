@@ -0,0 +1,713 @@
+//! Golden-file tests for discovery against the fake Docker daemon
+//!
+//! Outputs are compared with `tests/golden/<name>.json`. Run with
+//! `UPDATE_GOLDEN=1 cargo test` to rewrite them after an intended change.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::DockerDiscovery;
+use crate::docker_api::fake::{FakeDocker, Fixture};
+use crate::docker_api::LogWindow;
+use crate::envfile::{self, EnvFile};
+use crate::hosts::MultiDocker;
+use crate::jobs::JobRegistry;
+use crate::models::{
+    ContainerGroup, ContainerSpec, ContainerStatus, DockerEvent, EventActor, FileChangeKind, FlowDirection, JobStatus, LogStreams,
+    NumaNode,
+    PortSpec, ServiceCategory,
+};
+use crate::projects::{self, ProjectAction};
+
+fn discovery() -> DockerDiscovery<FakeDocker> {
+    DockerDiscovery::new(FakeDocker::load("stack"))
+}
+
+/// Drop fields that change on every run
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for key in ["generatedAt", "timestamp"] {
+                if map.contains_key(key) {
+                    map.insert(key.to_string(), "<redacted>".into());
+                }
+            }
+            map.values_mut().for_each(redact);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn assert_golden<T: Serialize>(name: &str, actual: &T) {
+    let mut value = serde_json::to_value(actual).unwrap();
+    redact(&mut value);
+    assert_golden_text(&format!("{}.json", name), &(serde_json::to_string_pretty(&value).unwrap() + "\n"));
+}
+
+fn assert_golden_text(file: &str, actual: &str) {
+    let path = format!("{}/tests/golden/{}", env!("CARGO_MANIFEST_DIR"), file);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing {}; run with UPDATE_GOLDEN=1 to create it", path));
+    assert!(
+        expected == actual,
+        "{} does not match; run with UPDATE_GOLDEN=1 to accept the new output\n\n{}",
+        path,
+        actual
+    );
+}
+
+#[tokio::test]
+async fn containers() {
+    let containers = discovery().list_containers().await.unwrap();
+    assert_eq!(containers.len(), 5);
+    assert_eq!(containers[3].status, ContainerStatus::Healthy);
+    assert_golden("containers", &containers);
+}
+
+#[tokio::test]
+async fn listings_are_cached_while_events_are_trusted() {
+    let discovery = discovery();
+    let event = |action: &str| DockerEvent {
+        time: chrono::Utc::now(),
+        event_type: "container".to_string(),
+        action: action.to_string(),
+        detail: None,
+        actor: EventActor {
+            id: "a1".to_string(),
+            name: Some("application-api-1".to_string()),
+        },
+        attributes: Default::default(),
+        origin: None,
+    };
+    discovery.list_containers().await.unwrap();
+    discovery.list_containers().await.unwrap();
+    assert_eq!(discovery.docker.listings(), 2);
+
+    discovery.trust_cache(true);
+    let listed = discovery.list_containers().await.unwrap();
+    assert_eq!(discovery.list_containers().await.unwrap(), listed);
+    assert_eq!(discovery.docker.listings(), 3);
+
+    // Health check execs leave the listing as it was; a stop does not
+    discovery.observe_event(&event("exec_start"));
+    discovery.list_containers().await.unwrap();
+    assert_eq!(discovery.docker.listings(), 3);
+    discovery.observe_event(&event("stop"));
+    discovery.list_containers().await.unwrap();
+    discovery.list_containers().await.unwrap();
+    assert_eq!(discovery.docker.listings(), 4);
+
+    discovery.trust_cache(false);
+    discovery.list_containers().await.unwrap();
+    assert_eq!(discovery.docker.listings(), 5);
+}
+
+#[tokio::test]
+async fn topology() {
+    let discovery = discovery();
+    let containers = discovery.list_containers().await.unwrap();
+    assert_golden("topology", &discovery.build_topology(&containers));
+}
+
+#[tokio::test]
+async fn system_overview_flowchart() {
+    let flowchart = discovery().generate_flowchart("system-overview").await.unwrap();
+    assert_golden("flowchart_system_overview", &flowchart);
+}
+
+#[tokio::test]
+async fn category_flowchart() {
+    let flowchart = discovery().generate_flowchart("application-overview").await.unwrap().unwrap();
+    assert!(flowchart.nodes.iter().any(|n| !n.warnings.is_empty()));
+    assert_golden("flowchart_application", &flowchart);
+}
+
+#[tokio::test]
+async fn container_flowchart() {
+    let flowchart = discovery().generate_flowchart("application-api-1").await.unwrap();
+    assert_golden("flowchart_container", &flowchart);
+}
+
+#[tokio::test]
+async fn network_flowchart() {
+    // Both api replicas are on backend and frontend-net; each is one node here
+    let flowchart = discovery().generate_flowchart("network:backend").await.unwrap().unwrap();
+    assert_eq!(flowchart.nodes.len(), 4);
+    assert_golden("flowchart_network", &flowchart);
+}
+
+#[tokio::test]
+async fn volumes_list_their_containers() {
+    let discovery = discovery();
+    let volumes = discovery.list_volumes().await.unwrap();
+    assert_golden("volumes", &volumes);
+
+    // The api replicas share uploads, which links them in the graph
+    let graph = discovery.dependency_graph().await.unwrap();
+    let shared: Vec<_> = graph.edges.iter().filter(|e| e.kind == crate::graph::EdgeKind::Volume).collect();
+    assert_eq!(shared.len(), 1);
+    assert_eq!((shared[0].source.as_str(), shared[0].target.as_str()), ("application-api-1", "application-api-2"));
+    assert_eq!(shared[0].label, "uploads");
+}
+
+#[tokio::test]
+async fn project_flowchart() {
+    let discovery = discovery();
+    let flowchart = discovery.generate_flowchart("project:shop").await.unwrap().unwrap();
+    assert_eq!(flowchart.nodes.len(), 4);
+    let alias = discovery.generate_flowchart("project-shop").await.unwrap().unwrap();
+    assert_eq!(alias.id, "project-shop");
+    assert_eq!(alias.nodes.len(), flowchart.nodes.len());
+    assert!(discovery.generate_flowchart("project-billing").await.unwrap().is_none());
+}
+
+#[test]
+fn memberships_cover_every_project_and_network() {
+    let labels = HashMap::from([(projects::PROJECT_LABEL.to_string(), "shop".to_string())]);
+    let networks = ["shop_default".to_string(), "billing_default".to_string(), "bridge".to_string()];
+    let network_projects = HashMap::from([
+        ("shop_default".to_string(), "shop".to_string()),
+        ("billing_default".to_string(), "billing".to_string()),
+    ]);
+    let ids: Vec<(String, Option<String>)> =
+        super::memberships(&ServiceCategory::Application, &labels, &networks, &network_projects)
+            .into_iter()
+            .map(|m| (m.flowchart_id(), m.via))
+            .collect();
+    let via = |network: &str| Some(network.to_string());
+    assert_eq!(
+        ids,
+        [
+            ("application-overview".to_string(), None),
+            ("project:shop".to_string(), None),
+            ("project:billing".to_string(), via("billing_default")),
+            ("network:shop_default".to_string(), None),
+            ("network:billing_default".to_string(), None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unknown_flowchart() {
+    assert!(discovery().generate_flowchart("nope").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn container_detail() {
+    let detail = discovery().get_container_detail("infrastructure-postgres", false).await.unwrap();
+    assert_golden("container_detail", &detail);
+
+    let redacted = discovery().get_container_detail("infrastructure-postgres", true).await.unwrap().unwrap();
+    assert!(!redacted.environment.is_empty());
+    assert!(redacted.environment.iter().all(|v| v.ends_with("=[redacted]")));
+}
+
+#[tokio::test]
+async fn cpu_map() {
+    let numa = vec![
+        NumaNode { node: 0, cores: vec![0, 1, 2, 3] },
+        NumaNode { node: 1, cores: vec![4, 5, 6, 7] },
+    ];
+    assert_golden("cpu_map", &discovery().cpu_map(numa).await.unwrap());
+}
+
+#[tokio::test]
+async fn diagnostics_report() {
+    assert_golden("diagnostics", &discovery().get_diagnostics().await.unwrap());
+}
+
+#[tokio::test]
+async fn system_info() {
+    assert_golden("system_info", &discovery().system_info().await.unwrap());
+}
+
+#[tokio::test]
+async fn preflight_report() {
+    let spec = ContainerSpec {
+        name: Some("application-api-1".to_string()),
+        image: "flowscope/api".to_string(),
+        env: vec!["DB_HOST=postgres".to_string(), "CACHE_HOST=frontend-web".to_string()],
+        ports: vec![PortSpec {
+            container_port: 8080,
+            host_port: Some(8080),
+            protocol: "tcp".to_string(),
+        }],
+        networks: vec!["backend".to_string(), "missing".to_string()],
+        volumes: vec!["pgdata:/data".to_string(), "cache:/cache".to_string()],
+        labels: Default::default(),
+    };
+    let report = discovery().preflight(&spec).await.unwrap();
+    assert!(!report.ok);
+    assert_golden("preflight", &report);
+}
+
+#[tokio::test]
+async fn creates_containers_from_specs() {
+    let discovery = discovery();
+    let spec = ContainerSpec {
+        name: Some("worker".to_string()),
+        image: "flowscope/worker:1.2".to_string(),
+        env: vec!["QUEUE=jobs".to_string()],
+        ports: vec![PortSpec {
+            container_port: 9000,
+            host_port: None,
+            protocol: "tcp".to_string(),
+        }],
+        networks: vec!["backend".to_string()],
+        volumes: Vec::new(),
+        labels: Default::default(),
+    };
+    assert!(discovery.preflight(&spec).await.unwrap().ok);
+    assert_eq!(discovery.create_from_spec(&spec).await.unwrap(), "worker");
+    // The daemon lacked the image, so it was pulled before creating again
+    assert_eq!(
+        discovery.docker.actions(),
+        vec!["pull flowscope/worker:1.2", "create worker", "start worker"]
+    );
+}
+
+#[tokio::test]
+async fn lifecycle_actions() {
+    let discovery = discovery();
+    let result = discovery.restart_container("application-api-1").await.unwrap().unwrap();
+    assert!(result.success);
+    discovery.stop_container("b1b2c3d4e5f6").await.unwrap();
+    assert!(discovery.start_container("nope").await.unwrap().is_none());
+    let paused = discovery.pause_container("frontend-web").await.unwrap().unwrap();
+    assert_eq!((paused.action.as_str(), paused.message.as_str()), ("pause", "Container paused"));
+    discovery.unpause_container("frontend-web").await.unwrap();
+    let reloaded = discovery.kill_container("frontend-web", "SIGHUP").await.unwrap().unwrap();
+    assert_eq!(reloaded.message, "Sent SIGHUP");
+
+    assert_eq!(
+        discovery.docker.actions(),
+        vec![
+            "restart application-api-1",
+            "stop application-api-1",
+            "pause frontend-web",
+            "unpause frontend-web",
+            "kill SIGHUP frontend-web",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn removal_stops_running_containers_only_when_forced() {
+    let discovery = discovery();
+    let stopped = discovery.remove_container("monitoring-prometheus", false, true).await.unwrap().unwrap();
+    assert_eq!((stopped.success, stopped.message.as_str()), (true, "Container removed"));
+    let running = discovery.remove_container("frontend-web", true, false).await.unwrap().unwrap();
+    assert_eq!(running.message, "Container stopped and removed");
+    assert!(discovery.remove_container("missing", true, false).await.unwrap().is_none());
+
+    assert_eq!(
+        discovery.docker.actions(),
+        vec!["remove --volumes monitoring-prometheus", "stop frontend-web", "remove frontend-web"]
+    );
+}
+
+#[tokio::test]
+async fn recreate_puts_the_old_container_back_on_failure() {
+    let discovery = discovery();
+    let api = discovery.get_container("application-api-1").await.unwrap().unwrap();
+
+    // Not among the fixture's images, so the create fails
+    assert!(discovery.recreate_container(&api, "sha256:99").await.is_err());
+    let id = discovery.recreate_container(&api, "sha256:02").await.unwrap();
+    assert_eq!(id, "application-api-1");
+    assert_eq!(
+        discovery.docker.actions(),
+        vec![
+            "stop application-api-1",
+            "rename application-api-1 application-api-1-replaced-b1b2c3d4e5f6",
+            "rename application-api-1 application-api-1",
+            "start application-api-1",
+            "stop application-api-1",
+            "rename application-api-1 application-api-1-replaced-b1b2c3d4e5f6",
+            "create application-api-1",
+            "start application-api-1",
+            "remove application-api-1",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn recreate_keeps_user_env_and_anonymous_volumes() {
+    let mut fixture = Fixture::load("stack");
+    let inspect = fixture.inspect.get_mut("application-api-1").unwrap();
+    inspect.image = Some("sha256:02".to_string());
+    inspect.host_config.as_mut().unwrap().binds = Some(vec!["uploads:/app/uploads".to_string()]);
+    inspect.mounts = Some(serde_json::from_value(serde_json::json!([
+        { "Type": "volume", "Name": "uploads", "Destination": "/app/uploads", "RW": true },
+        { "Type": "volume", "Name": "3f9a0c", "Destination": "/var/lib/api", "RW": true },
+        { "Type": "volume", "Name": "7be21d", "Destination": "/etc/api", "RW": false },
+        { "Type": "bind", "Source": "/srv/certs", "Destination": "/certs", "RW": false },
+    ])).unwrap());
+    fixture.image_inspect.insert(
+        "sha256:02".to_string(),
+        serde_json::from_value(serde_json::json!({
+            "Id": "sha256:02",
+            "Config": { "Env": ["PATH=/usr/bin", "TZ=Etc/UTC", "LANG=C.UTF-8"] },
+        }))
+        .unwrap(),
+    );
+    let discovery = DockerDiscovery::new(FakeDocker::new(fixture));
+    let api = discovery.get_container("application-api-1").await.unwrap().unwrap();
+
+    discovery.recreate_container(&api, "sha256:02").await.unwrap();
+    let created = discovery.docker.created("application-api-1").unwrap();
+    // LANG was the image's default; TZ was overridden on the container
+    assert_eq!(
+        created.env.unwrap(),
+        vec!["DB_HOST=postgres", "CACHE_HOST=redis", "API_PORT=8080", "TZ=UTC"]
+    );
+    assert_eq!(
+        created.host_config.unwrap().binds.unwrap(),
+        vec!["uploads:/app/uploads", "3f9a0c:/var/lib/api", "7be21d:/etc/api:ro"]
+    );
+}
+
+#[tokio::test]
+async fn logs_are_tailed() {
+    let discovery = discovery();
+    let logs = discovery.get_container_logs("application-api-1", LogWindow::tail(2)).await.unwrap().unwrap();
+    let lines: Vec<&str> = logs.logs.iter().map(|l| l.line.as_str()).collect();
+    assert_eq!(lines, vec!["listening on :8080", "GET /health 200"]);
+
+    let at = |time| DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc);
+    let window = LogWindow {
+        tail: 100,
+        since: Some(at("2026-10-15T07:59:00Z")),
+        until: Some(at("2026-10-15T08:05:00Z")),
+        streams: LogStreams::Both,
+    };
+    let logs = discovery.get_container_logs("infrastructure-postgres", window).await.unwrap().unwrap();
+    assert_eq!(logs.logs.len(), 1);
+    assert_eq!((logs.logs[0].ts, logs.logs[0].line.as_str()), (Some(at("2026-10-15T08:00:00Z")), "checkpoint starting: time"));
+    let stderr = LogWindow {
+        streams: LogStreams::Stderr,
+        ..window
+    };
+    assert!(discovery.get_container_logs("infrastructure-postgres", stderr).await.unwrap().unwrap().logs.is_empty());
+}
+
+#[tokio::test]
+async fn processes_of_running_containers() {
+    let discovery = discovery();
+    let postgres = discovery.list_processes("infrastructure-postgres").await.unwrap().unwrap();
+    let commands: Vec<(u64, &str)> = postgres.processes.iter().map(|p| (p.pid, p.command.as_str())).collect();
+    assert_eq!(commands, vec![(1, "postgres"), (27, "postgres: checkpointer")]);
+    let stopped = discovery.list_processes("monitoring-prometheus").await.unwrap().unwrap();
+    assert!(stopped.processes.is_empty());
+    assert!(discovery.list_processes("missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn inspect_passthrough_redacts_environment_values() {
+    let discovery = discovery();
+    let raw = discovery.inspect_raw("infrastructure-postgres", false).await.unwrap().unwrap();
+    let env = raw.config.unwrap().env.unwrap();
+    assert!(env.contains(&"POSTGRES_DB=app".to_string()));
+    let redacted = discovery.inspect_raw("infrastructure-postgres", true).await.unwrap().unwrap();
+    let names: Vec<&str> = env.iter().map(|v| v.split('=').next().unwrap()).collect();
+    let expected: Vec<String> = names.iter().map(|n| format!("{}=[redacted]", n)).collect();
+    assert_eq!(redacted.config.unwrap().env.unwrap(), expected);
+    assert!(discovery.inspect_raw("missing", true).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn filesystem_changes() {
+    let discovery = discovery();
+    let api = discovery.container_changes("application-api-1").await.unwrap().unwrap();
+    assert_eq!((api.added, api.modified, api.deleted), (1, 3, 1));
+    let paths: Vec<&str> = api.changes.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(paths, vec!["/etc", "/etc/api.conf", "/etc/hosts.allow", "/tmp", "/tmp/uploads-cache"]);
+    assert_eq!(api.changes[2].kind, FileChangeKind::Deleted);
+    let web = discovery.container_changes("frontend-web").await.unwrap().unwrap();
+    assert!(web.changes.is_empty());
+}
+
+#[tokio::test]
+async fn group_flowchart_and_stats() {
+    let group = ContainerGroup {
+        name: "checkout".to_string(),
+        description: None,
+        containers: vec!["infrastructure-postgres".to_string()],
+        labels: [("com.docker.compose.service".to_string(), "api".to_string())].into(),
+        created_at: Default::default(),
+        updated_at: Default::default(),
+    };
+    let discovery = discovery();
+
+    assert_golden("flowchart_group", &discovery.generate_group_flowchart(&group).await.unwrap());
+
+    let stats = discovery.get_group_stats(&group).await.unwrap();
+    assert_eq!(stats.container_count, 3);
+    assert_eq!(stats.pids, 29);
+}
+
+#[tokio::test]
+async fn dot_export() {
+    let flowchart = discovery().generate_flowchart("application-overview").await.unwrap().unwrap();
+    assert_golden_text(
+        "flowchart_application.dot",
+        &crate::dot::render(&flowchart, FlowDirection::Td),
+    );
+}
+
+#[tokio::test]
+async fn mermaid_export() {
+    let flowchart = discovery().generate_flowchart("application-overview").await.unwrap().unwrap();
+    assert_golden_text("flowchart_application.mmd", &crate::mermaid::render(&flowchart));
+
+    let top_down = crate::mermaid::render_directed(&flowchart, FlowDirection::Td);
+    assert!(top_down.contains("\nflowchart TD\n"));
+    assert_eq!(top_down.replace("flowchart TD", "flowchart LR"), crate::mermaid::render(&flowchart));
+}
+
+#[tokio::test]
+async fn dependency_graph_exports() {
+    let graph = discovery().dependency_graph().await.unwrap();
+    assert_eq!(graph.nodes.len(), 5);
+    assert_golden_text("graph.graphml", &crate::graph::to_graphml(&graph));
+
+    let jgf = crate::graph::to_jgf(&graph);
+    let edges = jgf["graph"]["edges"].as_array().unwrap();
+    assert!(edges.iter().any(|e| e["relation"] == "env"
+        && e["source"] == "application-api-2"
+        && e["target"] == "infrastructure-postgres"
+        && e["label"] == "DB_HOST"));
+}
+
+#[tokio::test]
+async fn image_usage() {
+    let usage = discovery().get_image_usage().await.unwrap();
+    let api = usage.iter().find(|u| u.image == "flowscope/api:latest").unwrap();
+    assert_eq!(api.containers, ["application-api-1", "application-api-2"]);
+    assert_golden("image_usage", &usage);
+}
+
+#[tokio::test]
+async fn project_restart_job() {
+    let discovery = Arc::new(discovery());
+    let members = discovery.project_members("shop").await.unwrap().unwrap();
+    assert_golden("projects", &discovery.list_projects().await.unwrap());
+
+    let jobs = Arc::new(JobRegistry::new());
+    let steps = projects::plan(ProjectAction::Restart, members);
+    let job = jobs.spawn("project-restart", "shop", steps.len(), |handle| {
+        projects::apply(discovery.clone(), ProjectAction::Restart, steps, handle)
+    });
+    let job = loop {
+        let current = jobs.get(&job.id).unwrap();
+        if current.status != JobStatus::Running {
+            break current;
+        }
+        tokio::task::yield_now().await;
+    };
+
+    assert_eq!(job.status, JobStatus::Succeeded);
+    assert_eq!(job.results.len(), 4);
+    assert_eq!(
+        discovery.docker.actions(),
+        [
+            "restart infrastructure-postgres",
+            "restart application-api-1",
+            "restart application-api-2",
+            "restart frontend-web",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn compose_service_scaling() {
+    let discovery = Arc::new(discovery());
+    let members = discovery.project_members("shop").await.unwrap().unwrap();
+    let jobs = Arc::new(JobRegistry::new());
+
+    for replicas in [3, 1] {
+        let steps = projects::plan_scale("shop", "api", replicas, members.clone()).unwrap();
+        let job = jobs.spawn("service-scale", "shop/api", steps.len(), |handle| {
+            projects::apply_scale(discovery.clone(), steps, handle)
+        });
+        while jobs.get(&job.id).unwrap().status == JobStatus::Running {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(jobs.get(&job.id).unwrap().status, JobStatus::Succeeded);
+    }
+
+    assert_eq!(
+        discovery.docker.actions(),
+        [
+            "create application-api-3",
+            "start application-api-3",
+            "stop application-api-2",
+            "remove application-api-2",
+        ]
+    );
+    assert!(projects::plan_scale("shop", "nope", 1, members).is_err());
+}
+
+#[tokio::test]
+async fn env_file_drift() {
+    let file = EnvFile {
+        content: "DB_HOST=postgres\nAPI_PORT=8080\nAPI_URL=http://api:8080\n".to_string(),
+        uploaded_at: Default::default(),
+    };
+    let declared = envfile::parse(&file.content).unwrap();
+    let members = discovery().project_environment("shop").await.unwrap().unwrap();
+    let report = envfile::report("shop", &file, &declared, members);
+
+    assert!(!report.ok);
+    let api = report.services.iter().find(|s| s.service == "api").unwrap();
+    assert_eq!(api.unset, ["API_PORT", "API_URL"]);
+    assert_eq!(api.drifted[0].container, "application-api-2");
+    assert_golden("env_drift", &report);
+}
+
+#[tokio::test]
+async fn debug_sidecar_that_fails_to_start_is_removed() {
+    let discovery = DockerDiscovery::new(FakeDocker::new(Fixture {
+        unstartable: vec!["application-api-1-debug".to_string()],
+        ..Fixture::load("stack")
+    }));
+    let target = discovery.get_container("application-api-1").await.unwrap().unwrap();
+    let launched = discovery
+        .launch_debug_sidecar(
+            &target,
+            "application-api-1-debug",
+            "nicolaka/netshoot:latest",
+            std::time::Duration::from_secs(60),
+            Default::default(),
+        )
+        .await;
+
+    assert!(launched.is_err());
+    assert_eq!(
+        discovery.docker.actions(),
+        [
+            "pull nicolaka/netshoot:latest",
+            "create application-api-1-debug",
+            "remove application-api-1-debug",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn debug_sidecar_pulls_missing_image() {
+    let discovery = discovery();
+    let target = discovery.get_container("application-api-1").await.unwrap().unwrap();
+    let id = discovery
+        .launch_debug_sidecar(
+            &target,
+            "application-api-1-debug",
+            "nicolaka/netshoot:latest",
+            std::time::Duration::from_secs(60),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    discovery.discard_container(&id).await.unwrap();
+
+    assert_eq!(
+        discovery.docker.actions(),
+        [
+            "pull nicolaka/netshoot:latest",
+            "create application-api-1-debug",
+            "start application-api-1-debug",
+            "stop application-api-1-debug",
+            "remove application-api-1-debug",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn container_address_from_networks() {
+    let discovery = discovery();
+    assert_eq!(
+        discovery.container_address("infrastructure-postgres").await.unwrap(),
+        Some("172.18.0.2".parse().unwrap())
+    );
+    assert_eq!(discovery.container_address("frontend-web").await.unwrap(), None);
+}
+
+fn swarm_node(node: &str, tasks: &[(&str, u32)], services: Vec<bollard::models::Service>) -> FakeDocker {
+    let containers = tasks
+        .iter()
+        .map(|(task, slot)| bollard::models::ContainerSummary {
+            id: Some(format!("{}{}", node, task)),
+            names: Some(vec![format!("/web.{}.{}", slot, task)]),
+            image: Some("nginx:1.27".to_string()),
+            state: Some("running".to_string()),
+            labels: Some(HashMap::from([
+                ("com.docker.swarm.service.name".to_string(), "web".to_string()),
+                ("com.docker.swarm.service.id".to_string(), "svc1".to_string()),
+                ("com.docker.swarm.task.id".to_string(), task.to_string()),
+                ("com.docker.swarm.task.name".to_string(), format!("web.{}.{}", slot, task)),
+                ("com.docker.swarm.node.id".to_string(), node.to_string()),
+            ])),
+            ..Default::default()
+        })
+        .collect();
+    FakeDocker::new(Fixture {
+        containers,
+        services,
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn swarm_service_fans_out_to_tasks_across_nodes() {
+    use bollard::models::{Service, ServiceSpec, ServiceSpecMode, ServiceSpecModeReplicated, TaskSpec, TaskSpecContainerSpec};
+
+    let web = Service {
+        id: Some("svc1".to_string()),
+        spec: Some(ServiceSpec {
+            name: Some("web".to_string()),
+            task_template: Some(TaskSpec {
+                container_spec: Some(TaskSpecContainerSpec {
+                    image: Some("nginx:1.27@sha256:abc".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            mode: Some(ServiceSpecMode {
+                replicated: Some(ServiceSpecModeReplicated { replicas: Some(3) }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let discovery = DockerDiscovery::new(MultiDocker::new(vec![
+        ("manager".to_string(), swarm_node("m", &[("t1", 1)], vec![web])),
+        ("worker".to_string(), swarm_node("w", &[("t2", 2), ("t3", 3)], Vec::new())),
+    ]));
+
+    let services = discovery.list_services().await.unwrap();
+    assert_eq!(services.len(), 1);
+    let web = &services[0];
+    assert_eq!((web.image.as_str(), web.mode.as_str()), ("nginx:1.27", "replicated"));
+    assert_eq!((web.desired_replicas, web.running_tasks), (Some(3), 3));
+    let placed: Vec<_> = web.tasks.iter().map(|t| (t.slot, t.host.as_deref())).collect();
+    assert_eq!(placed, [(Some(1), Some("manager")), (Some(2), Some("worker")), (Some(3), Some("worker"))]);
+
+    let flowchart = discovery.generate_flowchart("service:web").await.unwrap().unwrap();
+    assert_eq!(flowchart.nodes.len(), 4);
+    assert_eq!(flowchart.nodes[0].status, ContainerStatus::Healthy);
+    assert!(flowchart.connections.iter().all(|c| c.source == "service:web"));
+    assert_eq!(flowchart.connections[2].label.as_deref(), Some("worker"));
+    assert!(discovery.generate_flowchart("service:nope").await.unwrap().is_none());
+
+    let topology = discovery.get_topology().await.unwrap();
+    assert!(topology.flowcharts.iter().any(|f| f.id == "service:web" && f.node_count == 4));
+}
@@ -0,0 +1,600 @@
+//! Docker API abstraction
+//!
+//! [`DockerApi`] is the subset of the Docker Engine API that discovery uses.
+//! Production code talks to the daemon through bollard's [`Docker`]; tests use
+//! the fixture-backed fake in [`fake`] so topology and flowchart generation can
+//! run without a daemon.
+
+use std::{future::Future, pin::Pin};
+
+use bollard::{
+    container::{
+        AttachContainerOptions, AttachContainerResults, Config, CreateContainerOptions, InspectContainerOptions, KillContainerOptions,
+        ListContainersOptions, LogsOptions,
+        LogOutput, RemoveContainerOptions, RenameContainerOptions, RestartContainerOptions, Stats, StatsOptions, StopContainerOptions,
+        TopOptions, WaitContainerOptions,
+    },
+    errors::Error,
+    exec::{CreateExecOptions, ResizeExecOptions, StartExecOptions, StartExecResults},
+    image::{CreateImageOptions, ListImagesOptions, RemoveImageOptions},
+    models::{
+        ContainerInspectResponse, ContainerSummary, ContainerTopResponse, EventMessage, FilesystemChange, ImageInspect, ImageSummary,
+        Network,
+        Service, SystemInfo, Volume,
+    },
+    network::ListNetworksOptions,
+    service::{InspectServiceOptions, ListServicesOptions, UpdateServiceOptions},
+    system::EventsOptions,
+    volume::ListVolumesOptions,
+    Docker,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{stream::BoxStream, StreamExt, TryStreamExt};
+use tokio::io::AsyncWrite;
+
+use crate::models::{LogEntry, LogStream, LogStreams};
+
+#[cfg(test)]
+pub mod fake;
+
+/// Default request timeout, in seconds
+const TIMEOUT_SECS: u64 = 120;
+
+/// Seconds a stopping container is given to exit before it is killed
+pub const STOP_GRACE_SECS: u64 = 10;
+
+/// Connect to the daemon at `host`, a `unix://`, `npipe://`, `tcp://` or
+/// `http://` URL; see [`crate::daemon`] for how the host is chosen
+pub fn connect(host: &str) -> Result<Docker, Error> {
+    match host {
+        #[cfg(unix)]
+        h if h.starts_with("unix://") => Docker::connect_with_unix(h, TIMEOUT_SECS, bollard::API_DEFAULT_VERSION),
+        #[cfg(windows)]
+        h if h.starts_with("npipe://") => Docker::connect_with_named_pipe(h, TIMEOUT_SECS, bollard::API_DEFAULT_VERSION),
+        h if h.starts_with("tcp://") || h.starts_with("http://") => {
+            Docker::connect_with_http(h, TIMEOUT_SECS, bollard::API_DEFAULT_VERSION)
+        }
+        h => Err(Error::UnsupportedURISchemeError { uri: h.to_string() }),
+    }
+}
+
+/// What a command run with [`DockerApi::exec_output`] wrote and how it exited
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i64>,
+    /// Output beyond the limit was dropped
+    pub truncated: bool,
+}
+
+impl ExecOutput {
+    /// Append `chunk` to `stream`, dropping whatever passes `limit`
+    fn push(stream: &mut String, truncated: &mut bool, chunk: &[u8], limit: usize) {
+        let room = limit.saturating_sub(stream.len());
+        if chunk.len() > room {
+            *truncated = true;
+        }
+        stream.push_str(&String::from_utf8_lossy(&chunk[..chunk.len().min(room)]));
+    }
+}
+
+/// Which log lines [`DockerApi::logs`] reads
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LogWindow {
+    /// The last `tail` lines of those in the window
+    pub tail: usize,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub streams: LogStreams,
+}
+
+impl LogWindow {
+    /// The last `tail` lines of both streams
+    pub fn tail(tail: usize) -> Self {
+        Self {
+            tail,
+            ..Default::default()
+        }
+    }
+}
+
+/// A log line read with timestamps, split from the RFC 3339 time the daemon
+/// puts in front of it
+/// The RFC 3339 timestamp Docker put before a line, and the line without it
+pub fn split_timestamp(text: &str) -> (Option<DateTime<Utc>>, &str) {
+    match text.split_once(' ') {
+        Some((ts, line)) => match DateTime::parse_from_rfc3339(ts) {
+            Ok(ts) => (Some(ts.with_timezone(&Utc)), line),
+            Err(_) => (None, text),
+        },
+        None => match DateTime::parse_from_rfc3339(text) {
+            Ok(ts) => (Some(ts.with_timezone(&Utc)), ""),
+            Err(_) => (None, text),
+        },
+    }
+}
+
+fn log_entry(output: LogOutput) -> Option<LogEntry> {
+    let (stream, message) = match output {
+        LogOutput::StdOut { message } | LogOutput::Console { message } => (LogStream::Stdout, message),
+        LogOutput::StdErr { message } => (LogStream::Stderr, message),
+        LogOutput::StdIn { .. } => return None,
+    };
+    let text = String::from_utf8_lossy(&message);
+    let (ts, line) = split_timestamp(text.trim_end_matches(['\n', '\r']));
+    Some(LogEntry {
+        ts,
+        stream,
+        line: line.to_string(),
+        json: None,
+    })
+}
+
+/// A command started with [`DockerApi::exec_terminal`]: its exec id, the
+/// terminal's output and its input
+pub struct ExecSession {
+    pub id: String,
+    pub output: BoxStream<'static, Result<LogOutput, Error>>,
+    pub input: Pin<Box<dyn AsyncWrite + Send>>,
+}
+
+/// Docker Engine operations used by FlowScope
+pub trait DockerApi: Send + Sync + 'static {
+    /// All containers, including stopped ones
+    fn list_containers(&self) -> impl Future<Output = Result<Vec<ContainerSummary>, Error>> + Send;
+
+    fn inspect_container(&self, id: &str) -> impl Future<Output = Result<ContainerInspectResponse, Error>> + Send;
+
+    /// A single stats sample, or `None` if the daemon returned nothing usable
+    fn stats(&self, id: &str) -> impl Future<Output = Result<Option<Stats>, Error>> + Send;
+
+    fn top_processes(&self, id: &str, ps_args: &str) -> impl Future<Output = Result<ContainerTopResponse, Error>> + Send;
+
+    /// Paths added, changed or deleted in the container's writable layer
+    fn container_changes(&self, id: &str) -> impl Future<Output = Result<Vec<FilesystemChange>, Error>> + Send;
+
+    /// The log lines in `window`, oldest first
+    fn logs(&self, id: &str, window: LogWindow) -> impl Future<Output = Result<Vec<LogEntry>, Error>> + Send;
+
+    /// Log output written from now on, until the container stops
+    fn follow_logs(&self, id: &str) -> BoxStream<'static, Result<String, Error>>;
+
+    /// The last `tail` lines (all of them when `None`) written since `since`
+    /// (Unix seconds, 0 for the beginning), then new output until the
+    /// container stops
+    fn stream_logs(&self, id: &str, tail: Option<usize>, since: i64) -> BoxStream<'static, Result<String, Error>>;
+
+    /// Output written since `since` (Unix seconds), then new output until the
+    /// container stops, each line prefixed with its RFC 3339 timestamp
+    fn stream_timestamped_logs(&self, id: &str, since: i64) -> BoxStream<'static, Result<String, Error>>;
+
+    /// Run `cmd` in a running container and return its combined output, or
+    /// `None` when it exited non-zero (including when it does not exist)
+    fn exec(&self, id: &str, cmd: &[&str]) -> impl Future<Output = Result<Option<String>, Error>> + Send;
+
+    /// Run `cmd` in a running container, keeping stdout and stderr apart and
+    /// each to at most `limit` bytes
+    fn exec_output(
+        &self,
+        id: &str,
+        cmd: &[String],
+        working_dir: Option<&str>,
+        limit: usize,
+    ) -> impl Future<Output = Result<ExecOutput, Error>> + Send;
+
+    /// Start `cmd` in a running container on a `rows` x `cols` TTY, attached
+    /// to its input and output
+    fn exec_terminal(
+        &self,
+        id: &str,
+        cmd: &[String],
+        rows: u16,
+        cols: u16,
+    ) -> impl Future<Output = Result<ExecSession, Error>> + Send;
+
+    /// Resize the TTY of an exec started with [`DockerApi::exec_terminal`]
+    fn resize_exec(&self, exec_id: &str, rows: u16, cols: u16) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Exit code of an exec, or `None` while it is still running
+    fn exec_exit_code(&self, exec_id: &str) -> impl Future<Output = Result<Option<i64>, Error>> + Send;
+
+    /// Daemon events from `since` (Unix seconds) onwards, then new ones as
+    /// they happen
+    fn events(&self, since: i64) -> BoxStream<'static, Result<EventMessage, Error>>;
+
+    fn list_images(&self) -> impl Future<Output = Result<Vec<ImageSummary>, Error>> + Send;
+
+    /// A local image by id or tag, with the configuration it gives containers
+    fn inspect_image(&self, image: &str) -> impl Future<Output = Result<ImageInspect, Error>> + Send;
+
+    fn list_networks(&self) -> impl Future<Output = Result<Vec<Network>, Error>> + Send;
+
+    fn list_volumes(&self) -> impl Future<Output = Result<Vec<Volume>, Error>> + Send;
+
+    /// Swarm services; empty when the daemon is not a swarm manager
+    fn list_services(&self) -> impl Future<Output = Result<Vec<Service>, Error>> + Send;
+
+    fn info(&self) -> impl Future<Output = Result<SystemInfo, Error>> + Send;
+
+    fn restart_container(&self, id: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn stop_container(&self, id: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn start_container(&self, id: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Freeze every process of a running container
+    fn pause_container(&self, id: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn unpause_container(&self, id: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Send `signal`, such as `SIGHUP`, to a container's main process
+    fn kill_container(&self, id: &str, signal: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Create a container named `name`, returning its id
+    fn create_container(&self, name: &str, config: Config<String>) -> impl Future<Output = Result<String, Error>> + Send;
+
+    /// Remove a stopped container, with its anonymous volumes when `volumes` is set
+    fn remove_container(&self, id: &str, volumes: bool) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Give a container a new name
+    fn rename_container(&self, id: &str, name: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Set the replica count of a replicated Swarm service
+    fn scale_service(&self, service: &str, replicas: u64) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Pull an image, waiting for the pull to finish
+    fn pull_image(&self, image: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Remove an image by id or tag; without `force` the daemon refuses
+    /// images that containers were created from
+    fn remove_image(&self, image: &str, force: bool) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Attach to a container's stdin, stdout and stderr; attaching before
+    /// starting it means no early output is missed
+    fn attach_container(&self, id: &str) -> impl Future<Output = Result<AttachContainerResults, Error>> + Send;
+
+    /// Wait until a container is not running and return its exit code
+    fn wait_container(&self, id: &str) -> impl Future<Output = Result<i64, Error>> + Send;
+}
+
+impl DockerApi for Docker {
+    async fn list_containers(&self) -> Result<Vec<ContainerSummary>, Error> {
+        let options = ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        };
+        Docker::list_containers(self, Some(options)).await
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse, Error> {
+        Docker::inspect_container(self, id, None::<InspectContainerOptions>).await
+    }
+
+    async fn stats(&self, id: &str) -> Result<Option<Stats>, Error> {
+        let options = StatsOptions {
+            stream: false,
+            one_shot: true,
+        };
+        Docker::stats(self, id, Some(options)).next().await.transpose()
+    }
+
+    async fn top_processes(&self, id: &str, ps_args: &str) -> Result<ContainerTopResponse, Error> {
+        let options = (!ps_args.is_empty()).then_some(TopOptions { ps_args });
+        Docker::top_processes(self, id, options).await
+    }
+
+    async fn container_changes(&self, id: &str) -> Result<Vec<FilesystemChange>, Error> {
+        Ok(Docker::container_changes(self, id).await?.unwrap_or_default())
+    }
+
+    async fn logs(&self, id: &str, window: LogWindow) -> Result<Vec<LogEntry>, Error> {
+        let options = LogsOptions::<String> {
+            stdout: window.streams.stdout(),
+            stderr: window.streams.stderr(),
+            since: window.since.map_or(0, |t| t.timestamp()),
+            until: window.until.map_or(0, |t| t.timestamp()),
+            timestamps: true,
+            tail: window.tail.to_string(),
+            ..Default::default()
+        };
+
+        let mut stream = Docker::logs(self, id, Some(options));
+        let mut lines = Vec::new();
+        while let Some(Ok(output)) = stream.next().await {
+            lines.extend(log_entry(output));
+        }
+        Ok(lines)
+    }
+
+    fn follow_logs(&self, id: &str) -> BoxStream<'static, Result<String, Error>> {
+        let options = LogsOptions::<String> {
+            follow: true,
+            stdout: true,
+            stderr: true,
+            since: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        };
+        Docker::logs(self, id, Some(options))
+            .map(|output| output.map(|o| o.to_string()))
+            .boxed()
+    }
+
+    fn stream_logs(&self, id: &str, tail: Option<usize>, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        let options = LogsOptions::<String> {
+            follow: true,
+            stdout: true,
+            stderr: true,
+            since,
+            tail: tail.map_or("all".to_string(), |t| t.to_string()),
+            ..Default::default()
+        };
+        Docker::logs(self, id, Some(options))
+            .map(|output| output.map(|o| o.to_string()))
+            .boxed()
+    }
+
+    fn stream_timestamped_logs(&self, id: &str, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        let options = LogsOptions::<String> {
+            follow: true,
+            stdout: true,
+            stderr: true,
+            since,
+            timestamps: true,
+            ..Default::default()
+        };
+        Docker::logs(self, id, Some(options))
+            .map(|output| output.map(|o| o.to_string()))
+            .boxed()
+    }
+
+    async fn exec(&self, id: &str, cmd: &[&str]) -> Result<Option<String>, Error> {
+        let options = CreateExecOptions {
+            cmd: Some(cmd.iter().map(|c| c.to_string()).collect()),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            ..Default::default()
+        };
+        let exec = Docker::create_exec(self, id, options).await?;
+        let mut output = String::new();
+        if let StartExecResults::Attached { output: mut stream, .. } = Docker::start_exec(self, &exec.id, None).await? {
+            while let Some(chunk) = stream.next().await {
+                output.push_str(&chunk?.to_string());
+            }
+        }
+        let exit_code = Docker::inspect_exec(self, &exec.id).await?.exit_code;
+        Ok((exit_code == Some(0)).then_some(output))
+    }
+
+    async fn exec_output(
+        &self,
+        id: &str,
+        cmd: &[String],
+        working_dir: Option<&str>,
+        limit: usize,
+    ) -> Result<ExecOutput, Error> {
+        let options = CreateExecOptions {
+            cmd: Some(cmd.to_vec()),
+            working_dir: working_dir.map(str::to_string),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            ..Default::default()
+        };
+        let exec = Docker::create_exec(self, id, options).await?;
+        let mut output = ExecOutput::default();
+        if let StartExecResults::Attached { output: mut stream, .. } = Docker::start_exec(self, &exec.id, None).await? {
+            // Read to the end even past the limit, so the command is not
+            // blocked writing to a full pipe
+            while let Some(chunk) = stream.next().await {
+                match chunk? {
+                    LogOutput::StdErr { message } => {
+                        ExecOutput::push(&mut output.stderr, &mut output.truncated, &message, limit)
+                    }
+                    other => ExecOutput::push(&mut output.stdout, &mut output.truncated, &other.into_bytes(), limit),
+                }
+            }
+        }
+        output.exit_code = Docker::inspect_exec(self, &exec.id).await?.exit_code;
+        Ok(output)
+    }
+
+    async fn exec_terminal(&self, id: &str, cmd: &[String], rows: u16, cols: u16) -> Result<ExecSession, Error> {
+        let options = CreateExecOptions {
+            cmd: Some(cmd.to_vec()),
+            attach_stdin: Some(true),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            tty: Some(true),
+            env: Some(vec!["TERM=xterm-256color".to_string()]),
+            ..Default::default()
+        };
+        let exec = Docker::create_exec(self, id, options).await?;
+        let start = StartExecOptions {
+            tty: true,
+            ..Default::default()
+        };
+        match Docker::start_exec(self, &exec.id, Some(start)).await? {
+            StartExecResults::Attached { output, input } => {
+                DockerApi::resize_exec(self, &exec.id, rows, cols).await?;
+                Ok(ExecSession {
+                    id: exec.id,
+                    output,
+                    input,
+                })
+            }
+            StartExecResults::Detached => Err(Error::DockerStreamError {
+                error: "exec started detached".to_string(),
+            }),
+        }
+    }
+
+    async fn resize_exec(&self, exec_id: &str, rows: u16, cols: u16) -> Result<(), Error> {
+        let options = ResizeExecOptions {
+            height: rows,
+            width: cols,
+        };
+        Docker::resize_exec(self, exec_id, options).await
+    }
+
+    async fn exec_exit_code(&self, exec_id: &str) -> Result<Option<i64>, Error> {
+        let exec = Docker::inspect_exec(self, exec_id).await?;
+        Ok(if exec.running == Some(true) { None } else { exec.exit_code })
+    }
+
+    fn events(&self, since: i64) -> BoxStream<'static, Result<EventMessage, Error>> {
+        let options = EventsOptions::<String> {
+            since: Some(since.to_string()),
+            ..Default::default()
+        };
+        Docker::events(self, Some(options)).boxed()
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>, Error> {
+        Docker::list_images(self, Some(ListImagesOptions::<String>::default())).await
+    }
+
+    async fn inspect_image(&self, image: &str) -> Result<ImageInspect, Error> {
+        Docker::inspect_image(self, image).await
+    }
+
+    async fn list_networks(&self) -> Result<Vec<Network>, Error> {
+        Docker::list_networks(self, Some(ListNetworksOptions::<String>::default())).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<Volume>, Error> {
+        Ok(Docker::list_volumes(self, None::<ListVolumesOptions<String>>)
+            .await?
+            .volumes
+            .unwrap_or_default())
+    }
+
+    async fn list_services(&self) -> Result<Vec<Service>, Error> {
+        match Docker::list_services(self, None::<ListServicesOptions<String>>).await {
+            // Not a swarm manager, or not in a swarm at all
+            Err(Error::DockerResponseServerError { status_code: 503, .. }) => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    async fn info(&self) -> Result<SystemInfo, Error> {
+        Docker::info(self).await
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<(), Error> {
+        Docker::restart_container(self, id, Some(RestartContainerOptions { t: STOP_GRACE_SECS as isize })).await
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<(), Error> {
+        Docker::stop_container(self, id, Some(StopContainerOptions { t: STOP_GRACE_SECS as i64 })).await
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<(), Error> {
+        Docker::pause_container(self, id).await
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<(), Error> {
+        Docker::unpause_container(self, id).await
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<(), Error> {
+        Docker::kill_container(self, id, Some(KillContainerOptions { signal })).await
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), Error> {
+        Docker::start_container::<String>(self, id, None).await
+    }
+
+    async fn create_container(&self, name: &str, config: Config<String>) -> Result<String, Error> {
+        let options = CreateContainerOptions { name, platform: None };
+        Ok(Docker::create_container(self, Some(options), config).await?.id)
+    }
+
+    async fn remove_container(&self, id: &str, volumes: bool) -> Result<(), Error> {
+        let options = RemoveContainerOptions {
+            v: volumes,
+            ..Default::default()
+        };
+        Docker::remove_container(self, id, Some(options)).await
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<(), Error> {
+        Docker::rename_container(self, id, RenameContainerOptions { name }).await
+    }
+
+    async fn scale_service(&self, service: &str, replicas: u64) -> Result<(), Error> {
+        let current = Docker::inspect_service(self, service, None::<InspectServiceOptions>).await?;
+        let version = current.version.and_then(|v| v.index).unwrap_or_default();
+        let mut spec = current.spec.unwrap_or_default();
+        let mode = spec.mode.get_or_insert_with(Default::default);
+        mode.replicated.get_or_insert_with(Default::default).replicas = Some(replicas as i64);
+
+        let options = UpdateServiceOptions {
+            version,
+            ..Default::default()
+        };
+        Docker::update_service(self, service, spec, options, None).await?;
+        Ok(())
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<(), Error> {
+        let options = CreateImageOptions {
+            from_image: image,
+            ..Default::default()
+        };
+        Docker::create_image(self, Some(options), None, None)
+            .try_for_each(|_| async { Ok(()) })
+            .await
+    }
+
+    async fn remove_image(&self, image: &str, force: bool) -> Result<(), Error> {
+        let options = RemoveImageOptions { force, noprune: false };
+        Docker::remove_image(self, image, Some(options), None).await?;
+        Ok(())
+    }
+
+    async fn attach_container(&self, id: &str) -> Result<AttachContainerResults, Error> {
+        let options = AttachContainerOptions::<String> {
+            stdin: Some(true),
+            stdout: Some(true),
+            stderr: Some(true),
+            stream: Some(true),
+            ..Default::default()
+        };
+        Docker::attach_container(self, id, Some(options)).await
+    }
+
+    async fn wait_container(&self, id: &str) -> Result<i64, Error> {
+        let options = WaitContainerOptions { condition: "not-running" };
+        match Docker::wait_container(self, id, Some(options)).next().await {
+            Some(Ok(response)) => Ok(response.status_code),
+            // bollard reports a non-zero exit as an error
+            Some(Err(Error::DockerContainerWaitError { code, .. })) => Ok(code),
+            Some(Err(e)) => Err(e),
+            None => Err(Error::DockerStreamError {
+                error: "wait returned no status".to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_lines_are_split_from_their_timestamps() {
+        let entry = |output| log_entry(output).unwrap();
+        let stderr = entry(LogOutput::StdErr {
+            message: "2026-10-15T08:00:01.123456789Z connection refused\n".into(),
+        });
+        assert_eq!(stderr.stream, LogStream::Stderr);
+        assert_eq!(stderr.ts.unwrap().to_rfc3339(), "2026-10-15T08:00:01.123456789+00:00");
+        assert_eq!(stderr.line, "connection refused");
+
+        let plain = entry(LogOutput::Console {
+            message: "no timestamp here".into(),
+        });
+        assert_eq!((plain.stream, plain.ts, plain.line.as_str()), (LogStream::Stdout, None, "no timestamp here"));
+        assert_eq!(entry(LogOutput::StdOut { message: "2026-10-15T08:00:01Z\n".into() }).line, "");
+    }
+}
@@ -0,0 +1,471 @@
+//! In-memory [`DockerApi`] backed by JSON fixtures
+//!
+//! Fixtures live in `tests/fixtures/<name>.json` and use the Engine API's own
+//! field names, so a fixture can be assembled from `docker ps`/`docker inspect`
+//! output. Per-container maps are keyed by container name.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use bollard::{
+    container::{AttachContainerResults, Config, LogOutput, Stats},
+    errors::Error,
+    models::{
+        ContainerInspectResponse, ContainerSummary, ContainerTopResponse, EventMessage, FilesystemChange, ImageInspect, ImageSummary,
+        Network,
+        Service, SystemInfo, Volume,
+    },
+};
+use futures_util::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use tokio::io::AsyncReadExt;
+use serde::Deserialize;
+
+use super::{log_entry, DockerApi, ExecOutput, ExecSession, LogWindow};
+use crate::models::{ContainerInfo, LogEntry};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Fixture {
+    pub info: SystemInfo,
+    pub containers: Vec<ContainerSummary>,
+    pub inspect: HashMap<String, ContainerInspectResponse>,
+    pub stats: HashMap<String, Stats>,
+    pub top: HashMap<String, ContainerTopResponse>,
+    pub changes: HashMap<String, Vec<FilesystemChange>>,
+    pub logs: HashMap<String, Vec<String>>,
+    pub events: Vec<EventMessage>,
+    /// Output of any command run in a container; a command fails in
+    /// containers missing here
+    pub exec: HashMap<String, String>,
+    pub images: Vec<ImageSummary>,
+    /// `docker image inspect` output by image id; images listed in `images`
+    /// but missing here inspect as empty
+    pub image_inspect: HashMap<String, ImageInspect>,
+    pub networks: Vec<Network>,
+    pub volumes: Vec<Volume>,
+    /// Swarm services; task containers are in `containers`
+    pub services: Vec<Service>,
+    /// Names of containers the daemon fails to start
+    pub unstartable: Vec<String>,
+}
+
+/// A running `shop/api` container whose id and name are `name`, with no
+/// ports, networks or labels; tests set what they need with struct update
+/// syntax
+pub fn container(name: &str) -> ContainerInfo {
+    serde_json::from_value(serde_json::json!({
+        "id": name,
+        "name": name,
+        "image": "shop/api",
+        "status": "running",
+        "category": "application",
+        "ports": [],
+        "networks": [],
+        "created": "2026-10-15T08:00:00Z",
+        "labels": {},
+    }))
+    .unwrap()
+}
+
+/// Container labels from `key`/`value` pairs
+pub fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+impl Fixture {
+    /// Read `tests/fixtures/<name>.json`
+    pub fn load(name: &str) -> Self {
+        let path = format!("{}/tests/fixtures/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        let bytes = std::fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path, e));
+        serde_json::from_slice(&bytes).unwrap_or_else(|e| panic!("parsing {}: {}", path, e))
+    }
+}
+
+/// Fake Docker daemon serving a fixture; lifecycle actions are recorded, not applied
+#[derive(Debug, Default)]
+pub struct FakeDocker {
+    fixture: Fixture,
+    actions: Mutex<Vec<String>>,
+    /// Configuration of each container created, by name
+    created: Mutex<HashMap<String, Config<String>>>,
+    listings: AtomicUsize,
+}
+
+impl FakeDocker {
+    pub fn new(fixture: Fixture) -> Self {
+        Self {
+            fixture,
+            actions: Mutex::default(),
+            created: Mutex::default(),
+            listings: AtomicUsize::default(),
+        }
+    }
+
+    /// Load `tests/fixtures/<name>.json`
+    pub fn load(name: &str) -> Self {
+        Self::new(Fixture::load(name))
+    }
+
+    /// Configuration the container named `name` was created with
+    pub fn created(&self, name: &str) -> Option<Config<String>> {
+        self.created.lock().unwrap().get(name).cloned()
+    }
+
+    /// Lifecycle actions performed so far, as `"<action> <name>"`
+    pub fn actions(&self) -> Vec<String> {
+        self.actions.lock().unwrap().clone()
+    }
+
+    /// Times containers were listed
+    pub fn listings(&self) -> usize {
+        self.listings.load(Ordering::Relaxed)
+    }
+
+    /// Resolve an id prefix or name to the container's name, like the daemon does
+    fn resolve(&self, id: &str) -> Result<String, Error> {
+        self.fixture
+            .containers
+            .iter()
+            .find_map(|c| {
+                let name = c.names.as_ref()?.first()?.trim_start_matches('/');
+                let matches = name == id || c.id.as_deref().is_some_and(|full| full.starts_with(id));
+                matches.then(|| name.to_string())
+            })
+            .or_else(|| {
+                let created = format!("create {}", id);
+                self.actions.lock().unwrap().contains(&created).then(|| id.to_string())
+            })
+            .ok_or_else(|| Error::DockerResponseServerError {
+                status_code: 404,
+                message: format!("No such container: {}", id),
+            })
+    }
+
+    fn record(&self, action: &str, id: &str) -> Result<(), Error> {
+        let name = self.resolve(id)?;
+        self.actions.lock().unwrap().push(format!("{} {}", action, name));
+        Ok(())
+    }
+}
+
+/// An output stream repeating whatever is written to the input, ending when
+/// the input is shut down
+fn echo() -> AttachContainerResults {
+    let (input, echo) = tokio::io::duplex(1024);
+    let output = stream::unfold(echo, |mut echo| async move {
+        let mut buf = vec![0; 1024];
+        match echo.read(&mut buf).await {
+            Ok(0) | Err(_) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(LogOutput::StdOut { message: buf.into() }), echo))
+            }
+        }
+    });
+    AttachContainerResults {
+        output: output.boxed(),
+        input: Box::pin(input),
+    }
+}
+
+impl DockerApi for FakeDocker {
+    async fn list_containers(&self) -> Result<Vec<ContainerSummary>, Error> {
+        self.listings.fetch_add(1, Ordering::Relaxed);
+        Ok(self.fixture.containers.clone())
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse, Error> {
+        let name = self.resolve(id)?;
+        Ok(self.fixture.inspect.get(&name).cloned().unwrap_or_default())
+    }
+
+    async fn stats(&self, id: &str) -> Result<Option<Stats>, Error> {
+        let name = self.resolve(id)?;
+        Ok(self.fixture.stats.get(&name).cloned())
+    }
+
+    async fn top_processes(&self, id: &str, _ps_args: &str) -> Result<ContainerTopResponse, Error> {
+        let name = self.resolve(id)?;
+        Ok(self.fixture.top.get(&name).cloned().unwrap_or_default())
+    }
+
+    async fn container_changes(&self, id: &str) -> Result<Vec<FilesystemChange>, Error> {
+        let name = self.resolve(id)?;
+        Ok(self.fixture.changes.get(&name).cloned().unwrap_or_default())
+    }
+
+    /// Fixture lines are stdout, and may start with a timestamp as the daemon sends them
+    async fn logs(&self, id: &str, window: LogWindow) -> Result<Vec<LogEntry>, Error> {
+        let name = self.resolve(id)?;
+        if !window.streams.stdout() {
+            return Ok(Vec::new());
+        }
+        let lines: Vec<LogEntry> = self
+            .fixture
+            .logs
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .filter_map(|line| log_entry(LogOutput::StdOut { message: line.clone().into() }))
+            .filter(|entry| match entry.ts {
+                Some(ts) => window.since.is_none_or(|since| ts >= since) && window.until.is_none_or(|until| ts < until),
+                None => true,
+            })
+            .collect();
+        Ok(lines[lines.len().saturating_sub(window.tail)..].to_vec())
+    }
+
+    /// Replays the fixture's log lines as if they were being written now
+    fn follow_logs(&self, id: &str) -> BoxStream<'static, Result<String, Error>> {
+        let lines = self
+            .resolve(id)
+            .map(|name| self.fixture.logs.get(&name).cloned().unwrap_or_default());
+        match lines {
+            Ok(lines) => stream::iter(lines.into_iter().map(Ok)).boxed(),
+            Err(e) => stream::once(async { Err(e) }).boxed(),
+        }
+    }
+
+    /// The tail of the fixture's log lines, which all predate any `since`
+    fn stream_logs(&self, id: &str, tail: Option<usize>, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        let lines = self
+            .resolve(id)
+            .map(|name| self.fixture.logs.get(&name).cloned().unwrap_or_default());
+        match lines {
+            Ok(_) if since > 0 => stream::empty().boxed(),
+            Ok(lines) => {
+                let skip = tail.map_or(0, |tail| lines.len().saturating_sub(tail));
+                stream::iter(lines.into_iter().skip(skip).map(Ok)).boxed()
+            }
+            Err(e) => stream::once(async { Err(e) }).boxed(),
+        }
+    }
+
+    /// The fixture's log lines, newline-terminated as Docker sends them, when
+    /// `since` is 0
+    fn stream_timestamped_logs(&self, id: &str, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        self.stream_logs(id, None, since)
+            .map(|line| line.map(|line| line + "\n"))
+            .boxed()
+    }
+
+    async fn exec(&self, id: &str, _cmd: &[&str]) -> Result<Option<String>, Error> {
+        let name = self.resolve(id)?;
+        Ok(self.fixture.exec.get(&name).cloned())
+    }
+
+    /// The fixture's exec output on stdout with exit code 0, or exit code 1
+    /// when the fixture has none
+    async fn exec_output(
+        &self,
+        id: &str,
+        _cmd: &[String],
+        _working_dir: Option<&str>,
+        limit: usize,
+    ) -> Result<ExecOutput, Error> {
+        let name = self.resolve(id)?;
+        let mut output = ExecOutput::default();
+        match self.fixture.exec.get(&name) {
+            Some(stdout) => {
+                ExecOutput::push(&mut output.stdout, &mut output.truncated, stdout.as_bytes(), limit);
+                output.exit_code = Some(0);
+            }
+            None => output.exit_code = Some(1),
+        }
+        Ok(output)
+    }
+
+    /// Echoes input like [`FakeDocker::attach_container`]; the exec id is
+    /// `exec-<container name>`
+    async fn exec_terminal(&self, id: &str, _cmd: &[String], rows: u16, cols: u16) -> Result<ExecSession, Error> {
+        self.record("exec-terminal", id)?;
+        let exec_id = format!("exec-{}", self.resolve(id)?);
+        self.resize_exec(&exec_id, rows, cols).await?;
+        let AttachContainerResults { output, input } = echo();
+        Ok(ExecSession {
+            id: exec_id,
+            output,
+            input,
+        })
+    }
+
+    async fn resize_exec(&self, exec_id: &str, rows: u16, cols: u16) -> Result<(), Error> {
+        self.actions
+            .lock()
+            .unwrap()
+            .push(format!("resize {} {}x{}", exec_id, cols, rows));
+        Ok(())
+    }
+
+    async fn exec_exit_code(&self, _exec_id: &str) -> Result<Option<i64>, Error> {
+        Ok(Some(0))
+    }
+
+    fn events(&self, since: i64) -> BoxStream<'static, Result<EventMessage, Error>> {
+        let events: Vec<EventMessage> = self
+            .fixture
+            .events
+            .iter()
+            .filter(|e| e.time.unwrap_or_default() >= since)
+            .cloned()
+            .collect();
+        stream::iter(events.into_iter().map(Ok)).boxed()
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>, Error> {
+        Ok(self.fixture.images.clone())
+    }
+
+    async fn inspect_image(&self, image: &str) -> Result<ImageInspect, Error> {
+        if let Some(inspect) = self.fixture.image_inspect.get(image) {
+            return Ok(inspect.clone());
+        }
+        self.fixture
+            .images
+            .iter()
+            .find(|i| i.id == image || i.repo_tags.iter().any(|t| t == image))
+            .map(|i| ImageInspect {
+                id: Some(i.id.clone()),
+                repo_tags: Some(i.repo_tags.clone()),
+                ..Default::default()
+            })
+            .ok_or_else(|| Error::DockerResponseServerError {
+                status_code: 404,
+                message: format!("No such image: {}", image),
+            })
+    }
+
+    async fn list_networks(&self) -> Result<Vec<Network>, Error> {
+        Ok(self.fixture.networks.clone())
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<Volume>, Error> {
+        Ok(self.fixture.volumes.clone())
+    }
+
+    async fn list_services(&self) -> Result<Vec<Service>, Error> {
+        Ok(self.fixture.services.clone())
+    }
+
+    async fn info(&self) -> Result<SystemInfo, Error> {
+        Ok(self.fixture.info.clone())
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<(), Error> {
+        self.record("restart", id)
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<(), Error> {
+        self.record("stop", id)
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<(), Error> {
+        self.record("pause", id)
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<(), Error> {
+        self.record("unpause", id)
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<(), Error> {
+        self.record(&format!("kill {}", signal), id)
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), Error> {
+        let name = self.resolve(id)?;
+        if self.fixture.unstartable.contains(&name) {
+            return Err(Error::DockerResponseServerError {
+                status_code: 500,
+                message: format!("cannot start container {}", name),
+            });
+        }
+        self.record("start", id)
+    }
+
+    /// Created containers can be started and removed but are not listed.
+    /// Like the daemon, refuses images that are neither in the fixture nor pulled
+    async fn create_container(&self, name: &str, config: Config<String>) -> Result<String, Error> {
+        let mut actions = self.actions.lock().unwrap();
+        if let Some(image) = &config.image {
+            let local = self
+                .fixture
+                .images
+                .iter()
+                .any(|i| i.id == *image || i.repo_tags.contains(image))
+                || actions.contains(&format!("pull {}", image));
+            if !local {
+                return Err(Error::DockerResponseServerError {
+                    status_code: 404,
+                    message: format!("No such image: {}", image),
+                });
+            }
+        }
+        actions.push(format!("create {}", name));
+        self.created.lock().unwrap().insert(name.to_string(), config);
+        Ok(name.to_string())
+    }
+
+    async fn remove_container(&self, id: &str, volumes: bool) -> Result<(), Error> {
+        self.record(if volumes { "remove --volumes" } else { "remove" }, id)
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<(), Error> {
+        let current = self.resolve(id)?;
+        self.actions.lock().unwrap().push(format!("rename {} {}", current, name));
+        Ok(())
+    }
+
+    async fn scale_service(&self, service: &str, replicas: u64) -> Result<(), Error> {
+        self.actions.lock().unwrap().push(format!("scale {} {}", service, replicas));
+        Ok(())
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<(), Error> {
+        self.actions.lock().unwrap().push(format!("pull {}", image));
+        Ok(())
+    }
+
+    /// Like the daemon, refuses images a listed container was created from
+    /// unless forced
+    async fn remove_image(&self, image: &str, force: bool) -> Result<(), Error> {
+        let found = self
+            .fixture
+            .images
+            .iter()
+            .find(|i| i.id == image || i.id.trim_start_matches("sha256:").starts_with(image) || i.repo_tags.iter().any(|t| t == image))
+            .ok_or_else(|| Error::DockerResponseServerError {
+                status_code: 404,
+                message: format!("No such image: {}", image),
+            })?;
+        let in_use = self.fixture.containers.iter().any(|c| {
+            c.image_id.as_ref() == Some(&found.id) || c.image.as_ref().is_some_and(|i| found.repo_tags.contains(i))
+        });
+        if in_use && !force {
+            return Err(Error::DockerResponseServerError {
+                status_code: 409,
+                message: format!("conflict: unable to remove {}: image is being used by a container", image),
+            });
+        }
+        self.actions.lock().unwrap().push(format!("remove-image {}", found.id));
+        Ok(())
+    }
+
+    /// Echoes whatever is written to stdin back on stdout
+    async fn attach_container(&self, id: &str) -> Result<AttachContainerResults, Error> {
+        self.record("attach", id)?;
+        Ok(echo())
+    }
+
+    async fn wait_container(&self, id: &str) -> Result<i64, Error> {
+        self.resolve(id)?;
+        Ok(0)
+    }
+}
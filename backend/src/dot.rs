@@ -0,0 +1,108 @@
+//! Graphviz DOT rendering of flowcharts
+//!
+//! Produces a `digraph` for `dot -Tsvg` and the rest of the Graphviz
+//! toolchain: node shapes follow the node type, fills follow the status with
+//! the same palette as the Mermaid export, and edge styles follow the
+//! connection type.
+
+use std::fmt::Write;
+
+use crate::models::{ConnectionType, ContainerStatus, FlowDirection, Flowchart, NodeType};
+
+/// Fill and border per status; stopped containers get a dashed border
+pub fn status_style(status: &ContainerStatus) -> (&'static str, &'static str, &'static str) {
+    match status {
+        ContainerStatus::Healthy => ("#dcfce7", "#16a34a", "filled,rounded"),
+        ContainerStatus::Running => ("#dbeafe", "#2563eb", "filled,rounded"),
+        ContainerStatus::Unhealthy => ("#fee2e2", "#dc2626", "filled,rounded"),
+        ContainerStatus::Restarting => ("#fef3c7", "#d97706", "filled,rounded"),
+        ContainerStatus::Paused | ContainerStatus::Created => ("#f3f4f6", "#6b7280", "filled,rounded"),
+        ContainerStatus::Exited => ("#f3f4f6", "#374151", "filled,rounded,dashed"),
+        ContainerStatus::Dead => ("#fee2e2", "#7f1d1d", "filled,rounded,dashed"),
+    }
+}
+
+fn shape(node_type: &NodeType) -> &'static str {
+    match node_type {
+        NodeType::Service => "box",
+        NodeType::Process => "ellipse",
+        NodeType::Decision => "diamond",
+        NodeType::Group => "folder",
+    }
+}
+
+/// Line style and arrowhead per connection type
+fn edge_style(connection_type: &ConnectionType) -> (&'static str, &'static str) {
+    match connection_type {
+        ConnectionType::Primary => ("solid", "normal"),
+        ConnectionType::Secondary => ("dashed", "normal"),
+        ConnectionType::Data => ("bold", "normal"),
+        ConnectionType::Control => ("solid", "diamond"),
+        ConnectionType::Network => ("dotted", "none"),
+        ConnectionType::Volume => ("dashed", "odot"),
+        ConnectionType::Depends => ("dashed", "empty"),
+    }
+}
+
+/// A double-quoted DOT string
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Render a flowchart as DOT source flowing in `direction`
+pub fn render(flowchart: &Flowchart, direction: FlowDirection) -> String {
+    let rankdir = match direction {
+        FlowDirection::Lr => "LR",
+        FlowDirection::Td => "TB",
+    };
+    let mut out = String::new();
+    writeln!(out, "digraph {} {{", quote(&flowchart.id)).unwrap();
+    writeln!(out, "    label={};", quote(&flowchart.name)).unwrap();
+    writeln!(out, "    labelloc=t;").unwrap();
+    writeln!(out, "    rankdir={};", rankdir).unwrap();
+    writeln!(out, "    node [fontname=\"Helvetica\"];").unwrap();
+    writeln!(out, "    edge [fontname=\"Helvetica\", fontsize=10];").unwrap();
+
+    for node in &flowchart.nodes {
+        let mut label = node.name.clone();
+        if let Some(port) = node.port {
+            write!(label, "\n:{}", port).unwrap();
+        }
+        let (fill, border, style) = status_style(&node.status);
+        writeln!(
+            out,
+            "    {} [label={}, shape={}, style=\"{}\", fillcolor=\"{}\", color=\"{}\"];",
+            quote(&node.id),
+            quote(&label),
+            shape(&node.node_type),
+            style,
+            fill,
+            border
+        )
+        .unwrap();
+    }
+
+    for connection in &flowchart.connections {
+        // Connections to nodes outside this chart would add stray nodes
+        let known = |id: &str| flowchart.nodes.iter().any(|n| n.id == id);
+        if !known(&connection.source) || !known(&connection.target) {
+            continue;
+        }
+        let (style, arrowhead) = edge_style(&connection.connection_type);
+        write!(
+            out,
+            "    {} -> {} [style={}, arrowhead={}",
+            quote(&connection.source),
+            quote(&connection.target),
+            style,
+            arrowhead
+        )
+        .unwrap();
+        if let Some(label) = &connection.label {
+            write!(out, ", label={}", quote(label)).unwrap();
+        }
+        writeln!(out, "];").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
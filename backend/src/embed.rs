@@ -0,0 +1,424 @@
+//! Embeddable read-only flowchart widget
+//!
+//! Serves a self-contained HTML page (or the raw JSON) for a single flowchart so
+//! other internal dashboards can iframe live topology. Access is granted by a
+//! signed, expiring token whose scope lists the flowchart ids it may render.
+//! The page may only be framed by the origins in
+//! `FLOWSCOPE_EMBED_ALLOWED_ORIGINS`; without it no site may frame it.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{ContainerStatus, EmbedToken, EmbedTokenRequest, Flowchart},
+    provider::DiscoveryProvider,
+    AppState,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Default embed token lifetime
+const DEFAULT_TTL_SECONDS: i64 = 24 * 60 * 60;
+/// Longest lifetime a token may be issued with
+const MAX_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+/// How often the embedded page reloads itself
+const REFRESH_SECONDS: u32 = 30;
+
+/// Signs and verifies embed tokens
+pub struct EmbedSigner {
+    secret: Vec<u8>,
+    /// Value for the CSP `frame-ancestors` directive
+    frame_ancestors: String,
+}
+
+/// `frame-ancestors` sources from a comma-separated origin list; `'none'`
+/// when no origin is given, so the page is not framed by arbitrary sites
+fn frame_ancestors(origins: Option<&str>) -> String {
+    let sources: Vec<&str> = origins
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .collect();
+    if sources.is_empty() {
+        "'none'".to_string()
+    } else {
+        sources.join(" ")
+    }
+}
+
+impl EmbedSigner {
+    /// Build from `FLOWSCOPE_EMBED_SECRET` and `FLOWSCOPE_EMBED_ALLOWED_ORIGINS`.
+    /// Without a configured secret a random one is generated, so tokens do not
+    /// survive a restart.
+    pub fn from_env() -> Self {
+        let secret = match std::env::var("FLOWSCOPE_EMBED_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+            _ => {
+                warn!("FLOWSCOPE_EMBED_SECRET not set, embed tokens will not survive a restart");
+                uuid::Uuid::new_v4().as_bytes().to_vec()
+            }
+        };
+
+        let origins = std::env::var("FLOWSCOPE_EMBED_ALLOWED_ORIGINS").ok();
+        let frame_ancestors = frame_ancestors(origins.as_deref());
+        if frame_ancestors == "'none'" {
+            warn!("FLOWSCOPE_EMBED_ALLOWED_ORIGINS not set, embedded flowcharts cannot be framed by other sites");
+        }
+
+        Self {
+            secret,
+            frame_ancestors,
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length")
+    }
+
+    pub fn sign(&self, claims: &EmbedClaims) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap());
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<EmbedClaims, EmbedError> {
+        let (payload, signature) = token.split_once('.').ok_or(EmbedError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| EmbedError::Malformed)?;
+
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| EmbedError::BadSignature)?;
+
+        let claims: EmbedClaims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(EmbedError::Malformed)?;
+
+        if claims.exp < Utc::now().timestamp() {
+            return Err(EmbedError::Expired);
+        }
+        Ok(claims)
+    }
+}
+
+/// Contents of an embed token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedClaims {
+    /// Flowchart ids this token may render; `*` allows any
+    pub scope: Vec<String>,
+    /// Expiry as a unix timestamp
+    pub exp: i64,
+}
+
+impl EmbedClaims {
+    pub fn allows(&self, flowchart_id: &str) -> bool {
+        self.scope.iter().any(|s| s == "*" || s == flowchart_id)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbedError {
+    #[error("malformed embed token")]
+    Malformed,
+    #[error("invalid embed token signature")]
+    BadSignature,
+    #[error("embed token expired")]
+    Expired,
+}
+
+/// POST /api/embed/tokens - Issue a signed embed token
+pub async fn create_embed_token(
+    State(state): State<AppState>,
+    Json(req): Json<EmbedTokenRequest>,
+) -> impl IntoResponse {
+    if req.scope.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "Embed token scope must list at least one flowchart id"
+            })),
+        )
+            .into_response();
+    }
+
+    let ttl = req
+        .ttl_seconds
+        .unwrap_or(DEFAULT_TTL_SECONDS)
+        .clamp(1, MAX_TTL_SECONDS);
+    let claims = EmbedClaims {
+        scope: req.scope,
+        exp: Utc::now().timestamp() + ttl,
+    };
+    let token = state.embed.sign(&claims);
+
+    info!("Issued embed token for {:?} (ttl {}s)", claims.scope, ttl);
+    (
+        StatusCode::CREATED,
+        Json(EmbedToken {
+            token,
+            expires_at: chrono::DateTime::from_timestamp(claims.exp, 0).unwrap_or_default(),
+            scope: claims.scope,
+        }),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct EmbedQuery {
+    pub token: String,
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// GET /embed/flowchart/:id - Render a read-only flowchart widget
+pub async fn get_embed_flowchart(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<EmbedQuery>,
+) -> Response {
+    let claims = match state.embed.verify(&query.token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            warn!("Rejected embed request for '{}': {}", id, e);
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+    };
+
+    if !claims.allows(&id) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "Embed token does not grant access to this flowchart",
+                "id": id
+            })),
+        )
+            .into_response();
+    }
+
+    let flowchart = match state.provider.generate_flowchart(&id).await {
+        Ok(Some(mut flowchart)) => {
+            state.layouts.apply(&mut flowchart);
+            flowchart
+        }
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "Flowchart not found",
+                    "id": id
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to generate embed flowchart '{}': {}", id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to generate flowchart",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let mut response = if query.format.as_deref() == Some("json") {
+        Json(flowchart).into_response()
+    } else {
+        Html(render_html(&flowchart)).into_response()
+    };
+
+    let headers = response.headers_mut();
+    if let Ok(csp) = HeaderValue::from_str(&format!(
+        "default-src 'none'; style-src 'unsafe-inline'; frame-ancestors {}",
+        state.embed.frame_ancestors
+    )) {
+        headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("no-referrer"),
+    );
+    response
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn status_color(status: &ContainerStatus) -> &'static str {
+    match status {
+        ContainerStatus::Healthy | ContainerStatus::Running => "#22c55e",
+        ContainerStatus::Unhealthy | ContainerStatus::Dead => "#ef4444",
+        ContainerStatus::Restarting | ContainerStatus::Paused => "#f59e0b",
+        ContainerStatus::Exited | ContainerStatus::Created => "#6b7280",
+    }
+}
+
+/// Render a flowchart as a static page with inline styles and no scripts
+fn render_html(flowchart: &Flowchart) -> String {
+    let names: std::collections::HashMap<&str, &str> = flowchart
+        .nodes
+        .iter()
+        .map(|n| (n.id.as_str(), n.name.as_str()))
+        .collect();
+
+    let nodes: String = flowchart
+        .nodes
+        .iter()
+        .map(|node| {
+            format!(
+                r#"<div class="node" style="border-color:{color}"><span class="dot" style="background:{color}"></span><strong>{name}</strong><small>{status}</small><p>{description}</p></div>"#,
+                color = status_color(&node.status),
+                name = escape_html(&node.name),
+                status = escape_html(&format!("{:?}", node.status).to_lowercase()),
+                description = escape_html(&node.description),
+            )
+        })
+        .collect();
+
+    let connections: String = flowchart
+        .connections
+        .iter()
+        .map(|c| {
+            format!(
+                "<li>{} &rarr; {}{}</li>",
+                escape_html(names.get(c.source.as_str()).unwrap_or(&c.source.as_str())),
+                escape_html(names.get(c.target.as_str()).unwrap_or(&c.target.as_str())),
+                c.label
+                    .as_deref()
+                    .map(|l| format!(" <em>({})</em>", escape_html(l)))
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="{refresh}">
+<title>{title}</title>
+<style>
+body {{ margin: 0; padding: 12px; font-family: system-ui, sans-serif; background: #0f172a; color: #e2e8f0; }}
+h1 {{ font-size: 16px; margin: 0 0 4px; }}
+.meta {{ font-size: 12px; color: #94a3b8; margin-bottom: 12px; }}
+.nodes {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(180px, 1fr)); gap: 8px; }}
+.node {{ border: 1px solid; border-radius: 6px; padding: 8px; background: #1e293b; }}
+.node small {{ display: block; color: #94a3b8; }}
+.node p {{ margin: 4px 0 0; font-size: 11px; color: #cbd5e1; word-break: break-all; }}
+.dot {{ display: inline-block; width: 8px; height: 8px; border-radius: 50%; margin-right: 6px; }}
+ul {{ font-size: 12px; color: #cbd5e1; padding-left: 18px; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class="meta">{description} &middot; updated {updated}</div>
+<div class="nodes">{nodes}</div>
+<ul>{connections}</ul>
+</body>
+</html>
+"#,
+        refresh = REFRESH_SECONDS,
+        title = escape_html(&flowchart.name),
+        description = escape_html(&flowchart.description),
+        updated = Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        nodes = nodes,
+        connections = connections,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(secret: &str) -> EmbedSigner {
+        EmbedSigner {
+            secret: secret.as_bytes().to_vec(),
+            frame_ancestors: frame_ancestors(None),
+        }
+    }
+
+    fn claims(scope: &[&str], exp: i64) -> EmbedClaims {
+        EmbedClaims {
+            scope: scope.iter().map(|s| s.to_string()).collect(),
+            exp,
+        }
+    }
+
+    #[test]
+    fn signed_tokens_verify() {
+        let exp = Utc::now().timestamp() + 60;
+        let token = signer("secret").sign(&claims(&["stack"], exp));
+        let verified = signer("secret").verify(&token).unwrap();
+        assert_eq!((verified.scope, verified.exp), (vec!["stack".to_string()], exp));
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let token = signer("secret").sign(&claims(&["stack"], Utc::now().timestamp() - 1));
+        assert!(matches!(signer("secret").verify(&token), Err(EmbedError::Expired)));
+    }
+
+    #[test]
+    fn tampered_or_foreign_tokens_are_rejected() {
+        let token = signer("secret").sign(&claims(&["stack"], Utc::now().timestamp() + 60));
+        assert!(matches!(signer("other").verify(&token), Err(EmbedError::BadSignature)));
+
+        // Widening the scope without re-signing
+        let (_, signature) = token.split_once('.').unwrap();
+        let widened = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims(&["*"], Utc::now().timestamp() + 60)).unwrap());
+        let forged = format!("{}.{}", widened, signature);
+        assert!(matches!(signer("secret").verify(&forged), Err(EmbedError::BadSignature)));
+
+        assert!(matches!(signer("secret").verify("no-dot"), Err(EmbedError::Malformed)));
+        assert!(matches!(signer("secret").verify("a.!!"), Err(EmbedError::Malformed)));
+    }
+
+    #[test]
+    fn scope_restricts_flowcharts() {
+        let scoped = claims(&["stack", "infra"], 0);
+        assert!(scoped.allows("stack") && scoped.allows("infra"));
+        assert!(!scoped.allows("application"));
+        assert!(claims(&["*"], 0).allows("application"));
+        assert!(!claims(&[], 0).allows("stack"));
+    }
+
+    #[test]
+    fn framing_is_denied_unless_origins_are_configured() {
+        assert_eq!(frame_ancestors(None), "'none'");
+        assert_eq!(frame_ancestors(Some(" , ")), "'none'");
+        assert_eq!(
+            frame_ancestors(Some("https://grafana.internal, https://ops.internal")),
+            "https://grafana.internal https://ops.internal"
+        );
+    }
+}
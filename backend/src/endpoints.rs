@@ -0,0 +1,565 @@
+//! Published port self-tests
+//!
+//! `POST /api/endpoints/:id/test` has FlowScope connect to a port a container
+//! publishes on the host, to check that "published" also means "reachable": a
+//! port can be bound to an address nobody routes to, blocked by a firewall, or
+//! forwarded to a process that is not listening. An endpoint id is
+//! `<container>:<hostPort>`, such as `frontend-web:80`.
+//!
+//! Tests connect to `FLOWSCOPE_ENDPOINT_HOST`, `127.0.0.1` when unset, which
+//! suits FlowScope running on the Docker host or with host networking, and
+//! time the TCP handshake. A TLS handshake is attempted on the connection; when
+//! the port speaks TLS, the negotiated session and whether its certificate is
+//! publicly trusted are reported too.
+//!
+//! With `"vantage": "relay"` the test runs on the relay at
+//! `FLOWSCOPE_ENDPOINT_RELAY` instead, so reachability is checked from outside
+//! the host. The relay receives an [`EndpointProbe`] as JSON, with
+//! `FLOWSCOPE_ENDPOINT_RELAY_TOKEN` as a bearer token when set, and answers
+//! with a [`ProbeOutcome`]. Relay tests connect to
+//! `FLOWSCOPE_ENDPOINT_PUBLIC_HOST` unless the request names a host.
+//!
+//! Only the container's published port is ever tested, so the route cannot be
+//! pointed at arbitrary addresses: local tests always connect to the local test
+//! host, and a relay test may name the public host or one listed in
+//! `FLOWSCOPE_ENDPOINT_RELAY_HOSTS` (comma-separated). Other hosts need the
+//! `admin` scope.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    rustls::{
+        client::{
+            danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+            WebPkiServerVerifier,
+        },
+        crypto::ring,
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, ProtocolVersion, RootCertStore, SignatureScheme,
+    },
+    TlsConnector,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    auth::Caller,
+    models::{ApiScope, ContainerInfo, EndpointProbe, EndpointTest, EndpointTestRequest, PortMapping, ProbeOutcome, ProbeVantage, TlsInfo},
+    AppState,
+};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest wait for a TLS handshake nobody asked for; ports that wait for the
+/// client to speak first would otherwise hold the test for the full timeout
+pub const SNIFF_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time a relay gets on top of the test's own timeout
+const RELAY_OVERHEAD: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum EndpointError {
+    #[error("invalid endpoint '{0}', expected <container>:<hostPort>")]
+    InvalidId(String),
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("{container} does not publish host port {port}")]
+    NotPublished { container: String, port: u16 },
+    #[error("{container} publishes host port {port} over {protocol}; only TCP ports can be tested")]
+    NotTcp { container: String, port: u16, protocol: String },
+    #[error("invalid endpoint test: {0}")]
+    Invalid(String),
+    #[error("no endpoint relay is configured")]
+    NoRelay,
+    #[error("relay tests may not connect to '{0}'; it must be the public host or listed in FLOWSCOPE_ENDPOINT_RELAY_HOSTS")]
+    HostNotAllowed(String),
+    #[error("relay error: {0}")]
+    Relay(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl EndpointError {
+    fn status(&self) -> StatusCode {
+        match self {
+            EndpointError::ContainerNotFound(_) | EndpointError::NotPublished { .. } => StatusCode::NOT_FOUND,
+            EndpointError::InvalidId(_) | EndpointError::Invalid(_) | EndpointError::NoRelay => {
+                StatusCode::BAD_REQUEST
+            }
+            EndpointError::HostNotAllowed(_) => StatusCode::FORBIDDEN,
+            EndpointError::NotTcp { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            EndpointError::Relay(_) => StatusCode::BAD_GATEWAY,
+            EndpointError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for EndpointError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Endpoint test error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Container name and host port of an endpoint id
+fn parse_id(id: &str) -> Result<(&str, u16), EndpointError> {
+    let invalid = || EndpointError::InvalidId(id.to_string());
+    let (container, port) = id.rsplit_once(':').ok_or_else(invalid)?;
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 && !container.is_empty() => Ok((container, port)),
+        _ => Err(invalid()),
+    }
+}
+
+/// The TCP mapping publishing `port` on the host
+fn published(container: &ContainerInfo, port: u16) -> Result<&PortMapping, EndpointError> {
+    let mut mappings = container.ports.iter().filter(|p| p.host_port == Some(port)).peekable();
+    let first = mappings.peek().copied().ok_or_else(|| EndpointError::NotPublished {
+        container: container.name.clone(),
+        port,
+    })?;
+    mappings
+        .find(|p| p.protocol.eq_ignore_ascii_case("tcp"))
+        .ok_or_else(|| EndpointError::NotTcp {
+            container: container.name.clone(),
+            port,
+            protocol: first.protocol.clone(),
+        })
+}
+
+/// Requested timeout, defaulting to [`DEFAULT_TIMEOUT`] and capped at [`MAX_TIMEOUT`]
+pub fn timeout(request: &EndpointTestRequest) -> Result<Duration, EndpointError> {
+    match request.timeout_ms.map(Duration::from_millis) {
+        None => Ok(DEFAULT_TIMEOUT),
+        Some(timeout) if timeout.is_zero() => Err(EndpointError::Invalid("timeoutMs must be positive".to_string())),
+        Some(timeout) if timeout > MAX_TIMEOUT => Err(EndpointError::Invalid(format!(
+            "timeoutMs must be at most {}",
+            MAX_TIMEOUT.as_millis()
+        ))),
+        Some(timeout) => Ok(timeout),
+    }
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    (elapsed.as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+}
+
+/// Verifies certificates against the public web roots but only records the
+/// verdict, so the session of a port with a private or expired certificate
+/// can still be reported
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    verdict: Mutex<Option<Result<(), String>>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        let verdict = self
+            .inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        *self.verdict.lock().unwrap() = Some(verdict);
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Run a TLS handshake over `stream` and describe the session
+async fn handshake(stream: TcpStream, host: &str) -> Result<TlsInfo, String> {
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+    let provider = Arc::new(ring::default_provider());
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let verifier = Arc::new(RecordingVerifier {
+        inner: WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+            .build()
+            .map_err(|e| e.to_string())?,
+        verdict: Mutex::new(None),
+    });
+    let mut config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    let started = Instant::now();
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| e.to_string())?;
+    let handshake_ms = millis(started.elapsed());
+
+    let (_, session) = stream.get_ref();
+    let verdict = verifier
+        .verdict
+        .lock()
+        .unwrap()
+        .take()
+        .unwrap_or_else(|| Err("no certificate was presented".to_string()));
+    Ok(TlsInfo {
+        version: match session.protocol_version() {
+            Some(ProtocolVersion::TLSv1_3) => "TLS 1.3".to_string(),
+            Some(ProtocolVersion::TLSv1_2) => "TLS 1.2".to_string(),
+            other => format!("{:?}", other),
+        },
+        cipher_suite: session
+            .negotiated_cipher_suite()
+            .map(|s| format!("{:?}", s.suite()))
+            .unwrap_or_default(),
+        alpn: session.alpn_protocol().map(|p| String::from_utf8_lossy(p).into_owned()),
+        handshake_ms,
+        certificates: session.peer_certificates().map_or(0, |c| c.len() as u32),
+        trusted: verdict.is_ok(),
+        trust_error: verdict.err(),
+    })
+}
+
+/// Connect to the probed port from here
+pub async fn probe(probe: &EndpointProbe) -> ProbeOutcome {
+    let timeout = Duration::from_millis(probe.timeout_ms);
+    let unreachable = |error: String| ProbeOutcome {
+        reachable: false,
+        connect_ms: None,
+        error: Some(error),
+        tls: None,
+        tls_error: None,
+    };
+
+    // Resolve first so the connect time is the TCP handshake alone
+    let address = match tokio::net::lookup_host((probe.host.as_str(), probe.port)).await {
+        Ok(mut addresses) => match addresses.next() {
+            Some(address) => address,
+            None => return unreachable(format!("{} has no address", probe.host)),
+        },
+        Err(e) => return unreachable(format!("cannot resolve {}: {}", probe.host, e)),
+    };
+    let started = Instant::now();
+    let stream = match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return unreachable(e.to_string()),
+        Err(_) => return unreachable(format!("no answer within {}ms", probe.timeout_ms)),
+    };
+    let mut outcome = ProbeOutcome {
+        reachable: true,
+        connect_ms: Some(millis(started.elapsed())),
+        error: None,
+        tls: None,
+        tls_error: None,
+    };
+
+    let requested = probe.tls == Some(true);
+    if probe.tls != Some(false) {
+        let limit = if requested { timeout } else { timeout.min(SNIFF_TIMEOUT) };
+        match tokio::time::timeout(limit, handshake(stream, &probe.host)).await {
+            Ok(Ok(tls)) => outcome.tls = Some(tls),
+            Ok(Err(e)) if requested => outcome.tls_error = Some(e),
+            Err(_) if requested => outcome.tls_error = Some(format!("no TLS handshake within {}ms", probe.timeout_ms)),
+            _ => {}
+        }
+    }
+    outcome
+}
+
+struct Relay {
+    url: String,
+    token: Option<String>,
+}
+
+/// Where endpoint tests connect to and from
+pub struct EndpointTester {
+    host: String,
+    public_host: Option<String>,
+    /// Hosts besides the public host that relay tests may name
+    relay_hosts: Vec<String>,
+    relay: Option<Relay>,
+    http: reqwest::Client,
+}
+
+impl EndpointTester {
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            public_host: None,
+            relay_hosts: Vec::new(),
+            relay: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Test `FLOWSCOPE_ENDPOINT_HOST` locally, and `FLOWSCOPE_ENDPOINT_PUBLIC_HOST`
+    /// or a host from `FLOWSCOPE_ENDPOINT_RELAY_HOSTS` through
+    /// `FLOWSCOPE_ENDPOINT_RELAY` when one is set
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let relay = var("FLOWSCOPE_ENDPOINT_RELAY").map(|url| Relay {
+            url,
+            token: var("FLOWSCOPE_ENDPOINT_RELAY_TOKEN"),
+        });
+        Self {
+            public_host: var("FLOWSCOPE_ENDPOINT_PUBLIC_HOST"),
+            relay_hosts: var("FLOWSCOPE_ENDPOINT_RELAY_HOSTS")
+                .map(|hosts| {
+                    hosts
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|h| !h.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            relay,
+            ..Self::new(&var("FLOWSCOPE_ENDPOINT_HOST").unwrap_or_else(|| "127.0.0.1".to_string()))
+        }
+    }
+
+    /// The host local tests connect to
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The host a test from `vantage` connects to. Local tests ignore the
+    /// requested host; relay tests may only name a configured one unless the
+    /// caller is an admin.
+    fn target_host(&self, vantage: ProbeVantage, requested: Option<&str>, admin: bool) -> Result<String, EndpointError> {
+        match (vantage, requested.map(str::trim)) {
+            (ProbeVantage::Local, _) => Ok(self.host.clone()),
+            (ProbeVantage::Relay, Some("")) => Err(EndpointError::Invalid("host must not be empty".to_string())),
+            (ProbeVantage::Relay, Some(host)) => {
+                let configured = self.public_host.as_deref() == Some(host) || self.relay_hosts.iter().any(|h| h == host);
+                if configured || admin {
+                    Ok(host.to_string())
+                } else {
+                    Err(EndpointError::HostNotAllowed(host.to_string()))
+                }
+            }
+            (ProbeVantage::Relay, None) => self.public_host.clone().ok_or_else(|| {
+                EndpointError::Invalid(
+                    "relay tests need a host; pass one or set FLOWSCOPE_ENDPOINT_PUBLIC_HOST".to_string(),
+                )
+            }),
+        }
+    }
+
+    /// Connect to `port` on the local test host with the default timeout,
+    /// without attempting TLS
+    pub async fn probe_local(&self, port: u16) -> ProbeOutcome {
+        probe(&EndpointProbe {
+            host: self.host.clone(),
+            port,
+            tls: Some(false),
+            timeout_ms: DEFAULT_TIMEOUT.as_millis() as u64,
+        })
+        .await
+    }
+
+    async fn run(&self, vantage: ProbeVantage, endpoint_probe: &EndpointProbe) -> Result<ProbeOutcome, EndpointError> {
+        if vantage == ProbeVantage::Local {
+            return Ok(probe(endpoint_probe).await);
+        }
+        let relay = self.relay.as_ref().ok_or(EndpointError::NoRelay)?;
+        let mut request = self
+            .http
+            .post(&relay.url)
+            .timeout(Duration::from_millis(endpoint_probe.timeout_ms) + RELAY_OVERHEAD)
+            .json(endpoint_probe);
+        if let Some(token) = &relay.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| EndpointError::Relay(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(EndpointError::Relay(format!("relay answered {}", status)));
+        }
+        response.json().await.map_err(|e| EndpointError::Relay(e.to_string()))
+    }
+}
+
+/// POST /api/endpoints/:id/test - Connect to a published port and report
+/// whether it answers
+pub async fn test_endpoint(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    caller: Option<Extension<Caller>>,
+    request: Option<Json<EndpointTestRequest>>,
+) -> Result<Json<EndpointTest>, EndpointError> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    let timeout = timeout(&request)?;
+    let token = caller.as_ref().map(|Extension(Caller(token))| token);
+    let admin = state.auth.grants(token, ApiScope::Admin);
+    let host = state
+        .endpoints
+        .target_host(request.vantage, request.host.as_deref(), admin)?;
+
+    let (name, host_port) = parse_id(&id)?;
+    let container = state
+        .docker
+        .get_container(name)
+        .await?
+        .ok_or_else(|| EndpointError::ContainerNotFound(name.to_string()))?;
+    let container_port = published(&container, host_port)?.container_port;
+
+    let endpoint_probe = EndpointProbe {
+        host,
+        port: host_port,
+        tls: request.tls,
+        timeout_ms: timeout.as_millis() as u64,
+    };
+    let outcome = state.endpoints.run(request.vantage, &endpoint_probe).await?;
+    if outcome.reachable {
+        info!("{}:{} is reachable on {}:{}", container.name, container_port, endpoint_probe.host, host_port);
+    } else {
+        warn!(
+            "{}:{} is not reachable on {}:{}: {}",
+            container.name,
+            container_port,
+            endpoint_probe.host,
+            host_port,
+            outcome.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    Ok(Json(EndpointTest {
+        endpoint: format!("{}:{}", container.name, host_port),
+        container: container.name,
+        container_port,
+        host: endpoint_probe.host,
+        host_port,
+        vantage: request.vantage,
+        outcome,
+        tested_at: Utc::now(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DockerDiscovery;
+    use crate::docker_api::fake::FakeDocker;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    fn local(port: u16, tls: Option<bool>) -> EndpointProbe {
+        EndpointProbe {
+            host: "127.0.0.1".to_string(),
+            port,
+            tls,
+            timeout_ms: 1000,
+        }
+    }
+
+    #[tokio::test]
+    async fn endpoints_resolve_to_published_tcp_ports() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let web = discovery.get_container("frontend-web").await.unwrap().unwrap();
+
+        assert_eq!(parse_id("frontend-web:80").unwrap(), ("frontend-web", 80));
+        assert!(matches!(parse_id("frontend-web"), Err(EndpointError::InvalidId(_))));
+        assert!(matches!(parse_id("frontend-web:0"), Err(EndpointError::InvalidId(_))));
+        assert!(matches!(parse_id(":80"), Err(EndpointError::InvalidId(_))));
+
+        assert_eq!(published(&web, 80).unwrap().container_port, 3000);
+        assert!(matches!(published(&web, 3000), Err(EndpointError::NotPublished { .. })));
+        let mut udp = web.clone();
+        udp.ports[0].protocol = "udp".to_string();
+        assert!(matches!(published(&udp, 80), Err(EndpointError::NotTcp { .. })));
+    }
+
+    #[test]
+    fn tests_only_connect_to_configured_hosts() {
+        let tester = EndpointTester {
+            public_host: Some("shop.example.com".to_string()),
+            relay_hosts: vec!["edge.example.com".to_string()],
+            ..EndpointTester::new("127.0.0.1")
+        };
+
+        // Local tests ignore the requested host
+        let host = tester.target_host(ProbeVantage::Local, Some("10.0.0.5"), false).unwrap();
+        assert_eq!(host, "127.0.0.1");
+
+        let relay = |host, admin| tester.target_host(ProbeVantage::Relay, host, admin);
+        assert_eq!(relay(None, false).unwrap(), "shop.example.com");
+        assert_eq!(relay(Some("shop.example.com"), false).unwrap(), "shop.example.com");
+        assert_eq!(relay(Some(" edge.example.com "), false).unwrap(), "edge.example.com");
+        assert!(matches!(relay(Some("10.0.0.5"), false), Err(EndpointError::HostNotAllowed(_))));
+        assert_eq!(relay(Some("10.0.0.5"), true).unwrap(), "10.0.0.5");
+        assert!(matches!(relay(Some(""), true), Err(EndpointError::Invalid(_))));
+
+        let unconfigured = EndpointTester::new("127.0.0.1");
+        assert!(unconfigured.target_host(ProbeVantage::Relay, None, true).is_err());
+    }
+
+    #[tokio::test]
+    async fn probes_report_reachability_and_tls() {
+        // A plain-text server that answers as soon as a client connects
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n").await;
+            }
+        });
+        let closed = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        let outcome = probe(&local(open, None)).await;
+        assert!(outcome.reachable);
+        assert!(outcome.connect_ms.is_some());
+        assert_eq!((outcome.tls, outcome.tls_error), (None, None));
+
+        let outcome = probe(&local(open, Some(true))).await;
+        assert!(outcome.reachable);
+        assert!(outcome.tls_error.is_some());
+
+        let outcome = probe(&local(closed, None)).await;
+        assert!(!outcome.reachable);
+        assert!(outcome.error.is_some());
+    }
+}
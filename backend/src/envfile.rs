@@ -0,0 +1,303 @@
+//! Project `.env` files and environment drift
+//!
+//! Compose reads a project's `.env` file when it creates containers, so
+//! editing the file changes nothing until the services are recreated. An
+//! uploaded `.env` is kept per project and compared with the environment the
+//! containers actually run with. Values never leave the server: drifted values
+//! are reported as `sha256:` fingerprints.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::{
+    models::{ContainerInfo, EnvDrift, EnvDriftReport, ServiceEnvDrift},
+    projects,
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+/// An uploaded `.env` file, stored as sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvFile {
+    pub content: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+pub type EnvFileStore = JsonStore<EnvFile>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnvFileError {
+    #[error("compose project '{0}' not found")]
+    ProjectNotFound(String),
+    #[error("no .env file uploaded for project '{0}'")]
+    NotUploaded(String),
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl EnvFileError {
+    fn status(&self) -> StatusCode {
+        match self {
+            EnvFileError::ProjectNotFound(_) | EnvFileError::NotUploaded(_) => StatusCode::NOT_FOUND,
+            EnvFileError::Parse { .. } => StatusCode::BAD_REQUEST,
+            EnvFileError::Docker(_) | EnvFileError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for EnvFileError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Env file error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+/// Parse a `.env` file the way Compose does: `#` comments, an optional
+/// `export` prefix, single quotes taken literally, `\n`-style escapes in double
+/// quotes and ` #` comments after unquoted values. Bare `NAME` lines pass a
+/// variable through from the shell and declare no value, so they are skipped.
+/// `${VAR}` interpolation is not expanded.
+pub fn parse(content: &str) -> Result<BTreeMap<String, String>, EnvFileError> {
+    let mut variables = BTreeMap::new();
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+        let err = |message: &str| EnvFileError::Parse {
+            line: index + 1,
+            message: message.to_string(),
+        };
+
+        let Some((name, value)) = line.split_once('=') else {
+            if valid_name(line) {
+                continue;
+            }
+            return Err(err("expected NAME=value"));
+        };
+        let name = name.trim();
+        if !valid_name(name) {
+            return Err(err(&format!("invalid variable name '{}'", name)));
+        }
+
+        let value = value.trim();
+        let value = if let Some(rest) = value.strip_prefix('\'') {
+            rest.split_once('\'').ok_or_else(|| err("unterminated single quote"))?.0.to_string()
+        } else if let Some(rest) = value.strip_prefix('"') {
+            unescape(rest).ok_or_else(|| err("unterminated double quote"))?
+        } else {
+            value.split(" #").next().unwrap_or_default().trim_end().to_string()
+        };
+        variables.insert(name.to_string(), value);
+    }
+    Ok(variables)
+}
+
+/// Contents of a double-quoted value up to the closing quote
+fn unescape(quoted: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// `sha256:` and the first 8 hex digits of the value's digest
+pub fn fingerprint(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let hex: String = digest.iter().take(4).map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// Compare declared variables with each service's running environment
+pub fn report(
+    project: &str,
+    file: &EnvFile,
+    declared: &BTreeMap<String, String>,
+    members: Vec<(ContainerInfo, Vec<String>)>,
+) -> EnvDriftReport {
+    let mut by_service: BTreeMap<String, Vec<(ContainerInfo, Vec<String>)>> = BTreeMap::new();
+    for (container, env) in members {
+        by_service
+            .entry(projects::service(&container).to_string())
+            .or_default()
+            .push((container, env));
+    }
+
+    let services: Vec<ServiceEnvDrift> = by_service
+        .into_iter()
+        .map(|(service, replicas)| {
+            let mut unset = BTreeSet::new();
+            let mut extra = BTreeSet::new();
+            let mut drifted = Vec::new();
+            let mut uses_env_file = false;
+
+            for (container, env) in &replicas {
+                let running: BTreeMap<&str, &str> = env
+                    .iter()
+                    .map(|entry| entry.split_once('=').unwrap_or((entry.as_str(), "")))
+                    .collect();
+                uses_env_file |= running.keys().any(|name| declared.contains_key(*name));
+
+                for (name, expected) in declared {
+                    match running.get(name.as_str()) {
+                        None => {
+                            unset.insert(name.clone());
+                        }
+                        Some(actual) if actual != expected => drifted.push(EnvDrift {
+                            variable: name.clone(),
+                            container: container.name.clone(),
+                            expected: fingerprint(expected),
+                            actual: fingerprint(actual),
+                        }),
+                        Some(_) => {}
+                    }
+                }
+                extra.extend(
+                    running
+                        .keys()
+                        .filter(|name| !declared.contains_key(**name))
+                        .map(|name| name.to_string()),
+                );
+            }
+
+            // Services that take nothing from the file would list every
+            // variable as unset and their whole environment as extra
+            if !uses_env_file {
+                unset.clear();
+                extra.clear();
+            }
+            ServiceEnvDrift {
+                service,
+                containers: replicas.iter().map(|(c, _)| c.name.clone()).collect(),
+                uses_env_file,
+                unset: unset.into_iter().collect(),
+                extra: extra.into_iter().collect(),
+                drifted,
+            }
+        })
+        .collect();
+
+    EnvDriftReport {
+        project: project.to_string(),
+        uploaded_at: file.uploaded_at,
+        declared: declared.keys().cloned().collect(),
+        ok: services.iter().all(|s| s.unset.is_empty() && s.drifted.is_empty()),
+        services,
+    }
+}
+
+async fn drift_report(state: &AppState, project: &str, file: &EnvFile) -> Result<EnvDriftReport, EnvFileError> {
+    let declared = parse(&file.content)?;
+    let members = state
+        .docker
+        .project_environment(project)
+        .await?
+        .ok_or_else(|| EnvFileError::ProjectNotFound(project.to_string()))?;
+    Ok(report(project, file, &declared, members))
+}
+
+/// PUT /api/projects/:name/env - Upload a project's `.env` file and check it
+pub async fn upload_env_file(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    content: String,
+) -> Result<Json<EnvDriftReport>, EnvFileError> {
+    parse(&content)?;
+    let file = EnvFile {
+        content,
+        uploaded_at: Utc::now(),
+    };
+    let report = drift_report(&state, &name, &file).await?;
+
+    state.env_files.put(&name, file)?;
+    info!("Stored .env file for project '{}'", name);
+    Ok(Json(report))
+}
+
+/// GET /api/projects/:name/env/drift - Compare the uploaded `.env` with the running containers
+pub async fn get_env_drift(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<EnvDriftReport>, EnvFileError> {
+    let file = state
+        .env_files
+        .get(&name)
+        .ok_or_else(|| EnvFileError::NotUploaded(name.clone()))?;
+    drift_report(&state, &name, &file).await.map(Json)
+}
+
+/// DELETE /api/projects/:name/env - Forget a project's `.env` file
+pub async fn delete_env_file(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, EnvFileError> {
+    match state.env_files.remove(&name)? {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(EnvFileError::NotUploaded(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compose_dotenv_syntax() {
+        let parsed = parse(
+            "# database\nexport DB_HOST=postgres\nTOKEN='a#b $c'\nGREETING=\"hi\\tthere\" # comment\nPORT=8080 # web\nSHELL_ONLY\nEMPTY=\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            BTreeMap::from([
+                ("DB_HOST".to_string(), "postgres".to_string()),
+                ("EMPTY".to_string(), String::new()),
+                ("GREETING".to_string(), "hi\tthere".to_string()),
+                ("PORT".to_string(), "8080".to_string()),
+                ("TOKEN".to_string(), "a#b $c".to_string()),
+            ])
+        );
+
+        assert!(matches!(parse("OK=1\nBAD NAME=2"), Err(EnvFileError::Parse { line: 2, .. })));
+        assert!(matches!(parse("QUOTE=\"open"), Err(EnvFileError::Parse { line: 1, .. })));
+    }
+}
@@ -0,0 +1,430 @@
+//! Docker event history
+//!
+//! A collector task follows the daemon's event stream and keeps the last
+//! [`RETENTION`] of events (at most [`CAPACITY`]) in memory, so questions like
+//! "what stopped this container at 3am" can be answered from `GET /api/events`
+//! without access to the daemon. When the stream drops, the collector
+//! reconnects asking for everything since the last event it recorded, so a
+//! daemon restart leaves no gap. On startup it backfills whatever the daemon
+//! still holds of the retention window.
+//!
+//! Requests that act on containers are journalled too, with the token that made them.
+//! An event is attributed to a request when it happened while the request
+//! was in flight (give or take [`ORIGIN_SLACK`]) and concerns what the request
+//! targeted: the container of `/api/container/:id/...`, the project of
+//! `/api/projects/:name/...`, or anything for other routes such as group
+//! actions. Everything else is an external change.
+
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use axum::{
+    extract::{Query, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use bollard::models::EventMessage;
+use chrono::{DateTime, TimeZone, Utc};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    auth::{is_destructive, required_scope, Caller},
+    models::{ApiScope, DockerEvent, EventActor, EventOrigin},
+    projects::PROJECT_LABEL,
+    AppState,
+};
+
+/// How far back events are kept
+pub const RETENTION: chrono::Duration = chrono::Duration::hours(24);
+/// Most events kept, however recent
+pub const CAPACITY: usize = 20_000;
+/// Events returned when the request sets no limit
+const DEFAULT_LIMIT: usize = 1000;
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// Leeway around a request's lifetime for the events it caused, covering
+/// clock skew and the stream's delivery delay
+pub const ORIGIN_SLACK: chrono::Duration = chrono::Duration::seconds(3);
+
+/// Normalise a daemon event; `None` when it carries no time
+pub fn normalize(message: EventMessage) -> Option<DockerEvent> {
+    let time = match (message.time_nano, message.time) {
+        (Some(nanos), _) => Utc.timestamp_nanos(nanos),
+        (None, Some(secs)) => Utc.timestamp_opt(secs, 0).single()?,
+        (None, None) => return None,
+    };
+    // `health_status: healthy`, `exec_start: sh -c ...`
+    let raw = message.action.unwrap_or_default();
+    let (action, detail) = match raw.split_once(':') {
+        Some((action, detail)) => (action.trim().to_string(), Some(detail.trim().to_string())),
+        None => (raw, None),
+    };
+    let actor = message.actor.unwrap_or_default();
+    let attributes: std::collections::BTreeMap<String, String> =
+        actor.attributes.unwrap_or_default().into_iter().collect();
+
+    Some(DockerEvent {
+        time,
+        event_type: message.typ.map(|t| t.to_string()).unwrap_or_default(),
+        action,
+        detail,
+        actor: EventActor {
+            id: actor.id.unwrap_or_default(),
+            name: attributes.get("name").cloned(),
+        },
+        attributes,
+        origin: None,
+    })
+}
+
+/// Whether an event is about a container, given by name or id prefix;
+/// network events name the container they connect
+fn concerns(event: &DockerEvent, container: &str) -> bool {
+    event.actor.name.as_deref() == Some(container)
+        || event.actor.id.starts_with(container)
+        || event.attributes.get("container").is_some_and(|id| id.starts_with(container))
+}
+
+/// What a request acts on, as far as its path tells
+#[derive(Debug, Clone, PartialEq)]
+enum ActionTarget {
+    Container(String),
+    Project(String),
+    Any,
+}
+
+impl ActionTarget {
+    fn of(path: &str) -> Self {
+        let mut segments = path.trim_start_matches('/').split('/');
+        match (segments.next(), segments.next(), segments.next()) {
+            (Some("api"), Some("container"), Some(id)) => Self::Container(id.to_string()),
+            (Some("api"), Some("projects"), Some(name)) => Self::Project(name.to_string()),
+            _ => Self::Any,
+        }
+    }
+
+    fn covers(&self, event: &DockerEvent) -> bool {
+        match self {
+            Self::Container(id) => concerns(event, id),
+            Self::Project(name) => event.attributes.get(PROJECT_LABEL) == Some(name),
+            Self::Any => true,
+        }
+    }
+}
+
+/// A mutating API request, kept to attribute the events it causes
+#[derive(Debug, Clone)]
+struct Action {
+    id: u64,
+    origin: EventOrigin,
+    target: ActionTarget,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl Action {
+    fn caused(&self, event: &DockerEvent, now: DateTime<Utc>) -> bool {
+        let end = self.finished_at.unwrap_or(now) + ORIGIN_SLACK;
+        event.time >= self.origin.requested_at - ORIGIN_SLACK && event.time <= end && self.target.covers(event)
+    }
+}
+
+/// Where an event came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OriginFilter {
+    Flowscope,
+    External,
+}
+
+/// Which events a query wants
+#[derive(Debug, Default, Deserialize)]
+pub struct EventQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    /// Container name or id prefix; also matches network events naming it
+    pub container: Option<String>,
+    pub origin: Option<OriginFilter>,
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    fn matches(&self, event: &DockerEvent) -> bool {
+        self.since.is_none_or(|since| event.time >= since)
+            && self.until.is_none_or(|until| event.time <= until)
+            && self.event_type.as_ref().is_none_or(|t| &event.event_type == t)
+            && self.container.as_deref().is_none_or(|c| concerns(event, c))
+            && self.origin.is_none_or(|origin| match origin {
+                OriginFilter::Flowscope => event.origin.is_some(),
+                OriginFilter::External => event.origin.is_none(),
+            })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: Mutex<VecDeque<DockerEvent>>,
+    actions: Mutex<VecDeque<Action>>,
+    next_action: std::sync::atomic::AtomicU64,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time of the newest event recorded
+    pub fn latest(&self) -> Option<DateTime<Utc>> {
+        self.events.lock().unwrap().back().map(|e| e.time)
+    }
+
+    /// Record an event in time order, ignoring one already recorded (a
+    /// reconnect replays the second the stream dropped in)
+    pub fn record(&self, event: DockerEvent) {
+        let mut events = self.events.lock().unwrap();
+        let at = events.partition_point(|e| e.time <= event.time);
+        if events.range(..at).rev().take_while(|e| e.time == event.time).any(|e| *e == event) {
+            return;
+        }
+        events.insert(at, event);
+
+        let newest = events.back().map(|e| e.time).unwrap_or_default();
+        while events.len() > CAPACITY || events.front().is_some_and(|e| newest - e.time > RETENTION) {
+            events.pop_front();
+        }
+    }
+
+    /// Journal a request as it starts; returns the id to finish it with
+    pub fn begin_action(&self, method: &Method, path: &str, token: Option<String>, at: DateTime<Utc>) -> u64 {
+        let id = self.next_action.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let mut actions = self.actions.lock().unwrap();
+        actions.push_back(Action {
+            id,
+            origin: EventOrigin {
+                method: method.to_string(),
+                path: path.to_string(),
+                token,
+                requested_at: at,
+            },
+            target: ActionTarget::of(path),
+            finished_at: None,
+        });
+        while actions.front().is_some_and(|a| at - a.origin.requested_at > RETENTION) {
+            actions.pop_front();
+        }
+        id
+    }
+
+    pub fn finish_action(&self, id: u64, at: DateTime<Utc>) {
+        if let Some(action) = self.actions.lock().unwrap().iter_mut().rev().find(|a| a.id == id) {
+            action.finished_at = Some(at);
+        }
+    }
+
+    /// The newest events matching `query`, up to its limit, oldest first, with
+    /// the requests that caused them
+    pub fn query(&self, query: &EventQuery) -> Vec<DockerEvent> {
+        let now = Utc::now();
+        let actions = self.actions.lock().unwrap();
+        let events = self.events.lock().unwrap();
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+        let mut matching: Vec<DockerEvent> = events
+            .iter()
+            .rev()
+            .map(|event| DockerEvent {
+                // The latest request wins when several overlap
+                origin: actions.iter().rev().find(|a| a.caused(event, now)).map(|a| a.origin.clone()),
+                ..event.clone()
+            })
+            .filter(|e| query.matches(e))
+            .take(limit)
+            .collect();
+        matching.reverse();
+        matching
+    }
+}
+
+/// Middleware journalling requests that act on containers, so the events they cause can be
+/// attributed to them; sits inside the auth layer to see the caller's token
+pub async fn record_actions(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let acts = matches!(
+        required_scope(request.method(), request.uri().path()),
+        Some(ApiScope::ContainersActions | ApiScope::Exec)
+    ) || is_destructive(request.method(), request.uri().path());
+    if !acts {
+        return next.run(request).await;
+    }
+    let token = request.extensions().get::<Caller>().map(|Caller(token)| token.name.clone());
+    let id = state
+        .events
+        .begin_action(request.method(), request.uri().path(), token, Utc::now());
+    let response = next.run(request).await;
+    state.events.finish_action(id, Utc::now());
+    // The action's events may still be on their way
+    state.docker.invalidate_cache();
+    response
+}
+
+/// Spawn the background task that follows the daemon's events into the log
+pub fn spawn_collector(state: AppState) {
+    tokio::spawn(async move {
+        let mut backoff = RECONNECT_MIN;
+        loop {
+            let since = state.events.latest().unwrap_or_else(|| Utc::now() - RETENTION);
+            let mut stream = state.docker.events(since.timestamp());
+            state.updates.stream_opened();
+            state.docker.trust_cache(state.updates.event_driven());
+            let mut lost = "stream ended".to_string();
+            while let Some(message) = stream.next().await {
+                match message {
+                    Ok(message) => {
+                        backoff = RECONNECT_MIN;
+                        if let Some(event) = normalize(message) {
+                            state.docker.observe_event(&event);
+                            state.updates.event_seen(&event);
+                            state.events.record(event);
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Docker event stream failed: {}", e);
+                        lost = e.to_string();
+                        break;
+                    }
+                }
+            }
+            state.updates.stream_lost(&lost);
+            state.docker.trust_cache(false);
+            info!("Reconnecting to the Docker event stream in {:?}", backoff);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(RECONNECT_MAX);
+        }
+    });
+}
+
+/// GET /api/events?since=&until=&type=&container=&origin=&limit= - Recorded daemon
+/// events, oldest first
+pub async fn list_events(State(state): State<AppState>, Query(query): Query<EventQuery>) -> Json<Vec<DockerEvent>> {
+    Json(state.events.query(&query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::{EventActor as DaemonActor, EventMessageTypeEnum};
+    use std::collections::HashMap;
+
+    fn message(secs: i64, typ: EventMessageTypeEnum, action: &str, id: &str, attributes: &[(&str, &str)]) -> EventMessage {
+        EventMessage {
+            typ: Some(typ),
+            action: Some(action.to_string()),
+            actor: Some(DaemonActor {
+                id: Some(id.to_string()),
+                attributes: Some(attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>()),
+            }),
+            time: Some(1_750_000_000 + secs),
+            time_nano: Some((1_750_000_000 + secs) * 1_000_000_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_actions_and_actors() {
+        let event = normalize(message(
+            0,
+            EventMessageTypeEnum::CONTAINER,
+            "health_status: unhealthy",
+            "abc123",
+            &[("name", "api"), ("image", "api:1")],
+        ))
+        .unwrap();
+        assert_eq!(event.event_type, "container");
+        assert_eq!((event.action.as_str(), event.detail.as_deref()), ("health_status", Some("unhealthy")));
+        assert_eq!(event.actor.name.as_deref(), Some("api"));
+        assert_eq!(event.attributes["image"], "api:1");
+    }
+
+    #[test]
+    fn attributes_events_to_requests() {
+        let log = EventLog::new();
+        let at = |secs: i64| Utc.timestamp_opt(1_750_000_000 + secs, 0).unwrap();
+        let restart = log.begin_action(&Method::POST, "/api/container/api/restart", Some("ops".to_string()), at(0));
+        log.finish_action(restart, at(4));
+        let group = log.begin_action(&Method::POST, "/api/groups/shop/actions/stop", None, at(100));
+        log.finish_action(group, at(101));
+
+        let container = EventMessageTypeEnum::CONTAINER;
+        for message in [
+            message(2, container, "restart", "abc123", &[("name", "api")]),
+            // Same moment, another container: not ours
+            message(3, container, "die", "def456", &[("name", "worker")]),
+            // Same container, long after: someone else
+            message(60, container, "kill", "abc123", &[("name", "api")]),
+            message(102, container, "stop", "def456", &[("name", "worker")]),
+        ] {
+            log.record(normalize(message).unwrap());
+        }
+
+        let origins: Vec<Option<(String, Option<String>)>> = log
+            .query(&EventQuery::default())
+            .into_iter()
+            .map(|e| e.origin.map(|o| (o.path, o.token)))
+            .collect();
+        assert_eq!(
+            origins,
+            [
+                Some(("/api/container/api/restart".to_string(), Some("ops".to_string()))),
+                None,
+                None,
+                Some(("/api/groups/shop/actions/stop".to_string(), None)),
+            ]
+        );
+
+        let external = EventQuery {
+            origin: Some(OriginFilter::External),
+            ..Default::default()
+        };
+        assert_eq!(log.query(&external).len(), 2);
+    }
+
+    #[test]
+    fn records_once_and_filters() {
+        let log = EventLog::new();
+        let container = EventMessageTypeEnum::CONTAINER;
+        let events = [
+            message(0, container, "start", "abc123", &[("name", "api")]),
+            message(10, EventMessageTypeEnum::NETWORK, "disconnect", "net1", &[("name", "backend"), ("container", "abc123")]),
+            message(20, container, "kill", "abc123", &[("name", "api"), ("signal", "15")]),
+            message(20, container, "die", "def456", &[("name", "worker"), ("exitCode", "137")]),
+        ];
+        // A reconnect replays the last second
+        for message in events.iter().chain(&events[2..]) {
+            log.record(normalize(message.clone()).unwrap());
+        }
+        assert_eq!(log.query(&EventQuery::default()).len(), 4);
+
+        let api = EventQuery {
+            container: Some("api".to_string()),
+            ..Default::default()
+        };
+        let actions: Vec<String> = log.query(&api).into_iter().map(|e| e.action).collect();
+        assert_eq!(actions, ["start", "kill"]);
+
+        let by_id = EventQuery {
+            container: Some("abc".to_string()),
+            event_type: Some("network".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(log.query(&by_id)[0].action, "disconnect");
+
+        let latest = EventQuery {
+            since: Some(Utc.timestamp_opt(1_750_000_005, 0).unwrap()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(log.query(&latest)[0].actor.name.as_deref(), Some("worker"));
+    }
+}
@@ -0,0 +1,147 @@
+//! Commands run inside containers
+//!
+//! `POST /api/container/:id/exec` runs a command in a running container
+//! through the daemon's exec API and answers with its stdout, stderr and exit
+//! code, for quick diagnostics such as `ps`, `env` or
+//! `curl localhost:8080/health` without a shell on the host. The command runs
+//! without a shell unless it starts one itself. Output beyond
+//! [`OUTPUT_LIMIT`] per stream is dropped, and a command still running after
+//! its timeout is reported as timed out; Docker cannot stop an exec, so it is
+//! left to finish on its own.
+
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{ContainerStatus, ExecRequest, ExecResult},
+    AppState,
+};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Bytes kept of each of stdout and stderr
+pub const OUTPUT_LIMIT: usize = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExecError {
+    #[error("container '{0}' not found")]
+    NotFound(String),
+    #[error("container '{0}' is not running")]
+    NotRunning(String),
+    #[error("invalid exec request: {0}")]
+    Invalid(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl ExecError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ExecError::NotFound(_) => StatusCode::NOT_FOUND,
+            ExecError::NotRunning(_) => StatusCode::CONFLICT,
+            ExecError::Invalid(_) => StatusCode::BAD_REQUEST,
+            ExecError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ExecError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Exec error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// The request's timeout, after checking it and the command
+pub fn validate(request: &ExecRequest) -> Result<Duration, ExecError> {
+    if request.cmd.first().is_none_or(|program| program.trim().is_empty()) {
+        return Err(ExecError::Invalid("cmd must name a program to run".to_string()));
+    }
+    match request.timeout_seconds.map(Duration::from_secs) {
+        None => Ok(DEFAULT_TIMEOUT),
+        Some(timeout) if timeout.is_zero() => Err(ExecError::Invalid("timeoutSeconds must be positive".to_string())),
+        Some(timeout) if timeout > MAX_TIMEOUT => Err(ExecError::Invalid(format!(
+            "timeoutSeconds must be at most {}",
+            MAX_TIMEOUT.as_secs()
+        ))),
+        Some(timeout) => Ok(timeout),
+    }
+}
+
+/// POST /api/container/:id/exec - Run a command inside a running container
+pub async fn exec_command(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<ExecRequest>,
+) -> Result<Json<ExecResult>, ExecError> {
+    let timeout = validate(&request)?;
+    let target = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or_else(|| ExecError::NotFound(id.clone()))?;
+    if !matches!(
+        target.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    ) {
+        return Err(ExecError::NotRunning(target.name));
+    }
+
+    let started = Instant::now();
+    let run = state
+        .docker
+        .exec_command(&target.id, &request.cmd, request.working_dir.as_deref(), OUTPUT_LIMIT);
+    let (output, timed_out) = match tokio::time::timeout(timeout, run).await {
+        Ok(output) => (output?, false),
+        Err(_) => (Default::default(), true),
+    };
+    if timed_out {
+        warn!("{:?} in {} still running after {}s", request.cmd, target.name, timeout.as_secs());
+    } else {
+        info!("Ran {:?} in {}, exit code {:?}", request.cmd, target.name, output.exit_code);
+    }
+
+    Ok(Json(ExecResult {
+        container: target.name,
+        cmd: request.cmd,
+        stdout: output.stdout,
+        stderr: output.stderr,
+        exit_code: output.exit_code,
+        timed_out,
+        truncated: output.truncated,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_command_and_timeout() {
+        let request = |cmd: &[&str], timeout_seconds| ExecRequest {
+            cmd: cmd.iter().map(|c| c.to_string()).collect(),
+            working_dir: None,
+            timeout_seconds,
+        };
+        assert_eq!(validate(&request(&["ps"], None)).unwrap(), DEFAULT_TIMEOUT);
+        assert_eq!(validate(&request(&["env"], Some(5))).unwrap(), Duration::from_secs(5));
+        assert!(validate(&request(&[], None)).is_err());
+        assert!(validate(&request(&[" "], None)).is_err());
+        assert!(validate(&request(&["ps"], Some(0))).is_err());
+        assert!(validate(&request(&["ps"], Some(MAX_TIMEOUT.as_secs() + 1))).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Sparse fieldsets
+//!
+//! Heavy endpoints accept `?fields=` with a comma-separated list of JSON field
+//! names to keep, so clients that only need a few fields of each container or
+//! node don't download the rest. Dotted paths select inside nested objects and
+//! apply to every element of an array:
+//!
+//! ```text
+//! /api/containers?fields=name,status,stats.cpuPercent
+//! /api/flowchart/overview?fields=name,nodes.id,nodes.status,edges
+//! ```
+//!
+//! Names are matched against the serialized (camelCase) output; fields that a
+//! response does not have are ignored. Selection runs on the serialized value,
+//! so any handler can use it by returning [`Sparse`].
+
+use std::collections::BTreeMap;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Paths nested deeper than this are rejected
+pub const MAX_DEPTH: usize = 4;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FieldsQuery {
+    /// Comma-separated fields to keep, e.g. `name,status,stats.cpuPercent`
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FieldsError {
+    #[error("invalid field '{0}' in fields")]
+    Invalid(String),
+    #[error("field '{0}' is nested more than {MAX_DEPTH} levels deep")]
+    TooDeep(String),
+}
+
+impl IntoResponse for FieldsError {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Selected fields; a field maps to `None` when it is kept whole
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldSet(BTreeMap<String, Option<FieldSet>>);
+
+impl FieldSet {
+    /// Parse a `fields` parameter; absent or blank means every field
+    pub fn parse(fields: Option<&str>) -> Result<Option<FieldSet>, FieldsError> {
+        let Some(fields) = fields.map(str::trim).filter(|f| !f.is_empty()) else {
+            return Ok(None);
+        };
+
+        let mut set = FieldSet::default();
+        for path in fields.split(',').map(str::trim) {
+            let segments: Vec<&str> = path.split('.').collect();
+            let valid = |s: &&str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !segments.iter().all(valid) {
+                return Err(FieldsError::Invalid(path.to_string()));
+            }
+            if segments.len() > MAX_DEPTH {
+                return Err(FieldsError::TooDeep(path.to_string()));
+            }
+            set.insert(&segments);
+        }
+        Ok(Some(set))
+    }
+
+    fn insert(&mut self, segments: &[&str]) {
+        let Some((first, rest)) = segments.split_first() else {
+            return;
+        };
+        if rest.is_empty() {
+            // Keeping the whole field subsumes any narrower selection
+            self.0.insert(first.to_string(), None);
+            return;
+        }
+        if let Some(child) = self.0.entry(first.to_string()).or_insert_with(|| Some(FieldSet::default())) {
+            child.insert(rest);
+        }
+    }
+
+    /// Drop every field of `value` that is not selected
+    pub fn apply(&self, value: &mut Value) {
+        match value {
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply(item)),
+            Value::Object(map) => {
+                map.retain(|key, _| self.0.contains_key(key));
+                for (key, field) in map.iter_mut() {
+                    if let Some(Some(child)) = self.0.get(key) {
+                        child.apply(field);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A JSON response trimmed to a [`FieldSet`]; untouched without one
+pub struct Sparse<T>(pub T, pub Option<FieldSet>);
+
+impl<T: Serialize> IntoResponse for Sparse<T> {
+    fn into_response(self) -> Response {
+        let Sparse(body, fields) = self;
+        let Some(fields) = fields else {
+            return Json(body).into_response();
+        };
+        match serde_json::to_value(&body) {
+            Ok(mut value) => {
+                fields.apply(&mut value);
+                Json(value).into_response()
+            }
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": format!("failed to serialize response: {}", e) })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn selects_nested_fields_across_arrays() {
+        let fields = FieldSet::parse(Some("name, nodes.id,nodes.metrics.cpu,edges,nodes.metrics")).unwrap().unwrap();
+        let mut value = json!({
+            "id": "overview",
+            "name": "Overview",
+            "nodes": [
+                { "id": "a", "label": "A", "metrics": { "cpu": 1.0, "memory": 2.0 } },
+                { "id": "b", "label": "B" }
+            ],
+            "edges": [{ "from": "a", "to": "b" }]
+        });
+        fields.apply(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "name": "Overview",
+                "nodes": [
+                    { "id": "a", "metrics": { "cpu": 1.0, "memory": 2.0 } },
+                    { "id": "b" }
+                ],
+                "edges": [{ "from": "a", "to": "b" }]
+            })
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(FieldSet::parse(None).unwrap(), None);
+        assert_eq!(FieldSet::parse(Some(" ")).unwrap(), None);
+        assert!(matches!(FieldSet::parse(Some("name,,status")), Err(FieldsError::Invalid(_))));
+        assert!(matches!(FieldSet::parse(Some("stats.")), Err(FieldsError::Invalid(_))));
+        assert!(matches!(FieldSet::parse(Some("a.b.c.d.e")), Err(FieldsError::TooDeep(_))));
+    }
+}
@@ -0,0 +1,607 @@
+//! Port forwards
+//!
+//! `POST /api/container/:id/forward` opens a listener on an ephemeral
+//! FlowScope port that proxies to a container port over the container's
+//! network address, so internal admin UIs can be reached without publishing
+//! the port. FlowScope must be able to route to the container, i.e. run on the
+//! Docker host or share a network with it.
+//!
+//! Forwards are closed when their TTL runs out or on `DELETE
+//! /api/forwards/:id`, which also cuts their open connections. HTTP forwards
+//! admit a client once it presents the forward's token, either as the
+//! `flowscope_token` query parameter (exchanged for a cookie with a redirect)
+//! or as that cookie. Raw TCP has nowhere to carry a token, so TCP forwards
+//! only admit the address that opened them. Every admission and refusal is
+//! kept in the forward's audit trail and logged.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    auth::Caller,
+    models::{ContainerInfo, ContainerStatus, ForwardAuditEvent, ForwardProtocol, PortForward, PortForwardRequest},
+    ttl,
+    AppState,
+};
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+pub const MAX_TTL: Duration = Duration::from_secs(60 * 60);
+/// Forwards open at once
+pub const MAX_FORWARDS: usize = 16;
+/// Audit events kept per forward; the oldest are dropped first
+pub const MAX_AUDIT_EVENTS: usize = 200;
+/// Query parameter carrying an HTTP forward's token
+pub const TOKEN_PARAM: &str = "flowscope_token";
+
+/// Largest HTTP request head read before admitting a client
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ForwardError {
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("container '{0}' is not running")]
+    NotRunning(String),
+    #[error("container '{0}' has no network address FlowScope can reach")]
+    NoAddress(String),
+    #[error("port forward '{0}' not found")]
+    NotFound(String),
+    #[error("invalid port forward: {0}")]
+    Invalid(String),
+    #[error("{0} port forwards are already open")]
+    TooMany(usize),
+    #[error("failed to open a listener: {0}")]
+    Listen(#[from] std::io::Error),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl ForwardError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ForwardError::ContainerNotFound(_) | ForwardError::NotFound(_) => StatusCode::NOT_FOUND,
+            ForwardError::NotRunning(_) | ForwardError::NoAddress(_) | ForwardError::TooMany(_) => {
+                StatusCode::CONFLICT
+            }
+            ForwardError::Invalid(_) => StatusCode::BAD_REQUEST,
+            ForwardError::Listen(_) | ForwardError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ForwardError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Port forward error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+fn digest(secret: &str) -> [u8; 32] {
+    Sha256::digest(secret.as_bytes()).into()
+}
+
+/// An open forward and what its connections need
+struct Forward {
+    info: Mutex<PortForward>,
+    token_digest: [u8; 32],
+    allowed_peer: IpAddr,
+    upstream: SocketAddr,
+    closed: watch::Sender<bool>,
+}
+
+/// What an HTTP request head presents
+#[derive(Debug, PartialEq)]
+enum HttpAccess {
+    /// A valid cookie; the request is proxied
+    Cookie,
+    /// A valid token in the query; redirect to `location` with the cookie set
+    Token { token: String, location: String },
+    Denied,
+}
+
+impl Forward {
+    fn record(&self, event: &str, peer: Option<SocketAddr>, detail: Option<String>) {
+        let mut info = self.info.lock().unwrap();
+        if event == "connected" {
+            info.connections += 1;
+        }
+        info!(
+            "Port forward {} ({}:{}) {}{}{}",
+            info.id,
+            info.target,
+            info.container_port,
+            event,
+            peer.map(|p| format!(" from {}", p)).unwrap_or_default(),
+            detail.as_deref().map(|d| format!(": {}", d)).unwrap_or_default(),
+        );
+        info.audit.push(ForwardAuditEvent {
+            at: Utc::now(),
+            event: event.to_string(),
+            peer: peer.map(|p| p.to_string()),
+            detail,
+        });
+        if info.audit.len() > MAX_AUDIT_EVENTS {
+            info.audit.remove(0);
+        }
+    }
+
+    /// Cookies are scoped by host, not port, so each forward names its own
+    fn cookie_name(&self) -> String {
+        format!("flowscope_forward_{}", self.info.lock().unwrap().listen_port)
+    }
+
+    fn valid(&self, token: &str) -> bool {
+        digest(token) == self.token_digest
+    }
+
+    fn check_http(&self, head: &str) -> HttpAccess {
+        let mut lines = head.split("\r\n");
+        let target = lines
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/");
+
+        let cookie_name = self.cookie_name();
+        let cookie_ok = lines
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("cookie"))
+            .flat_map(|(_, value)| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .any(|(name, value)| name == cookie_name && self.valid(value));
+        if cookie_ok {
+            return HttpAccess::Cookie;
+        }
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (tokens, rest): (Vec<&str>, Vec<&str>) = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .partition(|p| p.split_once('=').is_some_and(|(name, _)| name == TOKEN_PARAM));
+        let Some(token) = tokens
+            .iter()
+            .filter_map(|p| p.split_once('='))
+            .map(|(_, value)| value)
+            .find(|value| self.valid(value))
+        else {
+            return HttpAccess::Denied;
+        };
+        let location = if rest.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, rest.join("&"))
+        };
+        HttpAccess::Token {
+            token: token.to_string(),
+            location,
+        }
+    }
+
+    /// Decide whether a client may connect, returning the bytes already read
+    /// from it, `Ok(None)` when the connection was answered here, or why it
+    /// was refused
+    async fn admit(&self, client: &mut TcpStream, peer: SocketAddr) -> Result<Option<Vec<u8>>, String> {
+        let protocol = self.info.lock().unwrap().protocol;
+        if protocol == ForwardProtocol::Tcp {
+            return if peer.ip() == self.allowed_peer {
+                Ok(Some(Vec::new()))
+            } else {
+                Err(format!("{} is not the address that opened the forward", peer.ip()))
+            };
+        }
+
+        let head = tokio::time::timeout(HEAD_TIMEOUT, read_head(client))
+            .await
+            .map_err(|_| "timed out waiting for the request".to_string())?
+            .map_err(|e| format!("failed to read the request: {}", e))?;
+        let end = head
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| "request head too large".to_string())?;
+
+        match self.check_http(&String::from_utf8_lossy(&head[..end])) {
+            HttpAccess::Cookie => Ok(Some(head)),
+            HttpAccess::Token { token, location } => {
+                let response = format!(
+                    "HTTP/1.1 303 See Other\r\nLocation: {}\r\nSet-Cookie: {}={}; Path=/; HttpOnly; SameSite=Strict\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    location,
+                    self.cookie_name(),
+                    token,
+                );
+                let _ = client.write_all(response.as_bytes()).await;
+                self.record("authorized", Some(peer), None);
+                Ok(None)
+            }
+            HttpAccess::Denied => {
+                let body = "FlowScope port forward: missing or invalid token\n";
+                let response = format!(
+                    "HTTP/1.1 401 Unauthorized\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = client.write_all(response.as_bytes()).await;
+                Err("missing or invalid token".to_string())
+            }
+        }
+    }
+}
+
+/// Read until the end of an HTTP request head or [`MAX_HEAD_BYTES`]
+async fn read_head(client: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 2048];
+    while head.len() < MAX_HEAD_BYTES && !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = client.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+    Ok(head)
+}
+
+/// Proxy one client connection until either side closes or the forward does
+async fn relay(forward: Arc<Forward>, mut client: TcpStream, peer: SocketAddr) {
+    let head = match forward.admit(&mut client, peer).await {
+        Ok(Some(head)) => head,
+        Ok(None) => return,
+        Err(reason) => {
+            forward.record("refused", Some(peer), Some(reason));
+            return;
+        }
+    };
+
+    let mut upstream = match TcpStream::connect(forward.upstream).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            forward.record("refused", Some(peer), Some(format!("container port unreachable: {}", e)));
+            return;
+        }
+    };
+    forward.record("connected", Some(peer), None);
+    if upstream.write_all(&head).await.is_err() {
+        return;
+    }
+
+    let mut closed = forward.closed.subscribe();
+    tokio::select! {
+        _ = tokio::io::copy_bidirectional(&mut client, &mut upstream) => {}
+        _ = closed.wait_for(|closed| *closed) => {}
+    }
+}
+
+/// Open port forwards
+pub struct PortForwards {
+    bind: IpAddr,
+    forwards: Mutex<HashMap<String, Arc<Forward>>>,
+}
+
+impl PortForwards {
+    pub fn new(bind: IpAddr) -> Self {
+        Self {
+            bind,
+            forwards: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Listen on `FLOWSCOPE_FORWARD_BIND`, every interface when unset
+    pub fn from_env() -> Self {
+        let bind = match std::env::var("FLOWSCOPE_FORWARD_BIND") {
+            Ok(value) => value.parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid FLOWSCOPE_FORWARD_BIND '{}'", value);
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+            }),
+            Err(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        };
+        Self::new(bind)
+    }
+
+    /// Open forwards, oldest first
+    pub fn list(&self) -> Vec<PortForward> {
+        let mut forwards: Vec<PortForward> = self
+            .forwards
+            .lock()
+            .unwrap()
+            .values()
+            .map(|f| f.info.lock().unwrap().clone())
+            .collect();
+        forwards.sort_by_key(|f| f.created_at);
+        forwards
+    }
+
+    pub fn get(&self, id: &str) -> Option<PortForward> {
+        let forwards = self.forwards.lock().unwrap();
+        forwards.get(id).map(|f| f.info.lock().unwrap().clone())
+    }
+
+    /// Close a forward and its connections, returning whether it was open
+    pub fn close(&self, id: &str) -> bool {
+        match self.forwards.lock().unwrap().remove(id) {
+            Some(forward) => {
+                forward.closed.send_replace(true);
+                forward.record("closed", None, Some("closed by request".to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start listening for `target` and proxy to `upstream` until the TTL runs
+    /// out. The returned forward carries the token and, for HTTP, a link built
+    /// from `host`, the host name the API was reached at.
+    pub async fn open(
+        self: &Arc<Self>,
+        target: &str,
+        upstream: SocketAddr,
+        request: &PortForwardRequest,
+        allowed_peer: IpAddr,
+        opened_by: Option<String>,
+        host: &str,
+    ) -> Result<PortForward, ForwardError> {
+        let ttl = ttl::requested(request.ttl_seconds, DEFAULT_TTL, MAX_TTL).map_err(ForwardError::Invalid)?;
+        let listener = TcpListener::bind(SocketAddr::new(self.bind, 0)).await?;
+        let listen_port = listener.local_addr()?.port();
+
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let created_at = Utc::now();
+        let info = PortForward {
+            id: uuid::Uuid::new_v4().to_string(),
+            target: target.to_string(),
+            container_port: upstream.port(),
+            protocol: request.protocol,
+            listen_port,
+            url: None,
+            token: None,
+            opened_by,
+            created_at,
+            expires_at: created_at + chrono::Duration::from_std(ttl).expect("TTL is capped"),
+            connections: 0,
+            audit: Vec::new(),
+        };
+        let forward = Arc::new(Forward {
+            info: Mutex::new(info.clone()),
+            token_digest: digest(&token),
+            allowed_peer,
+            upstream,
+            closed: watch::Sender::new(false),
+        });
+
+        {
+            let mut forwards = self.forwards.lock().unwrap();
+            if forwards.len() >= MAX_FORWARDS {
+                return Err(ForwardError::TooMany(forwards.len()));
+            }
+            forwards.insert(info.id.clone(), forward.clone());
+        }
+        forward.record(
+            "opened",
+            None,
+            Some(format!("{:?} on port {} for {}s", request.protocol, listen_port, ttl.as_secs()).to_lowercase()),
+        );
+        tokio::spawn(self.clone().serve(forward, listener, ttl));
+
+        let url = (request.protocol == ForwardProtocol::Http)
+            .then(|| format!("http://{}:{}/?{}={}", host, listen_port, TOKEN_PARAM, token));
+        Ok(PortForward {
+            url,
+            token: Some(token),
+            ..info
+        })
+    }
+
+    async fn serve(self: Arc<Self>, forward: Arc<Forward>, listener: TcpListener, ttl: Duration) {
+        let mut closed = forward.closed.subscribe();
+        let expiry = tokio::time::sleep(ttl);
+        tokio::pin!(expiry);
+        loop {
+            tokio::select! {
+                _ = &mut expiry => break,
+                _ = closed.wait_for(|closed| *closed) => return,
+                accepted = listener.accept() => match accepted {
+                    Ok((client, peer)) => {
+                        tokio::spawn(relay(forward.clone(), client, peer));
+                    }
+                    Err(e) => warn!("Port forward failed to accept a connection: {}", e),
+                },
+            }
+        }
+
+        let id = forward.info.lock().unwrap().id.clone();
+        self.forwards.lock().unwrap().remove(&id);
+        forward.closed.send_replace(true);
+        forward.record("closed", None, Some("expired".to_string()));
+    }
+}
+
+fn is_running(container: &ContainerInfo) -> bool {
+    matches!(
+        container.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    )
+}
+
+/// Host part of a `Host` header, without the port
+fn host_name(headers: &HeaderMap) -> &str {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    match host.strip_prefix('[') {
+        Some(v6) => v6.split_once(']').map_or(host, |(addr, _)| &host[..addr.len() + 2]),
+        None => host.split(':').next().unwrap_or(host),
+    }
+}
+
+/// GET /api/forwards - List open port forwards
+pub async fn list_forwards(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.forwards.list())
+}
+
+/// GET /api/forwards/:id - A port forward and its audit trail
+pub async fn get_forward(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<PortForward>, ForwardError> {
+    state.forwards.get(&id).map(Json).ok_or(ForwardError::NotFound(id))
+}
+
+/// POST /api/container/:id/forward - Open a temporary proxy to a container port
+pub async fn open_forward(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    caller: Option<Extension<Caller>>,
+    headers: HeaderMap,
+    Json(request): Json<PortForwardRequest>,
+) -> Result<impl IntoResponse, ForwardError> {
+    if request.port == 0 {
+        return Err(ForwardError::Invalid("port must be between 1 and 65535".to_string()));
+    }
+    ttl::requested(request.ttl_seconds, DEFAULT_TTL, MAX_TTL).map_err(ForwardError::Invalid)?;
+
+    let target = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or_else(|| ForwardError::ContainerNotFound(id.clone()))?;
+    if !is_running(&target) {
+        return Err(ForwardError::NotRunning(target.name));
+    }
+    let address = state
+        .docker
+        .container_address(&target.id)
+        .await?
+        .ok_or_else(|| ForwardError::NoAddress(target.name.clone()))?;
+
+    let opened_by = caller.map(|Extension(Caller(token))| token.name);
+    let forward = state
+        .forwards
+        .open(
+            &target.name,
+            SocketAddr::new(address, request.port),
+            &request,
+            peer.ip(),
+            opened_by,
+            host_name(&headers),
+        )
+        .await?;
+    Ok((StatusCode::CREATED, Json(forward)))
+}
+
+/// DELETE /api/forwards/:id - Close a port forward and its connections
+pub async fn close_forward(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ForwardError> {
+    if state.forwards.close(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ForwardError::NotFound(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An upstream that answers every connection with a fixed line
+    async fn upstream() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+            }
+        });
+        addr
+    }
+
+    async fn exchange(port: u16, request: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+        response
+    }
+
+    fn request(protocol: ForwardProtocol) -> PortForwardRequest {
+        PortForwardRequest {
+            port: 15672,
+            protocol,
+            ttl_seconds: Some(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn http_forward_requires_token() {
+        let forwards = Arc::new(PortForwards::new(Ipv4Addr::LOCALHOST.into()));
+        let forward = forwards
+            .open("rabbitmq", upstream().await, &request(ForwardProtocol::Http), Ipv4Addr::LOCALHOST.into(), None, "localhost")
+            .await
+            .unwrap();
+        let (port, token) = (forward.listen_port, forward.token.unwrap());
+        assert_eq!(forward.url.unwrap(), format!("http://localhost:{}/?flowscope_token={}", port, token));
+
+        let denied = exchange(port, "GET / HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(denied.starts_with("HTTP/1.1 401"));
+
+        let redirect = exchange(port, &format!("GET /api/overview?{}={}&x=1 HTTP/1.1\r\n\r\n", TOKEN_PARAM, token)).await;
+        assert!(redirect.starts_with("HTTP/1.1 303"));
+        assert!(redirect.contains("Location: /api/overview?x=1\r\n"));
+        assert!(redirect.contains(&format!("Set-Cookie: flowscope_forward_{}={};", port, token)));
+
+        let proxied = exchange(port, &format!("GET / HTTP/1.1\r\nCookie: a=b; flowscope_forward_{}={}\r\n\r\n", port, token)).await;
+        assert!(proxied.ends_with("\r\n\r\nok"));
+
+        let events: Vec<String> = forwards.get(&forward.id).unwrap().audit.into_iter().map(|e| e.event).collect();
+        assert_eq!(events, ["opened", "refused", "authorized", "connected"]);
+
+        assert!(forwards.close(&forward.id));
+        assert!(forwards.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tcp_forward_admits_only_the_opener() {
+        let forwards = Arc::new(PortForwards::new(Ipv4Addr::LOCALHOST.into()));
+        let upstream = upstream().await;
+        let mine = forwards
+            .open("db", upstream, &request(ForwardProtocol::Tcp), Ipv4Addr::LOCALHOST.into(), None, "localhost")
+            .await
+            .unwrap();
+        let theirs = forwards
+            .open("db", upstream, &request(ForwardProtocol::Tcp), Ipv4Addr::new(10, 0, 0, 9).into(), None, "localhost")
+            .await
+            .unwrap();
+        assert!(mine.url.is_none());
+
+        assert!(exchange(mine.listen_port, "").await.ends_with("ok"));
+        assert!(exchange(theirs.listen_port, "").await.is_empty());
+        assert_eq!(forwards.get(&mine.id).unwrap().connections, 1);
+        assert_eq!(forwards.get(&theirs.id).unwrap().connections, 0);
+    }
+}
@@ -0,0 +1,345 @@
+//! Dependency graph export
+//!
+//! Serializes the inferred container graph as GraphML (Gephi, yEd, NetworkX's
+//! `read_graphml`) or JSON Graph Format v2, for analysis beyond what the
+//! FlowScope UI renders. Node ids are container names. The same edges link
+//! the services of a category flowchart.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::models::{ConnectionType, ContainerInfo, FlowchartConnection};
+
+/// How two containers are known to be related
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Attached to the same non-default network; symmetric
+    Network,
+    /// The source names the target in a `*_HOST`, `*_URL` or similar variable
+    Env,
+    /// The source's compose service `depends_on` the target's
+    DependsOn,
+    /// The source holds a connection open to a port the target listens on
+    Socket,
+    /// Both mount the same named volume; symmetric
+    Volume,
+}
+
+impl EdgeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EdgeKind::Network => "network",
+            EdgeKind::Env => "env",
+            EdgeKind::DependsOn => "depends_on",
+            EdgeKind::Socket => "socket",
+            EdgeKind::Volume => "volume",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    /// Shared network or volume names, or the variable holding the reference
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    pub nodes: Vec<ContainerInfo>,
+    pub edges: Vec<GraphEdge>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Graphml,
+    /// JSON Graph Format; `json` is accepted as an alias
+    #[serde(alias = "json")]
+    Jgf,
+}
+
+impl GraphFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            GraphFormat::Graphml => "application/graphml+xml",
+            GraphFormat::Jgf => "application/vnd.jgf+json",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            GraphFormat::Graphml => "flowscope-graph.graphml",
+            GraphFormat::Jgf => "flowscope-graph.json",
+        }
+    }
+
+    pub fn render(self, graph: &DependencyGraph) -> String {
+        match self {
+            GraphFormat::Graphml => to_graphml(graph),
+            GraphFormat::Jgf => serde_json::to_string_pretty(&to_jgf(graph)).expect("JGF serializes") + "\n",
+        }
+    }
+}
+
+fn lowercase_debug<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value).to_lowercase()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// GraphML with every edge directed, since NetworkX rejects mixed graphs;
+/// network and volume edges carry their `kind` so analyses can treat them as
+/// symmetric
+pub fn to_graphml(graph: &DependencyGraph) -> String {
+    const NODE_KEYS: [&str; 5] = ["name", "image", "status", "category", "networks"];
+    const EDGE_KEYS: [&str; 2] = ["kind", "label"];
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for key in NODE_KEYS {
+        writeln!(out, "  <key id=\"{0}\" for=\"node\" attr.name=\"{0}\" attr.type=\"string\"/>", key).unwrap();
+    }
+    for key in EDGE_KEYS {
+        writeln!(out, "  <key id=\"{0}\" for=\"edge\" attr.name=\"{0}\" attr.type=\"string\"/>", key).unwrap();
+    }
+    writeln!(out, "  <graph id=\"flowscope\" edgedefault=\"directed\">").unwrap();
+
+    for node in &graph.nodes {
+        let values = [
+            node.name.clone(),
+            node.image.clone(),
+            lowercase_debug(&node.status),
+            lowercase_debug(&node.category),
+            node.networks.join(", "),
+        ];
+        writeln!(out, "    <node id=\"{}\">", xml_escape(&node.name)).unwrap();
+        for (key, value) in NODE_KEYS.iter().zip(values) {
+            writeln!(out, "      <data key=\"{}\">{}</data>", key, xml_escape(&value)).unwrap();
+        }
+        writeln!(out, "    </node>").unwrap();
+    }
+
+    for (i, edge) in graph.edges.iter().enumerate() {
+        writeln!(
+            out,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">",
+            i,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        )
+        .unwrap();
+        writeln!(out, "      <data key=\"kind\">{}</data>", edge.kind.as_str()).unwrap();
+        writeln!(out, "      <data key=\"label\">{}</data>", xml_escape(&edge.label)).unwrap();
+        writeln!(out, "    </edge>").unwrap();
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+/// JSON Graph Format v2 (<https://jsongraphformat.info>)
+pub fn to_jgf(graph: &DependencyGraph) -> serde_json::Value {
+    let nodes: serde_json::Map<String, serde_json::Value> = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            (
+                node.name.clone(),
+                json!({
+                    "label": node.name,
+                    "metadata": {
+                        "id": node.id,
+                        "image": node.image,
+                        "status": node.status,
+                        "category": node.category,
+                        "networks": node.networks,
+                    }
+                }),
+            )
+        })
+        .collect();
+    let edges: Vec<serde_json::Value> = graph
+        .edges
+        .iter()
+        .map(|edge| {
+            json!({
+                "source": edge.source,
+                "target": edge.target,
+                "relation": edge.kind.as_str(),
+                "directed": !matches!(edge.kind, EdgeKind::Network | EdgeKind::Volume),
+                "label": edge.label,
+            })
+        })
+        .collect();
+
+    json!({
+        "graph": {
+            "id": "flowscope",
+            "type": "container-dependencies",
+            "label": "FlowScope container dependencies",
+            "directed": true,
+            "metadata": { "generatedAt": graph.generated_at },
+            "nodes": nodes,
+            "edges": edges,
+        }
+    })
+}
+
+/// Flowchart connections between `containers` from the edges inferred among
+/// them. Env references are drawn as data flows, `depends_on` edges as
+/// dependencies, shared volumes as volume links and open connections as
+/// primary links; a shared network
+/// only links containers with no more specific edge between them. Replicas
+/// (two or more containers running the same image) are linked in a ring
+/// instead, since every replica shares its siblings' networks.
+pub fn flowchart_connections(containers: &[ContainerInfo], edges: &[GraphEdge]) -> Vec<FlowchartConnection> {
+    let ids: HashMap<&str, &str> = containers.iter().map(|c| (c.name.as_str(), c.id.as_str())).collect();
+    let mut per_image: HashMap<&str, Vec<&ContainerInfo>> = HashMap::new();
+    for container in containers {
+        per_image.entry(&container.image).or_default().push(container);
+    }
+    let replicas = |a: &str, b: &str| {
+        let image = |name: &str| containers.iter().find(|c| c.name == name).map(|c| c.image.as_str());
+        image(a).is_some_and(|i| image(b) == Some(i) && per_image[i].len() > 1)
+    };
+
+    let mut connections: Vec<FlowchartConnection> = Vec::new();
+    let mut push = |source: &str, target: &str, label: Option<String>, connection_type: ConnectionType| {
+        let id = format!("{}-to-{}", source, target);
+        if !connections.iter().any(|c| c.id == id) {
+            connections.push(FlowchartConnection {
+                id,
+                source: source.to_string(),
+                target: target.to_string(),
+                label,
+                connection_type,
+            });
+        }
+    };
+
+    let mut linked: HashSet<(&str, &str)> = HashSet::new();
+    for edge in edges.iter().filter(|e| e.kind != EdgeKind::Network) {
+        let (Some(source), Some(target)) = (ids.get(edge.source.as_str()), ids.get(edge.target.as_str())) else {
+            continue;
+        };
+        let connection_type = match edge.kind {
+            EdgeKind::Env => ConnectionType::Data,
+            EdgeKind::DependsOn => ConnectionType::Depends,
+            EdgeKind::Volume => ConnectionType::Volume,
+            _ => ConnectionType::Primary,
+        };
+        push(source, target, Some(edge.label.clone()), connection_type);
+        linked.insert((source, target));
+        linked.insert((target, source));
+    }
+    for edge in edges.iter().filter(|e| e.kind == EdgeKind::Network) {
+        let (Some(source), Some(target)) = (ids.get(edge.source.as_str()), ids.get(edge.target.as_str())) else {
+            continue;
+        };
+        if !linked.contains(&(*source, *target)) && !replicas(&edge.source, &edge.target) {
+            push(source, target, Some(edge.label.clone()), ConnectionType::Network);
+        }
+    }
+
+    // Rings follow the order containers were given in
+    let mut images: Vec<&str> = Vec::new();
+    for container in containers {
+        if per_image[container.image.as_str()].len() > 1 && !images.contains(&container.image.as_str()) {
+            images.push(&container.image);
+        }
+    }
+    for image in images {
+        let set = &per_image[image];
+        // A pair is linked once rather than both ways
+        let links = if set.len() == 2 { 1 } else { set.len() };
+        for (i, source) in set.iter().enumerate().take(links) {
+            let target = set[(i + 1) % set.len()];
+            push(&source.id, &target.id, None, ConnectionType::Network);
+        }
+    }
+    connections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{docker_api::fake, models::ServiceCategory};
+
+    fn container(name: &str, image: &str) -> ContainerInfo {
+        ContainerInfo {
+            id: format!("{}-id", name),
+            image: image.to_string(),
+            category: ServiceCategory::Blockchain,
+            networks: vec!["chain".to_string()],
+            ..fake::container(name)
+        }
+    }
+
+    fn edge(source: &str, target: &str, kind: EdgeKind, label: &str) -> GraphEdge {
+        GraphEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            kind,
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn prefers_specific_edges_and_rings_replicas() {
+        let containers = [
+            container("faucet", "chain/faucet"),
+            container("validator-1", "chain/validator"),
+            container("validator-2", "chain/validator"),
+            container("validator-3", "chain/validator"),
+            container("explorer", "chain/explorer"),
+        ];
+        let edges = [
+            edge("faucet", "validator-1", EdgeKind::Network, "chain"),
+            edge("faucet", "explorer", EdgeKind::Network, "chain"),
+            edge("validator-1", "validator-2", EdgeKind::Network, "chain"),
+            edge("faucet", "validator-1", EdgeKind::Env, "RPC_URL"),
+            edge("explorer", "faucet", EdgeKind::DependsOn, "faucet"),
+        ];
+
+        let connections: Vec<(String, Option<String>, ConnectionType)> = flowchart_connections(&containers, &edges)
+            .into_iter()
+            .map(|c| (c.id, c.label, c.connection_type))
+            .collect();
+        let label = |l: &str| Some(l.to_string());
+        assert_eq!(
+            connections,
+            [
+                ("faucet-id-to-validator-1-id".to_string(), label("RPC_URL"), ConnectionType::Data),
+                ("explorer-id-to-faucet-id".to_string(), label("faucet"), ConnectionType::Depends),
+                ("validator-1-id-to-validator-2-id".to_string(), None, ConnectionType::Network),
+                ("validator-2-id-to-validator-3-id".to_string(), None, ConnectionType::Network),
+                ("validator-3-id-to-validator-1-id".to_string(), None, ConnectionType::Network),
+            ]
+        );
+    }
+
+    #[test]
+    fn replica_pairs_are_linked_once() {
+        let containers = [container("web-1", "shop/web"), container("web-2", "shop/web")];
+        let edges = [edge("web-1", "web-2", EdgeKind::Network, "shop")];
+
+        let ids: Vec<String> = flowchart_connections(&containers, &edges).into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, ["web-1-id-to-web-2-id"]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Container groups
+//!
+//! A group is a named set of containers picked by explicit name and/or label
+//! selectors, independent of the name-prefix categories. Groups get their own
+//! flowchart, aggregate stats and bulk lifecycle actions.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{
+    fields::{FieldSet, FieldsError, FieldsQuery, Sparse},
+    models::{ActionResult, ContainerGroup, ContainerInfo, Flowchart, GroupStats},
+    selector::{self, Selector, SelectorError, SelectorQuery},
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub type GroupStore = JsonStore<ContainerGroup>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GroupError {
+    #[error("group '{0}' not found")]
+    NotFound(String),
+    #[error("group '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("invalid group: {0}")]
+    Invalid(String),
+    #[error("unknown action '{0}'; expected restart, stop or start")]
+    UnknownAction(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error(transparent)]
+    Fields(#[from] FieldsError),
+    #[error(transparent)]
+    Selector(#[from] SelectorError),
+}
+
+impl GroupError {
+    fn status(&self) -> StatusCode {
+        match self {
+            GroupError::NotFound(_) => StatusCode::NOT_FOUND,
+            GroupError::AlreadyExists(_) => StatusCode::CONFLICT,
+            GroupError::Docker(_) | GroupError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for GroupError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Group error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Whether `container` belongs to one of the groups called `names`; with no
+/// names every container does, and a name no group has matches nothing
+pub fn member_of(groups: &[ContainerGroup], names: &[String], container: &ContainerInfo) -> bool {
+    names.is_empty() || groups.iter().any(|g| names.contains(&g.name) && g.matches(container))
+}
+
+pub fn validate(group: &ContainerGroup) -> Result<(), GroupError> {
+    if group.name.trim().is_empty() {
+        return Err(GroupError::Invalid("name must not be empty".to_string()));
+    }
+    if group.containers.is_empty() && group.labels.is_empty() {
+        return Err(GroupError::Invalid(
+            "a group needs at least one container name or label selector".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn lookup(state: &AppState, name: &str) -> Result<ContainerGroup, GroupError> {
+    state
+        .groups
+        .get(name)
+        .ok_or_else(|| GroupError::NotFound(name.to_string()))
+}
+
+/// GET /api/groups - List groups
+pub async fn list_groups(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.groups.list())
+}
+
+/// GET /api/groups/:name - Get a group
+pub async fn get_group(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<ContainerGroup>, GroupError> {
+    lookup(&state, &name).map(Json)
+}
+
+/// POST /api/groups - Create a group
+pub async fn create_group(
+    State(state): State<AppState>,
+    Json(mut group): Json<ContainerGroup>,
+) -> Result<impl IntoResponse, GroupError> {
+    if state.groups.contains(&group.name) {
+        return Err(GroupError::AlreadyExists(group.name));
+    }
+    validate(&group)?;
+    group.created_at = Utc::now();
+    group.updated_at = group.created_at;
+
+    state.groups.put(&group.name, group.clone())?;
+    info!("Created group '{}'", group.name);
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+/// PUT /api/groups/:name - Replace a group
+pub async fn update_group(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(mut group): Json<ContainerGroup>,
+) -> Result<Json<ContainerGroup>, GroupError> {
+    let existing = lookup(&state, &name)?;
+
+    group.name = name.clone();
+    validate(&group)?;
+    group.created_at = existing.created_at;
+    group.updated_at = Utc::now();
+
+    state.groups.put(&name, group.clone())?;
+    info!("Updated group '{}'", name);
+    Ok(Json(group))
+}
+
+/// DELETE /api/groups/:name - Delete a group
+pub async fn delete_group(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, GroupError> {
+    match state.groups.remove(&name)? {
+        Some(_) => {
+            info!("Deleted group '{}'", name);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(GroupError::NotFound(name)),
+    }
+}
+
+/// GET /api/groups/:name/flowchart - Flowchart of the group's members, optionally trimmed with `?fields=`
+pub async fn get_group_flowchart(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<FieldsQuery>,
+) -> Result<Sparse<Flowchart>, GroupError> {
+    let fields = FieldSet::parse(query.fields.as_deref())?;
+    let group = lookup(&state, &name)?;
+    let mut flowchart = state.docker.generate_group_flowchart(&group).await?;
+    state.layouts.apply(&mut flowchart);
+    Ok(Sparse(flowchart, fields))
+}
+
+/// GET /api/groups/:name/stats - Aggregate stats across the group
+pub async fn get_group_stats(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<GroupStats>, GroupError> {
+    let group = lookup(&state, &name)?;
+    Ok(Json(state.docker.get_group_stats(&group).await?))
+}
+
+/// Run `action` on every container of `members` at once
+async fn run_action(state: &AppState, members: &[ContainerInfo], action: &str) -> Result<Vec<ActionResult>, GroupError> {
+    if !matches!(action, "restart" | "stop" | "start") {
+        return Err(GroupError::UnknownAction(action.to_string()));
+    }
+
+    let results = futures_util::future::join_all(members.iter().map(|c| {
+        let docker = &state.docker;
+        async move {
+            match action {
+                "restart" => docker.restart_container(&c.id).await,
+                "stop" => docker.stop_container(&c.id).await,
+                _ => docker.start_container(&c.id).await,
+            }
+        }
+    }))
+    .await;
+
+    Ok(results
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// POST /api/groups/:name/actions/:action - Restart, stop or start every
+/// member, or only the members `?selector=` picks
+pub async fn group_action(
+    State(state): State<AppState>,
+    Path((name, action)): Path<(String, String)>,
+    Query(selection): Query<SelectorQuery>,
+) -> Result<Json<Vec<ActionResult>>, GroupError> {
+    let selector = Selector::parse(selection.selector.as_deref())?;
+    let group = lookup(&state, &name)?;
+    let members = selector::select(selector.as_ref(), state.docker.group_members(&group).await?);
+    let results = run_action(&state, &members, &action).await?;
+
+    info!(
+        "Group '{}' {}: {}/{} succeeded",
+        name,
+        action,
+        results.iter().filter(|r| r.success).count(),
+        results.len()
+    );
+    Ok(Json(results))
+}
+
+/// POST /api/containers/actions/:action?selector= - Restart, stop or start
+/// every container the selector picks. The selector is required, so a
+/// missing parameter can't act on every container.
+pub async fn bulk_action(
+    State(state): State<AppState>,
+    Path(action): Path<String>,
+    Query(selection): Query<SelectorQuery>,
+) -> Result<Json<Vec<ActionResult>>, GroupError> {
+    let Some(selector) = Selector::parse(selection.selector.as_deref())? else {
+        return Err(GroupError::Invalid("selector is required".to_string()));
+    };
+    let containers = selector::select(Some(&selector), state.docker.list_containers().await?);
+    let results = run_action(&state, &containers, &action).await?;
+
+    info!(
+        "Bulk {} of {} containers: {}/{} succeeded",
+        action,
+        containers.len(),
+        results.iter().filter(|r| r.success).count(),
+        results.len()
+    );
+    Ok(Json(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake;
+    use std::collections::BTreeMap;
+
+    fn group(name: &str, containers: &[&str], labels: &[(&str, &str)]) -> ContainerGroup {
+        ContainerGroup {
+            name: name.to_string(),
+            description: None,
+            containers: containers.iter().map(|c| c.to_string()).collect(),
+            labels: labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<_, _>>(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn container(name: &str, labels: &[(&str, &str)]) -> ContainerInfo {
+        ContainerInfo {
+            labels: fake::labels(labels),
+            ..fake::container(name)
+        }
+    }
+
+    #[test]
+    fn groups_need_a_name_and_a_way_to_pick_members() {
+        assert!(validate(&group("checkout", &["shop-api"], &[])).is_ok());
+        assert!(validate(&group("checkout", &[], &[("team", "shop")])).is_ok());
+        assert!(matches!(validate(&group(" ", &["shop-api"], &[])), Err(GroupError::Invalid(_))));
+        assert!(matches!(validate(&group("checkout", &[], &[])), Err(GroupError::Invalid(_))));
+    }
+
+    #[test]
+    fn members_are_listed_or_match_every_label() {
+        let checkout = group("checkout", &["payments"], &[("team", "shop"), ("tier", "*")]);
+        assert!(checkout.matches(&container("payments", &[])));
+        assert!(checkout.matches(&container("shop-api", &[("team", "shop"), ("tier", "web")])));
+        assert!(!checkout.matches(&container("shop-api", &[("team", "shop")])));
+        assert!(!checkout.matches(&container("shop-api", &[("team", "ops"), ("tier", "web")])));
+        // Label selectors never pick everything
+        assert!(!group("names-only", &["payments"], &[]).matches(&container("shop-api", &[])));
+    }
+
+    #[test]
+    fn membership_across_named_groups() {
+        let groups = [group("checkout", &["payments"], &[]), group("search", &["search-api"], &[])];
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let payments = container("payments", &[]);
+
+        assert!(member_of(&groups, &[], &payments));
+        assert!(member_of(&groups, &names(&["checkout"]), &payments));
+        assert!(member_of(&groups, &names(&["search", "checkout"]), &payments));
+        assert!(!member_of(&groups, &names(&["search"]), &payments));
+        assert!(!member_of(&groups, &names(&["missing"]), &payments));
+    }
+}
@@ -0,0 +1,296 @@
+//! Topology history
+//!
+//! A background task snapshots the containers, their statuses and the
+//! inferred dependencies every [`SNAPSHOT_INTERVAL`] into
+//! `topology-history.json`, keeping the last [`MAX_SNAPSHOTS`]. A snapshot
+//! identical to the latest one is not stored, so the history only grows when
+//! something changed. `GET /api/history/diff?from=&to=` compares two points
+//! in time: each is a snapshot id or an RFC 3339 time, which picks the last
+//! snapshot taken at or before it, and `to` defaults to the latest snapshot.
+//! Containers are matched by name, so a recreated container shows up as
+//! changed rather than removed and added.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    graph::DependencyGraph,
+    models::{ContainerChange, SnapshotContainer, SnapshotEdge, SnapshotSummary, TopologyDiff, TopologySnapshot},
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(300);
+/// A week of snapshots at one per interval, oldest dropped first
+pub const MAX_SNAPSHOTS: usize = 2016;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("no topology snapshots have been recorded yet")]
+    Empty,
+    #[error("no snapshot matches '{0}'")]
+    NotFound(String),
+    #[error("'{0}' is neither a snapshot id nor an RFC 3339 time")]
+    InvalidPoint(String),
+}
+
+impl IntoResponse for HistoryError {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self {
+            HistoryError::Empty | HistoryError::NotFound(_) => StatusCode::NOT_FOUND,
+            HistoryError::InvalidPoint(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, Json(serde_json::json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// The snapshot of `graph`, taken at `at`
+pub fn snapshot(graph: &DependencyGraph, at: DateTime<Utc>) -> TopologySnapshot {
+    let mut containers: Vec<SnapshotContainer> = graph
+        .nodes
+        .iter()
+        .map(|c| SnapshotContainer {
+            name: c.name.clone(),
+            id: c.id.clone(),
+            image: c.image.clone(),
+            status: c.status.clone(),
+        })
+        .collect();
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut edges: Vec<SnapshotEdge> = graph
+        .edges
+        .iter()
+        .map(|e| SnapshotEdge {
+            source: e.source.clone(),
+            target: e.target.clone(),
+            kind: e.kind.as_str().to_string(),
+        })
+        .collect();
+    edges.sort_by(|a, b| (&a.source, &a.target, &a.kind).cmp(&(&b.source, &b.target, &b.kind)));
+    edges.dedup();
+    TopologySnapshot {
+        id: at.format("%Y%m%dT%H%M%SZ").to_string(),
+        taken_at: at,
+        containers,
+        edges,
+    }
+}
+
+fn summary(snapshot: &TopologySnapshot) -> SnapshotSummary {
+    SnapshotSummary {
+        id: snapshot.id.clone(),
+        taken_at: snapshot.taken_at,
+        container_count: snapshot.containers.len(),
+        edge_count: snapshot.edges.len(),
+    }
+}
+
+/// What changed from `from` to `to`
+pub fn diff(from: &TopologySnapshot, to: &TopologySnapshot) -> TopologyDiff {
+    let before: BTreeMap<&str, &SnapshotContainer> = from.containers.iter().map(|c| (c.name.as_str(), c)).collect();
+    let after: BTreeMap<&str, &SnapshotContainer> = to.containers.iter().map(|c| (c.name.as_str(), c)).collect();
+
+    let added = after
+        .iter()
+        .filter(|(name, _)| !before.contains_key(*name))
+        .map(|(_, c)| (*c).clone())
+        .collect();
+    let removed = before
+        .iter()
+        .filter(|(name, _)| !after.contains_key(*name))
+        .map(|(_, c)| (*c).clone())
+        .collect();
+    let changed = before
+        .iter()
+        .filter_map(|(name, old)| {
+            let new = after.get(name)?;
+            let fields: Vec<String> = [
+                ("id", old.id != new.id),
+                ("image", old.image != new.image),
+                ("status", old.status != new.status),
+            ]
+            .into_iter()
+            .filter(|(_, differs)| *differs)
+            .map(|(field, _)| field.to_string())
+            .collect();
+            (!fields.is_empty()).then(|| ContainerChange {
+                name: name.to_string(),
+                fields,
+                before: (*old).clone(),
+                after: (*new).clone(),
+            })
+        })
+        .collect();
+
+    let old_edges: HashSet<&SnapshotEdge> = from.edges.iter().collect();
+    let new_edges: HashSet<&SnapshotEdge> = to.edges.iter().collect();
+    TopologyDiff {
+        from: summary(from),
+        to: summary(to),
+        added,
+        removed,
+        changed,
+        added_edges: to.edges.iter().filter(|e| !old_edges.contains(e)).cloned().collect(),
+        removed_edges: from.edges.iter().filter(|e| !new_edges.contains(e)).cloned().collect(),
+    }
+}
+
+/// Snapshots of the topology, persisted across restarts
+pub struct TopologyHistory {
+    snapshots: JsonStore<TopologySnapshot>,
+}
+
+impl TopologyHistory {
+    pub fn open(file_name: &str) -> Self {
+        Self {
+            snapshots: JsonStore::open(file_name),
+        }
+    }
+
+    /// Store `snapshot` unless it matches the latest one; returns whether it
+    /// was stored
+    pub fn record(&self, snapshot: TopologySnapshot) -> Result<bool, StoreError> {
+        let latest = self.snapshots.keys().pop().and_then(|id| self.snapshots.get(&id));
+        if latest.is_some_and(|l| l.containers == snapshot.containers && l.edges == snapshot.edges) {
+            return Ok(false);
+        }
+        self.snapshots.put(&snapshot.id.clone(), snapshot)?;
+        let keys = self.snapshots.keys();
+        for id in &keys[..keys.len().saturating_sub(MAX_SNAPSHOTS)] {
+            self.snapshots.remove(id)?;
+        }
+        Ok(true)
+    }
+
+    /// Every snapshot, oldest first
+    pub fn list(&self) -> Vec<SnapshotSummary> {
+        self.snapshots.list().iter().map(summary).collect()
+    }
+
+    /// The snapshot with id `point`, or the last one taken at or before the
+    /// time `point`; the latest snapshot when there is no `point`
+    pub fn find(&self, point: Option<&str>) -> Result<TopologySnapshot, HistoryError> {
+        let Some(point) = point.map(str::trim).filter(|p| !p.is_empty()) else {
+            let latest = self.snapshots.keys().pop().ok_or(HistoryError::Empty)?;
+            return self.snapshots.get(&latest).ok_or(HistoryError::Empty);
+        };
+        if let Some(snapshot) = self.snapshots.get(point) {
+            return Ok(snapshot);
+        }
+        let at = DateTime::parse_from_rfc3339(point)
+            .map_err(|_| HistoryError::InvalidPoint(point.to_string()))?
+            .with_timezone(&Utc);
+        self.snapshots
+            .list()
+            .into_iter()
+            .take_while(|s| s.taken_at <= at)
+            .last()
+            .ok_or_else(|| HistoryError::NotFound(point.to_string()))
+    }
+}
+
+/// Spawn the background task that records topology snapshots
+pub fn spawn_recorder(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            match state.docker.dependency_graph().await {
+                Ok(graph) => match state.history.record(snapshot(&graph, Utc::now())) {
+                    Ok(true) => info!("Recorded a topology snapshot of {} containers", graph.nodes.len()),
+                    Ok(false) => {}
+                    Err(e) => warn!("Failed to save the topology snapshot: {}", e),
+                },
+                Err(e) => warn!("Failed to snapshot the topology: {}", e),
+            }
+            tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+        }
+    });
+}
+
+/// GET /api/history/snapshots - Recorded topology snapshots, oldest first
+pub async fn list_snapshots(State(state): State<AppState>) -> Json<Vec<SnapshotSummary>> {
+    Json(state.history.list())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: String,
+    pub to: Option<String>,
+}
+
+/// GET /api/history/diff?from=&to= - Containers and dependencies that changed
+/// between two snapshots
+pub async fn get_diff(
+    State(state): State<AppState>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<TopologyDiff>, HistoryError> {
+    let from = state.history.find(Some(&query.from))?;
+    let to = state.history.find(query.to.as_deref())?;
+    Ok(Json(diff(&from, &to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DockerDiscovery;
+    use crate::docker_api::fake::FakeDocker;
+    use crate::models::ContainerStatus;
+
+    #[tokio::test]
+    async fn diffs_snapshots_by_name() {
+        let dir = std::env::temp_dir().join(format!("flowscope-topology-history-{}", std::process::id()));
+        let history = TopologyHistory {
+            snapshots: JsonStore::open_at(dir.join("topology-history.json")),
+        };
+        let graph = DockerDiscovery::new(FakeDocker::load("stack")).dependency_graph().await.unwrap();
+        let evening: DateTime<Utc> = "2026-10-14T18:00:00Z".parse().unwrap();
+        let morning: DateTime<Utc> = "2026-10-15T08:00:00Z".parse().unwrap();
+
+        let before = snapshot(&graph, evening);
+        assert!(history.record(before.clone()).unwrap());
+        assert!(!history.record(snapshot(&graph, evening + chrono::Duration::minutes(5))).unwrap());
+
+        // Overnight the first container was recreated and stopped, and the
+        // last one went away along with its dependencies
+        let mut after = snapshot(&graph, morning);
+        let gone = after.containers.pop().unwrap();
+        after.edges.retain(|e| e.source != gone.name && e.target != gone.name);
+        after.containers[0].id = "recreated".to_string();
+        after.containers[0].status = ContainerStatus::Exited;
+        after.containers.push(SnapshotContainer {
+            name: "zz-new".to_string(),
+            ..gone.clone()
+        });
+        assert!(history.record(after).unwrap());
+        assert_eq!(history.list().len(), 2);
+
+        let from = history.find(Some("2026-10-14T23:00:00+02:00")).unwrap();
+        assert_eq!(from.id, "20261014T180000Z");
+        let to = history.find(None).unwrap();
+        let changes = diff(&from, &to);
+        assert_eq!(changes.added.len(), 1);
+        assert_eq!(changes.removed, std::slice::from_ref(&gone));
+        assert_eq!(changes.changed.len(), 1);
+        assert_eq!(changes.changed[0].fields, ["id", "status"]);
+        assert_eq!(
+            changes.removed_edges.len(),
+            before.edges.iter().filter(|e| e.source == gone.name || e.target == gone.name).count()
+        );
+        assert!(changes.added_edges.is_empty());
+
+        assert!(matches!(history.find(Some("2026-10-13T00:00:00Z")), Err(HistoryError::NotFound(_))));
+        assert!(matches!(history.find(Some("yesterday")), Err(HistoryError::InvalidPoint(_))));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
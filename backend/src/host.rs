@@ -0,0 +1,318 @@
+//! Host resource collection
+//!
+//! Reads host-level CPU, memory and disk figures from `/proc`, `statvfs` and
+//! `docker info`, and combines them with per-container limits to compute how
+//! much capacity is left once every limit is honoured. Also maps CPU pinning
+//! (`--cpuset-cpus`) onto the host's cores and NUMA nodes.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use bollard::models::HostConfig;
+
+use crate::models::{CoreAssignment, CpuMap, CpuPlacement, HostResources, NumaNode, PinnedContainer};
+
+/// Weight the kernel gives containers that set no `--cpu-shares`
+const DEFAULT_CPU_SHARES: u64 = 1024;
+const DEFAULT_CPU_WEIGHT: u64 = 100;
+
+/// Limits reserved by containers, summed across running containers
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CommittedLimits {
+    pub memory_bytes: u64,
+    pub cpus: f64,
+    pub unlimited_memory: usize,
+    pub unlimited_cpu: usize,
+}
+
+impl CommittedLimits {
+    /// Add a container's `HostConfig` limits
+    pub fn add(&mut self, memory: Option<i64>, nano_cpus: Option<i64>, cpu_quota: Option<i64>, cpu_period: Option<i64>) {
+        match memory {
+            Some(bytes) if bytes > 0 => self.memory_bytes += bytes as u64,
+            _ => self.unlimited_memory += 1,
+        }
+
+        let cpus = match (nano_cpus, cpu_quota, cpu_period) {
+            (Some(nano), _, _) if nano > 0 => Some(nano as f64 / 1_000_000_000.0),
+            (_, Some(quota), Some(period)) if quota > 0 && period > 0 => Some(quota as f64 / period as f64),
+            _ => None,
+        };
+        match cpus {
+            Some(cpus) => self.cpus += cpus,
+            None => self.unlimited_cpu += 1,
+        }
+    }
+}
+
+/// Memory figures from `/proc/meminfo`, in bytes
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemInfo {
+    pub total: Option<u64>,
+    pub available: Option<u64>,
+}
+
+pub fn read_meminfo(proc_root: &Path) -> MemInfo {
+    std::fs::read_to_string(proc_root.join("meminfo"))
+        .map(|s| parse_meminfo(&s))
+        .unwrap_or_default()
+}
+
+fn parse_meminfo(contents: &str) -> MemInfo {
+    let field = |name: &str| {
+        contents
+            .lines()
+            .find(|l| l.starts_with(name))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    };
+    MemInfo {
+        total: field("MemTotal:"),
+        available: field("MemAvailable:"),
+    }
+}
+
+/// 1-minute load average from `/proc/loadavg`
+pub fn read_load_average(proc_root: &Path) -> Option<f64> {
+    std::fs::read_to_string(proc_root.join("loadavg"))
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Total and available bytes of the filesystem containing `path`
+#[cfg(unix)]
+pub fn disk_usage(path: &Path) -> Option<(u64, u64)> {
+    let c_path = std::ffi::CString::new(path.to_str()?).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is a valid NUL-terminated string and `stat` is a valid out-pointer
+    let rc = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    if rc != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+/// Disk headroom is not collected on Windows hosts
+#[cfg(not(unix))]
+pub fn disk_usage(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Path used for disk headroom; the Docker root when visible, else `FLOWSCOPE_HOST_DISK_PATH` or `/`
+pub fn disk_path(docker_root: Option<&str>) -> String {
+    std::env::var("FLOWSCOPE_HOST_DISK_PATH")
+        .ok()
+        .or_else(|| docker_root.filter(|p| Path::new(p).exists()).map(str::to_string))
+        .unwrap_or_else(|| "/".to_string())
+}
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+fn mb(bytes: u64) -> f64 {
+    round2(bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn gb(bytes: u64) -> f64 {
+    round2(bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
+
+/// Combine raw host figures into the headroom summary
+pub fn summarize(
+    cpus: u64,
+    memory_total: u64,
+    meminfo: MemInfo,
+    load_average: Option<f64>,
+    disk: Option<(u64, u64)>,
+    disk_path: String,
+    committed: CommittedLimits,
+) -> HostResources {
+    let memory_total = if memory_total > 0 {
+        memory_total
+    } else {
+        meminfo.total.unwrap_or(0)
+    };
+    let percent = |part: f64, whole: f64| if whole > 0.0 { round2(part / whole * 100.0) } else { 0.0 };
+
+    HostResources {
+        cpus,
+        load_average_1m: load_average,
+        cpu_load_percent: load_average.map(|l| percent(l, cpus as f64)),
+        cpu_committed: round2(committed.cpus),
+        cpu_headroom: round2(cpus as f64 - committed.cpus),
+        containers_without_cpu_limit: committed.unlimited_cpu,
+        memory_total_mb: mb(memory_total),
+        memory_available_mb: meminfo.available.map(mb),
+        memory_used_percent: meminfo
+            .available
+            .map(|a| percent(memory_total.saturating_sub(a) as f64, memory_total as f64)),
+        memory_committed_mb: mb(committed.memory_bytes),
+        memory_committed_percent: percent(committed.memory_bytes as f64, memory_total as f64),
+        memory_headroom_mb: round2((memory_total as f64 - committed.memory_bytes as f64) / (1024.0 * 1024.0)),
+        containers_without_memory_limit: committed.unlimited_memory,
+        disk_path,
+        disk_total_gb: disk.map(|(total, _)| gb(total)),
+        disk_available_gb: disk.map(|(_, available)| gb(available)),
+        disk_used_percent: disk.map(|(total, available)| {
+            percent(total.saturating_sub(available) as f64, total as f64)
+        }),
+    }
+}
+
+/// Parse a kernel CPU list such as `0-3,8,10-11`
+pub fn parse_cpu_list(list: &str) -> Option<Vec<u32>> {
+    let mut cores = BTreeSet::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to): (u32, u32) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+                if from > to {
+                    return None;
+                }
+                cores.extend(from..=to);
+            }
+            None => {
+                cores.insert(part.trim().parse().ok()?);
+            }
+        }
+    }
+    Some(cores.into_iter().collect())
+}
+
+/// Shares on the cgroup v2 weight scale, converted the way runc does
+fn shares_to_weight(shares: u64) -> u64 {
+    1 + (shares.clamp(2, 262_144) - 2) * 9999 / 262_142
+}
+
+/// A container's pinning and CPU weight from its `HostConfig`
+pub fn cpu_placement(host_config: &HostConfig) -> CpuPlacement {
+    let set = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+    let cpuset_cpus = set(&host_config.cpuset_cpus);
+    let shares = host_config.cpu_shares.filter(|s| *s > 0).map(|s| s as u64);
+
+    let mut committed = CommittedLimits::default();
+    committed.add(None, host_config.nano_cpus, host_config.cpu_quota, host_config.cpu_period);
+
+    CpuPlacement {
+        cores: cpuset_cpus.as_deref().and_then(parse_cpu_list).unwrap_or_default(),
+        cpuset_cpus,
+        cpuset_mems: set(&host_config.cpuset_mems),
+        cpu_shares: shares.unwrap_or(DEFAULT_CPU_SHARES),
+        cpu_weight: shares.map_or(DEFAULT_CPU_WEIGHT, shares_to_weight),
+        cpu_limit: (committed.unlimited_cpu == 0).then(|| round2(committed.cpus)),
+    }
+}
+
+/// NUMA nodes from sysfs; empty where the host does not expose them
+pub fn read_numa_nodes() -> Vec<NumaNode> {
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<NumaNode> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let node = entry.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            let list = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            Some(NumaNode {
+                node,
+                cores: parse_cpu_list(&list)?,
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|n| n.node);
+    nodes
+}
+
+/// Map running containers' pinning onto the host's cores. Without NUMA
+/// information every core is taken to be on node 0.
+pub fn cpu_map(cpus: u64, numa_nodes: Vec<NumaNode>, containers: &[(String, CpuPlacement)]) -> CpuMap {
+    let numa_nodes = if numa_nodes.is_empty() {
+        vec![NumaNode {
+            node: 0,
+            cores: (0..cpus as u32).collect(),
+        }]
+    } else {
+        numa_nodes
+    };
+    let node_of: BTreeMap<u32, u32> = numa_nodes
+        .iter()
+        .flat_map(|n| n.cores.iter().map(move |core| (*core, n.node)))
+        .collect();
+
+    let mut by_core: BTreeMap<u32, Vec<String>> = node_of.keys().map(|core| (*core, Vec::new())).collect();
+    let mut pinned = Vec::new();
+    let mut floating = Vec::new();
+    for (name, placement) in containers {
+        if placement.cores.is_empty() {
+            floating.push(name.clone());
+            continue;
+        }
+        for core in &placement.cores {
+            by_core.entry(*core).or_default().push(name.clone());
+        }
+        let nodes: BTreeSet<u32> = placement.cores.iter().filter_map(|c| node_of.get(c).copied()).collect();
+        pinned.push(PinnedContainer {
+            container: name.clone(),
+            cores: placement.cores.clone(),
+            spans_numa_nodes: nodes.len() > 1,
+            numa_nodes: nodes.into_iter().collect(),
+            cpu_weight: placement.cpu_weight,
+        });
+    }
+
+    let cores: Vec<CoreAssignment> = by_core
+        .into_iter()
+        .map(|(core, containers)| CoreAssignment {
+            core,
+            numa_node: node_of.get(&core).copied().unwrap_or(0),
+            contended: containers.len() > 1,
+            containers,
+        })
+        .collect();
+    CpuMap {
+        cpus,
+        contended_cores: cores.iter().filter(|c| c.contended).count(),
+        numa_nodes,
+        cores,
+        pinned,
+        floating,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_lists() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11"), Some(vec![0, 1, 2, 3, 8, 10, 11]));
+        assert_eq!(parse_cpu_list("2\n"), Some(vec![2]));
+        assert_eq!(parse_cpu_list("3-1"), None);
+        assert_eq!(parse_cpu_list("a"), None);
+    }
+
+    #[test]
+    fn placement_weights() {
+        let placement = cpu_placement(&HostConfig {
+            cpuset_cpus: Some("0-1".to_string()),
+            cpu_shares: Some(2048),
+            nano_cpus: Some(1_500_000_000),
+            ..Default::default()
+        });
+        assert_eq!(placement.cores, [0, 1]);
+        assert_eq!((placement.cpu_shares, placement.cpu_weight), (2048, 79));
+        assert_eq!(placement.cpu_limit, Some(1.5));
+
+        let default = cpu_placement(&HostConfig::default());
+        assert!(default.cores.is_empty());
+        assert_eq!((default.cpu_shares, default.cpu_weight, default.cpu_limit), (1024, 100, None));
+    }
+}
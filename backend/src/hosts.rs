@@ -0,0 +1,446 @@
+//! Several Docker hosts as one
+//!
+//! `FLOWSCOPE_DOCKER_HOSTS=edge=tcp://10.0.0.2:2375,db=tcp://10.0.0.3:2375`
+//! names every daemon FlowScope watches; each value takes the same forms as
+//! `--docker-host`. [`MultiDocker`] lists all of them concurrently and tags
+//! every container with its host's name in the [`HOST_LABEL`] label, which
+//! discovery copies to [`ContainerInfo::host`](crate::models::ContainerInfo).
+//! Calls about one container go to the host it was last listed on, by id or
+//! name, and exec ids to the host that started them; a name used on several
+//! hosts goes to the first. Images, networks and volumes are gathered from
+//! every host, and swarm services from every host once each. Creating
+//! containers, pulling images, scaling services and host information use the
+//! first host, as do containers not listed yet.
+//!
+//! A host that fails is left out of listings with a warning; only when every
+//! host fails does the listing fail.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Mutex,
+};
+
+use bollard::{
+    container::{AttachContainerResults, Config, Stats},
+    errors::Error,
+    models::{
+        ContainerInspectResponse, ContainerSummary, ContainerTopResponse, EventMessage, FilesystemChange, ImageInspect, ImageSummary,
+        Network,
+        Service, SystemInfo, Volume,
+    },
+    Docker,
+};
+use futures_util::{future::join_all, stream::BoxStream, StreamExt};
+use tracing::{error, warn};
+
+use crate::{
+    daemon::{self, DaemonEndpoint},
+    docker_api::{self, DockerApi, ExecOutput, ExecSession, LogWindow},
+    models::LogEntry,
+};
+
+/// Label naming the host a container was listed on, set only when there are
+/// several
+pub const HOST_LABEL: &str = "flowscope.host";
+
+/// `name=url` pairs from `FLOWSCOPE_DOCKER_HOSTS`, with URLs expanded like
+/// `--docker-host`
+pub fn parse(value: &str, var: &impl Fn(&str) -> Option<String>) -> Result<Vec<(String, String)>, String> {
+    let mut hosts: Vec<(String, String)> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let Some((name, url)) = entry.split_once('=') else {
+            return Err(format!("'{}' is not name=url", entry));
+        };
+        let (name, url) = (name.trim(), url.trim());
+        if name.is_empty() || url.is_empty() {
+            return Err(format!("'{}' is not name=url", entry));
+        }
+        if hosts.iter().any(|(n, _)| n == name) {
+            return Err(format!("host '{}' is named twice", name));
+        }
+        hosts.push((name.to_string(), daemon::expand(url, var)));
+    }
+    Ok(hosts)
+}
+
+/// Which host each container and exec lives on
+#[derive(Default)]
+struct Routes {
+    names: HashMap<String, usize>,
+    ids: Vec<(String, usize)>,
+    execs: HashMap<String, usize>,
+}
+
+/// [`DockerApi`] over every configured host
+pub struct MultiDocker<D = Docker> {
+    hosts: Vec<(String, D)>,
+    routes: Mutex<Routes>,
+}
+
+impl<D: DockerApi> MultiDocker<D> {
+    /// `hosts` by name; the first is the one used for calls about no
+    /// particular container
+    pub fn new(hosts: Vec<(String, D)>) -> Self {
+        assert!(!hosts.is_empty(), "at least one host is needed");
+        Self {
+            hosts,
+            routes: Mutex::default(),
+        }
+    }
+
+    /// One unnamed host; containers are not tagged
+    pub fn single(docker: D) -> Self {
+        Self::new(vec![("local".to_string(), docker)])
+    }
+
+    fn primary(&self) -> &D {
+        &self.hosts[0].1
+    }
+
+    /// Index of the host a container id, id prefix or name was last listed on
+    fn route_index(&self, id: &str) -> usize {
+        let routes = self.routes.lock().unwrap();
+        routes
+            .names
+            .get(id.trim_start_matches('/'))
+            .or_else(|| routes.ids.iter().find(|(full, _)| full.starts_with(id)).map(|(_, i)| i))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn route(&self, id: &str) -> &D {
+        &self.hosts[self.route_index(id)].1
+    }
+
+    fn exec_route(&self, exec_id: &str) -> &D {
+        let index = self.routes.lock().unwrap().execs.get(exec_id).copied().unwrap_or(0);
+        &self.hosts[index].1
+    }
+
+    /// `call` on every host at once, joining the results of those that answer
+    async fn gather<'a, T, Fut>(&'a self, what: &str, call: impl Fn(&'a D) -> Fut) -> Result<Vec<T>, Error>
+    where
+        Fut: Future<Output = Result<Vec<T>, Error>> + Send + 'a,
+    {
+        let results = join_all(self.hosts.iter().map(|(_, docker)| call(docker))).await;
+        let mut gathered = Vec::new();
+        let mut failures = Vec::new();
+        for ((name, _), result) in self.hosts.iter().zip(results) {
+            match result {
+                Ok(items) => gathered.extend(items),
+                Err(e) => {
+                    warn!("Listing {} on host {} failed: {}", what, name, e);
+                    failures.push(e);
+                }
+            }
+        }
+        if failures.len() == self.hosts.len() {
+            return Err(failures.swap_remove(0));
+        }
+        Ok(gathered)
+    }
+}
+
+impl<D: DockerApi> DockerApi for MultiDocker<D> {
+    async fn list_containers(&self) -> Result<Vec<ContainerSummary>, Error> {
+        let results = join_all(self.hosts.iter().map(|(_, docker)| docker.list_containers())).await;
+        let tag = self.hosts.len() > 1;
+        let mut routes = Routes::default();
+        let mut listed = Vec::new();
+        let mut failures = Vec::new();
+        for (index, ((name, _), result)) in self.hosts.iter().zip(results).enumerate() {
+            let containers = match result {
+                Ok(containers) => containers,
+                Err(e) => {
+                    warn!("Listing containers on host {} failed: {}", name, e);
+                    failures.push(e);
+                    continue;
+                }
+            };
+            for mut container in containers {
+                if let Some(id) = &container.id {
+                    routes.ids.push((id.clone(), index));
+                }
+                for container_name in container.names.iter().flatten() {
+                    routes.names.entry(container_name.trim_start_matches('/').to_string()).or_insert(index);
+                }
+                if tag {
+                    container.labels.get_or_insert_with(HashMap::new).insert(HOST_LABEL.to_string(), name.clone());
+                }
+                listed.push(container);
+            }
+        }
+        if failures.len() == self.hosts.len() {
+            return Err(failures.swap_remove(0));
+        }
+        let mut current = self.routes.lock().unwrap();
+        routes.execs = std::mem::take(&mut current.execs);
+        *current = routes;
+        Ok(listed)
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse, Error> {
+        self.route(id).inspect_container(id).await
+    }
+
+    async fn stats(&self, id: &str) -> Result<Option<Stats>, Error> {
+        self.route(id).stats(id).await
+    }
+
+    async fn top_processes(&self, id: &str, ps_args: &str) -> Result<ContainerTopResponse, Error> {
+        self.route(id).top_processes(id, ps_args).await
+    }
+
+    async fn container_changes(&self, id: &str) -> Result<Vec<FilesystemChange>, Error> {
+        self.route(id).container_changes(id).await
+    }
+
+    async fn logs(&self, id: &str, window: LogWindow) -> Result<Vec<LogEntry>, Error> {
+        self.route(id).logs(id, window).await
+    }
+
+    fn follow_logs(&self, id: &str) -> BoxStream<'static, Result<String, Error>> {
+        self.route(id).follow_logs(id)
+    }
+
+    fn stream_logs(&self, id: &str, tail: Option<usize>, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        self.route(id).stream_logs(id, tail, since)
+    }
+
+    fn stream_timestamped_logs(&self, id: &str, since: i64) -> BoxStream<'static, Result<String, Error>> {
+        self.route(id).stream_timestamped_logs(id, since)
+    }
+
+    async fn exec(&self, id: &str, cmd: &[&str]) -> Result<Option<String>, Error> {
+        self.route(id).exec(id, cmd).await
+    }
+
+    async fn exec_output(
+        &self,
+        id: &str,
+        cmd: &[String],
+        working_dir: Option<&str>,
+        limit: usize,
+    ) -> Result<ExecOutput, Error> {
+        self.route(id).exec_output(id, cmd, working_dir, limit).await
+    }
+
+    async fn exec_terminal(&self, id: &str, cmd: &[String], rows: u16, cols: u16) -> Result<ExecSession, Error> {
+        let index = self.route_index(id);
+        let session = self.hosts[index].1.exec_terminal(id, cmd, rows, cols).await?;
+        self.routes.lock().unwrap().execs.insert(session.id.clone(), index);
+        Ok(session)
+    }
+
+    async fn resize_exec(&self, exec_id: &str, rows: u16, cols: u16) -> Result<(), Error> {
+        self.exec_route(exec_id).resize_exec(exec_id, rows, cols).await
+    }
+
+    async fn exec_exit_code(&self, exec_id: &str) -> Result<Option<i64>, Error> {
+        let code = self.exec_route(exec_id).exec_exit_code(exec_id).await?;
+        if code.is_some() {
+            self.routes.lock().unwrap().execs.remove(exec_id);
+        }
+        Ok(code)
+    }
+
+    fn events(&self, since: i64) -> BoxStream<'static, Result<EventMessage, Error>> {
+        futures_util::stream::select_all(self.hosts.iter().map(|(_, docker)| docker.events(since))).boxed()
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>, Error> {
+        self.gather("images", |docker| docker.list_images()).await
+    }
+
+    async fn inspect_image(&self, image: &str) -> Result<ImageInspect, Error> {
+        self.primary().inspect_image(image).await
+    }
+
+    async fn list_networks(&self) -> Result<Vec<Network>, Error> {
+        self.gather("networks", |docker| docker.list_networks()).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<Volume>, Error> {
+        self.gather("volumes", |docker| docker.list_volumes()).await
+    }
+
+    /// Every manager answers with the whole swarm, so each service is kept once
+    async fn list_services(&self) -> Result<Vec<Service>, Error> {
+        let mut services = self.gather("services", |docker| docker.list_services()).await?;
+        let mut seen = std::collections::HashSet::new();
+        services.retain(|s| seen.insert(s.id.clone()));
+        Ok(services)
+    }
+
+    async fn info(&self) -> Result<SystemInfo, Error> {
+        self.primary().info().await
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<(), Error> {
+        self.route(id).restart_container(id).await
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<(), Error> {
+        self.route(id).stop_container(id).await
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<(), Error> {
+        self.route(id).pause_container(id).await
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<(), Error> {
+        self.route(id).unpause_container(id).await
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<(), Error> {
+        self.route(id).kill_container(id, signal).await
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), Error> {
+        self.route(id).start_container(id).await
+    }
+
+    async fn create_container(&self, name: &str, config: Config<String>) -> Result<String, Error> {
+        self.primary().create_container(name, config).await
+    }
+
+    async fn remove_container(&self, id: &str, volumes: bool) -> Result<(), Error> {
+        self.route(id).remove_container(id, volumes).await
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<(), Error> {
+        self.route(id).rename_container(id, name).await
+    }
+
+    async fn scale_service(&self, service: &str, replicas: u64) -> Result<(), Error> {
+        self.primary().scale_service(service, replicas).await
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<(), Error> {
+        self.primary().pull_image(image).await
+    }
+
+    async fn remove_image(&self, image: &str, force: bool) -> Result<(), Error> {
+        self.primary().remove_image(image, force).await
+    }
+
+    async fn attach_container(&self, id: &str) -> Result<AttachContainerResults, Error> {
+        self.route(id).attach_container(id).await
+    }
+
+    async fn wait_container(&self, id: &str) -> Result<i64, Error> {
+        self.route(id).wait_container(id).await
+    }
+}
+
+/// Connect to every host in `FLOWSCOPE_DOCKER_HOSTS`, or the single endpoint
+/// from `--docker-host`, the environment, the configuration file or probing
+/// when it is unset. Hosts that cannot be used fail every call, with the
+/// reasons on the endpoint.
+pub async fn connect(flag: Option<&str>, file: Option<&str>) -> (MultiDocker, DaemonEndpoint) {
+    let var = |name: &str| std::env::var(name).ok();
+    let configured = var("FLOWSCOPE_DOCKER_HOSTS").filter(|v| !v.trim().is_empty());
+    let Some(value) = configured else {
+        let (docker, endpoint) = daemon::connect(flag, file).await;
+        return (MultiDocker::single(docker), endpoint);
+    };
+    let hosts = match parse(&value, &var) {
+        Ok(hosts) if !hosts.is_empty() => hosts,
+        invalid => {
+            let reason = match invalid {
+                Err(e) => format!("FLOWSCOPE_DOCKER_HOSTS is invalid: {}", e),
+                Ok(_) => "FLOWSCOPE_DOCKER_HOSTS names no hosts".to_string(),
+            };
+            error!("{}", reason);
+            let endpoint = DaemonEndpoint {
+                host: value,
+                source: "FLOWSCOPE_DOCKER_HOSTS".to_string(),
+                error: Some(reason.clone()),
+            };
+            return (MultiDocker::single(daemon::unavailable(reason)), endpoint);
+        }
+    };
+
+    let mut failures = Vec::new();
+    let mut connected = Vec::new();
+    for (name, url) in &hosts {
+        let docker = match docker_api::connect(url) {
+            Ok(docker) => docker,
+            Err(e) => {
+                let reason = format!("{}: cannot use {}: {}", name, url, e);
+                error!("{}", reason);
+                failures.push(reason.clone());
+                daemon::unavailable(reason)
+            }
+        };
+        connected.push((name.clone(), docker));
+    }
+    let endpoint = DaemonEndpoint {
+        host: hosts.iter().map(|(name, url)| format!("{}={}", name, url)).collect::<Vec<_>>().join(","),
+        source: "FLOWSCOPE_DOCKER_HOSTS".to_string(),
+        error: Some(failures.join("; ")).filter(|f| !f.is_empty()),
+    };
+    (MultiDocker::new(connected), endpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        discovery::DockerDiscovery,
+        docker_api::fake::{FakeDocker, Fixture},
+    };
+
+    fn edge() -> FakeDocker {
+        FakeDocker::new(Fixture {
+            containers: vec![ContainerSummary {
+                id: Some("e1d2c3b4a5f6e1d2c3b4a5f6".to_string()),
+                names: Some(vec!["/edge-proxy".to_string()]),
+                image: Some("traefik:3".to_string()),
+                state: Some("running".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn parses_named_hosts() {
+        let var = |_: &str| None;
+        assert_eq!(
+            parse("edge=tcp://10.0.0.2:2375, db=/var/run/docker.sock", &var).unwrap(),
+            [
+                ("edge".to_string(), "tcp://10.0.0.2:2375".to_string()),
+                ("db".to_string(), "unix:///var/run/docker.sock".to_string())
+            ]
+        );
+        assert!(parse("edge", &var).is_err());
+        assert!(parse("a=tcp://x:1,a=tcp://y:1", &var).is_err());
+    }
+
+    #[tokio::test]
+    async fn lists_every_host_and_routes_by_container() {
+        let discovery = DockerDiscovery::new(MultiDocker::new(vec![
+            ("main".to_string(), FakeDocker::load("stack")),
+            ("edge".to_string(), edge()),
+        ]));
+        let containers = discovery.list_containers().await.unwrap();
+        assert_eq!(containers.len(), 6);
+        let proxy = containers.iter().find(|c| c.name == "edge-proxy").unwrap();
+        assert_eq!(proxy.host.as_deref(), Some("edge"));
+        let api = containers.iter().find(|c| c.name == "application-api-1").unwrap();
+        assert_eq!(api.host.as_deref(), Some("main"));
+
+        discovery.api().restart_container("edge-proxy").await.unwrap();
+        discovery.api().restart_container(&api.id).await.unwrap();
+        assert_eq!(discovery.api().hosts[1].1.actions(), ["restart edge-proxy"]);
+        assert_eq!(discovery.api().hosts[0].1.actions(), ["restart application-api-1"]);
+
+        // The overview groups containers by host too
+        let overview = discovery.generate_flowchart("system-overview").await.unwrap().unwrap();
+        assert!(overview.nodes.iter().any(|n| n.id == "host:edge"));
+        let host = discovery.generate_flowchart("host:edge").await.unwrap().unwrap();
+        assert_eq!(host.nodes.len(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+//! Image history and rollback
+//!
+//! A background tracker notes which image each service runs every
+//! [`TRACK_INTERVAL`], and starts a new run in the service's timeline
+//! whenever the image id changes. The timeline is kept in
+//! `image-history.json`. If an update goes bad,
+//! `POST /api/container/:id/rollback?to=<digest>` recreates the container on
+//! an image its service ran before. The old image must still be on the host,
+//! since an image id cannot be pulled.
+
+use std::{collections::HashMap, time::Duration};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    discovery::error_status,
+    models::{ActionResult, ContainerInfo, ImageRun, ImageTimeline},
+    projects::{PROJECT_LABEL, SERVICE_LABEL},
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub const TRACK_INTERVAL: Duration = Duration::from_secs(60);
+/// Runs kept per service, oldest dropped first
+pub const MAX_RUNS: usize = 50;
+/// Shortest digest prefix accepted for `to`, as in `docker images`
+pub const MIN_DIGEST_PREFIX: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RollbackError {
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("no image matching '{digest}' in the history of {service}")]
+    UnknownDigest { service: String, digest: String },
+    #[error("'{digest}' matches several images in the history of {service}")]
+    Ambiguous { service: String, digest: String },
+    #[error("{container} already runs {digest}")]
+    AlreadyRunning { container: String, digest: String },
+    #[error("image {0} is no longer on this host")]
+    ImageGone(String),
+    #[error("failed to save image history: {0}")]
+    Store(#[from] StoreError),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl RollbackError {
+    fn status(&self) -> StatusCode {
+        match self {
+            RollbackError::ContainerNotFound(_) => StatusCode::NOT_FOUND,
+            RollbackError::UnknownDigest { .. } | RollbackError::Ambiguous { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            RollbackError::AlreadyRunning { .. } => StatusCode::CONFLICT,
+            RollbackError::ImageGone(_) => StatusCode::GONE,
+            RollbackError::Store(_) | RollbackError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for RollbackError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Rollback error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Timeline key of a container: `<project>/<service>` for compose services,
+/// so replicas and recreated containers share one, else the container name
+pub fn service_key(container: &ContainerInfo) -> String {
+    match (container.labels.get(PROJECT_LABEL), container.labels.get(SERVICE_LABEL)) {
+        (Some(project), Some(service)) => format!("{}/{}", project, service),
+        _ => container.name.clone(),
+    }
+}
+
+/// Whether `digest` names `run`'s image, in full or by a prefix of at least
+/// [`MIN_DIGEST_PREFIX`] hex digits
+fn names(run: &ImageRun, digest: &str) -> bool {
+    let wanted = digest.trim_start_matches("sha256:");
+    let id = run.digest.trim_start_matches("sha256:");
+    wanted.len() >= MIN_DIGEST_PREFIX && id.starts_with(wanted)
+}
+
+/// The images every service ran, persisted across restarts
+pub struct ImageHistory {
+    timelines: JsonStore<ImageTimeline>,
+}
+
+impl ImageHistory {
+    pub fn open(file_name: &str) -> Self {
+        Self {
+            timelines: JsonStore::open(file_name),
+        }
+    }
+
+    pub fn get(&self, service: &str) -> Option<ImageTimeline> {
+        self.timelines.get(service)
+    }
+
+    /// Note that `container` runs `digest` at `at`, starting a new run when
+    /// that is not the service's current image; returns whether it did
+    pub fn observe(
+        &self,
+        at: DateTime<Utc>,
+        container: &ContainerInfo,
+        digest: &str,
+    ) -> Result<bool, StoreError> {
+        let service = service_key(container);
+        let mut timeline = self.get(&service).unwrap_or_else(|| ImageTimeline {
+            service: service.clone(),
+            runs: Vec::new(),
+        });
+        if timeline.runs.last().is_some_and(|run| run.digest == digest) {
+            return Ok(false);
+        }
+        timeline.runs.push(ImageRun {
+            digest: digest.to_string(),
+            image: container.image.clone(),
+            container: container.name.clone(),
+            since: at,
+        });
+        let excess = timeline.runs.len().saturating_sub(MAX_RUNS);
+        timeline.runs.drain(..excess);
+        self.timelines.put(&service, timeline)?;
+        Ok(true)
+    }
+
+    /// The run of `service` whose image `digest` names
+    fn find(&self, service: &str, digest: &str) -> Result<ImageRun, RollbackError> {
+        let unknown = || RollbackError::UnknownDigest {
+            service: service.to_string(),
+            digest: digest.to_string(),
+        };
+        let timeline = self.get(service).ok_or_else(unknown)?;
+        let mut matches: Vec<&ImageRun> = timeline.runs.iter().filter(|run| names(run, digest)).collect();
+        matches.dedup_by(|a, b| a.digest == b.digest);
+        match matches.as_slice() {
+            [] => Err(unknown()),
+            [run] => Ok((*run).clone()),
+            // A service can go back to an image it ran before
+            [first, rest @ ..] if rest.iter().all(|run| run.digest == first.digest) => Ok((*first).clone()),
+            _ => Err(RollbackError::Ambiguous {
+                service: service.to_string(),
+                digest: digest.to_string(),
+            }),
+        }
+    }
+}
+
+/// Note the image of every service. Replicas can disagree halfway through
+/// an update; the newest container tells which image the service is on.
+async fn track(state: &AppState) -> Result<(), bollard::errors::Error> {
+    let (containers, images) = futures_util::future::try_join(
+        state.docker.list_containers(),
+        state.docker.image_ids(),
+    )
+    .await?;
+    let mut newest: HashMap<String, (&ContainerInfo, &String)> = HashMap::new();
+    for container in &containers {
+        let Some(digest) = images.get(&container.id) else {
+            continue;
+        };
+        let current = newest.entry(service_key(container)).or_insert((container, digest));
+        if container.created > current.0.created {
+            *current = (container, digest);
+        }
+    }
+
+    let now = Utc::now();
+    for (container, digest) in newest.into_values() {
+        match state.images.observe(now, container, digest) {
+            Ok(true) => info!("{} now runs {} ({})", service_key(container), container.image, digest),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to record the image of {}: {}", container.name, e),
+        }
+    }
+    Ok(())
+}
+
+/// Spawn the background task that records which image each service runs
+pub fn spawn_tracker(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = track(&state).await {
+                warn!("Failed to track service images: {}", e);
+            }
+            tokio::time::sleep(TRACK_INTERVAL).await;
+        }
+    });
+}
+
+/// GET /api/container/:id/image-history - Images the container's service ran
+pub async fn get_image_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ImageTimeline>, RollbackError> {
+    let container = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or(RollbackError::ContainerNotFound(id))?;
+    let service = service_key(&container);
+    Ok(Json(state.images.get(&service).unwrap_or(ImageTimeline {
+        service,
+        runs: Vec::new(),
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RollbackQuery {
+    pub to: String,
+}
+
+/// POST /api/container/:id/rollback?to=<digest> - Recreate a container on an
+/// image its service ran before
+pub async fn rollback_container(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<RollbackQuery>,
+) -> Result<Json<ActionResult>, RollbackError> {
+    let container = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or(RollbackError::ContainerNotFound(id))?;
+    let run = state.images.find(&service_key(&container), &query.to)?;
+    if state.docker.image_ids().await?.get(&container.id) == Some(&run.digest) {
+        return Err(RollbackError::AlreadyRunning {
+            container: container.name,
+            digest: run.digest,
+        });
+    }
+
+    info!("Rolling {} back to {} ({})", container.name, run.image, run.digest);
+    let id = match state.docker.recreate_container(&container, &run.digest).await {
+        Err(e) if error_status(&e) == Some(404) => return Err(RollbackError::ImageGone(run.digest)),
+        recreated => recreated?,
+    };
+    state.images.observe(Utc::now(), &container, &run.digest)?;
+    Ok(Json(ActionResult {
+        success: true,
+        container_id: id,
+        container_name: container.name,
+        action: "rollback".to_string(),
+        message: format!("Recreated on {} ({})", run.image, run.digest),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DockerDiscovery;
+    use crate::docker_api::fake::FakeDocker;
+
+    #[tokio::test]
+    async fn runs_start_when_the_image_changes() {
+        let dir = std::env::temp_dir().join(format!("flowscope-image-history-{}", std::process::id()));
+        let history = ImageHistory {
+            timelines: JsonStore::open_at(dir.join("image-history.json")),
+        };
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let api = discovery.get_container("application-api-1").await.unwrap().unwrap();
+
+        let start = Utc::now();
+        assert!(history.observe(start, &api, "sha256:01aaaaaaaaaaaaaa").unwrap());
+        assert!(!history.observe(start, &api, "sha256:01aaaaaaaaaaaaaa").unwrap());
+        assert!(history.observe(start, &api, "sha256:02bbbbbbbbbbbbbb").unwrap());
+        assert!(history.observe(start, &api, "sha256:01aaaaaaaaaaaaaa").unwrap());
+        assert_eq!(history.get("shop/api").unwrap().runs.len(), 3);
+
+        // Running an image again does not make it ambiguous
+        let run = history.find("shop/api", "01aaaaaaaaaa").unwrap();
+        assert_eq!(run.digest, "sha256:01aaaaaaaaaaaaaa");
+        assert!(matches!(history.find("shop/api", "01aa"), Err(RollbackError::UnknownDigest { .. })));
+        assert!(matches!(history.find("shop/web", "sha256:01aaaaaaaaaaaaaa"), Err(RollbackError::UnknownDigest { .. })));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,170 @@
+//! Image management
+//!
+//! Lists local images with their tags, digests and the containers created
+//! from them, pulls new ones and removes old ones. A pull runs as a
+//! [job](crate::jobs) since it takes as long as the download; a reference
+//! without a tag or digest pulls `latest` rather than every tag of the
+//! repository. Removing an image a container was created from needs
+//! `force=true`, as with `docker rmi`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::{
+    discovery::error_status,
+    models::{ActionResult, ImageInfo, ImagePullRequest, Job},
+    AppState,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageError {
+    #[error("image '{0}' not found")]
+    NotFound(String),
+    #[error("image '{0}' is used by a container; remove the container first or pass force=true")]
+    InUse(String),
+    #[error("'{0}' is not an image reference")]
+    InvalidReference(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl ImageError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ImageError::NotFound(_) => StatusCode::NOT_FOUND,
+            ImageError::InUse(_) => StatusCode::CONFLICT,
+            ImageError::InvalidReference(_) => StatusCode::BAD_REQUEST,
+            ImageError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ImageError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Image error: {}", self);
+        }
+        (self.status(), Json(serde_json::json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// `image` with `:latest` appended when it names neither a tag nor a digest
+pub fn reference(image: &str) -> Result<String, ImageError> {
+    let image = image.trim();
+    if image.is_empty() || image.chars().any(char::is_whitespace) || image.ends_with([':', '@', '/']) {
+        return Err(ImageError::InvalidReference(image.to_string()));
+    }
+    // A colon before the last slash belongs to a registry port
+    let name = image.rsplit('/').next().unwrap_or(image);
+    if image.contains('@') || name.contains(':') {
+        Ok(image.to_string())
+    } else {
+        Ok(format!("{}:latest", image))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImagesQuery {
+    /// Only dangling images, or only tagged ones
+    pub dangling: Option<bool>,
+}
+
+/// GET /api/images?dangling= - Local images, newest first
+pub async fn list_images(
+    State(state): State<AppState>,
+    Query(query): Query<ImagesQuery>,
+) -> Result<Json<Vec<ImageInfo>>, ImageError> {
+    let mut images = state.docker.list_images().await?;
+    if let Some(dangling) = query.dangling {
+        images.retain(|i| i.dangling == dangling);
+    }
+    Ok(Json(images))
+}
+
+/// POST /api/images/pull - Start a job that pulls an image
+pub async fn pull_image(
+    State(state): State<AppState>,
+    Json(request): Json<ImagePullRequest>,
+) -> Result<impl IntoResponse, ImageError> {
+    let image = reference(&request.image)?;
+    let discovery = state.docker.clone();
+    let target = image.clone();
+    let job: Job = state.jobs.spawn("image-pull", &image, 1, move |handle| async move {
+        discovery.pull_image(&target).await.map_err(|e| e.to_string())?;
+        handle.record(ActionResult {
+            success: true,
+            container_id: String::new(),
+            container_name: String::new(),
+            action: "pull".to_string(),
+            message: format!("Pulled {}", target),
+        });
+        Ok(())
+    });
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RemoveImageQuery {
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// DELETE /api/image/:id?force= - Remove an image by id or tag
+pub async fn remove_image(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<RemoveImageQuery>,
+) -> Result<StatusCode, ImageError> {
+    match state.docker.remove_image(&id, query.force).await {
+        Ok(()) => {
+            info!("Removed image {}", id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(e) => Err(match error_status(&e) {
+            Some(404) => ImageError::NotFound(id),
+            Some(409) => ImageError::InUse(id),
+            _ => ImageError::Docker(e),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DockerDiscovery;
+    use crate::docker_api::fake::FakeDocker;
+
+    #[test]
+    fn references_default_to_latest() {
+        assert_eq!(reference("postgres").unwrap(), "postgres:latest");
+        assert_eq!(reference(" postgres:16 ").unwrap(), "postgres:16");
+        assert_eq!(reference("registry.local:5000/shop/api").unwrap(), "registry.local:5000/shop/api:latest");
+        assert_eq!(reference("shop/api@sha256:abc").unwrap(), "shop/api@sha256:abc");
+        assert!(matches!(reference(""), Err(ImageError::InvalidReference(_))));
+        assert!(matches!(reference("shop/api:"), Err(ImageError::InvalidReference(_))));
+        assert!(matches!(reference("shop api"), Err(ImageError::InvalidReference(_))));
+    }
+
+    #[tokio::test]
+    async fn lists_and_removes_images() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let images = discovery.list_images().await.unwrap();
+        let dangling: Vec<&ImageInfo> = images.iter().filter(|i| i.dangling).collect();
+        assert_eq!(dangling.len(), 1);
+        assert!(dangling[0].tags.is_empty() && dangling[0].containers.is_empty());
+        let api = images.iter().find(|i| i.tags == ["flowscope/api:latest"]).unwrap();
+        assert_eq!(api.containers, ["application-api-1", "application-api-2"]);
+
+        assert!(discovery.remove_image(&dangling[0].id, false).await.is_ok());
+        let in_use = discovery.remove_image("flowscope/api:latest", false).await.unwrap_err();
+        assert_eq!(error_status(&in_use), Some(409));
+        assert!(discovery.remove_image("flowscope/api:latest", true).await.is_ok());
+        assert_eq!(error_status(&discovery.remove_image("missing:1", false).await.unwrap_err()), Some(404));
+    }
+}
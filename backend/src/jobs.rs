@@ -0,0 +1,159 @@
+//! Background jobs
+//!
+//! Operations that touch many containers run as jobs: the request that starts
+//! one returns immediately with the job, which records a result per step and
+//! can be polled at `/api/jobs/:id`. Jobs that produce files store them as
+//! [artifacts](crate::artifacts) and list their ids. Jobs live in memory only;
+//! the most recent [`MAX_JOBS`] are kept.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::{
+    models::{ActionResult, Artifact, Job, JobStatus},
+    AppState,
+};
+
+/// Jobs kept for polling; the oldest finished ones are dropped first
+pub const MAX_JOBS: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("job '{0}' not found")]
+    NotFound(String),
+}
+
+impl IntoResponse for JobError {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct JobRegistry {
+    jobs: Mutex<VecDeque<Job>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All retained jobs, newest first
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().iter().find(|j| j.id == id).cloned()
+    }
+
+    /// Register a job and run it in the background. `run` reports each step
+    /// through the [`JobHandle`]; an `Err` marks the job failed.
+    pub fn spawn<F, Fut>(self: &Arc<Self>, kind: &str, target: &str, total_steps: usize, run: F) -> Job
+    where
+        F: FnOnce(JobHandle) -> Fut,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            target: target.to_string(),
+            status: JobStatus::Running,
+            total_steps,
+            results: Vec::new(),
+            error: None,
+            created_at: Utc::now(),
+            finished_at: None,
+            artifacts: Vec::new(),
+        };
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.push_back(job.clone());
+            while jobs.len() > MAX_JOBS {
+                match jobs.iter().position(|j| j.status != JobStatus::Running) {
+                    Some(oldest_finished) => jobs.remove(oldest_finished),
+                    None => break,
+                };
+            }
+        }
+
+        let handle = JobHandle {
+            registry: self.clone(),
+            id: job.id.clone(),
+        };
+        let work = run(handle.clone());
+        tokio::spawn(async move {
+            let outcome = work.await;
+            handle.update(|job| {
+                job.finished_at = Some(Utc::now());
+                match outcome {
+                    Ok(()) => job.status = JobStatus::Succeeded,
+                    Err(e) => {
+                        warn!("Job {} ({} {}) failed: {}", job.id, job.kind, job.target, e);
+                        job.status = JobStatus::Failed;
+                        job.error = Some(e);
+                    }
+                }
+            });
+        });
+
+        info!("Started job {} ({} {}, {} steps)", job.id, kind, target, total_steps);
+        job
+    }
+}
+
+/// A running job's way to report progress
+#[derive(Clone)]
+pub struct JobHandle {
+    registry: Arc<JobRegistry>,
+    id: String,
+}
+
+impl JobHandle {
+    fn update(&self, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.registry.jobs.lock().unwrap().iter_mut().find(|j| j.id == self.id) {
+            f(job);
+        }
+    }
+
+    /// Record the outcome of one step
+    pub fn record(&self, result: ActionResult) {
+        self.update(|job| job.results.push(result));
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// List an artifact the job produced
+    pub fn attach(&self, artifact: &Artifact) {
+        self.update(|job| job.artifacts.push(artifact.id.clone()));
+    }
+}
+
+/// GET /api/jobs - Recent jobs, newest first
+pub async fn list_jobs(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.jobs.list())
+}
+
+/// GET /api/jobs/:id - One job and its progress
+pub async fn get_job(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<Job>, JobError> {
+    state.jobs.get(&id).map(Json).ok_or(JobError::NotFound(id))
+}
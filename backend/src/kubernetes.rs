@@ -0,0 +1,1026 @@
+//! Kubernetes discovery
+//!
+//! With `provider: kubernetes` in the configuration (or `FLOWSCOPE_PROVIDER`,
+//! `--provider`) FlowScope lists a cluster instead of a Docker host. Pods
+//! become [`ContainerInfo`] values: the pod phase and readiness give the
+//! status, the first container the image, every container port a port, and
+//! the namespace the network. Flowcharts follow the cluster's
+//! own structure: `system-overview` shows one group per namespace,
+//! `namespace:<ns>` its deployments, standalone pods and services,
+//! `deployment:<name>.<ns>` a deployment's pods, and a pod's name its services.
+//! A service is linked to whatever its selector matches.
+//!
+//! The API is reached through the service account when FlowScope runs in the
+//! cluster, or `FLOWSCOPE_KUBE_API` with `FLOWSCOPE_KUBE_TOKEN` (or
+//! `FLOWSCOPE_KUBE_TOKEN_FILE`) and `FLOWSCOPE_KUBE_CA_FILE` otherwise; a
+//! `kubectl proxy` address needs neither. `FLOWSCOPE_KUBE_NAMESPACE` limits
+//! discovery to one namespace. Kubeconfig files are not read.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    categorize::{Categorizers, Subject},
+    models::{
+        ConnectionType, ContainerInfo, ContainerStatus, Flowchart, FlowchartConnection, FlowchartNode,
+        FlowchartSummary, GroupMembership, MembershipKind, NodeType, PortMapping, ServiceCategory, SystemTopology,
+    },
+};
+
+/// Where the service account's token and CA are mounted in a pod
+const SERVICE_ACCOUNT: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+/// How long one API request may take
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Labels added to every pod's container, as the Docker shim did
+pub const NAMESPACE_LABEL: &str = "io.kubernetes.pod.namespace";
+pub const POD_LABEL: &str = "io.kubernetes.pod.name";
+/// Label naming the deployment a pod belongs to
+pub const DEPLOYMENT_LABEL: &str = "flowscope.kubernetes.deployment";
+
+#[derive(Debug, thiserror::Error)]
+pub enum KubeError {
+    #[error("{0}")]
+    Config(String),
+    #[error("Kubernetes API request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Kubernetes API answered {status} for {path}: {message}")]
+    Status { status: u16, path: String, message: String },
+}
+
+enum Token {
+    Value(String),
+    /// Re-read for every request, as service account tokens are rotated
+    File(PathBuf),
+    None,
+}
+
+/// How to reach the API server
+pub struct KubeConfig {
+    pub api: String,
+    token: Token,
+    ca: Option<Vec<u8>>,
+    /// Namespace to list, or every namespace
+    pub namespace: Option<String>,
+}
+
+impl KubeConfig {
+    pub fn from_env() -> Result<Self, KubeError> {
+        Self::from_vars(&|name| std::env::var(name).ok(), Path::new(SERVICE_ACCOUNT))
+    }
+
+    /// Settings from `var`, falling back to the service account mounted at
+    /// `service_account`
+    fn from_vars(var: &impl Fn(&str) -> Option<String>, service_account: &Path) -> Result<Self, KubeError> {
+        let set = |name: &str| var(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        let api = match (set("FLOWSCOPE_KUBE_API"), set("KUBERNETES_SERVICE_HOST")) {
+            (Some(api), _) => api.trim_end_matches('/').to_string(),
+            (None, Some(host)) => {
+                let port = set("KUBERNETES_SERVICE_PORT").unwrap_or_else(|| "443".to_string());
+                let host = if host.contains(':') { format!("[{}]", host) } else { host };
+                format!("https://{}:{}", host, port)
+            }
+            (None, None) => {
+                return Err(KubeError::Config(
+                    "FLOWSCOPE_KUBE_API is not set and FlowScope is not running in a cluster".to_string(),
+                ))
+            }
+        };
+        if reqwest::Url::parse(&api).is_err() {
+            return Err(KubeError::Config(format!("'{}' is not a URL", api)));
+        }
+
+        let default_token = service_account.join("token");
+        let token = match (set("FLOWSCOPE_KUBE_TOKEN"), set("FLOWSCOPE_KUBE_TOKEN_FILE")) {
+            (Some(token), _) => Token::Value(token),
+            (None, Some(file)) => Token::File(PathBuf::from(file)),
+            (None, None) if default_token.is_file() => Token::File(default_token),
+            (None, None) => Token::None,
+        };
+        if let Token::File(path) = &token {
+            std::fs::metadata(path)
+                .map_err(|e| KubeError::Config(format!("token file {} cannot be read: {}", path.display(), e)))?;
+        }
+
+        let default_ca = service_account.join("ca.crt");
+        let ca = match set("FLOWSCOPE_KUBE_CA_FILE").map(PathBuf::from) {
+            Some(path) => Some(path),
+            None => Some(default_ca).filter(|p| p.is_file()),
+        };
+        let ca = ca
+            .map(|path| {
+                std::fs::read(&path)
+                    .map_err(|e| KubeError::Config(format!("CA file {} cannot be read: {}", path.display(), e)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            api,
+            token,
+            ca,
+            namespace: set("FLOWSCOPE_KUBE_NAMESPACE"),
+        })
+    }
+
+    fn bearer(&self) -> Result<Option<String>, KubeError> {
+        match &self.token {
+            Token::Value(token) => Ok(Some(token.clone())),
+            Token::File(path) => std::fs::read_to_string(path)
+                .map(|t| Some(t.trim().to_string()))
+                .map_err(|e| KubeError::Config(format!("token file {} cannot be read: {}", path.display(), e))),
+            Token::None => Ok(None),
+        }
+    }
+}
+
+// Only the fields FlowScope reads from the API's objects
+
+#[derive(Debug, Deserialize)]
+struct List<T> {
+    items: Vec<T>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectMeta {
+    name: String,
+    #[serde(default)]
+    namespace: String,
+    #[serde(default)]
+    uid: String,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    creation_timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pod {
+    metadata: ObjectMeta,
+    #[serde(default)]
+    spec: PodSpec,
+    #[serde(default)]
+    status: PodStatus,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PodSpec {
+    #[serde(default)]
+    containers: Vec<PodContainer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PodContainer {
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    ports: Vec<ContainerPort>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContainerPort {
+    container_port: u16,
+    host_port: Option<u16>,
+    protocol: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PodStatus {
+    phase: Option<String>,
+    #[serde(default)]
+    container_statuses: Vec<PodContainerStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PodContainerStatus {
+    #[serde(default)]
+    ready: bool,
+    #[serde(default)]
+    state: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Deployment {
+    metadata: ObjectMeta,
+    spec: DeploymentSpec,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeploymentSpec {
+    selector: LabelSelector,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelSelector {
+    #[serde(default)]
+    match_labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Service {
+    metadata: ObjectMeta,
+    #[serde(default)]
+    spec: ServiceSpec,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ServiceSpec {
+    #[serde(default)]
+    selector: HashMap<String, String>,
+    #[serde(default)]
+    ports: Vec<ServicePort>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServicePort {
+    port: u16,
+    protocol: Option<String>,
+}
+
+/// Whether `selector` picks an object with `labels`; an empty selector picks
+/// nothing, as for a service without one
+fn selects(selector: &HashMap<String, String>, labels: &HashMap<String, String>) -> bool {
+    !selector.is_empty() && selector.iter().all(|(k, v)| labels.get(k) == Some(v))
+}
+
+/// Name pods and groups go by across namespaces
+fn qualified(name: &str, namespace: &str) -> String {
+    format!("{}.{}", name, namespace)
+}
+
+fn pod_status(status: &PodStatus) -> (ContainerStatus, Option<String>) {
+    match status.phase.as_deref() {
+        Some("Running") => {
+            let crashing = status
+                .container_statuses
+                .iter()
+                .any(|c| c.state.get("waiting").and_then(|w| w.get("reason")).and_then(|r| r.as_str()) == Some("CrashLoopBackOff"));
+            if crashing {
+                (ContainerStatus::Restarting, None)
+            } else if status.container_statuses.iter().all(|c| c.ready) {
+                (ContainerStatus::Healthy, Some("healthy".to_string()))
+            } else {
+                (ContainerStatus::Unhealthy, Some("unhealthy".to_string()))
+            }
+        }
+        Some("Pending") => (ContainerStatus::Created, None),
+        Some("Succeeded") => (ContainerStatus::Exited, None),
+        _ => (ContainerStatus::Dead, None),
+    }
+}
+
+/// How a group of pods is doing as a whole
+fn group_status<'a>(containers: impl IntoIterator<Item = &'a ContainerInfo>) -> ContainerStatus {
+    let statuses: Vec<&ContainerStatus> = containers.into_iter().map(|c| &c.status).collect();
+    if statuses.iter().any(|s| matches!(s, ContainerStatus::Unhealthy | ContainerStatus::Restarting | ContainerStatus::Dead)) {
+        ContainerStatus::Unhealthy
+    } else if !statuses.is_empty() && statuses.iter().all(|s| **s == ContainerStatus::Healthy) {
+        ContainerStatus::Healthy
+    } else if statuses.iter().any(|s| matches!(s, ContainerStatus::Running | ContainerStatus::Healthy)) {
+        ContainerStatus::Running
+    } else {
+        ContainerStatus::Exited
+    }
+}
+
+fn connection(source: &str, target: &str, label: Option<String>) -> FlowchartConnection {
+    FlowchartConnection {
+        id: format!("{}-to-{}", source, target),
+        source: source.to_string(),
+        target: target.to_string(),
+        label,
+        connection_type: ConnectionType::Network,
+    }
+}
+
+/// Pods, deployments and services as listed at one moment
+pub struct Cluster {
+    pods: Vec<Pod>,
+    deployments: Vec<Deployment>,
+    services: Vec<Service>,
+}
+
+impl Cluster {
+    fn deployment_of(&self, pod: &Pod) -> Option<&Deployment> {
+        self.deployments.iter().find(|d| {
+            d.metadata.namespace == pod.metadata.namespace && selects(&d.spec.selector.match_labels, &pod.metadata.labels)
+        })
+    }
+
+    fn services_of<'a>(&'a self, pod: &'a Pod) -> impl Iterator<Item = &'a Service> + 'a {
+        self.services
+            .iter()
+            .filter(move |s| s.metadata.namespace == pod.metadata.namespace && selects(&s.spec.selector, &pod.metadata.labels))
+    }
+
+    fn namespaces(&self) -> Vec<&str> {
+        let mut namespaces: Vec<&str> = self
+            .pods
+            .iter()
+            .map(|p| p.metadata.namespace.as_str())
+            .chain(self.services.iter().map(|s| s.metadata.namespace.as_str()))
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        namespaces
+    }
+
+    /// Every pod as a container, sorted by name
+    pub fn containers(&self, categorizers: &Categorizers) -> Vec<ContainerInfo> {
+        let mut containers: Vec<ContainerInfo> = self.pods.iter().map(|pod| self.container(pod, categorizers)).collect();
+        containers.sort_by(|a, b| a.name.cmp(&b.name));
+        containers
+    }
+
+    fn container(&self, pod: &Pod, categorizers: &Categorizers) -> ContainerInfo {
+        let ObjectMeta { name, namespace, uid, .. } = &pod.metadata;
+        let name = qualified(name, namespace);
+        let image = pod.spec.containers.first().map(|c| c.image.clone()).unwrap_or_default();
+        let (status, health) = pod_status(&pod.status);
+        let ports = pod
+            .spec
+            .containers
+            .iter()
+            .flat_map(|c| &c.ports)
+            .map(|p| PortMapping {
+                host_port: p.host_port,
+                container_port: p.container_port,
+                protocol: p.protocol.as_deref().unwrap_or("TCP").to_lowercase(),
+            })
+            .collect();
+
+        let mut labels = pod.metadata.labels.clone();
+        labels.insert(NAMESPACE_LABEL.to_string(), namespace.clone());
+        labels.insert(POD_LABEL.to_string(), pod.metadata.name.clone());
+        if let Some(deployment) = self.deployment_of(pod) {
+            labels.insert(DEPLOYMENT_LABEL.to_string(), deployment.metadata.name.clone());
+        }
+
+        let category = categorizers.categorize(Subject {
+            name: &name,
+            image: &image,
+            labels: &labels,
+        });
+        let memberships = vec![
+            GroupMembership {
+                kind: MembershipKind::Category,
+                name: format!("{:?}", category).to_lowercase(),
+                via: None,
+            },
+            GroupMembership {
+                kind: MembershipKind::Network,
+                name: namespace.clone(),
+                via: None,
+            },
+        ];
+
+        ContainerInfo {
+            id: if uid.is_empty() { name.clone() } else { uid.clone() },
+            name,
+            image,
+            status,
+            health,
+            category,
+            ports,
+            networks: vec![namespace.clone()],
+            created: pod.metadata.creation_timestamp.unwrap_or_else(Utc::now),
+            labels,
+            host: None,
+            rust_equivalent: None,
+            stats: None,
+            image_size_mb: None,
+            memberships,
+            volume_names: Vec::new(),
+            last_started: None,
+            last_status_change: None,
+            last_image_change: None,
+            last_config_change: None,
+            probe: None,
+            vulnerabilities: None,
+        }
+    }
+
+    /// Counts over `containers` and a flowchart per namespace
+    pub fn topology(&self, containers: &[ContainerInfo]) -> SystemTopology {
+        let mut categories: HashMap<String, usize> = HashMap::new();
+        for container in containers {
+            *categories.entry(format!("{:?}", container.category).to_lowercase()).or_insert(0) += 1;
+        }
+
+        let mut flowcharts = vec![FlowchartSummary {
+            id: "system-overview".to_string(),
+            name: "Cluster Overview".to_string(),
+            node_count: self.namespaces().len(),
+            category: ServiceCategory::Other,
+        }];
+        for namespace in self.namespaces() {
+            flowcharts.push(FlowchartSummary {
+                id: format!("namespace:{}", namespace),
+                name: format!("{} namespace", namespace),
+                node_count: containers.iter().filter(|c| c.networks.iter().any(|n| n == namespace)).count(),
+                category: ServiceCategory::Other,
+            });
+        }
+
+        SystemTopology {
+            total_containers: containers.len(),
+            running_containers: containers
+                .iter()
+                .filter(|c| matches!(c.status, ContainerStatus::Running | ContainerStatus::Healthy))
+                .count(),
+            healthy_containers: containers.iter().filter(|c| c.status == ContainerStatus::Healthy).count(),
+            unhealthy_containers: containers.iter().filter(|c| c.status == ContainerStatus::Unhealthy).count(),
+            categories,
+            flowcharts,
+            generated_at: Utc::now(),
+            host: None,
+        }
+    }
+
+    /// The flowchart with `id`: `system-overview`, `namespace:<ns>`,
+    /// `deployment:<name>.<ns>` or a pod's `<name>.<ns>` or uid
+    pub fn flowchart(&self, id: &str, containers: &[ContainerInfo]) -> Option<Flowchart> {
+        let by_pod: HashMap<(&str, &str), &ContainerInfo> = self
+            .pods
+            .iter()
+            .filter_map(|p| {
+                let name = qualified(&p.metadata.name, &p.metadata.namespace);
+                let container = containers.iter().find(|c| c.name == name)?;
+                Some(((p.metadata.name.as_str(), p.metadata.namespace.as_str()), container))
+            })
+            .collect();
+        let container_of = |pod: &Pod| by_pod.get(&(pod.metadata.name.as_str(), pod.metadata.namespace.as_str())).copied();
+
+        if id == "system-overview" {
+            return Some(self.overview_flowchart(&container_of));
+        }
+        if let Some(namespace) = id.strip_prefix("namespace:") {
+            return self.namespace_flowchart(namespace, &container_of);
+        }
+        if let Some(qualified_name) = id.strip_prefix("deployment:") {
+            return self.deployment_flowchart(qualified_name, &container_of);
+        }
+        let pod = self.pods.iter().find(|p| {
+            p.metadata.uid == id || qualified(&p.metadata.name, &p.metadata.namespace) == id
+        })?;
+        Some(self.pod_flowchart(pod, container_of(pod)?, &container_of))
+    }
+
+    fn overview_flowchart<'a>(&'a self, container_of: &impl Fn(&Pod) -> Option<&'a ContainerInfo>) -> Flowchart {
+        let nodes = self
+            .namespaces()
+            .into_iter()
+            .map(|namespace| {
+                let pods: Vec<&ContainerInfo> = self
+                    .pods
+                    .iter()
+                    .filter(|p| p.metadata.namespace == namespace)
+                    .filter_map(container_of)
+                    .collect();
+                let services = self.services.iter().filter(|s| s.metadata.namespace == namespace).count();
+                let id = format!("namespace:{}", namespace);
+                FlowchartNode {
+                    id: id.clone(),
+                    name: namespace.to_string(),
+                    description: format!("{} pods, {} services", pods.len(), services),
+                    status: group_status(pods.iter().copied()),
+                    node_type: NodeType::Group,
+                    category: ServiceCategory::Other,
+                    port: None,
+                    child_flowchart: Some(id),
+                    metrics: None,
+                    stats: None,
+                    warnings: Vec::new(),
+                    probe: None,
+                    vulnerabilities: None,
+                    position: None,
+                }
+            })
+            .collect();
+        Flowchart {
+            id: "system-overview".to_string(),
+            name: "Cluster Overview".to_string(),
+            description: "Namespaces in the cluster".to_string(),
+            nodes,
+            connections: Vec::new(),
+            parent_id: None,
+        }
+    }
+
+    fn namespace_flowchart<'a>(
+        &'a self,
+        namespace: &str,
+        container_of: &impl Fn(&Pod) -> Option<&'a ContainerInfo>,
+    ) -> Option<Flowchart> {
+        if !self.namespaces().contains(&namespace) {
+            return None;
+        }
+        let pods: Vec<&Pod> = self.pods.iter().filter(|p| p.metadata.namespace == namespace).collect();
+
+        // Pods of a deployment are shown as the deployment
+        let mut nodes = Vec::new();
+        let mut node_of: HashMap<&str, String> = HashMap::new();
+        let mut deployments: BTreeMap<&str, Vec<&ContainerInfo>> = BTreeMap::new();
+        for pod in &pods {
+            let Some(container) = container_of(pod) else { continue };
+            match self.deployment_of(pod) {
+                Some(deployment) => {
+                    let name = deployment.metadata.name.as_str();
+                    deployments.entry(name).or_default().push(container);
+                    node_of.insert(&pod.metadata.name, format!("deployment:{}", qualified(name, namespace)));
+                }
+                None => {
+                    nodes.push(pod_node(container));
+                    node_of.insert(&pod.metadata.name, container.id.clone());
+                }
+            }
+        }
+        for (name, members) in deployments {
+            let id = format!("deployment:{}", qualified(name, namespace));
+            nodes.push(FlowchartNode {
+                id: id.clone(),
+                name: name.to_string(),
+                description: format!("Deployment, {} pods", members.len()),
+                status: group_status(members.iter().copied()),
+                node_type: NodeType::Group,
+                category: members[0].category.clone(),
+                port: None,
+                child_flowchart: Some(id),
+                metrics: None,
+                stats: None,
+                warnings: Vec::new(),
+                probe: None,
+                vulnerabilities: None,
+                position: None,
+            });
+        }
+
+        let mut connections = Vec::new();
+        for service in self.services.iter().filter(|s| s.metadata.namespace == namespace) {
+            let selected: Vec<&&Pod> = pods.iter().filter(|p| selects(&service.spec.selector, &p.metadata.labels)).collect();
+            let node = service_node(service, selected.first().and_then(|p| container_of(p)));
+            let mut targets: Vec<&String> = selected.iter().filter_map(|p| node_of.get(p.metadata.name.as_str())).collect();
+            targets.sort();
+            targets.dedup();
+            for target in targets {
+                connections.push(connection(&node.id, target, service_label(service)));
+            }
+            nodes.push(node);
+        }
+
+        Some(Flowchart {
+            id: format!("namespace:{}", namespace),
+            name: format!("{} namespace", namespace),
+            description: format!("Deployments, pods and services in {}", namespace),
+            nodes,
+            connections,
+            parent_id: Some("system-overview".to_string()),
+        })
+    }
+
+    fn deployment_flowchart<'a>(
+        &'a self,
+        qualified_name: &str,
+        container_of: &impl Fn(&Pod) -> Option<&'a ContainerInfo>,
+    ) -> Option<Flowchart> {
+        let deployment = self
+            .deployments
+            .iter()
+            .find(|d| qualified(&d.metadata.name, &d.metadata.namespace) == qualified_name)?;
+        let namespace = &deployment.metadata.namespace;
+        let pods: Vec<&Pod> = self
+            .pods
+            .iter()
+            .filter(|p| &p.metadata.namespace == namespace && selects(&deployment.spec.selector.match_labels, &p.metadata.labels))
+            .collect();
+
+        let (mut nodes, connections) = self.pods_with_services(&pods, container_of);
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        Some(Flowchart {
+            id: format!("deployment:{}", qualified_name),
+            name: deployment.metadata.name.clone(),
+            description: format!("Pods of the {} deployment and the services in front of them", deployment.metadata.name),
+            nodes,
+            connections,
+            parent_id: Some(format!("namespace:{}", namespace)),
+        })
+    }
+
+    fn pod_flowchart<'a>(
+        &'a self,
+        pod: &Pod,
+        container: &ContainerInfo,
+        container_of: &impl Fn(&Pod) -> Option<&'a ContainerInfo>,
+    ) -> Flowchart {
+        let (mut nodes, connections) = self.pods_with_services(&[pod], container_of);
+        if let Some(node) = nodes.iter_mut().find(|n| n.id == container.id) {
+            node.child_flowchart = None;
+        }
+        let parent = match self.deployment_of(pod) {
+            Some(deployment) => format!("deployment:{}", qualified(&deployment.metadata.name, &deployment.metadata.namespace)),
+            None => format!("namespace:{}", pod.metadata.namespace),
+        };
+        Flowchart {
+            id: container.name.clone(),
+            name: container.name.clone(),
+            description: format!("Pod {} and the services selecting it", container.name),
+            nodes,
+            connections,
+            parent_id: Some(parent),
+        }
+    }
+
+    /// Nodes for `pods` and every service selecting one of them, with a link
+    /// from each service to the pods it selects
+    fn pods_with_services<'a>(
+        &'a self,
+        pods: &[&Pod],
+        container_of: &impl Fn(&Pod) -> Option<&'a ContainerInfo>,
+    ) -> (Vec<FlowchartNode>, Vec<FlowchartConnection>) {
+        let mut nodes = Vec::new();
+        let mut connections = Vec::new();
+        let mut services: Vec<&Service> = Vec::new();
+        for pod in pods {
+            let Some(container) = container_of(pod) else { continue };
+            nodes.push(pod_node(container));
+            for service in self.services_of(pod) {
+                let id = format!("service:{}", qualified(&service.metadata.name, &service.metadata.namespace));
+                connections.push(connection(&id, &container.id, service_label(service)));
+                if !services.iter().any(|s| std::ptr::eq(*s, service)) {
+                    services.push(service);
+                }
+            }
+        }
+        for service in services {
+            let first = pods.iter().find(|p| selects(&service.spec.selector, &p.metadata.labels));
+            nodes.push(service_node(service, first.and_then(|p| container_of(p))));
+        }
+        (nodes, connections)
+    }
+}
+
+fn pod_node(container: &ContainerInfo) -> FlowchartNode {
+    FlowchartNode {
+        id: container.id.clone(),
+        name: container.name.clone(),
+        description: format!("Image: {}", container.image),
+        status: container.status.clone(),
+        node_type: NodeType::Service,
+        category: container.category.clone(),
+        port: container.ports.first().map(|p| p.host_port.unwrap_or(p.container_port)),
+        child_flowchart: Some(container.name.clone()),
+        metrics: None,
+        stats: None,
+        warnings: Vec::new(),
+        probe: None,
+        vulnerabilities: None,
+        position: None,
+    }
+}
+
+/// A service, in the category of a pod it selects
+fn service_node(service: &Service, selected: Option<&ContainerInfo>) -> FlowchartNode {
+    let warnings = match (service.spec.selector.is_empty(), selected) {
+        (false, None) => vec!["selects no pods".to_string()],
+        _ => Vec::new(),
+    };
+    FlowchartNode {
+        id: format!("service:{}", qualified(&service.metadata.name, &service.metadata.namespace)),
+        name: service.metadata.name.clone(),
+        description: "Service".to_string(),
+        status: selected.map_or(ContainerStatus::Created, |_| ContainerStatus::Running),
+        node_type: NodeType::Process,
+        category: selected.map_or(ServiceCategory::Other, |c| c.category.clone()),
+        port: service.spec.ports.first().map(|p| p.port),
+        child_flowchart: None,
+        metrics: None,
+        stats: None,
+        warnings,
+        probe: None,
+        vulnerabilities: None,
+        position: None,
+    }
+}
+
+/// The service's ports, e.g. `80/tcp, 443/tcp`
+fn service_label(service: &Service) -> Option<String> {
+    let ports: Vec<String> = service
+        .spec
+        .ports
+        .iter()
+        .map(|p| format!("{}/{}", p.port, p.protocol.as_deref().unwrap_or("TCP").to_lowercase()))
+        .collect();
+    Some(ports.join(", ")).filter(|l| !l.is_empty())
+}
+
+/// Discovery against a Kubernetes API server
+pub struct KubernetesDiscovery {
+    config: KubeConfig,
+    http: reqwest::Client,
+    categorizers: Categorizers,
+}
+
+impl KubernetesDiscovery {
+    pub fn new(config: KubeConfig) -> Result<Self, KubeError> {
+        let mut http = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
+        if let Some(ca) = &config.ca {
+            http = http.add_root_certificate(reqwest::Certificate::from_pem(ca)?);
+        }
+        Ok(Self {
+            config,
+            http: http.build()?,
+            categorizers: Categorizers::default(),
+        })
+    }
+
+    pub fn from_env() -> Result<Self, KubeError> {
+        Self::new(KubeConfig::from_env()?)
+    }
+
+    /// Use `categorizers` instead of the default strategies
+    pub fn with_categorizers(mut self, categorizers: Categorizers) -> Self {
+        self.categorizers = categorizers;
+        self
+    }
+
+    pub fn config(&self) -> &KubeConfig {
+        &self.config
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, KubeError> {
+        let mut request = self.http.get(format!("{}{}", self.config.api, path));
+        if let Some(token) = self.config.bearer()? {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(str::to_string))
+                .unwrap_or(body);
+            return Err(KubeError::Status {
+                status: status.as_u16(),
+                path: path.to_string(),
+                message,
+            });
+        }
+        Ok(response.json().await?)
+    }
+
+    /// `resource` under `group`, in the configured namespace or all of them
+    fn path(&self, group: &str, resource: &str) -> String {
+        match &self.config.namespace {
+            Some(namespace) => format!("{}/namespaces/{}/{}", group, namespace, resource),
+            None => format!("{}/{}", group, resource),
+        }
+    }
+
+    /// The API server's version, to check that it can be reached
+    pub async fn version(&self) -> Result<String, KubeError> {
+        let version: serde_json::Value = self.get("/version").await?;
+        Ok(version.get("gitVersion").and_then(|v| v.as_str()).unwrap_or("unknown").to_string())
+    }
+
+    /// List pods, deployments and services
+    pub async fn cluster(&self) -> Result<Cluster, KubeError> {
+        let paths = [
+            self.path("/api/v1", "pods"),
+            self.path("/apis/apps/v1", "deployments"),
+            self.path("/api/v1", "services"),
+        ];
+        let (pods, deployments, services) = tokio::try_join!(
+            self.get::<List<Pod>>(&paths[0]),
+            self.get::<List<Deployment>>(&paths[1]),
+            self.get::<List<Service>>(&paths[2]),
+        )?;
+        Ok(Cluster {
+            pods: pods.items,
+            deployments: deployments.items,
+            services: services.items,
+        })
+    }
+
+    pub async fn list_containers(&self) -> Result<Vec<ContainerInfo>, KubeError> {
+        Ok(self.cluster().await?.containers(&self.categorizers))
+    }
+
+    pub async fn get_topology(&self) -> Result<SystemTopology, KubeError> {
+        let cluster = self.cluster().await?;
+        let containers = cluster.containers(&self.categorizers);
+        Ok(cluster.topology(&containers))
+    }
+
+    pub async fn generate_flowchart(&self, id: &str) -> Result<Option<Flowchart>, KubeError> {
+        let cluster = self.cluster().await?;
+        let containers = cluster.containers(&self.categorizers);
+        Ok(cluster.flowchart(id, &containers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cluster() -> Cluster {
+        let pod = |name: &str, app: &str, phase: &str, ready: bool| {
+            json!({
+                "metadata": {
+                    "name": name,
+                    "namespace": "shop",
+                    "uid": format!("uid-{}", name),
+                    "labels": {"app": app},
+                    "creationTimestamp": "2025-12-01T10:00:00Z"
+                },
+                "spec": {"containers": [{"image": format!("shop/{}:1.0", app), "ports": [{"containerPort": 8080}]}]},
+                "status": {"phase": phase, "containerStatuses": [{"ready": ready, "state": {"running": {}}}]}
+            })
+        };
+        let pods = json!({"items": [
+            pod("api-7d9f-abcde", "api", "Running", true),
+            pod("api-7d9f-fghij", "api", "Running", false),
+            pod("migrate", "migrate", "Succeeded", false),
+        ]});
+        let deployments = json!({"items": [{
+            "metadata": {"name": "api", "namespace": "shop"},
+            "spec": {"selector": {"matchLabels": {"app": "api"}}}
+        }]});
+        let services = json!({"items": [
+            {"metadata": {"name": "api", "namespace": "shop"}, "spec": {"selector": {"app": "api"}, "ports": [{"port": 80}]}},
+            {"metadata": {"name": "orphan", "namespace": "shop"}, "spec": {"selector": {"app": "gone"}}}
+        ]});
+        Cluster {
+            pods: serde_json::from_value::<List<Pod>>(pods).unwrap().items,
+            deployments: serde_json::from_value::<List<Deployment>>(deployments).unwrap().items,
+            services: serde_json::from_value::<List<Service>>(services).unwrap().items,
+        }
+    }
+
+    #[test]
+    fn maps_pods_deployments_and_services() {
+        let cluster = cluster();
+        let containers = cluster.containers(&Categorizers::default());
+        let names: Vec<&str> = containers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["api-7d9f-abcde.shop", "api-7d9f-fghij.shop", "migrate.shop"]);
+        let statuses: Vec<&ContainerStatus> = containers.iter().map(|c| &c.status).collect();
+        assert_eq!(statuses, [&ContainerStatus::Healthy, &ContainerStatus::Unhealthy, &ContainerStatus::Exited]);
+        assert_eq!(containers[0].labels[DEPLOYMENT_LABEL], "api");
+        assert_eq!(containers[0].networks, ["shop"]);
+        assert_eq!(containers[0].ports[0].container_port, 8080);
+
+        let topology = cluster.topology(&containers);
+        assert_eq!((topology.total_containers, topology.healthy_containers), (3, 1));
+        assert_eq!(topology.flowcharts[1].id, "namespace:shop");
+
+        let namespace = cluster.flowchart("namespace:shop", &containers).unwrap();
+        let nodes: Vec<&str> = namespace.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(nodes, ["uid-migrate", "deployment:api.shop", "service:api.shop", "service:orphan.shop"]);
+        assert_eq!(namespace.connections.len(), 1);
+        assert_eq!(namespace.connections[0].target, "deployment:api.shop");
+        assert_eq!(namespace.connections[0].label.as_deref(), Some("80/tcp"));
+        assert_eq!(namespace.nodes[3].warnings, ["selects no pods"]);
+
+        let deployment = cluster.flowchart("deployment:api.shop", &containers).unwrap();
+        assert_eq!(deployment.nodes.len(), 3);
+        assert_eq!(deployment.connections.len(), 2);
+
+        let pod = cluster.flowchart("api-7d9f-abcde.shop", &containers).unwrap();
+        assert_eq!(pod.parent_id.as_deref(), Some("deployment:api.shop"));
+        assert!(cluster.flowchart("namespace:missing", &containers).is_none());
+    }
+
+    #[test]
+    fn config_from_the_environment() {
+        let none = Path::new("/nonexistent/serviceaccount");
+        let env: HashMap<&str, &str> = HashMap::from([("KUBERNETES_SERVICE_HOST", "10.0.0.1")]);
+        let config = KubeConfig::from_vars(&|name| env.get(name).map(|v| v.to_string()), none).unwrap();
+        assert_eq!(config.api, "https://10.0.0.1:443");
+        assert!(config.namespace.is_none());
+
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("FLOWSCOPE_KUBE_API", "http://127.0.0.1:8001/"),
+            ("FLOWSCOPE_KUBE_NAMESPACE", "shop"),
+        ]);
+        let config = KubeConfig::from_vars(&|name| env.get(name).map(|v| v.to_string()), none).unwrap();
+        assert_eq!(config.api, "http://127.0.0.1:8001");
+        assert_eq!(config.namespace.as_deref(), Some("shop"));
+        assert!(config.bearer().unwrap().is_none());
+
+        let env: HashMap<&str, &str> = HashMap::new();
+        assert!(KubeConfig::from_vars(&|name| env.get(name).map(|v| v.to_string()), none).is_err());
+    }
+
+    fn status(value: serde_json::Value) -> PodStatus {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn pod_phases_and_readiness_give_the_status() {
+        let crashing = status(json!({
+            "phase": "Running",
+            "containerStatuses": [{"ready": false, "state": {"waiting": {"reason": "CrashLoopBackOff"}}}]
+        }));
+        assert_eq!(pod_status(&crashing), (ContainerStatus::Restarting, None));
+        let ready = status(json!({"phase": "Running", "containerStatuses": [{"ready": true}, {"ready": true}]}));
+        assert_eq!(pod_status(&ready), (ContainerStatus::Healthy, Some("healthy".to_string())));
+        assert_eq!(pod_status(&status(json!({"phase": "Pending"}))).0, ContainerStatus::Created);
+        assert_eq!(pod_status(&status(json!({"phase": "Failed"}))).0, ContainerStatus::Dead);
+        assert_eq!(pod_status(&PodStatus::default()).0, ContainerStatus::Dead);
+
+        let containers = cluster().containers(&Categorizers::default());
+        // One healthy and one unready api pod
+        assert_eq!(group_status(&containers[..2]), ContainerStatus::Unhealthy);
+        assert_eq!(group_status(&containers[..1]), ContainerStatus::Healthy);
+        assert_eq!(group_status(&containers[2..]), ContainerStatus::Exited);
+        assert_eq!(group_status(&[]), ContainerStatus::Exited);
+    }
+
+    #[test]
+    fn empty_selectors_select_nothing() {
+        let labels = HashMap::from([("app".to_string(), "api".to_string())]);
+        assert!(selects(&labels.clone(), &labels));
+        assert!(!selects(&HashMap::new(), &labels));
+        assert!(!selects(&HashMap::from([("app".to_string(), "web".to_string())]), &labels));
+    }
+
+    #[test]
+    fn overview_groups_pods_by_namespace() {
+        let cluster = cluster();
+        let containers = cluster.containers(&Categorizers::default());
+        let overview = cluster.flowchart("system-overview", &containers).unwrap();
+        assert_eq!(overview.nodes.len(), 1);
+        assert_eq!(overview.nodes[0].id, "namespace:shop");
+        assert_eq!(overview.nodes[0].description, "3 pods, 2 services");
+        assert_eq!(overview.nodes[0].child_flowchart.as_deref(), Some("namespace:shop"));
+
+        // Pods are found by uid as well as by name
+        assert!(cluster.flowchart("uid-migrate", &containers).is_some());
+        assert!(cluster.flowchart("deployment:missing.shop", &containers).is_none());
+    }
+
+    #[tokio::test]
+    async fn lists_the_namespace_through_the_api_server() {
+        use axum::{extract::Path as UrlPath, http::HeaderMap, routing::get, Router};
+
+        let app = Router::new()
+            .route(
+                "/api/v1/namespaces/:namespace/:resource",
+                get(|UrlPath((namespace, resource)): UrlPath<(String, String)>, headers: HeaderMap| async move {
+                    assert_eq!(namespace, "shop");
+                    assert_eq!(headers["authorization"], "Bearer s3cret");
+                    match resource.as_str() {
+                        "pods" => axum::Json(json!({"items": [{
+                            "metadata": {"name": "api-1", "namespace": "shop", "labels": {"app": "api"}},
+                            "spec": {"containers": [{"image": "shop/api:1.0"}]},
+                            "status": {"phase": "Running", "containerStatuses": [{"ready": true}]}
+                        }]})),
+                        _ => axum::Json(json!({"items": []})),
+                    }
+                }),
+            )
+            .route(
+                "/apis/apps/v1/namespaces/:namespace/deployments",
+                get(|| async {
+                    (
+                        axum::http::StatusCode::FORBIDDEN,
+                        axum::Json(json!({"message": "deployments is forbidden"})),
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let env: HashMap<&str, String> = HashMap::from([
+            ("FLOWSCOPE_KUBE_API", api),
+            ("FLOWSCOPE_KUBE_TOKEN", "s3cret".to_string()),
+            ("FLOWSCOPE_KUBE_NAMESPACE", "shop".to_string()),
+        ]);
+        let config = KubeConfig::from_vars(&|name| env.get(name).cloned(), Path::new("/nonexistent")).unwrap();
+        let discovery = KubernetesDiscovery::new(config).unwrap();
+
+        // Every listing must succeed; the API's own message is reported
+        match discovery.list_containers().await {
+            Err(KubeError::Status { status, path, message }) => {
+                assert_eq!(status, 403);
+                assert_eq!(path, "/apis/apps/v1/namespaces/shop/deployments");
+                assert_eq!(message, "deployments is forbidden");
+            }
+            other => panic!("expected a status error, got {:?}", other.map(|c| c.len())),
+        }
+
+        let pods = discovery.get::<List<Pod>>(&discovery.path("/api/v1", "pods")).await.unwrap();
+        assert_eq!(pods.items[0].metadata.name, "api-1");
+    }
+}
@@ -0,0 +1,236 @@
+//! Stable flowchart layouts
+//!
+//! Flowcharts are regenerated on every refresh, and laying them out from
+//! scratch each time moved every node whenever a container came or went. The
+//! last layout of each flowchart id is kept in `layouts.json`: nodes seen
+//! before keep their place in the node order and their suggested position,
+//! and only new nodes are placed, on the free grid cell closest to the nodes
+//! they are connected to. A removed node just frees its cell.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    models::{Flowchart, NodePosition},
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+/// Grid pitch, matching the frontend's default node size plus gaps
+pub const CELL_WIDTH: f64 = 320.0;
+pub const CELL_HEIGHT: f64 = 220.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cell {
+    pub col: i64,
+    pub row: i64,
+}
+
+/// Node order and grid cells of a flowchart as last served
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedLayout {
+    pub order: Vec<String>,
+    pub cells: BTreeMap<String, Cell>,
+}
+
+/// Order and position `flowchart`'s nodes, keeping everything `previous`
+/// already placed where it was, and return the resulting layout
+pub fn arrange(flowchart: &mut Flowchart, previous: Option<&SavedLayout>) -> SavedLayout {
+    let previous = previous.cloned().unwrap_or_default();
+    let rank = |id: &str| previous.order.iter().position(|o| o == id).unwrap_or(usize::MAX);
+    // Stable, so new nodes follow the known ones in generated order
+    flowchart.nodes.sort_by_key(|n| rank(&n.id));
+
+    let mut cells: BTreeMap<String, Cell> = previous
+        .cells
+        .into_iter()
+        .filter(|(id, _)| flowchart.nodes.iter().any(|n| &n.id == id))
+        .collect();
+    let count = flowchart.nodes.len() as i64;
+    let cols = ((count as f64).sqrt().ceil() as i64).max(1);
+
+    for node in &flowchart.nodes {
+        if cells.contains_key(&node.id) {
+            continue;
+        }
+        let neighbours: Vec<Cell> = flowchart
+            .connections
+            .iter()
+            .filter_map(|c| match (c.source == node.id, c.target == node.id) {
+                (true, false) => cells.get(&c.target),
+                (false, true) => cells.get(&c.source),
+                _ => None,
+            })
+            .copied()
+            .collect();
+        let rows = cells.values().map(|c| c.row + 1).max().unwrap_or(0).max(count / cols) + 1;
+        let distance = |cell: Cell| -> i64 {
+            neighbours
+                .iter()
+                .map(|n| (n.col - cell.col).pow(2) + (n.row - cell.row).pow(2))
+                .sum()
+        };
+        let cell = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| Cell { col, row }))
+            .filter(|cell| !cells.values().any(|c| c == cell))
+            .min_by_key(|cell| (distance(*cell), cell.row, cell.col))
+            .expect("the grid has more cells than nodes");
+        cells.insert(node.id.clone(), cell);
+    }
+
+    for node in &mut flowchart.nodes {
+        let cell = cells[&node.id];
+        node.position = Some(NodePosition {
+            x: cell.col as f64 * CELL_WIDTH,
+            y: cell.row as f64 * CELL_HEIGHT,
+        });
+    }
+    SavedLayout {
+        order: flowchart.nodes.iter().map(|n| n.id.clone()).collect(),
+        cells,
+    }
+}
+
+/// Last layout of every flowchart served, persisted across restarts
+pub struct LayoutStore {
+    layouts: JsonStore<SavedLayout>,
+}
+
+impl LayoutStore {
+    pub fn open(file_name: &str) -> Self {
+        Self {
+            layouts: JsonStore::open(file_name),
+        }
+    }
+
+    /// Lay out `flowchart` from its previous layout, saving the result when it changed
+    pub fn apply(&self, flowchart: &mut Flowchart) {
+        let previous = self.layouts.get(&flowchart.id);
+        let layout = arrange(flowchart, previous.as_ref());
+        if previous.as_ref() != Some(&layout) {
+            if let Err(e) = self.layouts.put(&flowchart.id, layout) {
+                warn!("Failed to save layout of '{}': {}", flowchart.id, e);
+            }
+        }
+    }
+
+    /// Forget a flowchart's layout; returns whether there was one
+    pub fn reset(&self, id: &str) -> Result<bool, StoreError> {
+        Ok(self.layouts.remove(id)?.is_some())
+    }
+}
+
+/// DELETE /api/flowchart/:id/layout - Lay the flowchart out afresh on its next refresh
+pub async fn reset_layout(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.layouts.reset(&id) {
+        Ok(true) => {
+            info!("Reset layout of '{}'", id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("no saved layout for '{}'", id) })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "Failed to reset layout",
+                "details": e.to_string()
+            })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConnectionType, ContainerStatus, FlowchartConnection, FlowchartNode, NodeType, ServiceCategory};
+
+    fn node(id: &str) -> FlowchartNode {
+        FlowchartNode {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            status: ContainerStatus::Running,
+            node_type: NodeType::Service,
+            category: ServiceCategory::Application,
+            port: None,
+            child_flowchart: None,
+            metrics: None,
+            stats: None,
+            warnings: Vec::new(),
+            probe: None,
+            vulnerabilities: None,
+            position: None,
+        }
+    }
+
+    fn flowchart(ids: &[&str], links: &[(&str, &str)]) -> Flowchart {
+        Flowchart {
+            id: "application-overview".to_string(),
+            name: "Application Services".to_string(),
+            description: String::new(),
+            nodes: ids.iter().map(|id| node(id)).collect(),
+            connections: links
+                .iter()
+                .map(|(source, target)| FlowchartConnection {
+                    id: format!("{}-to-{}", source, target),
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    label: None,
+                    connection_type: ConnectionType::Network,
+                })
+                .collect(),
+            parent_id: None,
+        }
+    }
+
+    fn placed(flowchart: &Flowchart) -> Vec<(String, f64, f64)> {
+        flowchart
+            .nodes
+            .iter()
+            .map(|n| {
+                let p = n.position.unwrap();
+                (n.id.clone(), p.x / CELL_WIDTH, p.y / CELL_HEIGHT)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn known_nodes_stay_put() {
+        let mut first = flowchart(&["a", "b", "c", "d"], &[]);
+        let layout = arrange(&mut first, None);
+        let at = |id: &str, x: f64, y: f64| (id.to_string(), x, y);
+        assert_eq!(placed(&first), [at("a", 0.0, 0.0), at("b", 1.0, 0.0), at("c", 0.0, 1.0), at("d", 1.0, 1.0)]);
+
+        // b goes away, e arrives linked to d and is generated first
+        let mut second = flowchart(&["e", "a", "c", "d"], &[("e", "d")]);
+        let layout = arrange(&mut second, Some(&layout));
+        assert_eq!(placed(&second), [at("a", 0.0, 0.0), at("c", 0.0, 1.0), at("d", 1.0, 1.0), at("e", 1.0, 0.0)]);
+
+        // Nothing changed, nothing moves
+        let mut third = flowchart(&["a", "c", "d", "e"], &[("e", "d")]);
+        assert_eq!(arrange(&mut third, Some(&layout)), layout);
+    }
+
+    #[test]
+    fn new_nodes_land_near_their_neighbours() {
+        let mut first = flowchart(&["a", "b", "c", "d"], &[]);
+        let layout = arrange(&mut first, None);
+
+        let mut second = flowchart(&["a", "b", "c", "d", "e"], &[("c", "e")]);
+        arrange(&mut second, Some(&layout));
+        let e = second.nodes.iter().find(|n| n.id == "e").unwrap().position.unwrap();
+        assert_eq!((e.x, e.y), (0.0, 2.0 * CELL_HEIGHT));
+    }
+}
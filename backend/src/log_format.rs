@@ -0,0 +1,76 @@
+//! Log line post-processing
+//!
+//! Services often colour their output or log JSON objects one per line. With
+//! `strip_ansi=true` the logs endpoints drop terminal escape sequences before
+//! grepping, and with `parse_json=true` lines holding a JSON object also carry
+//! it parsed as `json`, leaving `line` as it was. Patterns
+//! to grep or watch logs with are compiled here too, within size limits.
+
+use std::{borrow::Cow, sync::LazyLock};
+
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+
+/// Longest accepted pattern, in bytes
+const MAX_PATTERN_LEN: usize = 512;
+/// Compiled size limit, so a pathological pattern cannot eat memory
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// CSI sequences (colours, cursor movement), OSC sequences (titles,
+/// hyperlinks) ended by BEL or ST, and two-character escapes
+static ANSI_ESCAPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]").unwrap()
+});
+
+/// `line` without terminal escape sequences
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if line.contains('\x1b') {
+        ANSI_ESCAPE.replace_all(line, "")
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+/// The JSON object `line` holds, if it is one
+pub fn parse_json(line: &str) -> Option<Value> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    serde_json::from_str::<Value>(line).ok().filter(Value::is_object)
+}
+
+/// Compile a pattern to grep or watch logs with, within the length and size
+/// limits
+pub fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    if pattern.is_empty() || pattern.len() > MAX_PATTERN_LEN {
+        return Err(format!("pattern must be 1-{} bytes", MAX_PATTERN_LEN));
+    }
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("invalid pattern: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_are_stripped() {
+        assert_eq!(strip_ansi("\x1b[1;31mERROR\x1b[0m refused"), "ERROR refused");
+        assert_eq!(strip_ansi("\x1b]8;;https://example.com\x07link\x1b]8;;\x07"), "link");
+        assert_eq!(strip_ansi("\x1b[2K\x1b[Gprogress 40%"), "progress 40%");
+        assert!(matches!(strip_ansi("plain"), Cow::Borrowed("plain")));
+    }
+
+    #[test]
+    fn only_objects_are_parsed() {
+        let parsed = parse_json(r#"{"level":"warn","msg":"slow query","ms":812}"#).unwrap();
+        assert_eq!(parsed["level"], "warn");
+        assert_eq!(parsed["ms"], 812);
+        assert_eq!(parse_json("[1, 2]"), None);
+        assert_eq!(parse_json("{not json"), None);
+        assert_eq!(parse_json("GET /health 200"), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Log error rates
+//!
+//! A green healthcheck says little about a service that logs an error on
+//! every request. With `log_rates.enabled` in the configuration, every line
+//! the log tailer reads from a running container is matched against an error
+//! and a warning pattern, and the matches are counted per container and
+//! minute. The rates, averaged over the last five minutes, are listed in the
+//! `metrics` of the container's flowchart node and as gauges on `/metrics`.
+//!
+//! A line with an explicit level, such as `[warn]`, `level=warn` or
+//! `"level":"warn"`, counts at that level whatever else it mentions, and not
+//! at all when the level is `info` or below. Other lines are matched against
+//! the patterns, and a line matching both counts as an error. The default
+//! patterns match the usual level words on their own, so `ERROR` counts but
+//! `errors=0` does not.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{LazyLock, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+/// Error lines unless `log_rates.error_pattern` says otherwise
+pub const DEFAULT_ERROR_PATTERN: &str = r"(?i)\b(error|err|fatal|panic|critical|crit|exception)\b";
+/// Warning lines unless `log_rates.warn_pattern` says otherwise
+pub const DEFAULT_WARN_PATTERN: &str = r"(?i)\b(warn|warning)\b";
+/// A level in brackets or given as a `level` field
+static LEVEL_TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(?:\[|\b(?:level|lvl|severity)"?\s*[=:]\s*"?)([a-z]+)\b"#).unwrap()
+});
+/// Minutes the rates are averaged over
+const WINDOW_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warning,
+}
+
+/// The level `line` states explicitly; `Some(None)` for one below a warning
+fn explicit_level(line: &str) -> Option<Option<LogLevel>> {
+    LEVEL_TOKEN.captures_iter(line).find_map(|token| match token[1].to_lowercase().as_str() {
+        "error" | "err" | "fatal" | "panic" | "critical" | "crit" | "alert" | "emerg" => Some(Some(LogLevel::Error)),
+        "warn" | "warning" => Some(Some(LogLevel::Warning)),
+        "info" | "notice" | "debug" | "trace" => Some(None),
+        _ => None,
+    })
+}
+
+/// Whether `line` is an error or a warning
+pub fn classify(line: &str, error: &Regex, warn: &Regex) -> Option<LogLevel> {
+    if let Some(level) = explicit_level(line) {
+        level
+    } else if error.is_match(line) {
+        Some(LogLevel::Error)
+    } else if warn.is_match(line) {
+        Some(LogLevel::Warning)
+    } else {
+        None
+    }
+}
+
+/// Error and warning lines per minute of one container
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogRate {
+    pub errors_per_minute: f64,
+    pub warnings_per_minute: f64,
+}
+
+#[derive(Debug, Default)]
+struct MinuteCounts {
+    /// Minutes since the Unix epoch
+    minute: i64,
+    errors: u64,
+    warnings: u64,
+}
+
+/// Recent error and warning counts of the containers whose logs are counted,
+/// by name
+#[derive(Default)]
+pub struct LogRateTable {
+    counts: Mutex<HashMap<String, VecDeque<MinuteCounts>>>,
+}
+
+fn minute(at: DateTime<Utc>) -> i64 {
+    at.timestamp().div_euclid(60)
+}
+
+impl LogRateTable {
+    /// Count the logs of exactly `containers`, starting the new ones at zero
+    pub fn track(&self, containers: &HashSet<String>) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.retain(|name, _| containers.contains(name));
+        for name in containers {
+            counts.entry(name.clone()).or_default();
+        }
+    }
+
+    /// Count one line of `container` logged at `at`
+    pub fn record(&self, container: &str, at: DateTime<Utc>, level: LogLevel) {
+        let mut counts = self.counts.lock().unwrap();
+        let Some(minutes) = counts.get_mut(container) else {
+            return;
+        };
+        let now = minute(at);
+        while minutes.front().is_some_and(|m| m.minute <= now - WINDOW_MINUTES) {
+            minutes.pop_front();
+        }
+        if minutes.back().is_none_or(|m| m.minute != now) {
+            minutes.push_back(MinuteCounts {
+                minute: now,
+                ..Default::default()
+            });
+        }
+        let current = minutes.back_mut().unwrap();
+        match level {
+            LogLevel::Error => current.errors += 1,
+            LogLevel::Warning => current.warnings += 1,
+        }
+    }
+
+    fn rate_of(minutes: &VecDeque<MinuteCounts>, now: DateTime<Utc>) -> LogRate {
+        let (mut errors, mut warnings) = (0, 0);
+        for counts in minutes.iter().filter(|m| m.minute > minute(now) - WINDOW_MINUTES) {
+            errors += counts.errors;
+            warnings += counts.warnings;
+        }
+        LogRate {
+            errors_per_minute: errors as f64 / WINDOW_MINUTES as f64,
+            warnings_per_minute: warnings as f64 / WINDOW_MINUTES as f64,
+        }
+    }
+
+    /// The rates of `container`, or `None` when its logs are not counted
+    pub fn rate(&self, container: &str, now: DateTime<Utc>) -> Option<LogRate> {
+        let counts = self.counts.lock().unwrap();
+        counts.get(container).map(|minutes| Self::rate_of(minutes, now))
+    }
+
+    /// The rates of every counted container, by name
+    pub fn rates(&self, now: DateTime<Utc>) -> Vec<(String, LogRate)> {
+        let counts = self.counts.lock().unwrap();
+        let mut rates: Vec<(String, LogRate)> = counts
+            .iter()
+            .map(|(name, minutes)| (name.clone(), Self::rate_of(minutes, now)))
+            .collect();
+        rates.sort_by(|a, b| a.0.cmp(&b.0));
+        rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_patterns_match_level_words() {
+        let error = Regex::new(DEFAULT_ERROR_PATTERN).unwrap();
+        let warn = Regex::new(DEFAULT_WARN_PATTERN).unwrap();
+        let level = |line: &str| classify(line, &error, &warn);
+        assert_eq!(level("2026-10-15 ERROR connection refused"), Some(LogLevel::Error));
+        assert_eq!(level(r#"{"level":"error","msg":"boom"}"#), Some(LogLevel::Error));
+        assert_eq!(level("[warn] slow query, error budget fine"), Some(LogLevel::Warning));
+        assert_eq!(level("ts=12:00 level=warn msg=\"retrying after error\""), Some(LogLevel::Warning));
+        assert_eq!(level(r#"{"level":"info","msg":"0 errors"}"#), None);
+        assert_eq!(level("[12:00:01] ERROR refused"), Some(LogLevel::Error));
+        assert_eq!(level("WARNING: deprecated flag"), Some(LogLevel::Warning));
+        assert_eq!(level("GET /health 200 errors=0"), None);
+        assert_eq!(level("interrupted by warnings_total"), None);
+    }
+
+    #[test]
+    fn rates_average_the_last_five_minutes() {
+        let table = LogRateTable::default();
+        table.track(&HashSet::from(["api".to_string(), "web".to_string()]));
+        let start = DateTime::parse_from_rfc3339("2026-10-15T08:00:30Z").unwrap().with_timezone(&Utc);
+        let at = |minutes: i64| start + chrono::Duration::minutes(minutes);
+        for _ in 0..10 {
+            table.record("api", at(0), LogLevel::Error);
+        }
+        table.record("api", at(3), LogLevel::Error);
+        table.record("api", at(3), LogLevel::Warning);
+        table.record("unknown", at(3), LogLevel::Error);
+
+        let api = table.rate("api", at(4)).unwrap();
+        assert_eq!(api.errors_per_minute, 11.0 / 5.0);
+        assert_eq!(api.warnings_per_minute, 1.0 / 5.0);
+        // The first minute has left the window
+        assert_eq!(table.rate("api", at(5)).unwrap().errors_per_minute, 1.0 / 5.0);
+        assert_eq!(table.rate("web", at(5)).unwrap().errors_per_minute, 0.0);
+        assert_eq!(table.rate("unknown", at(5)), None);
+
+        table.track(&HashSet::from(["web".to_string()]));
+        assert_eq!(table.rates(at(5)).len(), 1);
+    }
+}
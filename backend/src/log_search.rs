@@ -0,0 +1,383 @@
+//! Log search
+//!
+//! With `log_search.enabled` in the configuration, the log tailer follows the
+//! logs of every running container the `log_search.selector` picks (all of
+//! them without one) and the lines are kept in an in-memory inverted index,
+//! so lines that have rotated out of Docker's log files can still be found.
+//! Lines are kept for `retention_hours` and at most `max_lines` of them,
+//! oldest dropped first. New lines are appended to `log-index.jsonl` in the
+//! data directory on every tailer round, the file is read back on startup and
+//! rewritten without dropped lines every [`COMPACT_EVERY`] rounds.
+//!
+//! `GET /api/logs/search?q=error timeout&container=api&range=6h` returns the
+//! newest lines containing every word of `q`, matched case-insensitively on
+//! whole words.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    metrics::append,
+    models::{LogSearchHit, LogSearchResult},
+    routes::parse_log_time,
+    store::data_dir,
+    AppState,
+};
+
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+pub const DEFAULT_MAX_LINES: usize = 200_000;
+/// Searched back this far without `range`
+const DEFAULT_RANGE: &str = "1h";
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+/// Words longer than this are not indexed
+const MAX_WORD_LEN: usize = 64;
+/// Saves appended between rewrites of the index file, an hour's worth
+pub const COMPACT_EVERY: usize = 120;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogSearchError {
+    #[error("log search is turned off in the configuration")]
+    Disabled,
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl IntoResponse for LogSearchError {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self {
+            LogSearchError::Disabled => StatusCode::NOT_FOUND,
+            LogSearchError::Invalid(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, Json(serde_json::json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Lowercased words of `text`, each once
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty() && w.len() <= MAX_WORD_LEN)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct IndexedLine {
+    container: String,
+    ts: DateTime<Utc>,
+    line: String,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Id of the first line in `lines`; ids count up from there without gaps
+    first_id: u64,
+    lines: VecDeque<IndexedLine>,
+    /// Ids of the lines holding each word, ascending; may still list dropped
+    /// lines until the next prune
+    postings: HashMap<String, VecDeque<u64>>,
+    /// Id of the first line not yet written to the index file
+    saved_id: u64,
+}
+
+impl Inner {
+    fn line(&self, id: u64) -> Option<&IndexedLine> {
+        id.checked_sub(self.first_id).and_then(|offset| self.lines.get(offset as usize))
+    }
+}
+
+/// Indexed log lines of the followed containers
+pub struct LogIndex {
+    inner: Mutex<Inner>,
+    retention: Duration,
+    max_lines: usize,
+    /// The index file and the saves appended to it since it was written
+    file: Option<Mutex<(PathBuf, usize)>>,
+}
+
+impl LogIndex {
+    /// An index kept in memory only
+    pub fn new(retention: Duration, max_lines: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            retention,
+            max_lines,
+            file: None,
+        }
+    }
+
+    /// Open `<data_dir>/<file_name>`, indexing the lines in it that are
+    /// still within retention
+    pub fn open(file_name: &str, retention: Duration, max_lines: usize) -> Self {
+        Self::open_at(data_dir().join(file_name), retention, max_lines, Utc::now())
+    }
+
+    pub fn open_at(path: PathBuf, retention: Duration, max_lines: usize, now: DateTime<Utc>) -> Self {
+        let index = Self {
+            file: Some(Mutex::new((path.clone(), 0))),
+            ..Self::new(retention, max_lines)
+        };
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            // A line cut short by a crash is skipped, not fatal
+            for line in text.lines().filter_map(|line| serde_json::from_str::<IndexedLine>(line).ok()) {
+                index.add(&line.container, line.ts, &line.line);
+            }
+        }
+        index.prune(now);
+        info!("Loaded {} log lines from {}", index.len(), path.display());
+        index.compact();
+        index
+    }
+
+    /// Append the lines indexed since the last save to the index file, or
+    /// rewrite it with the lines still kept every [`COMPACT_EVERY`] saves
+    pub fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut file = file.lock().unwrap();
+        file.1 += 1;
+        if file.1 >= COMPACT_EVERY {
+            drop(file);
+            self.compact();
+            return;
+        }
+        let lines: Vec<IndexedLine> = {
+            let mut inner = self.inner.lock().unwrap();
+            let start = inner.saved_id.saturating_sub(inner.first_id) as usize;
+            let lines = inner.lines.range(start..).cloned().collect();
+            inner.saved_id = inner.first_id + inner.lines.len() as u64;
+            lines
+        };
+        if let Err(e) = append(&file.0, &lines) {
+            warn!("Failed to save log lines to {}: {}", file.0.display(), e);
+        }
+    }
+
+    /// Rewrite the index file with the lines still kept
+    fn compact(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut file = file.lock().unwrap();
+        let lines: Vec<IndexedLine> = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.saved_id = inner.first_id + inner.lines.len() as u64;
+            inner.lines.iter().cloned().collect()
+        };
+        let tmp = file.0.with_extension("jsonl.tmp");
+        let written = append(&tmp, &lines).and_then(|_| std::fs::rename(&tmp, &file.0));
+        match written {
+            Ok(()) => file.1 = 0,
+            Err(e) => warn!("Failed to rewrite {}: {}", file.0.display(), e),
+        }
+    }
+
+    /// Index one line, dropping the oldest when the index is full
+    pub fn add(&self, container: &str, ts: DateTime<Utc>, line: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.first_id + inner.lines.len() as u64;
+        for word in words(line) {
+            inner.postings.entry(word).or_default().push_back(id);
+        }
+        inner.lines.push_back(IndexedLine {
+            container: container.to_string(),
+            ts,
+            line: line.to_string(),
+        });
+        if inner.lines.len() > self.max_lines {
+            inner.lines.pop_front();
+            inner.first_id += 1;
+        }
+    }
+
+    /// Drop lines past retention and forget the postings of dropped lines
+    pub fn prune(&self, now: DateTime<Utc>) {
+        let cutoff = now - chrono::Duration::from_std(self.retention).unwrap_or(chrono::Duration::MAX);
+        let mut inner = self.inner.lock().unwrap();
+        while inner.lines.front().is_some_and(|l| l.ts < cutoff) {
+            inner.lines.pop_front();
+            inner.first_id += 1;
+        }
+        let first_id = inner.first_id;
+        inner.postings.retain(|_, ids| {
+            while ids.front().is_some_and(|&id| id < first_id) {
+                ids.pop_front();
+            }
+            !ids.is_empty()
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().lines.len()
+    }
+
+    /// The newest lines since `since` holding every word of `query`, from
+    /// `container` when named, at most `limit`
+    pub fn search(
+        &self,
+        query: &str,
+        container: Option<&str>,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> (Vec<LogSearchHit>, bool) {
+        let inner = self.inner.lock().unwrap();
+        let mut postings = Vec::new();
+        for word in words(query) {
+            match inner.postings.get(&word) {
+                Some(ids) => postings.push(ids),
+                None => return (Vec::new(), false),
+            }
+        }
+        // Walk the rarest word's lines and check the others by binary search
+        postings.sort_by_key(|ids| ids.len());
+        let Some((rarest, others)) = postings.split_first() else {
+            return (Vec::new(), false);
+        };
+
+        let mut hits = Vec::new();
+        for &id in rarest.iter().rev() {
+            let Some(line) = inner.line(id) else {
+                break;
+            };
+            // Containers' timestamps interleave, so older lines can follow newer ones
+            if line.ts < since {
+                continue;
+            }
+            if container.is_some_and(|c| c != line.container) || others.iter().any(|ids| ids.binary_search(&id).is_err()) {
+                continue;
+            }
+            if hits.len() == limit {
+                return (hits, true);
+            }
+            hits.push(LogSearchHit {
+                container: line.container.clone(),
+                ts: line.ts,
+                line: line.line.clone(),
+            });
+        }
+        (hits, false)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogSearchQuery {
+    pub q: String,
+    /// Container name
+    pub container: Option<String>,
+    /// How far back, such as `6h`, or a start as RFC 3339 or Unix seconds
+    pub range: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// GET /api/logs/search?q=&container=&range=&limit= - Indexed log lines
+/// holding every word of `q`, newest first
+pub async fn search_logs(
+    State(state): State<AppState>,
+    Query(query): Query<LogSearchQuery>,
+) -> Result<Json<LogSearchResult>, LogSearchError> {
+    if !state.config.log_search.enabled {
+        return Err(LogSearchError::Disabled);
+    }
+    if words(&query.q).is_empty() {
+        return Err(LogSearchError::Invalid("q needs at least one word".to_string()));
+    }
+    let range = query.range.as_deref().unwrap_or(DEFAULT_RANGE);
+    let since = parse_log_time(range, Utc::now()).ok_or_else(|| {
+        LogSearchError::Invalid(format!("'{}' is not a valid range; use an age such as 6h, RFC 3339 or Unix seconds", range))
+    })?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let (hits, truncated) = state.log_index.search(&query.q, query.container.as_deref(), since, limit);
+    info!("Log search '{}' found {} lines", query.q, hits.len());
+    Ok(Json(LogSearchResult {
+        query: query.q,
+        since,
+        hits,
+        truncated,
+        indexed_lines: state.log_index.len(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_match_every_word_newest_first() {
+        let index = LogIndex::new(Duration::from_secs(3600), 4);
+        let now = Utc::now();
+        let at = |minutes: i64| now - chrono::Duration::minutes(minutes);
+        index.add("api", at(50), "ERROR upstream timeout after 30s");
+        index.add("web", at(40), "GET /health 200");
+        index.add("api", at(30), "error: Timeout talking to postgres");
+        index.add("worker", at(20), "job failed: timeout");
+        index.add("api", at(10), "ERROR disk full");
+
+        let lines = |hits: Vec<LogSearchHit>| hits.into_iter().map(|h| h.line).collect::<Vec<_>>();
+        // Five lines in an index of four: the first was dropped
+        let (hits, truncated) = index.search("timeout error", None, at(60), 10);
+        assert_eq!(lines(hits), ["error: Timeout talking to postgres"]);
+        assert!(!truncated);
+        let (hits, truncated) = index.search("timeout", None, at(60), 1);
+        assert_eq!(lines(hits), ["job failed: timeout"]);
+        assert!(truncated);
+        let (hits, _) = index.search("timeout", Some("api"), at(60), 10);
+        assert_eq!(hits.len(), 1);
+        let (hits, _) = index.search("timeout", None, at(25), 10);
+        assert_eq!(lines(hits), ["job failed: timeout"]);
+        assert!(index.search("nowhere", None, at(60), 10).0.is_empty());
+
+        // Retention drops lines older than an hour
+        index.prune(now + chrono::Duration::minutes(35));
+        assert_eq!(index.len(), 2);
+        assert!(index.search("postgres", None, at(60), 10).0.is_empty());
+        assert_eq!(index.search("disk", None, at(60), 10).0.len(), 1);
+    }
+
+    #[test]
+    fn lines_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("flowscope-log-index-{}", std::process::id()));
+        let path = dir.join("log-index.jsonl");
+        let now: DateTime<Utc> = "2026-10-15T12:00:00Z".parse().unwrap();
+        let hour = Duration::from_secs(3600);
+        std::fs::create_dir_all(&dir).unwrap();
+        let line = |minutes: i64, text: &str| {
+            serde_json::json!({ "container": "api", "ts": now - chrono::Duration::minutes(minutes), "line": text }).to_string()
+        };
+        let lines = [
+            line(90, "ERROR expired"),
+            line(30, "ERROR upstream timeout"),
+            "{\"container\":\"ap".to_string(),
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        // The expired line and the torn last line are dropped
+        let index = LogIndex::open_at(path.clone(), hour, 100, now);
+        assert_eq!(index.len(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+        index.add("web", now, "ERROR disk full");
+        index.save();
+        index.save();
+        let reopened = LogIndex::open_at(path, hour, 100, now);
+        let (hits, _) = reopened.search("error", None, now - chrono::Duration::hours(1), 10);
+        let lines: Vec<&str> = hits.iter().map(|h| h.line.as_str()).collect();
+        assert_eq!(lines, ["ERROR disk full", "ERROR upstream timeout"]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
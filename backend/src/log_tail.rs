@@ -0,0 +1,161 @@
+//! Log tailing
+//!
+//! Log search and log error rates both read every new line of running
+//! containers. One background task follows each container once, from the
+//! moment it is first seen running, and hands its lines to the search index
+//! when the container is selected for search and to the rate counter when
+//! rates are turned on. Lines are indexed with the timestamps Docker gives
+//! them, and a line split across two chunks of the stream is put back
+//! together first. Every `RESCAN_INTERVAL` it starts following the
+//! containers that started since, drops lines past the index retention and
+//! saves the new ones.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::{
+    docker_api::split_timestamp,
+    log_rates,
+    models::{ContainerInfo, ContainerStatus},
+    AppState,
+};
+
+/// How often newly started containers are followed and old lines dropped
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+/// A line is handed on unfinished once it grows this long without a newline
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Complete lines out of stream chunks, holding back a line the chunk cut off
+#[derive(Default)]
+struct LineBuffer {
+    partial: String,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.partial.push_str(chunk);
+        let complete = match self.partial.rfind('\n') {
+            Some(end) => {
+                let rest = self.partial.split_off(end + 1);
+                std::mem::replace(&mut self.partial, rest)
+            }
+            None if self.partial.len() >= MAX_LINE_BYTES => std::mem::take(&mut self.partial),
+            None => return Vec::new(),
+        };
+        complete
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+fn is_running(status: &ContainerStatus) -> bool {
+    matches!(
+        status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    )
+}
+
+/// Hand a container's new log lines on until its logs end; `index` adds them
+/// to the search index
+fn follow(state: &AppState, container: &ContainerInfo, index: bool) -> JoinHandle<()> {
+    let mut logs = state.docker.stream_timestamped_logs(&container.id, Utc::now().timestamp());
+    let name = container.name.clone();
+    let state = state.clone();
+    tokio::spawn(async move {
+        let rates = &state.config.log_rates;
+        let mut buffer = LineBuffer::default();
+        while let Some(chunk) = logs.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    // Rates count lines as they arrive
+                    let now = Utc::now();
+                    for line in buffer.push(&chunk) {
+                        let (ts, line) = split_timestamp(&line);
+                        if index {
+                            state.log_index.add(&name, ts.unwrap_or(now), line);
+                        }
+                        if rates.enabled {
+                            if let Some(level) = log_rates::classify(line, &rates.error_pattern, &rates.warn_pattern) {
+                                state.docker.log_rates().record(&name, now, level);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    debug!("Stopped following the logs of {}: {}", name, e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Spawn the background task that follows container logs for search and rates
+pub fn spawn_tailer(state: AppState) {
+    tokio::spawn(async move {
+        let (search, rates) = (&state.config.log_search, &state.config.log_rates);
+        let mut followed: HashMap<String, JoinHandle<()>> = HashMap::new();
+        loop {
+            match state.docker.list_containers().await {
+                Ok(containers) => {
+                    followed.retain(|_, task| !task.is_finished());
+                    let mut counted = HashSet::new();
+                    for container in containers.iter().filter(|c| is_running(&c.status)) {
+                        let index = search.enabled && search.selector.as_ref().is_none_or(|s| s.matches(container));
+                        if !index && !rates.enabled {
+                            continue;
+                        }
+                        if rates.enabled {
+                            counted.insert(container.name.clone());
+                        }
+                        if !followed.contains_key(&container.id) {
+                            debug!("Following the logs of {}", container.name);
+                            followed.insert(container.id.clone(), follow(&state, container, index));
+                        }
+                    }
+                    state.docker.log_rates().track(&counted);
+                }
+                Err(e) => warn!("Failed to list containers to follow the logs of: {}", e),
+            }
+            state.log_index.prune(Utc::now());
+            // Writing the index file blocks
+            let index = state.log_index.clone();
+            if let Err(e) = tokio::task::spawn_blocking(move || index.save()).await {
+                warn!("Failed to save the log index: {}", e);
+            }
+            tokio::time::sleep(RESCAN_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_split_across_chunks_are_joined() {
+        let mut buffer = LineBuffer::default();
+        assert_eq!(
+            buffer.push("2026-10-15T12:00:00.1Z GET /health 200\n2026-10-15T12:00:00.2Z ERROR upstr"),
+            ["2026-10-15T12:00:00.1Z GET /health 200"]
+        );
+        assert!(buffer.push("eam time").is_empty());
+        assert_eq!(buffer.push("out\n\n"), ["2026-10-15T12:00:00.2Z ERROR upstream timeout"]);
+
+        let (ts, line) = split_timestamp("2026-10-15T12:00:00.2Z ERROR upstream timeout");
+        assert_eq!(ts, Some("2026-10-15T12:00:00.2Z".parse().unwrap()));
+        assert_eq!(line, "ERROR upstream timeout");
+
+        let long = "x".repeat(MAX_LINE_BYTES);
+        assert_eq!(buffer.push(&long), [long.as_str()]);
+    }
+}
@@ -13,86 +13,586 @@
 
 use anyhow::Result;
 use axum::{
+    extract::State,
+    http::HeaderValue,
+    middleware,
     response::IntoResponse,
-    routing::get,
+    routing::{delete, get, post, put},
     Json, Router,
 };
-use bollard::Docker;
-use std::{net::SocketAddr, sync::Arc};
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use clap::{Parser, Subcommand};
+use std::{net::SocketAddr, process::ExitCode, sync::Arc};
+use tower_http::{
+    cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
+mod alerts;
+mod artifacts;
+mod auth;
+mod breaker;
+mod bundle;
+mod categorize;
+mod cgroup;
+mod changes;
+mod chat;
+mod check;
+mod config;
+mod daemon;
+mod dashboards;
+mod debug;
+mod delivery;
+mod diagnostics;
 mod discovery;
+mod docker_api;
+mod dot;
+mod embed;
+mod endpoints;
+mod envfile;
+mod events;
+mod exec;
+mod fields;
+mod forward;
+mod graph;
+mod groups;
+mod history;
+mod host;
+mod hosts;
+mod image_history;
+mod images;
+mod jobs;
+mod kubernetes;
+mod layout;
+mod log_format;
+mod log_rates;
+mod log_search;
+mod log_tail;
+mod mermaid;
+mod metrics;
+mod migration;
 mod models;
+mod network_path;
+mod notifications;
+mod oneshot;
+mod preflight;
+mod probe;
+mod procfs;
+mod projects;
+mod prometheus;
+mod provider;
 mod routes;
+mod runs;
+mod selector;
+mod sockets;
+mod sse;
+mod store;
+#[cfg(feature = "svg")]
+mod svg;
+mod system;
+mod templates;
+mod ttl;
+mod topology_cache;
+mod update_feed;
+mod usage;
+mod validate_config;
+mod vulnerabilities;
+mod warmup;
 mod websocket;
 
+use alerts::AlertEngine;
+use artifacts::ArtifactStore;
+use auth::Authenticator;
+use breaker::{BreakerConfig, CircuitBreaker, ResilientDocker};
+use bundle::BundleSigner;
+use categorize::Categorizers;
+use check::CheckArgs;
+use config::{Config, ConfigArgs};
+use daemon::DaemonEndpoint;
+use dashboards::DashboardStore;
+use debug::DebugSidecars;
+use delivery::Notifier;
 use discovery::DockerDiscovery;
+use embed::EmbedSigner;
+use endpoints::EndpointTester;
+use envfile::EnvFileStore;
+use events::EventLog;
+use forward::PortForwards;
+use groups::GroupStore;
+use hosts::MultiDocker;
+use history::TopologyHistory;
+use image_history::ImageHistory;
+use jobs::JobRegistry;
+use kubernetes::KubernetesDiscovery;
+use layout::LayoutStore;
+use log_search::LogIndex;
+use metrics::MetricsHistory;
+use notifications::NotificationStore;
+use oneshot::OneshotArgs;
+use prometheus::RequestCounts;
+use provider::{Provider, ProviderKind};
+use runs::OneOffRuns;
+use templates::TemplateStore;
+use topology_cache::TopologyCache;
+use update_feed::UpdateFeed;
+use usage::UsageMeter;
+use validate_config::ValidateConfigArgs;
+use vulnerabilities::VulnerabilityStore;
+use warmup::Warmups;
+use websocket::WsHub;
 
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
-    pub docker: Arc<DockerDiscovery>,
+    pub config: Arc<Config>,
+    pub docker: Arc<DockerDiscovery<ResilientDocker<MultiDocker>>>,
+    pub daemon: Arc<DaemonEndpoint>,
+    /// Where topology, containers and flowcharts come from; `docker` itself
+    /// unless the `provider` setting is `kubernetes`
+    pub provider: Arc<Provider>,
+    pub breaker: Arc<CircuitBreaker>,
+    pub hub: Arc<WsHub>,
+    pub alerts: Arc<AlertEngine>,
+    pub embed: Arc<EmbedSigner>,
+    pub templates: Arc<TemplateStore>,
+    pub groups: Arc<GroupStore>,
+    pub dashboards: Arc<DashboardStore>,
+    pub bundles: Arc<BundleSigner>,
+    pub jobs: Arc<JobRegistry>,
+    pub env_files: Arc<EnvFileStore>,
+    pub metrics: Arc<MetricsHistory>,
+    pub notifications: Arc<NotificationStore>,
+    pub notifier: Arc<Notifier>,
+    pub auth: Arc<Authenticator>,
+    pub usage: Arc<UsageMeter>,
+    pub requests: Arc<RequestCounts>,
+    pub debug: Arc<DebugSidecars>,
+    pub runs: Arc<OneOffRuns>,
+    pub forwards: Arc<PortForwards>,
+    pub endpoints: Arc<EndpointTester>,
+    pub artifacts: Arc<ArtifactStore>,
+    pub layouts: Arc<LayoutStore>,
+    pub events: Arc<EventLog>,
+    pub images: Arc<ImageHistory>,
+    pub history: Arc<TopologyHistory>,
+    pub warmups: Arc<Warmups>,
+    pub updates: Arc<UpdateFeed>,
+    pub topology: Arc<TopologyCache>,
+    pub vulnerabilities: Arc<VulnerabilityStore>,
+    pub log_index: Arc<LogIndex>,
+}
+
+/// Command-line options; with no flags the server starts as usual
+#[derive(Debug, Parser)]
+#[command(name = "flowscope", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Discover once, write the export and exit instead of serving
+    #[arg(long)]
+    oneshot: bool,
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(flatten)]
+    export: OneshotArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Evaluate topology rules and exit non-zero if any fail
+    Check(CheckArgs),
+    /// Check the configuration, reporting every problem, and exit non-zero on errors
+    ValidateConfig(ValidateConfigArgs),
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
+async fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+
+    // Initialize logging; one-shot runs keep stdout for the export itself
     tracing_subscriber::registry()
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| {
             EnvFilter::new("flowscope_backend=debug,tower_http=debug")
         }))
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
+    if let Some(Command::ValidateConfig(args)) = &cli.command {
+        return Ok(validate_config::run(args, &cli.config).await);
+    }
+
+    let config = match Config::load(&cli.config) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("Invalid configuration: {}", e);
+            return Ok(ExitCode::from(oneshot::EXIT_CONFIG));
+        }
+    };
+
+    if cli.oneshot || cli.command.is_some() {
+        let categorizers = match Categorizers::open(&config.categories_file) {
+            Ok(categorizers) => categorizers,
+            Err(e) => {
+                tracing::error!("Failed to load categorization strategies: {}", e);
+                return Ok(ExitCode::from(oneshot::EXIT_DISCOVERY));
+            }
+        };
+        let discovery = match hosts::connect(config.docker_host_flag.as_deref(), config.docker_host_file.as_deref()).await {
+            (docker, daemon @ DaemonEndpoint { error: None, .. }) => {
+                let discovery = DockerDiscovery::new(docker).with_categorizers(categorizers);
+                if daemon.is_local() {
+                    discovery.with_proc_root(procfs::proc_root())
+                } else {
+                    discovery
+                }
+            }
+            (_, DaemonEndpoint { error: Some(_), .. }) => return Ok(ExitCode::from(oneshot::EXIT_DISCOVERY)),
+        };
+        return Ok(match &cli.command {
+            Some(Command::Check(args)) => check::run(&discovery, args).await,
+            Some(Command::ValidateConfig(_)) => unreachable!("handled above"),
+            None => oneshot::run(&discovery, &cli.export).await,
+        });
+    }
+
     info!("🔭 FlowScope Backend starting...");
 
-    // Connect to Docker
-    let docker = Docker::connect_with_local_defaults()
-        .expect("Failed to connect to Docker daemon");
-    
-    let discovery = DockerDiscovery::new(docker);
-    
+    // Report every configuration problem at once rather than the first
+    let report = validate_config::validate(false, &cli.config).await;
+    report.log();
+    if report.errors() > 0 {
+        tracing::error!("Not starting; run `flowscope validate-config` after fixing the errors above");
+        return Ok(ExitCode::from(oneshot::EXIT_CONFIG));
+    }
+
+    // Connect to Docker or Podman, or every host in FLOWSCOPE_DOCKER_HOSTS;
+    // without one the server still starts and reports why on /health
+    let (docker, daemon) = hosts::connect(config.docker_host_flag.as_deref(), config.docker_host_file.as_deref()).await;
+
+    let breaker = Arc::new(CircuitBreaker::new(BreakerConfig::from_env()));
+    let discovery = DockerDiscovery::new(ResilientDocker::new(docker, breaker.clone())).with_categorizers(
+        Categorizers::open(&config.categories_file).expect("Failed to load categorization strategies"),
+    );
+    // This host's /proc only describes the daemon's host when they are the same
+    let discovery = if daemon.is_local() {
+        discovery.with_proc_root(procfs::proc_root())
+    } else {
+        info!("Docker daemon is remote, not reading host figures from /proc");
+        discovery
+    };
+    let docker = Arc::new(discovery);
+    let provider = match config.provider {
+        ProviderKind::Docker => Provider::Docker(docker.clone()),
+        ProviderKind::Kubernetes => {
+            let cluster = KubernetesDiscovery::from_env()
+                .expect("Failed to configure the Kubernetes API")
+                .with_categorizers(
+                    Categorizers::open(&config.categories_file).expect("Failed to load categorization strategies"),
+                );
+            info!("Discovering pods from the Kubernetes API at {}", cluster.config().api);
+            Provider::Kubernetes(cluster)
+        }
+    };
+
+    let hub = Arc::new(WsHub::new().with_default_interval(config.ws_interval));
+    let alerts = Arc::new(AlertEngine::new(config.alert_rules.clone()));
+    let (retention, max_lines) = (config.log_search.retention, config.log_search.max_lines);
+    let log_index = Arc::new(if config.log_search.enabled {
+        LogIndex::open("log-index.jsonl", retention, max_lines)
+    } else {
+        LogIndex::new(retention, max_lines)
+    });
+    // Without the sampler nothing is recorded, so no samples file is kept
+    let metrics = Arc::new(if config.features.stats_history {
+        MetricsHistory::open("stats-history.jsonl")
+    } else {
+        MetricsHistory::new()
+    });
     let state = AppState {
-        docker: Arc::new(discovery),
+        config: Arc::new(config),
+        docker,
+        daemon: Arc::new(daemon),
+        provider: Arc::new(provider),
+        breaker,
+        hub,
+        alerts,
+        embed: Arc::new(EmbedSigner::from_env()),
+        templates: Arc::new(TemplateStore::open("templates.json")),
+        groups: Arc::new(GroupStore::open("groups.json")),
+        dashboards: Arc::new(DashboardStore::open("dashboards.json")),
+        bundles: Arc::new(BundleSigner::from_env()),
+        jobs: Arc::new(JobRegistry::new()),
+        env_files: Arc::new(EnvFileStore::open("env-files.json")),
+        metrics,
+        notifications: Arc::new(NotificationStore::open("notification-channels.json")),
+        notifier: Arc::new(Notifier::new()),
+        auth: Arc::new(Authenticator::from_env().expect("Failed to load API tokens")),
+        usage: Arc::new(UsageMeter::new()),
+        requests: Arc::new(RequestCounts::new()),
+        debug: Arc::new(DebugSidecars::from_env()),
+        runs: Arc::new(OneOffRuns::new()),
+        forwards: Arc::new(PortForwards::from_env()),
+        endpoints: Arc::new(EndpointTester::from_env()),
+        artifacts: Arc::new(ArtifactStore::from_env()),
+        layouts: Arc::new(LayoutStore::open("layouts.json")),
+        events: Arc::new(EventLog::new()),
+        images: Arc::new(ImageHistory::open("image-history.json")),
+        history: Arc::new(TopologyHistory::open("topology-history.json")),
+        warmups: Arc::new(Warmups::open("warmup-profiles.json")),
+        updates: Arc::new(UpdateFeed::new()),
+        topology: Arc::new(TopologyCache::from_env()),
+        vulnerabilities: Arc::new(VulnerabilityStore::open("vulnerability-scans.json")),
+        log_index,
     };
 
-    // Build router
-    let app = Router::new()
-        .route("/health", get(health_check))
+    // Start the WebSocket publisher, the notification dispatcher, the topology
+    // refresher, the stats sampler, the event collector, the image tracker,
+    // the topology recorder, the alert evaluator, the health prober, the
+    // vulnerability scanner, the log tailer and the sidecar, run and artifact
+    // reapers; the sampler, collector, tracker and recorder only watch Docker
+    // and each can be turned off in the configuration, the evaluator runs when
+    // there are alert rules, the prober when probes are turned on, the scanner
+    // when one is configured and the tailer when log search or log rates are
+    // turned on
+    websocket::spawn_publisher(state.clone());
+    delivery::spawn_dispatcher(state.clone());
+    topology_cache::spawn_refresher(state.clone());
+    if state.provider.kind() == ProviderKind::Docker {
+        let features = state.config.features;
+        if features.stats_history {
+            metrics::spawn_sampler(state.clone());
+        }
+        if features.event_log {
+            events::spawn_collector(state.clone());
+        }
+        if features.image_history {
+            image_history::spawn_tracker(state.clone());
+        }
+        if features.topology_history {
+            history::spawn_recorder(state.clone());
+        }
+        if !state.alerts.is_empty() {
+            alerts::spawn_evaluator(state.clone());
+        }
+        if state.config.probes.enabled {
+            probe::spawn_prober(state.clone());
+        }
+        if let Some(scanner) = state.config.vulnerabilities.scanner {
+            vulnerabilities::spawn_scanner(state.clone(), scanner);
+        }
+        if state.config.log_search.enabled || state.config.log_rates.enabled {
+            log_tail::spawn_tailer(state.clone());
+        }
+    }
+    debug::spawn_reaper(state.clone());
+    runs::spawn_reaper(state.clone());
+    artifacts::spawn_reaper(state.clone());
+
+    // Docker-backed routes sit behind the circuit breaker; with the kubernetes
+    // provider only those that go through the provider are served
+    let docker_routes = Router::new()
         .route("/api/topology", get(routes::get_topology))
-        .route("/api/containers", get(routes::get_containers))
+        .route("/api/containers", get(routes::get_containers).post(routes::create_container))
         .route("/api/containers/stats", get(routes::get_containers_with_stats))
+        .route("/api/containers/preflight", post(routes::preflight_container))
+        .route("/api/containers/run", post(runs::create_run))
+        .route("/api/containers/actions/:action", post(groups::bulk_action))
+        .route("/api/logs", get(routes::get_logs))
+        .route("/api/logs/search", get(log_search::search_logs))
         .route("/api/networks", get(routes::get_networks))
+        .route("/api/volumes", get(routes::get_volumes))
+        .route("/api/services", get(routes::get_services))
+        .route("/api/images", get(images::list_images))
+        .route("/api/images/pull", post(images::pull_image))
+        .route("/api/image/:id", delete(images::remove_image))
         .route("/api/images/sizes", get(routes::get_image_sizes))
+        .route("/api/images/usage", get(routes::get_image_usage))
+        .route("/api/system/info", get(routes::get_system_info))
+        .route("/api/host/cpus", get(routes::get_cpu_map))
         .route("/api/flowchart/:id", get(routes::get_flowchart))
-        .route("/api/container/:id", get(routes::get_container_detail))
+        .route("/api/flowchart/:id/export", get(routes::export_flowchart))
+        .route("/api/graph/export", get(routes::export_graph))
+        .route("/api/graph/path", get(network_path::get_path))
+        .route(
+            "/api/container/:id",
+            get(routes::get_container_detail).delete(routes::remove_container),
+        )
         .route("/api/container/:id/detail", get(routes::get_container_full_detail))
+        .route("/api/container/:id/inspect", get(routes::get_container_inspect))
         .route("/api/container/:id/logs", get(routes::get_container_logs))
+        .route("/api/container/:id/logs/export", post(artifacts::export_logs))
         .route("/api/container/:id/stats", get(routes::get_container_stats))
-        .route("/api/container/:id/restart", axum::routing::post(routes::restart_container))
-        .route("/api/container/:id/stop", axum::routing::post(routes::stop_container))
-        .route("/api/container/:id/start", axum::routing::post(routes::start_container))
+        .route("/api/container/:id/stats/history", get(metrics::get_stats_history))
+        .route("/api/container/:id/sockets", get(sockets::get_container_sockets))
+        .route("/api/container/:id/vulnerabilities", get(vulnerabilities::get_vulnerabilities))
+        .route("/api/container/:id/category/explain", get(categorize::explain_category))
+        .route("/api/container/:id/restart", post(routes::restart_container))
+        .route("/api/container/:id/stop", post(routes::stop_container))
+        .route("/api/container/:id/start", post(routes::start_container))
+        .route("/api/container/:id/pause", post(routes::pause_container))
+        .route("/api/container/:id/unpause", post(routes::unpause_container))
+        .route("/api/container/:id/kill", post(routes::kill_container))
+        .route(
+            "/api/container/:id/warmup",
+            get(warmup::get_profile)
+                .put(warmup::put_profile)
+                .delete(warmup::delete_profile)
+                .post(warmup::run_warmup),
+        )
+        .route("/api/container/:id/image-history", get(image_history::get_image_history))
+        .route("/api/container/:id/rollback", post(image_history::rollback_container))
+        .route("/api/container/:id/exec", post(exec::exec_command))
+        .route("/api/container/:id/debug-sidecar", post(debug::launch_sidecar))
+        .route("/api/container/:id/forward", post(forward::open_forward))
+        .route("/api/endpoints/:id/test", post(endpoints::test_endpoint))
+        .route("/api/migrations", get(migration::list_migrations))
+        .route("/api/migrations/:legacy", get(migration::get_migration))
+        .route("/api/migrations/:legacy/cutover", post(migration::cut_over))
+        .route("/api/diagnostics", get(routes::get_diagnostics))
+        .route("/api/diagnostics/processes", get(routes::get_process_diagnostics))
+        .route("/api/container/:id/diagnostics/processes", get(routes::get_container_process_diagnostics))
+        .route("/api/container/:id/processes", get(routes::get_container_processes))
+        .route("/api/container/:id/changes", get(routes::get_container_changes))
+        .route("/api/groups/:name/flowchart", get(groups::get_group_flowchart))
+        .route("/api/groups/:name/stats", get(groups::get_group_stats))
+        .route("/api/groups/:name/actions/:action", post(groups::group_action))
+        .route("/api/dashboards/:id/data", get(dashboards::get_dashboard_data))
+        .route("/api/projects", get(projects::list_projects))
+        .route("/api/projects/:name/:action", post(projects::project_action))
+        .route("/api/projects/:name/services/:svc/scale", post(projects::scale_service))
+        .route(
+            "/api/projects/:name/env",
+            put(envfile::upload_env_file).delete(envfile::delete_env_file),
+        )
+        .route("/api/projects/:name/env/drift", get(envfile::get_env_drift))
+        .route_layer(middleware::from_fn_with_state(state.clone(), breaker::shed_load))
+        .route_layer(middleware::from_fn_with_state(state.clone(), provider::require_docker));
+
+    // Any origin may call the API unless the configuration lists them
+    let cors = if state.config.cors_origins.is_empty() {
+        CorsLayer::very_permissive()
+    } else {
+        let origins = state.config.cors_origins.iter().filter_map(|o| match o.parse::<HeaderValue>() {
+            Ok(origin) => Some(origin),
+            Err(_) => {
+                tracing::warn!("Ignoring CORS origin '{}'", o);
+                None
+            }
+        });
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods(AllowMethods::mirror_request())
+            .allow_headers(AllowHeaders::mirror_request())
+    };
+    let addr = state.config.bind;
+
+    // Build router
+    let app = Router::new()
+        .route("/health", get(health_check))
+        .route("/metrics", get(prometheus::get_metrics))
+        .merge(docker_routes)
+        .route("/api/templates", get(templates::list_templates).post(templates::create_template))
+        .route(
+            "/api/templates/:name",
+            get(templates::get_template)
+                .put(templates::update_template)
+                .delete(templates::delete_template),
+        )
+        .route("/api/templates/:name/instantiate", post(templates::instantiate_template))
+        .route("/api/groups", get(groups::list_groups).post(groups::create_group))
+        .route(
+            "/api/groups/:name",
+            get(groups::get_group)
+                .put(groups::update_group)
+                .delete(groups::delete_group),
+        )
+        .route(
+            "/api/dashboards",
+            get(dashboards::list_dashboards).post(dashboards::create_dashboard),
+        )
+        .route(
+            "/api/dashboards/:id",
+            get(dashboards::get_dashboard)
+                .put(dashboards::update_dashboard)
+                .delete(dashboards::delete_dashboard),
+        )
+        .route("/api/topology/wait", get(routes::wait_topology))
+        .route("/api/flowchart/:id/layout", delete(layout::reset_layout))
+        .route("/api/diagnostics/host", get(routes::get_host_diagnostics))
+        .route("/api/events", get(events::list_events))
+        .route("/api/events/stream", get(sse::stream_updates))
+        .route("/api/history/snapshots", get(history::list_snapshots))
+        .route("/api/history/diff", get(history::get_diff))
+        .route("/api/jobs", get(jobs::list_jobs))
+        .route("/api/jobs/:id", get(jobs::get_job))
+        .route("/api/artifacts", get(artifacts::list_artifacts))
+        .route(
+            "/api/artifacts/:id",
+            get(artifacts::download_artifact).delete(artifacts::delete_artifact),
+        )
+        .route("/api/debug-sidecars", get(debug::list_sidecars))
+        .route("/api/debug-sidecars/:id", delete(debug::remove_sidecar))
+        .route("/api/runs", get(runs::list_runs))
+        .route("/api/runs/:id", delete(runs::remove_run))
+        .route("/api/forwards", get(forward::list_forwards))
+        .route("/api/forwards/:id", get(forward::get_forward).delete(forward::close_forward))
+        .route("/api/alerts", get(alerts::list_alerts))
+        .route("/api/alerts/rules/test", post(alerts::test_rule))
+        .route(
+            "/api/notifications/channels",
+            get(notifications::list_channels).post(notifications::create_channel),
+        )
+        .route(
+            "/api/notifications/channels/:name",
+            get(notifications::get_channel)
+                .put(notifications::update_channel)
+                .delete(notifications::delete_channel),
+        )
+        .route("/api/notifications/preview", post(notifications::preview_channel))
+        .route("/api/notifications/status", get(delivery::get_status))
+        .route("/api/bundle/export", get(bundle::export_bundle))
+        .route("/api/bundle/import", post(bundle::import_bundle))
+        .route("/api/schema/typescript", get(routes::get_typescript_schema))
+        .route("/api/embed/tokens", post(embed::create_embed_token))
+        .route("/embed/flowchart/:id", get(embed::get_embed_flowchart))
         .route("/ws", get(websocket::ws_handler))
-        .with_state(state)
-        .layer(
-            CorsLayer::very_permissive()
+        .route(
+            "/ws/exec/:id",
+            get(websocket::terminal_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), provider::require_docker)),
         )
+        .route(
+            "/ws/logs/:id",
+            get(websocket::log_stream_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), provider::require_docker)),
+        )
+        .route("/api/auth/scopes", get(auth::list_scopes))
+        .route("/api/auth/usage", get(usage::get_usage))
+        .layer(middleware::from_fn_with_state(state.clone(), events::record_actions))
+        .layer(middleware::from_fn_with_state(state.clone(), config::require_features))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::require_scope))
+        .layer(middleware::from_fn_with_state(state.clone(), prometheus::count_requests))
+        .with_state(state)
+        .layer(cors)
         .layer(TraceLayer::new_for_http());
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8850));
     info!("🚀 FlowScope Backend listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-async fn health_check() -> impl IntoResponse {
+async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
+    let docker = state.breaker.status();
+    let degraded = state.breaker.is_open() || state.daemon.error.is_some();
     Json(serde_json::json!({
-        "status": "healthy",
+        "status": if degraded { "degraded" } else { "healthy" },
+        "docker": docker,
+        "daemon": *state.daemon,
+        "updates": state.updates.status(),
         "service": "flowscope-backend",
         "version": "0.1.0"
     }))
@@ -0,0 +1,103 @@
+//! Mermaid rendering of flowcharts
+//!
+//! Produces a `flowchart LR` (or `TD`) diagram that renders in GitHub, GitLab
+//! and most Markdown tooling, so archived topology can be read without
+//! FlowScope.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::models::{ConnectionType, ContainerStatus, FlowDirection, Flowchart};
+
+/// Fill/stroke per status, matching the frontend's node colours
+const STATUS_CLASSES: [(ContainerStatus, &str); 8] = [
+    (ContainerStatus::Healthy, "fill:#dcfce7,stroke:#16a34a"),
+    (ContainerStatus::Running, "fill:#dbeafe,stroke:#2563eb"),
+    (ContainerStatus::Unhealthy, "fill:#fee2e2,stroke:#dc2626"),
+    (ContainerStatus::Restarting, "fill:#fef3c7,stroke:#d97706"),
+    (ContainerStatus::Paused, "fill:#f3f4f6,stroke:#6b7280"),
+    (ContainerStatus::Created, "fill:#f3f4f6,stroke:#6b7280"),
+    (ContainerStatus::Exited, "fill:#f3f4f6,stroke:#374151,stroke-dasharray:4"),
+    (ContainerStatus::Dead, "fill:#fee2e2,stroke:#7f1d1d,stroke-dasharray:4"),
+];
+
+fn status_class(status: &ContainerStatus) -> String {
+    format!("{:?}", status).to_lowercase()
+}
+
+/// Mermaid treats quotes and angle brackets specially even inside quoted labels
+fn escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+fn keyword(direction: FlowDirection) -> &'static str {
+    match direction {
+        FlowDirection::Lr => "LR",
+        FlowDirection::Td => "TD",
+    }
+}
+
+/// Render a flowchart as left-to-right Mermaid source
+pub fn render(flowchart: &Flowchart) -> String {
+    render_directed(flowchart, FlowDirection::Lr)
+}
+
+/// Render a flowchart as Mermaid source flowing in `direction`
+pub fn render_directed(flowchart: &Flowchart, direction: FlowDirection) -> String {
+    let mut out = String::new();
+    writeln!(out, "---\ntitle: {}\n---", escape(&flowchart.name)).unwrap();
+    writeln!(out, "flowchart {}", keyword(direction)).unwrap();
+    if !flowchart.description.is_empty() {
+        writeln!(out, "    %% {}", flowchart.description.replace('\n', " ")).unwrap();
+    }
+
+    // Node ids may contain characters Mermaid rejects, so use positional ids
+    let ids: HashMap<&str, String> = flowchart
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.as_str(), format!("n{}", i)))
+        .collect();
+
+    for node in &flowchart.nodes {
+        let mut label = escape(&node.name);
+        if let Some(port) = node.port {
+            write!(label, "<br/>:{}", port).unwrap();
+        }
+        writeln!(
+            out,
+            "    {}[\"{}\"]:::{}",
+            ids[node.id.as_str()],
+            label,
+            status_class(&node.status)
+        )
+        .unwrap();
+    }
+
+    for connection in &flowchart.connections {
+        // Connections to nodes outside this chart cannot be drawn
+        let (Some(source), Some(target)) = (
+            ids.get(connection.source.as_str()),
+            ids.get(connection.target.as_str()),
+        ) else {
+            continue;
+        };
+        let arrow = match connection.connection_type {
+            ConnectionType::Data => "==>",
+            ConnectionType::Secondary | ConnectionType::Network => "-.->",
+            _ => "-->",
+        };
+        match &connection.label {
+            Some(label) => writeln!(out, "    {} {}|\"{}\"| {}", source, arrow, escape(label), target),
+            None => writeln!(out, "    {} {} {}", source, arrow, target),
+        }
+        .unwrap();
+    }
+
+    for (status, style) in STATUS_CLASSES {
+        writeln!(out, "    classDef {} {}", status_class(&status), style).unwrap();
+    }
+    out
+}
@@ -0,0 +1,469 @@
+//! Container stats history
+//!
+//! A background sampler records the stats of every running container every
+//! [`SAMPLE_INTERVAL`] and keeps the last [`RETENTION`], so questions about
+//! the recent past, such as when an alert rule would have fired, can be
+//! answered without waiting for new samples. Each sampling round is appended
+//! to `stats-history.jsonl`, one sample per line, which is read back on
+//! startup and rewritten without expired samples every [`COMPACT_EVERY`]
+//! rounds; the file is only kept while the `stats_history` feature is on.
+//! `GET /api/container/:id/stats/history?range=1h&step=30s` averages a
+//! container's samples into steps for charting trends, and
+//! `GET /api/images/usage?range=1h` adds the same history summed per image.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{ContainerInfo, ContainerStats, ImageUsagePoint, StatsHistory, StatsPoint},
+    store::data_dir,
+    AppState,
+};
+
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+/// How far back samples are kept
+pub const RETENTION: chrono::Duration = chrono::Duration::hours(24);
+/// Rounds appended between rewrites of the samples file, an hour's worth
+pub const COMPACT_EVERY: usize = 120;
+/// Most points a stats history request may ask for
+pub const MAX_POINTS: i64 = 2880;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricSample {
+    pub container: String,
+    /// Daemon the container runs on when FlowScope watches several, as in
+    /// [`ContainerInfo::host`]; samples are keyed by host and name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Image the container ran when sampled; absent in samples recorded
+    /// before images were
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub at: DateTime<Utc>,
+    pub stats: ContainerStats,
+}
+
+#[derive(Debug, Default)]
+pub struct MetricsHistory {
+    samples: Mutex<VecDeque<MetricSample>>,
+    /// The samples file and the rounds appended to it since it was written
+    file: Option<Mutex<(PathBuf, usize)>>,
+}
+
+impl MetricsHistory {
+    /// A history kept in memory only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open `<data_dir>/<file_name>`, keeping the samples in it that have
+    /// not expired
+    pub fn open(file_name: &str) -> Self {
+        Self::open_at(data_dir().join(file_name), Utc::now())
+    }
+
+    pub fn open_at(path: PathBuf, now: DateTime<Utc>) -> Self {
+        let samples: VecDeque<MetricSample> = match std::fs::read_to_string(&path) {
+            Ok(text) => text
+                .lines()
+                // A line cut short by a crash is skipped, not fatal
+                .filter_map(|line| serde_json::from_str::<MetricSample>(line).ok())
+                .filter(|s| now - s.at <= RETENTION)
+                .collect(),
+            Err(_) => VecDeque::new(),
+        };
+        info!("Loaded {} stats samples from {}", samples.len(), path.display());
+        let history = Self {
+            samples: Mutex::new(samples),
+            file: Some(Mutex::new((path, 0))),
+        };
+        history.compact();
+        history
+    }
+
+    /// Record the stats of every container that has them, dropping samples
+    /// older than [`RETENTION`]
+    pub fn record(&self, at: DateTime<Utc>, containers: &[ContainerInfo]) {
+        let round: Vec<MetricSample> = containers
+            .iter()
+            .filter_map(|c| {
+                Some(MetricSample {
+                    container: c.name.clone(),
+                    host: c.host.clone(),
+                    image: Some(c.image.clone()),
+                    at,
+                    stats: c.stats.clone()?,
+                })
+            })
+            .collect();
+        {
+            let mut samples = self.samples.lock().unwrap();
+            samples.extend(round.iter().cloned());
+            while samples.front().is_some_and(|s| at - s.at > RETENTION) {
+                samples.pop_front();
+            }
+        }
+
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut file = file.lock().unwrap();
+        file.1 += 1;
+        if file.1 >= COMPACT_EVERY {
+            drop(file);
+            self.compact();
+        } else if let Err(e) = append(&file.0, &round) {
+            warn!("Failed to save stats samples to {}: {}", file.0.display(), e);
+        }
+    }
+
+    /// Rewrite the samples file with the samples still kept
+    fn compact(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut file = file.lock().unwrap();
+        let samples: Vec<MetricSample> = self.samples.lock().unwrap().iter().cloned().collect();
+        let tmp = file.0.with_extension("jsonl.tmp");
+        let written = append(&tmp, &samples).and_then(|_| std::fs::rename(&tmp, &file.0));
+        match written {
+            Ok(()) => file.1 = 0,
+            Err(e) => warn!("Failed to rewrite {}: {}", file.0.display(), e),
+        }
+    }
+
+    /// Samples taken at or after `from`, oldest first
+    pub fn since(&self, from: DateTime<Utc>) -> Vec<MetricSample> {
+        let samples = self.samples.lock().unwrap();
+        let start = samples.partition_point(|s| s.at < from);
+        samples.range(start..).cloned().collect()
+    }
+
+    /// Samples from the most recent sampling round
+    pub fn latest(&self) -> Vec<MetricSample> {
+        let samples = self.samples.lock().unwrap();
+        let Some(last) = samples.back().map(|s| s.at) else {
+            return Vec::new();
+        };
+        let start = samples.partition_point(|s| s.at < last);
+        samples.range(start..).cloned().collect()
+    }
+}
+
+/// Append `items` to `path` as JSON lines
+pub fn append<T: Serialize>(path: &std::path::Path, items: &[T]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = String::new();
+    for item in items {
+        out += &serde_json::to_string(item).map_err(std::io::Error::other)?;
+        out.push('\n');
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(out.as_bytes())
+}
+
+/// Spawn the background task that samples container stats into the history
+pub fn spawn_sampler(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            match state.docker.list_containers_with_stats().await {
+                Ok(containers) => {
+                    // Appending to and compacting the samples file block
+                    let metrics = state.metrics.clone();
+                    let recorded = tokio::task::spawn_blocking(move || metrics.record(Utc::now(), &containers)).await;
+                    if let Err(e) = recorded {
+                        warn!("Failed to record container stats: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to sample container stats: {}", e),
+            }
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+/// A span such as `90s`, `15m`, `6h` or `1d`; bare numbers are seconds
+pub fn parse_span(value: &str) -> Option<chrono::Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: i64 = number.parse().ok()?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "m" => number.checked_mul(60)?,
+        "h" => number.checked_mul(3600)?,
+        "d" => number.checked_mul(86_400)?,
+        _ => return None,
+    };
+    chrono::Duration::try_seconds(seconds).filter(|_| seconds > 0)
+}
+
+/// Average `samples` into steps of `step` starting at `from`, oldest first
+pub fn downsample(samples: &[MetricSample], from: DateTime<Utc>, step: chrono::Duration) -> Vec<StatsPoint> {
+    let mut steps: BTreeMap<i64, Vec<&ContainerStats>> = BTreeMap::new();
+    for sample in samples.iter().filter(|s| s.at >= from) {
+        let index = (sample.at - from).num_milliseconds() / step.num_milliseconds();
+        steps.entry(index).or_default().push(&sample.stats);
+    }
+
+    let mean = |stats: &[&ContainerStats], value: &dyn Fn(&ContainerStats) -> f64| {
+        stats.iter().map(|s| value(s)).sum::<f64>() / stats.len() as f64
+    };
+    let max = |stats: &[&ContainerStats], value: &dyn Fn(&ContainerStats) -> f64| {
+        stats.iter().map(|s| value(s)).fold(f64::MIN, f64::max)
+    };
+    let rate = |stats: &[&ContainerStats], value: &dyn Fn(&ContainerStats) -> Option<f64>| {
+        let known: Vec<f64> = stats.iter().filter_map(|s| value(s)).collect();
+        (!known.is_empty()).then(|| known.iter().sum::<f64>() / known.len() as f64)
+    };
+    steps
+        .into_iter()
+        .map(|(index, stats)| StatsPoint {
+            at: from + step * index as i32,
+            samples: stats.len(),
+            cpu_percent: mean(&stats, &|s| s.cpu_percent),
+            cpu_percent_max: max(&stats, &|s| s.cpu_percent),
+            memory_usage_mb: mean(&stats, &|s| s.memory_usage_mb),
+            memory_usage_mb_max: max(&stats, &|s| s.memory_usage_mb),
+            memory_percent: mean(&stats, &|s| s.memory_percent),
+            network_rx_bytes_per_sec: rate(&stats, &|s| s.network_rx_bytes_per_sec),
+            network_tx_bytes_per_sec: rate(&stats, &|s| s.network_tx_bytes_per_sec),
+            block_read_bytes_per_sec: rate(&stats, &|s| s.block_read_bytes_per_sec),
+            block_write_bytes_per_sec: rate(&stats, &|s| s.block_write_bytes_per_sec),
+            pids: mean(&stats, &|s| s.pids as f64),
+        })
+        .collect()
+}
+
+/// Step means of every container that ran `image`, summed per step, oldest
+/// first; containers since removed still count for the steps they ran in
+pub fn image_history(
+    samples: &[MetricSample],
+    image: &str,
+    from: DateTime<Utc>,
+    step: chrono::Duration,
+) -> Vec<ImageUsagePoint> {
+    let mut by_container: BTreeMap<(Option<&str>, &str), Vec<MetricSample>> = BTreeMap::new();
+    for sample in samples.iter().filter(|s| s.image.as_deref() == Some(image)) {
+        by_container
+            .entry((sample.host.as_deref(), &sample.container))
+            .or_default()
+            .push(sample.clone());
+    }
+
+    let mut steps: BTreeMap<DateTime<Utc>, ImageUsagePoint> = BTreeMap::new();
+    for point in by_container.values().flat_map(|samples| downsample(samples, from, step)) {
+        let sum = steps.entry(point.at).or_insert_with(|| ImageUsagePoint {
+            at: point.at,
+            ..Default::default()
+        });
+        sum.containers += 1;
+        sum.cpu_percent += point.cpu_percent;
+        sum.memory_usage_mb += point.memory_usage_mb;
+        sum.network_rx_bytes_per_sec += point.network_rx_bytes_per_sec.unwrap_or(0.0);
+        sum.network_tx_bytes_per_sec += point.network_tx_bytes_per_sec.unwrap_or(0.0);
+        sum.pids += point.pids;
+    }
+    steps.into_values().collect()
+}
+
+/// Range and step of a history request
+pub fn history_window(query: &StatsHistoryQuery) -> Result<(chrono::Duration, chrono::Duration), String> {
+    let range = match query.range.as_deref().map(parse_span) {
+        None => chrono::Duration::hours(1),
+        Some(Some(range)) if range <= RETENTION => range,
+        Some(_) => {
+            return Err(format!(
+                "range must be a span such as 30m or 6h, at most {}h",
+                RETENTION.num_hours()
+            ))
+        }
+    };
+    let step = match query.step.as_deref().map(parse_span) {
+        None => chrono::Duration::from_std(SAMPLE_INTERVAL).unwrap(),
+        Some(Some(step)) => step,
+        Some(None) => return Err("step must be a span such as 30s or 5m".to_string()),
+    };
+    if range.num_seconds() / step.num_seconds() > MAX_POINTS {
+        return Err(format!("range/step asks for more than {} points", MAX_POINTS));
+    }
+    Ok((range, step))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsHistoryQuery {
+    /// How far back to look, `1h` by default and at most [`RETENTION`]
+    pub range: Option<String>,
+    /// Width of each point, [`SAMPLE_INTERVAL`] by default
+    pub step: Option<String>,
+}
+
+pub fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// GET /api/container/:id/stats/history?range=1h&step=30s - A container's
+/// recorded stats, averaged per step
+pub async fn get_stats_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<StatsHistoryQuery>,
+) -> Response {
+    let (range, step) = match history_window(&query) {
+        Ok(window) => window,
+        Err(message) => return bad_request(message),
+    };
+
+    let container = match state.docker.get_container(&id).await {
+        Ok(Some(container)) => container,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "Container not found",
+                    "id": id
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to get container '{}': {}", id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to get container",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let to = Utc::now();
+    let from = to - range;
+    let samples: Vec<MetricSample> = state
+        .metrics
+        .since(from)
+        .into_iter()
+        .filter(|s| s.container == container.name && s.host == container.host)
+        .collect();
+    Json(StatsHistory {
+        points: downsample(&samples, from, step),
+        container: container.name,
+        from,
+        to,
+        step_secs: step.num_seconds() as u64,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(at: DateTime<Utc>, cpu: f64) -> MetricSample {
+        MetricSample {
+            container: "shop-api-1".to_string(),
+            host: None,
+            image: Some("shop/api".to_string()),
+            at,
+            stats: ContainerStats {
+                cpu_percent: cpu,
+                pids: 4,
+                network_rx_bytes_per_sec: (cpu > 10.0).then_some(100.0),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn image_history_sums_every_container_of_the_image() {
+        let now: DateTime<Utc> = "2026-10-15T12:00:00Z".parse().unwrap();
+        let minute = chrono::Duration::minutes(1);
+        let on = |container: &str, host: Option<&str>, image: &str, at, cpu| MetricSample {
+            container: container.to_string(),
+            host: host.map(str::to_string),
+            image: Some(image.to_string()),
+            ..sample(at, cpu)
+        };
+        let samples = [
+            on("shop-api-1", None, "shop/api", now - minute * 2, 10.0),
+            on("shop-api-1", None, "shop/api", now - minute, 20.0),
+            on("shop-api-1", None, "shop/api", now - minute + chrono::Duration::seconds(30), 40.0),
+            // Same name on another daemon is another container
+            on("shop-api-1", Some("edge"), "shop/api", now - minute, 5.0),
+            on("shop-web-1", None, "shop/web", now - minute, 50.0),
+            MetricSample { image: None, ..on("shop-api-2", None, "shop/api", now - minute, 50.0) },
+        ];
+
+        let points = image_history(&samples, "shop/api", now - minute * 2, minute);
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[0].containers, points[0].cpu_percent), (1, 10.0));
+        // The first container's mean of 30 plus the edge container's 5
+        assert_eq!((points[1].containers, points[1].cpu_percent, points[1].pids), (2, 35.0, 8.0));
+        // The edge container has no rate yet and counts as 0
+        assert_eq!(points[1].network_rx_bytes_per_sec, 100.0);
+        assert_eq!(points[1].at, now - minute);
+    }
+
+    #[test]
+    fn samples_survive_a_restart_and_average_per_step() {
+        let dir = std::env::temp_dir().join(format!("flowscope-stats-history-{}", std::process::id()));
+        let path = dir.join("stats-history.jsonl");
+        let now: DateTime<Utc> = "2026-10-15T12:00:00Z".parse().unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        let lines: Vec<String> = [
+            sample(now - RETENTION - chrono::Duration::minutes(1), 1.0),
+            sample(now - chrono::Duration::seconds(90), 10.0),
+            sample(now - chrono::Duration::seconds(60), 20.0),
+            sample(now - chrono::Duration::seconds(30), 30.0),
+        ]
+        .iter()
+        .map(|s| serde_json::to_string(s).unwrap())
+        .chain(["{\"container\":\"shop-".to_string()])
+        .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        // The expired sample and the torn last line are dropped
+        let history = MetricsHistory::open_at(path.clone(), now);
+        assert_eq!(history.since(now - RETENTION * 2).len(), 3);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let minute = chrono::Duration::minutes(1);
+        let points = downsample(&history.since(now - RETENTION), now - minute * 2, minute);
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[0].samples, points[0].cpu_percent), (1, 10.0));
+        assert_eq!(points[0].network_rx_bytes_per_sec, None);
+        assert_eq!((points[1].samples, points[1].cpu_percent, points[1].cpu_percent_max), (2, 25.0, 30.0));
+        assert_eq!(points[1].network_rx_bytes_per_sec, Some(100.0));
+        assert_eq!(points[1].at, now - minute);
+
+        let summed = image_history(&history.since(now - RETENTION), "shop/api", now - minute * 2, minute);
+        assert_eq!(summed.len(), 2);
+        assert!(image_history(&history.since(now - RETENTION), "shop/web", now - minute * 2, minute).is_empty());
+
+        assert_eq!(parse_span("90s"), Some(chrono::Duration::seconds(90)));
+        assert_eq!(parse_span("6h"), Some(chrono::Duration::hours(6)));
+        assert_eq!(parse_span("0m"), None);
+        assert_eq!(parse_span("1w"), None);
+        assert_eq!(parse_span("9223372036854775807s"), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
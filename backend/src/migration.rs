@@ -0,0 +1,423 @@
+//! Legacy-to-rust migration assistant
+//!
+//! A container `<name>-prod` whose `<name>-rust-prod` counterpart exists forms
+//! a migration pair (see [`ContainerInfo::rust_equivalent`]). For each pair
+//! `GET /api/migrations` probes both sides' first published TCP port, compares
+//! their CPU and memory over a window of the stats history and checks the
+//! rust side against a fixed set of readiness criteria.
+//! `POST /api/migrations/:legacy/cutover` re-evaluates them and, when every
+//! one passes, starts a job that stops the legacy container.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::{
+    metrics::{self, MetricSample},
+    models::{
+        ActionResult, ContainerInfo, ContainerStatus, Job, MigrationReport, MigrationSide, ReadinessCriterion,
+        UsageSummary,
+    },
+    AppState,
+};
+
+pub const DEFAULT_WINDOW_MINUTES: u32 = 60;
+/// Stats samples the rust container needs in the window, so a container that
+/// just started cannot look cheap
+pub const MIN_SAMPLES: usize = 10;
+/// How much more the rust container may use on average than the legacy one
+pub const USAGE_TOLERANCE: f64 = 1.1;
+/// CPU points ignored when comparing, so two idle containers compare equal
+pub const CPU_SLACK_PERCENT: f64 = 1.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("'{0}' has no rust equivalent")]
+    NotPaired(String),
+    #[error("invalid window: {0}")]
+    InvalidWindow(String),
+    #[error("'{container}' is not ready to cut over: {failed}")]
+    NotReady { container: String, failed: String },
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl MigrationError {
+    fn status(&self) -> StatusCode {
+        match self {
+            MigrationError::NotPaired(_) => StatusCode::NOT_FOUND,
+            MigrationError::InvalidWindow(_) => StatusCode::BAD_REQUEST,
+            MigrationError::NotReady { .. } => StatusCode::CONFLICT,
+            MigrationError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for MigrationError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Migration error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WindowQuery {
+    pub minutes: Option<u32>,
+}
+
+impl WindowQuery {
+    /// Requested window, defaulting to [`DEFAULT_WINDOW_MINUTES`] and capped
+    /// at the stats retention
+    fn minutes(&self) -> Result<u32, MigrationError> {
+        let max = metrics::RETENTION.num_minutes() as u32;
+        match self.minutes {
+            None => Ok(DEFAULT_WINDOW_MINUTES),
+            Some(minutes) if (1..=max).contains(&minutes) => Ok(minutes),
+            Some(_) => Err(MigrationError::InvalidWindow(format!("minutes must be 1-{}", max))),
+        }
+    }
+}
+
+/// Legacy containers paired with their rust equivalents, by legacy name
+pub fn pairs(containers: &[ContainerInfo]) -> Vec<(&ContainerInfo, &ContainerInfo)> {
+    containers
+        .iter()
+        .filter_map(|legacy| {
+            let rust_name = legacy.rust_equivalent.as_deref().filter(|r| *r != legacy.name)?;
+            let rust = containers.iter().find(|c| c.name == rust_name)?;
+            Some((legacy, rust))
+        })
+        .collect()
+}
+
+/// Usage of `container` across `samples`, or `None` without any
+pub fn summarize(samples: &[MetricSample], container: &str) -> Option<UsageSummary> {
+    let stats: Vec<_> = samples.iter().filter(|s| s.container == container).map(|s| &s.stats).collect();
+    if stats.is_empty() {
+        return None;
+    }
+    let count = stats.len() as f64;
+    Some(UsageSummary {
+        samples: stats.len(),
+        avg_cpu_percent: stats.iter().map(|s| s.cpu_percent).sum::<f64>() / count,
+        peak_cpu_percent: stats.iter().map(|s| s.cpu_percent).fold(0.0, f64::max),
+        avg_memory_mb: stats.iter().map(|s| s.memory_usage_mb).sum::<f64>() / count,
+        peak_memory_mb: stats.iter().map(|s| s.memory_usage_mb).fold(0.0, f64::max),
+    })
+}
+
+fn criterion(name: &str, passed: bool, detail: String) -> ReadinessCriterion {
+    ReadinessCriterion {
+        name: name.to_string(),
+        passed,
+        detail,
+    }
+}
+
+/// Check the rust side of a pair against the legacy side
+pub fn evaluate(legacy: &MigrationSide, rust: &MigrationSide) -> Vec<ReadinessCriterion> {
+    let mut criteria = Vec::new();
+
+    let status = format!("{:?}", rust.status).to_lowercase();
+    criteria.push(criterion(
+        "rust-running",
+        matches!(
+            rust.status,
+            ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+        ),
+        format!("{} is {}", rust.container, status),
+    ));
+    criteria.push(match rust.health.as_deref() {
+        Some("unhealthy") => criterion("rust-healthy", false, format!("{} fails its health check", rust.container)),
+        Some(health) => criterion("rust-healthy", true, format!("{} is {}", rust.container, health)),
+        None => criterion("rust-healthy", true, format!("{} has no health check", rust.container)),
+    });
+
+    criteria.push(match (&legacy.probe, &rust.probe) {
+        (_, Some(probe)) if probe.reachable => criterion(
+            "rust-reachable",
+            true,
+            format!("port {} answered", rust.probed_port.unwrap_or_default()),
+        ),
+        (_, Some(probe)) => criterion(
+            "rust-reachable",
+            false,
+            format!(
+                "port {} did not answer: {}",
+                rust.probed_port.unwrap_or_default(),
+                probe.error.as_deref().unwrap_or("unknown error")
+            ),
+        ),
+        (Some(_), None) => criterion(
+            "rust-reachable",
+            false,
+            format!("{} publishes a TCP port but {} does not", legacy.container, rust.container),
+        ),
+        (None, None) => criterion("rust-reachable", true, "neither container publishes a TCP port".to_string()),
+    });
+
+    let samples = rust.usage.as_ref().map_or(0, |u| u.samples);
+    criteria.push(criterion(
+        "rust-sampled",
+        samples >= MIN_SAMPLES,
+        format!("{} stats samples in the window, {} needed", samples, MIN_SAMPLES),
+    ));
+
+    match (&legacy.usage, &rust.usage) {
+        (Some(old), Some(new)) => {
+            let cpu_budget = old.avg_cpu_percent * USAGE_TOLERANCE + CPU_SLACK_PERCENT;
+            criteria.push(criterion(
+                "cpu-usage",
+                new.avg_cpu_percent <= cpu_budget,
+                format!(
+                    "average CPU {:.1}% against {:.1}% for {}",
+                    new.avg_cpu_percent, old.avg_cpu_percent, legacy.container
+                ),
+            ));
+            criteria.push(criterion(
+                "memory-usage",
+                new.avg_memory_mb <= old.avg_memory_mb * USAGE_TOLERANCE,
+                format!(
+                    "average memory {:.1} MB against {:.1} MB for {}",
+                    new.avg_memory_mb, old.avg_memory_mb, legacy.container
+                ),
+            ));
+        }
+        _ => {
+            let missing = if legacy.usage.is_none() { &legacy.container } else { &rust.container };
+            let detail = format!("no stats for {} in the window", missing);
+            criteria.push(criterion("cpu-usage", false, detail.clone()));
+            criteria.push(criterion("memory-usage", false, detail));
+        }
+    }
+    criteria
+}
+
+/// The first TCP port `container` publishes on the host
+fn probed_port(container: &ContainerInfo) -> Option<u16> {
+    container
+        .ports
+        .iter()
+        .filter(|p| p.protocol.eq_ignore_ascii_case("tcp"))
+        .find_map(|p| p.host_port)
+}
+
+async fn side(state: &AppState, container: &ContainerInfo, samples: &[MetricSample]) -> MigrationSide {
+    let probed_port = probed_port(container);
+    let probe = match probed_port {
+        Some(port) => Some(state.endpoints.probe_local(port).await),
+        None => None,
+    };
+    MigrationSide {
+        container: container.name.clone(),
+        status: container.status.clone(),
+        health: container.health.clone(),
+        probed_port,
+        probe,
+        usage: summarize(samples, &container.name),
+    }
+}
+
+/// Probe both sides of a pair at once and judge the rust side
+async fn report(
+    state: &AppState,
+    legacy: &ContainerInfo,
+    rust: &ContainerInfo,
+    window_minutes: u32,
+) -> MigrationReport {
+    let generated_at = Utc::now();
+    let samples = state
+        .metrics
+        .since(generated_at - chrono::Duration::minutes(window_minutes.into()));
+    let (legacy, rust) = tokio::join!(side(state, legacy, &samples), side(state, rust, &samples));
+    let criteria = evaluate(&legacy, &rust);
+    MigrationReport {
+        ready: criteria.iter().all(|c| c.passed),
+        legacy,
+        rust,
+        window_minutes,
+        criteria,
+        generated_at,
+    }
+}
+
+/// Report on the pair whose legacy side is `name`
+async fn report_for(state: &AppState, name: &str, window_minutes: u32) -> Result<MigrationReport, MigrationError> {
+    let containers = state.docker.list_containers().await?;
+    let (legacy, rust) = pairs(&containers)
+        .into_iter()
+        .find(|(legacy, _)| legacy.name == name || legacy.id.starts_with(name))
+        .ok_or_else(|| MigrationError::NotPaired(name.to_string()))?;
+    Ok(report(state, legacy, rust, window_minutes).await)
+}
+
+/// GET /api/migrations?minutes=N - Readiness of every legacy/rust pair
+pub async fn list_migrations(
+    State(state): State<AppState>,
+    Query(query): Query<WindowQuery>,
+) -> Result<Json<Vec<MigrationReport>>, MigrationError> {
+    let minutes = query.minutes()?;
+    let containers = state.docker.list_containers().await?;
+    let mut reports = Vec::new();
+    for (legacy, rust) in pairs(&containers) {
+        reports.push(report(&state, legacy, rust, minutes).await);
+    }
+    Ok(Json(reports))
+}
+
+/// GET /api/migrations/:legacy?minutes=N - Readiness of one pair
+pub async fn get_migration(
+    State(state): State<AppState>,
+    Path(legacy): Path<String>,
+    Query(query): Query<WindowQuery>,
+) -> Result<Json<MigrationReport>, MigrationError> {
+    Ok(Json(report_for(&state, &legacy, query.minutes()?).await?))
+}
+
+/// POST /api/migrations/:legacy/cutover?minutes=N - Start a job stopping the
+/// legacy container once its rust equivalent is ready
+pub async fn cut_over(
+    State(state): State<AppState>,
+    Path(legacy): Path<String>,
+    Query(query): Query<WindowQuery>,
+) -> Result<impl IntoResponse, MigrationError> {
+    let report = report_for(&state, &legacy, query.minutes()?).await?;
+    if !report.ready {
+        let failed: Vec<&str> = report.criteria.iter().filter(|c| !c.passed).map(|c| c.name.as_str()).collect();
+        return Err(MigrationError::NotReady {
+            container: report.legacy.container,
+            failed: failed.join(", "),
+        });
+    }
+
+    info!("Cutting {} over to {}", report.legacy.container, report.rust.container);
+    let discovery = state.docker.clone();
+    let (name, rust) = (report.legacy.container.clone(), report.rust.container);
+    let job: Job = state.jobs.spawn("migration-cutover", &report.legacy.container, 1, |handle| async move {
+        let result = match discovery.stop_container(&name).await {
+            Ok(Some(result)) => result,
+            Ok(None) => ActionResult {
+                success: false,
+                container_id: String::new(),
+                container_name: name.clone(),
+                action: "stop".to_string(),
+                message: "Container no longer exists".to_string(),
+            },
+            Err(e) => return Err(format!("stopping {} failed: {}", name, e)),
+        };
+        let success = result.success;
+        handle.record(result);
+        if success {
+            info!("Cut {} over to {}", name, rust);
+            Ok(())
+        } else {
+            Err(format!("{} could not be stopped", name))
+        }
+    });
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::DockerDiscovery;
+    use crate::docker_api::fake::FakeDocker;
+    use crate::models::{ContainerStats, ProbeOutcome};
+
+    fn side(container: &str, status: ContainerStatus, port: bool, usage: Option<(usize, f64, f64)>) -> MigrationSide {
+        MigrationSide {
+            container: container.to_string(),
+            status,
+            health: None,
+            probed_port: port.then_some(8080),
+            probe: port.then_some(ProbeOutcome {
+                reachable: true,
+                connect_ms: Some(0.4),
+                error: None,
+                tls: None,
+                tls_error: None,
+            }),
+            usage: usage.map(|(samples, cpu, memory)| UsageSummary {
+                samples,
+                avg_cpu_percent: cpu,
+                peak_cpu_percent: cpu,
+                avg_memory_mb: memory,
+                peak_memory_mb: memory,
+            }),
+        }
+    }
+
+    fn failed(criteria: &[ReadinessCriterion]) -> Vec<&str> {
+        criteria.iter().filter(|c| !c.passed).map(|c| c.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn pairs_follow_the_naming_convention() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let base = discovery.list_containers().await.unwrap().remove(0);
+        let named = |name: &str| ContainerInfo {
+            name: name.to_string(),
+            rust_equivalent: (!name.contains("rust")).then(|| name.replace("-prod", "-rust-prod")),
+            ..base.clone()
+        };
+        let containers = [named("auth-prod"), named("auth-rust-prod"), named("billing-prod"), named("cache")];
+        let found: Vec<(&str, &str)> = pairs(&containers)
+            .into_iter()
+            .map(|(legacy, rust)| (legacy.name.as_str(), rust.name.as_str()))
+            .collect();
+        assert_eq!(found, [("auth-prod", "auth-rust-prod")]);
+    }
+
+    #[test]
+    fn usage_is_summarized_per_container() {
+        let at = Utc::now();
+        let sample = |container: &str, cpu, memory| MetricSample {
+            container: container.to_string(),
+            host: None,
+            image: None,
+            at,
+            stats: ContainerStats {
+                cpu_percent: cpu,
+                memory_usage_mb: memory,
+                ..Default::default()
+            },
+        };
+        let samples = [sample("a", 10.0, 100.0), sample("b", 90.0, 900.0), sample("a", 30.0, 300.0)];
+        let usage = summarize(&samples, "a").unwrap();
+        assert_eq!((usage.samples, usage.avg_cpu_percent, usage.peak_cpu_percent), (2, 20.0, 30.0));
+        assert_eq!((usage.avg_memory_mb, usage.peak_memory_mb), (200.0, 300.0));
+        assert!(summarize(&samples, "c").is_none());
+    }
+
+    #[test]
+    fn criteria_compare_the_rust_side_with_the_legacy_side() {
+        let legacy = side("auth-prod", ContainerStatus::Running, true, Some((120, 40.0, 512.0)));
+
+        let ready = side("auth-rust-prod", ContainerStatus::Healthy, true, Some((120, 12.0, 64.0)));
+        assert!(failed(&evaluate(&legacy, &ready)).is_empty());
+
+        let hungry = side("auth-rust-prod", ContainerStatus::Running, true, Some((120, 60.0, 600.0)));
+        assert_eq!(failed(&evaluate(&legacy, &hungry)), ["cpu-usage", "memory-usage"]);
+
+        let fresh = side("auth-rust-prod", ContainerStatus::Running, false, Some((3, 1.0, 10.0)));
+        assert_eq!(failed(&evaluate(&legacy, &fresh)), ["rust-reachable", "rust-sampled"]);
+
+        let stopped = side("auth-rust-prod", ContainerStatus::Exited, true, None);
+        assert_eq!(
+            failed(&evaluate(&legacy, &stopped)),
+            ["rust-running", "rust-sampled", "cpu-usage", "memory-usage"]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Network paths between two containers
+//!
+//! `GET /api/graph/path?from=<a>&to=<b>` answers "how does traffic get from
+//! the frontend to this DB": the shortest chain of user-defined networks
+//! linking the two containers, hopping through containers attached to more
+//! than one of them. Hops through proxies and gateways, recognised from their
+//! labels, are preferred; other multi-homed containers are only used when no
+//! gateway route exists and are flagged as such. The path is returned as a
+//! mini-flowchart alternating containers and the networks between them.
+
+use std::collections::{HashMap, VecDeque};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{
+    models::{ConnectionType, ContainerInfo, ContainerStatus, Flowchart, FlowchartConnection, FlowchartNode, NodeType},
+    AppState,
+};
+
+/// Label marking a container as a `proxy` or `gateway` explicitly
+pub const ROLE_LABEL: &str = "flowscope.role";
+
+/// Networks that never link containers by name
+const UNROUTED: &[&str] = &["bridge", "host", "none"];
+
+/// Why `container` looks like a proxy or gateway, from its labels
+pub fn gateway_role(container: &ContainerInfo) -> Option<String> {
+    if let Some(role) = container.labels.get(ROLE_LABEL).filter(|r| matches!(r.as_str(), "proxy" | "gateway")) {
+        return Some(format!("{} ({}={})", role, ROLE_LABEL, role));
+    }
+    let labelled = |prefix: &str| container.labels.keys().any(|k| k.starts_with(prefix));
+    if labelled("traefik.") {
+        Some("proxy (traefik labels)".to_string())
+    } else if labelled("caddy") {
+        Some("proxy (caddy labels)".to_string())
+    } else if labelled("com.github.jrcs.letsencrypt_nginx_proxy_companion.nginx_proxy") {
+        Some("proxy (nginx-proxy label)".to_string())
+    } else {
+        None
+    }
+}
+
+fn routed(container: &ContainerInfo) -> impl Iterator<Item = &String> {
+    container.networks.iter().filter(|n| !UNROUTED.contains(&n.as_str()))
+}
+
+/// One container on a path and the network leading to the next one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hop<'a> {
+    pub container: &'a ContainerInfo,
+    /// Absent for the destination
+    pub via: Option<&'a str>,
+}
+
+/// Shortest path from `from` to `to`, letting only containers `relay` accepts
+/// pass traffic on
+fn shortest<'a>(
+    containers: &'a [ContainerInfo],
+    from: &'a ContainerInfo,
+    to: &'a ContainerInfo,
+    relay: impl Fn(&ContainerInfo) -> bool,
+) -> Option<Vec<Hop<'a>>> {
+    let mut sorted: Vec<&ContainerInfo> = containers.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Reached container name -> (previous container, network used)
+    let mut reached: HashMap<&str, Option<(&ContainerInfo, &str)>> = HashMap::from([(from.name.as_str(), None)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(current) = queue.pop_front() {
+        if current.name == to.name {
+            break;
+        }
+        if current.name != from.name && !relay(current) {
+            continue;
+        }
+        let mut networks: Vec<&String> = routed(current).collect();
+        networks.sort();
+        for network in networks {
+            for next in sorted.iter().filter(|c| c.networks.contains(network)) {
+                if !reached.contains_key(next.name.as_str()) {
+                    reached.insert(&next.name, Some((current, network)));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    reached.get(to.name.as_str())?;
+    let mut hops = vec![Hop { container: to, via: None }];
+    let mut at = to.name.as_str();
+    while let Some(Some((previous, network))) = reached.get(at) {
+        hops.push(Hop {
+            container: previous,
+            via: Some(network),
+        });
+        at = &previous.name;
+    }
+    hops.reverse();
+    Some(hops)
+}
+
+/// The path from `from` to `to`, through gateways when possible; `None` when
+/// no chain of shared networks links them
+pub fn find<'a>(containers: &'a [ContainerInfo], from: &'a ContainerInfo, to: &'a ContainerInfo) -> Option<Vec<Hop<'a>>> {
+    shortest(containers, from, to, |c| gateway_role(c).is_some()).or_else(|| shortest(containers, from, to, |_| true))
+}
+
+fn container_node(container: &ContainerInfo, description: String, node_type: NodeType) -> FlowchartNode {
+    FlowchartNode {
+        id: container.id.clone(),
+        name: container.name.clone(),
+        description,
+        status: container.status.clone(),
+        node_type,
+        category: container.category.clone(),
+        port: container.ports.first().and_then(|p| p.host_port),
+        child_flowchart: Some(container.name.clone()),
+        metrics: None,
+        stats: None,
+        warnings: Vec::new(),
+        probe: None,
+        vulnerabilities: None,
+        position: None,
+    }
+}
+
+/// The path as a flowchart: containers linked through network nodes, which
+/// open the network's own flowchart
+pub fn path_flowchart(from: &ContainerInfo, to: &ContainerInfo, hops: Option<&[Hop<'_>]>) -> Flowchart {
+    let id = format!("path:{}:{}", from.name, to.name);
+    let name = format!("{} → {}", from.name, to.name);
+    let Some(hops) = hops else {
+        return Flowchart {
+            id,
+            name,
+            description: "No shared network links these containers".to_string(),
+            nodes: vec![
+                container_node(from, "Source".to_string(), NodeType::Service),
+                container_node(to, "Destination".to_string(), NodeType::Service),
+            ],
+            connections: Vec::new(),
+            parent_id: Some("system-overview".to_string()),
+        };
+    };
+
+    let mut nodes = Vec::new();
+    let mut connections = Vec::new();
+    let mut connect = |source: &str, target: &str, connection_type: ConnectionType| {
+        connections.push(FlowchartConnection {
+            id: format!("{}-to-{}", source, target),
+            source: source.to_string(),
+            target: target.to_string(),
+            label: None,
+            connection_type,
+        });
+    };
+    let mut unlabelled = 0;
+    for (i, hop) in hops.iter().enumerate() {
+        let container = hop.container;
+        let node = if i == 0 {
+            container_node(container, "Source".to_string(), NodeType::Service)
+        } else if i == hops.len() - 1 {
+            container_node(container, "Destination".to_string(), NodeType::Service)
+        } else if let Some(role) = gateway_role(container) {
+            container_node(container, format!("Relays traffic as a {}", role), NodeType::Process)
+        } else {
+            unlabelled += 1;
+            let mut node = container_node(container, "Attached to both networks".to_string(), NodeType::Process);
+            node.warnings.push("Not labelled as a proxy or gateway".to_string());
+            node
+        };
+        nodes.push(node);
+
+        if let Some(network) = hop.via {
+            let network_id = format!("network:{}", network);
+            if !nodes.iter().any(|n| n.id == network_id) {
+                nodes.push(FlowchartNode {
+                    id: network_id.clone(),
+                    name: network.to_string(),
+                    description: format!("Network {}", network),
+                    status: ContainerStatus::Running,
+                    node_type: NodeType::Group,
+                    category: container.category.clone(),
+                    port: None,
+                    child_flowchart: Some(network_id.clone()),
+                    metrics: None,
+                    stats: None,
+                    warnings: Vec::new(),
+                    probe: None,
+                    vulnerabilities: None,
+                    position: None,
+                });
+            }
+            connect(&container.id, &network_id, ConnectionType::Network);
+            connect(&network_id, &hops[i + 1].container.id, ConnectionType::Network);
+        }
+    }
+
+    let networks = hops.iter().filter(|h| h.via.is_some()).count();
+    let relays = hops.len().saturating_sub(2);
+    let mut description = format!(
+        "{} network{} and {} relay{}",
+        networks,
+        if networks == 1 { "" } else { "s" },
+        relays,
+        if relays == 1 { "" } else { "s" }
+    );
+    if unlabelled > 0 {
+        description.push_str(&format!(", {} not labelled as a proxy or gateway", unlabelled));
+    }
+    Flowchart {
+        id,
+        name,
+        description,
+        nodes,
+        connections,
+        parent_id: Some("system-overview".to_string()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PathQuery {
+    /// Container name or id traffic starts from
+    pub from: String,
+    /// Container name or id traffic goes to
+    pub to: String,
+}
+
+/// GET /api/graph/path?from=&to= - The network path between two containers as a flowchart
+pub async fn get_path(State(state): State<AppState>, Query(query): Query<PathQuery>) -> impl IntoResponse {
+    let containers = match state.docker.list_containers().await {
+        Ok(containers) => containers,
+        Err(e) => {
+            error!("Failed to list containers for a network path: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list containers",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response();
+        }
+    };
+    let lookup = |id: &str| containers.iter().find(|c| c.id == id || c.name == id);
+    let (Some(from), Some(to)) = (lookup(&query.from), lookup(&query.to)) else {
+        let missing = if lookup(&query.from).is_none() { &query.from } else { &query.to };
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": missing
+            })),
+        )
+            .into_response();
+    };
+
+    let hops = find(&containers, from, to);
+    (StatusCode::OK, Json(path_flowchart(from, to, hops.as_deref()))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake;
+    use serde_json::json;
+
+    fn container(name: &str, networks: &[&str], labels: serde_json::Value) -> ContainerInfo {
+        ContainerInfo {
+            id: format!("{}-id", name),
+            image: "shop/app".to_string(),
+            networks: networks.iter().map(|n| n.to_string()).collect(),
+            labels: serde_json::from_value(labels).unwrap(),
+            ..fake::container(name)
+        }
+    }
+
+    fn names(hops: &[Hop<'_>]) -> Vec<(String, Option<String>)> {
+        hops.iter()
+            .map(|h| (h.container.name.clone(), h.via.map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn prefers_gateways_over_other_multi_homed_containers() {
+        let containers = [
+            container("web", &["frontend", "bridge"], json!({})),
+            container("debug-shell", &["frontend", "backend"], json!({})),
+            container("edge", &["frontend", "backend"], json!({ "traefik.enable": "true" })),
+            container("db", &["backend", "bridge"], json!({})),
+        ];
+        let (web, db) = (&containers[0], &containers[3]);
+        let hops = find(&containers, web, db).unwrap();
+        let via = |n: &str| Some(n.to_string());
+        assert_eq!(
+            names(&hops),
+            [
+                ("web".to_string(), via("frontend")),
+                ("edge".to_string(), via("backend")),
+                ("db".to_string(), None)
+            ]
+        );
+
+        let flowchart = path_flowchart(web, db, Some(&hops));
+        let ids: Vec<&str> = flowchart.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["web-id", "network:frontend", "edge-id", "network:backend", "db-id"]);
+        assert_eq!(flowchart.connections.len(), 4);
+        assert_eq!(flowchart.description, "2 networks and 1 relay");
+
+        // Without the gateway the other multi-homed container is used, flagged
+        let without: Vec<ContainerInfo> = containers.iter().filter(|c| c.name != "edge").cloned().collect();
+        let hops = find(&without, &without[0], &without[2]).unwrap();
+        let flowchart = path_flowchart(&without[0], &without[2], Some(&hops));
+        assert_eq!(flowchart.nodes[2].warnings, ["Not labelled as a proxy or gateway"]);
+    }
+
+    #[test]
+    fn default_bridge_does_not_link_containers() {
+        let containers = [
+            container("web", &["bridge"], json!({})),
+            container("db", &["bridge"], json!({})),
+        ];
+        assert_eq!(find(&containers, &containers[0], &containers[1]), None);
+        let flowchart = path_flowchart(&containers[0], &containers[1], None);
+        assert!(flowchart.connections.is_empty());
+        assert_eq!(flowchart.nodes.len(), 2);
+    }
+}
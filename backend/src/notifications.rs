@@ -0,0 +1,391 @@
+//! Notification channels
+//!
+//! A channel is somewhere FlowScope sends alerts and container events: a
+//! webhook, a Slack or Discord incoming webhook or an email address, limited
+//! to some container categories or a minimum severity if need be. Payloads
+//! are rendered from a Handlebars template stored with the channel, so they
+//! can match whatever JSON shape the receiving incident tooling expects;
+//! Slack and Discord channels without one get formatted blocks and embeds
+//! from the [chat](crate::chat) notifiers. Templates
+//! see a [`NotificationContext`]; `{{{json value}}}` writes any part of it as
+//! JSON, and strings interpolated into JSON channels are escaped for JSON.
+//! Sending is up to the delivery module.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::{
+    models::{
+        AlertMetric, AlertOperator, ContainerInfo, NotificationChannel, NotificationKind, RenderedNotification,
+        ServiceCategory, Severity,
+    },
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub type NotificationStore = JsonStore<NotificationChannel>;
+
+const DEFAULT_WEBHOOK_TEMPLATE: &str = "{{{json this}}}";
+const DEFAULT_SLACK_TEMPLATE: &str = r#"{"text": "{{summary}}"}"#;
+const DEFAULT_DISCORD_TEMPLATE: &str = r#"{"content": "{{summary}}"}"#;
+const DEFAULT_EMAIL_TEMPLATE: &str = "Subject: [FlowScope] {{summary}}
+
+{{summary}}
+
+Container: {{container.name}} ({{container.image}})
+Status: {{container.status}}
+Event: {{event}} at {{timestamp}}
+";
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotificationError {
+    #[error("notification channel '{0}' not found")]
+    NotFound(String),
+    #[error("notification channel '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("invalid notification channel: {0}")]
+    Invalid(String),
+    #[error("template error: {0}")]
+    Template(String),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl NotificationError {
+    fn status(&self) -> StatusCode {
+        match self {
+            NotificationError::NotFound(_) => StatusCode::NOT_FOUND,
+            NotificationError::AlreadyExists(_) => StatusCode::CONFLICT,
+            NotificationError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for NotificationError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Notification error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Alert fields available to templates as `alert.*`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertContext {
+    pub rule: String,
+    pub metric: AlertMetric,
+    pub operator: AlertOperator,
+    pub threshold: f64,
+    pub value: f64,
+    pub fired_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Container fields available to templates as `container.*`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerContext {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub status: String,
+    pub category: ServiceCategory,
+    pub labels: HashMap<String, String>,
+}
+
+impl From<&ContainerInfo> for ContainerContext {
+    fn from(container: &ContainerInfo) -> Self {
+        Self {
+            id: container.id.clone(),
+            name: container.name.clone(),
+            image: container.image.clone(),
+            status: serde_json::to_value(&container.status)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default(),
+            category: container.category.clone(),
+            labels: container.labels.clone(),
+        }
+    }
+}
+
+/// Everything a payload template can reference
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationContext {
+    /// e.g. `alert.firing`, `alert.resolved`, `container.unhealthy`
+    pub event: String,
+    /// One-line description of the event
+    pub summary: String,
+    pub severity: Severity,
+    pub timestamp: DateTime<Utc>,
+    /// Absent for container events that are not alerts
+    pub alert: Option<AlertContext>,
+    pub container: ContainerContext,
+}
+
+impl NotificationContext {
+    /// A firing alert with every field set, used to validate and preview templates
+    pub fn sample() -> Self {
+        let now = Utc::now();
+        Self {
+            event: "alert.firing".to_string(),
+            summary: "memory-high: memory_percent 95.2 > 90 on application-api-1".to_string(),
+            severity: Severity::Warning,
+            timestamp: now,
+            alert: Some(AlertContext {
+                rule: "memory-high".to_string(),
+                metric: AlertMetric::MemoryPercent,
+                operator: AlertOperator::Gt,
+                threshold: 90.0,
+                value: 95.2,
+                fired_at: now,
+                resolved_at: Some(now),
+            }),
+            container: ContainerContext {
+                id: "b1b2c3d4e5f6".to_string(),
+                name: "application-api-1".to_string(),
+                image: "flowscope/api:latest".to_string(),
+                status: "running".to_string(),
+                category: ServiceCategory::Application,
+                labels: [("com.docker.compose.service".to_string(), "api".to_string())].into(),
+            },
+        }
+    }
+}
+
+handlebars_helper!(json: |value: Json| serde_json::to_string(value).unwrap_or_default());
+
+/// JSON channels escape interpolated strings so they stay valid inside JSON strings
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn template(channel: &NotificationChannel) -> &str {
+    channel.template.as_deref().unwrap_or(match channel.kind {
+        NotificationKind::Webhook => DEFAULT_WEBHOOK_TEMPLATE,
+        NotificationKind::Slack => DEFAULT_SLACK_TEMPLATE,
+        NotificationKind::Discord => DEFAULT_DISCORD_TEMPLATE,
+        NotificationKind::Email => DEFAULT_EMAIL_TEMPLATE,
+    })
+}
+
+/// Render a channel's payload. In strict mode referencing a field the context
+/// does not have is an error, which catches typos when a template is saved.
+pub fn render(
+    channel: &NotificationChannel,
+    context: &NotificationContext,
+    strict: bool,
+) -> Result<RenderedNotification, NotificationError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(strict);
+    handlebars.register_helper("json", Box::new(json));
+    let json_payload = channel.kind != NotificationKind::Email;
+    if json_payload {
+        handlebars.register_escape_fn(json_escape);
+    } else {
+        handlebars.register_escape_fn(handlebars::no_escape);
+    }
+
+    let body = handlebars
+        .render_template(template(channel), context)
+        .map_err(|e| NotificationError::Template(e.to_string()))?;
+    if json_payload {
+        serde_json::from_str::<serde::de::IgnoredAny>(&body)
+            .map_err(|e| NotificationError::Template(format!("payload is not valid JSON: {}", e)))?;
+    }
+
+    Ok(RenderedNotification {
+        channel: channel.name.clone(),
+        content_type: if json_payload { "application/json" } else { "text/plain" }.to_string(),
+        body,
+    })
+}
+
+/// Whether `channel` wants to hear about `context`
+pub fn wants(channel: &NotificationChannel, context: &NotificationContext) -> bool {
+    (channel.categories.is_empty() || channel.categories.contains(&context.container.category))
+        && channel.min_severity.is_none_or(|min| context.severity >= min)
+}
+
+pub fn validate(channel: &NotificationChannel) -> Result<(), NotificationError> {
+    if channel.name.trim().is_empty() {
+        return Err(NotificationError::Invalid("name must not be empty".to_string()));
+    }
+    let target_ok = match channel.kind {
+        NotificationKind::Webhook | NotificationKind::Slack | NotificationKind::Discord => reqwest::Url::parse(&channel.target)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host()),
+        NotificationKind::Email => channel.target.contains('@'),
+    };
+    if !target_ok {
+        return Err(NotificationError::Invalid(format!(
+            "'{}' is not a valid {} target",
+            channel.target,
+            if channel.kind == NotificationKind::Email { "email" } else { "webhook" }
+        )));
+    }
+    render(channel, &NotificationContext::sample(), true).map(|_| ())
+}
+
+fn lookup(state: &AppState, name: &str) -> Result<NotificationChannel, NotificationError> {
+    state
+        .notifications
+        .get(name)
+        .ok_or_else(|| NotificationError::NotFound(name.to_string()))
+}
+
+/// GET /api/notifications/channels - List channels
+pub async fn list_channels(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.notifications.list())
+}
+
+/// GET /api/notifications/channels/:name - Get a channel
+pub async fn get_channel(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<NotificationChannel>, NotificationError> {
+    lookup(&state, &name).map(Json)
+}
+
+/// POST /api/notifications/channels - Create a channel
+pub async fn create_channel(
+    State(state): State<AppState>,
+    Json(mut channel): Json<NotificationChannel>,
+) -> Result<impl IntoResponse, NotificationError> {
+    if state.notifications.contains(&channel.name) {
+        return Err(NotificationError::AlreadyExists(channel.name));
+    }
+    validate(&channel)?;
+    channel.created_at = Utc::now();
+    channel.updated_at = channel.created_at;
+
+    state.notifications.put(&channel.name, channel.clone())?;
+    info!("Created notification channel '{}'", channel.name);
+    Ok((StatusCode::CREATED, Json(channel)))
+}
+
+/// PUT /api/notifications/channels/:name - Replace a channel
+pub async fn update_channel(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(mut channel): Json<NotificationChannel>,
+) -> Result<Json<NotificationChannel>, NotificationError> {
+    let existing = lookup(&state, &name)?;
+
+    channel.name = name.clone();
+    validate(&channel)?;
+    channel.created_at = existing.created_at;
+    channel.updated_at = Utc::now();
+
+    state.notifications.put(&name, channel.clone())?;
+    info!("Updated notification channel '{}'", name);
+    Ok(Json(channel))
+}
+
+/// DELETE /api/notifications/channels/:name - Delete a channel
+pub async fn delete_channel(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, NotificationError> {
+    match state.notifications.remove(&name)? {
+        Some(_) => {
+            info!("Deleted notification channel '{}'", name);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(NotificationError::NotFound(name)),
+    }
+}
+
+/// POST /api/notifications/preview - Render a channel, saved or not, against a sample alert
+pub async fn preview_channel(
+    State(state): State<AppState>,
+    Json(channel): Json<NotificationChannel>,
+) -> Result<Json<RenderedNotification>, NotificationError> {
+    state.notifier.format(&channel, &NotificationContext::sample(), true).map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(kind: NotificationKind, template: Option<&str>) -> NotificationChannel {
+        NotificationChannel {
+            name: "ops".to_string(),
+            kind,
+            target: "https://hooks.example.com/T000".to_string(),
+            template: template.map(String::from),
+            categories: Vec::new(),
+            min_severity: None,
+            created_at: Default::default(),
+            updated_at: Default::default(),
+        }
+    }
+
+    #[test]
+    fn json_payloads_escape_strings() {
+        let mut context = NotificationContext::sample();
+        context.summary = "quote \" and\nnewline".to_string();
+
+        let slack = render(&channel(NotificationKind::Slack, None), &context, true).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&slack.body).unwrap();
+        assert_eq!(payload["text"], "quote \" and\nnewline");
+
+        let custom = channel(
+            NotificationKind::Webhook,
+            Some(r#"{"title": "{{alert.rule}}", "severity": "page", "labels": {{{json container.labels}}}}"#),
+        );
+        let payload: serde_json::Value = serde_json::from_str(&render(&custom, &context, true).unwrap().body).unwrap();
+        assert_eq!(payload["title"], "memory-high");
+        assert_eq!(payload["labels"]["com.docker.compose.service"], "api");
+    }
+
+    #[test]
+    fn templates_are_validated() {
+        assert!(validate(&channel(NotificationKind::Webhook, None)).is_ok());
+        // Typo in a field name
+        let typo = channel(NotificationKind::Webhook, Some(r#"{"rule": "{{alert.rulee}}"}"#));
+        assert!(matches!(validate(&typo), Err(NotificationError::Template(_))));
+        // Renders, but not to JSON
+        let not_json = channel(NotificationKind::Slack, Some("{{summary}}"));
+        assert!(matches!(validate(&not_json), Err(NotificationError::Template(_))));
+
+        let mut email = channel(NotificationKind::Email, None);
+        assert!(validate(&email).is_err());
+        email.target = "oncall@example.com".to_string();
+        let rendered = render(&email, &NotificationContext::sample(), true).unwrap();
+        assert!(rendered.body.starts_with("Subject: [FlowScope] memory-high"));
+    }
+
+    #[test]
+    fn channels_filter_by_category_and_severity() {
+        let mut context = NotificationContext::sample();
+        let mut infra = channel(NotificationKind::Discord, None);
+        infra.categories = vec![ServiceCategory::Infrastructure];
+        assert!(!wants(&infra, &context));
+        context.container.category = ServiceCategory::Infrastructure;
+        assert!(wants(&infra, &context));
+
+        infra.min_severity = Some(Severity::Error);
+        assert!(!wants(&infra, &context));
+        context.severity = Severity::Error;
+        assert!(wants(&infra, &context));
+    }
+}
@@ -0,0 +1,144 @@
+//! One-shot topology export
+//!
+//! `flowscope --oneshot` discovers once, writes the result and exits instead
+//! of serving, so cron jobs and CI can archive or validate topology. The exit
+//! status tells the caller what went wrong without parsing the logs.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, ValueEnum};
+use tracing::{error, info, warn};
+
+use crate::discovery::DockerDiscovery;
+use crate::docker_api::DockerApi;
+use crate::{dot, mermaid};
+use crate::models::{ContainerStatus, FlowDirection, TopologyExport};
+
+/// Export written and, with `--strict`, every container is up
+pub const EXIT_OK: u8 = 0;
+/// Docker could not be reached or discovery failed
+pub const EXIT_DISCOVERY: u8 = 1;
+/// The export could not be produced or written
+pub const EXIT_OUTPUT: u8 = 2;
+/// `--strict` found containers unhealthy or not running, or a `check` rule failed
+pub const EXIT_UNHEALTHY: u8 = 3;
+/// `validate-config`, or the server at startup, found configuration errors
+pub const EXIT_CONFIG: u8 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Topology summary plus every container and network
+    #[default]
+    Json,
+    /// A Mermaid diagram of one flowchart
+    Mermaid,
+    /// A Graphviz DOT graph of one flowchart
+    Dot,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct OneshotArgs {
+    /// File to write; stdout when omitted
+    #[arg(long, short, requires = "oneshot")]
+    pub output: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t, requires = "oneshot")]
+    pub format: ExportFormat,
+    /// Flowchart to render with `--format mermaid` or `--format dot`
+    #[arg(long, default_value = "system-overview", requires = "oneshot")]
+    pub flowchart: String,
+    /// Exit with status 3 when any container is unhealthy, exited or dead
+    #[arg(long, requires = "oneshot")]
+    pub strict: bool,
+}
+
+pub fn discovery_failed(e: bollard::errors::Error) -> ExitCode {
+    error!("Discovery failed: {}", e);
+    ExitCode::from(EXIT_DISCOVERY)
+}
+
+async fn export<D: DockerApi>(
+    discovery: &DockerDiscovery<D>,
+    args: &OneshotArgs,
+) -> Result<(String, Vec<String>), ExitCode> {
+    let containers = discovery.list_containers().await.map_err(discovery_failed)?;
+    let failing: Vec<String> = containers
+        .iter()
+        .filter(|c| {
+            matches!(
+                c.status,
+                ContainerStatus::Unhealthy | ContainerStatus::Exited | ContainerStatus::Dead | ContainerStatus::Restarting
+            )
+        })
+        .map(|c| c.name.clone())
+        .collect();
+
+    let body = match args.format {
+        ExportFormat::Json => {
+            let networks = discovery.list_networks().await.map_err(discovery_failed)?;
+            let mut topology = discovery.build_topology(&containers);
+            topology.host = discovery.get_host_resources(&containers).await;
+            let export = TopologyExport {
+                topology,
+                containers,
+                networks,
+            };
+            serde_json::to_string_pretty(&export).map_err(|e| {
+                error!("Failed to serialize topology: {}", e);
+                ExitCode::from(EXIT_OUTPUT)
+            })? + "\n"
+        }
+        ExportFormat::Mermaid | ExportFormat::Dot => {
+            let flowchart = discovery
+                .generate_flowchart(&args.flowchart)
+                .await
+                .map_err(discovery_failed)?
+                .ok_or_else(|| {
+                    error!("Flowchart '{}' not found", args.flowchart);
+                    ExitCode::from(EXIT_OUTPUT)
+                })?;
+            match args.format {
+                ExportFormat::Dot => dot::render(&flowchart, FlowDirection::Lr),
+                _ => mermaid::render(&flowchart),
+            }
+        }
+    };
+    Ok((body, failing))
+}
+
+/// Write to `path`, or stdout when none is given, returning where it went
+pub fn write_output(path: Option<&Path>, body: &str) -> std::io::Result<String> {
+    match path {
+        Some(path) => std::fs::write(path, body).map(|_| path.display().to_string()),
+        None => {
+            use std::io::Write;
+            std::io::stdout()
+                .write_all(body.as_bytes())
+                .map(|_| "stdout".to_string())
+        }
+    }
+}
+
+/// Discover, write the export and report how it went as an exit status
+pub async fn run<D: DockerApi>(discovery: &DockerDiscovery<D>, args: &OneshotArgs) -> ExitCode {
+    let (body, failing) = match export(discovery, args).await {
+        Ok(result) => result,
+        Err(code) => return code,
+    };
+
+    match write_output(args.output.as_deref(), &body) {
+        Ok(target) => info!("Wrote {:?} export to {}", args.format, target),
+        Err(e) => {
+            error!("Failed to write export: {}", e);
+            return ExitCode::from(EXIT_OUTPUT);
+        }
+    }
+
+    if args.strict && !failing.is_empty() {
+        warn!("{} containers are not healthy: {}", failing.len(), failing.join(", "));
+        return ExitCode::from(EXIT_UNHEALTHY);
+    }
+    ExitCode::from(EXIT_OK)
+}
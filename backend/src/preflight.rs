@@ -0,0 +1,298 @@
+//! Pre-flight checks for container specs
+//!
+//! Validates a [`ContainerSpec`] against the current Docker state before anything
+//! is created, so a missing network or a port clash is reported up front instead
+//! of surfacing as a crash-looping container.
+
+use std::collections::HashSet;
+
+use crate::models::{
+    ContainerInfo, ContainerSpec, ContainerStatus, FeatureFlags, PreflightCheck, PreflightReport, PreflightStatus,
+};
+
+/// Docker state the checks run against
+pub struct PreflightContext {
+    pub containers: Vec<ContainerInfo>,
+    pub networks: HashSet<String>,
+    pub volumes: HashSet<String>,
+    pub images: HashSet<String>,
+    /// What the daemon supports, see [`crate::system`]
+    pub features: FeatureFlags,
+}
+
+fn check(name: &str, status: PreflightStatus, message: String) -> PreflightCheck {
+    PreflightCheck {
+        check: name.to_string(),
+        status,
+        message,
+    }
+}
+
+/// Hostname referenced by an env value such as `db`, `db:5432` or
+/// `postgres://user:pass@db:5432/app`
+pub fn extract_host(value: &str) -> Option<&str> {
+    let rest = value.split_once("://").map_or(value, |(_, rest)| rest);
+    let rest = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+    let host = rest
+        .split(['/', '?', ','])
+        .next()?
+        .split(':')
+        .next()?
+        .trim();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Hosts that resolve without Docker DNS
+fn is_external_host(host: &str) -> bool {
+    host == "localhost"
+        || host == "host.docker.internal"
+        || host.parse::<std::net::IpAddr>().is_ok()
+        || host.contains('.')
+        || host.contains('$')
+}
+
+/// Env vars whose values are expected to name another service
+fn references_host(key: &str, value: &str) -> bool {
+    key.ends_with("_HOST") || (key.ends_with("_URL") && value.contains("://"))
+}
+
+/// Split `source:destination[:mode]`, keeping Windows drive letters such as
+/// `C:\data` in one piece
+fn volume_parts(volume: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = volume;
+    while !rest.is_empty() {
+        let drive = rest.len() > 2
+            && rest.as_bytes()[0].is_ascii_alphabetic()
+            && rest.as_bytes()[1] == b':'
+            && matches!(rest.as_bytes()[2], b'\\' | b'/');
+        let start = if drive { 2 } else { 0 };
+        match rest[start..].find(':') {
+            Some(i) => {
+                parts.push(&rest[..start + i]);
+                rest = &rest[start + i + 1..];
+            }
+            None => {
+                parts.push(rest);
+                break;
+            }
+        }
+    }
+    // `c:/data` on Linux is volume `c` mounted at `/data`
+    if parts.len() < 2 {
+        return volume.split(':').collect();
+    }
+    parts
+}
+
+/// Absolute or relative host paths, including Windows drive and UNC paths
+fn is_host_path(source: &str) -> bool {
+    source.starts_with(['/', '.', '~', '\\'])
+        || (source.len() > 2 && source.as_bytes()[1] == b':' && source.as_bytes()[0].is_ascii_alphabetic())
+}
+
+/// Run every check; the spec is acceptable when no check failed
+pub fn run(spec: &ContainerSpec, ctx: &PreflightContext) -> PreflightReport {
+    let mut checks = Vec::new();
+
+    // Image
+    let image = if spec.image.contains(':') || spec.image.contains('@') {
+        spec.image.clone()
+    } else {
+        format!("{}:latest", spec.image)
+    };
+    checks.push(if ctx.images.contains(&image) {
+        check("image", PreflightStatus::Pass, format!("Image {} is present locally", image))
+    } else {
+        check("image", PreflightStatus::Warn, format!("Image {} is not present locally and will be pulled", image))
+    });
+
+    // Name collisions
+    if let Some(name) = &spec.name {
+        if ctx.containers.iter().any(|c| &c.name == name) {
+            checks.push(check(
+                "name",
+                PreflightStatus::Fail,
+                format!("A container named '{}' already exists; remove or rename it first", name),
+            ));
+        }
+    }
+
+    // Networks
+    for network in &spec.networks {
+        checks.push(if ctx.networks.contains(network) {
+            check("network", PreflightStatus::Pass, format!("Network '{}' exists", network))
+        } else {
+            check(
+                "network",
+                PreflightStatus::Fail,
+                format!("Network '{}' does not exist; create it with `docker network create {}`", network, network),
+            )
+        });
+    }
+
+    // Volumes: only named volumes can be verified, bind mounts live on the host
+    for volume in &spec.volumes {
+        let parts = volume_parts(volume);
+        let source = parts[0];
+        let relabelled = parts
+            .get(2)
+            .is_some_and(|mode| mode.split(',').any(|m| m == "z" || m == "Z"));
+        if is_host_path(source) {
+            checks.push(if !ctx.features.unlabelled_bind_mounts && !relabelled {
+                check(
+                    "volume",
+                    PreflightStatus::Warn,
+                    format!("SELinux is enforced: bind mount '{}' needs the :z or :Z option to be readable", source),
+                )
+            } else {
+                check(
+                    "volume",
+                    PreflightStatus::Skip,
+                    format!("Bind mount '{}' cannot be verified from FlowScope", source),
+                )
+            });
+        } else if ctx.volumes.contains(source) {
+            checks.push(check("volume", PreflightStatus::Pass, format!("Volume '{}' exists", source)));
+        } else {
+            checks.push(check(
+                "volume",
+                PreflightStatus::Warn,
+                format!("Volume '{}' does not exist and will be created empty", source),
+            ));
+        }
+    }
+
+    // Env-referenced hosts must be reachable on one of the chosen networks
+    let resolvable: HashSet<String> = ctx
+        .containers
+        .iter()
+        .filter(|c| c.networks.iter().any(|n| spec.networks.contains(n)))
+        .flat_map(|c| {
+            let mut names = vec![c.name.to_lowercase()];
+            if let Some(service) = c.labels.get("com.docker.compose.service") {
+                names.push(service.to_lowercase());
+            }
+            names
+        })
+        .collect();
+
+    for (key, value) in spec.env.iter().filter_map(|e| e.split_once('=')) {
+        if !references_host(key, value) {
+            continue;
+        }
+        let Some(host) = extract_host(value) else {
+            continue;
+        };
+        if is_external_host(host) {
+            continue;
+        }
+        checks.push(if resolvable.contains(&host.to_lowercase()) {
+            check("env-host", PreflightStatus::Pass, format!("{} resolves to container '{}'", key, host))
+        } else if ctx.containers.iter().any(|c| c.name.eq_ignore_ascii_case(host)) {
+            check(
+                "env-host",
+                PreflightStatus::Fail,
+                format!("{} points at '{}', which is not attached to any of the chosen networks", key, host),
+            )
+        } else {
+            check(
+                "env-host",
+                PreflightStatus::Fail,
+                format!("{} points at '{}', which does not match any container", key, host),
+            )
+        });
+    }
+
+    // Host ports already published by another container
+    for port in &spec.ports {
+        let Some(host_port) = port.host_port else {
+            continue;
+        };
+        let owner = ctx.containers.iter().find(|c| {
+            matches!(c.status, ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy)
+                && c.ports.iter().any(|p| p.host_port == Some(host_port) && p.protocol == port.protocol)
+        });
+        checks.push(match owner {
+            Some(owner) => check(
+                "port",
+                PreflightStatus::Fail,
+                format!("Host port {}/{} is already published by '{}'", host_port, port.protocol, owner.name),
+            ),
+            None if host_port < 1024 && !ctx.features.privileged_ports => check(
+                "port",
+                PreflightStatus::Warn,
+                format!(
+                    "Host port {} is privileged; a rootless daemon can only publish it if net.ipv4.ip_unprivileged_port_start allows",
+                    host_port
+                ),
+            ),
+            None => check("port", PreflightStatus::Pass, format!("Host port {}/{} is free", host_port, port.protocol)),
+        });
+    }
+
+    PreflightReport {
+        ok: !checks.iter().any(|c| c.status == PreflightStatus::Fail),
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PortSpec;
+
+    #[test]
+    fn rootless_and_selinux_warnings() {
+        let ctx = PreflightContext {
+            containers: Vec::new(),
+            networks: HashSet::new(),
+            volumes: HashSet::new(),
+            images: HashSet::from(["nginx:1.27".to_string()]),
+            features: FeatureFlags {
+                resource_limits: true,
+                privileged_ports: false,
+                unlabelled_bind_mounts: false,
+            },
+        };
+        let spec = ContainerSpec {
+            name: None,
+            image: "nginx:1.27".to_string(),
+            env: Vec::new(),
+            ports: [80, 8080]
+                .map(|port| PortSpec {
+                    container_port: port,
+                    host_port: Some(port),
+                    protocol: "tcp".to_string(),
+                })
+                .to_vec(),
+            networks: Vec::new(),
+            volumes: vec!["/srv/site:/usr/share/nginx/html:ro".to_string(), "/srv/conf:/etc/nginx:ro,Z".to_string()],
+            labels: Default::default(),
+        };
+
+        let report = run(&spec, &ctx);
+        let statuses: Vec<(&str, PreflightStatus)> =
+            report.checks.iter().map(|c| (c.check.as_str(), c.status)).collect();
+        assert!(report.ok);
+        assert_eq!(
+            statuses,
+            [
+                ("image", PreflightStatus::Pass),
+                ("volume", PreflightStatus::Warn),
+                ("volume", PreflightStatus::Skip),
+                ("port", PreflightStatus::Warn),
+                ("port", PreflightStatus::Pass),
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_volume_specs() {
+        assert_eq!(volume_parts(r"C:\site:C:\inetpub\wwwroot:ro"), [r"C:\site", r"C:\inetpub\wwwroot", "ro"]);
+        assert_eq!(volume_parts(r"logs:C:\logs"), ["logs", r"C:\logs"]);
+        assert_eq!(volume_parts("c:/data"), ["c", "/data"]);
+        assert_eq!(volume_parts("/srv:/data:ro,Z"), ["/srv", "/data", "ro,Z"]);
+        assert!(is_host_path(r"C:\site") && is_host_path(r"\\server\share") && !is_host_path("logs"));
+    }
+}
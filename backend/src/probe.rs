@@ -0,0 +1,235 @@
+//! HTTP health probes
+//!
+//! Docker healthchecks are often missing, so with probes turned on in the
+//! configuration a background task requests a health path on the first
+//! published TCP port of every running container each `interval_secs`, and
+//! records whether anything answered, with which status and how fast. The
+//! latest result is listed as `probe` on the container and its flowchart node.
+//!
+//! Requests go to the endpoint test host (`FLOWSCOPE_ENDPOINT_HOST`,
+//! `127.0.0.1` unless set) and do not follow redirects. The path is
+//! `probes.path`, `/health` by default; a container's `flowscope.probe` label
+//! names another path, or turns its probe off with `false`.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use futures::future::join_all;
+use tracing::{debug, warn};
+
+use crate::{
+    config::parse_bool,
+    models::{ContainerInfo, ContainerStatus, HealthProbe},
+    AppState,
+};
+
+pub const DEFAULT_PATH: &str = "/health";
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Label naming a container's health path, or `false` to skip it
+pub const PROBE_LABEL: &str = "flowscope.probe";
+
+/// Latest probe of each container, by name
+#[derive(Default)]
+pub struct ProbeTable {
+    results: Mutex<HashMap<String, HealthProbe>>,
+}
+
+impl ProbeTable {
+    /// Replace every result with `results`, forgetting containers no longer probed
+    pub fn replace(&self, results: HashMap<String, HealthProbe>) {
+        *self.results.lock().unwrap() = results;
+    }
+
+    /// Set the latest probe on each listed container
+    pub fn apply(&self, containers: &mut [ContainerInfo]) {
+        let results = self.results.lock().unwrap();
+        for container in containers {
+            container.probe = results.get(&container.name).cloned();
+        }
+    }
+}
+
+/// The URL to probe `container` at through `host`, or `None` when it is not
+/// running, publishes no TCP port or opted out
+pub fn target(container: &ContainerInfo, host: &str, default_path: &str) -> Option<String> {
+    if !matches!(
+        container.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    ) {
+        return None;
+    }
+    let path = match container.labels.get(PROBE_LABEL).map(|l| l.trim()) {
+        Some(label) if label.starts_with('/') => label,
+        Some(label) if parse_bool(label) == Some(false) => return None,
+        _ => default_path,
+    };
+    let port = container
+        .ports
+        .iter()
+        .find(|p| p.protocol == "tcp")
+        .and_then(|p| p.host_port)?;
+    Some(format!("http://{}:{}{}", host, port, path))
+}
+
+/// What went wrong, with the causes reqwest leaves out of its own message
+fn describe(error: reqwest::Error, timeout: Duration) -> String {
+    if error.is_timeout() {
+        return format!("no answer within {}ms", timeout.as_millis());
+    }
+    let error = error.without_url();
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+/// Request `url` once
+pub async fn probe(http: &reqwest::Client, url: String, timeout: Duration) -> HealthProbe {
+    let started = Instant::now();
+    let response = http.get(&url).timeout(timeout).send().await;
+    let checked_at = Utc::now();
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            HealthProbe {
+                url,
+                reachable: true,
+                ok: status.is_success() || status.is_redirection(),
+                status: Some(status.as_u16()),
+                latency_ms: Some(started.elapsed().as_millis() as u64),
+                error: None,
+                checked_at,
+            }
+        }
+        Err(e) => HealthProbe {
+            url,
+            reachable: false,
+            ok: false,
+            status: None,
+            latency_ms: None,
+            error: Some(describe(e, timeout)),
+            checked_at,
+        },
+    }
+}
+
+/// Spawn the background task that probes running containers
+pub fn spawn_prober(state: AppState) {
+    let settings = state.config.probes.clone();
+    let http = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build the probe HTTP client");
+    tokio::spawn(async move {
+        loop {
+            match state.docker.list_containers().await {
+                Ok(containers) => {
+                    let host = state.endpoints.host();
+                    let probes = containers.iter().filter_map(|c| {
+                        let url = target(c, host, &settings.path)?;
+                        let http = &http;
+                        Some(async move { (c.name.clone(), probe(http, url, settings.timeout).await) })
+                    });
+                    let results: HashMap<String, HealthProbe> = join_all(probes).await.into_iter().collect();
+                    let failing = results.values().filter(|p| !p.ok).count();
+                    debug!("Probed {} containers, {} failing", results.len(), failing);
+                    state.docker.probes().replace(results);
+                }
+                Err(e) => warn!("Failed to list containers to probe: {}", e),
+            }
+            tokio::time::sleep(settings.interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, routing::get, Router};
+
+    use super::*;
+    use crate::{docker_api::fake, models::PortMapping};
+
+    fn container(status: ContainerStatus, port: Option<u16>, label: Option<&str>) -> ContainerInfo {
+        let mut container = ContainerInfo {
+            id: "c0ffee".to_string(),
+            status,
+            ..fake::container("application-api")
+        };
+        container.ports = port
+            .map(|host_port| {
+                vec![
+                    PortMapping {
+                        host_port: Some(host_port),
+                        container_port: 53,
+                        protocol: "udp".to_string(),
+                    },
+                    PortMapping {
+                        host_port: Some(host_port),
+                        container_port: 8080,
+                        protocol: "tcp".to_string(),
+                    },
+                ]
+            })
+            .unwrap_or_default();
+        if let Some(label) = label {
+            container.labels.insert(PROBE_LABEL.to_string(), label.to_string());
+        }
+        container
+    }
+
+    #[test]
+    fn targets_follow_the_label() {
+        let url = |c: ContainerInfo| target(&c, "127.0.0.1", DEFAULT_PATH);
+        assert_eq!(
+            url(container(ContainerStatus::Running, Some(8080), None)).as_deref(),
+            Some("http://127.0.0.1:8080/health")
+        );
+        assert_eq!(
+            url(container(ContainerStatus::Healthy, Some(8080), Some("/ready"))).as_deref(),
+            Some("http://127.0.0.1:8080/ready")
+        );
+        assert_eq!(url(container(ContainerStatus::Running, Some(8080), Some("false"))), None);
+        assert_eq!(url(container(ContainerStatus::Exited, Some(8080), None)), None);
+        assert_eq!(url(container(ContainerStatus::Running, None, None)), None);
+    }
+
+    #[tokio::test]
+    async fn probes_record_status_and_reachability() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .route("/starting", get(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let http = reqwest::Client::new();
+
+        let healthy = probe(&http, format!("http://{}/health", address), DEFAULT_TIMEOUT).await;
+        assert!(healthy.reachable && healthy.ok);
+        assert_eq!(healthy.status, Some(200));
+        assert!(healthy.latency_ms.is_some());
+
+        let starting = probe(&http, format!("http://{}/starting", address), DEFAULT_TIMEOUT).await;
+        assert!(starting.reachable && !starting.ok);
+        assert_eq!(starting.status, Some(503));
+
+        // Nothing listens on a port that was just released
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let down = probe(&http, format!("http://{}/health", closed), DEFAULT_TIMEOUT).await;
+        assert!(!down.reachable);
+        assert!(down.error.is_some());
+
+        let table = ProbeTable::default();
+        table.replace(HashMap::from([("application-api".to_string(), healthy.clone())]));
+        let mut containers = vec![container(ContainerStatus::Running, Some(8080), None)];
+        table.apply(&mut containers);
+        assert_eq!(containers[0].probe.as_ref(), Some(&healthy));
+    }
+}
@@ -0,0 +1,180 @@
+//! Per-container file descriptor and inotify usage
+//!
+//! The stats API reports neither, yet "too many open files" and inotify
+//! ENOSPC are two of the most common ways a long-running container breaks.
+//! Both are read from `<proc>/<pid>` for each of a container's processes,
+//! where `<proc>` is `FLOWSCOPE_HOST_PROC` (default `/proc`; mount the host's
+//! `/proc` there when FlowScope runs in a container).
+//!
+//! `docker top` reports host PIDs, so the figures are only meaningful when
+//! FlowScope sees the daemon host's `/proc`. A PID only counts when its
+//! cgroup names the container, so a remote daemon or a container-private
+//! `/proc` yields no figures rather than wrong ones.
+
+use std::path::{Path, PathBuf};
+
+use crate::models::ContainerStats;
+
+/// Link target of an inotify instance's descriptor
+const INOTIFY_LINK: &str = "anon_inode:inotify";
+
+/// `/proc` of the Docker host, `FLOWSCOPE_HOST_PROC` or `/proc`
+pub fn proc_root() -> PathBuf {
+    std::env::var("FLOWSCOPE_HOST_PROC")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("/proc"))
+}
+
+/// Descriptor and inotify usage summed across a container's processes
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FdUsage {
+    pub open_fds: u64,
+    /// Highest share of any one process's open-files soft limit in use
+    pub fd_percent: Option<f64>,
+    pub inotify_instances: u64,
+    pub inotify_watches: u64,
+    /// Share of `fs.inotify.max_user_watches`; the budget is per uid, so
+    /// other containers running as the same user draw on it too
+    pub inotify_watch_percent: Option<f64>,
+}
+
+impl FdUsage {
+    pub fn apply(&self, stats: &mut ContainerStats) {
+        stats.open_fds = Some(self.open_fds);
+        stats.fd_percent = self.fd_percent;
+        stats.inotify_instances = Some(self.inotify_instances);
+        stats.inotify_watches = Some(self.inotify_watches);
+        stats.inotify_watch_percent = self.inotify_watch_percent;
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Soft "Max open files" limit from a `limits` file; `None` when unlimited
+fn open_files_limit(limits: &str) -> Option<u64> {
+    limits
+        .lines()
+        .find_map(|line| line.strip_prefix("Max open files"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|soft| soft.parse().ok())
+        .filter(|&soft| soft > 0)
+}
+
+/// Whether the process's cgroup path names the container
+pub fn in_container(dir: &Path, container_id: &str) -> bool {
+    std::fs::read_to_string(dir.join("cgroup")).is_ok_and(|cgroup| cgroup.contains(container_id))
+}
+
+/// Usage of the processes in `pids` that belong to `container_id` (full or
+/// short id); `None` when none of them could be read
+pub fn fd_usage(root: &Path, container_id: &str, pids: &[u64]) -> Option<FdUsage> {
+    let mut usage = FdUsage::default();
+    let mut readable = false;
+
+    for pid in pids {
+        let dir = root.join(pid.to_string());
+        if !in_container(&dir, container_id) {
+            continue;
+        }
+        let Ok(fds) = std::fs::read_dir(dir.join("fd")) else {
+            continue;
+        };
+        readable = true;
+
+        let mut open = 0u64;
+        for fd in fds.flatten() {
+            open += 1;
+            let inotify = std::fs::read_link(fd.path()).is_ok_and(|target| target.as_os_str() == INOTIFY_LINK);
+            if !inotify {
+                continue;
+            }
+            usage.inotify_instances += 1;
+            if let Ok(info) = std::fs::read_to_string(dir.join("fdinfo").join(fd.file_name())) {
+                usage.inotify_watches += info.lines().filter(|l| l.starts_with("inotify wd:")).count() as u64;
+            }
+        }
+        usage.open_fds += open;
+
+        let limit = std::fs::read_to_string(dir.join("limits")).ok();
+        if let Some(limit) = limit.as_deref().and_then(open_files_limit) {
+            let percent = round2(open as f64 / limit as f64 * 100.0);
+            usage.fd_percent = Some(usage.fd_percent.map_or(percent, |p| p.max(percent)));
+        }
+    }
+
+    if !readable {
+        return None;
+    }
+    let max_watches = std::fs::read_to_string(root.join("sys/fs/inotify/max_user_watches"))
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&max| max > 0);
+    usage.inotify_watch_percent = max_watches.map(|max| round2(usage.inotify_watches as f64 / max as f64 * 100.0));
+    Some(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    const ID: &str = "a1b2c3d4e5f60000000000000000000000000000000000000000000000000001";
+
+    fn process(root: &Path, pid: u64, cgroup: &str, fds: &[(u32, &str)], soft_limit: &str) {
+        let dir = root.join(pid.to_string());
+        std::fs::create_dir_all(dir.join("fd")).unwrap();
+        std::fs::create_dir_all(dir.join("fdinfo")).unwrap();
+        std::fs::write(dir.join("cgroup"), cgroup).unwrap();
+        std::fs::write(
+            dir.join("limits"),
+            format!(
+                "Limit                     Soft Limit           Hard Limit           Units\n\
+                 Max open files            {:<20} 1048576              files\n",
+                soft_limit
+            ),
+        )
+        .unwrap();
+        for (fd, target) in fds {
+            symlink(target, dir.join("fd").join(fd.to_string())).unwrap();
+        }
+    }
+
+    #[test]
+    fn sums_descriptors_and_watches_of_the_container() {
+        let root = std::env::temp_dir().join(format!("flowscope-procfs-{}", uuid::Uuid::new_v4()));
+        let scope = format!("0::/system.slice/docker-{}.scope\n", ID);
+        process(&root, 100, &scope, &[(0, "/dev/null"), (1, "pipe:[1]"), (2, "pipe:[2]"), (3, INOTIFY_LINK)], "8");
+        std::fs::write(
+            root.join("100/fdinfo/3"),
+            "pos:\t0\nflags:\t02000000\ninotify wd:1 ino:2 sdev:3 mask:fc6\ninotify wd:2 ino:4 sdev:3 mask:fc6\n",
+        )
+        .unwrap();
+        process(&root, 101, &scope, &[(0, "/dev/null")], "unlimited");
+        // A process of another container is not counted
+        process(&root, 102, "0::/system.slice/docker-ffff.scope\n", &[(0, "/dev/null")], "1");
+        std::fs::create_dir_all(root.join("sys/fs/inotify")).unwrap();
+        std::fs::write(root.join("sys/fs/inotify/max_user_watches"), "8\n").unwrap();
+
+        let usage = fd_usage(&root, &ID[..12], &[100, 101, 102, 103]).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            usage,
+            FdUsage {
+                open_fds: 5,
+                fd_percent: Some(50.0),
+                inotify_instances: 1,
+                inotify_watches: 2,
+                inotify_watch_percent: Some(25.0),
+            }
+        );
+    }
+
+    #[test]
+    fn nothing_readable_is_no_usage() {
+        let root = std::env::temp_dir().join(format!("flowscope-procfs-{}", uuid::Uuid::new_v4()));
+        assert_eq!(fd_usage(&root, ID, &[1]), None);
+    }
+}
@@ -0,0 +1,495 @@
+//! Docker Compose projects
+//!
+//! Containers started by Compose carry their project, service and (since
+//! Compose 2.20) `depends_on` in labels, which is enough to treat a project as
+//! a unit without the compose file. `up`, `down` and `restart` walk the
+//! members in dependency order as a [job](crate::jobs), the way
+//! `docker compose` does: dependencies start first and stop last.
+//!
+//! Services can also be scaled. Compose replicas are copies of an existing
+//! replica named `<project>-<service>-<n>`; services deployed with
+//! `docker stack deploy` are scaled through Swarm instead.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{
+    discovery::DockerDiscovery,
+    docker_api::DockerApi,
+    jobs::JobHandle,
+    models::{ActionResult, ComposeProject, ContainerInfo, ContainerStatus, Job},
+    AppState,
+};
+
+pub const PROJECT_LABEL: &str = "com.docker.compose.project";
+pub const SERVICE_LABEL: &str = "com.docker.compose.service";
+/// `db:service_healthy:false,cache:service_started:true`
+pub const DEPENDS_ON_LABEL: &str = "com.docker.compose.depends_on";
+pub const CONTAINER_NUMBER_LABEL: &str = "com.docker.compose.container-number";
+/// Set on the tasks of a service deployed with `docker stack deploy`
+pub const STACK_LABEL: &str = "com.docker.stack.namespace";
+pub const SWARM_SERVICE_LABEL: &str = "com.docker.swarm.service.name";
+
+/// Upper bound on `replicas`, so a typo cannot start hundreds of containers
+pub const MAX_REPLICAS: u32 = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectError {
+    #[error("compose project '{0}' not found")]
+    NotFound(String),
+    #[error("unknown action '{0}'; expected up, down or restart")]
+    UnknownAction(String),
+    #[error("service '{0}' not found in the project")]
+    ServiceNotFound(String),
+    #[error("replicas must be at most {MAX_REPLICAS}")]
+    TooManyReplicas,
+    #[error("service '{service}' publishes fixed host ports ({ports}); another replica could not bind them")]
+    FixedPorts { service: String, ports: String },
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl ProjectError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ProjectError::NotFound(_) | ProjectError::ServiceNotFound(_) => StatusCode::NOT_FOUND,
+            ProjectError::UnknownAction(_) | ProjectError::TooManyReplicas => StatusCode::BAD_REQUEST,
+            ProjectError::FixedPorts { .. } => StatusCode::CONFLICT,
+            ProjectError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ProjectError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Project error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectAction {
+    /// Start stopped members, dependencies first
+    Up,
+    /// Stop running members, dependents first. Containers are kept, since
+    /// FlowScope cannot recreate them without the compose file.
+    Down,
+    /// Restart every member, dependencies first
+    Restart,
+}
+
+impl ProjectAction {
+    pub fn parse(action: &str) -> Result<Self, ProjectError> {
+        match action {
+            "up" => Ok(ProjectAction::Up),
+            "down" => Ok(ProjectAction::Down),
+            "restart" => Ok(ProjectAction::Restart),
+            other => Err(ProjectError::UnknownAction(other.to_string())),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ProjectAction::Up => "up",
+            ProjectAction::Down => "down",
+            ProjectAction::Restart => "restart",
+        }
+    }
+}
+
+pub(crate) fn service(container: &ContainerInfo) -> &str {
+    if let Some(service) = container.labels.get(SERVICE_LABEL) {
+        return service;
+    }
+    // Swarm names services `<stack>_<service>`
+    match (container.labels.get(SWARM_SERVICE_LABEL), container.labels.get(STACK_LABEL)) {
+        (Some(service), Some(stack)) => service
+            .strip_prefix(stack.as_str())
+            .and_then(|s| s.strip_prefix('_'))
+            .unwrap_or(service),
+        (Some(service), None) => service,
+        _ => &container.name,
+    }
+}
+
+fn project(container: &ContainerInfo) -> Option<&String> {
+    container
+        .labels
+        .get(PROJECT_LABEL)
+        .or_else(|| container.labels.get(STACK_LABEL))
+}
+
+fn is_running(container: &ContainerInfo) -> bool {
+    matches!(
+        container.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy | ContainerStatus::Restarting
+    )
+}
+
+/// Services named in a `depends_on` label
+pub fn depends_on(container: &ContainerInfo) -> impl Iterator<Item = &str> {
+    container
+        .labels
+        .get(DEPENDS_ON_LABEL)
+        .into_iter()
+        .flat_map(|deps| deps.split(','))
+        .filter_map(|dep| dep.split(':').next())
+        .map(str::trim)
+        .filter(|dep| !dep.is_empty())
+}
+
+/// Whether `source` depends on `target`'s service in the same project
+pub fn depends(source: &ContainerInfo, target: &ContainerInfo) -> bool {
+    let project = source.labels.get(PROJECT_LABEL);
+    project.is_some()
+        && target.labels.get(PROJECT_LABEL) == project
+        && target
+            .labels
+            .get(SERVICE_LABEL)
+            .is_some_and(|service| depends_on(source).any(|dep| dep == service))
+}
+
+/// Services ordered so every service comes after its dependencies; ties and
+/// any dependency cycle fall back to name order
+pub fn service_order(members: &[ContainerInfo]) -> Vec<String> {
+    let mut deps: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for container in members {
+        deps.entry(service(container)).or_default();
+    }
+    for container in members {
+        let known: Vec<&str> = depends_on(container).filter(|d| deps.contains_key(d)).collect();
+        deps.entry(service(container)).or_default().extend(known);
+    }
+
+    let mut order = Vec::with_capacity(deps.len());
+    while !deps.is_empty() {
+        let ready: Vec<&str> = deps
+            .iter()
+            .filter(|(_, pending)| pending.is_empty())
+            .map(|(service, _)| *service)
+            .collect();
+        // A cycle leaves nothing ready; break it at the first service by name
+        let ready = if ready.is_empty() {
+            vec![*deps.keys().next().expect("deps is not empty")]
+        } else {
+            ready
+        };
+        for service in ready {
+            deps.remove(service);
+            deps.values_mut().for_each(|pending| {
+                pending.remove(service);
+            });
+            order.push(service.to_string());
+        }
+    }
+    order
+}
+
+/// Members sorted by service order, then name
+pub fn dependency_order(mut members: Vec<ContainerInfo>) -> Vec<ContainerInfo> {
+    let order = service_order(&members);
+    let rank = |c: &ContainerInfo| order.iter().position(|s| s == service(c)).unwrap_or(usize::MAX);
+    members.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| a.name.cmp(&b.name)));
+    members
+}
+
+/// Group containers into compose projects
+pub fn projects(containers: Vec<ContainerInfo>) -> BTreeMap<String, Vec<ContainerInfo>> {
+    let mut projects: BTreeMap<String, Vec<ContainerInfo>> = BTreeMap::new();
+    for container in containers {
+        if let Some(project) = project(&container).cloned() {
+            projects.entry(project).or_default().push(container);
+        }
+    }
+    projects
+        .into_iter()
+        .map(|(name, members)| (name, dependency_order(members)))
+        .collect()
+}
+
+pub fn summary(name: String, members: &[ContainerInfo]) -> ComposeProject {
+    ComposeProject {
+        name,
+        services: service_order(members),
+        containers: members.iter().map(|c| c.name.clone()).collect(),
+        running: members.iter().filter(|c| is_running(c)).count(),
+    }
+}
+
+/// Containers an action touches, in the order it touches them
+pub fn plan(action: ProjectAction, members: Vec<ContainerInfo>) -> Vec<ContainerInfo> {
+    match action {
+        ProjectAction::Up => members.into_iter().filter(|c| !is_running(c)).collect(),
+        ProjectAction::Down => members.into_iter().rev().filter(is_running).collect(),
+        ProjectAction::Restart => members,
+    }
+}
+
+/// Apply the planned steps. Starting or restarting stops at the first failure,
+/// since dependents would fail too; stopping carries on.
+pub async fn apply<D: DockerApi>(
+    discovery: Arc<DockerDiscovery<D>>,
+    action: ProjectAction,
+    steps: Vec<ContainerInfo>,
+    job: JobHandle,
+) -> Result<(), String> {
+    let mut failed = Vec::new();
+    for container in steps {
+        let result = match action {
+            ProjectAction::Up => discovery.start_container(&container.id).await,
+            ProjectAction::Down => discovery.stop_container(&container.id).await,
+            ProjectAction::Restart => discovery.restart_container(&container.id).await,
+        };
+        let result = result.map_err(|e| e.to_string())?.unwrap_or_else(|| ActionResult {
+            success: false,
+            container_id: container.id.clone(),
+            container_name: container.name.clone(),
+            action: action.name().to_string(),
+            message: "Container no longer exists".to_string(),
+        });
+        let success = result.success;
+        job.record(result);
+
+        if !success {
+            if action != ProjectAction::Down {
+                return Err(format!("{} failed on {}", action.name(), container.name));
+            }
+            failed.push(container.name);
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} failed on {}", action.name(), failed.join(", ")))
+    }
+}
+
+/// Replica number from the compose label, or the `-<n>`/`_<n>` name suffix
+fn replica_number(container: &ContainerInfo) -> Option<u32> {
+    container
+        .labels
+        .get(CONTAINER_NUMBER_LABEL)
+        .and_then(|n| n.parse().ok())
+        .or_else(|| container.name.rsplit(['-', '_']).next()?.parse().ok())
+}
+
+/// Name for replica `number`, following the naming of `template`: Compose v2
+/// uses `<project>-<service>-<n>`, v1 `<project>_<service>_<n>`, and renamed
+/// replicas keep whatever prefix they were given
+fn replica_name(template: &ContainerInfo, project: &str, number: u32) -> String {
+    let suffix = replica_number(template)
+        .map(|n| n.to_string())
+        .filter(|n| template.name.len() > n.len() + 1 && template.name.ends_with(n.as_str()));
+    match suffix {
+        Some(n) => {
+            let stem = &template.name[..template.name.len() - n.len()];
+            format!("{}{}", stem, number)
+        }
+        None => format!("{}-{}-{}", project, service(template), number),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ScaleStep {
+    /// Create and start a copy of the template replica
+    Create {
+        template: ContainerInfo,
+        name: String,
+        number: u32,
+    },
+    /// Stop and remove a replica
+    Remove(ContainerInfo),
+    /// Let Swarm converge the service on a replica count
+    Swarm { service: String, replicas: u32 },
+}
+
+/// Steps that bring `service` to `replicas` containers. Scaling down removes
+/// the highest-numbered replicas first, as `docker compose up --scale` does.
+pub fn plan_scale(
+    project: &str,
+    service_name: &str,
+    replicas: u32,
+    members: Vec<ContainerInfo>,
+) -> Result<Vec<ScaleStep>, ProjectError> {
+    if replicas > MAX_REPLICAS {
+        return Err(ProjectError::TooManyReplicas);
+    }
+    let mut replicas_now: Vec<ContainerInfo> = members.into_iter().filter(|c| service(c) == service_name).collect();
+    if replicas_now.is_empty() {
+        return Err(ProjectError::ServiceNotFound(service_name.to_string()));
+    }
+    replicas_now.sort_by_key(|c| (replica_number(c).unwrap_or(0), c.name.clone()));
+
+    if let Some(swarm_service) = replicas_now[0].labels.get(SWARM_SERVICE_LABEL) {
+        return Ok(vec![ScaleStep::Swarm {
+            service: swarm_service.clone(),
+            replicas,
+        }]);
+    }
+
+    let current = replicas_now.len() as u32;
+    if replicas <= current {
+        return Ok(replicas_now
+            .into_iter()
+            .skip(replicas as usize)
+            .rev()
+            .map(ScaleStep::Remove)
+            .collect());
+    }
+
+    let template = replicas_now.last().expect("replicas_now is not empty").clone();
+    let mut taken: BTreeSet<u32> = replicas_now.iter().filter_map(replica_number).collect();
+    let mut steps = Vec::new();
+    let mut number = 1;
+    while (steps.len() as u32) < replicas - current {
+        if taken.insert(number) {
+            steps.push(ScaleStep::Create {
+                template: template.clone(),
+                name: replica_name(&template, project, number),
+                number,
+            });
+        }
+        number += 1;
+    }
+    Ok(steps)
+}
+
+/// Apply the planned scale steps, carrying on past failures
+pub async fn apply_scale<D: DockerApi>(
+    discovery: Arc<DockerDiscovery<D>>,
+    steps: Vec<ScaleStep>,
+    job: JobHandle,
+) -> Result<(), String> {
+    let mut failed = Vec::new();
+    for step in steps {
+        let result = match step {
+            ScaleStep::Create { template, name, number } => discovery.create_replica(&template, &name, number).await,
+            ScaleStep::Remove(container) => discovery
+                .remove_container(&container.id, true, false)
+                .await
+                .map_err(|e| e.to_string())?
+                .unwrap_or_else(|| ActionResult {
+                    success: false,
+                    container_id: container.id.clone(),
+                    container_name: container.name.clone(),
+                    action: "remove".to_string(),
+                    message: "Container no longer exists".to_string(),
+                }),
+            ScaleStep::Swarm { service, replicas } => discovery.scale_swarm_service(&service, replicas).await,
+        };
+        if !result.success {
+            failed.push(result.container_name.clone());
+        }
+        job.record(result);
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("scale failed on {}", failed.join(", ")))
+    }
+}
+
+/// GET /api/projects - Compose projects with their services in dependency order
+pub async fn list_projects(State(state): State<AppState>) -> Result<Json<Vec<ComposeProject>>, ProjectError> {
+    Ok(Json(state.docker.list_projects().await?))
+}
+
+/// POST /api/projects/:name/:action - Start an `up`, `down` or `restart` job
+pub async fn project_action(
+    State(state): State<AppState>,
+    Path((name, action)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ProjectError> {
+    let action = ProjectAction::parse(&action)?;
+    let members = state
+        .docker
+        .project_members(&name)
+        .await?
+        .ok_or_else(|| ProjectError::NotFound(name.clone()))?;
+
+    let steps = plan(action, members);
+    let discovery = state.docker.clone();
+    let job: Job = state.jobs.spawn(
+        &format!("project-{}", action.name()),
+        &name,
+        steps.len(),
+        |handle| apply(discovery, action, steps, handle),
+    );
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScaleQuery {
+    pub replicas: u32,
+}
+
+/// POST /api/projects/:name/services/:svc/scale?replicas=N - Start a job that
+/// brings a service to N replicas
+pub async fn scale_service(
+    State(state): State<AppState>,
+    Path((name, svc)): Path<(String, String)>,
+    Query(query): Query<ScaleQuery>,
+) -> Result<impl IntoResponse, ProjectError> {
+    let members = state
+        .docker
+        .project_members(&name)
+        .await?
+        .ok_or_else(|| ProjectError::NotFound(name.clone()))?;
+
+    let steps = plan_scale(&name, &svc, query.replicas, members)?;
+    if let Some(ScaleStep::Create { template, .. }) = steps.first() {
+        let ports = state.docker.fixed_host_ports(template).await?;
+        if !ports.is_empty() {
+            return Err(ProjectError::FixedPorts {
+                service: svc,
+                ports: ports.join(", "),
+            });
+        }
+    }
+
+    let discovery = state.docker.clone();
+    let job: Job = state.jobs.spawn(
+        "service-scale",
+        &format!("{}/{}", name, svc),
+        steps.len(),
+        |handle| apply_scale(discovery, steps, handle),
+    );
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+
+    #[tokio::test]
+    async fn cycles_fall_back_to_name_order() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let base = discovery.list_containers().await.unwrap().remove(0);
+        let container = |name: &str, depends: &str| ContainerInfo {
+            name: name.to_string(),
+            labels: [
+                (SERVICE_LABEL.to_string(), name.to_string()),
+                (DEPENDS_ON_LABEL.to_string(), depends.to_string()),
+            ]
+            .into(),
+            ..base.clone()
+        };
+        let members = [container("a", "b:service_started:false"), container("b", "a"), container("c", "a")];
+        assert_eq!(service_order(&members), ["a", "b", "c"]);
+    }
+}
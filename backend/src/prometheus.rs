@@ -0,0 +1,236 @@
+//! Prometheus exposition
+//!
+//! `GET /metrics` serves, in the Prometheus text format, the latest stats the
+//! [sampler](crate::metrics) recorded for each running container, labelled
+//! by container name and, when FlowScope watches several daemons, host, the API
+//! requests served by route and status, and the Docker calls that failed by
+//! kind. Container series come from the last sampling round rather than a
+//! fresh read, so a scrape costs the daemon nothing; they are absent until the
+//! first round completes. Network and block I/O are byte counters since the
+//! container started, the rest gauges. With log rates turned on, the error
+//! and warning lines each container logged per minute are gauges as well. The
+//! request and Docker error counters live in memory and reset on restart.
+
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use chrono::Utc;
+
+use crate::{log_rates::LogRate, metrics::MetricSample, models::ContainerStats, AppState};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Name after `flowscope_container_`, help text and value of a container
+/// gauge or counter
+type Series = (&'static str, &'static str, fn(&ContainerStats) -> f64);
+
+const GAUGES: [Series; 5] = [
+    ("cpu_percent", "CPU usage in percent", |s| s.cpu_percent),
+    ("memory_usage_bytes", "Memory in use", |s| s.memory_usage_mb * MIB),
+    ("memory_limit_bytes", "Memory limit, 0 when unlimited", |s| s.memory_limit_mb * MIB),
+    ("memory_percent", "Memory in use in percent of the limit", |s| s.memory_percent),
+    ("pids", "Processes and threads", |s| s.pids as f64),
+];
+
+/// Byte totals since the container started, so `rate()` applies
+const COUNTERS: [Series; 4] = [
+    ("network_receive_bytes_total", "Bytes received since the container started", |s| {
+        s.network_rx_mb * MIB
+    }),
+    ("network_transmit_bytes_total", "Bytes sent since the container started", |s| {
+        s.network_tx_mb * MIB
+    }),
+    ("block_read_bytes_total", "Bytes read from block devices", |s| s.block_read_mb * MIB),
+    ("block_write_bytes_total", "Bytes written to block devices", |s| s.block_write_mb * MIB),
+];
+
+/// Name after `flowscope_container_`, help text and value of a log rate gauge
+type RateGauge = (&'static str, &'static str, fn(&LogRate) -> f64);
+
+const RATE_GAUGES: [RateGauge; 2] = [
+    ("log_errors_per_minute", "Error lines logged per minute over the last five minutes", |r| {
+        r.errors_per_minute
+    }),
+    ("log_warnings_per_minute", "Warning lines logged per minute over the last five minutes", |r| {
+        r.warnings_per_minute
+    }),
+];
+
+/// Requests served, by method, matched route and status
+#[derive(Debug, Default)]
+pub struct RequestCounts {
+    counts: Mutex<BTreeMap<(String, String, u16), u64>>,
+}
+
+impl RequestCounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, method: &str, route: &str, status: u16) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+    }
+
+    fn snapshot(&self) -> BTreeMap<(String, String, u16), u64> {
+        self.counts.lock().unwrap().clone()
+    }
+}
+
+/// Count every request under its route pattern, such as
+/// `/api/container/:id/logs`, so container ids do not multiply the series
+pub async fn count_requests(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str())
+        .to_string();
+    let response = next.run(request).await;
+    state.requests.record(&method, &route, response.status().as_u16());
+    response
+}
+
+/// A label value with `\`, `"` and newlines escaped
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// The exposition for the given samples and counters; `log_rates` is `None`
+/// while rates are turned off, leaving their families out
+fn render(
+    samples: &[MetricSample],
+    log_rates: Option<&[(String, LogRate)]>,
+    requests: &BTreeMap<(String, String, u16), u64>,
+    docker_errors: &BTreeMap<&'static str, u64>,
+) -> String {
+    let mut out = String::new();
+    let series = GAUGES.iter().map(|s| (s, "gauge")).chain(COUNTERS.iter().map(|s| (s, "counter")));
+    for ((name, help, value), kind) in series {
+        let name = format!("flowscope_container_{}", name);
+        family(&mut out, &name, kind, help);
+        for sample in samples {
+            let _ = writeln!(
+                out,
+                "{}{{container=\"{}\",host=\"{}\"}} {}",
+                name,
+                escape(&sample.container),
+                escape(sample.host.as_deref().unwrap_or_default()),
+                value(&sample.stats)
+            );
+        }
+    }
+
+    if let Some(log_rates) = log_rates {
+        for (name, help, value) in RATE_GAUGES {
+            let name = format!("flowscope_container_{}", name);
+            family(&mut out, &name, "gauge", help);
+            for (container, rate) in log_rates {
+                let _ = writeln!(out, "{}{{container=\"{}\"}} {}", name, escape(container), value(rate));
+            }
+        }
+    }
+
+    family(&mut out, "flowscope_api_requests_total", "counter", "API requests served");
+    for ((method, route, status), count) in requests {
+        let _ = writeln!(
+            out,
+            "flowscope_api_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+            method,
+            escape(route),
+            status,
+            count
+        );
+    }
+
+    family(&mut out, "flowscope_docker_errors_total", "counter", "Docker calls that failed, by kind");
+    for (kind, count) in docker_errors {
+        let _ = writeln!(out, "flowscope_docker_errors_total{{kind=\"{}\"}} {}", kind, count);
+    }
+    out
+}
+
+/// GET /metrics - Container stats and FlowScope counters for Prometheus
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let log_rates = state.config.log_rates.enabled.then(|| state.docker.log_rates().rates(Utc::now()));
+    let body = render(
+        &state.metrics.latest(),
+        log_rates.as_deref(),
+        &state.requests.snapshot(),
+        &state.breaker.error_counts(),
+    );
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_gauges_and_counters() {
+        let sample = |host: Option<&str>, cpu_percent| MetricSample {
+            container: "api \"blue\"".to_string(),
+            host: host.map(str::to_string),
+            image: None,
+            at: Utc::now(),
+            stats: ContainerStats {
+                cpu_percent,
+                memory_usage_mb: 2.0,
+                network_rx_mb: 1.5,
+                pids: 7,
+                ..Default::default()
+            },
+        };
+        // The same name on two daemons stays two series
+        let samples = [sample(None, 12.5), sample(Some("edge-1"), 3.0)];
+        let requests = RequestCounts::new();
+        requests.record("GET", "/api/container/:id/logs", 200);
+        requests.record("GET", "/api/container/:id/logs", 200);
+        let errors = BTreeMap::from([("timeout", 3)]);
+        let log_rates = [(
+            "api".to_string(),
+            LogRate {
+                errors_per_minute: 2.4,
+                warnings_per_minute: 0.0,
+            },
+        )];
+
+        let text = render(&samples, Some(&log_rates), &requests.snapshot(), &errors);
+        assert!(text.contains("# TYPE flowscope_container_cpu_percent gauge\n"));
+        assert!(text.contains("flowscope_container_cpu_percent{container=\"api \\\"blue\\\"\",host=\"\"} 12.5\n"));
+        assert!(text.contains("flowscope_container_memory_usage_bytes{container=\"api \\\"blue\\\"\",host=\"\"} 2097152\n"));
+        assert!(text.contains("flowscope_container_pids{container=\"api \\\"blue\\\"\",host=\"\"} 7\n"));
+        assert!(text.contains("# TYPE flowscope_container_network_receive_bytes_total counter\n"));
+        assert!(text.contains(
+            "flowscope_container_network_receive_bytes_total{container=\"api \\\"blue\\\"\",host=\"\"} 1572864\n"
+        ));
+        assert!(!text.contains("flowscope_container_network_receive_bytes{"));
+        assert!(text.contains("flowscope_container_cpu_percent{container=\"api \\\"blue\\\"\",host=\"edge-1\"} 3\n"));
+        assert!(text.contains("flowscope_container_log_errors_per_minute{container=\"api\"} 2.4\n"));
+        assert!(text.contains("flowscope_container_log_warnings_per_minute{container=\"api\"} 0\n"));
+        assert!(text.contains(
+            "flowscope_api_requests_total{method=\"GET\",route=\"/api/container/:id/logs\",status=\"200\"} 2\n"
+        ));
+        assert!(text.contains("flowscope_docker_errors_total{kind=\"timeout\"} 3\n"));
+
+        // Without log rates turned on the families are left out
+        let text = render(&samples, None, &requests.snapshot(), &errors);
+        assert!(!text.contains("log_errors_per_minute") && !text.contains("log_warnings_per_minute"));
+    }
+}
@@ -0,0 +1,217 @@
+//! Discovery providers
+//!
+//! [`DiscoveryProvider`] is what the topology, container and flowchart
+//! endpoints and the WebSocket publisher need from a discovery backend: a
+//! Docker host through [`DockerDiscovery`] or a cluster through
+//! [`KubernetesDiscovery`]. The `provider` setting (`docker`, the default, or
+//! `kubernetes`) picks one at startup. Everything else, such as stats, logs,
+//! exec and compose projects, stays Docker-only: with the kubernetes provider
+//! [`require_docker`] answers those routes with 501, and WebSocket actions
+//! and log watches are refused.
+
+use std::{future::Future, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{
+    auth::matches_route,
+    breaker::ResilientDocker,
+    discovery::DockerDiscovery,
+    docker_api::DockerApi,
+    hosts::MultiDocker,
+    kubernetes::{KubeError, KubernetesDiscovery},
+    models::{ContainerInfo, Flowchart, SystemTopology},
+    AppState,
+};
+
+/// Docker-backed routes that go through the provider and so also serve the
+/// kubernetes provider; `*` matches one path segment
+pub const PROVIDER_ROUTES: &[(&str, &str)] = &[
+    ("GET", "/api/topology"),
+    ("GET", "/api/containers"),
+    ("GET", "/api/flowchart/*"),
+    ("GET", "/api/flowchart/*/export"),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    #[error(transparent)]
+    Docker(#[from] bollard::errors::Error),
+    #[error(transparent)]
+    Kubernetes(#[from] KubeError),
+}
+
+/// A source of containers, topology and flowcharts
+pub trait DiscoveryProvider: Send + Sync {
+    /// Every container, sorted by name; may be served from a cache
+    fn list_containers(&self) -> impl Future<Output = Result<Vec<ContainerInfo>, ProviderError>> + Send;
+
+    /// Every container, listed afresh
+    fn refresh_containers(&self) -> impl Future<Output = Result<Vec<ContainerInfo>, ProviderError>> + Send;
+
+    fn get_topology(&self) -> impl Future<Output = Result<SystemTopology, ProviderError>> + Send;
+
+    /// The flowchart with `id`, or `None` if there is no such view
+    fn generate_flowchart(&self, id: &str) -> impl Future<Output = Result<Option<Flowchart>, ProviderError>> + Send;
+}
+
+impl<D: DockerApi> DiscoveryProvider for DockerDiscovery<D> {
+    async fn list_containers(&self) -> Result<Vec<ContainerInfo>, ProviderError> {
+        Ok(DockerDiscovery::list_containers(self).await?)
+    }
+
+    async fn refresh_containers(&self) -> Result<Vec<ContainerInfo>, ProviderError> {
+        Ok(DockerDiscovery::refresh_containers(self).await?)
+    }
+
+    async fn get_topology(&self) -> Result<SystemTopology, ProviderError> {
+        Ok(DockerDiscovery::get_topology(self).await?)
+    }
+
+    async fn generate_flowchart(&self, id: &str) -> Result<Option<Flowchart>, ProviderError> {
+        Ok(DockerDiscovery::generate_flowchart(self, id).await?)
+    }
+}
+
+impl DiscoveryProvider for KubernetesDiscovery {
+    async fn list_containers(&self) -> Result<Vec<ContainerInfo>, ProviderError> {
+        Ok(KubernetesDiscovery::list_containers(self).await?)
+    }
+
+    async fn refresh_containers(&self) -> Result<Vec<ContainerInfo>, ProviderError> {
+        Ok(KubernetesDiscovery::list_containers(self).await?)
+    }
+
+    async fn get_topology(&self) -> Result<SystemTopology, ProviderError> {
+        Ok(KubernetesDiscovery::get_topology(self).await?)
+    }
+
+    async fn generate_flowchart(&self, id: &str) -> Result<Option<Flowchart>, ProviderError> {
+        Ok(KubernetesDiscovery::generate_flowchart(self, id).await?)
+    }
+}
+
+/// Which backend the `provider` setting selects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    Docker,
+    Kubernetes,
+}
+
+impl ProviderKind {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "docker" => Ok(Self::Docker),
+            "kubernetes" | "k8s" => Ok(Self::Kubernetes),
+            other => Err(format!("'{}' is not a provider; use docker or kubernetes", other)),
+        }
+    }
+}
+
+/// The provider the server was started with
+pub enum Provider {
+    Docker(Arc<DockerDiscovery<ResilientDocker<MultiDocker>>>),
+    Kubernetes(KubernetesDiscovery),
+}
+
+impl Provider {
+    pub fn kind(&self) -> ProviderKind {
+        match self {
+            Self::Docker(_) => ProviderKind::Docker,
+            Self::Kubernetes(_) => ProviderKind::Kubernetes,
+        }
+    }
+}
+
+impl ProviderKind {
+    /// Whether this provider can serve a Docker-backed route
+    pub fn serves(self, method: &str, path: &str) -> bool {
+        self == Self::Docker || PROVIDER_ROUTES.iter().any(|(m, pattern)| *m == method && matches_route(pattern, path))
+    }
+
+    /// Why a Docker-only feature is unavailable, or `None` with Docker
+    pub fn docker_only(self, what: &str) -> Result<(), String> {
+        match self {
+            Self::Docker => Ok(()),
+            Self::Kubernetes => Err(format!("{} needs the docker provider", what)),
+        }
+    }
+}
+
+/// Middleware for Docker-backed routes: answers 501 for those the provider
+/// cannot serve
+pub async fn require_docker(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let kind = state.provider.kind();
+    if kind.serves(request.method().as_str(), request.uri().path()) {
+        return next.run(request).await;
+    }
+    (
+        StatusCode::NOT_IMPLEMENTED,
+        Json(serde_json::json!({
+            "error": format!("{} {} needs the docker provider", request.method(), request.uri().path()),
+        })),
+    )
+        .into_response()
+}
+
+impl DiscoveryProvider for Provider {
+    async fn list_containers(&self) -> Result<Vec<ContainerInfo>, ProviderError> {
+        match self {
+            Self::Docker(docker) => DiscoveryProvider::list_containers(docker.as_ref()).await,
+            Self::Kubernetes(cluster) => DiscoveryProvider::list_containers(cluster).await,
+        }
+    }
+
+    async fn refresh_containers(&self) -> Result<Vec<ContainerInfo>, ProviderError> {
+        match self {
+            Self::Docker(docker) => DiscoveryProvider::refresh_containers(docker.as_ref()).await,
+            Self::Kubernetes(cluster) => DiscoveryProvider::refresh_containers(cluster).await,
+        }
+    }
+
+    async fn get_topology(&self) -> Result<SystemTopology, ProviderError> {
+        match self {
+            Self::Docker(docker) => DiscoveryProvider::get_topology(docker.as_ref()).await,
+            Self::Kubernetes(cluster) => DiscoveryProvider::get_topology(cluster).await,
+        }
+    }
+
+    async fn generate_flowchart(&self, id: &str) -> Result<Option<Flowchart>, ProviderError> {
+        match self {
+            Self::Docker(docker) => DiscoveryProvider::generate_flowchart(docker.as_ref(), id).await,
+            Self::Kubernetes(cluster) => DiscoveryProvider::generate_flowchart(cluster, id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kubernetes_serves_only_provider_routes() {
+        let kubernetes = ProviderKind::Kubernetes;
+        assert!(kubernetes.serves("GET", "/api/topology"));
+        assert!(kubernetes.serves("GET", "/api/containers"));
+        assert!(kubernetes.serves("GET", "/api/flowchart/system-overview"));
+        assert!(kubernetes.serves("GET", "/api/flowchart/namespace:shop/export"));
+        assert!(!kubernetes.serves("POST", "/api/containers"));
+        assert!(!kubernetes.serves("GET", "/api/container/web/detail"));
+        assert!(!kubernetes.serves("GET", "/api/container/web/logs"));
+        assert!(!kubernetes.serves("POST", "/api/groups/web/actions/restart"));
+        assert!(!kubernetes.serves("GET", "/api/dashboards/ops/data"));
+        assert!(!kubernetes.serves("GET", "/ws/exec/web"));
+        assert!(kubernetes.docker_only("container actions").is_err());
+
+        assert!(ProviderKind::Docker.serves("GET", "/api/container/web/detail"));
+        assert!(ProviderKind::Docker.docker_only("container actions").is_ok());
+        assert_eq!(ProviderKind::parse("k8s"), Ok(ProviderKind::Kubernetes));
+        assert!(ProviderKind::parse("nomad").is_err());
+    }
+}
@@ -4,24 +4,52 @@
 
 use axum::{
     extract::{Path, State, Query},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use tracing::{debug, error, info};
+use std::{borrow::Cow, sync::LazyLock, time::Duration};
+use tracing::{debug, error, info, warn};
 
-use crate::AppState;
+use crate::{
+    auth::Caller,
+    diagnostics::kernel,
+    fields::{FieldSet, FieldsQuery, Sparse},
+    graph::GraphFormat,
+    host,
+    docker_api::LogWindow,
+    log_format,
+    dot,
+    mermaid,
+    metrics,
+    models::{
+        ApiScope, Flowchart, ContainerLogs, ContainerSpec, ContainerStatus, FlowDirection, FlowchartFormat, KillRequest, LogStreams, TopologyChange,
+        WarmedActionResult,
+    },
+    provider::DiscoveryProvider,
+    selector::{self, Selector, SelectorQuery},
+    warmup, AppState,
+};
 
-/// GET /api/topology - Get system topology overview
-pub async fn get_topology(State(state): State<AppState>) -> impl IntoResponse {
-    match state.docker.get_topology().await {
-        Ok(topology) => {
-            info!(
+#[derive(Debug, Default, Deserialize)]
+pub struct TopologyQuery {
+    /// Rebuild the snapshot instead of serving the cached one
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// GET /api/topology?refresh= - Get system topology overview, from the shared
+/// snapshot with its age in seconds in the `Age` header
+pub async fn get_topology(State(state): State<AppState>, Query(query): Query<TopologyQuery>) -> impl IntoResponse {
+    match state.topology.current(state.provider.as_ref(), query.refresh).await {
+        Ok((topology, age)) => {
+            debug!(
                 "Topology: {} containers, {} running",
                 topology.total_containers, topology.running_containers
             );
-            (StatusCode::OK, Json(topology)).into_response()
+            (StatusCode::OK, [(header::AGE, age.as_secs().to_string())], Json(topology)).into_response()
         }
         Err(e) => {
             error!("Failed to get topology: {}", e);
@@ -37,12 +65,83 @@ pub async fn get_topology(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
-/// GET /api/containers - List all containers
-pub async fn get_containers(State(state): State<AppState>) -> impl IntoResponse {
-    match state.docker.list_containers().await {
+/// Default and longest wait of `/api/topology/wait`
+const DEFAULT_TOPOLOGY_WAIT: Duration = Duration::from_secs(30);
+const MAX_TOPOLOGY_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+pub struct TopologyWaitQuery {
+    /// Last version the client has seen; 0 returns as soon as anything is known
+    #[serde(default)]
+    pub version: u64,
+    /// `30s`, `500ms`, `1m` or bare seconds
+    pub timeout: Option<String>,
+}
+
+/// Parse a wait timeout such as `30s`, `500ms`, `1m` or `30`
+fn parse_timeout(value: &str) -> Option<Duration> {
+    match value.trim().strip_suffix("ms") {
+        Some(millis) => millis.parse().ok().map(Duration::from_millis),
+        None => metrics::parse_span(value)?.to_std().ok(),
+    }
+}
+
+/// GET /api/topology/wait - Long-poll until the topology moves past `version`
+///
+/// Answers immediately when the topology is already newer, otherwise when it
+/// next changes or after `timeout` (30s by default, at most 60s) with
+/// `changed: false`. The topology is built from the publisher's last snapshot
+/// rather than a fresh Docker listing.
+pub async fn wait_topology(
+    State(state): State<AppState>,
+    Query(query): Query<TopologyWaitQuery>,
+) -> impl IntoResponse {
+    let timeout = match query.timeout.as_deref().map(parse_timeout) {
+        None => DEFAULT_TOPOLOGY_WAIT,
+        Some(Some(timeout)) => timeout.min(MAX_TOPOLOGY_WAIT),
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "timeout must look like 30s, 500ms, 1m or 30"
+                })),
+            )
+                .into_response()
+        }
+    };
+
+    let version = state.hub.wait_for_topology(query.version, timeout).await;
+    let changed = version > query.version;
+    let topology = changed.then(|| state.docker.build_topology(&state.hub.containers()));
+    Json(TopologyChange {
+        version,
+        changed,
+        topology,
+    })
+    .into_response()
+}
+
+/// GET /api/containers - List all containers, optionally narrowed with
+/// `?selector=` and trimmed with `?fields=`
+pub async fn get_containers(
+    State(state): State<AppState>,
+    Query(query): Query<FieldsQuery>,
+    Query(selection): Query<SelectorQuery>,
+) -> impl IntoResponse {
+    let fields = match FieldSet::parse(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(e) => return e.into_response(),
+    };
+    let selector = match Selector::parse(selection.selector.as_deref()) {
+        Ok(selector) => selector,
+        Err(e) => return e.into_response(),
+    };
+
+    match state.provider.list_containers().await {
         Ok(containers) => {
+            let containers = selector::select(selector.as_ref(), containers);
             info!("Listed {} containers", containers.len());
-            (StatusCode::OK, Json(containers)).into_response()
+            (StatusCode::OK, Sparse(containers, fields)).into_response()
         }
         Err(e) => {
             error!("Failed to list containers: {}", e);
@@ -79,21 +178,91 @@ pub async fn get_networks(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
-/// GET /api/flowchart/:id - Get a specific flowchart
+/// GET /api/volumes - List named volumes and the containers mounting them
+pub async fn get_volumes(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.list_volumes().await {
+        Ok(volumes) => {
+            info!("Listed {} volumes", volumes.len());
+            (StatusCode::OK, Json(volumes)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to list volumes: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list volumes",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/services - Swarm services with the tasks running them
+pub async fn get_services(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.list_services().await {
+        Ok(services) => {
+            info!("Listed {} swarm services", services.len());
+            (StatusCode::OK, Json(services)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to list services: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list services",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/flowchart/:id - Get a specific flowchart, optionally narrowed to
+/// the containers `?selector=` picks and trimmed with `?fields=`; as an image
+/// when the id ends in `.svg`
 pub async fn get_flowchart(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<FieldsQuery>,
+    Query(selection): Query<SelectorQuery>,
 ) -> impl IntoResponse {
     debug!("Getting flowchart: {}", id);
+    // The router cannot match a suffix, so `:id.svg` arrives here
+    #[cfg(feature = "svg")]
+    if let Some(id) = id.strip_suffix(".svg") {
+        return crate::svg::respond(&state, id).await;
+    }
+    let fields = match FieldSet::parse(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(e) => return e.into_response(),
+    };
+    let selector = match Selector::parse(selection.selector.as_deref()) {
+        Ok(selector) => selector,
+        Err(e) => return e.into_response(),
+    };
 
-    match state.docker.generate_flowchart(&id).await {
-        Ok(Some(flowchart)) => {
+    let generated = match state.provider.generate_flowchart(&id).await {
+        Ok(Some(mut flowchart)) => match &selector {
+            Some(selector) => state.provider.list_containers().await.map(|containers| {
+                selector::retain_nodes(selector, &mut flowchart, &containers);
+                Some(flowchart)
+            }),
+            None => Ok(Some(flowchart)),
+        },
+        other => other,
+    };
+    match generated {
+        Ok(Some(mut flowchart)) => {
+            state.layouts.apply(&mut flowchart);
             info!(
                 "Generated flowchart '{}' with {} nodes",
                 flowchart.name,
                 flowchart.nodes.len()
             );
-            (StatusCode::OK, Json(flowchart)).into_response()
+            (StatusCode::OK, Sparse(flowchart, fields)).into_response()
         }
         Ok(None) => (
             StatusCode::NOT_FOUND,
@@ -117,6 +286,73 @@ pub async fn get_flowchart(
     }
 }
 
+/// A flowchart as diagram source in `format`
+fn render_flowchart(format: FlowchartFormat, flowchart: &Flowchart, direction: FlowDirection) -> String {
+    match format {
+        FlowchartFormat::Mermaid => mermaid::render_directed(flowchart, direction),
+        FlowchartFormat::Dot => dot::render(flowchart, direction),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: FlowchartFormat,
+    /// Top down unless asked otherwise
+    #[serde(default = "top_down")]
+    pub direction: FlowDirection,
+}
+
+fn top_down() -> FlowDirection {
+    FlowDirection::Td
+}
+
+/// GET /api/flowchart/:id/export?format=mermaid|dot - Flowchart as diagram source
+pub async fn export_flowchart(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    match state.provider.generate_flowchart(&id).await {
+        Ok(Some(flowchart)) => {
+            info!("Exported flowchart '{}' as {:?}", flowchart.name, query.format);
+            let disposition = format!(
+                "inline; filename=\"{}.{}\"",
+                id.replace(['"', '/', '\\'], "_"),
+                query.format.extension()
+            );
+            (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, query.format.content_type().to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                render_flowchart(query.format, &flowchart, query.direction),
+            )
+                .into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Flowchart not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to export flowchart '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to generate flowchart",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// GET /api/container/:id - Get container details
 pub async fn get_container_detail(
     State(state): State<AppState>,
@@ -151,14 +387,79 @@ pub async fn get_container_detail(
     }
 }
 
-/// GET /api/container/:id/detail - Get detailed container info (env, volumes, health)
+#[derive(Debug, Deserialize)]
+pub struct InspectQuery {
+    /// Blank environment variable values; only admins may turn it off
+    #[serde(default = "redacted")]
+    pub redact: bool,
+}
+
+fn redacted() -> bool {
+    true
+}
+
+/// GET /api/container/:id/inspect?redact= - The full Docker inspect response,
+/// for fields the detail view does not cover. Environment values are blanked
+/// unless an admin asks for `redact=false`.
+pub async fn get_container_inspect(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<InspectQuery>,
+    caller: Option<Extension<Caller>>,
+) -> impl IntoResponse {
+    debug!("Inspecting container: {}", id);
+    let token = caller.as_ref().map(|Extension(Caller(token))| token);
+    if !query.redact && !state.auth.grants(token, ApiScope::Admin) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "redact=false needs the admin scope" })),
+        )
+            .into_response();
+    }
+
+    match state.docker.inspect_raw(&id, query.redact).await {
+        Ok(Some(inspect)) => (StatusCode::OK, Json(inspect)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to inspect '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to inspect container",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/container/:id/detail?redact= - Get detailed container info (env,
+/// volumes, health). Environment values are blanked as for inspect.
 pub async fn get_container_full_detail(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<InspectQuery>,
+    caller: Option<Extension<Caller>>,
 ) -> impl IntoResponse {
     debug!("Getting container detail: {}", id);
+    let token = caller.as_ref().map(|Extension(Caller(token))| token);
+    if !query.redact && !state.auth.grants(token, ApiScope::Admin) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "redact=false needs the admin scope" })),
+        )
+            .into_response();
+    }
 
-    match state.docker.get_container_detail(&id).await {
+    match state.docker.get_container_detail(&id, query.redact).await {
         Ok(Some(detail)) => {
             info!("Found container detail: {}", detail.info.name);
             (StatusCode::OK, Json(detail)).into_response()
@@ -189,22 +490,131 @@ pub async fn get_container_full_detail(
 pub struct LogsQuery {
     #[serde(default = "default_tail")]
     pub tail: usize,
+    /// RFC 3339, Unix seconds, or an age such as `15m`
+    pub since: Option<String>,
+    /// Same forms as `since`
+    pub until: Option<String>,
+    #[serde(default)]
+    pub streams: LogStreams,
+    /// Keep only the last `tail` lines containing this text, searched within
+    /// the last [`GREP_SCAN_LINES`]
+    pub grep: Option<String>,
+    /// Treat `grep` as a regular expression
+    #[serde(default)]
+    pub regex: bool,
+    /// Drop terminal escape sequences, before grepping
+    #[serde(default)]
+    pub strip_ansi: bool,
+    /// Parse lines holding a JSON object into `json`
+    #[serde(default)]
+    pub parse_json: bool,
+}
+
+/// What `?grep=` keeps
+enum LogGrep {
+    Text(String),
+    Pattern(regex::Regex),
+}
+
+impl LogGrep {
+    fn matches(&self, line: &str) -> bool {
+        match self {
+            LogGrep::Text(text) => line.contains(text.as_str()),
+            LogGrep::Pattern(regex) => regex.is_match(line),
+        }
+    }
 }
 
 fn default_tail() -> usize {
     100
 }
 
-/// GET /api/container/:id/logs - Get container logs
+/// Lines read to grep through, so matches are not limited to the last `tail`
+const GREP_SCAN_LINES: usize = 10_000;
+
+/// A log time bound as RFC 3339, Unix seconds, or an age in `s`, `m`, `h`
+/// or `d` before `now`
+pub fn parse_log_time(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(seconds) = value.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0);
+    }
+    now.checked_sub_signed(metrics::parse_span(value)?)
+}
+
+impl LogsQuery {
+    /// The lines asked for, or why a bound does not parse
+    fn window(&self) -> Result<LogWindow, String> {
+        let now = Utc::now();
+        let bound = |value: &Option<String>, name: &str| match value.as_deref() {
+            None => Ok(None),
+            Some(value) => parse_log_time(value, now).map(Some).ok_or_else(|| {
+                format!("'{}' is not a valid {}; use RFC 3339, Unix seconds or an age such as 15m", value, name)
+            }),
+        };
+        let grepping = self.grep.as_deref().is_some_and(|grep| !grep.is_empty());
+        Ok(LogWindow {
+            tail: if grepping { self.tail.max(GREP_SCAN_LINES) } else { self.tail },
+            since: bound(&self.since, "since")?,
+            until: bound(&self.until, "until")?,
+            streams: self.streams,
+        })
+    }
+
+    /// The line filter asked for, or why its pattern does not compile
+    fn grep(&self) -> Result<Option<LogGrep>, String> {
+        match self.grep.as_deref() {
+            None | Some("") => Ok(None),
+            Some(pattern) if self.regex => log_format::compile_pattern(pattern).map(|r| Some(LogGrep::Pattern(r))),
+            Some(text) => Ok(Some(LogGrep::Text(text.to_string()))),
+        }
+    }
+}
+
+/// Clean lines up as `query` asks, then keep the last `tail` lines `grep` keeps
+fn process_logs(logs: &mut ContainerLogs, query: &LogsQuery, grep: Option<&LogGrep>) {
+    if query.strip_ansi {
+        for entry in &mut logs.logs {
+            if let Cow::Owned(line) = log_format::strip_ansi(&entry.line) {
+                entry.line = line;
+            }
+        }
+    }
+    if let Some(grep) = grep {
+        logs.logs.retain(|entry| grep.matches(&entry.line));
+        let excess = logs.logs.len().saturating_sub(query.tail);
+        logs.logs.drain(..excess);
+        logs.tail = query.tail;
+    }
+    if query.parse_json {
+        for entry in &mut logs.logs {
+            entry.json = log_format::parse_json(&entry.line);
+        }
+    }
+}
+
+/// GET /api/container/:id/logs - Get container logs, narrowed by `since`,
+/// `until`, `streams` and `grep` (a regex with `regex=true`); `strip_ansi`
+/// and `parse_json` clean up and structure the lines
 pub async fn get_container_logs(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Query(query): Query<LogsQuery>,
 ) -> impl IntoResponse {
     debug!("Getting container logs: {} (tail: {})", id, query.tail);
+    let (window, grep) = match query.window().and_then(|window| Ok((window, query.grep()?))) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response()
+        }
+    };
 
-    match state.docker.get_container_logs(&id, query.tail).await {
-        Ok(Some(logs)) => {
+    match state.docker.get_container_logs(&id, window).await {
+        Ok(Some(mut logs)) => {
+            process_logs(&mut logs, &query, grep.as_ref());
             info!("Got {} log lines for container: {}", logs.logs.len(), logs.container_name);
             (StatusCode::OK, Json(logs)).into_response()
         }
@@ -230,10 +640,72 @@ pub async fn get_container_logs(
     }
 }
 
-/// POST /api/container/:id/restart - Restart a container
+/// GET /api/logs - Recent logs of every container `?selector=` picks, `tail`
+/// lines each; all containers without a selector
+pub async fn get_logs(
+    State(state): State<AppState>,
+    Query(query): Query<LogsQuery>,
+    Query(selection): Query<SelectorQuery>,
+) -> impl IntoResponse {
+    let selector = match Selector::parse(selection.selector.as_deref()) {
+        Ok(selector) => selector,
+        Err(e) => return e.into_response(),
+    };
+    let (window, grep) = match query.window().and_then(|window| Ok((window, query.grep()?))) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message }))).into_response()
+        }
+    };
+    let containers = match state.docker.list_containers().await {
+        Ok(containers) => selector::select(selector.as_ref(), containers),
+        Err(e) => {
+            error!("Failed to list containers for logs: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list containers",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let results = futures_util::future::join_all(
+        containers
+            .iter()
+            .map(|c| state.docker.get_container_logs(&c.id, window)),
+    )
+    .await;
+    let mut logs: Vec<ContainerLogs> = Vec::new();
+    for (container, result) in containers.iter().zip(results) {
+        match result {
+            Ok(Some(mut found)) => {
+                process_logs(&mut found, &query, grep.as_ref());
+                logs.push(found)
+            }
+            // Removed since it was listed
+            Ok(None) => {}
+            Err(e) => warn!("Failed to get logs for '{}': {}", container.name, e),
+        }
+    }
+    info!("Got logs of {} containers", logs.len());
+    (StatusCode::OK, Json(logs)).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestartQuery {
+    /// `false` skips the service's warm-up requests
+    pub warmup: Option<bool>,
+}
+
+/// POST /api/container/:id/restart - Restart a container, then run its
+/// service's warm-up requests unless `?warmup=false`
 pub async fn restart_container(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<RestartQuery>,
 ) -> impl IntoResponse {
     info!("Restarting container: {}", id);
 
@@ -241,6 +713,15 @@ pub async fn restart_container(
         Ok(Some(result)) => {
             if result.success {
                 info!("Restarted container: {}", result.container_name);
+                if query.warmup != Some(false) {
+                    match warmup::after_restart(&state, &result.container_id).await {
+                        Ok(Some(warmup)) => {
+                            return (StatusCode::OK, Json(WarmedActionResult { result, warmup })).into_response()
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("Skipped warm-up of {}: {}", result.container_name, e),
+                    }
+                }
             } else {
                 error!("Failed to restart: {}", result.message);
             }
@@ -306,19 +787,19 @@ pub async fn stop_container(
     }
 }
 
-/// POST /api/container/:id/start - Start a container
-pub async fn start_container(
+/// POST /api/container/:id/pause - Pause a container
+pub async fn pause_container(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    info!("Starting container: {}", id);
+    info!("Pausing container: {}", id);
 
-    match state.docker.start_container(&id).await {
+    match state.docker.pause_container(&id).await {
         Ok(Some(result)) => {
             if result.success {
-                info!("Started container: {}", result.container_name);
+                info!("Paused container: {}", result.container_name);
             } else {
-                error!("Failed to start: {}", result.message);
+                error!("Failed to pause: {}", result.message);
             }
             (if result.success { StatusCode::OK } else { StatusCode::INTERNAL_SERVER_ERROR }, Json(result)).into_response()
         }
@@ -331,11 +812,11 @@ pub async fn start_container(
         )
             .into_response(),
         Err(e) => {
-            error!("Failed to start '{}': {}", id, e);
+            error!("Failed to pause '{}': {}", id, e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({
-                    "error": "Failed to start container",
+                    "error": "Failed to pause container",
                     "details": e.to_string()
                 })),
             )
@@ -344,32 +825,36 @@ pub async fn start_container(
     }
 }
 
-/// GET /api/container/:id/stats - Get container resource stats
-pub async fn get_container_stats(
+/// POST /api/container/:id/unpause - Resume a paused container
+pub async fn unpause_container(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    debug!("Getting container stats: {}", id);
+    info!("Unpausing container: {}", id);
 
-    match state.docker.get_container_stats(&id).await {
-        Ok(Some(stats)) => {
-            info!("Got stats for container: {}", id);
-            (StatusCode::OK, Json(stats)).into_response()
+    match state.docker.unpause_container(&id).await {
+        Ok(Some(result)) => {
+            if result.success {
+                info!("Unpaused container: {}", result.container_name);
+            } else {
+                error!("Failed to unpause: {}", result.message);
+            }
+            (if result.success { StatusCode::OK } else { StatusCode::INTERNAL_SERVER_ERROR }, Json(result)).into_response()
         }
         Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({
-                "error": "Container not found or not running",
+                "error": "Container not found",
                 "id": id
             })),
         )
             .into_response(),
         Err(e) => {
-            error!("Failed to get stats for '{}': {}", id, e);
+            error!("Failed to unpause '{}': {}", id, e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({
-                    "error": "Failed to get container stats",
+                    "error": "Failed to unpause container",
                     "details": e.to_string()
                 })),
             )
@@ -378,19 +863,71 @@ pub async fn get_container_stats(
     }
 }
 
-/// GET /api/containers/stats - Get all containers with live stats
-pub async fn get_containers_with_stats(State(state): State<AppState>) -> impl IntoResponse {
-    match state.docker.list_containers_with_stats().await {
-        Ok(containers) => {
-            info!("Listed {} containers with stats", containers.len());
-            (StatusCode::OK, Json(containers)).into_response()
+/// Linux signals by name, in number order
+const SIGNALS: &[&str] = &[
+    "HUP", "INT", "QUIT", "ILL", "TRAP", "ABRT", "BUS", "FPE", "KILL", "USR1", "SEGV", "USR2", "PIPE", "ALRM", "TERM",
+    "STKFLT", "CHLD", "CONT", "STOP", "TSTP", "TTIN", "TTOU", "URG", "XCPU", "XFSZ", "VTALRM", "PROF", "WINCH", "IO",
+    "PWR", "SYS",
+];
+
+/// A signal as `SIGHUP`, from `SIGHUP`, `hup` or `1`; numbers above the named
+/// ones (real-time signals) pass through as they are
+fn parse_signal(value: &str) -> Option<String> {
+    let value = value.trim();
+    if let Ok(number) = value.parse::<usize>() {
+        return match number {
+            1..=31 => Some(format!("SIG{}", SIGNALS[number - 1])),
+            32..=64 => Some(number.to_string()),
+            _ => None,
+        };
+    }
+    let upper = value.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS.contains(&name).then(|| format!("SIG{}", name))
+}
+
+/// POST /api/container/:id/kill - Send a signal to a container, `SIGKILL`
+/// unless the body names another such as `SIGHUP`
+pub async fn kill_container(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<KillRequest>>,
+) -> impl IntoResponse {
+    let requested = request.and_then(|Json(r)| r.signal).unwrap_or_else(|| "SIGKILL".to_string());
+    let Some(signal) = parse_signal(&requested) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("'{}' is not a signal", requested)
+            })),
+        )
+            .into_response();
+    };
+    info!("Sending {} to container: {}", signal, id);
+
+    match state.docker.kill_container(&id, &signal).await {
+        Ok(Some(result)) => {
+            if result.success {
+                info!("Sent {} to container: {}", signal, result.container_name);
+            } else {
+                error!("Failed to kill: {}", result.message);
+            }
+            (if result.success { StatusCode::OK } else { StatusCode::INTERNAL_SERVER_ERROR }, Json(result)).into_response()
         }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
         Err(e) => {
-            error!("Failed to list containers with stats: {}", e);
+            error!("Failed to kill '{}': {}", id, e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({
-                    "error": "Failed to list containers with stats",
+                    "error": "Failed to kill container",
                     "details": e.to_string()
                 })),
             )
@@ -399,19 +936,65 @@ pub async fn get_containers_with_stats(State(state): State<AppState>) -> impl In
     }
 }
 
-/// GET /api/images/sizes - Get all image sizes
-pub async fn get_image_sizes(State(state): State<AppState>) -> impl IntoResponse {
-    match state.docker.list_image_sizes().await {
-        Ok(sizes) => {
-            info!("Listed {} image sizes", sizes.len());
-            (StatusCode::OK, Json(sizes)).into_response()
+#[derive(Debug, Default, Deserialize)]
+pub struct RemoveQuery {
+    /// Stop the container first when it is running
+    #[serde(default)]
+    pub force: bool,
+    /// Remove its anonymous volumes with it
+    #[serde(default)]
+    pub remove_volumes: bool,
+}
+
+/// DELETE /api/container/:id?force=&remove_volumes= - Remove a container,
+/// refusing running ones unless `force` is set
+pub async fn remove_container(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<RemoveQuery>,
+) -> impl IntoResponse {
+    if !query.force {
+        if let Ok(Some(container)) = state.docker.get_container(&id).await {
+            if matches!(
+                container.status,
+                ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+            ) {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({
+                        "error": "Container is running; stop it first or pass force=true",
+                        "id": id
+                    })),
+                )
+                    .into_response();
+            }
+        }
+    }
+    info!("Removing container: {}", id);
+
+    match state.docker.remove_container(&id, query.force, query.remove_volumes).await {
+        Ok(Some(result)) => {
+            if result.success {
+                info!("Removed container: {}", result.container_name);
+            } else {
+                error!("Failed to remove: {}", result.message);
+            }
+            (if result.success { StatusCode::OK } else { StatusCode::INTERNAL_SERVER_ERROR }, Json(result)).into_response()
         }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
         Err(e) => {
-            error!("Failed to list image sizes: {}", e);
+            error!("Failed to remove '{}': {}", id, e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({
-                    "error": "Failed to list image sizes",
+                    "error": "Failed to remove container",
                     "details": e.to_string()
                 })),
             )
@@ -419,3 +1002,583 @@ pub async fn get_image_sizes(State(state): State<AppState>) -> impl IntoResponse
         }
     }
 }
+
+/// POST /api/container/:id/start - Start a container
+pub async fn start_container(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("Starting container: {}", id);
+
+    match state.docker.start_container(&id).await {
+        Ok(Some(result)) => {
+            if result.success {
+                info!("Started container: {}", result.container_name);
+            } else {
+                error!("Failed to start: {}", result.message);
+            }
+            (if result.success { StatusCode::OK } else { StatusCode::INTERNAL_SERVER_ERROR }, Json(result)).into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to start '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to start container",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/container/:id/stats - Get container resource stats
+pub async fn get_container_stats(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    debug!("Getting container stats: {}", id);
+
+    match state.docker.get_container_stats(&id).await {
+        Ok(Some(stats)) => {
+            info!("Got stats for container: {}", id);
+            (StatusCode::OK, Json(stats)).into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found or not running",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to get stats for '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to get container stats",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/containers/stats - Get all containers with live stats, optionally
+/// narrowed with `?selector=` and trimmed with `?fields=`
+pub async fn get_containers_with_stats(
+    State(state): State<AppState>,
+    Query(query): Query<FieldsQuery>,
+    Query(selection): Query<SelectorQuery>,
+) -> impl IntoResponse {
+    let fields = match FieldSet::parse(query.fields.as_deref()) {
+        Ok(fields) => fields,
+        Err(e) => return e.into_response(),
+    };
+    let selector = match Selector::parse(selection.selector.as_deref()) {
+        Ok(selector) => selector,
+        Err(e) => return e.into_response(),
+    };
+
+    match state.docker.list_containers_with_stats().await {
+        Ok(containers) => {
+            let containers = selector::select(selector.as_ref(), containers);
+            info!("Listed {} containers with stats", containers.len());
+            (StatusCode::OK, Sparse(containers, fields)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to list containers with stats: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list containers with stats",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/host/cpus - Which cores pinned containers share, by NUMA node
+pub async fn get_cpu_map(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.cpu_map(host::read_numa_nodes()).await {
+        Ok(map) => {
+            info!("CPU map: {} pinned containers, {} contended cores", map.pinned.len(), map.contended_cores);
+            (StatusCode::OK, Json(map)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to build CPU map: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to build CPU map",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/images/sizes - Get all image sizes
+pub async fn get_image_sizes(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.list_image_sizes().await {
+        Ok(sizes) => {
+            info!("Listed {} image sizes", sizes.len());
+            (StatusCode::OK, Json(sizes)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to list image sizes: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list image sizes",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/system/info - Daemon environment and supported features
+pub async fn get_system_info(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.system_info().await {
+        Ok(info) => (StatusCode::OK, Json(info)).into_response(),
+        Err(e) => {
+            error!("Failed to read system info: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to read system info",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/images/usage?range=1h&step=1m - CPU and memory aggregated per
+/// image, with each image's recorded history when `range` is given
+pub async fn get_image_usage(
+    State(state): State<AppState>,
+    Query(query): Query<metrics::StatsHistoryQuery>,
+) -> impl IntoResponse {
+    let window = match query.range.is_some().then(|| metrics::history_window(&query)).transpose() {
+        Ok(window) => window,
+        Err(message) => return metrics::bad_request(message),
+    };
+    match state.docker.get_image_usage().await {
+        Ok(mut usage) => {
+            if let Some((range, step)) = window {
+                let from = Utc::now() - range;
+                let samples = state.metrics.since(from);
+                for image in &mut usage {
+                    image.history = Some(metrics::image_history(&samples, &image.image, from, step));
+                }
+            }
+            info!("Aggregated usage for {} images", usage.len());
+            (StatusCode::OK, Json(usage)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to aggregate image usage: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to aggregate image usage",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/diagnostics - Combined misconfiguration report with per-finding severity
+pub async fn get_diagnostics(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.get_diagnostics().await {
+        Ok(report) => {
+            info!(
+                "Diagnostics: {} errors, {} warnings, {} infos across {} containers",
+                report.errors, report.warnings, report.infos, report.containers_checked
+            );
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to run diagnostics: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to run diagnostics",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/diagnostics/host - Host kernel and network parameters that break containers
+pub async fn get_host_diagnostics() -> impl IntoResponse {
+    let values = kernel::read_parameters(&kernel::sysctl_root());
+    let report = kernel::evaluate(values);
+    info!(
+        "Host diagnostics: {} errors, {} warnings",
+        report.errors, report.warnings
+    );
+    Json(report)
+}
+
+/// GET /api/diagnostics/processes - Zombie and PID-limit report for running containers
+pub async fn get_process_diagnostics(State(state): State<AppState>) -> impl IntoResponse {
+    match state.docker.list_process_diagnostics().await {
+        Ok(reports) => {
+            let flagged = reports.iter().filter(|r| !r.warnings.is_empty()).count();
+            info!("Process diagnostics: {} containers, {} flagged", reports.len(), flagged);
+            (StatusCode::OK, Json(reports)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to collect process diagnostics: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to collect process diagnostics",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/container/:id/diagnostics/processes - Process report for one container
+pub async fn get_container_process_diagnostics(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    debug!("Getting process diagnostics: {}", id);
+
+    match state.docker.get_process_diagnostics(&id).await {
+        Ok(Some(report)) => (StatusCode::OK, Json(report)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to get process diagnostics for '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to get process diagnostics",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/container/:id/processes - Processes running in a container, with
+/// PID, user, CPU, memory and command line
+pub async fn get_container_processes(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    debug!("Listing processes: {}", id);
+
+    match state.docker.list_processes(&id).await {
+        Ok(Some(processes)) => (StatusCode::OK, Json(processes)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to list processes of '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list processes",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/container/:id/changes - Paths added, modified or deleted in a
+/// container's writable layer
+pub async fn get_container_changes(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    debug!("Getting filesystem changes: {}", id);
+
+    match state.docker.container_changes(&id).await {
+        Ok(Some(changes)) => (StatusCode::OK, Json(changes)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Container not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to get filesystem changes of '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to get filesystem changes",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// POST /api/containers/preflight - Validate a container spec before creating it
+pub async fn preflight_container(
+    State(state): State<AppState>,
+    Json(spec): Json<ContainerSpec>,
+) -> impl IntoResponse {
+    debug!("Pre-flight check for image {}", spec.image);
+
+    match state.docker.preflight(&spec).await {
+        Ok(report) => {
+            if !report.ok {
+                info!("Pre-flight failed for image {}", spec.image);
+            }
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to run pre-flight for '{}': {}", spec.image, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to run pre-flight checks",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// POST /api/containers - Create and start a container from a spec, once
+/// its pre-flight checks pass
+pub async fn create_container(
+    State(state): State<AppState>,
+    Json(spec): Json<ContainerSpec>,
+) -> impl IntoResponse {
+    let failed = |error: &str, e: bollard::errors::Error| {
+        error!("{} from '{}': {}", error, spec.image, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": error,
+                "details": e.to_string()
+            })),
+        )
+            .into_response()
+    };
+    if spec.image.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "An image is required" }))).into_response();
+    }
+
+    match state.docker.preflight(&spec).await {
+        Ok(report) if !report.ok => {
+            info!("Refused to create a container from {}: pre-flight failed", spec.image);
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "error": "Pre-flight checks failed",
+                    "preflight": report
+                })),
+            )
+                .into_response();
+        }
+        Ok(_) => {}
+        Err(e) => return failed("Failed to run pre-flight checks", e),
+    }
+
+    let id = match state.docker.create_from_spec(&spec).await {
+        Ok(id) => id,
+        Err(e) => return failed("Failed to create container", e),
+    };
+    match state.docker.get_container(&id).await {
+        Ok(Some(container)) => {
+            info!("Created container {} from {}", container.name, spec.image);
+            (StatusCode::CREATED, Json(container)).into_response()
+        }
+        Ok(None) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "The created container is not listed",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => failed("Failed to list the created container", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQuery {
+    #[serde(default)]
+    pub format: GraphFormat,
+}
+
+/// GET /api/graph/export?format=graphml|jgf - Inferred dependency graph for external tools
+pub async fn export_graph(
+    State(state): State<AppState>,
+    Query(query): Query<GraphQuery>,
+) -> impl IntoResponse {
+    match state.docker.dependency_graph().await {
+        Ok(graph) => {
+            info!(
+                "Exported dependency graph: {} nodes, {} edges",
+                graph.nodes.len(),
+                graph.edges.len()
+            );
+            let disposition = format!("attachment; filename=\"{}\"", query.format.file_name());
+            (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, query.format.content_type().to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                query.format.render(&graph),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to build dependency graph: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to build dependency graph",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// TypeScript definitions for every API model, generated once at startup
+static TYPESCRIPT_SCHEMA: LazyLock<String> = LazyLock::new(flowscope_types::ts::definitions);
+
+/// GET /api/schema/typescript - TypeScript definitions generated from the Rust models
+pub async fn get_typescript_schema() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/typescript; charset=utf-8")],
+        TYPESCRIPT_SCHEMA.as_str(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LogEntry, LogStream};
+
+    #[test]
+    fn wait_timeouts() {
+        assert_eq!(parse_timeout("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_timeout("45"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_timeout("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_timeout("1m"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_timeout("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_timeout("s"), None);
+        assert_eq!(parse_timeout("500000000000000000m"), None);
+    }
+
+    #[test]
+    fn log_times() {
+        let now = DateTime::parse_from_rfc3339("2026-10-15T12:00:00Z").unwrap().with_timezone(&Utc);
+        let at = |value| parse_log_time(value, now).map(|t| t.to_rfc3339());
+        assert_eq!(at("2026-10-15T08:30:00+02:00").as_deref(), Some("2026-10-15T06:30:00+00:00"));
+        assert_eq!(at("1791979200").as_deref(), Some("2026-10-14T12:00:00+00:00"));
+        assert_eq!(at("15m").as_deref(), Some("2026-10-15T11:45:00+00:00"));
+        assert_eq!(at("2h").as_deref(), Some("2026-10-15T10:00:00+00:00"));
+        assert_eq!(at("yesterday"), None);
+        assert_eq!(at("5w"), None);
+    }
+
+    #[test]
+    fn log_grep() {
+        let query = |value| serde_json::from_value::<LogsQuery>(value).unwrap();
+        let text = query(serde_json::json!({ "grep": "ERROR" })).grep().unwrap().unwrap();
+        assert!(text.matches("12:00 ERROR timeout") && !text.matches("12:00 error timeout"));
+        let pattern = query(serde_json::json!({ "grep": "(?i)error|panic", "regex": true })).grep().unwrap().unwrap();
+        assert!(pattern.matches("thread main panicked") && pattern.matches("Error: refused"));
+        assert!(query(serde_json::json!({ "grep": "" })).grep().unwrap().is_none());
+        assert!(query(serde_json::json!({ "grep": "(", "regex": true })).grep().is_err());
+    }
+
+    #[test]
+    fn grep_reads_past_the_tail() {
+        let query = serde_json::from_value::<LogsQuery>(serde_json::json!({ "tail": 2, "grep": "ERROR" })).unwrap();
+        assert_eq!(query.window().unwrap().tail, GREP_SCAN_LINES);
+
+        let line = |line: &str| LogEntry {
+            ts: None,
+            stream: LogStream::Stdout,
+            line: line.to_string(),
+            json: None,
+        };
+        let mut logs = ContainerLogs {
+            container_id: "abc".to_string(),
+            container_name: "api".to_string(),
+            logs: ["ERROR one", "ERROR two", "ok", "ERROR three", "ok", "ok"].map(line).to_vec(),
+            tail: GREP_SCAN_LINES,
+            since: None,
+            until: None,
+            streams: LogStreams::Both,
+        };
+        process_logs(&mut logs, &query, query.grep().unwrap().as_ref());
+        let lines: Vec<&str> = logs.logs.iter().map(|e| e.line.as_str()).collect();
+        assert_eq!(lines, ["ERROR two", "ERROR three"]);
+        assert_eq!(logs.tail, 2);
+    }
+
+    #[test]
+    fn inspect_redacts_by_default() {
+        let query = |value| serde_json::from_value::<InspectQuery>(value).unwrap().redact;
+        assert!(query(serde_json::json!({})));
+        assert!(!query(serde_json::json!({ "redact": false })));
+    }
+
+    #[test]
+    fn signals() {
+        assert_eq!(parse_signal("SIGHUP").as_deref(), Some("SIGHUP"));
+        assert_eq!(parse_signal(" usr1 ").as_deref(), Some("SIGUSR1"));
+        assert_eq!(parse_signal("15").as_deref(), Some("SIGTERM"));
+        assert_eq!(parse_signal("34").as_deref(), Some("34"));
+        assert_eq!(parse_signal("SIGFOO"), None);
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal(""), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! One-off runs
+//!
+//! `POST /api/containers/run` creates a container for a single command, like
+//! `docker run --rm -it`, optionally on one of the networks FlowScope already
+//! knows, so migrations or debug jobs can reach the services they need. The
+//! container is created stopped; a WebSocket client starts it with `attachRun`
+//! and talks to it over the socket (see `websocket::runs`), and it is removed
+//! when the process exits or the connection closes.
+//!
+//! Runs nobody attaches to within [`ATTACH_TIMEOUT`], or that outlive their
+//! TTL, are removed by a background reaper. Run containers carry
+//! [`RUN_LABEL`], so ones left behind by an earlier FlowScope process are
+//! swept up as well.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use tracing::{error, info, warn};
+
+use crate::{
+    auth::Caller,
+    models::{OneOffRun, OneOffRunRequest},
+    ttl,
+    AppState,
+};
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+pub const MAX_TTL: Duration = Duration::from_secs(2 * 60 * 60);
+/// How long a created run waits for a client to attach
+pub const ATTACH_TIMEOUT: Duration = Duration::from_secs(60);
+/// Runs tracked at once, attached or not
+pub const MAX_RUNS: usize = 16;
+/// How often stale runs are looked for
+pub const REAP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Label marking a one-off run container; its value is the creating token
+pub const RUN_LABEL: &str = "flowscope.run";
+/// Label holding the RFC 3339 time a run expires
+pub const EXPIRES_LABEL: &str = "flowscope.run.expires-at";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RunError {
+    #[error("run '{0}' not found")]
+    NotFound(String),
+    #[error("network '{0}' not found")]
+    NetworkNotFound(String),
+    #[error("invalid run: {0}")]
+    Invalid(String),
+    #[error("at most {0} runs may exist at once")]
+    TooMany(usize),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl RunError {
+    fn status(&self) -> StatusCode {
+        match self {
+            RunError::NotFound(_) | RunError::NetworkNotFound(_) => StatusCode::NOT_FOUND,
+            RunError::Invalid(_) => StatusCode::BAD_REQUEST,
+            RunError::TooMany(_) => StatusCode::TOO_MANY_REQUESTS,
+            RunError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for RunError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("One-off run error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Runs created by this instance whose containers have not been removed yet
+#[derive(Debug, Default)]
+pub struct OneOffRuns {
+    runs: Mutex<Vec<OneOffRun>>,
+}
+
+impl OneOffRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All tracked runs, oldest first
+    pub fn list(&self) -> Vec<OneOffRun> {
+        let mut runs = self.runs.lock().unwrap().clone();
+        runs.sort_by_key(|r| r.created_at);
+        runs
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.runs.lock().unwrap().iter().any(|r| r.id == id)
+    }
+
+    /// Track a run unless [`MAX_RUNS`] already are
+    pub fn insert(&self, run: OneOffRun) -> Result<(), RunError> {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.len() >= MAX_RUNS {
+            return Err(RunError::TooMany(MAX_RUNS));
+        }
+        runs.push(run);
+        Ok(())
+    }
+
+    /// Mark the unattached run `id` attached on behalf of `token`, which must
+    /// be the token that created it
+    pub fn claim(&self, id: &str, token: Option<&str>) -> Result<OneOffRun, String> {
+        let mut runs = self.runs.lock().unwrap();
+        let run = runs
+            .iter_mut()
+            .find(|r| r.id == id)
+            .ok_or_else(|| format!("no run '{}'", id))?;
+        if run.attached {
+            return Err(format!("run '{}' is already attached", id));
+        }
+        if run.started_by.is_some() && run.started_by.as_deref() != token {
+            return Err(format!("run '{}' belongs to another token", id));
+        }
+        run.attached = true;
+        Ok(run.clone())
+    }
+
+    /// Stop tracking the run with this id or name
+    pub fn take(&self, id: &str) -> Option<OneOffRun> {
+        let mut runs = self.runs.lock().unwrap();
+        let index = runs.iter().position(|r| r.id == id || r.name == id)?;
+        Some(runs.remove(index))
+    }
+
+    /// Stop tracking and return every run that nobody attached to in time or
+    /// that expired at `now`. Attached runs enforce their own TTL, so expired
+    /// ones are only returned here as a backstop.
+    fn take_stale(&self, now: DateTime<Utc>) -> Vec<OneOffRun> {
+        let mut runs = self.runs.lock().unwrap();
+        let (stale, live) = std::mem::take(&mut *runs)
+            .into_iter()
+            .partition(|r| (!r.attached && r.attach_by <= now) || r.expires_at <= now);
+        *runs = live;
+        stale
+    }
+}
+
+/// Check the fields that do not need the daemon
+pub fn validate(request: &OneOffRunRequest) -> Result<(), RunError> {
+    if request.image.trim().is_empty() {
+        return Err(RunError::Invalid("image must not be empty".to_string()));
+    }
+    if let Some(entry) = request.env.iter().find(|e| e.split_once('=').is_none_or(|(key, _)| key.is_empty())) {
+        return Err(RunError::Invalid(format!("env entry '{}' must be KEY=value", entry)));
+    }
+    if request.network.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(RunError::Invalid("network must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Spawn the background task that removes stale and orphaned runs
+pub fn spawn_reaper(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(REAP_INTERVAL).await;
+            let now = Utc::now();
+            for run in state.runs.take_stale(now) {
+                match state.docker.discard_container(&run.id).await {
+                    Ok(()) => info!("Removed stale one-off run {}", run.name),
+                    Err(e) => warn!("Failed to remove stale one-off run {}: {}", run.name, e),
+                }
+            }
+
+            // Left behind by an earlier process; nothing can attach to them now
+            let containers = match state.docker.list_containers().await {
+                Ok(containers) => containers,
+                Err(e) => {
+                    warn!("Failed to list containers for one-off runs: {}", e);
+                    continue;
+                }
+            };
+            let grace = chrono::Duration::from_std(ATTACH_TIMEOUT).expect("timeout is small");
+            for container in containers {
+                let orphaned = container.labels.contains_key(RUN_LABEL)
+                    && container.created + grace <= now
+                    && !state.runs.contains(&container.id);
+                if orphaned {
+                    match state.docker.discard_container(&container.id).await {
+                        Ok(()) => info!("Removed orphaned one-off run {}", container.name),
+                        Err(e) => warn!("Failed to remove orphaned one-off run {}: {}", container.name, e),
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// GET /api/runs - List one-off runs that still have a container
+pub async fn list_runs(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.runs.list())
+}
+
+/// POST /api/containers/run - Create a one-off container to attach to over the WebSocket
+pub async fn create_run(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<OneOffRunRequest>,
+) -> Result<impl IntoResponse, RunError> {
+    validate(&request)?;
+    let ttl = ttl::requested(request.ttl_seconds, DEFAULT_TTL, MAX_TTL).map_err(RunError::Invalid)?;
+    if let Some(network) = &request.network {
+        let networks = state.docker.list_networks().await?;
+        if !networks.iter().any(|n| &n.name == network || &n.id == network) {
+            return Err(RunError::NetworkNotFound(network.clone()));
+        }
+    }
+
+    let started_by = caller.map(|Extension(Caller(token))| token.name);
+    let created_at = Utc::now();
+    let attach_by = created_at + chrono::Duration::from_std(ATTACH_TIMEOUT).expect("timeout is small");
+    let expires_at = created_at + chrono::Duration::from_std(ttl).expect("TTL is capped");
+    let suffix: String = uuid::Uuid::new_v4().simple().to_string().chars().take(8).collect();
+    let name = format!("flowscope-run-{}", suffix);
+    let labels = HashMap::from([
+        (RUN_LABEL.to_string(), started_by.clone().unwrap_or_default()),
+        (EXPIRES_LABEL.to_string(), expires_at.to_rfc3339()),
+    ]);
+
+    let id = state.docker.create_one_off(&name, &request, labels).await?;
+    let run = OneOffRun {
+        id,
+        name,
+        image: request.image,
+        cmd: request.cmd,
+        network: request.network,
+        tty: request.tty,
+        started_by,
+        attached: false,
+        created_at,
+        attach_by,
+        expires_at,
+    };
+    if let Err(e) = state.runs.insert(run.clone()) {
+        let _ = state.docker.discard_container(&run.id).await;
+        return Err(e);
+    }
+    info!("Created one-off run {} ({}) until {}", run.name, run.image, run.expires_at);
+    Ok((StatusCode::CREATED, Json(run)))
+}
+
+/// DELETE /api/runs/:id - Stop and remove a one-off run
+pub async fn remove_run(State(state): State<AppState>, Path(id): Path<String>) -> Result<StatusCode, RunError> {
+    let run = state.runs.take(&id).ok_or_else(|| RunError::NotFound(id))?;
+    if let Err(e) = state.docker.discard_container(&run.id).await {
+        // Keep tracking it so the reaper tries again; its slot was just freed
+        let _ = state.runs.insert(run);
+        return Err(e.into());
+    }
+    info!("Removed one-off run {}", run.name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(image: &str, env: &[&str]) -> OneOffRunRequest {
+        OneOffRunRequest {
+            image: image.to_string(),
+            cmd: Vec::new(),
+            env: env.iter().map(|e| e.to_string()).collect(),
+            network: None,
+            tty: true,
+            ttl_seconds: None,
+        }
+    }
+
+    fn run(id: &str, started_by: Option<&str>, attach_in: i64, expires_in: i64) -> OneOffRun {
+        let now = Utc::now();
+        OneOffRun {
+            id: id.to_string(),
+            name: format!("flowscope-run-{}", id),
+            image: "alpine".to_string(),
+            cmd: Vec::new(),
+            network: None,
+            tty: true,
+            started_by: started_by.map(str::to_string),
+            attached: false,
+            created_at: now,
+            attach_by: now + chrono::Duration::seconds(attach_in),
+            expires_at: now + chrono::Duration::seconds(expires_in),
+        }
+    }
+
+    #[test]
+    fn requests_are_validated() {
+        assert!(validate(&request("migrate:1", &["DATABASE_URL=postgres://db"])).is_ok());
+        assert!(validate(&request(" ", &[])).is_err());
+        assert!(validate(&request("alpine", &["NO_VALUE"])).is_err());
+        assert!(validate(&request("alpine", &["=value"])).is_err());
+    }
+
+    #[test]
+    fn only_the_creating_token_attaches_once() {
+        let runs = OneOffRuns::new();
+        runs.insert(run("a", Some("ops"), 60, 600)).unwrap();
+
+        assert!(runs.claim("a", Some("ci")).is_err());
+        assert!(runs.claim("a", Some("ops")).unwrap().attached);
+        assert!(runs.claim("a", Some("ops")).is_err());
+        assert!(runs.claim("missing", Some("ops")).is_err());
+    }
+
+    #[test]
+    fn unattached_and_expired_runs_are_stale() {
+        let runs = OneOffRuns::new();
+        runs.insert(run("waiting", None, 60, 600)).unwrap();
+        runs.insert(run("abandoned", None, -1, 600)).unwrap();
+        runs.insert(run("attached", None, -1, 600)).unwrap();
+        runs.insert(run("expired", None, -1, -1)).unwrap();
+        runs.claim("attached", None).unwrap();
+        runs.claim("expired", None).unwrap();
+
+        let mut stale: Vec<String> = runs.take_stale(Utc::now()).into_iter().map(|r| r.id).collect();
+        stale.sort();
+        assert_eq!(stale, ["abandoned", "expired"]);
+        let live: Vec<String> = runs.list().into_iter().map(|r| r.id).collect();
+        assert_eq!(live.len(), 2);
+    }
+}
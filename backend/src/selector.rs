@@ -0,0 +1,496 @@
+//! Container selectors
+//!
+//! Endpoints that work on a set of containers accept `?selector=` with a small
+//! boolean expression over container fields, parsed and evaluated server-side:
+//!
+//! ```text
+//! status=unhealthy and category in (aiml,val) and label team=core
+//! not (name=shop-* or network!=backend)
+//! label com.docker.compose.project
+//! ```
+//!
+//! Conditions compare `name`, `id`, `image`, `status`, `health`, `category`,
+//! `network` or `project` with `=`, `!=`, `in (...)` or `not in (...)`;
+//! `label key` checks a label is set and `label key=value` (or `!=`, `in`)
+//! compares its value. Values are bare words or double-quoted strings, and a
+//! trailing `*` matches a prefix. `network` and `project` hold when any of the
+//! container's networks or projects matches. `not` binds tighter than `and`,
+//! which binds tighter than `or`; keywords are case-insensitive.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::check::matches_pattern;
+use crate::models::{ContainerInfo, ContainerStatus, Flowchart, MembershipKind, ServiceCategory};
+
+/// Selectors longer than this are rejected
+pub const MAX_LENGTH: usize = 1024;
+/// Parentheses and `not`s nested deeper than this are rejected
+pub const MAX_DEPTH: usize = 16;
+
+const FIELDS: &[&str] = &["name", "id", "image", "status", "health", "category", "network", "project"];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SelectorQuery {
+    /// Expression picking containers, e.g. `status=unhealthy and label team=core`
+    pub selector: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SelectorError {
+    #[error("invalid selector at character {position}: {message}")]
+    Invalid { position: usize, message: String },
+    #[error("selector is longer than {MAX_LENGTH} characters")]
+    TooLong,
+    #[error("selector nests more than {MAX_DEPTH} levels deep")]
+    TooDeep,
+}
+
+impl IntoResponse for SelectorError {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// One comparison: holds when any candidate matches any value, or when none
+/// does if negated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub values: Vec<String>,
+    pub negated: bool,
+}
+
+impl Comparison {
+    fn holds<'a>(&self, mut candidates: impl Iterator<Item = &'a str>) -> bool {
+        candidates.any(|c| self.values.iter().any(|v| matches_pattern(v, c))) != self.negated
+    }
+}
+
+/// A parsed selector
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    And(Vec<Selector>),
+    Or(Vec<Selector>),
+    Not(Box<Selector>),
+    Field(String, Comparison),
+    /// A label that must be set, and optionally what its value must be
+    Label(String, Option<Comparison>),
+}
+
+impl Selector {
+    /// Parse a `selector` parameter; absent or blank selects every container
+    pub fn parse(selector: Option<&str>) -> Result<Option<Selector>, SelectorError> {
+        let Some(selector) = selector.filter(|s| !s.trim().is_empty()) else {
+            return Ok(None);
+        };
+        if selector.chars().count() > MAX_LENGTH {
+            return Err(SelectorError::TooLong);
+        }
+        let mut parser = Parser {
+            tokens: tokenize(selector)?,
+            next: 0,
+            end: selector.chars().count(),
+            depth: 0,
+        };
+        let parsed = parser.or()?;
+        match parser.peek() {
+            None => Ok(Some(parsed)),
+            Some((position, token)) => Err(invalid(*position, format!("unexpected {}", token))),
+        }
+    }
+
+    pub fn matches(&self, container: &ContainerInfo) -> bool {
+        match self {
+            Selector::And(all) => all.iter().all(|s| s.matches(container)),
+            Selector::Or(any) => any.iter().any(|s| s.matches(container)),
+            Selector::Not(inner) => !inner.matches(container),
+            Selector::Label(key, comparison) => match (container.labels.get(key), comparison) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(value), Some(comparison)) => comparison.holds(std::iter::once(value.as_str())),
+            },
+            Selector::Field(field, comparison) => match field.as_str() {
+                "name" => comparison.holds(std::iter::once(container.name.as_str())),
+                "id" => comparison.holds(std::iter::once(container.id.as_str())),
+                "image" => comparison.holds(std::iter::once(container.image.as_str())),
+                "status" => comparison.holds(std::iter::once(serialized(&container.status).as_str())),
+                "health" => comparison.holds(container.health.as_deref().into_iter()),
+                "category" => comparison.holds(std::iter::once(serialized(&container.category).as_str())),
+                "network" => comparison.holds(container.networks.iter().map(String::as_str)),
+                _ => comparison.holds(
+                    container
+                        .memberships
+                        .iter()
+                        .filter(|m| m.kind == MembershipKind::Project)
+                        .map(|m| m.name.as_str()),
+                ),
+            },
+        }
+    }
+}
+
+/// The selected containers; all of them without a selector
+pub fn select(selector: Option<&Selector>, containers: Vec<ContainerInfo>) -> Vec<ContainerInfo> {
+    match selector {
+        Some(selector) => containers.into_iter().filter(|c| selector.matches(c)).collect(),
+        None => containers,
+    }
+}
+
+/// Drop the nodes of containers the selector does not pick, and connections
+/// to them; nodes standing for categories or groups are kept
+pub fn retain_nodes(selector: &Selector, flowchart: &mut Flowchart, containers: &[ContainerInfo]) {
+    let dropped: Vec<&str> = containers
+        .iter()
+        .filter(|c| !selector.matches(c))
+        .map(|c| c.id.as_str())
+        .collect();
+    flowchart.nodes.retain(|n| !dropped.contains(&n.id.as_str()));
+    flowchart
+        .connections
+        .retain(|c| !dropped.contains(&c.source.as_str()) && !dropped.contains(&c.target.as_str()));
+}
+
+/// A value as it appears in JSON, e.g. `unhealthy` for a status
+fn serialized<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => String::new(),
+    }
+}
+
+fn invalid(position: usize, message: impl Into<String>) -> SelectorError {
+    SelectorError::Invalid {
+        position: position + 1,
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Eq,
+    Ne,
+    Open,
+    Close,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "'{}'", w),
+            Token::Quoted(q) => write!(f, "\"{}\"", q),
+            Token::Eq => f.write_str("'='"),
+            Token::Ne => f.write_str("'!='"),
+            Token::Open => f.write_str("'('"),
+            Token::Close => f.write_str("')'"),
+            Token::Comma => f.write_str("','"),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-.:/*@".contains(c)
+}
+
+/// Split a selector into tokens, each with the character position it starts at
+fn tokenize(selector: &str) -> Result<Vec<(usize, Token)>, SelectorError> {
+    let mut tokens = Vec::new();
+    let mut chars = selector.chars().enumerate().peekable();
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '=' => Token::Eq,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '!' => match chars.next() {
+                Some((_, '=')) => Token::Ne,
+                _ => return Err(invalid(position, "expected '=' after '!'")),
+            },
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err(invalid(position, "unterminated string")),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => return Err(invalid(position, "unterminated string")),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => return Err(invalid(position, format!("unexpected character '{}'", c))),
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Position reported for errors at the end of the input
+    end: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.next)
+    }
+
+    fn position(&self) -> usize {
+        self.peek().map_or(self.end, |(p, _)| *p)
+    }
+
+    /// Consume the next token if it is the (unquoted) keyword `keyword`
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some((_, Token::Word(w))) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), SelectorError> {
+        match self.peek() {
+            Some((_, token)) if *token == expected => {
+                self.next += 1;
+                Ok(())
+            }
+            _ => Err(invalid(self.position(), format!("expected {}", expected))),
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<(usize, String), SelectorError> {
+        match self.tokens.get(self.next).cloned() {
+            Some((position, Token::Word(w) | Token::Quoted(w))) => {
+                self.next += 1;
+                Ok((position, w))
+            }
+            _ => Err(invalid(self.position(), format!("expected {}", what))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Selector, SelectorError> {
+        let mut any = vec![self.and()?];
+        while self.keyword("or") {
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 { any.remove(0) } else { Selector::Or(any) })
+    }
+
+    fn and(&mut self) -> Result<Selector, SelectorError> {
+        let mut all = vec![self.unary()?];
+        while self.keyword("and") {
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 { all.remove(0) } else { Selector::And(all) })
+    }
+
+    fn unary(&mut self) -> Result<Selector, SelectorError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(SelectorError::TooDeep);
+        }
+        let parsed = if self.keyword("not") {
+            Selector::Not(Box::new(self.unary()?))
+        } else if matches!(self.peek(), Some((_, Token::Open))) {
+            self.next += 1;
+            let inner = self.or()?;
+            self.expect(Token::Close)?;
+            inner
+        } else {
+            self.condition()?
+        };
+        self.depth -= 1;
+        Ok(parsed)
+    }
+
+    fn condition(&mut self) -> Result<Selector, SelectorError> {
+        if self.keyword("label") {
+            let (_, key) = self.word("a label key")?;
+            let comparison = match self.peek() {
+                Some((_, Token::Eq | Token::Ne)) => Some(self.comparison(None)?),
+                Some((_, Token::Word(w))) if w.eq_ignore_ascii_case("in") || w.eq_ignore_ascii_case("not") => {
+                    Some(self.comparison(None)?)
+                }
+                _ => None,
+            };
+            return Ok(Selector::Label(key, comparison));
+        }
+
+        let (position, field) = self.word("a field or 'label'")?;
+        let field = field.to_ascii_lowercase();
+        if !FIELDS.contains(&field.as_str()) {
+            return Err(invalid(
+                position,
+                format!("unknown field '{}'; expected one of {} or label", field, FIELDS.join(", ")),
+            ));
+        }
+        let comparison = self.comparison(Some(&field))?;
+        Ok(Selector::Field(field, comparison))
+    }
+
+    /// `= value`, `!= value`, `in (values)` or `not in (values)`
+    fn comparison(&mut self, field: Option<&str>) -> Result<Comparison, SelectorError> {
+        let (negated, list) = match self.peek() {
+            Some((_, Token::Eq)) => (false, false),
+            Some((_, Token::Ne)) => (true, false),
+            Some((_, Token::Word(w))) if w.eq_ignore_ascii_case("in") => (false, true),
+            Some((_, Token::Word(w))) if w.eq_ignore_ascii_case("not") => {
+                self.next += 1;
+                if !matches!(self.peek(), Some((_, Token::Word(w))) if w.eq_ignore_ascii_case("in")) {
+                    return Err(invalid(self.position(), "expected 'in' after 'not'"));
+                }
+                (true, true)
+            }
+            _ => return Err(invalid(self.position(), "expected '=', '!=', 'in' or 'not in'")),
+        };
+        self.next += 1;
+
+        let mut values = Vec::new();
+        if list {
+            self.expect(Token::Open)?;
+            loop {
+                values.push(self.value(field)?);
+                if matches!(self.peek(), Some((_, Token::Comma))) {
+                    self.next += 1;
+                } else {
+                    break;
+                }
+            }
+            self.expect(Token::Close)?;
+        } else {
+            values.push(self.value(field)?);
+        }
+        Ok(Comparison { values, negated })
+    }
+
+    /// A value, checked against what `status` and `category` can be
+    fn value(&mut self, field: Option<&str>) -> Result<String, SelectorError> {
+        let (position, value) = self.word("a value")?;
+        let known = |parses: fn(&str) -> bool| value.ends_with('*') || parses(&value);
+        let valid = match field {
+            Some("status") => known(parses_as::<ContainerStatus>),
+            Some("category") => known(parses_as::<ServiceCategory>),
+            _ => true,
+        };
+        if !valid {
+            return Err(invalid(
+                position,
+                format!("'{}' is not a {}", value, field.unwrap_or_default()),
+            ));
+        }
+        Ok(value)
+    }
+}
+
+fn parses_as<T: DeserializeOwned>(value: &str) -> bool {
+    serde_json::from_value::<T>(serde_json::Value::String(value.to_string())).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+    use crate::discovery::DockerDiscovery;
+
+    fn parse(selector: &str) -> Selector {
+        Selector::parse(Some(selector)).unwrap().unwrap()
+    }
+
+    fn selected(selector: &str, containers: &[ContainerInfo]) -> Vec<String> {
+        let selector = parse(selector);
+        containers.iter().filter(|c| selector.matches(c)).map(|c| c.name.clone()).collect()
+    }
+
+    #[test]
+    fn precedence_and_lists() {
+        let term = |field: &str, values: &[&str], negated| {
+            Selector::Field(
+                field.to_string(),
+                Comparison {
+                    values: values.iter().map(|v| v.to_string()).collect(),
+                    negated,
+                },
+            )
+        };
+        assert_eq!(
+            parse("status=unhealthy and category in (aiml, val) or NOT name not in (\"a b\")"),
+            Selector::Or(vec![
+                Selector::And(vec![term("status", &["unhealthy"], false), term("category", &["aiml", "val"], false)]),
+                Selector::Not(Box::new(term("name", &["a b"], true))),
+            ])
+        );
+        assert_eq!(
+            parse("label team=core and label com.docker.compose.project"),
+            Selector::And(vec![
+                Selector::Label(
+                    "team".to_string(),
+                    Some(Comparison {
+                        values: vec!["core".to_string()],
+                        negated: false
+                    })
+                ),
+                Selector::Label("com.docker.compose.project".to_string(), None),
+            ])
+        );
+        assert_eq!(Selector::parse(Some("  ")), Ok(None));
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        let error = |selector: &str| Selector::parse(Some(selector)).unwrap_err().to_string();
+        assert_eq!(
+            error("status=unheathy"),
+            "invalid selector at character 8: 'unheathy' is not a status"
+        );
+        assert!(error("colour=red").starts_with("invalid selector at character 1: unknown field 'colour'"));
+        assert_eq!(error("name=a and"), "invalid selector at character 11: expected a field or 'label'");
+        assert_eq!(error("(name=a"), "invalid selector at character 8: expected ')'");
+        assert_eq!(error("name=a)"), "invalid selector at character 7: unexpected ')'");
+        assert_eq!(error("name ! a"), "invalid selector at character 6: expected '=' after '!'");
+        assert_eq!(error(&"not ".repeat(MAX_DEPTH + 1)), SelectorError::TooDeep.to_string());
+        assert_eq!(error(&"a".repeat(MAX_LENGTH + 1)), SelectorError::TooLong.to_string());
+    }
+
+    #[tokio::test]
+    async fn matches_listed_containers() {
+        let containers = DockerDiscovery::new(FakeDocker::load("stack")).list_containers().await.unwrap();
+        let all: Vec<String> = containers.iter().map(|c| c.name.clone()).collect();
+        assert_eq!(selected("name=*", &containers), all);
+        assert_eq!(selected("status=healthy", &containers), [containers[3].name.clone()]);
+        assert_eq!(
+            selected("not status=healthy", &containers).len() + selected("status = healthy", &containers).len(),
+            all.len()
+        );
+        assert_eq!(
+            selected("project=shop", &containers),
+            selected("label com.docker.compose.project = shop", &containers)
+        );
+        assert!(selected("network=no-such-network", &containers).is_empty());
+        assert_eq!(selected("network!=no-such-network", &containers), all);
+    }
+}
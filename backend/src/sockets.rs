@@ -0,0 +1,370 @@
+//! Listening sockets and live connections
+//!
+//! Declared ports (`EXPOSE`, published ports) are often incomplete: images
+//! listen on ports they never expose, and services talking over a shared
+//! network need no published port at all. `GET /api/container/:id/sockets`
+//! reads the container's TCP table instead, from `ss -tan` run inside it or,
+//! for images without `ss`, from `<proc>/<pid>/net/tcp{,6}` of one of its
+//! processes under the host's `/proc` (see [`crate::procfs`]).
+//!
+//! Established connections also link containers for edge inference: a
+//! container connected to another's address on a port that one listens on
+//! depends on it. Edge inference only reads `/proc`, since running `ss` in
+//! every container on every refresh would flood the event feed with execs.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+};
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use tracing::error;
+
+use crate::{
+    diagnostics::EnvProfile,
+    models::{ContainerSockets, ContainerStatus, ListeningPort, SocketPeer, SocketSource},
+    procfs, AppState,
+};
+
+/// Run inside a container to list its TCP sockets
+pub const SS_COMMAND: &[&str] = &["ss", "-tan"];
+/// Docker's embedded DNS listens on random ports in every container on a
+/// user-defined network
+const EMBEDDED_DNS: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 11));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketState {
+    Listen,
+    Established,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Socket {
+    pub state: SocketState,
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+}
+
+/// `*:80`, `0.0.0.0%lo:53`, `[::ffff:10.0.0.2]:5432`, `*:*`
+fn parse_ss_addr(addr: &str) -> Option<SocketAddr> {
+    let (host, port) = addr.rsplit_once(':')?;
+    let port = if port == "*" { 0 } else { port.parse().ok()? };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.split('%').next()?;
+    let ip: IpAddr = if host == "*" {
+        Ipv4Addr::UNSPECIFIED.into()
+    } else {
+        host.parse().ok()?
+    };
+    Some(SocketAddr::new(ip.to_canonical(), port))
+}
+
+/// Sockets in `ss -tan` output; `None` when it is not `ss` output at all
+pub fn parse_ss(output: &str) -> Option<Vec<Socket>> {
+    let mut lines = output.lines().filter(|l| !l.trim().is_empty()).peekable();
+    if !lines.peek()?.trim_start().starts_with("State") {
+        return None;
+    }
+    let sockets = lines
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let state = match *fields.first()? {
+                "LISTEN" => SocketState::Listen,
+                "ESTAB" => SocketState::Established,
+                _ => SocketState::Other,
+            };
+            Some(Socket {
+                state,
+                local: parse_ss_addr(fields.get(3)?)?,
+                remote: parse_ss_addr(fields.get(4)?)?,
+            })
+        })
+        .collect();
+    Some(sockets)
+}
+
+/// `0100007F:1F90`: the address as native-endian words, the port big-endian
+fn parse_proc_addr(addr: &str) -> Option<SocketAddr> {
+    let (ip, port) = addr.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let word = |i: usize| u32::from_str_radix(ip.get(i * 8..i * 8 + 8)?, 16).ok().map(u32::to_ne_bytes);
+    let ip: IpAddr = match ip.len() {
+        8 => Ipv4Addr::from(word(0)?).into(),
+        32 => {
+            let mut octets = [0u8; 16];
+            for i in 0..4 {
+                octets[i * 4..i * 4 + 4].copy_from_slice(&word(i)?);
+            }
+            Ipv6Addr::from(octets).into()
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip.to_canonical(), port))
+}
+
+/// Sockets in a `/proc/<pid>/net/tcp` or `tcp6` table
+pub fn parse_proc_net(table: &str) -> Vec<Socket> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let state = match *fields.get(3)? {
+                "0A" => SocketState::Listen,
+                "01" => SocketState::Established,
+                _ => SocketState::Other,
+            };
+            Some(Socket {
+                state,
+                local: parse_proc_addr(fields.get(1)?)?,
+                remote: parse_proc_addr(fields.get(2)?)?,
+            })
+        })
+        .collect()
+}
+
+/// TCP sockets of the network namespace of the first process in `pids` that
+/// belongs to `container_id`
+pub fn read_procfs(root: &Path, container_id: &str, pids: &[u64]) -> Option<Vec<Socket>> {
+    let dir = pids
+        .iter()
+        .map(|pid| root.join(pid.to_string()))
+        .find(|dir| procfs::in_container(dir, container_id))?;
+    let mut sockets = parse_proc_net(&std::fs::read_to_string(dir.join("net/tcp")).ok()?);
+    if let Ok(table) = std::fs::read_to_string(dir.join("net/tcp6")) {
+        sockets.extend(parse_proc_net(&table));
+    }
+    Some(sockets)
+}
+
+/// Ports listened on, lowest first, ignoring the embedded DNS resolver; a
+/// port bound on several addresses is reported once
+fn listening(sockets: &[Socket]) -> Vec<SocketAddr> {
+    let mut ports: Vec<SocketAddr> = Vec::new();
+    for socket in sockets.iter().filter(|s| s.state == SocketState::Listen && s.local.ip() != EMBEDDED_DNS) {
+        if !ports.iter().any(|p| p.port() == socket.local.port()) {
+            ports.push(socket.local);
+        }
+    }
+    ports.sort_by_key(|p| p.port());
+    ports
+}
+
+/// Remote ends of connections the container opened, as opposed to ones it
+/// accepted on a listening port
+fn outbound(sockets: &[Socket]) -> Vec<SocketAddr> {
+    let listening: HashSet<u16> = listening(sockets).iter().map(|p| p.port()).collect();
+    let mut remotes: Vec<SocketAddr> = Vec::new();
+    for socket in sockets {
+        let opened = socket.state == SocketState::Established && !listening.contains(&socket.local.port());
+        if opened && !remotes.contains(&socket.remote) {
+            remotes.push(socket.remote);
+        }
+    }
+    remotes
+}
+
+/// Profiles by the container addresses they own
+pub fn owners(profiles: &[EnvProfile]) -> HashMap<IpAddr, &EnvProfile> {
+    profiles
+        .iter()
+        .flat_map(|p| p.addresses.iter().map(move |ip| (*ip, p)))
+        .collect()
+}
+
+/// Summarise a container's sockets; `declared` holds its exposed and
+/// published ports
+pub fn report(
+    container: &str,
+    source: SocketSource,
+    sockets: &[Socket],
+    declared: &[u16],
+    owners: &HashMap<IpAddr, &EnvProfile>,
+) -> ContainerSockets {
+    ContainerSockets {
+        container: container.to_string(),
+        source,
+        listening: listening(sockets)
+            .into_iter()
+            .map(|addr| ListeningPort {
+                port: addr.port(),
+                address: addr.ip().to_string(),
+                declared: declared.contains(&addr.port()),
+            })
+            .collect(),
+        connections: outbound(sockets)
+            .into_iter()
+            .map(|addr| SocketPeer {
+                address: addr.ip().to_string(),
+                port: addr.port(),
+                container: owners.get(&addr.ip()).map(|p| p.container_name.clone()),
+            })
+            .collect(),
+    }
+}
+
+/// `(client id, server id, port)` for every connection between containers in
+/// `observed`, keyed by container id. A server whose sockets were read must
+/// be listening on the port; one whose were not is taken at its word.
+pub fn socket_edges(
+    observed: &[(String, Vec<Socket>)],
+    owners: &HashMap<IpAddr, &EnvProfile>,
+) -> Vec<(String, String, u16)> {
+    let listens: HashMap<&str, Vec<u16>> = observed
+        .iter()
+        .map(|(id, sockets)| (id.as_str(), listening(sockets).iter().map(|p| p.port()).collect()))
+        .collect();
+    let mut edges: Vec<(String, String, u16)> = Vec::new();
+    for (client, sockets) in observed {
+        for remote in outbound(sockets) {
+            let Some(server) = owners.get(&remote.ip()).map(|p| &p.container_id) else {
+                continue;
+            };
+            let serves = listens.get(server.as_str()).is_none_or(|ports| ports.contains(&remote.port()));
+            let edge = (client.clone(), server.clone(), remote.port());
+            if server != client && serves && !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    edges
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SocketError {
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("container '{0}' is not running")]
+    NotRunning(String),
+    #[error("cannot read the sockets of '{0}': it has no `ss` and the host's /proc is not available")]
+    Unreadable(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl SocketError {
+    fn status(&self) -> StatusCode {
+        match self {
+            SocketError::ContainerNotFound(_) => StatusCode::NOT_FOUND,
+            SocketError::NotRunning(_) => StatusCode::CONFLICT,
+            SocketError::Unreadable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            SocketError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for SocketError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Socket probe error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// GET /api/container/:id/sockets - Ports a container listens on and the
+/// connections it holds open
+pub async fn get_container_sockets(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Json<ContainerSockets>, SocketError> {
+    let container = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or_else(|| SocketError::ContainerNotFound(id.clone()))?;
+    if !matches!(
+        container.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    ) {
+        return Err(SocketError::NotRunning(container.name));
+    }
+    state
+        .docker
+        .container_sockets(&container)
+        .await?
+        .map(Json)
+        .ok_or(SocketError::Unreadable(container.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SS: &str = "\
+State  Recv-Q Send-Q     Local Address:Port      Peer Address:Port Process
+LISTEN 0      4096          127.0.0.11:40531          0.0.0.0:*
+LISTEN 0      511              0.0.0.0:8080           0.0.0.0:*
+LISTEN 0      511                 [::]:8080              [::]:*
+LISTEN 0      128                    *:9100                 *:*
+ESTAB  0      0             172.18.0.3:8080        172.18.0.9:41122
+ESTAB  0      0             172.18.0.3:52214       172.18.0.4:5432
+ESTAB  0      0     [::ffff:172.18.0.3]:52216 [::ffff:172.18.0.4]:5432
+";
+
+    const PROC_TCP: &str = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1 1 0 100 0 0 10 0
+   1: 030012AC:CBF6 040012AC:1538 01 00000000:00000000 00:00000000 00000000     0        0 2 1 0 20 4 30 10 -1
+";
+
+    fn profile(id: &str, ip: &str) -> EnvProfile {
+        EnvProfile {
+            container_id: id.to_string(),
+            container_name: format!("{}-name", id),
+            env: Vec::new(),
+            exposed_ports: Vec::new(),
+            mount_destinations: Vec::new(),
+            addresses: vec![ip.parse().unwrap()],
+        }
+    }
+
+    #[test]
+    fn reads_ss_and_proc_tables_alike() {
+        let from_ss = parse_ss(SS).unwrap();
+        let ports: Vec<u16> = listening(&from_ss).iter().map(|p| p.port()).collect();
+        assert_eq!(ports, [8080, 9100]);
+        assert_eq!(outbound(&from_ss), ["172.18.0.4:5432".parse::<SocketAddr>().unwrap()]);
+
+        let from_proc = parse_proc_net(PROC_TCP);
+        if cfg!(target_endian = "little") {
+            assert_eq!(from_proc[0].local, "0.0.0.0:8080".parse().unwrap());
+            assert_eq!(from_proc[1].remote, "172.18.0.4:5432".parse().unwrap());
+        }
+
+        assert_eq!(parse_ss("sh: ss: not found"), None);
+    }
+
+    #[test]
+    fn connections_link_clients_to_servers() {
+        let profiles = [profile("api", "172.18.0.3"), profile("db", "172.18.0.4"), profile("web", "172.18.0.9")];
+        let owners = owners(&profiles);
+        let db = vec![Socket {
+            state: SocketState::Listen,
+            local: "0.0.0.0:5432".parse().unwrap(),
+            remote: "0.0.0.0:0".parse().unwrap(),
+        }];
+        let observed = vec![("api".to_string(), parse_ss(SS).unwrap()), ("db".to_string(), db)];
+
+        // api accepted web's connection, so only api -> db is api's doing;
+        // web's sockets were not read, so web -> api cannot be seen
+        assert_eq!(socket_edges(&observed, &owners), [("api".to_string(), "db".to_string(), 5432)]);
+
+        let report = report("api-name", SocketSource::Exec, &observed[0].1, &[8080], &owners);
+        let declared: Vec<(u16, bool)> = report.listening.iter().map(|p| (p.port, p.declared)).collect();
+        assert_eq!(declared, [(8080, true), (9100, false)]);
+        assert_eq!(report.connections[0].container.as_deref(), Some("db-name"));
+    }
+}
@@ -0,0 +1,120 @@
+//! Live updates over Server-Sent Events
+//!
+//! `GET /api/events/stream` carries the same sequenced `topologyUpdate` and
+//! `containerUpdate` payloads as `/ws`, for dashboards behind proxies that
+//! refuse WebSocket upgrades. Each event's name is the message type, its data
+//! the [`WsEnvelope`] JSON and its id the sequence number, so a browser
+//! `EventSource` reconnecting with `Last-Event-ID` gets the messages it missed
+//! from the hub's replay buffers, or a fresh snapshot when they are gone.
+//! Unlike `/ws` there is no per-client interval: every update is sent as soon
+//! as it is published. Responses carry `X-Accel-Buffering: no` so nginx-style
+//! proxies pass events through instead of buffering them.
+
+use std::{collections::VecDeque, convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+};
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::{
+    websocket::{WsEnvelope, WsHub, WsMessage},
+    AppState,
+};
+
+/// Hub messages after `last_seq`, or a snapshot, followed by everything
+/// published from now on
+fn updates(hub: Arc<WsHub>, last_seq: Option<u64>) -> impl Stream<Item = WsEnvelope> {
+    let (backlog, receiver) = hub.subscribe(last_seq);
+    stream::unfold(
+        (VecDeque::from(backlog), receiver),
+        move |(mut pending, mut receiver)| {
+            let hub = hub.clone();
+            async move {
+                loop {
+                    if let Some(envelope) = pending.pop_front() {
+                        return Some((envelope, (pending, receiver)));
+                    }
+                    match receiver.recv().await {
+                        Ok(envelope) => pending.push_back(envelope),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("SSE client lagged by {} messages, sending snapshot", skipped);
+                            pending.extend(hub.snapshot());
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    )
+}
+
+fn event(envelope: &WsEnvelope) -> Event {
+    let name = match envelope.message {
+        WsMessage::TopologyUpdate { .. } => "topologyUpdate",
+        WsMessage::ContainerUpdate { .. } => "containerUpdate",
+        _ => "message",
+    };
+    Event::default()
+        .id(envelope.seq.to_string())
+        .event(name)
+        .data(serde_json::to_string(envelope).unwrap())
+}
+
+/// GET /api/events/stream - Topology and container updates as Server-Sent Events
+pub async fn stream_updates(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let last_seq = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    debug!("New SSE connection (last event: {:?})", last_seq);
+    let events = updates(state.hub.clone(), last_seq).map(|envelope| Ok::<_, Infallible>(event(&envelope)));
+    (
+        [("x-accel-buffering", "no")],
+        Sse::new(events).keep_alive(KeepAlive::default()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::websocket::WsTopic;
+
+    fn topology(total_containers: usize) -> WsMessage {
+        WsMessage::TopologyUpdate {
+            total_containers,
+            running_containers: 0,
+            healthy_containers: 0,
+            unhealthy_containers: 0,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_last_event_id() {
+        let hub = Arc::new(WsHub::new());
+        hub.publish(WsTopic::Topology, topology(1));
+        hub.publish(WsTopic::Topology, topology(2));
+
+        // Topology is a snapshot topic, so only its latest message is replayed
+        let mut resumed = Box::pin(updates(hub.clone(), Some(1)));
+        let envelope = resumed.next().await.unwrap();
+        assert_eq!(envelope.seq, 2);
+        assert!(matches!(envelope.message, WsMessage::TopologyUpdate { total_containers: 2, .. }));
+
+        // An id from before a restart gets the snapshot
+        let mut fresh = Box::pin(updates(hub.clone(), Some(99)));
+        assert_eq!(fresh.next().await.unwrap().seq, 2);
+
+        hub.publish(WsTopic::Topology, topology(3));
+        assert_eq!(resumed.next().await.unwrap().seq, 3);
+        assert_eq!(fresh.next().await.unwrap().seq, 3);
+    }
+}
@@ -0,0 +1,119 @@
+//! File-backed storage for user-authored configuration
+//!
+//! Each store is a JSON file holding a map of named records. The whole map is
+//! kept in memory and rewritten atomically on every change, which is plenty for
+//! the handful of templates, groups and views a FlowScope instance holds.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+use tracing::{info, warn};
+
+/// Directory holding all persisted state (`FLOWSCOPE_DATA_DIR`, default `./data`)
+pub fn data_dir() -> PathBuf {
+    std::env::var("FLOWSCOPE_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data"))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("failed to write {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to serialize store: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// A named collection of records persisted to a single JSON file
+pub struct JsonStore<T> {
+    path: PathBuf,
+    records: RwLock<BTreeMap<String, T>>,
+}
+
+impl<T> JsonStore<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Open `<data_dir>/<file_name>`, starting empty if it does not exist or cannot be parsed
+    pub fn open(file_name: &str) -> Self {
+        Self::open_at(data_dir().join(file_name))
+    }
+
+    pub fn open_at(path: PathBuf) -> Self {
+        let records = match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(records) => records,
+                Err(e) => {
+                    warn!("Ignoring unreadable store {}: {}", path.display(), e);
+                    BTreeMap::new()
+                }
+            },
+            Err(_) => BTreeMap::new(),
+        };
+
+        let store = Self {
+            path,
+            records: RwLock::new(records),
+        };
+        info!("Loaded {} records from {}", store.len(), store.path.display());
+        store
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.read().unwrap().len()
+    }
+
+    pub fn list(&self) -> Vec<T> {
+        self.records.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.records.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<T> {
+        self.records.read().unwrap().get(key).cloned()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.records.read().unwrap().contains_key(key)
+    }
+
+    /// Insert or replace a record, returning the previous value
+    pub fn put(&self, key: &str, value: T) -> Result<Option<T>, StoreError> {
+        let mut records = self.records.write().unwrap();
+        let previous = records.insert(key.to_string(), value);
+        Self::persist(&self.path, &records)?;
+        Ok(previous)
+    }
+
+    pub fn remove(&self, key: &str) -> Result<Option<T>, StoreError> {
+        let mut records = self.records.write().unwrap();
+        let removed = records.remove(key);
+        if removed.is_some() {
+            Self::persist(&self.path, &records)?;
+        }
+        Ok(removed)
+    }
+
+    fn persist(path: &Path, records: &BTreeMap<String, T>) -> Result<(), StoreError> {
+        let io_err = |source| StoreError::Io {
+            path: path.display().to_string(),
+            source,
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(records)?).map_err(io_err)?;
+        std::fs::rename(&tmp, path).map_err(io_err)
+    }
+}
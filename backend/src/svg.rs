@@ -0,0 +1,314 @@
+//! Server-side SVG rendering of flowcharts
+//!
+//! `GET /api/flowchart/:id.svg` returns a standalone image for wikis and
+//! Grafana text panels, where the SPA cannot run. Nodes are laid out in
+//! layers: each node sits one row below the deepest node connecting to it,
+//! ignoring the edge that closes each cycle. Built with the `svg` feature,
+//! which is on by default.
+
+use std::fmt::Write;
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, info};
+
+use crate::{
+    dot::status_style,
+    models::{ConnectionType, Flowchart},
+    provider::DiscoveryProvider,
+    AppState,
+};
+
+const NODE_WIDTH: usize = 180;
+const NODE_HEIGHT: usize = 48;
+const GAP_X: usize = 40;
+const GAP_Y: usize = 72;
+const MARGIN: usize = 24;
+const TITLE_HEIGHT: usize = 32;
+
+/// Mark the edges from `node` that lead back onto the current path
+fn find_back_edges(node: usize, edges: &[(usize, usize)], state: &mut [u8], back: &mut Vec<usize>) {
+    state[node] = 1;
+    for (i, &(source, target)) in edges.iter().enumerate() {
+        if source != node {
+            continue;
+        }
+        match state[target] {
+            0 => find_back_edges(target, edges, state, back),
+            1 => back.push(i),
+            _ => {}
+        }
+    }
+    state[node] = 2;
+}
+
+/// Node indices per row, top to bottom, each row in flowchart order
+pub fn layers(flowchart: &Flowchart) -> Vec<Vec<usize>> {
+    let count = flowchart.nodes.len();
+    let index = |id: &str| flowchart.nodes.iter().position(|n| n.id == id);
+    let mut edges: Vec<(usize, usize)> = flowchart
+        .connections
+        .iter()
+        .filter_map(|c| Some((index(&c.source)?, index(&c.target)?)))
+        .filter(|(source, target)| source != target)
+        .collect();
+
+    // Without the edges closing a cycle, found depth-first in node order, the
+    // rest form a DAG whose longest paths give the rows
+    let (mut state, mut back) = (vec![0; count], Vec::new());
+    for node in 0..count {
+        if state[node] == 0 {
+            find_back_edges(node, &edges, &mut state, &mut back);
+        }
+    }
+    let mut i = 0;
+    edges.retain(|_| {
+        i += 1;
+        !back.contains(&(i - 1))
+    });
+
+    let mut depth = vec![0; count];
+    for _ in 0..count {
+        let mut changed = false;
+        for &(source, target) in &edges {
+            if depth[target] < depth[source] + 1 {
+                depth[target] = depth[source] + 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let rows = depth.iter().max().map_or(0, |d| d + 1);
+    let mut layers = vec![Vec::new(); rows];
+    for (node, row) in depth.into_iter().enumerate() {
+        layers[row].push(node);
+    }
+    layers
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Dash pattern per connection type; solid when `None`
+fn dash(connection_type: &ConnectionType) -> Option<&'static str> {
+    match connection_type {
+        ConnectionType::Secondary | ConnectionType::Volume | ConnectionType::Depends => Some("6 4"),
+        ConnectionType::Network => Some("2 3"),
+        _ => None,
+    }
+}
+
+/// Render a flowchart as a standalone SVG document
+pub fn render(flowchart: &Flowchart) -> String {
+    let layers = layers(flowchart);
+    let widest = layers.iter().map(Vec::len).max().unwrap_or(0);
+    let width = (MARGIN * 2 + widest * (NODE_WIDTH + GAP_X)).saturating_sub(GAP_X).max(NODE_WIDTH + MARGIN * 2);
+    let height = MARGIN * 2 + TITLE_HEIGHT + (layers.len() * (NODE_HEIGHT + GAP_Y)).saturating_sub(GAP_Y);
+
+    // Top-left corner of every node; rows are centred on the widest
+    let mut corners = vec![(0, 0); flowchart.nodes.len()];
+    for (row, nodes) in layers.iter().enumerate() {
+        let row_width = (nodes.len() * (NODE_WIDTH + GAP_X)).saturating_sub(GAP_X);
+        let left = (width - row_width) / 2;
+        for (col, &node) in nodes.iter().enumerate() {
+            corners[node] = (
+                left + col * (NODE_WIDTH + GAP_X),
+                MARGIN + TITLE_HEIGHT + row * (NODE_HEIGHT + GAP_Y),
+            );
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="system-ui, sans-serif">"#,
+        w = width,
+        h = height
+    )
+    .unwrap();
+    writeln!(out, "<title>{}</title>", escape(&flowchart.name)).unwrap();
+    writeln!(
+        out,
+        r##"<defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z" fill="#64748b"/></marker></defs>"##
+    )
+    .unwrap();
+    writeln!(out, r##"<rect width="100%" height="100%" fill="#ffffff"/>"##).unwrap();
+    writeln!(
+        out,
+        r##"<text x="{}" y="{}" font-size="16" font-weight="600" fill="#0f172a">{}</text>"##,
+        MARGIN,
+        MARGIN + 16,
+        escape(&flowchart.name)
+    )
+    .unwrap();
+
+    let index = |id: &str| flowchart.nodes.iter().position(|n| n.id == id);
+    for connection in &flowchart.connections {
+        let (Some(source), Some(target)) = (index(&connection.source), index(&connection.target)) else {
+            continue;
+        };
+        let ((sx, sy), (tx, ty)) = (corners[source], corners[target]);
+        let (x1, y1) = (sx + NODE_WIDTH / 2, sy + NODE_HEIGHT);
+        // Edges within or back up the layers leave from the side instead
+        let (x2, y2) = if ty > sy { (tx + NODE_WIDTH / 2, ty) } else { (tx, ty + NODE_HEIGHT / 2) };
+        let dash = dash(&connection.connection_type)
+            .map(|d| format!(r#" stroke-dasharray="{}""#, d))
+            .unwrap_or_default();
+        let stroke = if connection.connection_type == ConnectionType::Data { 2.5 } else { 1.5 };
+        writeln!(
+            out,
+            r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#64748b" stroke-width="{}"{} marker-end="url(#arrow)"/>"##,
+            x1, y1, x2, y2, stroke, dash
+        )
+        .unwrap();
+        if let Some(label) = &connection.label {
+            writeln!(
+                out,
+                r##"<text x="{}" y="{}" font-size="11" fill="#475569" text-anchor="middle">{}</text>"##,
+                (x1 + x2) / 2,
+                (y1 + y2) / 2,
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+
+    for (node, &(x, y)) in flowchart.nodes.iter().zip(&corners) {
+        let (fill, border, style) = status_style(&node.status);
+        let dash = if style.contains("dashed") { r#" stroke-dasharray="4 3""# } else { "" };
+        let status = format!("{:?}", node.status).to_lowercase();
+        let detail = match node.port {
+            Some(port) => format!("{} · :{}", status, port),
+            None => status,
+        };
+        writeln!(
+            out,
+            r##"<g><title>{desc}</title><rect x="{x}" y="{y}" width="{w}" height="{h}" rx="8" fill="{fill}" stroke="{border}" stroke-width="1.5"{dash}/><text x="{cx}" y="{t1}" font-size="13" font-weight="600" fill="#0f172a" text-anchor="middle">{name}</text><text x="{cx}" y="{t2}" font-size="11" fill="#475569" text-anchor="middle">{detail}</text></g>"##,
+            desc = escape(&node.description),
+            x = x,
+            y = y,
+            w = NODE_WIDTH,
+            h = NODE_HEIGHT,
+            fill = fill,
+            border = border,
+            dash = dash,
+            cx = x + NODE_WIDTH / 2,
+            t1 = y + 20,
+            t2 = y + 37,
+            name = escape(&node.name),
+            detail = escape(&detail),
+        )
+        .unwrap();
+    }
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+/// The response to `GET /api/flowchart/:id.svg`
+pub async fn respond(state: &AppState, id: &str) -> Response {
+    match state.provider.generate_flowchart(id).await {
+        Ok(Some(flowchart)) => {
+            info!("Rendered flowchart '{}' as SVG", flowchart.name);
+            (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "image/svg+xml"),
+                    (header::CACHE_CONTROL, "no-cache"),
+                ],
+                render(&flowchart),
+            )
+                .into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Flowchart not found",
+                "id": id
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to render flowchart '{}': {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to generate flowchart",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ContainerStatus, FlowchartConnection, FlowchartNode, NodeType, ServiceCategory};
+
+    fn flowchart(ids: &[&str], links: &[(&str, &str)]) -> Flowchart {
+        Flowchart {
+            id: "test".to_string(),
+            name: "A & B".to_string(),
+            description: String::new(),
+            nodes: ids
+                .iter()
+                .map(|id| FlowchartNode {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    description: String::new(),
+                    status: ContainerStatus::Running,
+                    node_type: NodeType::Service,
+                    category: ServiceCategory::Other,
+                    port: None,
+                    child_flowchart: None,
+                    metrics: None,
+                    stats: None,
+                    warnings: Vec::new(),
+                    probe: None,
+                    vulnerabilities: None,
+                    position: None,
+                })
+                .collect(),
+            connections: links
+                .iter()
+                .map(|(source, target)| FlowchartConnection {
+                    id: format!("{}-{}", source, target),
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    label: None,
+                    connection_type: ConnectionType::Primary,
+                })
+                .collect(),
+            parent_id: None,
+        }
+    }
+
+    #[test]
+    fn nodes_sit_below_everything_pointing_at_them() {
+        let chart = flowchart(
+            &["web", "api", "db", "cache", "worker"],
+            &[("web", "api"), ("api", "db"), ("web", "cache"), ("worker", "db"), ("db", "api")],
+        );
+        // db -> api closes a cycle, so db stays below api
+        assert_eq!(layers(&chart), [vec![0, 4], vec![1, 3], vec![2]]);
+
+        let chain = flowchart(&["a", "b", "c", "d"], &[("a", "b"), ("b", "c"), ("a", "d")]);
+        assert_eq!(super::layers(&chain), [vec![0], vec![1, 3], vec![2]]);
+
+        let svg = render(&chain);
+        assert!(svg.starts_with("<svg ") && svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("<title>A &amp; B</title>"));
+        assert_eq!(svg.matches("<line ").count(), 3);
+    }
+}
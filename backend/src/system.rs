@@ -0,0 +1,93 @@
+//! Daemon environment detection
+//!
+//! Rootless daemons and Linux security modules change what FlowScope can do:
+//! a rootless daemon cannot publish privileged ports, or enforce limits at all
+//! on cgroup v1, and SELinux blocks bind mounts that were not relabelled. This
+//! reads them from `docker info` so pre-flight can warn about them and clients
+//! can hide what will not work.
+
+use bollard::models::{SystemInfo as DockerInfo, SystemInfoCgroupDriverEnum, SystemInfoCgroupVersionEnum};
+
+use crate::models::{FeatureFlags, SecurityProfile, SystemInfo};
+
+/// Parse `SecurityOptions` entries such as `name=seccomp,profile=builtin`
+pub fn security_profile(options: &[String]) -> SecurityProfile {
+    let mut profile = SecurityProfile::default();
+    for option in options {
+        let mut name = None;
+        let mut seccomp_profile = None;
+        for (key, value) in option.split(',').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "name" => name = Some(value),
+                "profile" => seccomp_profile = Some(value),
+                _ => {}
+            }
+        }
+        match name {
+            Some("rootless") => profile.rootless = true,
+            Some("selinux") => profile.selinux = true,
+            Some("apparmor") => profile.apparmor = true,
+            Some("userns") => profile.userns = true,
+            Some("seccomp") => profile.seccomp = Some(seccomp_profile.unwrap_or("default").to_string()),
+            _ => {}
+        }
+    }
+    profile
+}
+
+/// Features available given the daemon's isolation and cgroup setup
+pub fn feature_flags(security: &SecurityProfile, cgroup_v1: bool, cgroup_driver_none: bool) -> FeatureFlags {
+    FeatureFlags {
+        resource_limits: !(cgroup_driver_none || security.rootless && cgroup_v1),
+        privileged_ports: !security.rootless,
+        unlabelled_bind_mounts: !security.selinux,
+    }
+}
+
+/// Summarize `docker info` into what FlowScope reports
+pub fn system_info(info: &DockerInfo) -> SystemInfo {
+    let security = security_profile(info.security_options.as_deref().unwrap_or_default());
+    let cgroup_v1 = info.cgroup_version == Some(SystemInfoCgroupVersionEnum::_1);
+    let cgroup_driver_none = info.cgroup_driver == Some(SystemInfoCgroupDriverEnum::NONE);
+    let features = feature_flags(&security, cgroup_v1, cgroup_driver_none);
+    let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+
+    SystemInfo {
+        server_version: info.server_version.clone(),
+        operating_system: info.operating_system.clone(),
+        os_type: info.os_type.clone(),
+        kernel_version: info.kernel_version.clone(),
+        architecture: info.architecture.clone(),
+        cgroup_version: non_empty(info.cgroup_version.map(|v| v.to_string())),
+        cgroup_driver: non_empty(info.cgroup_driver.map(|d| d.to_string())),
+        security,
+        features,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rootless_selinux_on_cgroup_v1() {
+        let options: Vec<String> = ["name=seccomp,profile=builtin", "name=rootless", "name=selinux"]
+            .map(String::from)
+            .to_vec();
+        let security = security_profile(&options);
+        assert_eq!(
+            security,
+            SecurityProfile {
+                rootless: true,
+                selinux: true,
+                apparmor: false,
+                seccomp: Some("builtin".to_string()),
+                userns: false,
+            }
+        );
+
+        let features = feature_flags(&security, true, false);
+        assert!(!features.resource_limits && !features.privileged_ports && !features.unlabelled_bind_mounts);
+        assert!(feature_flags(&security, false, false).resource_limits);
+    }
+}
@@ -0,0 +1,393 @@
+//! Container templates
+//!
+//! A template is a [`ContainerSpec`] with `${VAR}` placeholders, a list of
+//! declared variables, and named profiles (dev/staging/prod value sets).
+//! Instantiating a template resolves every placeholder from explicit values,
+//! then the chosen profile, then variable defaults, and validates the result.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::LazyLock,
+};
+use tracing::{error, info};
+
+use crate::{
+    models::{ContainerSpec, ContainerTemplate, InstantiateRequest, InstantiateResponse},
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+pub type TemplateStore = JsonStore<ContainerTemplate>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("template '{0}' not found")]
+    NotFound(String),
+    #[error("template '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("profile '{0}' is not defined by this template")]
+    UnknownProfile(String),
+    #[error("undeclared variables: {}", .0.join(", "))]
+    UndeclaredVariables(Vec<String>),
+    #[error("missing values for required variables: {}", .0.join(", "))]
+    MissingVariables(Vec<String>),
+    #[error("resolved spec is invalid: {0}")]
+    InvalidSpec(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+impl TemplateError {
+    fn status(&self) -> StatusCode {
+        match self {
+            TemplateError::NotFound(_) => StatusCode::NOT_FOUND,
+            TemplateError::AlreadyExists(_) => StatusCode::CONFLICT,
+            TemplateError::Docker(_) | TemplateError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl IntoResponse for TemplateError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Template error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Every placeholder name referenced anywhere in a JSON value
+fn placeholders(value: &serde_json::Value, found: &mut BTreeSet<String>) {
+    match value {
+        serde_json::Value::String(s) => {
+            for cap in PLACEHOLDER.captures_iter(s) {
+                found.insert(cap[1].to_string());
+            }
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|v| placeholders(v, found)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| placeholders(v, found)),
+        _ => {}
+    }
+}
+
+/// Spec fields that hold numbers, so a placeholder standing alone in one
+/// becomes a JSON number
+const NUMERIC_FIELDS: &[&str] = &["containerPort", "hostPort"];
+
+/// Replace placeholders in every string. In a [`NUMERIC_FIELDS`] field, a
+/// string that is exactly one placeholder resolving to a number becomes a
+/// JSON number, so ports can be templated; everywhere else values stay strings.
+fn substitute(value: &serde_json::Value, vars: &BTreeMap<String, String>, numeric: bool) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => {
+            if numeric {
+                if let Some(cap) = PLACEHOLDER.captures(s).filter(|c| c[0].len() == s.len()) {
+                    if let Some(resolved) = vars.get(&cap[1]) {
+                        if let Ok(n) = resolved.parse::<u64>() {
+                            return serde_json::Value::from(n);
+                        }
+                    }
+                }
+            }
+            let replaced = PLACEHOLDER.replace_all(s, |cap: &regex::Captures| {
+                vars.get(&cap[1]).cloned().unwrap_or_else(|| cap[0].to_string())
+            });
+            serde_json::Value::String(replaced.into_owned())
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|v| substitute(v, vars, false)).collect())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), substitute(v, vars, NUMERIC_FIELDS.contains(&k.as_str()))))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Ensure every placeholder is declared and every profile only sets declared variables
+pub fn validate(template: &ContainerTemplate) -> Result<(), TemplateError> {
+    let declared: BTreeSet<&str> = template.variables.iter().map(|v| v.name.as_str()).collect();
+
+    let mut used = BTreeSet::new();
+    placeholders(&template.spec, &mut used);
+
+    let mut undeclared: BTreeSet<String> = used
+        .into_iter()
+        .filter(|name| !declared.contains(name.as_str()))
+        .collect();
+    for values in template.profiles.values() {
+        undeclared.extend(values.keys().filter(|k| !declared.contains(k.as_str())).cloned());
+    }
+
+    if !undeclared.is_empty() {
+        return Err(TemplateError::UndeclaredVariables(undeclared.into_iter().collect()));
+    }
+    Ok(())
+}
+
+/// Resolve variables and produce a concrete spec
+pub fn instantiate(
+    template: &ContainerTemplate,
+    profile: Option<&str>,
+    overrides: &BTreeMap<String, String>,
+) -> Result<(ContainerSpec, BTreeMap<String, String>), TemplateError> {
+    let profile_values = match profile {
+        Some(name) => Some(
+            template.profiles
+                .get(name)
+                .ok_or_else(|| TemplateError::UnknownProfile(name.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let declared: BTreeSet<&str> = template.variables.iter().map(|v| v.name.as_str()).collect();
+    let unknown: Vec<String> = overrides
+        .keys()
+        .filter(|k| !declared.contains(k.as_str()))
+        .cloned()
+        .collect();
+    if !unknown.is_empty() {
+        return Err(TemplateError::UndeclaredVariables(unknown));
+    }
+
+    let mut resolved = BTreeMap::new();
+    let mut missing = Vec::new();
+    for var in &template.variables {
+        let value = overrides
+            .get(&var.name)
+            .or_else(|| profile_values.and_then(|p| p.get(&var.name)))
+            .or(var.default.as_ref());
+        match value {
+            Some(value) => {
+                resolved.insert(var.name.clone(), value.clone());
+            }
+            None if var.required => missing.push(var.name.clone()),
+            None => {
+                resolved.insert(var.name.clone(), String::new());
+            }
+        }
+    }
+    if !missing.is_empty() {
+        return Err(TemplateError::MissingVariables(missing));
+    }
+
+    let spec = serde_json::from_value(substitute(&template.spec, &resolved, false))
+        .map_err(|e| TemplateError::InvalidSpec(e.to_string()))?;
+    Ok((spec, resolved))
+}
+
+/// GET /api/templates - List templates
+pub async fn list_templates(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.templates.list())
+}
+
+/// GET /api/templates/:name - Get a template
+pub async fn get_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<ContainerTemplate>, TemplateError> {
+    state
+        .templates
+        .get(&name)
+        .map(Json)
+        .ok_or(TemplateError::NotFound(name))
+}
+
+/// POST /api/templates - Create a template
+pub async fn create_template(
+    State(state): State<AppState>,
+    Json(mut template): Json<ContainerTemplate>,
+) -> Result<impl IntoResponse, TemplateError> {
+    if state.templates.contains(&template.name) {
+        return Err(TemplateError::AlreadyExists(template.name));
+    }
+    validate(&template)?;
+    template.created_at = Utc::now();
+    template.updated_at = template.created_at;
+
+    state.templates.put(&template.name, template.clone())?;
+    info!("Created template '{}'", template.name);
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+/// PUT /api/templates/:name - Replace a template
+pub async fn update_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(mut template): Json<ContainerTemplate>,
+) -> Result<Json<ContainerTemplate>, TemplateError> {
+    let existing = state
+        .templates
+        .get(&name)
+        .ok_or_else(|| TemplateError::NotFound(name.clone()))?;
+
+    template.name = name.clone();
+    validate(&template)?;
+    template.created_at = existing.created_at;
+    template.updated_at = Utc::now();
+
+    state.templates.put(&name, template.clone())?;
+    info!("Updated template '{}'", name);
+    Ok(Json(template))
+}
+
+/// DELETE /api/templates/:name - Delete a template
+pub async fn delete_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, TemplateError> {
+    match state.templates.remove(&name)? {
+        Some(_) => {
+            info!("Deleted template '{}'", name);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(TemplateError::NotFound(name)),
+    }
+}
+
+/// POST /api/templates/:name/instantiate - Resolve a template into a concrete spec
+pub async fn instantiate_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<InstantiateRequest>,
+) -> Result<Json<InstantiateResponse>, TemplateError> {
+    let template = state
+        .templates
+        .get(&name)
+        .ok_or_else(|| TemplateError::NotFound(name.clone()))?;
+
+    let (spec, variables) = instantiate(&template, req.profile.as_deref(), &req.variables)?;
+    let preflight = state.docker.preflight(&spec).await?;
+
+    info!(
+        "Instantiated template '{}' (profile: {})",
+        name,
+        req.profile.as_deref().unwrap_or("none")
+    );
+    Ok(Json(InstantiateResponse {
+        template: name,
+        profile: req.profile,
+        variables,
+        spec,
+        preflight,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> ContainerTemplate {
+        serde_json::from_value(serde_json::json!({
+            "name": "api",
+            "spec": {
+                "name": "api-${ENV}",
+                "image": "registry/api:${VERSION}",
+                "env": ["ENV=${ENV}", "WORKERS=${WORKERS}"],
+                "ports": [{"containerPort": "${PORT}", "hostPort": "${PORT}"}],
+                "labels": {"version": "${VERSION}", "tier": "${TIER}"}
+            },
+            "variables": [
+                {"name": "ENV", "default": "dev"},
+                {"name": "VERSION", "required": true},
+                {"name": "PORT", "default": "8080"},
+                {"name": "WORKERS", "default": "2"},
+                {"name": "TIER"}
+            ],
+            "profiles": {
+                "prod": {"ENV": "prod", "PORT": "80", "VERSION": "1"}
+            }
+        }))
+        .unwrap()
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn substitutes_placeholders_from_defaults() {
+        let (spec, resolved) = instantiate(&template(), None, &vars(&[("VERSION", "2.1")])).unwrap();
+        assert_eq!(spec.name.as_deref(), Some("api-dev"));
+        assert_eq!(spec.image, "registry/api:2.1");
+        assert_eq!(spec.env, vec!["ENV=dev", "WORKERS=2"]);
+        assert_eq!(spec.ports[0].container_port, 8080);
+        assert_eq!(spec.ports[0].host_port, Some(8080));
+        // Optional variables without a default resolve to nothing
+        assert_eq!(spec.labels["tier"], "");
+        assert_eq!(resolved["PORT"], "8080");
+    }
+
+    #[test]
+    fn overrides_beat_the_profile_which_beats_defaults() {
+        let (spec, _) = instantiate(&template(), Some("prod"), &BTreeMap::new()).unwrap();
+        assert_eq!(spec.name.as_deref(), Some("api-prod"));
+        assert_eq!(spec.ports[0].container_port, 80);
+        assert_eq!(spec.env[1], "WORKERS=2");
+
+        let (spec, _) = instantiate(&template(), Some("prod"), &vars(&[("PORT", "8443")])).unwrap();
+        assert_eq!(spec.ports[0].host_port, Some(8443));
+        assert_eq!(spec.image, "registry/api:1");
+
+        assert!(matches!(
+            instantiate(&template(), Some("qa"), &BTreeMap::new()),
+            Err(TemplateError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn missing_and_undeclared_variables_are_rejected() {
+        match instantiate(&template(), None, &BTreeMap::new()) {
+            Err(TemplateError::MissingVariables(names)) => assert_eq!(names, vec!["VERSION"]),
+            other => panic!("expected missing variables, got {:?}", other.map(|(spec, _)| spec)),
+        }
+        assert!(matches!(
+            instantiate(&template(), None, &vars(&[("VERSION", "1"), ("COLOR", "blue")])),
+            Err(TemplateError::UndeclaredVariables(_))
+        ));
+
+        let mut undeclared = template();
+        undeclared.spec["networks"] = serde_json::json!(["${NETWORK}"]);
+        match validate(&undeclared) {
+            Err(TemplateError::UndeclaredVariables(names)) => assert_eq!(names, vec!["NETWORK"]),
+            other => panic!("expected undeclared variables, got {:?}", other),
+        }
+        assert!(validate(&template()).is_ok());
+    }
+
+    #[test]
+    fn numbers_stay_strings_outside_numeric_fields() {
+        // A label that is exactly one numeric placeholder must not become a number
+        let (spec, _) = instantiate(&template(), Some("prod"), &BTreeMap::new()).unwrap();
+        assert_eq!(spec.labels["version"], "1");
+
+        let spec = substitute(
+            &serde_json::json!({"labels": {"replicas": "${N}"}, "ports": [{"hostPort": "${N}"}], "env": ["${N}"]}),
+            &vars(&[("N", "3")]),
+            false,
+        );
+        assert_eq!(spec["labels"]["replicas"], "3");
+        assert_eq!(spec["env"][0], "3");
+        assert_eq!(spec["ports"][0]["hostPort"], 3);
+    }
+}
@@ -0,0 +1,134 @@
+//! Shared topology snapshot
+//!
+//! `/api/topology` is the first thing every dashboard asks for, and building it
+//! lists every container and asks the daemon for host resources. A background
+//! task rebuilds the snapshot every `FLOWSCOPE_TOPOLOGY_REFRESH_SECS` (default
+//! 5) and requests are served from it, with its age in the `Age` header;
+//! `?refresh=true` rebuilds it first. Rebuilds requested while one is under way
+//! share its result, so a burst of refreshes costs the daemon one listing.
+
+use std::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use tracing::warn;
+
+use crate::{
+    models::SystemTopology,
+    provider::{DiscoveryProvider, ProviderError},
+    AppState,
+};
+
+/// Rebuild interval when `FLOWSCOPE_TOPOLOGY_REFRESH_SECS` is unset
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+struct Snapshot {
+    topology: SystemTopology,
+    built: Instant,
+}
+
+pub struct TopologyCache {
+    interval: Duration,
+    snapshot: RwLock<Option<Snapshot>>,
+    /// Held while rebuilding
+    rebuild: tokio::sync::Mutex<()>,
+}
+
+impl TopologyCache {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            snapshot: RwLock::new(None),
+            rebuild: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Rebuild every `FLOWSCOPE_TOPOLOGY_REFRESH_SECS` (default 5)
+    pub fn from_env() -> Self {
+        let interval = std::env::var("FLOWSCOPE_TOPOLOGY_REFRESH_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|&v| v > 0)
+            .map_or(DEFAULT_INTERVAL, Duration::from_secs);
+        Self::new(interval)
+    }
+
+    /// The last snapshot and its age
+    pub fn get(&self) -> Option<(SystemTopology, Duration)> {
+        let snapshot = self.snapshot.read().unwrap();
+        snapshot.as_ref().map(|s| (s.topology.clone(), s.built.elapsed()))
+    }
+
+    /// Rebuild the snapshot; a caller arriving while another rebuild is under
+    /// way gets that rebuild's result
+    pub async fn refresh<P: DiscoveryProvider>(&self, provider: &P) -> Result<(SystemTopology, Duration), ProviderError> {
+        let requested = Instant::now();
+        let _rebuilding = self.rebuild.lock().await;
+        if let Some(snapshot) = self.snapshot.read().unwrap().as_ref().filter(|s| s.built >= requested) {
+            return Ok((snapshot.topology.clone(), snapshot.built.elapsed()));
+        }
+
+        let built = Instant::now();
+        let topology = provider.get_topology().await?;
+        *self.snapshot.write().unwrap() = Some(Snapshot {
+            topology: topology.clone(),
+            built,
+        });
+        Ok((topology, built.elapsed()))
+    }
+
+    /// The snapshot, rebuilt first when `refresh` is set or there is none yet
+    pub async fn current<P: DiscoveryProvider>(
+        &self,
+        provider: &P,
+        refresh: bool,
+    ) -> Result<(SystemTopology, Duration), ProviderError> {
+        match self.get() {
+            Some(snapshot) if !refresh => Ok(snapshot),
+            _ => self.refresh(provider).await,
+        }
+    }
+}
+
+/// Spawn the task that keeps the snapshot fresh; a failed rebuild leaves the
+/// previous snapshot in place
+pub fn spawn_refresher(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = state.topology.refresh(state.provider.as_ref()).await {
+                warn!("Failed to refresh the topology snapshot: {}", e);
+            }
+            tokio::time::sleep(state.topology.interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{discovery::DockerDiscovery, docker_api::fake::FakeDocker};
+
+    #[tokio::test]
+    async fn serves_the_snapshot_until_asked_to_refresh() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let cache = TopologyCache::new(DEFAULT_INTERVAL);
+        assert!(cache.get().is_none());
+
+        let (topology, _) = cache.current(&discovery, false).await.unwrap();
+        assert_eq!(topology.total_containers, 5);
+        cache.current(&discovery, false).await.unwrap();
+        assert_eq!(discovery.api().listings(), 1);
+
+        cache.current(&discovery, true).await.unwrap();
+        assert_eq!(discovery.api().listings(), 2);
+
+        // Refreshes waiting on a rebuild share it
+        let rebuilding = cache.rebuild.lock().await;
+        let (a, b, _) = tokio::join!(cache.refresh(&discovery), cache.refresh(&discovery), async move {
+            drop(rebuilding)
+        });
+        assert_eq!(a.unwrap().0.generated_at, b.unwrap().0.generated_at);
+        assert_eq!(discovery.api().listings(), 3);
+    }
+}
@@ -0,0 +1,33 @@
+//! Lifetimes of short-lived resources
+//!
+//! Debug sidecars, one-off runs and port forwards go away on their own once
+//! their time is up. Each takes an optional `ttlSeconds` with its own default
+//! and cap.
+
+use std::time::Duration;
+
+/// Requested TTL, defaulting to `default` and capped at `max`; the error
+/// says what is wrong with `ttlSeconds`
+pub fn requested(ttl_seconds: Option<u64>, default: Duration, max: Duration) -> Result<Duration, String> {
+    match ttl_seconds.map(Duration::from_secs) {
+        None => Ok(default),
+        Some(ttl) if ttl.is_zero() => Err("ttlSeconds must be positive".to_string()),
+        Some(ttl) if ttl > max => Err(format!("ttlSeconds must be at most {}", max.as_secs())),
+        Some(ttl) => Ok(ttl),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ttl_bounds() {
+        let (default, max) = (Duration::from_secs(600), Duration::from_secs(3600));
+        assert_eq!(requested(None, default, max).unwrap(), default);
+        assert_eq!(requested(Some(60), default, max).unwrap(), Duration::from_secs(60));
+        assert_eq!(requested(Some(3600), default, max).unwrap(), max);
+        assert_eq!(requested(Some(0), default, max).unwrap_err(), "ttlSeconds must be positive");
+        assert_eq!(requested(Some(3601), default, max).unwrap_err(), "ttlSeconds must be at most 3600");
+    }
+}
@@ -0,0 +1,337 @@
+//! How the publisher learns about container changes
+//!
+//! While the daemon's event stream is connected and trusted, the publisher is
+//! event-driven: a container event wakes it to publish at once, and a slow
+//! safety poll catches anything else. Some daemons and proxied sockets accept
+//! the events request but deliver events late or not at all, so every poll
+//! also checks whether the changes it found were announced by an event. After
+//! [`MISSED_LIMIT`] unannounced changes in a row, or whenever the stream is
+//! down, the publisher falls back to adaptive polling: at the clients'
+//! interval while changes keep coming, slower once things have been idle for
+//! [`ACTIVE_WINDOW`]. Events are trusted again after [`CONFIRMATIONS`] changes
+//! in a row arrive with events. The active mode is reported on `/health`.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::{discovery, models::DockerEvent};
+
+/// Unannounced changes in a row that make events untrusted
+pub const MISSED_LIMIT: u32 = 3;
+/// Announced changes in a row that make events trusted again
+pub const CONFIRMATIONS: u32 = 3;
+/// Poll interval while event-driven, to catch changes events don't cover
+pub const SAFETY_POLL: Duration = Duration::from_secs(30);
+/// How long after the last change polling stays at the clients' interval
+pub const ACTIVE_WINDOW: Duration = Duration::from_secs(60);
+/// Idle polling runs this many times slower than the clients' interval...
+const IDLE_FACTOR: u32 = 4;
+/// ...but no slower than this, unless clients asked for slower still
+const IDLE_MAX: Duration = Duration::from_secs(30);
+/// Pause after an event before publishing, so a burst is published once
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateMode {
+    Events,
+    Polling,
+}
+
+/// Update mode as reported on `/health`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateStatus {
+    pub mode: UpdateMode,
+    /// Why the publisher is polling; absent while event-driven
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub stream_connected: bool,
+    pub since: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_event_at: Option<DateTime<Utc>>,
+    /// Current wait between polls while polling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_interval_ms: Option<u64>,
+}
+
+#[derive(Debug)]
+struct FeedState {
+    stream_connected: bool,
+    stream_error: Option<String>,
+    /// Cleared after [`MISSED_LIMIT`] unannounced changes
+    trusted: bool,
+    /// Unannounced changes in a row while trusted, announced ones while not
+    streak: u32,
+    /// Container events since the last poll
+    events_since_poll: u32,
+    last_event_at: Option<DateTime<Utc>>,
+    last_change: Option<Instant>,
+    /// The first poll only establishes what is there
+    primed: bool,
+    mode: UpdateMode,
+    since: DateTime<Utc>,
+    poll_interval: Option<Duration>,
+}
+
+impl FeedState {
+    fn mode(&self) -> UpdateMode {
+        if self.stream_connected && self.trusted {
+            UpdateMode::Events
+        } else {
+            UpdateMode::Polling
+        }
+    }
+
+    fn reason(&self) -> Option<String> {
+        if !self.stream_connected {
+            Some(match &self.stream_error {
+                Some(e) => format!("event stream unavailable: {}", e),
+                None => "event stream not connected".to_string(),
+            })
+        } else if !self.trusted {
+            Some(format!(
+                "{} changes in a row arrived without events; {} announced changes in a row restore event mode",
+                MISSED_LIMIT, CONFIRMATIONS
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Note a mode change, logging it
+    fn update_mode(&mut self) {
+        let mode = self.mode();
+        if mode == self.mode {
+            return;
+        }
+        match self.reason() {
+            Some(reason) => warn!("Falling back to polling for container updates: {}", reason),
+            None => info!("Container updates are event-driven again"),
+        }
+        self.mode = mode;
+        self.since = Utc::now();
+    }
+}
+
+pub struct UpdateFeed {
+    state: Mutex<FeedState>,
+    /// Woken by container events
+    notify: Notify,
+}
+
+impl Default for UpdateFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UpdateFeed {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(FeedState {
+                stream_connected: false,
+                stream_error: None,
+                trusted: true,
+                streak: 0,
+                events_since_poll: 0,
+                last_event_at: None,
+                last_change: None,
+                primed: false,
+                mode: UpdateMode::Polling,
+                since: Utc::now(),
+                poll_interval: None,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn status(&self) -> UpdateStatus {
+        let state = self.state.lock().unwrap();
+        UpdateStatus {
+            mode: state.mode,
+            reason: state.reason(),
+            stream_connected: state.stream_connected,
+            since: state.since,
+            last_event_at: state.last_event_at,
+            poll_interval_ms: state
+                .poll_interval
+                .filter(|_| state.mode == UpdateMode::Polling)
+                .map(|i| i.as_millis() as u64),
+        }
+    }
+
+    /// Whether events are trusted to announce every change
+    pub fn event_driven(&self) -> bool {
+        self.state.lock().unwrap().mode == UpdateMode::Events
+    }
+
+    /// The event stream (re)connected
+    pub fn stream_opened(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.stream_connected = true;
+        state.stream_error = None;
+        state.update_mode();
+    }
+
+    /// The event stream failed or ended
+    pub fn stream_lost(&self, error: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.stream_connected = false;
+        state.stream_error = Some(error.to_string());
+        state.update_mode();
+    }
+
+    /// An event arrived; events that change a container's listing wake the
+    /// publisher
+    pub fn event_seen(&self, event: &DockerEvent) {
+        if !discovery::changes_listing(event) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.events_since_poll += 1;
+        state.last_event_at = Some(event.time);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    /// A poll finished, having found changes or not
+    pub fn polled(&self, changed: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let announced = std::mem::take(&mut state.events_since_poll) > 0;
+        if !std::mem::replace(&mut state.primed, true) || !changed {
+            return;
+        }
+        state.last_change = Some(now);
+        if !state.stream_connected {
+            return;
+        }
+
+        if state.trusted {
+            state.streak = if announced { 0 } else { state.streak + 1 };
+            if state.streak >= MISSED_LIMIT {
+                state.trusted = false;
+                state.streak = 0;
+            }
+        } else {
+            state.streak = if announced { state.streak + 1 } else { 0 };
+            if state.streak >= CONFIRMATIONS {
+                state.trusted = true;
+                state.streak = 0;
+            }
+        }
+        state.update_mode();
+    }
+
+    /// How long to poll after while polling: the clients' interval while
+    /// changes keep coming, slower once idle
+    pub fn poll_interval(&self, client_interval: Duration, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let active = state.last_change.is_some_and(|at| now.duration_since(at) < ACTIVE_WINDOW);
+        let interval = if active {
+            client_interval
+        } else {
+            (client_interval * IDLE_FACTOR).min(IDLE_MAX).max(client_interval)
+        };
+        state.poll_interval = Some(interval);
+        interval
+    }
+
+    /// Wait until the next poll is due. `started` is when the last poll began.
+    pub async fn wait(&self, client_interval: Duration, started: Instant) {
+        let mode = self.state.lock().unwrap().mode;
+        match mode {
+            UpdateMode::Polling => {
+                let interval = self.poll_interval(client_interval, Instant::now());
+                tokio::time::sleep(interval.saturating_sub(started.elapsed())).await;
+            }
+            UpdateMode::Events => {
+                let safety = SAFETY_POLL.max(client_interval).saturating_sub(started.elapsed());
+                if tokio::time::timeout(safety, self.notify.notified()).await.is_ok() {
+                    tokio::time::sleep(DEBOUNCE).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EventActor;
+
+    fn event() -> DockerEvent {
+        DockerEvent {
+            time: Utc::now(),
+            event_type: "container".to_string(),
+            action: "die".to_string(),
+            detail: None,
+            actor: EventActor {
+                id: "a1".to_string(),
+                name: Some("shop-api-1".to_string()),
+            },
+            attributes: Default::default(),
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn unannounced_changes_fall_back_to_polling_and_back() {
+        let feed = UpdateFeed::new();
+        let now = Instant::now();
+        assert_eq!(feed.status().mode, UpdateMode::Polling);
+        feed.stream_opened();
+        assert_eq!(feed.status().mode, UpdateMode::Events);
+
+        // The first poll only sees what is already there
+        feed.polled(true, now);
+        for _ in 0..MISSED_LIMIT - 1 {
+            feed.polled(true, now);
+        }
+        assert_eq!(feed.status().mode, UpdateMode::Events);
+        feed.event_seen(&event());
+        feed.polled(true, now);
+        feed.polled(false, now);
+        assert_eq!(feed.status().mode, UpdateMode::Events);
+        for _ in 0..MISSED_LIMIT {
+            feed.polled(true, now);
+        }
+        let status = feed.status();
+        assert_eq!(status.mode, UpdateMode::Polling);
+        assert!(status.reason.unwrap().starts_with("3 changes in a row arrived without events"));
+
+        for _ in 0..CONFIRMATIONS {
+            feed.event_seen(&event());
+            feed.polled(true, now);
+        }
+        assert_eq!(feed.status().mode, UpdateMode::Events);
+
+        feed.stream_lost("connection reset");
+        let status = feed.status();
+        assert_eq!(status.mode, UpdateMode::Polling);
+        assert_eq!(status.reason.as_deref(), Some("event stream unavailable: connection reset"));
+    }
+
+    #[test]
+    fn polling_slows_down_when_idle() {
+        let feed = UpdateFeed::new();
+        let start = Instant::now();
+        let client = Duration::from_secs(2);
+        assert_eq!(feed.poll_interval(client, start), Duration::from_secs(8));
+
+        feed.polled(true, start);
+        feed.polled(true, start);
+        assert_eq!(feed.poll_interval(client, start + Duration::from_secs(10)), client);
+        assert_eq!(feed.poll_interval(client, start + ACTIVE_WINDOW), Duration::from_secs(8));
+        assert_eq!(feed.poll_interval(Duration::from_secs(10), start + ACTIVE_WINDOW), IDLE_MAX);
+        assert_eq!(feed.poll_interval(Duration::from_secs(60), start + ACTIVE_WINDOW), Duration::from_secs(60));
+        assert_eq!(feed.status().poll_interval_ms, Some(60_000));
+    }
+}
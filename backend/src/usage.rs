@@ -0,0 +1,217 @@
+//! Per-token usage metering and rate limits
+//!
+//! Every authenticated request is counted against its token, together with
+//! the actions it performed and the time it spent connected over WebSocket,
+//! so a shared instance can tell which automation client is noisy. Tokens in a
+//! rate class are also limited per minute of requests and per hour of actions;
+//! windows are fixed and start at the first request. Counters live in memory
+//! and reset on restart.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+
+use crate::{
+    auth::ApiToken,
+    models::{ApiScope, TokenUsage},
+    AppState,
+};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+impl Window {
+    /// Start a new window once the current one has run its length
+    fn roll(&mut self, now: Instant, length: Duration) {
+        if now.duration_since(self.started) >= length {
+            *self = Window { started: now, count: 0 };
+        }
+    }
+
+    fn retry_after(&self, now: Instant, length: Duration) -> Duration {
+        length.saturating_sub(now.duration_since(self.started))
+    }
+}
+
+#[derive(Debug)]
+struct Meter {
+    requests: u64,
+    actions: u64,
+    rejected: u64,
+    ws_connections: u64,
+    ws_closed_time: Duration,
+    /// When each open WebSocket connection was opened
+    ws_open: Vec<Instant>,
+    last_seen: Option<DateTime<Utc>>,
+    minute: Window,
+    hour: Window,
+}
+
+impl Meter {
+    fn new(now: Instant) -> Self {
+        let window = Window { started: now, count: 0 };
+        Self {
+            requests: 0,
+            actions: 0,
+            rejected: 0,
+            ws_connections: 0,
+            ws_closed_time: Duration::ZERO,
+            ws_open: Vec::new(),
+            last_seen: None,
+            minute: window,
+            hour: window,
+        }
+    }
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, Copy)]
+pub struct LimitExceeded {
+    pub what: &'static str,
+    pub retry_after: Duration,
+}
+
+fn is_action(scope: ApiScope) -> bool {
+    matches!(scope, ApiScope::ContainersActions | ApiScope::Exec)
+}
+
+#[derive(Debug, Default)]
+pub struct UsageMeter {
+    meters: Mutex<HashMap<String, Meter>>,
+}
+
+impl UsageMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request needing `scope`, or refuse it when it would exceed the
+    /// token's limits. Refused requests do not count towards the limits.
+    pub fn admit(&self, token: &ApiToken, scope: ApiScope, now: Instant) -> Result<(), LimitExceeded> {
+        let mut meters = self.meters.lock().unwrap();
+        let meter = meters.entry(token.name.clone()).or_insert_with(|| Meter::new(now));
+        meter.last_seen = Some(Utc::now());
+        meter.minute.roll(now, MINUTE);
+        meter.hour.roll(now, HOUR);
+
+        let action = is_action(scope);
+        let exceeded = if token.limits.requests_per_minute.is_some_and(|limit| meter.minute.count >= limit) {
+            Some(LimitExceeded {
+                what: "its requests per minute",
+                retry_after: meter.minute.retry_after(now, MINUTE),
+            })
+        } else if action && token.limits.actions_per_hour.is_some_and(|limit| meter.hour.count >= limit) {
+            Some(LimitExceeded {
+                what: "its actions per hour",
+                retry_after: meter.hour.retry_after(now, HOUR),
+            })
+        } else {
+            None
+        };
+        if let Some(exceeded) = exceeded {
+            meter.rejected += 1;
+            return Err(exceeded);
+        }
+
+        meter.requests += 1;
+        meter.minute.count += 1;
+        if action {
+            meter.actions += 1;
+            meter.hour.count += 1;
+        }
+        Ok(())
+    }
+
+    /// Note a WebSocket connection opened with `token`
+    pub fn ws_opened(&self, token: &str, now: Instant) {
+        let mut meters = self.meters.lock().unwrap();
+        let meter = meters.entry(token.to_string()).or_insert_with(|| Meter::new(now));
+        meter.ws_connections += 1;
+        meter.ws_open.push(now);
+    }
+
+    /// Note a WebSocket connection opened at `opened` has closed
+    pub fn ws_closed(&self, token: &str, opened: Instant, now: Instant) {
+        if let Some(meter) = self.meters.lock().unwrap().get_mut(token) {
+            if let Some(index) = meter.ws_open.iter().position(|&t| t == opened) {
+                meter.ws_open.swap_remove(index);
+                meter.ws_closed_time += now.duration_since(opened);
+            }
+        }
+    }
+
+    /// Usage of every configured token, including ones not used yet
+    pub fn report(&self, tokens: &[ApiToken], now: Instant) -> Vec<TokenUsage> {
+        let meters = self.meters.lock().unwrap();
+        tokens
+            .iter()
+            .map(|token| {
+                let meter = meters.get(&token.name);
+                let ws_time = meter.map_or(Duration::ZERO, |m| {
+                    m.ws_closed_time + m.ws_open.iter().map(|&t| now.duration_since(t)).sum::<Duration>()
+                });
+                TokenUsage {
+                    token: token.name.clone(),
+                    role: token.role,
+                    rate_class: token.rate_class.clone(),
+                    limits: token.limits,
+                    requests: meter.map_or(0, |m| m.requests),
+                    actions: meter.map_or(0, |m| m.actions),
+                    rejected: meter.map_or(0, |m| m.rejected),
+                    ws_connections: meter.map_or(0, |m| m.ws_connections),
+                    ws_active: meter.map_or(0, |m| m.ws_open.len() as u64),
+                    ws_seconds: (ws_time.as_secs_f64() * 10.0).round() / 10.0,
+                    last_seen: meter.and_then(|m| m.last_seen),
+                }
+            })
+            .collect()
+    }
+}
+
+/// GET /api/auth/usage - Requests, actions and WebSocket time per token
+pub async fn get_usage(State(state): State<AppState>) -> Json<Vec<TokenUsage>> {
+    Json(state.usage.report(state.auth.tokens(), Instant::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RateLimits;
+
+    #[test]
+    fn limits_reset_with_their_window() {
+        let meter = UsageMeter::new();
+        let mut token = ApiToken::named("bot");
+        token.limits = RateLimits {
+            requests_per_minute: Some(3),
+            actions_per_hour: Some(1),
+        };
+        let start = Instant::now();
+
+        assert!(meter.admit(&token, ApiScope::ContainersActions, start).is_ok());
+        let refused = meter.admit(&token, ApiScope::ContainersActions, start).unwrap_err();
+        assert_eq!(refused.what, "its actions per hour");
+        assert!(meter.admit(&token, ApiScope::ContainersRead, start).is_ok());
+        assert!(meter.admit(&token, ApiScope::ContainersRead, start).is_ok());
+        let refused = meter.admit(&token, ApiScope::ContainersRead, start + Duration::from_secs(20)).unwrap_err();
+        assert_eq!(refused.retry_after, Duration::from_secs(40));
+        assert!(meter.admit(&token, ApiScope::ContainersRead, start + MINUTE).is_ok());
+
+        meter.ws_opened("bot", start);
+        meter.ws_closed("bot", start, start + Duration::from_secs(90));
+        let usage = &meter.report(&[token], start + MINUTE)[0];
+        assert_eq!((usage.requests, usage.actions, usage.rejected), (4, 1, 2));
+        assert_eq!((usage.ws_connections, usage.ws_active, usage.ws_seconds), (1, 0, 90.0));
+    }
+}
@@ -0,0 +1,495 @@
+//! Configuration validation
+//!
+//! Runs before the server starts and as `flowscope validate-config`, checking
+//! everything FlowScope reads at startup in one pass: the configuration file
+//! and the settings overriding it, the discovery provider,
+//! the Docker endpoint and its TLS files or the Kubernetes API settings, the
+//! data directory, the stores and notification channels in it, the token and
+//! categorization files, and numeric and address settings from the
+//! environment. Every problem is reported with the file or variable it
+//! comes from, instead of the server stopping at the first one.
+//!
+//! Errors stop the server from starting. Warnings are settings FlowScope can
+//! run without, such as a daemon that is down for now or a variable that falls
+//! back to its default.
+
+use std::{
+    fmt,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::Args;
+use tracing::{error, info, warn};
+
+use crate::{
+    auth::Authenticator,
+    categorize::Categorizers,
+    config::{Config, ConfigArgs},
+    daemon, docker_api, hosts,
+    kubernetes::KubernetesDiscovery,
+    models::{CheckRules, NotificationChannel, Severity},
+    notifications,
+    oneshot::{EXIT_CONFIG, EXIT_OK},
+    provider::ProviderKind,
+    store::data_dir,
+};
+
+/// How long the Docker endpoint and the endpoint relay get to answer
+const REACH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stores opened from the data directory at startup
+const STORES: &[&str] = &[
+    "templates.json",
+    "groups.json",
+    "dashboards.json",
+    "env-files.json",
+    "layouts.json",
+    "image-history.json",
+    "warmup-profiles.json",
+    "artifacts/index.json",
+];
+
+/// Channels are checked record by record, see [`check_channels`]
+const CHANNELS: &str = "notification-channels.json";
+
+/// Settings read as positive whole numbers, falling back to a default otherwise
+const NUMERIC_VARS: &[&str] = &[
+    "FLOWSCOPE_DOCKER_TIMEOUT_SECS",
+    "FLOWSCOPE_BREAKER_THRESHOLD",
+    "FLOWSCOPE_BREAKER_COOLDOWN_SECS",
+    "FLOWSCOPE_ARTIFACT_RETENTION_HOURS",
+    "FLOWSCOPE_ARTIFACT_MAX_MB",
+    "FLOWSCOPE_TOPOLOGY_REFRESH_SECS",
+];
+
+#[derive(Debug, Clone, Args)]
+pub struct ValidateConfigArgs {
+    /// `flowscope check` rules files to validate as well
+    #[arg(long)]
+    pub rules: Vec<PathBuf>,
+    /// Skip contacting the Docker daemon and the endpoint relay
+    #[arg(long)]
+    pub offline: bool,
+}
+
+/// One thing wrong with the configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub severity: Severity,
+    /// File or environment variable the problem is in
+    pub source: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    fn error(&mut self, source: impl fmt::Display, message: impl Into<String>) {
+        self.push(Severity::Error, source, message);
+    }
+
+    fn warning(&mut self, source: impl fmt::Display, message: impl Into<String>) {
+        self.push(Severity::Warning, source, message);
+    }
+
+    fn push(&mut self, severity: Severity, source: impl fmt::Display, message: impl Into<String>) {
+        self.problems.push(Problem {
+            severity,
+            source: source.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn errors(&self) -> usize {
+        self.problems.iter().filter(|p| p.severity == Severity::Error).count()
+    }
+
+    /// Log every problem, then a summary
+    pub fn log(&self) {
+        for problem in &self.problems {
+            match problem.severity {
+                Severity::Error => error!("{}", problem),
+                _ => warn!("{}", problem),
+            }
+        }
+        let errors = self.errors();
+        let warnings = self.problems.len() - errors;
+        if self.problems.is_empty() {
+            info!("Configuration is valid");
+        } else {
+            info!("Configuration has {} error(s) and {} warning(s)", errors, warnings);
+        }
+    }
+}
+
+/// Check everything, contacting the configured or probed Docker daemon and
+/// the endpoint relay unless `offline`
+pub async fn validate(offline: bool, args: &ConfigArgs) -> Report {
+    let var = |name: &str| std::env::var(name).ok();
+    let mut report = Report::default();
+
+    let config = Config::load(args)
+        .map_err(|e| report.error("configuration", e.to_string()))
+        .ok();
+    let docker_host_file = config.as_ref().and_then(|c| c.docker_host_file.as_deref());
+
+    // An invalid provider is already reported with the configuration
+    match config.as_ref().map_or(ProviderKind::Docker, |c| c.provider) {
+        ProviderKind::Docker => {
+            check_docker(&mut report, args.docker_host.as_deref(), docker_host_file, &var, offline).await;
+            check_tls(&mut report, &var);
+        }
+        ProviderKind::Kubernetes => check_kubernetes(&mut report, offline).await,
+    }
+    let dir = data_dir();
+    check_storage(&mut report, &dir);
+    for store in STORES {
+        check_store(&mut report, &dir.join(store));
+    }
+    check_channels(&mut report, &dir.join(CHANNELS));
+    if let Err(e) = Authenticator::from_env() {
+        report.error("API tokens", e.to_string());
+    }
+    if let Some(Err(e)) = config.as_ref().map(|c| Categorizers::open(&c.categories_file)) {
+        report.error("categorization strategies", e.to_string());
+    }
+    check_env(&mut report, &var);
+    if let Some(relay) = var("FLOWSCOPE_ENDPOINT_RELAY").filter(|v| !v.trim().is_empty()) {
+        check_relay(&mut report, &relay, offline).await;
+    }
+    report
+}
+
+/// The daemon endpoints, `FLOWSCOPE_DOCKER_HOSTS` or a single one, and
+/// whether they answer
+async fn check_docker(
+    report: &mut Report,
+    flag: Option<&str>,
+    file: Option<&str>,
+    var: &impl Fn(&str) -> Option<String>,
+    offline: bool,
+) {
+    let targets = match var("FLOWSCOPE_DOCKER_HOSTS").filter(|v| !v.trim().is_empty()) {
+        Some(value) => match hosts::parse(&value, var) {
+            Ok(hosts) => hosts
+                .into_iter()
+                .map(|(name, url)| (url, format!("FLOWSCOPE_DOCKER_HOSTS ({})", name)))
+                .collect(),
+            Err(e) => {
+                report.error("FLOWSCOPE_DOCKER_HOSTS", e);
+                return;
+            }
+        },
+        None => match daemon::configured(flag, file, var) {
+            Some(target) => vec![target],
+            None => {
+                if offline {
+                    return;
+                }
+                // Nothing configured: whatever probing finds is what the server uses
+                let (_, endpoint) = daemon::connect(None, None).await;
+                if let Some(e) = endpoint.error {
+                    report.warning("DOCKER_HOST", e);
+                }
+                return;
+            }
+        },
+    };
+
+    for (host, source) in targets {
+        let docker = match docker_api::connect(&host) {
+            Ok(docker) => docker,
+            Err(e) => {
+                report.error(&source, format!("cannot use {}: {}", host, e));
+                continue;
+            }
+        };
+        if offline {
+            continue;
+        }
+        let reached = tokio::time::timeout(REACH_TIMEOUT, docker.info()).await;
+        let failure = match reached {
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("no answer within {}s", REACH_TIMEOUT.as_secs()),
+        };
+        report.warning(
+            &source,
+            format!("the daemon at {} is not reachable ({}); FlowScope will keep retrying", host, failure),
+        );
+    }
+}
+
+/// The API server settings, and whether it answers
+async fn check_kubernetes(report: &mut Report, offline: bool) {
+    let cluster = match KubernetesDiscovery::from_env() {
+        Ok(cluster) => cluster,
+        Err(e) => {
+            report.error("FLOWSCOPE_KUBE_API", e.to_string());
+            return;
+        }
+    };
+    if offline {
+        return;
+    }
+    let reached = tokio::time::timeout(REACH_TIMEOUT, cluster.version()).await;
+    let failure = match reached {
+        Ok(Ok(_)) => return,
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => format!("no answer within {}s", REACH_TIMEOUT.as_secs()),
+    };
+    report.warning(
+        "FLOWSCOPE_KUBE_API",
+        format!(
+            "the Kubernetes API at {} is not reachable ({}); FlowScope will keep retrying",
+            cluster.config().api,
+            failure
+        ),
+    );
+}
+
+/// Docker's TLS settings: the certificate files `DOCKER_TLS_VERIFY` asks for
+fn check_tls(report: &mut Report, var: &impl Fn(&str) -> Option<String>) {
+    if var("DOCKER_TLS_VERIFY").is_none_or(|v| v.is_empty() || v == "0") {
+        return;
+    }
+    let dir = var("DOCKER_CERT_PATH")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".docker")));
+    let Some(dir) = dir else {
+        report.error("DOCKER_CERT_PATH", "DOCKER_TLS_VERIFY is set but no certificate directory is");
+        return;
+    };
+    for file in ["ca.pem", "cert.pem", "key.pem"] {
+        let path = dir.join(file);
+        match std::fs::read_to_string(&path) {
+            Ok(text) if text.contains("-----BEGIN ") => {}
+            Ok(_) => report.error(path.display(), "is not a PEM file"),
+            Err(e) => report.error(path.display(), format!("cannot be read: {}", e)),
+        }
+    }
+    report.error(
+        "DOCKER_TLS_VERIFY",
+        "this build talks to tcp:// daemons without TLS; reach a TLS daemon through a unix socket or an SSH tunnel instead",
+    );
+}
+
+/// The data directory must exist, or be creatable, and be writable
+fn check_storage(report: &mut Report, dir: &Path) {
+    let shown = format!("data directory {}", dir.display());
+    if dir.exists() && !dir.is_dir() {
+        report.error(shown, "exists but is not a directory; set FLOWSCOPE_DATA_DIR elsewhere");
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        report.error(shown, format!("cannot be created: {}", e));
+        return;
+    }
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+        }
+        Err(e) => report.error(shown, format!("is not writable: {}", e)),
+    }
+}
+
+/// Unparseable stores start empty and are overwritten on the next change
+fn check_store(report: &mut Report, path: &Path) {
+    let Ok(bytes) = std::fs::read(path) else {
+        return;
+    };
+    if let Err(e) = serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&bytes) {
+        report.error(
+            path.display(),
+            format!("cannot be parsed ({}); it would be replaced by an empty store", e),
+        );
+    }
+}
+
+/// Every stored channel must pass the same checks as when it was saved, and
+/// webhook targets must be absolute http(s) URLs
+fn check_channels(report: &mut Report, path: &Path) {
+    let Ok(bytes) = std::fs::read(path) else {
+        return;
+    };
+    let channels: std::collections::BTreeMap<String, NotificationChannel> = match serde_json::from_slice(&bytes) {
+        Ok(channels) => channels,
+        Err(e) => {
+            report.error(path.display(), format!("cannot be parsed ({}); it would be replaced by an empty store", e));
+            return;
+        }
+    };
+    for (name, channel) in channels {
+        if let Err(e) = notifications::validate(&channel) {
+            report.error(format!("{} channel '{}'", path.display(), name), e.to_string());
+        }
+    }
+}
+
+/// Settings from the environment that fall back to a default when invalid
+fn check_env(report: &mut Report, var: &impl Fn(&str) -> Option<String>) {
+    for name in NUMERIC_VARS {
+        if let Some(value) = var(name) {
+            if value.parse::<u64>().is_ok_and(|v| v > 0) {
+                continue;
+            }
+            report.warning(name, format!("'{}' is not a positive whole number; the default is used", value));
+        }
+    }
+    if let Some(value) = var("FLOWSCOPE_FORWARD_BIND") {
+        if value.parse::<IpAddr>().is_err() {
+            report.warning(
+                "FLOWSCOPE_FORWARD_BIND",
+                format!("'{}' is not an IP address; port forwards listen on every interface", value),
+            );
+        }
+    }
+    for name in ["FLOWSCOPE_HOST_PROC", "FLOWSCOPE_HOST_DISK_PATH"] {
+        if let Some(value) = var(name) {
+            if !Path::new(&value).is_dir() {
+                report.warning(name, format!("{} is not a directory", value));
+            }
+        }
+    }
+}
+
+async fn check_relay(report: &mut Report, relay: &str, offline: bool) {
+    match reqwest::Url::parse(relay) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {}
+        Ok(_) => {
+            report.error("FLOWSCOPE_ENDPOINT_RELAY", format!("'{}' is not an http(s) URL", relay));
+            return;
+        }
+        Err(e) => {
+            report.error("FLOWSCOPE_ENDPOINT_RELAY", format!("'{}' is not a URL: {}", relay, e));
+            return;
+        }
+    }
+    if offline {
+        return;
+    }
+    if let Err(e) = reqwest::Client::new().head(relay).timeout(REACH_TIMEOUT).send().await {
+        report.warning("FLOWSCOPE_ENDPOINT_RELAY", format!("{} is not reachable: {}", relay, e));
+    }
+}
+
+/// Parse each `flowscope check` rules file
+fn check_rules_files(report: &mut Report, files: &[PathBuf]) {
+    for path in files {
+        let parsed = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot be read: {}", e))
+            .and_then(|text| serde_yaml::from_str::<CheckRules>(&text).map_err(|e| format!("invalid rules: {}", e)));
+        if let Err(e) = parsed {
+            report.error(path.display(), e);
+        }
+    }
+}
+
+/// `flowscope validate-config`: print every problem, exiting non-zero on errors
+pub async fn run(args: &ValidateConfigArgs, config: &ConfigArgs) -> ExitCode {
+    let mut report = validate(args.offline, config).await;
+    check_rules_files(&mut report, &args.rules);
+    for problem in &report.problems {
+        let level = if problem.severity == Severity::Error { "error" } else { "warning" };
+        println!("{:<8} {}", level, problem);
+    }
+    report.log();
+    if report.errors() > 0 {
+        ExitCode::from(EXIT_CONFIG)
+    } else {
+        ExitCode::from(EXIT_OK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("flowscope-validate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reports_every_problem_in_the_data_directory() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("groups.json"), "{\"core\": ").unwrap();
+        std::fs::write(
+            dir.join(CHANNELS),
+            serde_json::json!({
+                "ops": { "name": "ops", "kind": "webhook", "target": "hooks.example.com/ops" },
+                "oncall": { "name": "oncall", "kind": "slack", "target": "https://hooks.slack.com/x" },
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let mut report = Report::default();
+        check_storage(&mut report, &dir);
+        for store in STORES {
+            check_store(&mut report, &dir.join(store));
+        }
+        check_channels(&mut report, &dir.join(CHANNELS));
+
+        let sources: Vec<String> = report.problems.iter().map(|p| p.source.clone()).collect();
+        assert_eq!(
+            sources,
+            [
+                dir.join("groups.json").display().to_string(),
+                format!("{} channel 'ops'", dir.join(CHANNELS).display()),
+            ]
+        );
+        assert!(report.problems[1].message.contains("not a valid webhook target"));
+        assert_eq!(report.errors(), 2);
+
+        let file = dir.join("groups.json");
+        let mut report = Report::default();
+        check_storage(&mut report, &file);
+        assert!(report.problems[0].message.starts_with("exists but is not a directory"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn environment_and_tls_settings() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("FLOWSCOPE_BREAKER_THRESHOLD", "five"),
+            ("FLOWSCOPE_ARTIFACT_MAX_MB", "512"),
+            ("FLOWSCOPE_FORWARD_BIND", "localhost"),
+            ("DOCKER_TLS_VERIFY", "1"),
+            ("DOCKER_CERT_PATH", "/nonexistent/certs"),
+        ]);
+        let var = |name: &str| env.get(name).map(|v| v.to_string());
+        let mut report = Report::default();
+        check_env(&mut report, &var);
+        check_tls(&mut report, &var);
+
+        let sources: Vec<&str> = report.problems.iter().map(|p| p.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            [
+                "FLOWSCOPE_BREAKER_THRESHOLD",
+                "FLOWSCOPE_FORWARD_BIND",
+                "/nonexistent/certs/ca.pem",
+                "/nonexistent/certs/cert.pem",
+                "/nonexistent/certs/key.pem",
+                "DOCKER_TLS_VERIFY",
+            ]
+        );
+        assert_eq!(report.errors(), 4);
+    }
+}
@@ -0,0 +1,452 @@
+//! Vulnerability scans
+//!
+//! With a scanner named in the configuration, a background task runs Trivy
+//! or Grype against the image of every running container and keeps the
+//! reports in `vulnerability-scans.json`. Reports are keyed by image id, so
+//! replicas share one scan and a tag that moved to a new image is scanned
+//! again. An image is rescanned once its report is older than the configured
+//! interval, and reports of images no container uses any more are dropped.
+//! Scans run one at a time since each can take minutes of CPU and may first
+//! download the scanner's database.
+//!
+//! `GET /api/container/:id/vulnerabilities` serves the report of a
+//! container's image; containers and their flowchart nodes list its counts
+//! by severity.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    process::Stdio,
+    sync::Mutex,
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    config::ScanSettings,
+    models::{
+        ContainerInfo, ContainerStatus, Scanner, Vulnerability, VulnerabilityReport, VulnerabilitySeverity,
+        VulnerabilitySummary,
+    },
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+/// How often running containers are checked for images without a fresh scan
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, thiserror::Error)]
+pub enum VulnerabilityError {
+    #[error("vulnerability scanning is turned off in the configuration")]
+    Disabled,
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("the image of '{0}' has not been scanned yet")]
+    NotScanned(String),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl IntoResponse for VulnerabilityError {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self {
+            VulnerabilityError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::NOT_FOUND,
+        };
+        (status, Json(serde_json::json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScanError {
+    #[error("failed to run {0}: {1}")]
+    Spawn(String, std::io::Error),
+    #[error("no result within {}s", .0.as_secs())]
+    Timeout(Duration),
+    #[error("scanner failed: {0}")]
+    Failed(String),
+    #[error("unreadable scanner output: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TrivyReport {
+    #[serde(default)]
+    results: Vec<TrivyResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TrivyResult {
+    #[serde(default)]
+    vulnerabilities: Option<Vec<TrivyVulnerability>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TrivyVulnerability {
+    #[serde(rename = "VulnerabilityID")]
+    vulnerability_id: String,
+    pkg_name: String,
+    #[serde(default)]
+    installed_version: String,
+    #[serde(default)]
+    fixed_version: Option<String>,
+    #[serde(default)]
+    severity: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GrypeReport {
+    #[serde(default)]
+    matches: Vec<GrypeMatch>,
+}
+
+#[derive(Deserialize)]
+struct GrypeMatch {
+    vulnerability: GrypeVulnerability,
+    artifact: GrypeArtifact,
+}
+
+#[derive(Deserialize)]
+struct GrypeVulnerability {
+    id: String,
+    #[serde(default)]
+    severity: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    fix: Option<GrypeFix>,
+}
+
+#[derive(Deserialize)]
+struct GrypeFix {
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GrypeArtifact {
+    name: String,
+    #[serde(default)]
+    version: String,
+}
+
+/// Grype calls the lowest severity `Negligible`
+fn severity(name: &str) -> VulnerabilitySeverity {
+    match name.to_ascii_lowercase().as_str() {
+        "critical" => VulnerabilitySeverity::Critical,
+        "high" => VulnerabilitySeverity::High,
+        "medium" => VulnerabilitySeverity::Medium,
+        "low" | "negligible" => VulnerabilitySeverity::Low,
+        _ => VulnerabilitySeverity::Unknown,
+    }
+}
+
+/// Vulnerabilities in the JSON a scanner printed, most severe first; a
+/// package listed under several targets counts once
+pub fn parse(scanner: Scanner, output: &[u8]) -> Result<Vec<Vulnerability>, serde_json::Error> {
+    let mut found: Vec<Vulnerability> = match scanner {
+        Scanner::Trivy => serde_json::from_slice::<TrivyReport>(output)?
+            .results
+            .into_iter()
+            .flat_map(|r| r.vulnerabilities.unwrap_or_default())
+            .map(|v| Vulnerability {
+                severity: severity(&v.severity),
+                id: v.vulnerability_id,
+                package: v.pkg_name,
+                installed_version: v.installed_version,
+                fixed_version: v.fixed_version.filter(|f| !f.is_empty()),
+                title: v.title,
+            })
+            .collect(),
+        Scanner::Grype => serde_json::from_slice::<GrypeReport>(output)?
+            .matches
+            .into_iter()
+            .map(|m| Vulnerability {
+                severity: severity(&m.vulnerability.severity),
+                id: m.vulnerability.id,
+                package: m.artifact.name,
+                installed_version: m.artifact.version,
+                fixed_version: m.vulnerability.fix.and_then(|f| f.versions.into_iter().next()),
+                title: m.vulnerability.description,
+            })
+            .collect(),
+    };
+    found.sort_by(|a, b| {
+        (b.severity, &a.id, &a.package, &a.installed_version).cmp(&(a.severity, &b.id, &b.package, &b.installed_version))
+    });
+    found.dedup_by(|a, b| a.id == b.id && a.package == b.package && a.installed_version == b.installed_version);
+    Ok(found)
+}
+
+pub fn summarize(vulnerabilities: &[Vulnerability]) -> VulnerabilitySummary {
+    let mut summary = VulnerabilitySummary::default();
+    for vulnerability in vulnerabilities {
+        *match vulnerability.severity {
+            VulnerabilitySeverity::Critical => &mut summary.critical,
+            VulnerabilitySeverity::High => &mut summary.high,
+            VulnerabilitySeverity::Medium => &mut summary.medium,
+            VulnerabilitySeverity::Low => &mut summary.low,
+            VulnerabilitySeverity::Unknown => &mut summary.unknown,
+        } += 1;
+        if vulnerability.fixed_version.is_some() {
+            summary.fixable += 1;
+        }
+    }
+    summary
+}
+
+/// Arguments asking `scanner` for a JSON report on `image` and nothing else
+fn arguments(scanner: Scanner, image: &str) -> Vec<&str> {
+    match scanner {
+        Scanner::Trivy => vec!["image", "--quiet", "--format", "json", image],
+        Scanner::Grype => vec![image, "--output", "json", "--quiet"],
+    }
+}
+
+/// Scan `image` with the configured scanner
+pub async fn scan(
+    settings: &ScanSettings,
+    scanner: Scanner,
+    image: &str,
+    image_id: &str,
+) -> Result<VulnerabilityReport, ScanError> {
+    let child = tokio::process::Command::new(&settings.command)
+        .args(arguments(scanner, image))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| ScanError::Spawn(settings.command.clone(), e))?;
+    let output = tokio::time::timeout(settings.timeout, child.wait_with_output())
+        .await
+        .map_err(|_| ScanError::Timeout(settings.timeout))?
+        .map_err(|e| ScanError::Spawn(settings.command.clone(), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no output");
+        return Err(ScanError::Failed(format!("{} ({})", reason.trim(), output.status)));
+    }
+    let vulnerabilities = parse(scanner, &output.stdout)?;
+    Ok(VulnerabilityReport {
+        image: image.to_string(),
+        image_id: image_id.to_string(),
+        scanner,
+        scanned_at: Utc::now(),
+        summary: summarize(&vulnerabilities),
+        vulnerabilities,
+    })
+}
+
+/// Vulnerability counts of each running container's image, by container name
+#[derive(Default)]
+pub struct VulnerabilityTable {
+    summaries: Mutex<HashMap<String, VulnerabilitySummary>>,
+}
+
+impl VulnerabilityTable {
+    /// Replace every summary with `summaries`
+    pub fn replace(&self, summaries: HashMap<String, VulnerabilitySummary>) {
+        *self.summaries.lock().unwrap() = summaries;
+    }
+
+    /// Set the latest counts on each listed container
+    pub fn apply(&self, containers: &mut [ContainerInfo]) {
+        let summaries = self.summaries.lock().unwrap();
+        for container in containers {
+            container.vulnerabilities = summaries.get(&container.name).cloned();
+        }
+    }
+}
+
+/// Scan reports by image id, persisted across restarts
+pub struct VulnerabilityStore {
+    reports: JsonStore<VulnerabilityReport>,
+}
+
+impl VulnerabilityStore {
+    pub fn open(file_name: &str) -> Self {
+        Self {
+            reports: JsonStore::open(file_name),
+        }
+    }
+
+    pub fn get(&self, image_id: &str) -> Option<VulnerabilityReport> {
+        self.reports.get(image_id)
+    }
+
+    /// Whether the image has a report younger than `interval`
+    pub fn is_fresh(&self, image_id: &str, interval: Duration) -> bool {
+        let max_age = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX);
+        self.get(image_id).is_some_and(|r| Utc::now() - r.scanned_at < max_age)
+    }
+
+    pub fn put(&self, report: VulnerabilityReport) -> Result<(), StoreError> {
+        self.reports.put(&report.image_id.clone(), report)?;
+        Ok(())
+    }
+
+    /// Drop the reports of images not in `image_ids`
+    pub fn retain(&self, image_ids: &HashSet<&String>) -> Result<(), StoreError> {
+        for id in self.reports.keys() {
+            if !image_ids.contains(&id) {
+                self.reports.remove(&id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scan the images of running containers that lack a fresh report, then
+/// publish the counts per container
+async fn scan_running(state: &AppState, scanner: Scanner) -> Result<(), bollard::errors::Error> {
+    let settings = &state.config.vulnerabilities;
+    let (containers, image_ids) =
+        futures_util::future::try_join(state.docker.list_containers(), state.docker.image_ids()).await?;
+    let running: Vec<(&ContainerInfo, &String)> = containers
+        .iter()
+        .filter(|c| matches!(c.status, ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy))
+        .filter_map(|c| Some((c, image_ids.get(&c.id)?)))
+        .collect();
+
+    // One scan per image, under the reference its first container names
+    let mut images: BTreeMap<&String, &str> = BTreeMap::new();
+    for (container, image_id) in &running {
+        images.entry(image_id).or_insert(&container.image);
+    }
+    for (image_id, image) in images {
+        if state.vulnerabilities.is_fresh(image_id, settings.interval) {
+            continue;
+        }
+        match scan(settings, scanner, image, image_id).await {
+            Ok(report) => {
+                let summary = &report.summary;
+                info!(
+                    "Scanned {}: {} critical, {} high, {} medium, {} low",
+                    image, summary.critical, summary.high, summary.medium, summary.low
+                );
+                if let Err(e) = state.vulnerabilities.put(report) {
+                    warn!("Failed to save the vulnerability scan of {}: {}", image, e);
+                }
+            }
+            Err(e) => warn!("Failed to scan {} for vulnerabilities: {}", image, e),
+        }
+    }
+
+    if let Err(e) = state.vulnerabilities.retain(&image_ids.values().collect()) {
+        warn!("Failed to drop old vulnerability scans: {}", e);
+    }
+    let summaries = running
+        .into_iter()
+        .filter_map(|(container, image_id)| {
+            Some((container.name.clone(), state.vulnerabilities.get(image_id)?.summary))
+        })
+        .collect();
+    state.docker.vulnerabilities().replace(summaries);
+    Ok(())
+}
+
+/// Spawn the background task that scans the images of running containers
+pub fn spawn_scanner(state: AppState, scanner: Scanner) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = scan_running(&state, scanner).await {
+                warn!("Failed to list containers to scan: {}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// GET /api/container/:id/vulnerabilities - The latest scan of the
+/// container's image
+pub async fn get_vulnerabilities(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<VulnerabilityReport>, VulnerabilityError> {
+    if state.config.vulnerabilities.scanner.is_none() {
+        return Err(VulnerabilityError::Disabled);
+    }
+    let container = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or(VulnerabilityError::ContainerNotFound(id))?;
+    state
+        .docker
+        .image_ids()
+        .await?
+        .get(&container.id)
+        .and_then(|image_id| state.vulnerabilities.get(image_id))
+        .map(Json)
+        .ok_or(VulnerabilityError::NotScanned(container.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_trivy_and_grype_reports() {
+        let trivy = serde_json::json!({
+            "SchemaVersion": 2,
+            "Results": [
+                {
+                    "Target": "postgres:16 (debian 12.5)",
+                    "Vulnerabilities": [
+                        {"VulnerabilityID": "CVE-2024-2511", "PkgName": "libssl3", "InstalledVersion": "3.0.11-1", "FixedVersion": "3.0.13-1", "Severity": "LOW", "Title": "openssl: unbounded memory growth"},
+                        {"VulnerabilityID": "CVE-2023-45853", "PkgName": "zlib1g", "InstalledVersion": "1:1.2.13", "FixedVersion": "", "Severity": "CRITICAL"}
+                    ]
+                },
+                {"Target": "usr/local/bin/gosu", "Vulnerabilities": null},
+                {
+                    "Target": "usr/lib/x86_64-linux-gnu",
+                    "Vulnerabilities": [
+                        {"VulnerabilityID": "CVE-2023-45853", "PkgName": "zlib1g", "InstalledVersion": "1:1.2.13", "Severity": "CRITICAL"}
+                    ]
+                }
+            ]
+        });
+        let found = parse(Scanner::Trivy, trivy.to_string().as_bytes()).unwrap();
+        assert_eq!(found.iter().map(|v| v.id.as_str()).collect::<Vec<_>>(), ["CVE-2023-45853", "CVE-2024-2511"]);
+        assert_eq!(found[0].fixed_version, None);
+        assert_eq!(
+            summarize(&found),
+            VulnerabilitySummary {
+                critical: 1,
+                low: 1,
+                fixable: 1,
+                ..Default::default()
+            }
+        );
+
+        let grype = serde_json::json!({
+            "matches": [
+                {"vulnerability": {"id": "GHSA-j8xg-fqg3-53r7", "severity": "Medium", "fix": {"versions": ["2.1.2"], "state": "fixed"}}, "artifact": {"name": "word-wrap", "version": "1.2.3"}},
+                {"vulnerability": {"id": "CVE-2005-2541", "severity": "Negligible", "fix": {"versions": [], "state": "wont-fix"}}, "artifact": {"name": "tar", "version": "1.34"}},
+                {"vulnerability": {"id": "CVE-2024-0001", "severity": "Unknown"}, "artifact": {"name": "busybox", "version": "1.36"}}
+            ]
+        });
+        let found = parse(Scanner::Grype, grype.to_string().as_bytes()).unwrap();
+        assert_eq!(found[0].fixed_version.as_deref(), Some("2.1.2"));
+        let summary = summarize(&found);
+        assert_eq!((summary.medium, summary.low, summary.unknown, summary.fixable), (1, 1, 1, 1));
+
+        assert!(parse(Scanner::Grype, b"Vulnerability DB update failed").is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Warm-up requests
+//!
+//! A service's warm-up profile lists paths FlowScope requests after the
+//! service is restarted through `POST /api/container/:id/restart`, so caches
+//! and JITs are primed and the first real user does not pay for the cold
+//! start. The restart answers once the warm-up finished, with the report next
+//! to the action result; `?warmup=false` skips it. Profiles are kept per
+//! service (see [`service_key`]) in `warmup-profiles.json`, and requests go to
+//! the container's own address, so the port need not be published.
+
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use futures_util::{stream, StreamExt};
+use tokio::net::TcpStream;
+use tracing::{error, info};
+
+use crate::{
+    image_history::service_key,
+    models::{ContainerInfo, WarmupPathResult, WarmupProfile, WarmupReport},
+    store::{JsonStore, StoreError},
+    AppState,
+};
+
+pub const MAX_PATHS: usize = 20;
+/// Most requests sent to one path
+pub const MAX_COUNT: u32 = 1000;
+pub const MAX_CONCURRENCY: u32 = 32;
+pub const MAX_READY_TIMEOUT_SECS: u64 = 300;
+/// Longest a single warm-up request may take, body included
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause between connection attempts while waiting for the port
+const READY_POLL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum WarmupError {
+    #[error("container '{0}' not found")]
+    ContainerNotFound(String),
+    #[error("no warm-up profile for {0}")]
+    NoProfile(String),
+    #[error("invalid warm-up profile: {0}")]
+    Invalid(String),
+    #[error("{0} has no address FlowScope can reach")]
+    NoAddress(String),
+    #[error("failed to save warm-up profiles: {0}")]
+    Store(#[from] StoreError),
+    #[error("docker error: {0}")]
+    Docker(#[from] bollard::errors::Error),
+}
+
+impl WarmupError {
+    fn status(&self) -> StatusCode {
+        match self {
+            WarmupError::ContainerNotFound(_) | WarmupError::NoProfile(_) => StatusCode::NOT_FOUND,
+            WarmupError::Invalid(_) => StatusCode::BAD_REQUEST,
+            WarmupError::NoAddress(_) => StatusCode::CONFLICT,
+            WarmupError::Store(_) | WarmupError::Docker(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for WarmupError {
+    fn into_response(self) -> axum::response::Response {
+        if self.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Warm-up error: {}", self);
+        }
+        (
+            self.status(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Warm-up profiles by service, and the client that sends the requests
+pub struct Warmups {
+    profiles: JsonStore<WarmupProfile>,
+    http: reqwest::Client,
+}
+
+impl Warmups {
+    pub fn open(file_name: &str) -> Self {
+        Self::with_store(JsonStore::open(file_name))
+    }
+
+    fn with_store(profiles: JsonStore<WarmupProfile>) -> Self {
+        Self {
+            profiles,
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build the warm-up HTTP client"),
+        }
+    }
+
+    /// The profile of `container`'s service
+    pub fn profile(&self, container: &ContainerInfo) -> Option<WarmupProfile> {
+        self.profiles.get(&service_key(container))
+    }
+
+    /// Wait for `address` to accept connections, then send the profile's
+    /// requests to it
+    pub async fn warm_up(&self, container: &str, address: SocketAddr, profile: &WarmupProfile) -> WarmupReport {
+        let started = Instant::now();
+        let base_url = format!("http://{}", address);
+        let ready = wait_ready(address, Duration::from_secs(profile.ready_timeout_secs)).await;
+        let ready_ms = millis(started.elapsed());
+        if let Err(error) = ready {
+            return WarmupReport {
+                container: container.to_string(),
+                base_url,
+                ready_ms,
+                paths: Vec::new(),
+                duration_ms: ready_ms,
+                error: Some(error),
+            };
+        }
+
+        // Owned so the requests' futures stay Send inside handlers
+        let requests: Vec<(String, reqwest::RequestBuilder)> = profile
+            .paths
+            .iter()
+            .flat_map(|path| std::iter::repeat_n(path, profile.count as usize))
+            .map(|path| (path.clone(), self.http.get(format!("{}{}", base_url, path))))
+            .collect();
+        let outcomes: Vec<(String, Result<f64, String>)> = stream::iter(requests)
+            .map(|(path, request)| async move {
+                let sent = Instant::now();
+                let outcome = match request.send().await {
+                    Ok(response) if response.status().is_success() || response.status().is_redirection() => {
+                        // Reading the body is part of what gets warmed
+                        match response.bytes().await {
+                            Ok(_) => Ok(millis(sent.elapsed())),
+                            Err(e) => Err(e.to_string()),
+                        }
+                    }
+                    Ok(response) => Err(format!("answered {}", response.status())),
+                    Err(e) => Err(e.to_string()),
+                };
+                (path, outcome)
+            })
+            .buffer_unordered(profile.concurrency as usize)
+            .collect()
+            .await;
+
+        WarmupReport {
+            container: container.to_string(),
+            base_url,
+            ready_ms,
+            paths: profile.paths.iter().map(|path| summarize(path, &outcomes)).collect(),
+            duration_ms: millis(started.elapsed()),
+            error: None,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Try to connect until `address` accepts or `timeout` passes
+async fn wait_ready(address: SocketAddr, timeout: Duration) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let last_error = match tokio::time::timeout(READY_POLL, TcpStream::connect(address)).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "connection timed out".to_string(),
+        };
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "port {} did not open within {}s: {}",
+                address.port(),
+                timeout.as_secs(),
+                last_error
+            ));
+        }
+        tokio::time::sleep(READY_POLL).await;
+    }
+}
+
+fn summarize(path: &str, outcomes: &[(String, Result<f64, String>)]) -> WarmupPathResult {
+    let mine: Vec<&Result<f64, String>> = outcomes.iter().filter(|(p, _)| *p == path).map(|(_, o)| o).collect();
+    let times: Vec<f64> = mine.iter().filter_map(|o| o.as_ref().ok().copied()).collect();
+    WarmupPathResult {
+        path: path.to_string(),
+        requests: mine.len() as u32,
+        succeeded: times.len() as u32,
+        avg_ms: if times.is_empty() { 0.0 } else { times.iter().sum::<f64>() / times.len() as f64 },
+        max_ms: times.iter().copied().fold(0.0, f64::max),
+        last_error: mine.iter().rev().find_map(|o| o.as_ref().err().cloned()),
+    }
+}
+
+pub fn validate(profile: &WarmupProfile) -> Result<(), WarmupError> {
+    let invalid = |message: String| Err(WarmupError::Invalid(message));
+    if profile.port == 0 {
+        return invalid("port must be positive".to_string());
+    }
+    if profile.paths.is_empty() || profile.paths.len() > MAX_PATHS {
+        return invalid(format!("1-{} paths are needed", MAX_PATHS));
+    }
+    if let Some(path) = profile.paths.iter().find(|p| !p.starts_with('/')) {
+        return invalid(format!("path '{}' must start with '/'", path));
+    }
+    if !(1..=MAX_COUNT).contains(&profile.count) {
+        return invalid(format!("count must be 1-{}", MAX_COUNT));
+    }
+    if !(1..=MAX_CONCURRENCY).contains(&profile.concurrency) {
+        return invalid(format!("concurrency must be 1-{}", MAX_CONCURRENCY));
+    }
+    if !(1..=MAX_READY_TIMEOUT_SECS).contains(&profile.ready_timeout_secs) {
+        return invalid(format!("readyTimeoutSecs must be 1-{}", MAX_READY_TIMEOUT_SECS));
+    }
+    Ok(())
+}
+
+async fn container(state: &AppState, id: &str) -> Result<ContainerInfo, WarmupError> {
+    state
+        .docker
+        .get_container(id)
+        .await?
+        .ok_or_else(|| WarmupError::ContainerNotFound(id.to_string()))
+}
+
+/// Warm `container` up with `profile`, reaching it at its own address
+async fn run(state: &AppState, container: &ContainerInfo, profile: &WarmupProfile) -> Result<WarmupReport, WarmupError> {
+    let ip = state
+        .docker
+        .container_address(&container.id)
+        .await?
+        .ok_or_else(|| WarmupError::NoAddress(container.name.clone()))?;
+    let report = state
+        .warmups
+        .warm_up(&container.name, SocketAddr::new(ip, profile.port), profile)
+        .await;
+    let requests: u32 = report.paths.iter().map(|p| p.requests).sum();
+    let succeeded: u32 = report.paths.iter().map(|p| p.succeeded).sum();
+    info!(
+        "Warmed up {} with {}/{} successful requests in {:.0}ms",
+        container.name, succeeded, requests, report.duration_ms
+    );
+    Ok(report)
+}
+
+/// Warm a just-restarted container up, if its service has a profile
+pub async fn after_restart(state: &AppState, id: &str) -> Result<Option<WarmupReport>, WarmupError> {
+    let container = container(state, id).await?;
+    match state.warmups.profile(&container) {
+        Some(profile) => Ok(Some(run(state, &container, &profile).await?)),
+        None => Ok(None),
+    }
+}
+
+/// GET /api/container/:id/warmup - The warm-up profile of the container's service
+pub async fn get_profile(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WarmupProfile>, WarmupError> {
+    let container = container(&state, &id).await?;
+    state
+        .warmups
+        .profile(&container)
+        .map(Json)
+        .ok_or_else(|| WarmupError::NoProfile(service_key(&container)))
+}
+
+/// PUT /api/container/:id/warmup - Set the warm-up profile of the container's service
+pub async fn put_profile(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(profile): Json<WarmupProfile>,
+) -> Result<Json<WarmupProfile>, WarmupError> {
+    validate(&profile)?;
+    let container = container(&state, &id).await?;
+    state.warmups.profiles.put(&service_key(&container), profile.clone())?;
+    Ok(Json(profile))
+}
+
+/// DELETE /api/container/:id/warmup - Stop warming the container's service up
+pub async fn delete_profile(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, WarmupError> {
+    let container = container(&state, &id).await?;
+    let service = service_key(&container);
+    match state.warmups.profiles.remove(&service)? {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(WarmupError::NoProfile(service)),
+    }
+}
+
+/// POST /api/container/:id/warmup - Send the warm-up requests now
+pub async fn run_warmup(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WarmupReport>, WarmupError> {
+    let container = container(&state, &id).await?;
+    let profile = state
+        .warmups
+        .profile(&container)
+        .ok_or_else(|| WarmupError::NoProfile(service_key(&container)))?;
+    Ok(Json(run(&state, &container, &profile).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+
+    fn profile(paths: &[&str]) -> WarmupProfile {
+        WarmupProfile {
+            port: 8080,
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            count: 3,
+            concurrency: 2,
+            ready_timeout_secs: 5,
+        }
+    }
+
+    #[test]
+    fn profiles_are_validated() {
+        assert!(validate(&profile(&["/", "/api/products?page=1"])).is_ok());
+        assert!(validate(&profile(&[])).is_err());
+        assert!(validate(&profile(&["health"])).is_err());
+        assert!(validate(&WarmupProfile {
+            concurrency: MAX_CONCURRENCY + 1,
+            ..profile(&["/"])
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn every_path_is_requested_count_times() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().route("/", get(|| async { "warm" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let dir = std::env::temp_dir().join(format!("flowscope-warmup-{}", std::process::id()));
+        let warmups = Warmups::with_store(JsonStore::open_at(dir.join("warmup-profiles.json")));
+        let report = warmups.warm_up("web", address, &profile(&["/", "/missing"])).await;
+
+        assert!(report.error.is_none());
+        let counts: Vec<(&str, u32, u32)> = report
+            .paths
+            .iter()
+            .map(|p| (p.path.as_str(), p.requests, p.succeeded))
+            .collect();
+        assert_eq!(counts, [("/", 3, 3), ("/missing", 3, 0)]);
+        assert_eq!(report.paths[1].last_error.as_deref(), Some("answered 404 Not Found"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
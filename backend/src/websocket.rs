@@ -1,108 +1,739 @@
 //! WebSocket handler for real-time container updates
 //!
-//! Broadcasts container status changes to connected clients
+//! Broadcasts container status changes to connected clients.
+//!
+//! A single publisher task lists containers whenever Docker events announce a
+//! change, or polls adaptively when events can't be relied on (see
+//! [`crate::update_feed`]), and pushes sequenced messages into the [`WsHub`],
+//! which buffers recent messages per topic. Every client receives a resume
+//! token on connect; reconnecting with `?resume_token=<token>` replays the
+//! messages it missed instead of forcing a full-state reload.
+//!
+//! Clients choose how often they want updates with a `setInterval` control
+//! message (e.g. 1s while focused, 30s while backgrounded). Updates are batched
+//! per client; the publisher publishes as soon as an event announces a change,
+//! and otherwise polls no faster than the most eager client.
+//!
+//! `watchLogs` registers a pattern against one container's logs; matching lines
+//! are pushed as `logMatch` messages outside the batching and replay machinery.
+//! It needs the `logs:read` scope.
+//! `action` runs a container action and answers with an `actionResult`, also
+//! outside the batching; it needs the `containers:actions` scope.
+//! `attachRun` starts a one-off run and streams its output, and `runInput`
+//! feeds its stdin; both need the `exec` scope.
+//!
+//! Interactive terminals and live log streams have sockets of their own,
+//! `/ws/exec/:id` and `/ws/logs/:id`; see [`terminal`] and [`log_stream`].
 
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Extension, Query, State,
     },
     response::IntoResponse,
 };
-use futures::{SinkExt, StreamExt};
-use serde::Serialize;
-use std::time::Duration;
-use tokio::time::interval;
-use tracing::{debug, error, info};
-
-use crate::{models::ContainerInfo, AppState};
-
-#[derive(Debug, Clone, Serialize)]
-#[serde(tag = "type", rename_all = "camelCase")]
-pub enum WsMessage {
-    #[serde(rename_all = "camelCase")]
-    ContainerUpdate {
-        containers: Vec<ContainerInfo>,
-        timestamp: String,
-    },
-    #[serde(rename_all = "camelCase")]
-    TopologyUpdate {
-        total_containers: usize,
-        running_containers: usize,
-        healthy_containers: usize,
-        unhealthy_containers: usize,
-        timestamp: String,
-    },
-    Heartbeat {
-        timestamp: String,
-    },
+use futures::{Sink, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{broadcast, watch},
+    time::{interval, MissedTickBehavior},
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    auth::{ApiToken, Authenticator, Caller},
+    models::{ApiScope, ContainerInfo},
+    provider::DiscoveryProvider,
+    usage::UsageMeter,
+    AppState,
+};
+
+mod actions;
+mod log_stream;
+mod log_watch;
+mod runs;
+mod terminal;
+
+use actions::ContainerActions;
+pub use log_stream::log_stream_handler;
+use log_watch::LogWatches;
+use runs::RunAttachments;
+pub use terminal::terminal_handler;
+
+pub use flowscope_types::ws::{RunStream, WsAction, WsClientMessage, WsEnvelope, WsMessage, WsTopic};
+
+/// Update interval for clients that never asked for one, unless configured
+const DEFAULT_CLIENT_INTERVAL: Duration = Duration::from_secs(5);
+/// Fastest update interval a client may request
+const MIN_CLIENT_INTERVAL: Duration = Duration::from_secs(1);
+/// Slowest update interval a client may request
+const MAX_CLIENT_INTERVAL: Duration = Duration::from_secs(300);
+/// Keepalive sent to every client regardless of activity
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a disconnected client's resume token stays valid
+const RESUME_TTL: Duration = Duration::from_secs(300);
+/// Maximum number of container deltas retained for replay
+const CONTAINER_BUFFER_SIZE: usize = 256;
+/// Maximum number of alert changes retained for replay
+const ALERT_BUFFER_SIZE: usize = 64;
+
+/// Topology updates are full snapshots, so only the latest is worth replaying.
+/// Container updates and alerts are deltas and must all be replayed in order.
+fn buffer_size(topic: WsTopic) -> usize {
+    match topic {
+        WsTopic::Topology => 1,
+        WsTopic::Containers => CONTAINER_BUFFER_SIZE,
+        WsTopic::Alerts => ALERT_BUFFER_SIZE,
+    }
+}
+
+#[derive(Default)]
+struct TopicBuffer {
+    messages: VecDeque<WsEnvelope>,
+    /// Highest sequence number dropped from this buffer
+    evicted_through: u64,
+}
+
+struct ResumeSession {
+    last_seq: u64,
+    disconnected_at: Option<Instant>,
+    interval: Duration,
+}
+
+#[derive(Default)]
+struct HubState {
+    seq: u64,
+    buffers: HashMap<WsTopic, TopicBuffer>,
+    sessions: HashMap<String, ResumeSession>,
+    containers: BTreeMap<String, ContainerInfo>,
+    topology: Option<WsEnvelope>,
+}
+
+/// Result of attaching a client to the hub
+pub struct Attachment {
+    pub resume_token: String,
+    pub resumed: bool,
+    pub seq: u64,
+    /// Update interval carried over from a resumed session
+    pub interval: Duration,
+    /// Messages to send before anything from `receiver`
+    pub backlog: Vec<WsEnvelope>,
+    pub receiver: broadcast::Receiver<WsEnvelope>,
+}
+
+/// Fan-out point for WebSocket messages with per-topic replay buffers
+pub struct WsHub {
+    sender: broadcast::Sender<WsEnvelope>,
+    state: Mutex<HubState>,
+    /// Sequence number of the latest container delta, i.e. the topology version
+    topology_version: watch::Sender<u64>,
+    /// Update interval for clients that never asked for one
+    default_interval: Duration,
+}
+
+impl Default for WsHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WsHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CONTAINER_BUFFER_SIZE);
+        Self {
+            sender,
+            state: Mutex::new(HubState::default()),
+            topology_version: watch::Sender::new(0),
+            default_interval: DEFAULT_CLIENT_INTERVAL,
+        }
+    }
+
+    /// Use `interval`, clamped to the allowed range, for clients that never
+    /// ask for one
+    pub fn with_default_interval(mut self, interval: Duration) -> Self {
+        self.default_interval = interval.clamp(MIN_CLIENT_INTERVAL, MAX_CLIENT_INTERVAL);
+        self
+    }
+
+    /// Assign a sequence number, buffer and broadcast a message
+    pub fn publish(&self, topic: WsTopic, message: WsMessage) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        Self::publish_locked(&mut state, &self.sender, topic, message)
+    }
+
+    fn publish_locked(
+        state: &mut HubState,
+        sender: &broadcast::Sender<WsEnvelope>,
+        topic: WsTopic,
+        message: WsMessage,
+    ) -> u64 {
+        state.seq += 1;
+        let envelope = WsEnvelope {
+            seq: state.seq,
+            topic,
+            message,
+        };
+
+        let buffer = state.buffers.entry(topic).or_default();
+        buffer.messages.push_back(envelope.clone());
+        while buffer.messages.len() > buffer_size(topic) {
+            if let Some(evicted) = buffer.messages.pop_front() {
+                buffer.evicted_through = evicted.seq;
+            }
+        }
+
+        if topic == WsTopic::Topology {
+            state.topology = Some(envelope.clone());
+        }
+
+        // No receivers is not an error; the message stays buffered for replay
+        let _ = sender.send(envelope);
+        state.seq
+    }
+
+    /// Diff the current container list against the last published state and
+    /// publish a delta if anything changed
+    /// Publish what changed since the last call, returning whether anything did
+    pub fn publish_containers(&self, containers: Vec<ContainerInfo>) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        let current: BTreeMap<String, ContainerInfo> = containers
+            .into_iter()
+            .map(|c| (c.id.clone(), c))
+            .collect();
+
+        let changed: Vec<ContainerInfo> = current
+            .values()
+            .filter(|c| state.containers.get(&c.id) != Some(*c))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = state
+            .containers
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect();
+
+        state.containers = current;
+
+        if changed.is_empty() && removed.is_empty() {
+            return false;
+        }
+
+        debug!("Publishing container delta: {} changed, {} removed", changed.len(), removed.len());
+        let seq = Self::publish_locked(
+            &mut state,
+            &self.sender,
+            WsTopic::Containers,
+            WsMessage::ContainerUpdate {
+                containers: changed,
+                removed,
+                full: false,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+        self.topology_version.send_replace(seq);
+        true
+    }
+
+    /// Containers as of the last publish
+    pub fn containers(&self) -> Vec<ContainerInfo> {
+        self.state.lock().unwrap().containers.values().cloned().collect()
+    }
+
+    /// Wait until the topology version exceeds `after` or `timeout` passes,
+    /// returning the version then. Version 0 means nothing was published yet.
+    pub async fn wait_for_topology(&self, after: u64, timeout: Duration) -> u64 {
+        let mut version = self.topology_version.subscribe();
+        let _ = tokio::time::timeout(timeout, version.wait_for(|v| *v > after)).await;
+        let current = *version.borrow();
+        current
+    }
+
+    /// Register a client, resuming an existing session when possible
+    pub fn attach(&self, resume_token: Option<&str>) -> Attachment {
+        let mut state = self.state.lock().unwrap();
+        Self::prune_sessions(&mut state);
+
+        let receiver = self.sender.subscribe();
+        let seq = state.seq;
+
+        let resumable = resume_token.and_then(|token| {
+            let session = state.sessions.get(token)?;
+            Self::replayable(&state, session.last_seq).then(|| (token.to_string(), session.last_seq, session.interval))
+        });
+
+        let interval = resumable
+            .as_ref()
+            .map_or(self.default_interval, |(_, _, interval)| *interval);
+
+        // The session only moves past what the client was actually sent, so a
+        // connection that drops mid-backlog resumes where it stopped
+        let (token, resumed, last_seq, backlog) = match resumable {
+            Some((token, last_seq, _)) => (token, true, last_seq, Self::backlog_locked(&state, last_seq)),
+            None => {
+                let token = uuid::Uuid::new_v4().to_string();
+                (token, false, 0, Self::snapshot_locked(&state))
+            }
+        };
+
+        state.sessions.insert(
+            token.clone(),
+            ResumeSession {
+                last_seq,
+                disconnected_at: None,
+                interval,
+            },
+        );
+
+        Attachment {
+            resume_token: token,
+            resumed,
+            seq,
+            interval,
+            backlog,
+            receiver,
+        }
+    }
+
+    /// Messages after `last_seq` plus a receiver for new ones, for clients
+    /// that track sequence numbers themselves. Without `last_seq`, or when
+    /// messages after it are gone, the backlog is a snapshot instead.
+    pub fn subscribe(&self, last_seq: Option<u64>) -> (Vec<WsEnvelope>, broadcast::Receiver<WsEnvelope>) {
+        let state = self.state.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let backlog = match last_seq {
+            Some(last_seq) if last_seq <= state.seq && Self::replayable(&state, last_seq) => {
+                Self::backlog_locked(&state, last_seq)
+            }
+            _ => Self::snapshot_locked(&state),
+        };
+        (backlog, receiver)
+    }
+
+    /// Whether every delta after `last_seq` is still buffered
+    fn replayable(state: &HubState, last_seq: u64) -> bool {
+        WsTopic::ALL.iter().all(|topic| {
+            topic.is_snapshot()
+                || state
+                    .buffers
+                    .get(topic)
+                    .is_none_or(|b| b.evicted_through <= last_seq)
+        })
+    }
+
+    fn backlog_locked(state: &HubState, last_seq: u64) -> Vec<WsEnvelope> {
+        let mut backlog: Vec<WsEnvelope> = state
+            .buffers
+            .values()
+            .flat_map(|b| b.messages.iter())
+            .filter(|e| e.seq > last_seq)
+            .cloned()
+            .collect();
+        backlog.sort_by_key(|e| e.seq);
+        backlog
+    }
+
+    /// Full current state, stamped with the latest sequence number
+    pub fn snapshot(&self) -> Vec<WsEnvelope> {
+        let state = self.state.lock().unwrap();
+        Self::snapshot_locked(&state)
+    }
+
+    fn snapshot_locked(state: &HubState) -> Vec<WsEnvelope> {
+        let mut snapshot = Vec::new();
+        if let Some(topology) = &state.topology {
+            snapshot.push(topology.clone());
+        }
+        if !state.containers.is_empty() {
+            snapshot.push(WsEnvelope {
+                seq: state.seq,
+                topic: WsTopic::Containers,
+                message: WsMessage::ContainerUpdate {
+                    containers: state.containers.values().cloned().collect(),
+                    removed: Vec::new(),
+                    full: true,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                },
+            });
+        }
+        snapshot
+    }
+
+    /// Record the last sequence number delivered to a client
+    pub fn ack(&self, resume_token: &str, seq: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.sessions.get_mut(resume_token) {
+            session.last_seq = session.last_seq.max(seq);
+        }
+    }
+
+    /// Mark a client as disconnected; its token stays valid for `RESUME_TTL`
+    pub fn detach(&self, resume_token: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.sessions.get_mut(resume_token) {
+            session.disconnected_at = Some(Instant::now());
+        }
+    }
+
+    /// Change a client's update interval, clamped to the allowed range
+    pub fn set_interval(&self, resume_token: &str, requested: Duration) -> Duration {
+        let applied = requested.clamp(MIN_CLIENT_INTERVAL, MAX_CLIENT_INTERVAL);
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.sessions.get_mut(resume_token) {
+            session.interval = applied;
+        }
+        applied
+    }
+
+    /// How often the publisher should poll: as fast as the most eager connected client
+    pub fn publish_interval(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        state
+            .sessions
+            .values()
+            .filter(|s| s.disconnected_at.is_none())
+            .map(|s| s.interval)
+            .min()
+            .unwrap_or(self.default_interval)
+    }
+
+    fn prune_sessions(state: &mut HubState) {
+        state.sessions.retain(|_, s| {
+            s.disconnected_at
+                .is_none_or(|at| at.elapsed() < RESUME_TTL)
+        });
+    }
+}
+
+/// Spawn the background task that polls the discovery provider and publishes
+/// to the hub
+pub fn spawn_publisher(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            let started = Instant::now();
+
+            let containers = match state.provider.refresh_containers().await {
+                Ok(containers) => containers,
+                Err(e) => {
+                    error!("Failed to list containers for WS update: {}", e);
+                    state.updates.wait(state.hub.publish_interval(), started).await;
+                    continue;
+                }
+            };
+
+            let topology = state.docker.build_topology(&containers);
+            state.hub.publish(
+                WsTopic::Topology,
+                WsMessage::TopologyUpdate {
+                    total_containers: topology.total_containers,
+                    running_containers: topology.running_containers,
+                    healthy_containers: topology.healthy_containers,
+                    unhealthy_containers: topology.unhealthy_containers,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                },
+            );
+            let changed = state.hub.publish_containers(containers);
+            state.updates.polled(changed, Instant::now());
+            state.docker.trust_cache(state.updates.event_driven());
+            state.updates.wait(state.hub.publish_interval(), started).await;
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsParams {
+    pub resume_token: Option<String>,
 }
 
 /// WebSocket upgrade handler
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    Query(params): Query<WsParams>,
+    caller: Option<Extension<Caller>>,
 ) -> impl IntoResponse {
     info!("New WebSocket connection");
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    ws.on_upgrade(move |socket| async move {
+        // Connection time counts towards the token's usage
+        let token = caller.map(|Extension(Caller(token))| token);
+        let opened = Instant::now();
+        if let Some(token) = &token {
+            state.usage.ws_opened(&token.name, opened);
+        }
+        handle_socket(socket, state.clone(), params, token.clone()).await;
+        if let Some(token) = &token {
+            state.usage.ws_closed(&token.name, opened, Instant::now());
+        }
+    })
+}
+
+async fn send_json<T: Serialize, S: Sink<Message> + Unpin>(sender: &mut S, value: &T) -> bool {
+    let json = serde_json::to_string(value).unwrap();
+    sender.send(Message::Text(json)).await.is_ok()
+}
+
+/// Send `envelopes` in order, acknowledging each one that was sent, until a
+/// send fails. Returns whether the connection is still open.
+async fn deliver<S: Sink<Message> + Unpin>(
+    sender: &mut S,
+    hub: &WsHub,
+    resume_token: &str,
+    envelopes: impl IntoIterator<Item = WsEnvelope>,
+) -> bool {
+    for envelope in envelopes {
+        if !send_json(sender, &envelope).await {
+            return false;
+        }
+        hub.ack(resume_token, envelope.seq);
+    }
+    true
+}
+
+/// Whether the connection's token holds `scope`, counting the request
+/// against its rate limits like a REST call
+fn authorize(state: &AppState, token: Option<&ApiToken>, scope: ApiScope) -> Result<(), String> {
+    check_scope(&state.auth, &state.usage, token, scope)
+}
+
+fn check_scope(auth: &Authenticator, usage: &UsageMeter, token: Option<&ApiToken>, scope: ApiScope) -> Result<(), String> {
+    if !auth.enabled() {
+        return Ok(());
+    }
+    let Some(token) = token else {
+        return Err("missing or unknown API token".to_string());
+    };
+    if !token.allows(scope) {
+        return Err(format!("token '{}' lacks the '{}' scope", token.name, scope.as_str()));
+    }
+    usage
+        .admit(token, scope, Instant::now())
+        .map_err(|limit| format!("token '{}' exceeded {}", token.name, limit.what))
 }
 
 /// Handle WebSocket connection
-async fn handle_socket(socket: WebSocket, state: AppState) {
+async fn handle_socket(socket: WebSocket, state: AppState, params: WsParams, token: Option<ApiToken>) {
     let (mut sender, mut receiver) = socket.split();
 
-    // Spawn a task to send periodic updates
-    let state_clone = state.clone();
-    let send_task = tokio::spawn(async move {
-        let mut ticker = interval(Duration::from_secs(5));
-        
-        loop {
-            ticker.tick().await;
-            
-            // Get current topology
-            match state_clone.docker.get_topology().await {
-                Ok(topology) => {
-                    let msg = WsMessage::TopologyUpdate {
-                        total_containers: topology.total_containers,
-                        running_containers: topology.running_containers,
-                        healthy_containers: topology.healthy_containers,
-                        unhealthy_containers: topology.unhealthy_containers,
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                    };
-                    
-                    let json = serde_json::to_string(&msg).unwrap();
-                    if sender.send(Message::Text(json.into())).await.is_err() {
-                        break;
+    let Attachment {
+        resume_token,
+        resumed,
+        seq,
+        interval: client_interval,
+        backlog,
+        receiver: mut updates,
+    } = state.hub.attach(params.resume_token.as_deref());
+
+    if resumed {
+        info!("WebSocket resumed, replaying {} messages", backlog.len());
+    }
+
+    let welcome = WsMessage::Welcome {
+        resume_token: resume_token.clone(),
+        resumed,
+        seq,
+    };
+    let mut open =
+        send_json(&mut sender, &welcome).await && deliver(&mut sender, &state.hub, &resume_token, backlog).await;
+
+    let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+    heartbeat.tick().await;
+
+    // Log watch matches skip the batching below so alerts arrive immediately
+    let (mut log_watches, mut log_matches) = LogWatches::new();
+    let (mut actions, mut action_results) = ContainerActions::new();
+    let (mut runs, mut run_output) = RunAttachments::new();
+
+    // Updates are held here and flushed once per client interval
+    let mut pending: Vec<WsEnvelope> = Vec::new();
+    let mut flush = interval(client_interval);
+    flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    while open {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(envelope) => {
+                    // Snapshot topics supersede anything still pending on the same topic
+                    if envelope.topic.is_snapshot() {
+                        pending.retain(|e| e.topic != envelope.topic);
                     }
+                    pending.push(envelope);
                 }
-                Err(e) => {
-                    error!("Failed to get topology for WS update: {}", e);
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("WebSocket client lagged by {} messages, sending snapshot", skipped);
+                    pending = state.hub.snapshot();
                 }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = flush.tick() => {
+                pending.sort_by_key(|e| e.seq);
+                open = deliver(&mut sender, &state.hub, &resume_token, pending.drain(..)).await;
             }
-        }
-    });
-
-    // Handle incoming messages (mainly for ping/pong)
-    let recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            match msg {
-                Message::Text(text) => {
+            incoming = receiver.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
                     debug!("Received WS text: {}", text);
+                    let reply = match serde_json::from_str::<WsClientMessage>(&text) {
+                        Ok(WsClientMessage::SetInterval { interval_ms }) => {
+                            let applied = state
+                                .hub
+                                .set_interval(&resume_token, Duration::from_millis(interval_ms));
+                            flush = interval(applied);
+                            flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                            debug!("WebSocket interval set to {:?} (requested {}ms)", applied, interval_ms);
+                            Some(WsMessage::IntervalUpdated {
+                                interval_ms: applied.as_millis() as u64,
+                                requested_ms: interval_ms,
+                            })
+                        }
+                        Ok(WsClientMessage::WatchLogs { container, pattern }) => {
+                            let allowed = authorize(&state, token.as_ref(), ApiScope::LogsRead)
+                                .and_then(|()| state.provider.kind().docker_only("watching logs"));
+                            match allowed {
+                                Ok(()) => Some(log_watches.start(&state.docker, container, pattern)),
+                                Err(message) => Some(WsMessage::Error { message }),
+                            }
+                        }
+                        Ok(WsClientMessage::UnwatchLogs { watch_id }) => Some(log_watches.stop(&watch_id)),
+                        Ok(WsClientMessage::Action { request_id, action, container }) => {
+                            let allowed = authorize(&state, token.as_ref(), ApiScope::ContainersActions)
+                                .and_then(|()| state.provider.kind().docker_only("container actions"));
+                            match allowed {
+                                Ok(()) => actions.run(
+                                    state.docker.clone(),
+                                    state.events.clone(),
+                                    token.as_ref().map(|t| t.name.clone()),
+                                    request_id,
+                                    action,
+                                    container,
+                                ),
+                                Err(reason) => Some(actions::refused(request_id, action, container, reason)),
+                            }
+                        }
+                        Ok(WsClientMessage::AttachRun { run_id }) => {
+                            let allowed = authorize(&state, token.as_ref(), ApiScope::Exec)
+                                .and_then(|()| state.provider.kind().docker_only("attaching to runs"));
+                            match allowed {
+                                Ok(()) => runs.attach(
+                                    state.docker.clone(),
+                                    state.runs.clone(),
+                                    state.events.clone(),
+                                    token.as_ref().map(|t| t.name.clone()),
+                                    run_id,
+                                ),
+                                Err(message) => Some(WsMessage::Error { message }),
+                            }
+                        }
+                        Ok(WsClientMessage::RunInput { run_id, data, eof }) => runs.input(&run_id, data, eof),
+                        Err(e) => Some(WsMessage::Error {
+                            message: format!("Invalid client message: {}", e),
+                        }),
+                    };
+                    if let Some(reply) = reply {
+                        open = send_json(&mut sender, &reply).await;
+                    }
                 }
-                Message::Close(_) => {
+                Some(Ok(Message::Close(_))) | None => {
                     info!("WebSocket closed by client");
                     break;
                 }
-                _ => {}
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    debug!("WebSocket receive error: {}", e);
+                    break;
+                }
+            },
+            Some(message) = log_matches.recv() => {
+                open = send_json(&mut sender, &message).await;
+            }
+            Some(message) = action_results.recv() => {
+                open = send_json(&mut sender, &message).await;
+            }
+            Some(message) = run_output.recv() => {
+                open = send_json(&mut sender, &message).await;
+            }
+            _ = heartbeat.tick() => {
+                let msg = WsMessage::Heartbeat {
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                };
+                open = send_json(&mut sender, &msg).await;
             }
         }
-    });
-
-    // Wait for either task to finish
-    tokio::select! {
-        _ = send_task => {},
-        _ = recv_task => {},
     }
-    
+
+    state.hub.detach(&resume_token);
     info!("WebSocket connection closed");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_need_their_scope() {
+        let mut reader = ApiToken::named("dash");
+        reader.scopes = vec![ApiScope::ContainersRead];
+        let mut logs = ApiToken::named("logs");
+        logs.scopes = vec![ApiScope::ContainersRead, ApiScope::LogsRead];
+        let auth = Authenticator::with_tokens(vec![reader.clone(), logs.clone()]);
+        let usage = UsageMeter::new();
+        let check = |token: Option<&ApiToken>, scope| check_scope(&auth, &usage, token, scope);
+
+        // watchLogs
+        assert!(check(None, ApiScope::LogsRead).is_err());
+        assert_eq!(
+            check(Some(&reader), ApiScope::LogsRead).unwrap_err(),
+            "token 'dash' lacks the 'logs:read' scope"
+        );
+        assert!(check(Some(&logs), ApiScope::LogsRead).is_ok());
+        // action and attachRun
+        assert!(check(Some(&logs), ApiScope::ContainersActions).is_err());
+        assert!(check(Some(&logs), ApiScope::Exec).is_err());
+        assert!(check(Some(&ApiToken::named("ops")), ApiScope::Exec).is_ok());
+
+        assert!(check_scope(&Authenticator::default(), &usage, None, ApiScope::LogsRead).is_ok());
+    }
+
+    fn removal(hub: &WsHub, id: &str) -> u64 {
+        hub.publish(
+            WsTopic::Containers,
+            WsMessage::ContainerUpdate {
+                containers: Vec::new(),
+                removed: vec![id.to_string()],
+                full: false,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn resumes_after_a_failed_send() {
+        let hub = WsHub::new();
+        let attachment = hub.attach(None);
+        let token = attachment.resume_token;
+        for id in ["a", "b", "c"] {
+            removal(&hub, id);
+        }
+
+        // The connection takes one message and then drops
+        let mut sender = Box::pin(futures::sink::unfold(0, |sent, _: Message| async move {
+            if sent < 1 {
+                Ok(sent + 1)
+            } else {
+                Err(())
+            }
+        }));
+        let open = deliver(&mut sender, &hub, &token, hub.subscribe(Some(0)).0).await;
+        assert!(!open);
+        hub.detach(&token);
+
+        let resumed = hub.attach(Some(&token));
+        assert!(resumed.resumed);
+        let seqs: Vec<u64> = resumed.backlog.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+    }
+}
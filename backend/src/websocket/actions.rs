@@ -0,0 +1,139 @@
+//! Container actions over the socket
+//!
+//! Interactive dashboards already hold a WebSocket, so `action` messages let
+//! them restart, stop or start containers without a REST round trip. Each
+//! action runs in its own task and answers with an `actionResult` carrying
+//! the client's `requestId`; actions are journalled like their REST
+//! counterparts, so the events they cause are attributed to the caller.
+//! An action already started finishes even if the connection closes.
+
+use std::sync::Arc;
+
+use axum::http::Method;
+use chrono::Utc;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{debug, info};
+
+use crate::discovery::DockerDiscovery;
+use crate::docker_api::DockerApi;
+use crate::events::EventLog;
+
+use super::{WsAction, WsMessage};
+
+/// Actions that may run at once per connection
+pub const MAX_IN_FLIGHT: usize = 4;
+
+/// The running actions of one WebSocket connection
+pub struct ContainerActions {
+    tasks: Vec<JoinHandle<()>>,
+    sender: mpsc::UnboundedSender<WsMessage>,
+}
+
+impl ContainerActions {
+    /// Action set plus the receiver its results arrive on
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<WsMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let actions = Self {
+            tasks: Vec::new(),
+            sender,
+        };
+        (actions, receiver)
+    }
+
+    /// Start an action on behalf of `token`; its result arrives on the
+    /// receiver. Returns a failed result right away when too many are running.
+    pub fn run<D: DockerApi>(
+        &mut self,
+        discovery: Arc<DockerDiscovery<D>>,
+        journal: Arc<EventLog>,
+        token: Option<String>,
+        request_id: Option<String>,
+        action: WsAction,
+        container: String,
+    ) -> Option<WsMessage> {
+        self.tasks.retain(|task| !task.is_finished());
+        if self.tasks.len() >= MAX_IN_FLIGHT {
+            return Some(refused(
+                request_id,
+                action,
+                container,
+                format!("at most {} actions may run at once per connection", MAX_IN_FLIGHT),
+            ));
+        }
+
+        let sender = self.sender.clone();
+        self.tasks.push(tokio::spawn(async move {
+            info!("WebSocket {} of container: {}", action.as_str(), container);
+            let path = format!("/api/container/{}/{}", container, action.as_str());
+            let journalled = journal.begin_action(&Method::POST, &path, token, Utc::now());
+            let outcome = match action {
+                WsAction::Restart => discovery.restart_container(&container).await,
+                WsAction::Stop => discovery.stop_container(&container).await,
+                WsAction::Start => discovery.start_container(&container).await,
+            };
+            journal.finish_action(journalled, Utc::now());
+
+            let (success, message) = match outcome {
+                Ok(Some(result)) => (result.success, result.message),
+                Ok(None) => (false, "Container not found".to_string()),
+                Err(e) => (false, format!("Docker error: {}", e)),
+            };
+            debug!("WebSocket {} of {} finished: {}", action.as_str(), container, message);
+            let _ = sender.send(WsMessage::ActionResult {
+                request_id,
+                action,
+                container,
+                success,
+                message,
+            });
+        }));
+        None
+    }
+}
+
+/// A result for an action that never ran
+pub fn refused(request_id: Option<String>, action: WsAction, container: String, message: String) -> WsMessage {
+    WsMessage::ActionResult {
+        request_id,
+        action,
+        container,
+        success: false,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+
+    #[tokio::test]
+    async fn results_echo_the_request_id() {
+        let discovery = Arc::new(DockerDiscovery::new(FakeDocker::load("stack")));
+        let journal = Arc::new(EventLog::new());
+        let (mut actions, mut results) = ContainerActions::new();
+
+        let run = |actions: &mut ContainerActions, id: &str, container: &str| {
+            actions.run(
+                discovery.clone(),
+                journal.clone(),
+                Some("ops".to_string()),
+                Some(id.to_string()),
+                WsAction::Restart,
+                container.to_string(),
+            )
+        };
+        assert!(run(&mut actions, "1", "application-api-1").is_none());
+        assert!(run(&mut actions, "2", "missing").is_none());
+
+        let mut outcomes = Vec::new();
+        for _ in 0..2 {
+            let Some(WsMessage::ActionResult { request_id, success, .. }) = results.recv().await else {
+                panic!("expected an action result");
+            };
+            outcomes.push((request_id.unwrap(), success));
+        }
+        outcomes.sort();
+        assert_eq!(outcomes, [("1".to_string(), true), ("2".to_string(), false)]);
+    }
+}
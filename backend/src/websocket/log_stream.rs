@@ -0,0 +1,239 @@
+//! Live log streaming
+//!
+//! `GET /ws/logs/:id?tail=<n>` upgrades to a socket that sends the last `n`
+//! lines of a container's logs (default 100) and then follows them, batching
+//! lines that arrive together into one [`LogStreamMessage::Lines`]. When the
+//! container stops the stream is `paused`; FlowScope checks every
+//! [`RESTART_POLL`] whether it runs again and then `resumed`s with the lines
+//! written since, so a restart does not end the stream. It ends when the
+//! container is removed or the client goes away.
+
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use tracing::{debug, error, info};
+
+use crate::{
+    auth::Caller,
+    discovery::DockerDiscovery,
+    docker_api::DockerApi,
+    models::ContainerStatus,
+    AppState,
+};
+
+use flowscope_types::ws::LogStreamMessage;
+
+const DEFAULT_TAIL: usize = 100;
+/// Largest initial tail accepted
+pub const MAX_TAIL: usize = 10_000;
+/// Log chunks gathered into one message when they arrive together
+const LINE_BATCH: usize = 256;
+/// How often a stopped container is checked for a restart
+pub const RESTART_POLL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+pub struct LogStreamQuery {
+    /// Lines sent before following, at most [`MAX_TAIL`]
+    pub tail: Option<usize>,
+}
+
+/// GET /ws/logs/:id?tail= - Follow a container's logs
+pub async fn log_stream_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LogStreamQuery>,
+    caller: Option<Extension<Caller>>,
+) -> Response {
+    let target = match state.docker.get_container(&id).await {
+        Ok(Some(target)) => target,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "Container not found",
+                    "id": id
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to look up '{}' for a log stream: {}", id, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to look up the container",
+                    "details": e.to_string()
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let tail = query.tail.unwrap_or(DEFAULT_TAIL).min(MAX_TAIL);
+    debug!("Streaming logs of {} (tail: {})", target.name, tail);
+    ws.on_upgrade(move |socket| async move {
+        // Connection time counts towards the token's usage
+        let token = caller.map(|Extension(Caller(token))| token.name);
+        let opened = Instant::now();
+        if let Some(token) = &token {
+            state.usage.ws_opened(token, opened);
+        }
+        let (mut sender, mut receiver) = socket.split();
+        run(&state.docker, &target.id, &target.name, tail, &mut sender, &mut receiver).await;
+        if let Some(token) = &token {
+            state.usage.ws_closed(token, opened, Instant::now());
+        }
+    })
+}
+
+async fn send_message<S: Sink<Message> + Unpin>(sender: &mut S, message: &LogStreamMessage) -> bool {
+    let json = serde_json::to_string(message).unwrap();
+    sender.send(Message::Text(json)).await.is_ok()
+}
+
+/// Whether a message from the client means it went away; anything else it
+/// sends is ignored
+fn closed(message: Option<Result<Message, axum::Error>>) -> bool {
+    matches!(message, None | Some(Err(_)) | Some(Ok(Message::Close(_))))
+}
+
+/// Follow the container's logs across restarts until it is removed or the
+/// client goes away
+async fn run<D, S, R>(discovery: &DockerDiscovery<D>, id: &str, name: &str, tail: usize, sender: &mut S, receiver: &mut R)
+where
+    D: DockerApi,
+    S: Sink<Message> + Unpin,
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    let mut tail = Some(tail);
+    let mut since = 0;
+    let reason = 'stream: loop {
+        let mut logs = discovery.stream_logs(id, tail.take(), since).ready_chunks(LINE_BATCH);
+        loop {
+            tokio::select! {
+                chunks = logs.next() => {
+                    let Some(chunks) = chunks else { break };
+                    let mut lines = Vec::new();
+                    let mut failed = None;
+                    for chunk in chunks {
+                        match chunk {
+                            Ok(chunk) => lines.extend(chunk.lines().map(str::to_string)),
+                            Err(e) => failed = Some(e),
+                        }
+                    }
+                    if !lines.is_empty() && !send_message(sender, &LogStreamMessage::Lines { lines }).await {
+                        return;
+                    }
+                    if let Some(e) = failed {
+                        debug!("Log stream of {} failed: {}", name, e);
+                        break;
+                    }
+                }
+                message = receiver.next() => if closed(message) {
+                    return;
+                },
+            }
+        }
+        since = chrono::Utc::now().timestamp();
+
+        // Wait for the container to run again
+        let mut paused = false;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(RESTART_POLL) => {}
+                message = receiver.next() => if closed(message) {
+                    return;
+                } else {
+                    continue;
+                },
+            }
+            match discovery.get_container(id).await {
+                Ok(Some(container))
+                    if matches!(
+                        container.status,
+                        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+                    ) =>
+                {
+                    break
+                }
+                Ok(Some(_)) if !paused => {
+                    paused = true;
+                    let reason = "container stopped".to_string();
+                    if !send_message(sender, &LogStreamMessage::Paused { reason }).await {
+                        return;
+                    }
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break 'stream "container removed".to_string(),
+                Err(e) => break 'stream format!("failed to check the container: {}", e),
+            }
+        }
+        if paused {
+            info!("Log stream of {} resumed after a restart", name);
+            if !send_message(sender, &LogStreamMessage::Resumed).await {
+                return;
+            }
+        }
+    };
+
+    debug!("Log stream of {} ended: {}", name, reason);
+    if send_message(sender, &LogStreamMessage::Ended { reason }).await {
+        let _ = sender.send(Message::Close(None)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+    use futures::channel::mpsc;
+
+    async fn next_message(server: &mut mpsc::Receiver<Message>) -> LogStreamMessage {
+        match server.next().await {
+            Some(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_the_tail_and_pauses_while_stopped() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let (_client, mut receiver) = mpsc::channel::<Result<Message, axum::Error>>(1);
+        let (mut sender, mut server) = mpsc::channel::<Message>(8);
+        tokio::spawn(async move {
+            run(&discovery, "application-api-1", "application-api-1", 2, &mut sender, &mut receiver).await;
+        });
+        let LogStreamMessage::Lines { lines } = next_message(&mut server).await else {
+            panic!("expected the tail");
+        };
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "listening on :8080");
+
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let (client, mut receiver) = mpsc::channel::<Result<Message, axum::Error>>(1);
+        let (mut sender, mut server) = mpsc::channel::<Message>(8);
+        let stream = tokio::spawn(async move {
+            run(&discovery, "monitoring-prometheus", "monitoring-prometheus", 2, &mut sender, &mut receiver).await;
+        });
+        assert_eq!(
+            next_message(&mut server).await,
+            LogStreamMessage::Paused {
+                reason: "container stopped".to_string()
+            }
+        );
+        drop(client);
+        stream.await.unwrap();
+        assert_eq!(server.next().await, None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Ephemeral log watches
+//!
+//! A client registers a regex against one container's logs with `watchLogs`
+//! and receives only the matching lines, as a lighter alternative to streaming
+//! everything. Each watch is a task following the logs from the moment it was
+//! created; watches belong to a single connection and stop when it closes.
+
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::debug;
+
+use crate::discovery::DockerDiscovery;
+use crate::docker_api::DockerApi;
+use crate::log_format::compile_pattern;
+
+use super::WsMessage;
+
+/// Concurrent watches allowed per connection
+pub const MAX_WATCHES: usize = 8;
+/// Matches queued for a connection before further matches are dropped
+const MATCH_BUFFER: usize = 256;
+
+/// The log watches of one WebSocket connection
+pub struct LogWatches {
+    tasks: HashMap<String, JoinHandle<()>>,
+    sender: mpsc::Sender<WsMessage>,
+}
+
+impl LogWatches {
+    /// Watch set plus the receiver its matches arrive on
+    pub fn new() -> (Self, mpsc::Receiver<WsMessage>) {
+        let (sender, receiver) = mpsc::channel(MATCH_BUFFER);
+        let watches = Self {
+            tasks: HashMap::new(),
+            sender,
+        };
+        (watches, receiver)
+    }
+
+    /// Start a watch, replying with `LogWatchStarted` or an `Error`
+    pub fn start<D: DockerApi>(
+        &mut self,
+        discovery: &DockerDiscovery<D>,
+        container: String,
+        pattern: String,
+    ) -> WsMessage {
+        self.tasks.retain(|_, task| !task.is_finished());
+        if self.tasks.len() >= MAX_WATCHES {
+            return WsMessage::Error {
+                message: format!("at most {} log watches per connection", MAX_WATCHES),
+            };
+        }
+        let regex = match compile_pattern(&pattern) {
+            Ok(regex) => regex,
+            Err(message) => return WsMessage::Error { message },
+        };
+
+        let watch_id = uuid::Uuid::new_v4().to_string();
+        let mut logs = discovery.follow_logs(&container);
+        let sender = self.sender.clone();
+        let (id, name) = (watch_id.clone(), container.clone());
+
+        let task = tokio::spawn(async move {
+            let mut dropped = 0usize;
+            let reason = loop {
+                match logs.next().await {
+                    Some(Ok(chunk)) => {
+                        for line in chunk.lines().filter(|l| regex.is_match(l)) {
+                            let message = WsMessage::LogMatch {
+                                watch_id: id.clone(),
+                                container: name.clone(),
+                                line: line.to_string(),
+                                timestamp: chrono::Utc::now().to_rfc3339(),
+                            };
+                            match sender.try_send(message) {
+                                Ok(()) => {}
+                                Err(mpsc::error::TrySendError::Full(_)) => dropped += 1,
+                                Err(mpsc::error::TrySendError::Closed(_)) => return,
+                            }
+                        }
+                    }
+                    Some(Err(e)) => break format!("log stream failed: {}", e),
+                    None => break "log stream ended".to_string(),
+                }
+            };
+            if dropped > 0 {
+                debug!("Log watch {} dropped {} matches for a slow client", id, dropped);
+            }
+            let _ = sender
+                .send(WsMessage::LogWatchEnded { watch_id: id, reason })
+                .await;
+        });
+        self.tasks.insert(watch_id.clone(), task);
+
+        debug!("Log watch {} on {} for /{}/", watch_id, container, pattern);
+        WsMessage::LogWatchStarted {
+            watch_id,
+            container,
+            pattern,
+        }
+    }
+
+    /// Stop a watch, replying with `LogWatchEnded` or an `Error`
+    pub fn stop(&mut self, watch_id: &str) -> WsMessage {
+        match self.tasks.remove(watch_id) {
+            Some(task) => {
+                task.abort();
+                WsMessage::LogWatchEnded {
+                    watch_id: watch_id.to_string(),
+                    reason: "unwatched".to_string(),
+                }
+            }
+            None => WsMessage::Error {
+                message: format!("no log watch '{}'", watch_id),
+            },
+        }
+    }
+}
+
+impl Drop for LogWatches {
+    fn drop(&mut self) {
+        for task in self.tasks.values() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+
+    #[tokio::test]
+    async fn forwards_only_matching_lines() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let (mut watches, mut matches) = LogWatches::new();
+
+        let started = watches.start(&discovery, "application-api-1".into(), "listening|GET".into());
+        let WsMessage::LogWatchStarted { watch_id, .. } = started else {
+            panic!("watch was not started: {:?}", started);
+        };
+
+        let mut lines = Vec::new();
+        while let Some(message) = matches.recv().await {
+            match message {
+                WsMessage::LogMatch { line, .. } => lines.push(line),
+                WsMessage::LogWatchEnded { watch_id: ended, .. } => {
+                    assert_eq!(ended, watch_id);
+                    break;
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+        assert_eq!(lines, ["listening on :8080", "GET /health 200"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_patterns_and_unknown_ids() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let (mut watches, _matches) = LogWatches::new();
+
+        assert!(matches!(
+            watches.start(&discovery, "application-api-1".into(), "(unclosed".into()),
+            WsMessage::Error { .. }
+        ));
+        assert!(matches!(watches.stop("missing"), WsMessage::Error { .. }));
+    }
+}
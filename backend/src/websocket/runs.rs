@@ -0,0 +1,310 @@
+//! One-off runs over the socket
+//!
+//! `attachRun` starts a container created by `POST /api/containers/run` and
+//! streams what it writes as `runOutput` messages; `runInput` writes to its
+//! stdin. Each attachment is a task that owns the container: when the process
+//! exits, its TTL runs out or the connection closes, the container is stopped
+//! and removed and `runExited` is sent.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::http::Method;
+use bollard::container::{AttachContainerResults, LogOutput};
+use chrono::Utc;
+use futures_util::StreamExt;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+use tracing::{info, warn};
+
+use crate::discovery::DockerDiscovery;
+use crate::docker_api::DockerApi;
+use crate::events::EventLog;
+use crate::models::OneOffRun;
+use crate::runs::OneOffRuns;
+
+use super::{RunStream, WsMessage};
+
+/// Runs attached at once per connection
+pub const MAX_ATTACHED: usize = 4;
+/// Output messages queued for a connection; a run waits while it is full
+const OUTPUT_BUFFER: usize = 256;
+/// Input messages queued for a run before further input is refused
+const INPUT_BUFFER: usize = 64;
+
+struct Input {
+    data: Vec<u8>,
+    eof: bool,
+}
+
+/// How an attached run ended
+enum RunEnd {
+    Exited,
+    Expired,
+    Disconnected,
+    Failed(String),
+}
+
+/// The attached runs of one WebSocket connection
+pub struct RunAttachments {
+    inputs: HashMap<String, mpsc::Sender<Input>>,
+    sender: mpsc::Sender<WsMessage>,
+}
+
+impl RunAttachments {
+    /// Attachment set plus the receiver run output arrives on
+    pub fn new() -> (Self, mpsc::Receiver<WsMessage>) {
+        let (sender, receiver) = mpsc::channel(OUTPUT_BUFFER);
+        let attachments = Self {
+            inputs: HashMap::new(),
+            sender,
+        };
+        (attachments, receiver)
+    }
+
+    /// Attach to and start the run `run_id` on behalf of `token`. `runAttached`
+    /// or `runExited` arrives on the receiver; an `Error` is returned right
+    /// away when the run cannot be claimed.
+    pub fn attach<D: DockerApi>(
+        &mut self,
+        discovery: Arc<DockerDiscovery<D>>,
+        runs: Arc<OneOffRuns>,
+        journal: Arc<EventLog>,
+        token: Option<String>,
+        run_id: String,
+    ) -> Option<WsMessage> {
+        self.inputs.retain(|_, input| !input.is_closed());
+        if self.inputs.len() >= MAX_ATTACHED {
+            return Some(WsMessage::Error {
+                message: format!("at most {} runs may be attached per connection", MAX_ATTACHED),
+            });
+        }
+        let run = match runs.claim(&run_id, token.as_deref()) {
+            Ok(run) => run,
+            Err(message) => return Some(WsMessage::Error { message }),
+        };
+
+        let (input, inputs) = mpsc::channel(INPUT_BUFFER);
+        self.inputs.insert(run.id.clone(), input);
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let path = format!("/api/container/{}/start", run.name);
+            let journalled = journal.begin_action(&Method::POST, &path, token, Utc::now());
+            let attached = discovery.attach_and_start(&run.id).await;
+            journal.finish_action(journalled, Utc::now());
+
+            let end = match attached {
+                Ok(attached) => {
+                    let _ = sender.send(WsMessage::RunAttached { run_id: run.id.clone() }).await;
+                    pump(&run, attached, inputs, &sender).await
+                }
+                Err(e) => RunEnd::Failed(format!("failed to start: {}", e)),
+            };
+            let (exit_code, reason) = match end {
+                RunEnd::Exited => (discovery.exit_code(&run.id).await.ok(), "exited".to_string()),
+                RunEnd::Expired => (None, "TTL expired".to_string()),
+                RunEnd::Disconnected => (None, "client disconnected".to_string()),
+                RunEnd::Failed(reason) => (None, reason),
+            };
+
+            runs.take(&run.id);
+            if let Err(e) = discovery.discard_container(&run.id).await {
+                warn!("Failed to remove one-off run {}: {}", run.name, e);
+            }
+            info!("One-off run {} ended: {}", run.name, reason);
+            let _ = sender
+                .send(WsMessage::RunExited {
+                    run_id: run.id,
+                    exit_code,
+                    reason,
+                })
+                .await;
+        });
+        None
+    }
+
+    /// Queue `data` for an attached run's stdin, replying only with an `Error`
+    pub fn input(&mut self, run_id: &str, data: String, eof: bool) -> Option<WsMessage> {
+        let Some(input) = self.inputs.get(run_id) else {
+            return Some(WsMessage::Error {
+                message: format!("no attached run '{}'", run_id),
+            });
+        };
+        let message = match input.try_send(Input {
+            data: data.into_bytes(),
+            eof,
+        }) {
+            Ok(()) => return None,
+            Err(mpsc::error::TrySendError::Full(_)) => format!("run '{}' is not reading its input", run_id),
+            Err(mpsc::error::TrySendError::Closed(_)) => format!("run '{}' has exited", run_id),
+        };
+        Some(WsMessage::Error { message })
+    }
+}
+
+/// Copy output to the connection and input to the container until the run
+/// ends. Dropping the connection's input sender ends it too.
+async fn pump(
+    run: &OneOffRun,
+    attached: AttachContainerResults,
+    mut inputs: mpsc::Receiver<Input>,
+    sender: &mpsc::Sender<WsMessage>,
+) -> RunEnd {
+    let AttachContainerResults {
+        mut output,
+        input: mut stdin,
+    } = attached;
+    let remaining = (run.expires_at - Utc::now()).to_std().unwrap_or_default();
+    let deadline = tokio::time::sleep(remaining);
+    tokio::pin!(deadline);
+
+    let mut pending: HashMap<RunStream, Vec<u8>> = HashMap::new();
+    let mut stdin_open = true;
+    let end = loop {
+        tokio::select! {
+            chunk = output.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    let (stream, bytes) = match chunk {
+                        LogOutput::StdOut { message } => (RunStream::Stdout, message),
+                        LogOutput::StdErr { message } => (RunStream::Stderr, message),
+                        LogOutput::Console { message } => (RunStream::Console, message),
+                        LogOutput::StdIn { .. } => continue,
+                    };
+                    let data = decode(pending.entry(stream).or_default(), &bytes);
+                    if data.is_empty() {
+                        continue;
+                    }
+                    let message = WsMessage::RunOutput { run_id: run.id.clone(), stream, data };
+                    if sender.send(message).await.is_err() {
+                        break RunEnd::Disconnected;
+                    }
+                }
+                Some(Err(e)) => break RunEnd::Failed(format!("output stream failed: {}", e)),
+                None => break RunEnd::Exited,
+            },
+            input = inputs.recv() => match input {
+                // Input after stdin closed has nowhere to go
+                Some(_) if !stdin_open => {}
+                Some(Input { data, eof }) => {
+                    let written = stdin.write_all(&data).await.and(stdin.flush().await);
+                    if eof || written.is_err() {
+                        let _ = stdin.shutdown().await;
+                        stdin_open = false;
+                    }
+                }
+                None => break RunEnd::Disconnected,
+            },
+            _ = &mut deadline => break RunEnd::Expired,
+        }
+    };
+
+    // Whatever partial characters are left will never be completed
+    for (stream, bytes) in pending.into_iter().filter(|(_, b)| !b.is_empty()) {
+        let data = String::from_utf8_lossy(&bytes).into_owned();
+        let _ = sender
+            .send(WsMessage::RunOutput { run_id: run.id.clone(), stream, data })
+            .await;
+    }
+    end
+}
+
+/// Decode `bytes` following what `pending` holds, keeping a character split
+/// across chunks in `pending` until the rest of it arrives
+fn decode(pending: &mut Vec<u8>, bytes: &[u8]) -> String {
+    pending.extend_from_slice(bytes);
+    let complete = match std::str::from_utf8(pending) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => pending.len(),
+    };
+    let rest = pending.split_off(complete);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+    use crate::models::OneOffRunRequest;
+
+    #[test]
+    fn split_characters_wait_for_the_next_chunk() {
+        let mut pending = Vec::new();
+        let bytes = "héllo".as_bytes();
+        assert_eq!(decode(&mut pending, &bytes[..2]), "h");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(decode(&mut pending, &bytes[2..]), "éllo");
+        assert!(pending.is_empty());
+        assert_eq!(decode(&mut pending, b"\xff!"), "\u{fffd}!");
+    }
+
+    #[tokio::test]
+    async fn attached_runs_echo_input_and_are_removed() {
+        let discovery = Arc::new(DockerDiscovery::new(FakeDocker::load("stack")));
+        let runs = Arc::new(OneOffRuns::new());
+        let journal = Arc::new(EventLog::new());
+        let request = OneOffRunRequest {
+            image: "flowscope/api:latest".to_string(),
+            cmd: vec!["cat".to_string()],
+            env: Vec::new(),
+            network: None,
+            tty: false,
+            ttl_seconds: None,
+        };
+        let id = discovery
+            .create_one_off("flowscope-run-test", &request, HashMap::new())
+            .await
+            .unwrap();
+        let now = Utc::now();
+        runs.insert(OneOffRun {
+            id: id.clone(),
+            name: "flowscope-run-test".to_string(),
+            image: request.image,
+            cmd: request.cmd,
+            network: None,
+            tty: false,
+            started_by: Some("ops".to_string()),
+            attached: false,
+            created_at: now,
+            attach_by: now + chrono::Duration::minutes(1),
+            expires_at: now + chrono::Duration::minutes(5),
+        })
+        .unwrap();
+
+        let (mut attachments, mut messages) = RunAttachments::new();
+        let attach = |attachments: &mut RunAttachments, token: &str| {
+            attachments.attach(
+                discovery.clone(),
+                runs.clone(),
+                journal.clone(),
+                Some(token.to_string()),
+                id.clone(),
+            )
+        };
+        assert!(matches!(attach(&mut attachments, "ci"), Some(WsMessage::Error { .. })));
+        assert!(attach(&mut attachments, "ops").is_none());
+        assert!(matches!(messages.recv().await, Some(WsMessage::RunAttached { .. })));
+
+        assert!(attachments.input(&id, "select 1;\n".to_string(), true).is_none());
+        let Some(WsMessage::RunOutput { stream, data, .. }) = messages.recv().await else {
+            panic!("expected run output");
+        };
+        assert_eq!((stream, data.as_str()), (RunStream::Stdout, "select 1;\n"));
+        let Some(WsMessage::RunExited { exit_code, reason, .. }) = messages.recv().await else {
+            panic!("expected the run to exit");
+        };
+        assert_eq!((exit_code, reason.as_str()), (Some(0), "exited"));
+
+        assert!(runs.list().is_empty());
+        let actions = discovery.api().actions();
+        let tail: Vec<&str> = actions.iter().rev().take(4).rev().map(String::as_str).collect();
+        assert_eq!(
+            tail,
+            [
+                "attach flowscope-run-test",
+                "start flowscope-run-test",
+                "stop flowscope-run-test",
+                "remove flowscope-run-test"
+            ]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Interactive terminals
+//!
+//! `GET /ws/exec/:id` upgrades to a socket attached to a shell running in the
+//! container on a TTY, for embedding an xterm.js terminal. Binary frames carry
+//! raw bytes both ways; text frames carry [`TerminalControl`] messages from the
+//! client (`resize`) and [`TerminalMessage`] values from the server (`exited`,
+//! `error`). `?shell=` picks the program to run instead of bash or sh, and
+//! `?rows=&cols=` the initial size.
+//!
+//! Docker cannot kill an exec, so when the client goes away the terminal is
+//! hung up instead: Ctrl-C and Ctrl-D are typed and the input is closed, which
+//! ends an idle shell. A command that survives that is logged and left to
+//! finish on its own.
+
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+use crate::{
+    auth::Caller,
+    discovery::DockerDiscovery,
+    docker_api::{DockerApi, ExecSession},
+    exec::ExecError,
+    models::ContainerStatus,
+    AppState,
+};
+
+use flowscope_types::ws::{TerminalControl, TerminalMessage};
+
+/// Starts bash where the image has it, sh otherwise
+const DEFAULT_SHELL: &[&str] = &["/bin/sh", "-c", "if command -v bash >/dev/null 2>&1; then exec bash; else exec sh; fi"];
+const DEFAULT_ROWS: u16 = 24;
+const DEFAULT_COLS: u16 = 80;
+/// Typed into the terminal when the client goes away: Ctrl-C, then Ctrl-D
+const HANGUP: &[u8] = b"\x03\x04";
+/// How long a hung-up command gets to exit before it is left running
+const HANGUP_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+pub struct TerminalQuery {
+    /// Program to run instead of the default shell
+    pub shell: Option<String>,
+    pub rows: Option<u16>,
+    pub cols: Option<u16>,
+}
+
+impl TerminalQuery {
+    fn command(&self) -> Vec<String> {
+        match self.shell.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(shell) => vec![shell.to_string()],
+            None => DEFAULT_SHELL.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// How a terminal session ended
+#[derive(Debug, PartialEq)]
+enum TerminalEnd {
+    Exited,
+    Disconnected,
+    Failed(String),
+}
+
+/// GET /ws/exec/:id - Interactive terminal in a running container
+pub async fn terminal_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<TerminalQuery>,
+    caller: Option<Extension<Caller>>,
+) -> Result<Response, ExecError> {
+    let target = state
+        .docker
+        .get_container(&id)
+        .await?
+        .ok_or_else(|| ExecError::NotFound(id.clone()))?;
+    if !matches!(
+        target.status,
+        ContainerStatus::Running | ContainerStatus::Healthy | ContainerStatus::Unhealthy
+    ) {
+        return Err(ExecError::NotRunning(target.name));
+    }
+
+    info!("Opening a terminal in {}", target.name);
+    Ok(ws
+        .on_upgrade(move |socket| async move {
+            // Connection time counts towards the token's usage
+            let token = caller.map(|Extension(Caller(token))| token.name);
+            let opened = Instant::now();
+            if let Some(token) = &token {
+                state.usage.ws_opened(token, opened);
+            }
+            let (mut sender, mut receiver) = socket.split();
+            let rows = query.rows.filter(|&r| r > 0).unwrap_or(DEFAULT_ROWS);
+            let cols = query.cols.filter(|&c| c > 0).unwrap_or(DEFAULT_COLS);
+            run(
+                &state.docker,
+                &target.id,
+                &target.name,
+                &query.command(),
+                (rows, cols),
+                &mut sender,
+                &mut receiver,
+            )
+            .await;
+            if let Some(token) = &token {
+                state.usage.ws_closed(token, opened, Instant::now());
+            }
+        })
+        .into_response())
+}
+
+async fn send_message<S: Sink<Message> + Unpin>(sender: &mut S, message: &TerminalMessage) -> bool {
+    let json = serde_json::to_string(message).unwrap();
+    sender.send(Message::Text(json)).await.is_ok()
+}
+
+/// Run `cmd` in the container until it exits or the client goes away, then
+/// report how it ended and close the socket
+async fn run<D, S, R>(
+    discovery: &DockerDiscovery<D>,
+    id: &str,
+    name: &str,
+    cmd: &[String],
+    (rows, cols): (u16, u16),
+    sender: &mut S,
+    receiver: &mut R,
+) where
+    D: DockerApi,
+    S: Sink<Message> + Unpin,
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    let (exit_code, reason) = match discovery.open_terminal(id, cmd, rows, cols).await {
+        Ok(session) => {
+            let exec_id = session.id.clone();
+            match pump(discovery, session, sender, receiver).await {
+                TerminalEnd::Exited => (
+                    discovery.terminal_exit_code(&exec_id).await.ok().flatten(),
+                    "exited".to_string(),
+                ),
+                end => {
+                    if let Ok(None) = discovery.terminal_exit_code(&exec_id).await {
+                        warn!("{:?} in {} is still running after hanging up", cmd, name);
+                    }
+                    match end {
+                        TerminalEnd::Failed(reason) => (None, reason),
+                        _ => (None, "client disconnected".to_string()),
+                    }
+                }
+            }
+        }
+        Err(e) => (None, format!("failed to start: {}", e)),
+    };
+
+    info!("Terminal in {} ended: {}", name, reason);
+    if send_message(sender, &TerminalMessage::Exited { exit_code, reason }).await {
+        let _ = sender.send(Message::Close(None)).await;
+    }
+}
+
+/// Copy output to the client and input to the terminal until the command
+/// exits or the client goes away; in the latter case the terminal is hung up
+async fn pump<D, S, R>(discovery: &DockerDiscovery<D>, session: ExecSession, sender: &mut S, receiver: &mut R) -> TerminalEnd
+where
+    D: DockerApi,
+    S: Sink<Message> + Unpin,
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    let ExecSession {
+        id: exec_id,
+        mut output,
+        input: mut stdin,
+    } = session;
+
+    let end = loop {
+        tokio::select! {
+            chunk = output.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    if sender.send(Message::Binary(chunk.into_bytes().to_vec())).await.is_err() {
+                        break TerminalEnd::Disconnected;
+                    }
+                }
+                Some(Err(e)) => break TerminalEnd::Failed(format!("output stream failed: {}", e)),
+                None => return TerminalEnd::Exited,
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Binary(data))) => {
+                    if let Err(e) = stdin.write_all(&data).await.and(stdin.flush().await) {
+                        break TerminalEnd::Failed(format!("input stream failed: {}", e));
+                    }
+                }
+                Some(Ok(Message::Text(text))) => {
+                    let reply = match serde_json::from_str::<TerminalControl>(&text) {
+                        Ok(TerminalControl::Resize { rows, cols }) if rows == 0 || cols == 0 => {
+                            Some("rows and cols must be positive".to_string())
+                        }
+                        Ok(TerminalControl::Resize { rows, cols }) => discovery
+                            .resize_terminal(&exec_id, rows, cols)
+                            .await
+                            .err()
+                            .map(|e| format!("failed to resize: {}", e)),
+                        Err(e) => Some(format!("invalid control message: {}", e)),
+                    };
+                    if let Some(message) = reply {
+                        if !send_message(sender, &TerminalMessage::Error { message }).await {
+                            break TerminalEnd::Disconnected;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break TerminalEnd::Disconnected,
+                Some(Ok(_)) => {}
+            },
+        }
+    };
+
+    let _ = stdin.write_all(HANGUP).await;
+    let _ = stdin.flush().await;
+    let _ = stdin.shutdown().await;
+    // Keep reading so the command is not blocked writing while it exits
+    let drain = async { while let Some(Ok(_)) = output.next().await {} };
+    let _ = tokio::time::timeout(HANGUP_GRACE, drain).await;
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_api::fake::FakeDocker;
+    use futures::channel::mpsc;
+
+    #[tokio::test]
+    async fn proxies_bytes_resizes_and_hangs_up() {
+        let discovery = DockerDiscovery::new(FakeDocker::load("stack"));
+        let (mut client, mut receiver) = mpsc::channel::<Result<Message, axum::Error>>(8);
+        let (mut sender, mut server) = mpsc::channel::<Message>(8);
+        let cmd = TerminalQuery {
+            shell: None,
+            rows: None,
+            cols: None,
+        }
+        .command();
+
+        let terminal = tokio::spawn(async move {
+            run(
+                &discovery,
+                "application-api-1",
+                "application-api-1",
+                &cmd,
+                (24, 80),
+                &mut sender,
+                &mut receiver,
+            )
+            .await;
+            discovery
+        });
+
+        client.send(Ok(Message::Binary(b"ls\r".to_vec()))).await.unwrap();
+        assert_eq!(server.next().await, Some(Message::Binary(b"ls\r".to_vec())));
+
+        let resize = serde_json::to_string(&TerminalControl::Resize { rows: 40, cols: 120 }).unwrap();
+        client.send(Ok(Message::Text(resize))).await.unwrap();
+        client.send(Ok(Message::Text("{\"type\":\"resize\",\"rows\":0,\"cols\":1}".to_string()))).await.unwrap();
+        let Some(Message::Text(error)) = server.next().await else {
+            panic!("expected an error message");
+        };
+        assert!(error.contains("rows and cols must be positive"));
+
+        // Going away hangs the terminal up
+        drop(client);
+        let discovery = terminal.await.unwrap();
+        let Some(Message::Text(exited)) = server.next().await else {
+            panic!("expected an exit message");
+        };
+        assert!(exited.contains("client disconnected"));
+        assert_eq!(server.next().await, Some(Message::Close(None)));
+
+        let actions = discovery.api().actions();
+        assert_eq!(
+            actions,
+            [
+                "exec-terminal application-api-1",
+                "resize exec-application-api-1 80x24",
+                "resize exec-application-api-1 120x40"
+            ]
+        );
+    }
+}
@@ -1,6 +1,19 @@
 // FlowScope API Client
 // Connects to the Rust backend for real Docker container data
 
+import type {
+  AlertList,
+  FlowchartFormat,
+  FlowDirection,
+  ImageUsage,
+  NotificationStatus,
+  SnapshotSummary,
+  StatsHistory,
+  SwarmService,
+  TopologyDiff,
+  WsEnvelope,
+} from "./types.generated";
+
 const API_BASE = import.meta.env.VITE_API_URL || "/api";
 
 export interface ContainerStats {
@@ -10,6 +23,10 @@ export interface ContainerStats {
   memoryPercent: number;
   networkRxMb: number;
   networkTxMb: number;
+  networkRxErrors: number;
+  networkTxErrors: number;
+  networkRxDropped: number;
+  networkTxDropped: number;
   blockReadMb: number;
   blockWriteMb: number;
   pids: number;
@@ -46,6 +63,13 @@ export interface ContainerInfo {
   rustEquivalent: string | null;
   stats: ContainerStats | null;
   imageSizeMb: number | null;
+  memberships?: GroupMembership[];
+}
+
+export interface GroupMembership {
+  kind: "category" | "project" | "network";
+  name: string;
+  via?: string;
 }
 
 export interface PortMapping {
@@ -77,12 +101,68 @@ export interface ContainerDetail extends ContainerInfo {
   healthCheck: HealthCheckConfig | null;
 }
 
+export interface LogEntry {
+  ts: string | null;
+  stream: "stdout" | "stderr";
+  line: string;
+  json?: Record<string, unknown>;
+}
+
+export type LogStreams = "stdout" | "stderr" | "both";
+
 export interface ContainerLogs {
   containerId: string;
   containerName: string;
-  logs: string[];
+  logs: LogEntry[];
   tail: number;
   since: string | null;
+  until: string | null;
+  streams: LogStreams;
+}
+
+export interface LogSearchHit {
+  container: string;
+  ts: string;
+  line: string;
+}
+
+export interface LogSearchResult {
+  query: string;
+  since: string;
+  hits: LogSearchHit[];
+  truncated: boolean;
+  indexedLines: number;
+}
+
+export interface ProcessEntry {
+  pid: number;
+  ppid: number | null;
+  state: string;
+  command: string;
+  user?: string;
+  cpuPercent?: number;
+  memoryKb?: number;
+  elapsed?: string;
+}
+
+export interface ContainerProcesses {
+  containerId: string;
+  containerName: string;
+  processes: ProcessEntry[];
+}
+
+export interface FileChange {
+  path: string;
+  kind: "added" | "modified" | "deleted";
+}
+
+export interface ContainerChanges {
+  containerId: string;
+  containerName: string;
+  added: number;
+  modified: number;
+  deleted: number;
+  changes: FileChange[];
 }
 
 export interface ActionResult {
@@ -129,12 +209,16 @@ export interface FlowchartNode {
   port: number | null;
   childFlowchart: string | null;
   metrics: NodeMetrics | null;
+  warnings?: string[];
+  position?: { x: number; y: number };
 }
 
 export interface NodeMetrics {
   cpuPercent: number | null;
   memoryMb: number | null;
   uptimeHours: number | null;
+  logErrorsPerMinute?: number;
+  logWarningsPerMinute?: number;
 }
 
 export interface FlowchartConnection {
@@ -189,26 +273,151 @@ class FlowScopeAPI {
     return this.fetch<NetworkInfo[]>("/networks");
   }
 
+  /** Swarm services with the tasks running them */
+  async getServices(): Promise<SwarmService[]> {
+    return this.fetch<SwarmService[]>("/services");
+  }
+
   async getFlowchart(id: string): Promise<Flowchart> {
     return this.fetch<Flowchart>(`/flowchart/${encodeURIComponent(id)}`);
   }
 
+  /**
+   * A flowchart as Mermaid or Graphviz source, top down unless `direction`
+   * says otherwise
+   */
+  async exportFlowchart(
+    id: string,
+    format: FlowchartFormat = "mermaid",
+    direction: FlowDirection = "td"
+  ): Promise<string> {
+    const params = new URLSearchParams({ format, direction });
+    const response = await fetch(
+      `${this.baseUrl}/flowchart/${encodeURIComponent(id)}/export?${params}`
+    );
+    if (!response.ok) {
+      const error = await response
+        .json()
+        .catch(() => ({ error: "Unknown error" }));
+      throw new Error(error.error || `HTTP ${response.status}`);
+    }
+    return response.text();
+  }
+
+  /** Where a flowchart is served as an SVG image, e.g. for an `<img>` */
+  flowchartSvgUrl(id: string): string {
+    return `${this.baseUrl}/flowchart/${encodeURIComponent(id)}.svg`;
+  }
+
+  async getFlowchartSvg(id: string): Promise<string> {
+    const response = await fetch(this.flowchartSvgUrl(id));
+    if (!response.ok) {
+      const error = await response
+        .json()
+        .catch(() => ({ error: "Unknown error" }));
+      throw new Error(error.error || `HTTP ${response.status}`);
+    }
+    return response.text();
+  }
+
+  async getSnapshots(): Promise<SnapshotSummary[]> {
+    return this.fetch<SnapshotSummary[]>("/history/snapshots");
+  }
+
+  /**
+   * What changed between two snapshots, each an id or an RFC 3339 time;
+   * `to` defaults to the latest snapshot
+   */
+  async getTopologyDiff(from: string, to?: string): Promise<TopologyDiff> {
+    const params = new URLSearchParams({ from });
+    if (to) params.set("to", to);
+    return this.fetch<TopologyDiff>(`/history/diff?${params}`);
+  }
+
+  /** Configured rules, firing alerts and recently resolved ones */
+  async getAlerts(): Promise<AlertList> {
+    return this.fetch<AlertList>("/alerts");
+  }
+
+  /** Delivery totals per notification channel and the latest deliveries */
+  async getNotificationStatus(): Promise<NotificationStatus> {
+    return this.fetch<NotificationStatus>("/notifications/status");
+  }
+
   async getContainer(id: string): Promise<ContainerInfo> {
     return this.fetch<ContainerInfo>(`/container/${encodeURIComponent(id)}`);
   }
 
-  async getContainerDetail(id: string): Promise<ContainerDetail> {
+  /** Environment values are only shown unredacted to admin tokens */
+  async getContainerDetail(
+    id: string,
+    redact: boolean = true
+  ): Promise<ContainerDetail> {
     return this.fetch<ContainerDetail>(
-      `/container/${encodeURIComponent(id)}/detail`
+      `/container/${encodeURIComponent(id)}/detail?redact=${redact}`
     );
   }
 
+  /**
+   * `since` and `until` take RFC 3339, Unix seconds or an age such as `15m`;
+   * `grep` keeps the last `tail` lines that contain it
+   */
   async getContainerLogs(
     id: string,
-    tail: number = 100
+    tail: number = 100,
+    options: {
+      since?: string;
+      until?: string;
+      streams?: LogStreams;
+      grep?: string;
+      regex?: boolean;
+      stripAnsi?: boolean;
+      parseJson?: boolean;
+    } = {}
   ): Promise<ContainerLogs> {
+    const params = new URLSearchParams({ tail: String(tail) });
+    if (options.since) params.set("since", options.since);
+    if (options.until) params.set("until", options.until);
+    if (options.streams) params.set("streams", options.streams);
+    if (options.grep) params.set("grep", options.grep);
+    if (options.regex) params.set("regex", "true");
+    if (options.stripAnsi) params.set("strip_ansi", "true");
+    if (options.parseJson) params.set("parse_json", "true");
     return this.fetch<ContainerLogs>(
-      `/container/${encodeURIComponent(id)}/logs?tail=${tail}`
+      `/container/${encodeURIComponent(id)}/logs?${params}`
+    );
+  }
+
+  async searchLogs(
+    q: string,
+    options: { container?: string; range?: string; limit?: number } = {}
+  ): Promise<LogSearchResult> {
+    const params = new URLSearchParams({ q });
+    if (options.container) params.set("container", options.container);
+    if (options.range) params.set("range", options.range);
+    if (options.limit) params.set("limit", String(options.limit));
+    return this.fetch<LogSearchResult>(`/logs/search?${params}`);
+  }
+
+  async getContainerProcesses(id: string): Promise<ContainerProcesses> {
+    return this.fetch<ContainerProcesses>(
+      `/container/${encodeURIComponent(id)}/processes`
+    );
+  }
+
+  /** Environment values are only shown unredacted to admin tokens */
+  async getContainerInspect(
+    id: string,
+    redact: boolean = true
+  ): Promise<Record<string, unknown>> {
+    return this.fetch<Record<string, unknown>>(
+      `/container/${encodeURIComponent(id)}/inspect?redact=${redact}`
+    );
+  }
+
+  async getContainerChanges(id: string): Promise<ContainerChanges> {
+    return this.fetch<ContainerChanges>(
+      `/container/${encodeURIComponent(id)}/changes`
     );
   }
 
@@ -260,6 +469,79 @@ class FlowScopeAPI {
     return response.json();
   }
 
+  async pauseContainer(id: string): Promise<ActionResult> {
+    const response = await fetch(
+      `${this.baseUrl}/container/${encodeURIComponent(id)}/pause`,
+      {
+        method: "POST",
+      }
+    );
+    if (!response.ok) {
+      const error = await response
+        .json()
+        .catch(() => ({ error: "Unknown error" }));
+      throw new Error(error.error || `HTTP ${response.status}`);
+    }
+    return response.json();
+  }
+
+  async unpauseContainer(id: string): Promise<ActionResult> {
+    const response = await fetch(
+      `${this.baseUrl}/container/${encodeURIComponent(id)}/unpause`,
+      {
+        method: "POST",
+      }
+    );
+    if (!response.ok) {
+      const error = await response
+        .json()
+        .catch(() => ({ error: "Unknown error" }));
+      throw new Error(error.error || `HTTP ${response.status}`);
+    }
+    return response.json();
+  }
+
+  async removeContainer(
+    id: string,
+    options: { force?: boolean; removeVolumes?: boolean } = {}
+  ): Promise<ActionResult> {
+    const params = new URLSearchParams({
+      force: String(options.force ?? false),
+      remove_volumes: String(options.removeVolumes ?? false),
+    });
+    const response = await fetch(
+      `${this.baseUrl}/container/${encodeURIComponent(id)}?${params}`,
+      {
+        method: "DELETE",
+      }
+    );
+    if (!response.ok) {
+      const error = await response
+        .json()
+        .catch(() => ({ error: "Unknown error" }));
+      throw new Error(error.error || `HTTP ${response.status}`);
+    }
+    return response.json();
+  }
+
+  async killContainer(id: string, signal?: string): Promise<ActionResult> {
+    const response = await fetch(
+      `${this.baseUrl}/container/${encodeURIComponent(id)}/kill`,
+      {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ signal }),
+      }
+    );
+    if (!response.ok) {
+      const error = await response
+        .json()
+        .catch(() => ({ error: "Unknown error" }));
+      throw new Error(error.error || `HTTP ${response.status}`);
+    }
+    return response.json();
+  }
+
   async checkHealth(): Promise<boolean> {
     try {
       const response = await fetch(
@@ -277,6 +559,36 @@ class FlowScopeAPI {
     );
   }
 
+  /**
+   * Topology and container updates over Server-Sent Events, for proxies that
+   * refuse WebSocket upgrades; the browser resumes from the last event id
+   * when it reconnects
+   */
+  streamEvents(onUpdate: (envelope: WsEnvelope) => void): EventSource {
+    const source = new EventSource(`${this.baseUrl}/events/stream`);
+    const handle = (event: MessageEvent) => onUpdate(JSON.parse(event.data));
+    source.addEventListener("topologyUpdate", handle);
+    source.addEventListener("containerUpdate", handle);
+    source.onmessage = handle;
+    return source;
+  }
+
+  /**
+   * Recorded stats over the last `range` (such as `6h`), averaged per `step`
+   * (such as `1m`)
+   */
+  async getStatsHistory(
+    id: string,
+    options: { range?: string; step?: string } = {}
+  ): Promise<StatsHistory> {
+    const params = new URLSearchParams();
+    if (options.range) params.set("range", options.range);
+    if (options.step) params.set("step", options.step);
+    return this.fetch<StatsHistory>(
+      `/container/${encodeURIComponent(id)}/stats/history?${params}`
+    );
+  }
+
   async getContainersWithStats(): Promise<ContainerInfo[]> {
     return this.fetch<ContainerInfo[]>("/containers/stats");
   }
@@ -284,6 +596,19 @@ class FlowScopeAPI {
   async getImageSizes(): Promise<Record<string, number>> {
     return this.fetch<Record<string, number>>("/images/sizes");
   }
+
+  /**
+   * Usage summed per image; with a `range` (such as `6h`) each image also
+   * carries its recorded history, summed per `step`
+   */
+  async getImageUsage(
+    options: { range?: string; step?: string } = {}
+  ): Promise<ImageUsage[]> {
+    const params = new URLSearchParams();
+    if (options.range) params.set("range", options.range);
+    if (options.step) params.set("step", options.step);
+    return this.fetch<ImageUsage[]>(`/images/usage?${params}`);
+  }
 }
 
 export const api = new FlowScopeAPI();
@@ -3,6 +3,9 @@ export type {
   ContainerInfo,
   ContainerDetail,
   ContainerLogs,
+  LogEntry,
+  LogSearchHit,
+  LogSearchResult,
   ActionResult,
   PortMapping,
   VolumeMount,
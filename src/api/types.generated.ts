@@ -0,0 +1,1334 @@
+// Generated from the flowscope-types crate. Do not edit by hand.
+// Regenerate with: cargo run -p flowscope-types --features ts --bin flowscope-ts -- <path>
+
+export type JsonValue = number | string | boolean | Array<JsonValue> | { [key in string]?: JsonValue } | null;
+
+export type ContainerStatus = "running" | "healthy" | "unhealthy" | "exited" | "created" | "paused" | "restarting" | "dead";
+
+export type ContainerStats = { cpuPercent: number, memoryUsageMb: number, memoryLimitMb: number, memoryPercent: number, networkRxMb: number, networkTxMb: number, networkRxErrors: number, networkTxErrors: number, networkRxDropped: number, networkTxDropped: number, blockReadMb: number, blockWriteMb: number, 
+/**
+ * Receive rate over the window since the previous sample; absent on a
+ * container's first sample and right after a restart
+ */
+networkRxBytesPerSec?: number, networkTxBytesPerSec?: number, blockReadBytesPerSec?: number, blockWriteBytesPerSec?: number, 
+/**
+ * Packets dropped per second over the same window as the byte rates
+ */
+networkRxDroppedPerSec?: number, networkTxDroppedPerSec?: number, pids: number, pidsLimit?: number, 
+/**
+ * Open file descriptors across the container's processes; only known
+ * when FlowScope can read the Docker host's `/proc`
+ */
+openFds?: number, 
+/**
+ * Highest share of any one process's open-files limit in use
+ */
+fdPercent?: number, inotifyInstances?: number, inotifyWatches?: number, 
+/**
+ * Share of the per-user `fs.inotify.max_user_watches` budget in use
+ */
+inotifyWatchPercent?: number, };
+
+export type ServiceCategory = "aiml" | "application" | "infrastructure" | "frontend" | "monitoring" | "game" | "val" | "blockchain" | "other";
+
+export type ContainerInfo = { id: string, name: string, image: string, status: ContainerStatus, health: string | null, category: ServiceCategory, ports: Array<PortMapping>, networks: Array<string>, created: string, labels: { [key in string]?: string }, 
+/**
+ * Name of the Docker host the container runs on, when FlowScope
+ * watches several
+ */
+host?: string, rust_equivalent?: string, stats?: ContainerStats, image_size_mb?: number, 
+/**
+ * Every logical group the container belongs to: its category, compose
+ * projects and user-defined networks
+ */
+memberships: Array<GroupMembership>, 
+/**
+ * Named volumes it mounts
+ */
+volume_names: Array<string>, 
+/**
+ * When a container of this name last started, as far as the daemon's
+ * events and FlowScope's listings show
+ */
+last_started?: string, 
+/**
+ * When it last started, stopped, paused, restarted or changed health
+ */
+last_status_change?: string, 
+/**
+ * When it was last created from a different image
+ */
+last_image_change?: string, 
+/**
+ * When it was last created with a different command, labels, ports,
+ * networks or mounts, renamed or updated
+ */
+last_config_change?: string, 
+/**
+ * The latest HTTP health probe of its first published port, when probes
+ * are on
+ */
+probe?: HealthProbe, 
+/**
+ * Vulnerabilities found in its image, when scanning is on and the image
+ * was scanned
+ */
+vulnerabilities?: VulnerabilitySummary, };
+
+export type HealthProbe = { 
+/**
+ * What was requested, such as `http://127.0.0.1:8080/health`
+ */
+url: string, 
+/**
+ * Whether anything answered over HTTP
+ */
+reachable: boolean, 
+/**
+ * Whether the answer was a 2xx or 3xx
+ */
+ok: boolean, status?: number, 
+/**
+ * Time to the response headers
+ */
+latencyMs?: number, error?: string, checkedAt: string, };
+
+export type Scanner = "trivy" | "grype";
+
+export type VulnerabilitySeverity = "unknown" | "low" | "medium" | "high" | "critical";
+
+export type Vulnerability = { 
+/**
+ * CVE or advisory id, such as `CVE-2024-3094`
+ */
+id: string, package: string, installedVersion: string, 
+/**
+ * First version without it, when a fix exists
+ */
+fixedVersion?: string, severity: VulnerabilitySeverity, title?: string, };
+
+export type VulnerabilitySummary = { critical: number, high: number, medium: number, low: number, unknown: number, 
+/**
+ * How many have a fixed version available
+ */
+fixable: number, };
+
+export type VulnerabilityReport = { 
+/**
+ * The reference that was scanned, such as `postgres:16`
+ */
+image: string, imageId: string, scanner: Scanner, scannedAt: string, summary: VulnerabilitySummary, 
+/**
+ * Most severe first
+ */
+vulnerabilities: Array<Vulnerability>, };
+
+export type CategoryStrategyKind = "label" | "compose" | "regex" | "heuristic";
+
+export type CategoryStep = { strategy: CategoryStrategyKind, category?: ServiceCategory, 
+/**
+ * Why the strategy did or did not apply
+ */
+detail: string, };
+
+export type CategoryExplanation = { container: string, category: ServiceCategory, 
+/**
+ * The strategy that decided; absent when none did and `other` was used
+ */
+strategy?: CategoryStrategyKind, steps: Array<CategoryStep>, };
+
+export type MembershipKind = "category" | "project" | "network";
+
+export type GroupMembership = { kind: MembershipKind, name: string, 
+/**
+ * For a project the container was not started by: the project's
+ * network it is attached to
+ */
+via?: string, };
+
+export type PortMapping = { host_port: number | null, container_port: number, protocol: string, };
+
+export type ContainerDetail = { environment: Array<string>, command: string | null, entrypoint: Array<string> | null, workingDir: string | null, volumes: Array<VolumeMount>, healthCheck: HealthCheckConfig | null, 
+/**
+ * `linux` or `windows`; a Windows daemon can run either
+ */
+platform?: string, 
+/**
+ * CPU pinning and scheduling weight
+ */
+cpu?: CpuPlacement, id: string, name: string, image: string, status: ContainerStatus, health: string | null, category: ServiceCategory, ports: Array<PortMapping>, networks: Array<string>, created: string, labels: { [key in string]?: string }, 
+/**
+ * Name of the Docker host the container runs on, when FlowScope
+ * watches several
+ */
+host?: string, rust_equivalent?: string, stats?: ContainerStats, image_size_mb?: number, 
+/**
+ * Every logical group the container belongs to: its category, compose
+ * projects and user-defined networks
+ */
+memberships: Array<GroupMembership>, 
+/**
+ * Named volumes it mounts
+ */
+volume_names: Array<string>, 
+/**
+ * When a container of this name last started, as far as the daemon's
+ * events and FlowScope's listings show
+ */
+last_started?: string, 
+/**
+ * When it last started, stopped, paused, restarted or changed health
+ */
+last_status_change?: string, 
+/**
+ * When it was last created from a different image
+ */
+last_image_change?: string, 
+/**
+ * When it was last created with a different command, labels, ports,
+ * networks or mounts, renamed or updated
+ */
+last_config_change?: string, 
+/**
+ * The latest HTTP health probe of its first published port, when probes
+ * are on
+ */
+probe?: HealthProbe, 
+/**
+ * Vulnerabilities found in its image, when scanning is on and the image
+ * was scanned
+ */
+vulnerabilities?: VulnerabilitySummary, };
+
+export type VolumeMount = { source: string, destination: string, mode: string, };
+
+export type HealthCheckConfig = { test: Array<string>, intervalSeconds: number, timeoutSeconds: number, retries: number, startPeriodSeconds: number, };
+
+export type LogStream = "stdout" | "stderr";
+
+export type LogStreams = "stdout" | "stderr" | "both";
+
+export type LogEntry = { 
+/**
+ * When the daemon received the line; `None` if it sent no timestamp
+ */
+ts: string | null, stream: LogStream, line: string, 
+/**
+ * The object the line holds, when JSON parsing was asked for
+ */
+json?: JsonValue, };
+
+export type ContainerLogs = { containerId: string, containerName: string, logs: Array<LogEntry>, tail: number, since: string | null, until: string | null, streams: LogStreams, };
+
+export type LogSearchHit = { container: string, 
+/**
+ * When the line was indexed
+ */
+ts: string, line: string, };
+
+export type LogSearchResult = { query: string, 
+/**
+ * Start of the searched range
+ */
+since: string, hits: Array<LogSearchHit>, 
+/**
+ * More lines matched than `limit`
+ */
+truncated: boolean, 
+/**
+ * Lines currently in the index
+ */
+indexedLines: number, };
+
+export type ActionResult = { success: boolean, containerId: string, containerName: string, action: string, message: string, };
+
+export type NodeType = "service" | "process" | "decision" | "group";
+
+export type ConnectionType = "primary" | "secondary" | "data" | "control" | "network" | "volume" | "depends";
+
+export type FlowchartNode = { id: string, name: string, description: string, status: ContainerStatus, nodeType: NodeType, category: ServiceCategory, port?: number, childFlowchart?: string, metrics?: NodeMetrics, stats?: ContainerStats, 
+/**
+ * Diagnostic warnings rendered as a badge on the node
+ */
+warnings: Array<string>, 
+/**
+ * The container's latest HTTP health probe
+ */
+probe?: HealthProbe, 
+/**
+ * Vulnerability counts of the container's image, for a severity badge
+ */
+vulnerabilities?: VulnerabilitySummary, 
+/**
+ * Suggested canvas position, kept stable across refreshes
+ */
+position?: NodePosition, };
+
+export type NodePosition = { x: number, y: number, };
+
+export type NodeMetrics = { cpuPercent: number | null, memoryMb: number | null, uptimeHours: number | null, imageSizeMb?: number, 
+/**
+ * Error lines logged per minute, over the last five minutes; absent
+ * unless log rates are turned on
+ */
+logErrorsPerMinute?: number, 
+/**
+ * Warning lines logged per minute, over the last five minutes
+ */
+logWarningsPerMinute?: number, };
+
+export type FlowchartConnection = { id: string, source: string, target: string, label: string | null, connectionType: ConnectionType, };
+
+export type Flowchart = { id: string, name: string, description: string, nodes: Array<FlowchartNode>, connections: Array<FlowchartConnection>, parentId?: string, };
+
+export type FlowchartSummary = { id: string, name: string, nodeCount: number, category: ServiceCategory, };
+
+export type FlowDirection = "lr" | "td";
+
+export type FlowchartFormat = "mermaid" | "dot";
+
+export type SystemTopology = { totalContainers: number, runningContainers: number, healthyContainers: number, unhealthyContainers: number, categories: { [key in string]?: number }, flowcharts: Array<FlowchartSummary>, generatedAt: string, host?: HostResources, };
+
+export type TopologyChange = { 
+/**
+ * Current topology version; pass it back as `version` to wait for the next change
+ */
+version: number, 
+/**
+ * Whether the version moved past the one asked about before the timeout
+ */
+changed: boolean, 
+/**
+ * The new topology, when it changed
+ */
+topology?: SystemTopology, };
+
+export type HostResources = { cpus: number, loadAverage1m: number | null, cpuLoadPercent: number | null, 
+/**
+ * Sum of CPU limits across running containers
+ */
+cpuCommitted: number, cpuHeadroom: number, containersWithoutCpuLimit: number, memoryTotalMb: number, memoryAvailableMb: number | null, memoryUsedPercent: number | null, 
+/**
+ * Sum of memory limits across running containers
+ */
+memoryCommittedMb: number, memoryCommittedPercent: number, 
+/**
+ * Negative when limits are overcommitted
+ */
+memoryHeadroomMb: number, containersWithoutMemoryLimit: number, diskPath: string, diskTotalGb: number | null, diskAvailableGb: number | null, diskUsedPercent: number | null, };
+
+export type CpuPlacement = { 
+/**
+ * `--cpuset-cpus` as configured, e.g. `0-3,8`
+ */
+cpusetCpus?: string, 
+/**
+ * `--cpuset-mems`: NUMA nodes the container may allocate memory on
+ */
+cpusetMems?: string, 
+/**
+ * Cores the container is pinned to; empty when it may run on any
+ */
+cores: Array<number>, 
+/**
+ * Relative weight under contention (`--cpu-shares`), 1024 when unset
+ */
+cpuShares: number, 
+/**
+ * The same weight on the cgroup v2 scale (1-10000, 100 when unset)
+ */
+cpuWeight: number, 
+/**
+ * Hard limit in CPUs, from `--cpus` or quota/period
+ */
+cpuLimit?: number, };
+
+export type NumaNode = { node: number, cores: Array<number>, };
+
+export type CoreAssignment = { core: number, numaNode: number, 
+/**
+ * Running containers pinned to this core
+ */
+containers: Array<string>, 
+/**
+ * More than one pinned container competes for this core
+ */
+contended: boolean, };
+
+export type PinnedContainer = { container: string, cores: Array<number>, numaNodes: Array<number>, 
+/**
+ * Pinned across NUMA nodes, so some memory accesses are remote
+ */
+spansNumaNodes: boolean, cpuWeight: number, };
+
+export type CpuMap = { cpus: number, numaNodes: Array<NumaNode>, cores: Array<CoreAssignment>, pinned: Array<PinnedContainer>, 
+/**
+ * Running containers without a cpuset, free to run on any core
+ */
+floating: Array<string>, contendedCores: number, };
+
+export type NetworkInfo = { id: string, name: string, driver: string, containers: Array<string>, };
+
+export type VolumeInfo = { name: string, driver: string, 
+/**
+ * Where the volume's data lives on its host
+ */
+mountpoint: string, 
+/**
+ * `local` or `global`
+ */
+scope?: string, labels: { [key in string]?: string }, 
+/**
+ * Names of the containers mounting it, running or not
+ */
+containers: Array<string>, };
+
+export type ImageUsage = { image: string, containerCount: number, runningCount: number, cpuPercent: number, memoryUsageMb: number, memoryLimitMb: number, networkRxMb: number, networkTxMb: number, pids: number, 
+/**
+ * Names of the containers running the image
+ */
+containers: Array<string>, imageSizeMb?: number, 
+/**
+ * Usage over the requested `range`, oldest first; only present when a
+ * range was asked for
+ */
+history?: Array<ImageUsagePoint>, };
+
+export type ImageUsagePoint = { 
+/**
+ * Start of the step
+ */
+at: string, 
+/**
+ * Containers sampled in this step
+ */
+containers: number, cpuPercent: number, memoryUsageMb: number, 
+/**
+ * Containers without a known rate count as 0
+ */
+networkRxBytesPerSec: number, networkTxBytesPerSec: number, pids: number, };
+
+export type ImageInfo = { id: string, 
+/**
+ * `repository:tag` references; empty for dangling images
+ */
+tags: Array<string>, 
+/**
+ * `repository@sha256:...` references of pulled images
+ */
+digests: Array<string>, 
+/**
+ * Untagged, typically left behind when a newer pull took its tag
+ */
+dangling: boolean, sizeMb: number, created: string, 
+/**
+ * Names of the containers created from it, running or not
+ */
+containers: Array<string>, };
+
+export type ImagePullRequest = { 
+/**
+ * `repository[:tag]` or `repository@digest`; `latest` when no tag is given
+ */
+image: string, };
+
+export type ImageRun = { 
+/**
+ * Image id (`sha256:...`), which a rollback recreates the container from
+ */
+digest: string, 
+/**
+ * Reference the container was created with, such as `api:latest`
+ */
+image: string, 
+/**
+ * Container seen running it
+ */
+container: string, since: string, };
+
+export type ImageTimeline = { 
+/**
+ * `<project>/<service>` for compose services, else the container name
+ */
+service: string, 
+/**
+ * Oldest first; the last run is the current image
+ */
+runs: Array<ImageRun>, };
+
+export type TopologyExport = { topology: SystemTopology, containers: Array<ContainerInfo>, networks: Array<NetworkInfo>, };
+
+export type SnapshotContainer = { name: string, id: string, image: string, status: ContainerStatus, };
+
+export type SnapshotEdge = { source: string, target: string, 
+/**
+ * `network`, `env`, `depends_on` or `socket`
+ */
+kind: string, };
+
+export type TopologySnapshot = { 
+/**
+ * `YYYYMMDDTHHMMSSZ` of `taken_at`, so ids sort by time
+ */
+id: string, takenAt: string, 
+/**
+ * Sorted by name
+ */
+containers: Array<SnapshotContainer>, edges: Array<SnapshotEdge>, };
+
+export type SnapshotSummary = { id: string, takenAt: string, containerCount: number, edgeCount: number, };
+
+export type ContainerChange = { name: string, 
+/**
+ * Which of `id`, `image` and `status` changed; a new `id` means the
+ * container was recreated
+ */
+fields: Array<string>, before: SnapshotContainer, after: SnapshotContainer, };
+
+export type TopologyDiff = { from: SnapshotSummary, to: SnapshotSummary, added: Array<SnapshotContainer>, removed: Array<SnapshotContainer>, changed: Array<ContainerChange>, addedEdges: Array<SnapshotEdge>, removedEdges: Array<SnapshotEdge>, };
+
+export type ContainerSpec = { name: string | null, image: string, 
+/**
+ * `KEY=value` pairs, as Docker expects them
+ */
+env: Array<string>, ports: Array<PortSpec>, networks: Array<string>, 
+/**
+ * `source:destination[:mode]` entries; named volumes or absolute host paths
+ */
+volumes: Array<string>, labels: { [key in string]?: string }, };
+
+export type PortSpec = { containerPort: number, hostPort: number | null, protocol: string, };
+
+export type PreflightStatus = "pass" | "warn" | "fail" | "skip";
+
+export type PreflightCheck = { check: string, status: PreflightStatus, message: string, };
+
+export type PreflightReport = { ok: boolean, checks: Array<PreflightCheck>, };
+
+export type ProcessEntry = { pid: number, ppid: number | null, state: string, command: string, 
+/**
+ * Listed only when the top call asked for these columns
+ */
+user?: string, cpuPercent?: number, memoryKb?: number, 
+/**
+ * Running time as `ps` prints it, such as `02:13:07`
+ */
+elapsed?: string, };
+
+export type ContainerProcesses = { containerId: string, containerName: string, 
+/**
+ * Empty unless the container is running
+ */
+processes: Array<ProcessEntry>, };
+
+export type FileChangeKind = "added" | "modified" | "deleted";
+
+export type FileChange = { path: string, kind: FileChangeKind, };
+
+export type ContainerChanges = { containerId: string, containerName: string, added: number, modified: number, deleted: number, 
+/**
+ * Sorted by path; directories holding a change are listed as modified
+ */
+changes: Array<FileChange>, };
+
+export type ProcessDiagnostics = { containerId: string, containerName: string, processCount: number, zombieCount: number, zombies: Array<ProcessEntry>, pidsLimit: number | null, pidUsagePercent: number | null, warnings: Array<string>, };
+
+export type Severity = "info" | "warning" | "error";
+
+export type DiagnosticFinding = { containerId: string, containerName: string, check: string, severity: Severity, message: string, suggestion?: string, };
+
+export type DiagnosticsReport = { containersChecked: number, errors: number, warnings: number, infos: number, findings: Array<DiagnosticFinding>, generatedAt: string, };
+
+export type HostParameter = { 
+/**
+ * sysctl name, e.g. `net.core.somaxconn`
+ */
+name: string, 
+/**
+ * Current value; absent when the host does not expose it
+ */
+value?: string, 
+/**
+ * Value below which it gets flagged
+ */
+recommended?: string, };
+
+export type HostFinding = { parameter: string, severity: Severity, message: string, suggestion?: string, };
+
+export type HostDiagnostics = { parameters: Array<HostParameter>, errors: number, warnings: number, findings: Array<HostFinding>, generatedAt: string, };
+
+export type CheckRule = { "rule": "all-healthy", exclude: Array<string>, } | { "rule": "no-latest-tags", exclude: Array<string>, } | { "rule": "required-services", services: Array<string>, } | { "rule": "diagnostics", severity: Severity, };
+
+export type CheckRules = { rules: Array<CheckRule>, };
+
+export type RuleOutcome = { rule: string, passed: boolean, violations: Array<string>, };
+
+export type CheckReport = { passed: boolean, results: Array<RuleOutcome>, checkedAt: string, };
+
+export type TemplateVariable = { name: string, description: string | null, default: string | null, required: boolean, };
+
+export type ContainerTemplate = { name: string, description: string | null, 
+/**
+ * A `ContainerSpec` whose string values may contain `${VAR}` placeholders
+ */
+spec: JsonValue, variables: Array<TemplateVariable>, 
+/**
+ * Profile name -> variable values
+ */
+profiles: { [key in string]?: { [key in string]?: string } }, createdAt: string, updatedAt: string, };
+
+export type InstantiateRequest = { profile: string | null, variables: { [key in string]?: string }, };
+
+export type InstantiateResponse = { template: string, profile: string | null, variables: { [key in string]?: string }, spec: ContainerSpec, preflight: PreflightReport, };
+
+export type ContainerGroup = { name: string, description: string | null, 
+/**
+ * Container names that always belong to the group
+ */
+containers: Array<string>, 
+/**
+ * Label selectors that must all match; a value of `*` only requires the key
+ */
+labels: { [key in string]?: string }, createdAt: string, updatedAt: string, };
+
+export type GroupStats = { group: string, containerCount: number, runningCount: number, cpuPercent: number, memoryUsageMb: number, memoryLimitMb: number, networkRxMb: number, networkTxMb: number, 
+/**
+ * Summed over the members whose rates are known
+ */
+networkRxBytesPerSec: number, networkTxBytesPerSec: number, blockReadBytesPerSec: number, blockWriteBytesPerSec: number, pids: number, 
+/**
+ * Members with their individual stats
+ */
+containers: Array<ContainerInfo>, generatedAt: string, };
+
+export type WidgetTarget = { "kind": "container", name: string, } | { "kind": "group", name: string, } | { "kind": "category", category: ServiceCategory, };
+
+export type WidgetKind = { "type": "metric-chart", metric: AlertMetric, minutes: number, } | { "type": "log-panel", tail: number, } | { "type": "flowchart" } | { "type": "endpoint-list" };
+
+export type WidgetLayout = { x: number, y: number, w: number, h: number, };
+
+export type DashboardWidget = { 
+/**
+ * Unique within the dashboard; the widget's data is returned under it
+ */
+id: string, title: string | null, target: WidgetTarget, layout: WidgetLayout | null, } & ({ "type": "metric-chart", metric: AlertMetric, minutes: number, } | { "type": "log-panel", tail: number, } | { "type": "flowchart" } | { "type": "endpoint-list" });
+
+export type Dashboard = { 
+/**
+ * Assigned by the server when the dashboard is created
+ */
+id: string, name: string, description: string | null, widgets: Array<DashboardWidget>, createdAt: string, updatedAt: string, };
+
+export type MetricPoint = { at: string, value: number, };
+
+export type MetricSeries = { container: string, points: Array<MetricPoint>, };
+
+export type StatsPoint = { 
+/**
+ * Start of the step
+ */
+at: string, 
+/**
+ * Samples averaged into this point
+ */
+samples: number, cpuPercent: number, cpuPercentMax: number, memoryUsageMb: number, memoryUsageMbMax: number, memoryPercent: number, 
+/**
+ * Averages of the samples that had a rate
+ */
+networkRxBytesPerSec?: number, networkTxBytesPerSec?: number, blockReadBytesPerSec?: number, blockWriteBytesPerSec?: number, pids: number, };
+
+export type StatsHistory = { container: string, from: string, to: string, stepSecs: number, 
+/**
+ * Oldest first; steps without samples, such as while the container was
+ * stopped, have no point
+ */
+points: Array<StatsPoint>, };
+
+export type PublishedEndpoint = { 
+/**
+ * `<container>:<hostPort>`, as taken by `POST /api/endpoints/:id/test`
+ */
+endpoint: string, container: string, containerPort: number, hostPort: number, protocol: string, };
+
+export type WidgetPayload = { "type": "metric-chart", metric: AlertMetric, series: Array<MetricSeries>, } | { "type": "log-panel", logs: Array<ContainerLogs>, } | { "type": "flowchart", flowchart: Flowchart, } | { "type": "endpoint-list", endpoints: Array<PublishedEndpoint>, };
+
+export type WidgetData = { widget: string, data?: WidgetPayload, error?: string, };
+
+export type DashboardData = { dashboard: string, widgets: Array<WidgetData>, resolvedAt: string, };
+
+export type ConfigBundle = { version: number, exportedAt: string, templates: Array<ContainerTemplate>, groups: Array<ContainerGroup>, dashboards: Array<Dashboard>, notifications: Array<NotificationChannel>, 
+/**
+ * Base64url HMAC-SHA256 over the bundle's JSON form with this field empty
+ */
+signature: string, };
+
+export type ImportMode = "merge" | "replace";
+
+export type ImportChanges = { created: Array<string>, updated: Array<string>, removed: Array<string>, };
+
+export type ImportReport = { mode: ImportMode, 
+/**
+ * When true nothing was written
+ */
+dryRun: boolean, templates: ImportChanges, groups: ImportChanges, 
+/**
+ * Keyed by dashboard id
+ */
+dashboards: ImportChanges, notifications: ImportChanges, };
+
+export type ComposeProject = { name: string, 
+/**
+ * Services with their `depends_on` dependencies first
+ */
+services: Array<string>, 
+/**
+ * Member container names, in the order `up` starts them
+ */
+containers: Array<string>, running: number, };
+
+export type SwarmService = { id: string, name: string, image: string, 
+/**
+ * `replicated`, `global` or the job modes; empty when the service is
+ * only known from its tasks
+ */
+mode: string, 
+/**
+ * Replica count of a replicated service
+ */
+desiredReplicas?: number, runningTasks: number, 
+/**
+ * `com.docker.stack.namespace` of services deployed with `docker stack`
+ */
+stack?: string, 
+/**
+ * Ports published through the routing mesh
+ */
+ports: Array<PortMapping>, tasks: Array<SwarmTask>, };
+
+export type SwarmTask = { id: string, 
+/**
+ * `<service>.<slot>.<task id>`, or `<service>.<node id>.<task id>` for
+ * global services
+ */
+name: string, service: string, serviceId: string, slot?: number, nodeId: string, 
+/**
+ * Docker host the container was listed on, when watching several
+ */
+host?: string, containerId: string, containerName: string, status: ContainerStatus, };
+
+export type EnvDrift = { variable: string, container: string, expected: string, actual: string, };
+
+export type ServiceEnvDrift = { service: string, containers: Array<string>, 
+/**
+ * Whether any replica runs with a variable the `.env` file declares;
+ * `unset` and `extra` are only reported for services that do
+ */
+usesEnvFile: boolean, 
+/**
+ * Declared in `.env` but missing from a replica
+ */
+unset: Array<string>, 
+/**
+ * Set on a replica but not declared in `.env`
+ */
+extra: Array<string>, drifted: Array<EnvDrift>, };
+
+export type EnvDriftReport = { project: string, uploadedAt: string, 
+/**
+ * Variables the `.env` file declares
+ */
+declared: Array<string>, services: Array<ServiceEnvDrift>, 
+/**
+ * True when no service has unset or drifted variables
+ */
+ok: boolean, };
+
+export type JobStatus = "running" | "succeeded" | "failed";
+
+export type Job = { id: string, 
+/**
+ * What the job does, e.g. `project-up`
+ */
+kind: string, 
+/**
+ * What it acts on, e.g. a project name
+ */
+target: string, status: JobStatus, 
+/**
+ * Steps planned; progress is `results.length` of this
+ */
+totalSteps: number, results: Array<ActionResult>, error?: string, createdAt: string, finishedAt?: string, 
+/**
+ * Ids of the artifacts the job produced, downloadable at `/api/artifacts/:id`
+ */
+artifacts: Array<string>, };
+
+export type ArtifactKind = "pcap" | "logs" | "report" | "backup";
+
+export type Artifact = { id: string, kind: ArtifactKind, 
+/**
+ * Name offered to the browser when downloading
+ */
+fileName: string, contentType: string, sizeBytes: number, 
+/**
+ * Hex SHA-256 of the content, also sent as the download's ETag
+ */
+sha256: string, 
+/**
+ * Container the artifact was produced from
+ */
+container?: string, jobId?: string, createdAt: string, expiresAt: string, };
+
+export type SocketSource = "exec" | "procfs";
+
+export type ListeningPort = { port: number, 
+/**
+ * Bound address; `0.0.0.0` or `::` for every interface
+ */
+address: string, 
+/**
+ * Whether the image exposes the port or it is published
+ */
+declared: boolean, };
+
+export type SocketPeer = { address: string, port: number, 
+/**
+ * Container the address belongs to
+ */
+container?: string, };
+
+export type ContainerSockets = { container: string, source: SocketSource, listening: Array<ListeningPort>, connections: Array<SocketPeer>, };
+
+export type EventActor = { 
+/**
+ * Container, image, network or volume id
+ */
+id: string, name?: string, };
+
+export type EventOrigin = { method: string, path: string, 
+/**
+ * Name of the API token that made the request, when auth is enabled
+ */
+token?: string, requestedAt: string, };
+
+export type DockerEvent = { time: string, 
+/**
+ * `container`, `image`, `network`, `volume`, `daemon`, ...
+ */
+type: string, 
+/**
+ * `start`, `die`, `kill`, `health_status`, `exec_start`, ...
+ */
+action: string, 
+/**
+ * What followed the action's colon, such as the new health status or
+ * the command an exec ran
+ */
+detail?: string, actor: EventActor, 
+/**
+ * Labels and event-specific attributes such as `exitCode` or `signal`
+ */
+attributes: { [key in string]?: string }, 
+/**
+ * Set when the event followed a FlowScope action; absent for changes
+ * made outside FlowScope
+ */
+origin?: EventOrigin, };
+
+export type AlertMetric = "cpu_percent" | "memory_percent" | "memory_usage_mb" | "pids" | "network_rx_errors" | "network_tx_errors" | "network_rx_dropped" | "network_tx_dropped" | "fd_percent" | "inotify_watch_percent";
+
+export type AlertOperator = "gt" | "gte" | "lt" | "lte";
+
+export type AlertRule = { name: string, metric: AlertMetric, operator: AlertOperator, threshold: number, 
+/**
+ * How long the condition must hold before the rule fires; 0 fires on the first sample
+ */
+forSeconds: number, 
+/**
+ * Container names the rule applies to; a trailing `*` matches a prefix,
+ * and an empty list means every container
+ */
+containers: Array<string>, 
+/**
+ * Names of [`ContainerGroup`]s; when set the rule applies only to their
+ * members
+ */
+groups: Array<string>, 
+/**
+ * Container selector such as `category=aiml and label team=core`; the
+ * rule applies to current containers matching it, `containers` and `groups`
+ */
+selector?: string, };
+
+export type AlertRuleTest = { rule: AlertRule, 
+/**
+ * How far back to evaluate; defaults to the whole retained history
+ */
+hours?: number, };
+
+export type AlertFiring = { container: string, firedAt: string, 
+/**
+ * When the condition stopped holding; absent while it still holds
+ */
+resolvedAt?: string, 
+/**
+ * Most extreme value seen while firing
+ */
+peak: number, };
+
+export type AlertRuleTestReport = { rule: AlertRule, from: string, to: string, containersEvaluated: number, samplesEvaluated: number, firings: Array<AlertFiring>, };
+
+export type AlertCondition = { "kind": "stat", metric: AlertMetric, operator: AlertOperator, threshold: number, } | { "kind": "status", status: ContainerStatus, };
+
+export type ConfiguredAlertRule = { name: string, 
+/**
+ * The condition as written, such as `memory_percent > 90 for 5m`
+ */
+when: string, condition: AlertCondition, forSeconds: number, 
+/**
+ * How urgent the alert is, for routing notifications; `warning` unless set
+ */
+severity: Severity, 
+/**
+ * Container names the rule applies to, as in [`AlertRule`]
+ */
+containers: Array<string>, 
+/**
+ * Groups whose members the rule applies to, as in [`AlertRule`]
+ */
+groups: Array<string>, selector?: string, };
+
+export type Alert = { rule: string, when: string, container: string, firedAt: string, 
+/**
+ * Absent while the alert is firing
+ */
+resolvedAt?: string, 
+/**
+ * Most extreme value seen while firing; absent for status rules
+ */
+peak?: number, };
+
+export type AlertList = { rules: Array<ConfiguredAlertRule>, 
+/**
+ * Oldest first
+ */
+firing: Array<Alert>, 
+/**
+ * Recently resolved, newest first
+ */
+resolved: Array<Alert>, };
+
+export type NotificationKind = "webhook" | "slack" | "discord" | "email";
+
+export type NotificationChannel = { name: string, kind: NotificationKind, 
+/**
+ * Webhook URL, or the recipient address for email
+ */
+target: string, 
+/**
+ * Handlebars template for the payload, referencing `event`, `alert` and
+ * `container` fields; the kind's default payload when absent
+ */
+template?: string, 
+/**
+ * Only notify about containers in these categories; every category when empty
+ */
+categories: Array<ServiceCategory>, 
+/**
+ * Only notify about events at least this severe; every event when absent.
+ * Container transitions are errors, alerts carry their rule's severity
+ * and resolved alerts are info.
+ */
+minSeverity?: Severity, createdAt: string, updatedAt: string, };
+
+export type RenderedNotification = { channel: string, 
+/**
+ * `application/json` for webhooks, Slack and Discord, `text/plain` for email
+ */
+contentType: string, body: string, };
+
+export type DeliveryState = "pending" | "delivered" | "failed";
+
+export type NotificationDelivery = { id: string, channel: string, 
+/**
+ * e.g. `alert.firing` or `container.unhealthy`
+ */
+event: string, summary: string, state: DeliveryState, attempts: number, createdAt: string, finishedAt?: string, lastError?: string, };
+
+export type ChannelDeliveryStatus = { channel: string, kind: NotificationKind, 
+/**
+ * The sink that sends to this channel; absent when none handles its kind
+ */
+sink?: string, delivered: number, failed: number, lastDeliveredAt?: string, lastFailedAt?: string, lastError?: string, };
+
+export type NotificationStatus = { channels: Array<ChannelDeliveryStatus>, 
+/**
+ * Latest deliveries, newest first
+ */
+recent: Array<NotificationDelivery>, };
+
+export type ApiScope = "containers:read" | "containers:actions" | "logs:read" | "exec" | "admin";
+
+export type ApiRole = "viewer" | "operator" | "admin";
+
+export type ScopeInfo = { scope: ApiScope, description: string, 
+/**
+ * `METHOD /path` patterns, `*` matching one path segment
+ */
+routes: Array<string>, };
+
+export type RateLimits = { requestsPerMinute?: number, 
+/**
+ * Limit on requests needing `containers:actions` or `exec`
+ */
+actionsPerHour?: number, };
+
+export type TokenUsage = { token: string, role?: ApiRole, rateClass?: string, limits: RateLimits, requests: number, 
+/**
+ * Requests that needed `containers:actions` or `exec`
+ */
+actions: number, 
+/**
+ * Requests refused for exceeding a limit
+ */
+rejected: number, wsConnections: number, wsActive: number, 
+/**
+ * Time spent connected over WebSocket, counting open connections so far
+ */
+wsSeconds: number, lastSeen?: string, };
+
+export type ExecRequest = { 
+/**
+ * Program and arguments, run without a shell; use `["sh", "-c", "..."]`
+ * for pipes and variables
+ */
+cmd: Array<string>, 
+/**
+ * Working directory inside the container; the image's when absent
+ */
+workingDir?: string, 
+/**
+ * How long to wait for the command; 30 when absent, at most 300
+ */
+timeoutSeconds?: number, };
+
+export type ExecResult = { container: string, cmd: Array<string>, stdout: string, stderr: string, 
+/**
+ * Absent when the command timed out
+ */
+exitCode?: number, 
+/**
+ * The command outlived its timeout; it may still be running
+ */
+timedOut: boolean, 
+/**
+ * Output beyond the size limit was dropped
+ */
+truncated: boolean, durationMs: number, };
+
+export type KillRequest = { 
+/**
+ * `SIGHUP`, `HUP` or a signal number; `SIGKILL` when absent
+ */
+signal?: string, };
+
+export type DebugSidecarRequest = { 
+/**
+ * Tools image; the server's configured default when absent
+ */
+image?: string, ttlSeconds?: number, };
+
+export type DebugSidecar = { id: string, name: string, target: string, image: string, createdAt: string, 
+/**
+ * When the sidecar is removed
+ */
+expiresAt: string, };
+
+export type OneOffRunRequest = { image: string, 
+/**
+ * Command to run; the image's default when empty
+ */
+cmd: Array<string>, 
+/**
+ * `KEY=value` environment entries
+ */
+env: Array<string>, 
+/**
+ * Network to join, such as a compose project's default network
+ */
+network?: string, 
+/**
+ * Allocate a terminal, like `docker run -t`; output then arrives as a
+ * single console stream
+ */
+tty: boolean, ttlSeconds?: number, };
+
+export type OneOffRun = { 
+/**
+ * Container id, also the run's id on the WebSocket
+ */
+id: string, name: string, image: string, cmd: Array<string>, network: string | null, tty: boolean, 
+/**
+ * Name of the API token that created the run, the only one allowed to
+ * attach; `None` when auth is disabled
+ */
+startedBy: string | null, attached: boolean, createdAt: string, 
+/**
+ * When the run is removed if no client has attached
+ */
+attachBy: string, 
+/**
+ * When the container is stopped and removed, finished or not
+ */
+expiresAt: string, };
+
+export type ForwardProtocol = "http" | "tcp";
+
+export type PortForwardRequest = { 
+/**
+ * Container port to reach, published or not
+ */
+port: number, protocol: ForwardProtocol, ttlSeconds?: number, };
+
+export type ForwardAuditEvent = { at: string, 
+/**
+ * `opened`, `authorized`, `connected`, `refused` or `closed`
+ */
+event: string, peer?: string, detail?: string, };
+
+export type PortForward = { id: string, target: string, containerPort: number, protocol: ForwardProtocol, 
+/**
+ * FlowScope port the forward listens on
+ */
+listenPort: number, 
+/**
+ * Link that opens an HTTP forward; only returned when it is created
+ */
+url?: string, 
+/**
+ * Secret admitting HTTP clients; only returned when it is created
+ */
+token?: string, 
+/**
+ * Name of the API token that opened the forward
+ */
+openedBy?: string, createdAt: string, expiresAt: string, 
+/**
+ * Connections admitted so far
+ */
+connections: number, audit: Array<ForwardAuditEvent>, };
+
+export type ProbeVantage = "local" | "relay";
+
+export type EndpointTestRequest = { vantage: ProbeVantage, 
+/**
+ * Address a relay test connects to; the server's configured public host
+ * when absent. Must be a configured relay host unless the caller is an
+ * admin. Local tests always connect to the server's local test host.
+ */
+host?: string, 
+/**
+ * Whether to attempt a TLS handshake. When absent one is attempted, and
+ * a port that does not speak TLS is not an error.
+ */
+tls?: boolean, timeoutMs?: number, };
+
+export type EndpointProbe = { host: string, port: number, tls?: boolean, timeoutMs: number, };
+
+export type TlsInfo = { 
+/**
+ * `TLS 1.2` or `TLS 1.3`
+ */
+version: string, cipherSuite: string, 
+/**
+ * Protocol agreed through ALPN, such as `h2`
+ */
+alpn?: string, handshakeMs: number, 
+/**
+ * Certificates the server presented, its own first
+ */
+certificates: number, 
+/**
+ * Whether the chain verifies against the public web roots for the host
+ */
+trusted: boolean, 
+/**
+ * Why the chain did not verify
+ */
+trustError?: string, };
+
+export type ProbeOutcome = { reachable: boolean, 
+/**
+ * Time to complete the TCP handshake
+ */
+connectMs?: number, 
+/**
+ * Why the port could not be reached
+ */
+error?: string, tls?: TlsInfo, 
+/**
+ * Why a requested TLS handshake failed
+ */
+tlsError?: string, };
+
+export type EndpointTest = { 
+/**
+ * `<container>:<hostPort>`
+ */
+endpoint: string, container: string, containerPort: number, host: string, hostPort: number, vantage: ProbeVantage, testedAt: string, reachable: boolean, 
+/**
+ * Time to complete the TCP handshake
+ */
+connectMs?: number, 
+/**
+ * Why the port could not be reached
+ */
+error?: string, tls?: TlsInfo, 
+/**
+ * Why a requested TLS handshake failed
+ */
+tlsError?: string, };
+
+export type UsageSummary = { samples: number, avgCpuPercent: number, peakCpuPercent: number, avgMemoryMb: number, peakMemoryMb: number, };
+
+export type MigrationSide = { container: string, status: ContainerStatus, health: string | null, 
+/**
+ * Host port of the first published TCP port, the one probed
+ */
+probedPort: number | null, probe: ProbeOutcome | null, 
+/**
+ * `None` when no stats were sampled in the window
+ */
+usage: UsageSummary | null, };
+
+export type ReadinessCriterion = { 
+/**
+ * Stable identifier, such as `rust-reachable`
+ */
+name: string, passed: boolean, detail: string, };
+
+export type MigrationReport = { legacy: MigrationSide, rust: MigrationSide, 
+/**
+ * Minutes of stats history the usage figures cover
+ */
+windowMinutes: number, criteria: Array<ReadinessCriterion>, 
+/**
+ * Every criterion passed, so the legacy container may be stopped
+ */
+ready: boolean, generatedAt: string, };
+
+export type WarmupProfile = { 
+/**
+ * Container port the service answers HTTP on
+ */
+port: number, 
+/**
+ * Paths to request, such as `/` or `/api/products?page=1`
+ */
+paths: Array<string>, 
+/**
+ * GET requests sent to each path
+ */
+count: number, 
+/**
+ * Requests in flight at once
+ */
+concurrency: number, 
+/**
+ * How long to wait for the port to accept connections after a restart
+ */
+readyTimeoutSecs: number, };
+
+export type WarmupPathResult = { path: string, requests: number, 
+/**
+ * Requests answered with a 2xx or 3xx status
+ */
+succeeded: number, avgMs: number, maxMs: number, 
+/**
+ * Last failure seen, status or connection error
+ */
+lastError?: string, };
+
+export type WarmupReport = { container: string, 
+/**
+ * `http://<address>:<port>` the requests went to
+ */
+baseUrl: string, 
+/**
+ * Time spent waiting for the port to accept connections
+ */
+readyMs: number, paths: Array<WarmupPathResult>, durationMs: number, 
+/**
+ * Why warm-up stopped early, such as the port never opening
+ */
+error?: string, };
+
+export type WarmedActionResult = { warmup: WarmupReport, success: boolean, containerId: string, containerName: string, action: string, message: string, };
+
+export type SecurityProfile = { 
+/**
+ * The daemon runs as an unprivileged user (rootlesskit)
+ */
+rootless: boolean, selinux: boolean, apparmor: boolean, 
+/**
+ * Seccomp profile name, usually `builtin`
+ */
+seccomp?: string, 
+/**
+ * User namespace remapping (`userns-remap`)
+ */
+userns: boolean, };
+
+export type FeatureFlags = { 
+/**
+ * Memory, CPU and PID limits are enforced; rootless daemons on cgroup v1 cannot set them
+ */
+resourceLimits: boolean, 
+/**
+ * Ports below 1024 can be published; rootless daemons cannot by default
+ */
+privilegedPorts: boolean, 
+/**
+ * Bind mounts are readable without an SELinux relabel (`:z` or `:Z`)
+ */
+unlabelledBindMounts: boolean, };
+
+export type SystemInfo = { serverVersion: string | null, operatingSystem: string | null, 
+/**
+ * `linux` or `windows`
+ */
+osType: string | null, kernelVersion: string | null, architecture: string | null, 
+/**
+ * `1` or `2`
+ */
+cgroupVersion: string | null, 
+/**
+ * `cgroupfs`, `systemd` or `none`
+ */
+cgroupDriver: string | null, security: SecurityProfile, features: FeatureFlags, };
+
+export type EmbedTokenRequest = { 
+/**
+ * Flowchart ids the token may render; `*` allows any
+ */
+scope: Array<string>, ttlSeconds: number | null, };
+
+export type EmbedToken = { token: string, scope: Array<string>, expiresAt: string, };
+
+export type WsMessage = { "type": "welcome", resumeToken: string, resumed: boolean, seq: number, } | { "type": "containerUpdate", containers: Array<ContainerInfo>, removed: Array<string>, full: boolean, timestamp: string, } | { "type": "topologyUpdate", totalContainers: number, runningContainers: number, healthyContainers: number, unhealthyContainers: number, timestamp: string, } | { "type": "heartbeat", timestamp: string, } | { "type": "intervalUpdated", intervalMs: number, requestedMs: number, } | { "type": "logWatchStarted", watchId: string, container: string, pattern: string, } | { "type": "logMatch", watchId: string, container: string, line: string, timestamp: string, } | { "type": "logWatchEnded", watchId: string, reason: string, } | { "type": "actionResult", requestId?: string, action: WsAction, container: string, success: boolean, message: string, } | { "type": "runAttached", runId: string, } | { "type": "runOutput", runId: string, stream: RunStream, data: string, } | { "type": "runExited", runId: string, exitCode?: number, reason: string, } | { "type": "alert", alert: Alert, } | { "type": "error", message: string, };
+
+export type WsClientMessage = { "type": "setInterval", intervalMs: number, } | { "type": "watchLogs", container: string, pattern: string, } | { "type": "unwatchLogs", watchId: string, } | { "type": "action", requestId?: string, action: WsAction, container: string, } | { "type": "attachRun", runId: string, } | { "type": "runInput", runId: string, data: string, eof: boolean, };
+
+export type WsAction = "restart" | "stop" | "start";
+
+export type RunStream = "stdout" | "stderr" | "console";
+
+export type WsTopic = "topology" | "containers" | "alerts";
+
+export type WsEnvelope = { seq: number, topic: WsTopic, } & ({ "type": "welcome", resumeToken: string, resumed: boolean, seq: number, } | { "type": "containerUpdate", containers: Array<ContainerInfo>, removed: Array<string>, full: boolean, timestamp: string, } | { "type": "topologyUpdate", totalContainers: number, runningContainers: number, healthyContainers: number, unhealthyContainers: number, timestamp: string, } | { "type": "heartbeat", timestamp: string, } | { "type": "intervalUpdated", intervalMs: number, requestedMs: number, } | { "type": "logWatchStarted", watchId: string, container: string, pattern: string, } | { "type": "logMatch", watchId: string, container: string, line: string, timestamp: string, } | { "type": "logWatchEnded", watchId: string, reason: string, } | { "type": "actionResult", requestId?: string, action: WsAction, container: string, success: boolean, message: string, } | { "type": "runAttached", runId: string, } | { "type": "runOutput", runId: string, stream: RunStream, data: string, } | { "type": "runExited", runId: string, exitCode?: number, reason: string, } | { "type": "alert", alert: Alert, } | { "type": "error", message: string, });
+
+export type TerminalControl = { "type": "resize", rows: number, cols: number, };
+
+export type TerminalMessage = { "type": "exited", exitCode?: number, reason: string, } | { "type": "error", message: string, };
+
+export type LogStreamMessage = { "type": "lines", lines: Array<string>, } | { "type": "paused", reason: string, } | { "type": "resumed" } | { "type": "ended", reason: string, };
@@ -438,7 +438,8 @@ export default function ContainerDetailPanel() {
                 ) : logs ? (
                   <div className="bg-flow-bg rounded-lg p-3 max-h-[400px] overflow-y-auto">
                     <pre className="text-xs text-flow-muted font-mono whitespace-pre-wrap break-all">
-                      {logs.logs.join("\n") || "No logs available"}
+                      {logs.logs.map((entry) => entry.line).join("\n") ||
+                        "No logs available"}
                     </pre>
                   </div>
                 ) : (
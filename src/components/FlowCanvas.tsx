@@ -77,7 +77,8 @@ function layoutGridNodes(nodes: ServiceNodeType[]): Node[] {
     return {
       id: node.id,
       type: node.nodeType || "service",
-      position: {
+      // The backend suggests positions that stay put across refreshes
+      position: node.position ?? {
         x: col * (nodeWidth + gapX),
         y: row * (nodeHeight + gapY),
       },
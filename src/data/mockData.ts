@@ -72,6 +72,7 @@ export interface ServiceNode {
   color?: string; // for group nodes
   stats?: ContainerStats; // Real-time container stats
   imageSizeMb?: number; // Docker image size
+  position?: { x: number; y: number }; // Suggested position, stable across refreshes
   metrics?: {
     requests?: string;
     latency?: string;
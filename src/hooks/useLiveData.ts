@@ -123,6 +123,7 @@ function apiFlowchartToStore(apiFlowchart: APIFlowchart): ServiceFlowchart {
     description: node.description,
     port: node.port || undefined,
     linkedFlowchart: node.childFlowchart || undefined,
+    position: node.position,
     metrics: node.metrics
       ? {
           requests: node.metrics.cpuPercent
@@ -24,6 +24,8 @@ interface WsContainerUpdate {
     status: string;
     health: string;
   }>;
+  removed: string[];
+  full: boolean;
   timestamp: string;
 }
 
@@ -32,12 +34,21 @@ interface WsHeartbeat {
   timestamp: string;
 }
 
-type WsMessage = WsTopologyUpdate | WsContainerUpdate | WsHeartbeat;
+interface WsWelcome {
+  type: "welcome";
+  resumeToken: string;
+  resumed: boolean;
+  seq: number;
+}
+
+type WsMessage = WsTopologyUpdate | WsContainerUpdate | WsHeartbeat | WsWelcome;
 
 interface UseWebSocketOptions {
   enabled?: boolean;
   reconnectInterval?: number;
   maxReconnectAttempts?: number;
+  focusedIntervalMs?: number;
+  backgroundIntervalMs?: number;
 }
 
 export interface WebSocketState {
@@ -56,10 +67,13 @@ export function useWebSocket(options: UseWebSocketOptions = {}) {
     enabled = true,
     reconnectInterval = 5000,
     maxReconnectAttempts = 10,
+    focusedIntervalMs = 5000,
+    backgroundIntervalMs = 30000,
   } = options;
 
   const wsRef = useRef<WebSocket | null>(null);
   const reconnectAttemptsRef = useRef(0);
+  const resumeTokenRef = useRef<string | null>(null);
   const reconnectTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(
     null
   );
@@ -72,13 +86,24 @@ export function useWebSocket(options: UseWebSocketOptions = {}) {
 
   const { isLiveMode } = useFlowStore();
 
+  // Ask the server for slower updates while the tab is hidden
+  const sendInterval = useCallback(() => {
+    const ws = wsRef.current;
+    if (!ws || ws.readyState !== WebSocket.OPEN) return;
+    const intervalMs = document.hidden ? backgroundIntervalMs : focusedIntervalMs;
+    ws.send(JSON.stringify({ type: "setInterval", intervalMs }));
+  }, [focusedIntervalMs, backgroundIntervalMs]);
+
   const connect = useCallback(() => {
     if (!enabled || !isLiveMode) return;
 
     // Determine WebSocket URL
     const protocol = window.location.protocol === "https:" ? "wss:" : "ws:";
     const host = window.location.host;
-    const wsUrl = `${protocol}//${host}/ws`;
+    const resume = resumeTokenRef.current
+      ? `?resume_token=${encodeURIComponent(resumeTokenRef.current)}`
+      : "";
+    const wsUrl = `${protocol}//${host}/ws${resume}`;
 
     console.log("[WS] Connecting to", wsUrl);
 
@@ -90,6 +115,7 @@ export function useWebSocket(options: UseWebSocketOptions = {}) {
         console.log("[WS] Connected");
         reconnectAttemptsRef.current = 0;
         setState((prev) => ({ ...prev, connected: true }));
+        sendInterval();
       };
 
       ws.onmessage = (event) => {
@@ -97,6 +123,13 @@ export function useWebSocket(options: UseWebSocketOptions = {}) {
           const message: WsMessage = JSON.parse(event.data);
 
           switch (message.type) {
+            case "welcome":
+              resumeTokenRef.current = message.resumeToken;
+              console.log(
+                message.resumed ? "[WS] Session resumed" : "[WS] New session"
+              );
+              break;
+
             case "topologyUpdate":
               setState((prev) => ({
                 ...prev,
@@ -152,7 +185,7 @@ export function useWebSocket(options: UseWebSocketOptions = {}) {
     } catch (e) {
       console.error("[WS] Failed to connect:", e);
     }
-  }, [enabled, isLiveMode, reconnectInterval, maxReconnectAttempts]);
+  }, [enabled, isLiveMode, reconnectInterval, maxReconnectAttempts, sendInterval]);
 
   const disconnect = useCallback(() => {
     if (reconnectTimeoutRef.current) {
@@ -181,6 +214,11 @@ export function useWebSocket(options: UseWebSocketOptions = {}) {
     };
   }, [enabled, isLiveMode, connect, disconnect]);
 
+  useEffect(() => {
+    document.addEventListener("visibilitychange", sendInterval);
+    return () => document.removeEventListener("visibilitychange", sendInterval);
+  }, [sendInterval]);
+
   return {
     ...state,
     connect,